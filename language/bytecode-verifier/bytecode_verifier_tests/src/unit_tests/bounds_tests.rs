@@ -17,6 +17,20 @@ fn empty_module_no_errors() {
     basic_test_module().freeze().unwrap();
 }
 
+#[test]
+fn duplicate_metadata_keys() {
+    let entry = MetadataEntry {
+        key: Identifier::new("build").unwrap(),
+        value: ByteArray::new(vec![]),
+    };
+    let mut m = basic_test_module();
+    m.metadata.push(entry.clone());
+    m.metadata.push(entry);
+    let errors = m.freeze().unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].major_status, StatusCode::DUPLICATE_ELEMENT);
+}
+
 #[test]
 fn invalid_type_param_in_fn_return_types() {
     use SignatureToken::*;
@@ -160,6 +160,7 @@ fn no_verify_locals_good() {
                 },
             },
         ],
+        metadata: vec![],
     };
     assert!(VerifiedModule::new(compiled_module_good.freeze().unwrap()).is_ok());
 }
@@ -206,6 +207,7 @@ fn no_verify_locals_bad1() {
                 code: vec![Ret],
             },
         }],
+        metadata: vec![],
     };
     assert!(VerifiedModule::new(compiled_module_bad1.freeze().unwrap()).is_err());
 }
@@ -251,6 +253,7 @@ fn no_verify_locals_bad2() {
                 code: vec![Ret],
             },
         }],
+        metadata: vec![],
     };
     assert!(VerifiedModule::new(compiled_module_bad2.freeze().unwrap()).is_err());
 }
@@ -297,6 +300,7 @@ fn no_verify_locals_bad3() {
                 code: vec![Ret],
             },
         }],
+        metadata: vec![],
     };
     assert!(VerifiedModule::new(compiled_module_bad1.freeze().unwrap()).is_err());
 }
@@ -343,6 +347,7 @@ fn no_verify_locals_bad4() {
                 code: vec![Ret],
             },
         }],
+        metadata: vec![],
     };
     assert!(VerifiedModule::new(compiled_module_bad1.freeze().unwrap()).is_err());
 }
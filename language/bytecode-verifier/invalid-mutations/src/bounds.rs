@@ -61,6 +61,7 @@ impl PointerKind {
             Identifier => &[],
             ByteArrayPool => &[],
             AddressPool => &[],
+            Metadata => &[],
             // LocalPool and CodeDefinition are function-local, and this only works for
             // module-scoped indexes.
             // XXX maybe don't treat LocalPool and CodeDefinition the same way as the others?
@@ -16,7 +16,9 @@ use std::{collections::BTreeMap, fmt};
 use vm::{
     access::{ModuleAccess, ScriptAccess},
     errors::{append_err_info, verification_error},
-    file_format::{CompiledModule, CompiledProgram, CompiledScript},
+    file_format::{
+        CompiledModule, CompiledProgram, CompiledScript, FunctionSignature, SignatureToken,
+    },
     resolver::Resolver,
     views::{ModuleView, ViewInternals},
     IndexKind,
@@ -241,6 +243,22 @@ impl VerifiedScript {
     /// of struct definitions, the bounds checker will catch any occurrences of these illegal
     /// operations.
     pub fn new(script: CompiledScript) -> Result<Self, (CompiledScript, Vec<VMStatus>)> {
+        Self::new_impl(script, verify_main_signature)
+    }
+
+    /// Verifies this `CompiledScript` as a query, whose main function may return values, returning
+    /// a `VerifiedScript` on success. The script of a transaction must be verified with `new`
+    /// instead.
+    ///
+    /// On failure, returns the original `CompiledScript` and a list of verification errors.
+    pub fn new_query(script: CompiledScript) -> Result<Self, (CompiledScript, Vec<VMStatus>)> {
+        Self::new_impl(script, verify_query_main_signature)
+    }
+
+    fn new_impl(
+        script: CompiledScript,
+        verify_main: fn(&CompiledScript) -> Vec<VMStatus>,
+    ) -> Result<Self, (CompiledScript, Vec<VMStatus>)> {
         let fake_module = script.into_module();
         let (fake_module, mut errors) = match VerifiedModule::new(fake_module) {
             Ok(module) => (module.into_inner(), vec![]),
@@ -248,7 +266,7 @@ impl VerifiedScript {
         };
         let script = fake_module.into_script();
         errors.append(
-            &mut verify_main_signature(&script)
+            &mut verify_main(&script)
                 .into_iter()
                 .map(move |err| append_err_info(err, IndexKind::FunctionDefinition, 0))
                 .collect(),
@@ -309,6 +327,28 @@ pub fn verify_main_signature(script: &CompiledScript) -> Vec<VMStatus> {
     if !function_signature.return_types.is_empty() {
         return vec![VMStatus::new(StatusCode::INVALID_MAIN_FUNCTION_SIGNATURE)];
     }
+    verify_main_arguments(function_signature)
+}
+
+/// This function checks the extra requirements on the signature of the main function of a script
+/// run as a query. Unlike the main function of a transaction script, it may return values, of the
+/// types of the arguments of a transaction.
+pub fn verify_query_main_signature(script: &CompiledScript) -> Vec<VMStatus> {
+    let function_handle = &script.function_handle_at(script.main().function);
+    let function_signature = &script.function_signature_at(function_handle.signature);
+    for return_type in &function_signature.return_types {
+        match return_type {
+            SignatureToken::U64
+            | SignatureToken::Bool
+            | SignatureToken::Address
+            | SignatureToken::ByteArray => (),
+            _ => return vec![VMStatus::new(StatusCode::INVALID_MAIN_FUNCTION_SIGNATURE)],
+        }
+    }
+    verify_main_arguments(function_signature)
+}
+
+fn verify_main_arguments(function_signature: &FunctionSignature) -> Vec<VMStatus> {
     for arg_type in &function_signature.arg_types {
         if !arg_type.is_primitive() {
             return vec![VMStatus::new(StatusCode::INVALID_MAIN_FUNCTION_SIGNATURE)];
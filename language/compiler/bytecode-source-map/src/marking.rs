@@ -1,7 +1,7 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::BTreeMap;
 use vm::file_format::{
     CodeOffset, FieldDefinitionIndex, FunctionDefinitionIndex, StructDefinitionIndex, TableIndex,
 };
@@ -33,10 +33,10 @@ pub struct StructMarking {
 #[derive(Debug, Default)]
 pub struct MarkedSourceMapping {
     // Any function markings
-    function_marks: HashMap<TableIndex, FunctionMarking>,
+    function_marks: BTreeMap<TableIndex, FunctionMarking>,
 
     // Any struct marking
-    struct_marks: HashMap<TableIndex, StructMarking>,
+    struct_marks: BTreeMap<TableIndex, StructMarking>,
 }
 
 impl FunctionMarking {
@@ -88,8 +88,8 @@ impl StructMarking {
 impl MarkedSourceMapping {
     pub fn new() -> Self {
         Self {
-            function_marks: HashMap::new(),
-            struct_marks: HashMap::new(),
+            function_marks: BTreeMap::new(),
+            struct_marks: BTreeMap::new(),
         }
     }
 
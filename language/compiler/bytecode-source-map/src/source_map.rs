@@ -64,6 +64,10 @@ pub struct ModuleSourceMap<Location: Clone + Eq + Default> {
 
     // A mapping of FunctionDefinitionIndex to the soure map for that function.
     function_map: BTreeMap<TableIndex, FunctionSourceMap<Location>>,
+
+    /// The language version declared by the pragma of the source, if it has one
+    #[serde(default)]
+    pub language_version: Option<u64>,
 }
 
 impl<Location: Clone + Eq + Default> StructSourceMap<Location> {
@@ -208,6 +212,7 @@ impl<Location: Clone + Eq + Default> ModuleSourceMap<Location> {
             module_name: (module_name.address, module_name.name.into_inner()),
             struct_map: BTreeMap::new(),
             function_map: BTreeMap::new(),
+            language_version: None,
         }
     }
 
@@ -221,6 +226,11 @@ impl<Location: Clone + Eq + Default> ModuleSourceMap<Location> {
                 )) })
     }
 
+    /// Forgets the mapping of a function, so that it can be compiled again.
+    pub fn remove_function_mapping(&mut self, fdef_idx: FunctionDefinitionIndex) {
+        self.function_map.remove(&fdef_idx.0);
+    }
+
     pub fn add_function_type_parameter_mapping(
         &mut self,
         fdef_idx: FunctionDefinitionIndex,
@@ -4,16 +4,22 @@
 use crate::mapping::SourceMapping;
 use crate::source_map::{ModuleSourceMap, SourceMap};
 use anyhow::{format_err, Result};
-use codespan::{CodeMap, FileName};
+use codespan::{ByteIndex, CodeMap, FileName};
 use codespan_reporting::{
     emit,
     termcolor::{ColorChoice, StandardStream},
     Diagnostic, Label,
 };
+use libra_types::vm_error::VMStatus;
 use move_ir_types::ast::Loc;
 use serde::de::DeserializeOwned;
-use std::fs::File;
+use std::fs;
 use std::path::Path;
+use vm::{
+    errors::{index_of_error, offset_of_error},
+    file_format::{CodeOffset, FunctionDefinitionIndex, StructDefinitionIndex, TableIndex},
+    IndexKind,
+};
 
 pub type Error = (Loc, String);
 pub type Errors = Vec<Error>;
@@ -22,9 +28,11 @@ pub fn module_source_map_from_file<Location>(file_path: &Path) -> Result<ModuleS
 where
     Location: Clone + Eq + Default + DeserializeOwned,
 {
-    File::open(file_path)
+    // Read the whole file up front: some of the types in a source map deserialize from borrowed
+    // strings, which `serde_json::from_reader` cannot provide.
+    fs::read_to_string(file_path)
         .ok()
-        .and_then(|file| serde_json::from_reader(file).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
         .ok_or_else(|| format_err!("Error while reading in source map information"))
 }
 
@@ -32,9 +40,11 @@ pub fn source_map_from_file<Location>(file_path: &Path) -> Result<SourceMap<Loca
 where
     Location: Clone + Eq + Default + DeserializeOwned,
 {
-    File::open(file_path)
+    // Read the whole file up front: some of the types in a source map deserialize from borrowed
+    // strings, which `serde_json::from_reader` cannot provide.
+    fs::read_to_string(file_path)
         .ok()
-        .and_then(|file| serde_json::from_reader(file).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
         .ok_or_else(|| format_err!("Error while reading in source map information"))
 }
 
@@ -59,3 +69,38 @@ pub fn create_diagnostic(error: Error) -> Diagnostic {
     let label = Label::new_primary(error.0);
     Diagnostic::new_error(error.1).with_label(label)
 }
+
+/// Maps a bytecode verifier error for a module or script compiled with `source_map` back to a
+/// location in the IR source. Errors raised at a code offset map to the IR that produced the
+/// offending instruction, other errors on a function or struct map to its declaration. Returns
+/// `None` if the error does not point at anything in the source map.
+pub fn verification_error_location(
+    source_map: &ModuleSourceMap<Loc>,
+    status: &VMStatus,
+) -> Option<Loc> {
+    if let Some(fdef_idx) = index_of_error(status, IndexKind::FunctionDefinition) {
+        let fdef_idx = FunctionDefinitionIndex(fdef_idx as TableIndex);
+        let function_map = source_map.get_function_source_map(fdef_idx).ok()?;
+        return offset_of_error(status)
+            .and_then(|offset| function_map.get_code_location(offset as CodeOffset))
+            .or(Some(function_map.decl_location));
+    }
+    if let Some(sdef_idx) = index_of_error(status, IndexKind::StructDefinition) {
+        let sdef_idx = StructDefinitionIndex(sdef_idx as TableIndex);
+        return source_map
+            .get_struct_source_map(sdef_idx)
+            .ok()
+            .map(|struct_map| struct_map.decl_location);
+    }
+    None
+}
+
+/// Returns the 1-based line and column of `index` in `source`.
+pub fn line_and_column(source: &str, index: ByteIndex) -> (usize, usize) {
+    let offset = std::cmp::min(index.to_usize(), source.len());
+    let prefix = &source[..offset];
+    let line = prefix.matches('\n').count() + 1;
+    let line_start = prefix.rfind('\n').map_or(0, |pos| pos + 1);
+    let column = prefix[line_start..].chars().count() + 1;
+    (line, column)
+}
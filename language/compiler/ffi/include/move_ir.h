@@ -0,0 +1,57 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// C bindings of the Move IR formatter and compiler, implemented by the `compiler-ffi` crate.
+//
+// Sources are NUL-terminated UTF-8 strings holding a single script or module. On success, the
+// functions return MOVE_IR_OK and fill `output`. If the source has errors, they return
+// MOVE_IR_ERROR and fill `diagnostics` with a JSON array of
+// {"line": ..., "column": ..., "message": ..., "fix": ...} objects, where the line and column are
+// 1-based and null when unknown, and the fix is an edit the parser suggests, or null. Every buffer
+// must be released with move_ir_buffer_free.
+
+#ifndef MOVE_IR_H
+#define MOVE_IR_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define MOVE_IR_OK 0
+#define MOVE_IR_ERROR 1
+#define MOVE_IR_INVALID_ARGUMENT -1
+#define MOVE_IR_PANIC -2
+
+typedef struct {
+    uint8_t *data;
+    size_t len;
+} MoveIrBuffer;
+
+// Formats a module: fills `output` with its source printed back from its AST, in the canonical
+// layout of the formatter. A module that does not parse fills `diagnostics` with its syntax error.
+int move_ir_format_module(const char *source, MoveIrBuffer *output, MoveIrBuffer *diagnostics);
+
+// Compiles a script into bytecode. `address` is the sender, a 0x literal or 64 hex digits, or
+// NULL for the default address.
+int move_ir_compile_script(const char *source,
+                           const char *address,
+                           MoveIrBuffer *output,
+                           MoveIrBuffer *diagnostics);
+
+// Compiles a module into bytecode. `address` is where it is published, as for scripts.
+int move_ir_compile_module(const char *source,
+                           const char *address,
+                           MoveIrBuffer *output,
+                           MoveIrBuffer *diagnostics);
+
+// Releases a buffer filled by the functions above.
+void move_ir_buffer_free(MoveIrBuffer buffer);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif // MOVE_IR_H
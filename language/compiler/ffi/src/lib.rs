@@ -0,0 +1,340 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! C bindings of the Move IR formatter and compiler, so that programs written in other languages
+//! can embed the reference front-end. The declarations are in `include/move_ir.h`.
+//!
+//! Each function takes a NUL-terminated UTF-8 source holding a single script or module, as a
+//! `.mvir` file does, and fills two buffers: the output on success, and the diagnostics as a JSON
+//! array of the objects described in `ir_to_bytecode::diagnostics` otherwise. Buffers are owned by
+//! the caller, who releases them with `move_ir_buffer_free`.
+
+use compiler::Compiler;
+use ir_to_bytecode::{
+    diagnostics::{parse_address, Diagnostic},
+    formatter::{format_module, FormatOptions},
+    parser::try_parse_script_or_module,
+};
+use libra_types::account_address::AccountAddress;
+use move_ir_types::ast::ScriptOrModule;
+use std::{
+    ffi::CStr,
+    os::raw::{c_char, c_int},
+    panic, ptr,
+};
+
+/// The function succeeded, and the output buffer is filled.
+pub const MOVE_IR_OK: c_int = 0;
+/// The source has errors, and the diagnostics buffer is filled.
+pub const MOVE_IR_ERROR: c_int = 1;
+/// An argument is null or not UTF-8. No buffer is filled.
+pub const MOVE_IR_INVALID_ARGUMENT: c_int = -1;
+/// The front-end crashed on the source. No buffer is filled.
+pub const MOVE_IR_PANIC: c_int = -2;
+
+/// Bytes handed over to the caller, to be released with `move_ir_buffer_free`.
+#[repr(C)]
+pub struct MoveIrBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl MoveIrBuffer {
+    fn empty() -> Self {
+        MoveIrBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn new(bytes: Vec<u8>) -> Self {
+        // A boxed slice has no spare capacity, so the buffer is freed from its length alone.
+        let mut bytes = bytes.into_boxed_slice();
+        let buffer = MoveIrBuffer {
+            data: bytes.as_mut_ptr(),
+            len: bytes.len(),
+        };
+        std::mem::forget(bytes);
+        buffer
+    }
+}
+
+/// Parses `source` as a module and prints it back from its AST, in the canonical layout of the
+/// formatter.
+fn format_source(source: &str) -> Result<Vec<u8>, Diagnostic> {
+    match try_parse_script_or_module(source) {
+        Ok(ScriptOrModule::Module(module)) => {
+            Ok(format_module(&module, &FormatOptions::default()).into_bytes())
+        }
+        Ok(ScriptOrModule::Script(_)) => Err(Diagnostic::unlocated(
+            "Expected a module, found a script".to_string(),
+        )),
+        Err(err) => Err(Diagnostic::from_syntax_error(source, err)),
+    }
+}
+
+/// Compiles `source` against the standard library with the type checker and the bytecode verifier,
+/// returning the serialized script or module.
+fn compile(source: &str, address: Option<&str>, script: bool) -> Result<Vec<u8>, Diagnostic> {
+    let address = match address {
+        Some(address) => parse_address(address)?,
+        None => AccountAddress::default(),
+    };
+    // Syntax errors are located here, as the compiler would only print them.
+    let is_script = match try_parse_script_or_module(source) {
+        Ok(ScriptOrModule::Script(_)) => true,
+        Ok(ScriptOrModule::Module(_)) => false,
+        Err(err) => return Err(Diagnostic::from_syntax_error(source, err)),
+    };
+    if is_script != script {
+        let (expected, found) = if script {
+            ("script", "module")
+        } else {
+            ("module", "script")
+        };
+        return Err(Diagnostic::unlocated(format!(
+            "Expected a {}, found a {}",
+            expected, found
+        )));
+    }
+    let compiler = Compiler {
+        address,
+        check_types: true,
+        verify: true,
+        ..Compiler::default()
+    };
+    let compiled = if script {
+        compiler.into_script_blob(source)
+    } else {
+        compiler.into_module_blob(source)
+    };
+    compiled.map_err(|err| Diagnostic::unlocated(format!("{:#}", err)))
+}
+
+// Reads a C string argument, `None` if it is null or not UTF-8.
+unsafe fn read_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        None
+    } else {
+        CStr::from_ptr(s).to_str().ok()
+    }
+}
+
+// Runs `f` on the source, filling `output` or `diagnostics` with its result.
+unsafe fn run(
+    source: *const c_char,
+    output: *mut MoveIrBuffer,
+    diagnostics: *mut MoveIrBuffer,
+    f: impl FnOnce(&str) -> Result<Vec<u8>, Diagnostic> + panic::UnwindSafe,
+) -> c_int {
+    if output.is_null() || diagnostics.is_null() {
+        return MOVE_IR_INVALID_ARGUMENT;
+    }
+    *output = MoveIrBuffer::empty();
+    *diagnostics = MoveIrBuffer::empty();
+    let source = match read_str(source) {
+        Some(source) => source,
+        None => return MOVE_IR_INVALID_ARGUMENT,
+    };
+    // Unwinding into C is undefined behavior.
+    match panic::catch_unwind(|| f(source)) {
+        Ok(Ok(bytes)) => {
+            *output = MoveIrBuffer::new(bytes);
+            MOVE_IR_OK
+        }
+        Ok(Err(diagnostic)) => {
+            let json =
+                serde_json::to_vec(&[diagnostic]).expect("diagnostics always serialize to JSON");
+            *diagnostics = MoveIrBuffer::new(json);
+            MOVE_IR_ERROR
+        }
+        Err(_) => MOVE_IR_PANIC,
+    }
+}
+
+/// Formats the module of `source`: parses it and fills `output` with the IR source printed back
+/// from its AST, in the canonical layout of the formatter. A source that does not parse fills
+/// `diagnostics` with its syntax error, so this also checks the syntax of a module.
+///
+/// # Safety
+///
+/// `source` must be null or a NUL-terminated string, and `output` and `diagnostics` must be null
+/// or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn move_ir_format_module(
+    source: *const c_char,
+    output: *mut MoveIrBuffer,
+    diagnostics: *mut MoveIrBuffer,
+) -> c_int {
+    run(source, output, diagnostics, format_source)
+}
+
+/// Compiles the script of `source`, sent by `address`, a `0x` literal or 64 hex digits, or by the
+/// default address if it is null, and fills `output` with its bytecode.
+///
+/// # Safety
+///
+/// `source` and `address` must be null or NUL-terminated strings, and `output` and `diagnostics`
+/// must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn move_ir_compile_script(
+    source: *const c_char,
+    address: *const c_char,
+    output: *mut MoveIrBuffer,
+    diagnostics: *mut MoveIrBuffer,
+) -> c_int {
+    compile_with_address(source, address, output, diagnostics, true)
+}
+
+/// Compiles the module of `source`, published at `address`, a `0x` literal or 64 hex digits, or
+/// at the default address if it is null, and fills `output` with its bytecode.
+///
+/// # Safety
+///
+/// `source` and `address` must be null or NUL-terminated strings, and `output` and `diagnostics`
+/// must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn move_ir_compile_module(
+    source: *const c_char,
+    address: *const c_char,
+    output: *mut MoveIrBuffer,
+    diagnostics: *mut MoveIrBuffer,
+) -> c_int {
+    compile_with_address(source, address, output, diagnostics, false)
+}
+
+unsafe fn compile_with_address(
+    source: *const c_char,
+    address: *const c_char,
+    output: *mut MoveIrBuffer,
+    diagnostics: *mut MoveIrBuffer,
+    script: bool,
+) -> c_int {
+    let address = if address.is_null() {
+        None
+    } else {
+        match read_str(address) {
+            Some(address) => Some(address.to_string()),
+            None => return MOVE_IR_INVALID_ARGUMENT,
+        }
+    };
+    run(source, output, diagnostics, move |source| {
+        compile(source, address.as_deref(), script)
+    })
+}
+
+/// Releases a buffer filled by the functions above. Empty buffers may be released too.
+///
+/// # Safety
+///
+/// `buffer` must come from this library and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn move_ir_buffer_free(buffer: MoveIrBuffer) {
+    if !buffer.data.is_null() {
+        drop(Vec::from_raw_parts(buffer.data, buffer.len, buffer.len));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{ffi::CString, slice};
+    use vm::file_format::{CompiledModule, CompiledScript};
+
+    const MODULE: &str = "module M {
+    public f(): u64 {
+        return 42;
+    }
+}
+";
+
+    const SCRIPT: &str = "import 0x0.LibraAccount;
+main() {
+    let balance: u64;
+    balance = LibraAccount.balance(get_txn_sender());
+    return;
+}
+";
+
+    // Calls `f` with C strings and returns its status with the contents of both buffers.
+    fn call(
+        f: impl FnOnce(*const c_char, *mut MoveIrBuffer, *mut MoveIrBuffer) -> c_int,
+        source: &str,
+    ) -> (c_int, Vec<u8>, String) {
+        let source = CString::new(source).unwrap();
+        let mut output = MoveIrBuffer::empty();
+        let mut diagnostics = MoveIrBuffer::empty();
+        let status = f(source.as_ptr(), &mut output, &mut diagnostics);
+        unsafe {
+            let bytes = |buffer: &MoveIrBuffer| {
+                if buffer.data.is_null() {
+                    vec![]
+                } else {
+                    slice::from_raw_parts(buffer.data, buffer.len).to_vec()
+                }
+            };
+            let result = (
+                status,
+                bytes(&output),
+                String::from_utf8(bytes(&diagnostics)).unwrap(),
+            );
+            move_ir_buffer_free(output);
+            move_ir_buffer_free(diagnostics);
+            result
+        }
+    }
+
+    #[test]
+    fn modules_are_formatted() {
+        let (status, output, diagnostics) =
+            call(|s, o, d| unsafe { move_ir_format_module(s, o, d) }, MODULE);
+        assert_eq!(status, MOVE_IR_OK);
+        assert_eq!(String::from_utf8(output).unwrap(), MODULE);
+        assert!(diagnostics.is_empty());
+
+        let (status, output, diagnostics) = call(
+            |s, o, d| unsafe { move_ir_format_module(s, o, d) },
+            "module M {\n    f(",
+        );
+        assert_eq!(status, MOVE_IR_ERROR);
+        assert!(output.is_empty());
+        assert!(diagnostics.starts_with(r#"[{"line":2,"column":"#));
+    }
+
+    #[test]
+    fn scripts_and_modules_compile_to_bytecode() {
+        let address = CString::new("0x1").unwrap();
+        let (status, output, _) = call(
+            |s, o, d| unsafe { move_ir_compile_module(s, address.as_ptr(), o, d) },
+            MODULE,
+        );
+        assert_eq!(status, MOVE_IR_OK);
+        CompiledModule::deserialize(&output).unwrap();
+
+        let (status, output, _) = call(
+            |s, o, d| unsafe { move_ir_compile_script(s, ptr::null(), o, d) },
+            SCRIPT,
+        );
+        assert_eq!(status, MOVE_IR_OK);
+        CompiledScript::deserialize(&output).unwrap();
+
+        let (status, _, diagnostics) = call(
+            |s, o, d| unsafe { move_ir_compile_script(s, ptr::null(), o, d) },
+            MODULE,
+        );
+        assert_eq!(status, MOVE_IR_ERROR);
+        assert!(diagnostics.contains("Expected a script, found a module"));
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        let mut output = MoveIrBuffer::empty();
+        let mut diagnostics = MoveIrBuffer::empty();
+        let status = unsafe { move_ir_format_module(ptr::null(), &mut output, &mut diagnostics) };
+        assert_eq!(status, MOVE_IR_INVALID_ARGUMENT);
+        let source = CString::new(MODULE).unwrap();
+        let status =
+            unsafe { move_ir_format_module(source.as_ptr(), ptr::null_mut(), &mut output) };
+        assert_eq!(status, MOVE_IR_INVALID_ARGUMENT);
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Benchmarks of the lexer, parser and compiler over the standard library and over synthetic
+//! modules stressing deep expressions, many functions, huge literals and specifications.
+//!
+//! Run with `cargo bench -p ir-to-bytecode`. Criterion compares each run with the previous one;
+//! to track performance across releases, save a baseline with `-- --save-baseline <name>` and
+//! compare against it with `-- --baseline <name>`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ir_to_bytecode::{
+    compiler::compile_module,
+    parser::{parse_module, parse_module_with_options, parse_program_with_options, ParserOptions},
+};
+use libra_types::account_address::AccountAddress;
+use std::fmt::Write;
+use vm::file_format::CompiledModule;
+
+/// The standard library, in dependency order: a module only depends on earlier ones.
+const STDLIB_SOURCES: &[&str] = &[
+    include_str!("../../../stdlib/modules/offer.mvir"),
+    include_str!("../../../stdlib/modules/address_util.mvir"),
+    include_str!("../../../stdlib/modules/bytearray_util.mvir"),
+    include_str!("../../../stdlib/modules/libra_coin.mvir"),
+    include_str!("../../../stdlib/modules/hash.mvir"),
+    include_str!("../../../stdlib/modules/signature.mvir"),
+    include_str!("../../../stdlib/modules/u64_util.mvir"),
+    include_str!("../../../stdlib/modules/lcs.mvir"),
+    include_str!("../../../stdlib/modules/vector.mvir"),
+    include_str!("../../../stdlib/modules/validator_config.mvir"),
+    include_str!("../../../stdlib/modules/gas_schedule.mvir"),
+    include_str!("../../../stdlib/modules/libra_time.mvir"),
+    include_str!("../../../stdlib/modules/libra_transaction_timeout.mvir"),
+    include_str!("../../../stdlib/modules/libra_account.mvir"),
+    include_str!("../../../stdlib/modules/libra_system.mvir"),
+];
+
+/// A module with a single function returning a parenthesized expression nested `depth` deep.
+fn deep_expression_module(depth: usize) -> String {
+    let mut exp = "1".to_string();
+    for i in 0..depth {
+        let op = if i % 2 == 0 { "+" } else { "*" };
+        exp = format!("({} {} {})", i, op, exp);
+    }
+    format!(
+        "module Deep {{\n    public f(): u64 {{\n        return {};\n    }}\n}}\n",
+        exp
+    )
+}
+
+/// A module with a single function returning a flat sum of `length` operands.
+fn long_expression_module(length: usize) -> String {
+    let operands: Vec<_> = (0..length).map(|i| i.to_string()).collect();
+    format!(
+        "module Long {{\n    public f(): u64 {{\n        return {};\n    }}\n}}\n",
+        operands.join(" + ")
+    )
+}
+
+/// A module with `count` functions, each with locals, a loop and a call to the previous one.
+fn many_functions_module(count: usize) -> String {
+    let mut source =
+        "module Many {\n    f0(x: u64): u64 {\n        return move(x);\n    }\n".to_string();
+    for i in 1..count {
+        writeln!(
+            source,
+            "    f{}(x: u64): u64 {{
+        let y: u64;
+        y = 0;
+        while (copy(y) < copy(x)) {{
+            y = copy(y) + {};
+        }}
+        return Self.f{}(move(y));
+    }}",
+            i,
+            i,
+            i - 1
+        )
+        .unwrap();
+    }
+    source.push_str("}\n");
+    source
+}
+
+/// A module with a single function returning a byte array literal of `len` bytes.
+fn huge_literal_module(len: usize) -> String {
+    let bytes: String = (0..len).map(|i| format!("{:02x}", i % 256)).collect();
+    format!(
+        "module Huge {{\n    public f(): bytearray {{\n        return h\"{}\";\n    }}\n}}\n",
+        bytes
+    )
+}
+
+/// A module with `count` functions, each with a few specification conditions.
+fn specified_functions_module(count: usize) -> String {
+    let mut source = "module Specified {\n    resource T { i: u64 }\n".to_string();
+    for i in 0..count {
+        writeln!(
+            source,
+            "    public f{}(x: u64, y: u64): u64 acquires T
+    requires x > 0 && y > {}
+    requires global_exists<Self.T>(txn_sender)
+    aborts_if x + y < {}
+    ensures RET == old(x) + y * {} - x
+    {{
+        return move(x) + move(y);
+    }}",
+            i, i, i, i
+        )
+        .unwrap();
+    }
+    source.push_str("}\n");
+    source
+}
+
+/// A program with `count` modules like `many_functions_module(functions)`.
+fn many_modules_program(count: usize, functions: usize) -> String {
+    let mut source = "modules:\n".to_string();
+    for i in 0..count {
+        source.push_str(&many_functions_module(functions).replacen("Many", &format!("M{}", i), 1));
+    }
+    source.push_str("script:\nmain() {\n    return;\n}\n");
+    source
+}
+
+fn synthetic_modules() -> Vec<(String, String)> {
+    vec![
+        ("deep_expression_64".to_string(), deep_expression_module(64)),
+        (
+            "long_expression_4096".to_string(),
+            long_expression_module(4096),
+        ),
+        (
+            "many_functions_1000".to_string(),
+            many_functions_module(1000),
+        ),
+        (
+            "huge_literal_64k".to_string(),
+            huge_literal_module(64 * 1024),
+        ),
+    ]
+}
+
+fn parse_stdlib(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_stdlib");
+    let len: usize = STDLIB_SOURCES.iter().map(|source| source.len()).sum();
+    group.throughput(Throughput::Bytes(len as u64));
+    group.bench_function("all", |b| {
+        b.iter(|| {
+            for source in STDLIB_SOURCES {
+                parse_module(source).unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+fn parse_skip_specs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_skip_specs");
+    let source = specified_functions_module(1000);
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    for skip_specs in &[false, true] {
+        let options = ParserOptions {
+            skip_specs: *skip_specs,
+            ..ParserOptions::default()
+        };
+        group.bench_with_input(
+            BenchmarkId::new("specified_functions_1000", skip_specs),
+            &options,
+            |b, options| b.iter(|| parse_module_with_options(&source, *options).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn parse_parallel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_parallel");
+    let source = many_modules_program(16, 100);
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    for parallel in &[false, true] {
+        let options = ParserOptions {
+            parallel: *parallel,
+            ..ParserOptions::default()
+        };
+        group.bench_with_input(
+            BenchmarkId::new("many_modules_16", parallel),
+            &options,
+            |b, options| b.iter(|| parse_program_with_options(&source, *options).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn compile_stdlib(c: &mut Criterion) {
+    let modules: Vec<_> = STDLIB_SOURCES
+        .iter()
+        .map(|source| parse_module(source).unwrap())
+        .collect();
+    c.bench_function("compile_stdlib", |b| {
+        b.iter(|| {
+            let mut deps: Vec<CompiledModule> = vec![];
+            for module in &modules {
+                let (compiled, _) =
+                    compile_module(AccountAddress::default(), module.clone(), &deps).unwrap();
+                deps.push(compiled);
+            }
+        })
+    });
+}
+
+fn parse_synthetic(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_synthetic");
+    for (name, source) in synthetic_modules() {
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &source, |b, source| {
+            b.iter(|| parse_module(source).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn compile_synthetic(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compile_synthetic");
+    let deps: &[CompiledModule] = &[];
+    for (name, source) in synthetic_modules() {
+        let module = parse_module(&source).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(name), &module, |b, module| {
+            b.iter(|| compile_module(AccountAddress::default(), module.clone(), deps).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    parse_stdlib,
+    parse_skip_specs,
+    parse_parallel,
+    compile_stdlib,
+    parse_synthetic,
+    compile_synthetic
+);
+criterion_main!(benches);
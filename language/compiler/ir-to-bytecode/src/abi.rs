@@ -0,0 +1,230 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Extraction of the ABI of transaction scripts.
+//!
+//! The ABI of a script describes what a client needs to build a transaction calling it: the type
+//! arguments and arguments of `main`, with their types, and their documentation. It serializes to
+//! JSON, so that wallets and SDKs can generate argument encoders from it.
+//!
+//! As for the reference documentation of modules, doc comments are recovered from the source: the
+//! documentation of the script is the block of comment lines directly above `main`, and an
+//! argument declared on its own line can be documented by a comment at the end of that line or by
+//! comment lines above it.
+
+use crate::{
+    docgen::{doc_after, doc_before},
+    parser::{parse_script, strip_comments},
+};
+use anyhow::Result;
+use move_ir_types::ast::{self, ImportDefinition, ModuleIdent, Script};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The ABI of a transaction script.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScriptAbi {
+    /// The name of the script, chosen by the caller, usually the name of its file.
+    pub name: String,
+    /// The documentation of the script, empty if there is none.
+    pub doc: String,
+    /// The type parameters of `main`, in order.
+    pub type_arguments: Vec<TypeArgumentAbi>,
+    /// The parameters of `main`, in order.
+    pub arguments: Vec<ArgumentAbi>,
+}
+
+/// A type parameter of a script.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TypeArgumentAbi {
+    pub name: String,
+    pub kind: KindAbi,
+}
+
+/// A parameter of a script.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ArgumentAbi {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: TypeAbi,
+    /// The documentation of the parameter, empty if there is none.
+    pub doc: String,
+}
+
+/// The kind of a type parameter.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KindAbi {
+    All,
+    Resource,
+    Unrestricted,
+}
+
+/// The type of a parameter.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TypeAbi {
+    Address,
+    U8,
+    U64,
+    U128,
+    Bool,
+    ByteArray,
+    /// A struct, with the module it is declared in resolved through the imports of the script.
+    Struct {
+        /// The address the module is published under, or `None` for a module of the transaction.
+        address: Option<String>,
+        module: String,
+        name: String,
+        type_arguments: Vec<TypeAbi>,
+    },
+    Reference {
+        mutable: bool,
+        to: Box<TypeAbi>,
+    },
+    TypeParameter(String),
+    /// A tuple, which the script is given one argument per element of.
+    Tuple(Vec<TypeAbi>),
+}
+
+impl ScriptAbi {
+    /// Returns the ABI as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("an ABI always serializes to JSON")
+    }
+
+    /// Parses an ABI from JSON.
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+impl fmt::Display for TypeAbi {
+    /// Formats the type as in the IR, with struct names qualified by their module.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeAbi::Address => write!(f, "address"),
+            TypeAbi::U8 => write!(f, "u8"),
+            TypeAbi::U64 => write!(f, "u64"),
+            TypeAbi::U128 => write!(f, "u128"),
+            TypeAbi::Bool => write!(f, "bool"),
+            TypeAbi::ByteArray => write!(f, "bytearray"),
+            TypeAbi::Struct {
+                module,
+                name,
+                type_arguments,
+                ..
+            } => {
+                write!(f, "{}.{}", module, name)?;
+                if !type_arguments.is_empty() {
+                    let tys: Vec<String> = type_arguments.iter().map(|ty| ty.to_string()).collect();
+                    write!(f, "<{}>", tys.join(", "))?;
+                }
+                Ok(())
+            }
+            TypeAbi::Reference { mutable, to } => {
+                write!(f, "&{}{}", if *mutable { "mut " } else { "" }, to)
+            }
+            TypeAbi::TypeParameter(name) => write!(f, "{}", name),
+            TypeAbi::Tuple(tys) => {
+                let tys: Vec<String> = tys.iter().map(|ty| ty.to_string()).collect();
+                write!(f, "({})", tys.join(", "))
+            }
+        }
+    }
+}
+
+/// Parses the source of a script named `name` and returns its ABI.
+pub fn script_abi(name: &str, source: &str) -> Result<ScriptAbi> {
+    let script = parse_script(source)?;
+    Ok(extract_abi(name, source, &script))
+}
+
+/// Returns the ABI of a script named `name`, parsed from `source`.
+pub fn extract_abi(name: &str, source: &str, script: &Script) -> ScriptAbi {
+    let signature = &script.main.value.signature;
+    let doc = main_position(&strip_comments(source))
+        .map(|pos| doc_before(source, pos).join("\n"))
+        .unwrap_or_default();
+    let type_arguments = signature
+        .type_formals
+        .iter()
+        .map(|(var, kind)| TypeArgumentAbi {
+            name: var.value.to_string(),
+            kind: match kind {
+                ast::Kind::All => KindAbi::All,
+                ast::Kind::Resource => KindAbi::Resource,
+                ast::Kind::Unrestricted => KindAbi::Unrestricted,
+            },
+        })
+        .collect();
+    let arguments = signature
+        .formals
+        .iter()
+        .map(|(var, ty)| {
+            let pos = var.span.start().to_usize();
+            let mut doc = doc_before(source, pos);
+            doc.extend(doc_after(source, pos));
+            ArgumentAbi {
+                name: var.value.to_string(),
+                type_: type_abi(&script.imports, ty),
+                doc: doc.join("\n"),
+            }
+        })
+        .collect();
+    ScriptAbi {
+        name: name.to_string(),
+        doc,
+        type_arguments,
+        arguments,
+    }
+}
+
+/// Returns the position of the `main` keyword in the source of a script without comments. The
+/// span of `main` cannot be used, as it starts at the beginning of the script, before the imports.
+fn main_position(stripped: &str) -> Option<usize> {
+    let is_ident_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+    stripped
+        .match_indices("main")
+        .map(|(idx, _)| idx)
+        .find(|idx| {
+            !stripped[..*idx].ends_with(is_ident_char)
+                && !stripped[idx + "main".len()..].starts_with(is_ident_char)
+        })
+}
+
+fn type_abi(imports: &[ImportDefinition], ty: &ast::Type) -> TypeAbi {
+    match ty {
+        ast::Type::Address => TypeAbi::Address,
+        ast::Type::U8 => TypeAbi::U8,
+        ast::Type::U64 => TypeAbi::U64,
+        ast::Type::U128 => TypeAbi::U128,
+        ast::Type::Bool => TypeAbi::Bool,
+        ast::Type::ByteArray => TypeAbi::ByteArray,
+        ast::Type::Struct(ident, tys) => {
+            let import = imports.iter().find(|import| import.alias == ident.module);
+            let (address, module) = match import.map(|import| &import.ident) {
+                Some(ModuleIdent::Qualified(module)) => (
+                    Some(format!("0x{:x}", module.address)),
+                    module.name.to_string(),
+                ),
+                Some(ModuleIdent::Transaction(module)) => (None, module.to_string()),
+                None => (None, ident.module.to_string()),
+            };
+            TypeAbi::Struct {
+                address,
+                module,
+                name: ident.name.to_string(),
+                type_arguments: tys.iter().map(|ty| type_abi(imports, ty)).collect(),
+            }
+        }
+        ast::Type::Reference(mutable, ty) => TypeAbi::Reference {
+            mutable: *mutable,
+            to: Box::new(type_abi(imports, ty)),
+        },
+        ast::Type::TypeParameter(var) => TypeAbi::TypeParameter(var.to_string()),
+        ast::Type::Tuple(tys) => {
+            TypeAbi::Tuple(tys.iter().map(|ty| type_abi(imports, ty)).collect())
+        }
+    }
+}
@@ -0,0 +1,548 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Inference of the `acquires` list of module functions.
+//!
+//! A function acquires every resource it reads with `borrow_global` or removes with `move_from`,
+//! as well as everything acquired by the functions of the same module it calls. The inferred sets
+//! are computed as a fixpoint over the intra-module call graph, so recursive functions are
+//! handled. Native functions cannot be inspected, and their declared list is trusted as is.
+//!
+//! Declared lists can also be resolved against the module, to reject entries that do not name one
+//! of its resources before they reach translation.
+//!
+//! Declared entries that no path of a function exercises are reported as well, since stale lists
+//! make the transactions acquiring the same resources run one after the other. Code that no path
+//! reaches, following a statement that always exits its block or in a branch on a literal that is
+//! never taken, does not count, although the bytecode verifier still requires the resources it
+//! accesses to be declared.
+//!
+//! Diagnostics locate functions by their header. A resource acquired through calls is justified by
+//! the chain of calls leading to the `borrow_global` or `move_from` that acquires it.
+
+use crate::{constants::bool_value, errors::AcquiresError};
+use move_ir_types::ast::{
+    visit_exp, Block_, Builtin, Cmd_, Exp, Exp_, FunctionBody, FunctionCall_, FunctionName,
+    LValue_, Loc, ModuleDefinition, ModuleName, Statement, StructName,
+};
+use std::{
+    collections::{btree_map::Entry, BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// How a function comes to acquire a resource.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AcquiredBy {
+    /// A `borrow_global` of the resource in the function body
+    BorrowGlobal,
+    /// A `move_from` of the resource in the function body
+    MoveFrom,
+    /// A call to a function of the same module that acquires the resource
+    Call(FunctionName),
+    /// The declared list of a native function
+    Native,
+}
+
+/// The reason a function acquires a resource, along with the location that justifies it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Acquisition {
+    /// The location of the `borrow_global`, `move_from` or call expression, or of the function
+    /// header for native functions
+    pub loc: Loc,
+    /// The kind of use
+    pub by: AcquiredBy,
+}
+
+impl Acquisition {
+    /// Describes the use for the resource `name`.
+    pub fn describe(&self, name: &StructName) -> String {
+        match &self.by {
+            AcquiredBy::BorrowGlobal => format!("`borrow_global<{}>` acquires {}", name, name),
+            AcquiredBy::MoveFrom => format!("`move_from<{}>` acquires {}", name, name),
+            AcquiredBy::Call(function) => format!("the call to `{}` acquires {}", function, name),
+            AcquiredBy::Native => format!("the native declaration acquires {}", name),
+        }
+    }
+}
+
+/// Difference between the declared and the inferred `acquires` list of a function.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AcquiresDiff {
+    /// The function whose list does not match.
+    pub function: FunctionName,
+    /// The location of the function header.
+    pub loc: Loc,
+    /// Resources the function acquires but does not declare.
+    pub missing: Vec<StructName>,
+    /// Resources the function declares but never acquires.
+    pub extraneous: Vec<StructName>,
+    /// Why the function acquires each of the resources it does, as returned by
+    /// [`acquisition_chain`](fn.acquisition_chain.html).
+    pub uses: BTreeMap<StructName, Vec<(FunctionName, Acquisition)>>,
+}
+
+impl AcquiresDiff {
+    /// The list the function should declare.
+    pub fn suggested(&self) -> Vec<StructName> {
+        self.uses.keys().cloned().collect()
+    }
+}
+
+impl fmt::Display for AcquiresDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "function {}:", self.function)?;
+        if !self.missing.is_empty() {
+            write!(f, " missing acquires {}", comma_separated(&self.missing))?;
+        }
+        if !self.missing.is_empty() && !self.extraneous.is_empty() {
+            write!(f, ";")?;
+        }
+        if !self.extraneous.is_empty() {
+            write!(
+                f,
+                " extraneous acquires {}",
+                comma_separated(&self.extraneous)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Entries of the declared `acquires` list of a function naming resources that no path of the
+/// function accesses.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnusedAcquires {
+    /// The function declaring the list.
+    pub function: FunctionName,
+    /// The location of the function header.
+    pub loc: Loc,
+    /// The resources declared but never accessed.
+    pub unused: Vec<StructName>,
+    /// The declared list without the unused entries, in declaration order.
+    pub trimmed: Vec<StructName>,
+    /// The unused resources that are accessed in code no path reaches, with the uses leading to
+    /// the access as returned by [`acquisition_chain`](fn.acquisition_chain.html). The access
+    /// must be removed along with the entry.
+    pub unreachable: Vec<(StructName, Vec<(FunctionName, Acquisition)>)>,
+}
+
+impl fmt::Display for UnusedAcquires {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "function {} acquires {}, which no path of the function accesses",
+            self.function,
+            comma_separated(&self.unused)
+        )
+    }
+}
+
+/// Joins resource names the way they are written in an `acquires` list.
+pub fn comma_separated(names: &[StructName]) -> String {
+    names
+        .iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Infers the set of resources acquired by each function of the module.
+pub fn infer_acquires(module: &ModuleDefinition) -> BTreeMap<FunctionName, BTreeSet<StructName>> {
+    infer_acquisitions(module)
+        .into_iter()
+        .map(|(name, uses)| (name, uses.into_iter().map(|(name, _)| name).collect()))
+        .collect()
+}
+
+/// Infers the resources acquired by each function of the module, along with the first use that
+/// justifies each of them. Direct uses of global storage are preferred over calls.
+pub fn infer_acquisitions(
+    module: &ModuleDefinition,
+) -> BTreeMap<FunctionName, BTreeMap<StructName, Acquisition>> {
+    acquisitions(module, false)
+}
+
+fn acquisitions(
+    module: &ModuleDefinition,
+    reachable_only: bool,
+) -> BTreeMap<FunctionName, BTreeMap<StructName, Acquisition>> {
+    let mut acquired = BTreeMap::new();
+    let mut callees = BTreeMap::new();
+    for (name, function) in &module.functions {
+        let mut usage = Usage {
+            reachable_only,
+            ..Usage::default()
+        };
+        match &function.value.body {
+            FunctionBody::Move { code, .. } => {
+                usage.block(code);
+            }
+            FunctionBody::Native => {
+                for struct_name in &function.value.acquires {
+                    usage.acquired.insert(
+                        struct_name.clone(),
+                        Acquisition {
+                            loc: function.value.header,
+                            by: AcquiredBy::Native,
+                        },
+                    );
+                }
+            }
+        }
+        acquired.insert(name.clone(), usage.acquired);
+        callees.insert(name.clone(), usage.callees);
+    }
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (name, called) in &callees {
+            let mut transitive = vec![];
+            for (callee, loc) in called {
+                for struct_name in acquired
+                    .get(callee)
+                    .into_iter()
+                    .flat_map(|uses| uses.keys())
+                {
+                    let acquisition = Acquisition {
+                        loc: *loc,
+                        by: AcquiredBy::Call(callee.clone()),
+                    };
+                    transitive.push((struct_name.clone(), acquisition));
+                }
+            }
+            let uses = acquired.get_mut(name).expect("every function has an entry");
+            for (struct_name, acquisition) in transitive {
+                if let Entry::Vacant(entry) = uses.entry(struct_name) {
+                    entry.insert(acquisition);
+                    changed = true;
+                }
+            }
+        }
+    }
+    acquired
+}
+
+/// Follows the acquisition of the resource `name` by `function` through the calls justifying it,
+/// given the acquisitions inferred for the module. The use in `function` comes first, followed by
+/// the use in each called function, down to the one that does not go through a call. Empty if
+/// `function` does not acquire the resource.
+pub fn acquisition_chain(
+    acquisitions: &BTreeMap<FunctionName, BTreeMap<StructName, Acquisition>>,
+    function: &FunctionName,
+    name: &StructName,
+) -> Vec<(FunctionName, Acquisition)> {
+    let mut chain: Vec<(FunctionName, Acquisition)> = vec![];
+    let mut current = function;
+    while let Some(acquisition) = acquisitions.get(current).and_then(|uses| uses.get(name)) {
+        // An acquisition through a call is only inferred once the callee acquires the resource,
+        // so the chain ends. Stop on a cycle anyway rather than loop on an inconsistent map.
+        if chain.iter().any(|(visited, _)| visited == current) {
+            break;
+        }
+        chain.push((current.clone(), acquisition.clone()));
+        match &acquisition.by {
+            AcquiredBy::Call(callee) => current = callee,
+            AcquiredBy::BorrowGlobal | AcquiredBy::MoveFrom | AcquiredBy::Native => break,
+        }
+    }
+    chain
+}
+
+/// Compares the declared `acquires` lists with the inferred ones, returning an entry for every
+/// function where they differ, in declaration order.
+pub fn acquires_diff(module: &ModuleDefinition) -> Vec<AcquiresDiff> {
+    let inferred = infer_acquisitions(module);
+    module
+        .functions
+        .iter()
+        .filter_map(|(name, function)| {
+            let declared: BTreeSet<StructName> = function.value.acquires.iter().cloned().collect();
+            let uses: BTreeMap<_, _> = inferred
+                .get(name)
+                .into_iter()
+                .flat_map(|uses| uses.keys())
+                .map(|struct_name| {
+                    let chain = acquisition_chain(&inferred, name, struct_name);
+                    (struct_name.clone(), chain)
+                })
+                .collect();
+            let missing: Vec<_> = uses
+                .keys()
+                .filter(|struct_name| !declared.contains(*struct_name))
+                .cloned()
+                .collect();
+            let extraneous: Vec<_> = declared
+                .iter()
+                .filter(|struct_name| !uses.contains_key(*struct_name))
+                .cloned()
+                .collect();
+            if missing.is_empty() && extraneous.is_empty() {
+                None
+            } else {
+                Some(AcquiresDiff {
+                    function: name.clone(),
+                    loc: function.value.header,
+                    missing,
+                    extraneous,
+                    uses,
+                })
+            }
+        })
+        .collect()
+}
+
+/// Finds the entries of the declared `acquires` lists naming a resource of the module that no
+/// path of the function accesses, returning an entry for every function that has some, in
+/// declaration order. Code following a statement that always exits its block and branches on a
+/// `true` or `false` literal that are never taken do not count as accesses, but the bytecode
+/// verifier still requires them to be declared, so they are reported along with the entries.
+pub fn unused_acquires(module: &ModuleDefinition) -> Vec<UnusedAcquires> {
+    let resources: BTreeSet<&StructName> = module
+        .structs
+        .iter()
+        .filter(|s| s.value.is_nominal_resource)
+        .map(|s| &s.value.name)
+        .collect();
+    let reachable = acquisitions(module, true);
+    let all = infer_acquisitions(module);
+    module
+        .functions
+        .iter()
+        .filter(|(_, function)| match &function.value.body {
+            FunctionBody::Move { .. } => true,
+            FunctionBody::Native => false,
+        })
+        .filter_map(|(name, function)| {
+            let accessed = |struct_name: &StructName| {
+                reachable
+                    .get(name)
+                    .map_or(false, |uses| uses.contains_key(struct_name))
+            };
+            let (unused, trimmed): (Vec<StructName>, Vec<StructName>) = function
+                .value
+                .acquires
+                .iter()
+                .cloned()
+                .partition(|struct_name| resources.contains(struct_name) && !accessed(struct_name));
+            if unused.is_empty() {
+                return None;
+            }
+            let unreachable = unused
+                .iter()
+                .map(|struct_name| {
+                    let chain = acquisition_chain(&all, name, struct_name);
+                    (struct_name.clone(), chain)
+                })
+                .filter(|(_, chain)| !chain.is_empty())
+                .collect();
+            Some(UnusedAcquires {
+                function: name.clone(),
+                loc: function.value.header,
+                unused,
+                trimmed,
+                unreachable,
+            })
+        })
+        .collect()
+}
+
+/// Replaces the declared `acquires` list of every function in the module with the inferred one.
+pub fn fill_acquires(module: &mut ModuleDefinition) {
+    let mut inferred = infer_acquires(module);
+    for (name, function) in &mut module.functions {
+        if let Some(acquired) = inferred.remove(name) {
+            function.value.acquires = acquired.into_iter().collect();
+        }
+    }
+}
+
+/// Checks that every entry of the declared `acquires` lists names a resource declared in the
+/// module, returning an error for each entry that does not, in declaration order.
+pub fn resolve_acquires(module: &ModuleDefinition) -> Vec<AcquiresError> {
+    let resources: Vec<&StructName> = module
+        .structs
+        .iter()
+        .filter(|s| s.value.is_nominal_resource)
+        .map(|s| &s.value.name)
+        .collect();
+    let mut errors = vec![];
+    for (function, definition) in &module.functions {
+        for name in &definition.value.acquires {
+            if resources.contains(&name) {
+                continue;
+            }
+            let loc = definition.value.header;
+            let function = function.clone();
+            let suggestion = closest_name(name, &resources);
+            let is_struct = module.structs.iter().any(|s| &s.value.name == name);
+            errors.push(if is_struct {
+                AcquiresError::NotAResource {
+                    loc,
+                    function,
+                    name: name.clone(),
+                    suggestion,
+                }
+            } else {
+                AcquiresError::Unknown {
+                    loc,
+                    function,
+                    name: name.clone(),
+                    suggestion,
+                }
+            });
+        }
+    }
+    errors
+}
+
+/// Returns the candidate closest to `name`, if it is close enough to be a misspelling of it.
+fn closest_name(name: &StructName, candidates: &[&StructName]) -> Option<StructName> {
+    let name = name.as_inner().as_str();
+    let max_distance = std::cmp::max(1, name.len() / 3);
+    candidates
+        .iter()
+        .map(|candidate| {
+            (
+                edit_distance(name, candidate.as_inner().as_str()),
+                *candidate,
+            )
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.clone())
+}
+
+/// The number of single character insertions, deletions and substitutions turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.as_bytes();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.bytes().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + if ca == *cb { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = std::cmp::min(substitution, std::cmp::min(row[j], row[j + 1]) + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Global storage accesses and intra-module calls found in a function body.
+#[derive(Default)]
+struct Usage {
+    acquired: BTreeMap<StructName, Acquisition>,
+    callees: BTreeMap<FunctionName, Loc>,
+    /// Whether to skip the code no path reaches: the statements following one that always exits
+    /// the block, and the branches on a `true` or `false` literal that are never taken.
+    reachable_only: bool,
+}
+
+impl Usage {
+    fn acquire(&mut self, name: &StructName, loc: Loc, by: AcquiredBy) {
+        self.acquired
+            .entry(name.clone())
+            .or_insert(Acquisition { loc, by });
+    }
+
+    // Returns whether the block always exits, by returning, aborting, breaking or continuing.
+    fn block(&mut self, block: &Block_) -> bool {
+        let mut exits = false;
+        for statement in &block.stmts {
+            if exits && self.reachable_only {
+                break;
+            }
+            exits |= self.statement(statement);
+        }
+        exits
+    }
+
+    fn statement(&mut self, statement: &Statement) -> bool {
+        match statement {
+            Statement::CommandStatement(cmd) => {
+                self.cmd(&cmd.value);
+                match &cmd.value {
+                    Cmd_::Return(_) | Cmd_::Abort(_) | Cmd_::Break(_) | Cmd_::Continue => true,
+                    Cmd_::Assign(..) | Cmd_::Unpack(..) | Cmd_::Emit(..) | Cmd_::Exp(_) => false,
+                }
+            }
+            Statement::IfElseStatement(if_else) => {
+                self.exp(&if_else.cond);
+                let cond = bool_value(&if_else.cond).filter(|_| self.reachable_only);
+                let if_exits = cond != Some(false) && self.block(&if_else.if_block.value);
+                let else_exits = match &if_else.else_block {
+                    Some(else_block) if cond != Some(true) => self.block(&else_block.value),
+                    _ => false,
+                };
+                match cond {
+                    Some(true) => if_exits,
+                    Some(false) => else_exits,
+                    None => if_exits && else_exits,
+                }
+            }
+            Statement::WhileStatement(while_) => {
+                self.exp(&while_.cond);
+                if !self.reachable_only || bool_value(&while_.cond) != Some(false) {
+                    self.block(&while_.block.value);
+                }
+                false
+            }
+            Statement::LoopStatement(loop_) => {
+                for lvalue in &loop_.results {
+                    if let LValue_::Mutate(e) = &lvalue.value {
+                        self.exp(e);
+                    }
+                }
+                self.block(&loop_.block.value);
+                false
+            }
+            Statement::EmptyStatement => false,
+        }
+    }
+
+    fn cmd(&mut self, cmd: &Cmd_) {
+        match cmd {
+            Cmd_::Assign(lvalues, e) => {
+                for lvalue in lvalues {
+                    if let LValue_::Mutate(e) = &lvalue.value {
+                        self.exp(e);
+                    }
+                }
+                self.exp(e);
+            }
+            Cmd_::Unpack(_, _, _, e) | Cmd_::Return(e) | Cmd_::Exp(e) => self.exp(e),
+            Cmd_::Emit(handle, e) => {
+                self.exp(handle);
+                self.exp(e);
+            }
+            Cmd_::Abort(e_opt) | Cmd_::Break(e_opt) => {
+                if let Some(e) = e_opt {
+                    self.exp(e);
+                }
+            }
+            Cmd_::Continue => (),
+        }
+    }
+
+    fn exp(&mut self, e: &Exp) {
+        visit_exp(e, |e| {
+            if let Exp_::FunctionCall(call, _) = &e.value {
+                match &call.value {
+                    FunctionCall_::Builtin(Builtin::BorrowGlobal(_, name, _)) => {
+                        self.acquire(name, e.span, AcquiredBy::BorrowGlobal)
+                    }
+                    FunctionCall_::Builtin(Builtin::MoveFrom(name, _)) => {
+                        self.acquire(name, e.span, AcquiredBy::MoveFrom)
+                    }
+                    FunctionCall_::ModuleFunctionCall { module, name, .. }
+                        if module.as_inner() == ModuleName::self_name() =>
+                    {
+                        self.callees.entry(name.clone()).or_insert(e.span);
+                    }
+                    FunctionCall_::Builtin(_) | FunctionCall_::ModuleFunctionCall { .. } => (),
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Expected-failure annotations.
+//!
+//! Test files state how they are expected to fail with `//!` comments in their header, the lines
+//! of comments before the code, e.g.:
+//!
+//! ```text
+//! //! abort 7
+//! //! verifier: MOVELOC_UNAVAILABLE_ERROR
+//! ```
+//!
+//! Other `//!` lines are left to the test harnesses, which use them for their own configuration,
+//! such as the [directives](../directives/index.html) of the transactions of functional tests.
+
+use crate::{errors::AnnotationError, parser::parse_script_or_module};
+use anyhow::Result;
+use libra_types::vm_error::StatusCode;
+use move_ir_types::ast::ScriptOrModule;
+use std::convert::TryFrom;
+
+/// An expected outcome of compiling and running a source.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Annotation {
+    /// `//! abort <code>`: the code aborts with the given code.
+    Abort(u64),
+    /// `//! compiler: <message>`: the compiler rejects the code with an error containing the
+    /// message.
+    Compiler(String),
+    /// `//! verifier: <status>`: the bytecode verifier rejects the code with the given status.
+    Verifier(StatusCode),
+    /// `//! runtime: <status>`: execution fails with the given status.
+    Runtime(StatusCode),
+}
+
+impl Annotation {
+    /// Parses a line as an annotation. Returns `None` if the line is not one, including for `//!`
+    /// lines meant for test harnesses, and fails with a message if it is malformed.
+    pub fn parse_line(line: &str) -> std::result::Result<Option<Self>, String> {
+        let line = line.trim();
+        if !line.starts_with("//!") {
+            return Ok(None);
+        }
+        let text = line[3..].trim();
+        let key_len = text
+            .find(|c: char| c.is_whitespace() || c == ':')
+            .unwrap_or_else(|| text.len());
+        let (key, value) = text.split_at(key_len);
+        let value = value.trim_start();
+        let value = if value.starts_with(':') {
+            value[1..].trim_start()
+        } else {
+            value
+        };
+        let annotation = match key {
+            "abort" => Annotation::Abort(
+                value
+                    .parse()
+                    .map_err(|_| format!("expected an abort code, found {:?}", value))?,
+            ),
+            "compiler" if !value.is_empty() => Annotation::Compiler(value.to_string()),
+            "compiler" => return Err("expected an error message".to_string()),
+            "verifier" => Annotation::Verifier(status_code(value)?),
+            "runtime" => Annotation::Runtime(status_code(value)?),
+            _ => return Ok(None),
+        };
+        Ok(Some(annotation))
+    }
+}
+
+/// Returns the annotations in the header of `source`.
+pub fn parse_annotations(source: &str) -> std::result::Result<Vec<Annotation>, AnnotationError> {
+    let mut annotations = vec![];
+    for (idx, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        if !trimmed.is_empty() && !trimmed.starts_with("//") {
+            break;
+        }
+        let annotation = Annotation::parse_line(line).map_err(|message| AnnotationError {
+            line: idx + 1,
+            message,
+        })?;
+        annotations.extend(annotation);
+    }
+    Ok(annotations)
+}
+
+/// Parses a script or module, returning it with the annotations in its header.
+pub fn parse_annotated_script_or_module(source: &str) -> Result<(ScriptOrModule, Vec<Annotation>)> {
+    let annotations = parse_annotations(source)?;
+    Ok((parse_script_or_module(source)?, annotations))
+}
+
+/// Returns the status code with the given name.
+fn status_code(name: &str) -> std::result::Result<StatusCode, String> {
+    // Status codes are grouped in ranges of a thousand, and only the unknown status is above.
+    (0..10_000)
+        .chain(std::iter::once(std::u64::MAX))
+        .filter_map(|code| StatusCode::try_from(code).ok())
+        .find(|status| format!("{:?}", status) == name)
+        .ok_or_else(|| format!("unknown status {:?}", name))
+}
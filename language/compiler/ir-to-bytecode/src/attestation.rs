@@ -0,0 +1,226 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Digests of sources and of compiled output, for build attestation.
+//!
+//! The digest of a script or module is computed over its AST as printed by the formatter, so
+//! that it only changes with the code itself: comments, whitespace, the layout of the file and
+//! the locations the parser records do not affect it. The digest of compiled output is computed
+//! over its serialized form, which the compiler produces deterministically.
+//!
+//! A [`Manifest`](struct.Manifest.html) ties the digest of the source of every unit of a build to
+//! the digest of its bytecode. Publishing it attests which source a build was made from, and
+//! lets a source-verification service recompile the source and check both digests.
+//!
+//! A manifest is a JSON object starting with a `format` and a `version`, like bundles.
+
+use crate::{
+    bundle::UnitKind,
+    formatter::{format_module, format_script, FormatOptions},
+};
+use anyhow::{bail, format_err, Context, Result};
+use libra_crypto::HashValue;
+use move_ir_types::ast::{ModuleDefinition, Script};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use vm::file_format::{CompiledModule, CompiledScript};
+
+/// The value of the `format` field of manifests.
+pub const MANIFEST_FORMAT: &str = "move-ir-manifest";
+/// The version of the manifests this crate writes, and the only one it reads.
+pub const MANIFEST_VERSION: u64 = 1;
+
+// Prefixes keeping the digests of different kinds of input apart.
+const MODULE_SOURCE_SALT: &[u8] = b"MOVE_IR::ModuleSource";
+const SCRIPT_SOURCE_SALT: &[u8] = b"MOVE_IR::ScriptSource";
+const BYTECODE_SALT: &[u8] = b"MOVE_IR::Bytecode";
+
+/// The digest of the source of a module.
+pub fn module_digest(module: &ModuleDefinition) -> HashValue {
+    let source = format_module(module, &FormatOptions::default());
+    salted_digest(MODULE_SOURCE_SALT, source.as_bytes())
+}
+
+/// The digest of the source of a script.
+pub fn script_digest(script: &Script) -> HashValue {
+    let source = format_script(script, &FormatOptions::default());
+    salted_digest(SCRIPT_SOURCE_SALT, source.as_bytes())
+}
+
+/// The digest of a compiled module.
+pub fn compiled_module_digest(module: &CompiledModule) -> Result<HashValue> {
+    let mut bytecode = vec![];
+    module.serialize(&mut bytecode)?;
+    Ok(salted_digest(BYTECODE_SALT, &bytecode))
+}
+
+/// The digest of a compiled script.
+pub fn compiled_script_digest(script: &CompiledScript) -> Result<HashValue> {
+    let mut bytecode = vec![];
+    script.serialize(&mut bytecode)?;
+    Ok(salted_digest(BYTECODE_SALT, &bytecode))
+}
+
+fn salted_digest(salt: &[u8], bytes: &[u8]) -> HashValue {
+    let mut buffer = salt.to_vec();
+    buffer.extend_from_slice(bytes);
+    HashValue::from_sha3_256(&buffer)
+}
+
+/// The digests of a unit of a build, in hex.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UnitDigests {
+    pub kind: UnitKind,
+    pub source: String,
+    pub bytecode: String,
+}
+
+/// The digests of the units of a build.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Manifest {
+    /// Always `MANIFEST_FORMAT`.
+    pub format: String,
+    /// The version of the format.
+    pub version: u64,
+    /// The digests of the modules, by module name, and of the scripts, by the name given to them.
+    pub units: BTreeMap<String, UnitDigests>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Manifest {
+            format: MANIFEST_FORMAT.to_string(),
+            version: MANIFEST_VERSION,
+            units: BTreeMap::new(),
+        }
+    }
+}
+
+impl Manifest {
+    /// Records a module compiled from `module`.
+    pub fn add_module(
+        &mut self,
+        module: &ModuleDefinition,
+        compiled: &CompiledModule,
+    ) -> Result<()> {
+        let digests = UnitDigests {
+            kind: UnitKind::Module,
+            source: module_digest(module).to_hex(),
+            bytecode: compiled_module_digest(compiled)?.to_hex(),
+        };
+        self.add(module.name.to_string(), digests)
+    }
+
+    /// Records a script compiled from `script`, under `name`.
+    pub fn add_script(
+        &mut self,
+        name: &str,
+        script: &Script,
+        compiled: &CompiledScript,
+    ) -> Result<()> {
+        let digests = UnitDigests {
+            kind: UnitKind::Script,
+            source: script_digest(script).to_hex(),
+            bytecode: compiled_script_digest(compiled)?.to_hex(),
+        };
+        self.add(name.to_string(), digests)
+    }
+
+    fn add(&mut self, name: String, digests: UnitDigests) -> Result<()> {
+        if self.units.contains_key(&name) {
+            bail!("The manifest already has a unit named {}", name);
+        }
+        self.units.insert(name, digests);
+        Ok(())
+    }
+
+    /// Checks that `module` and the module compiled from it match the manifest.
+    pub fn check_module(&self, module: &ModuleDefinition, compiled: &CompiledModule) -> Result<()> {
+        self.check(
+            &module.name.to_string(),
+            UnitKind::Module,
+            module_digest(module),
+            compiled_module_digest(compiled)?,
+        )
+    }
+
+    /// Checks that `script` and the script compiled from it match the entry `name` of the
+    /// manifest.
+    pub fn check_script(
+        &self,
+        name: &str,
+        script: &Script,
+        compiled: &CompiledScript,
+    ) -> Result<()> {
+        self.check(
+            name,
+            UnitKind::Script,
+            script_digest(script),
+            compiled_script_digest(compiled)?,
+        )
+    }
+
+    fn check(
+        &self,
+        name: &str,
+        kind: UnitKind,
+        source: HashValue,
+        bytecode: HashValue,
+    ) -> Result<()> {
+        let digests = self
+            .units
+            .get(name)
+            .filter(|digests| digests.kind == kind)
+            .ok_or_else(|| {
+                let kind = match kind {
+                    UnitKind::Script => "script",
+                    UnitKind::Module => "module",
+                };
+                format_err!("The manifest has no {} named {}", kind, name)
+            })?;
+        if digests.source != source.to_hex() {
+            bail!(
+                "The source of {} does not match the manifest: expected digest {}, found {}",
+                name,
+                digests.source,
+                source.to_hex()
+            );
+        }
+        if digests.bytecode != bytecode.to_hex() {
+            bail!(
+                "The bytecode of {} does not match the manifest: expected digest {}, found {}",
+                name,
+                digests.bytecode,
+                bytecode.to_hex()
+            );
+        }
+        Ok(())
+    }
+
+    /// Serializes the manifest.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Deserializes a manifest, checking its format and version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Header {
+            format: String,
+            version: u64,
+        }
+
+        let header: Header = serde_json::from_slice(bytes).context("Not a manifest")?;
+        if header.format != MANIFEST_FORMAT {
+            bail!("Not a manifest: unknown format {}", header.format);
+        }
+        if header.version != MANIFEST_VERSION {
+            bail!(
+                "Unsupported manifest version {}, expected {}",
+                header.version,
+                MANIFEST_VERSION
+            );
+        }
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
@@ -0,0 +1,422 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Report of the references taken and handed out by each function.
+//!
+//! For every function with a body, the report lists what the function borrows, mutably or
+//! immutably: its locals, resources in global storage, and fields of either reached through a
+//! borrow. It also lists the references the function passes to the functions it calls, and the
+//! references it freezes, each with its location.
+//!
+//! A field borrow is attributed to the place its path starts from: `&mut move(x).f` borrows `x`
+//! and `&borrow_global<T>(a).f` borrows `T`. When that place is a local holding a reference, the
+//! borrow is of what the reference points to, written `*r`. A value passed to a function is only
+//! known to be a reference if it is a borrow, a freeze, or a local declared with a reference type;
+//! references returned by calls are not followed.
+
+use crate::metrics::ReportedFunction;
+use move_ir_types::ast::{
+    Block_, Builtin, Cmd_, Exp, Exp_, Field_, Function, FunctionBody, FunctionCall_, LValue_, Loc,
+    ModuleDefinition, Script, Statement, StructName, Type, Var_,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// What a reference points into.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Place {
+    /// A local holding a value.
+    Local(Var_),
+    /// The value a local holding a reference points to.
+    Reference(Var_),
+    /// A resource in global storage.
+    Global(StructName),
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Place::Local(var) => write!(f, "`{}`", var),
+            Place::Reference(var) => write!(f, "`*{}`", var),
+            Place::Global(name) => write!(f, "global `{}`", name),
+        }
+    }
+}
+
+/// A borrow, a reference passed to a function, or a freeze.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReferenceUse {
+    pub loc: Loc,
+    pub kind: ReferenceUseKind,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReferenceUseKind {
+    /// `&x`, `&mut x`, `borrow_global<T>`, or `&e.f` when `field` is set. The place is unknown
+    /// for the fields of a value returned by a call.
+    Borrow {
+        mutable: bool,
+        place: Option<Place>,
+        field: Option<Field_>,
+    },
+    /// A reference passed to `callee`, written as in the call.
+    Escape {
+        mutable: bool,
+        place: Option<Place>,
+        callee: String,
+    },
+    /// `freeze(e)`.
+    Freeze { place: Option<Place> },
+}
+
+fn mutability(mutable: bool) -> &'static str {
+    if mutable {
+        "mutably"
+    } else {
+        "immutably"
+    }
+}
+
+impl fmt::Display for ReferenceUse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ReferenceUseKind::Borrow {
+                mutable,
+                place,
+                field,
+            } => {
+                match (field, place) {
+                    (Some(field), Some(place)) => write!(f, "field `{}` of {}", field, place)?,
+                    (Some(field), None) => write!(f, "field `{}`", field)?,
+                    (None, Some(place)) => write!(f, "{}", place)?,
+                    (None, None) => write!(f, "value")?,
+                }
+                write!(f, " borrowed {}", mutability(*mutable))
+            }
+            ReferenceUseKind::Escape {
+                mutable,
+                place,
+                callee,
+            } => {
+                write!(
+                    f,
+                    "{}",
+                    if *mutable {
+                        "mutable reference"
+                    } else {
+                        "reference"
+                    }
+                )?;
+                if let Some(place) = place {
+                    write!(f, " to {}", place)?;
+                }
+                write!(f, " passed to `{}`", callee)
+            }
+            ReferenceUseKind::Freeze { place: Some(place) } => {
+                write!(f, "reference to {} frozen", place)
+            }
+            ReferenceUseKind::Freeze { place: None } => write!(f, "reference frozen"),
+        }
+    }
+}
+
+/// The references of a function.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FunctionBorrows {
+    pub function: ReportedFunction,
+    pub loc: Loc,
+    /// The places borrowed mutably, directly or through one of their fields.
+    pub mutable: BTreeSet<Place>,
+    /// The places borrowed immutably, directly or through one of their fields.
+    pub immutable: BTreeSet<Place>,
+    /// The borrows, escapes and freezes of the function, in source order.
+    pub uses: Vec<ReferenceUse>,
+}
+
+fn places(places: &BTreeSet<Place>) -> String {
+    places
+        .iter()
+        .map(Place::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for FunctionBorrows {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}` borrows", self.function)?;
+        match (self.mutable.is_empty(), self.immutable.is_empty()) {
+            (true, true) => write!(f, " nothing"),
+            (false, true) => write!(f, " {} mutably", places(&self.mutable)),
+            (true, false) => write!(f, " {} immutably", places(&self.immutable)),
+            (false, false) => write!(
+                f,
+                " {} mutably and {} immutably",
+                places(&self.mutable),
+                places(&self.immutable)
+            ),
+        }
+    }
+}
+
+/// Returns the references of every function of the module with a body, in declaration order.
+pub fn module_borrows(module: &ModuleDefinition) -> Vec<FunctionBorrows> {
+    module
+        .functions
+        .iter()
+        .filter_map(|(name, function)| {
+            function_borrows(ReportedFunction::in_module(&module.name, name), function)
+        })
+        .collect()
+}
+
+/// Returns the references of the script's `main` function.
+pub fn script_borrows(script: &Script) -> Vec<FunctionBorrows> {
+    function_borrows(ReportedFunction::main(), &script.main)
+        .into_iter()
+        .collect()
+}
+
+fn function_borrows(reported: ReportedFunction, function: &Function) -> Option<FunctionBorrows> {
+    let (locals, code) = match &function.value.body {
+        FunctionBody::Move { locals, code } => (locals, code),
+        FunctionBody::Native => return None,
+    };
+    let mut collector = Collector {
+        references: function
+            .value
+            .signature
+            .formals
+            .iter()
+            .chain(locals.iter())
+            .filter_map(|(var, ty)| match ty {
+                Type::Reference(mutable, _) => Some((var.value.clone(), *mutable)),
+                _ => None,
+            })
+            .collect(),
+        uses: vec![],
+    };
+    collector.block(code);
+    let mut uses = collector.uses;
+    uses.sort_by_key(|reference_use| reference_use.loc.start());
+
+    let mut borrows = FunctionBorrows {
+        function: reported,
+        loc: function.span,
+        mutable: BTreeSet::new(),
+        immutable: BTreeSet::new(),
+        uses: vec![],
+    };
+    for reference_use in &uses {
+        if let ReferenceUseKind::Borrow {
+            mutable,
+            place: Some(place),
+            ..
+        } = &reference_use.kind
+        {
+            if *mutable {
+                borrows.mutable.insert(place.clone());
+            } else {
+                borrows.immutable.insert(place.clone());
+            }
+        }
+    }
+    borrows.uses = uses;
+    Some(borrows)
+}
+
+struct Collector {
+    /// The locals declared with a reference type, with whether it is mutable.
+    references: BTreeMap<Var_, bool>,
+    uses: Vec<ReferenceUse>,
+}
+
+impl Collector {
+    fn block(&mut self, block: &Block_) {
+        for statement in &block.stmts {
+            match statement {
+                Statement::CommandStatement(cmd) => self.cmd(&cmd.value),
+                Statement::IfElseStatement(if_else) => {
+                    self.exp(&if_else.cond);
+                    self.block(&if_else.if_block.value);
+                    if let Some(else_block) = &if_else.else_block {
+                        self.block(&else_block.value);
+                    }
+                }
+                Statement::WhileStatement(while_) => {
+                    self.exp(&while_.cond);
+                    self.block(&while_.block.value);
+                }
+                Statement::LoopStatement(loop_) => {
+                    for lvalue in &loop_.results {
+                        if let LValue_::Mutate(e) = &lvalue.value {
+                            self.exp(e);
+                        }
+                    }
+                    self.block(&loop_.block.value);
+                }
+                Statement::EmptyStatement => (),
+            }
+        }
+    }
+
+    fn cmd(&mut self, cmd: &Cmd_) {
+        match cmd {
+            Cmd_::Assign(lvalues, e) => {
+                for lvalue in lvalues {
+                    if let LValue_::Mutate(e) = &lvalue.value {
+                        self.exp(e);
+                    }
+                }
+                self.exp(e);
+            }
+            Cmd_::Unpack(_, _, _, e)
+            | Cmd_::Return(e)
+            | Cmd_::Exp(e)
+            | Cmd_::Abort(Some(e))
+            | Cmd_::Break(Some(e)) => self.exp(e),
+            Cmd_::Emit(handle, e) => {
+                self.exp(handle);
+                self.exp(e);
+            }
+            Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+        }
+    }
+
+    fn exp(&mut self, e: &Exp) {
+        match &e.value {
+            Exp_::BorrowLocal(mutable, var) => self.push(
+                e.span,
+                ReferenceUseKind::Borrow {
+                    mutable: *mutable,
+                    place: Some(Place::Local(var.value.clone())),
+                    field: None,
+                },
+            ),
+            Exp_::Borrow {
+                is_mutable,
+                exp,
+                field,
+            } => {
+                self.push(
+                    e.span,
+                    ReferenceUseKind::Borrow {
+                        mutable: *is_mutable,
+                        place: self.place(exp),
+                        field: Some(field.clone()),
+                    },
+                );
+                self.exp(exp);
+            }
+            Exp_::FunctionCall(call, args) => {
+                match &call.value {
+                    FunctionCall_::Builtin(Builtin::BorrowGlobal(mutable, name, _)) => self.push(
+                        e.span,
+                        ReferenceUseKind::Borrow {
+                            mutable: *mutable,
+                            place: Some(Place::Global(name.clone())),
+                            field: None,
+                        },
+                    ),
+                    FunctionCall_::Builtin(Builtin::Freeze) => self.push(
+                        e.span,
+                        ReferenceUseKind::Freeze {
+                            place: self.place(args),
+                        },
+                    ),
+                    FunctionCall_::ModuleFunctionCall { module, name, .. } => {
+                        let args: Vec<&Exp> = match &args.value {
+                            Exp_::ExprList(exps) => exps.iter().collect(),
+                            _ => vec![args],
+                        };
+                        for arg in args {
+                            if let Some(mutable) = self.reference(arg) {
+                                self.push(
+                                    arg.span,
+                                    ReferenceUseKind::Escape {
+                                        mutable,
+                                        place: self.place(arg),
+                                        callee: format!("{}.{}", module, name),
+                                    },
+                                );
+                            }
+                        }
+                    }
+                    FunctionCall_::Builtin(_) => (),
+                }
+                self.exp(args);
+            }
+            Exp_::Dereference(e) | Exp_::UnaryExp(_, e) => self.exp(e),
+            Exp_::BinopExp(lhs, _, rhs) => {
+                self.exp(lhs);
+                self.exp(rhs);
+            }
+            Exp_::Pack(_, _, fields) => {
+                for (_, e) in fields {
+                    self.exp(e);
+                }
+            }
+            Exp_::ExprList(exps) => {
+                for e in exps {
+                    self.exp(e);
+                }
+            }
+            Exp_::Value(_)
+            | Exp_::Move(_)
+            | Exp_::Copy(_)
+            | Exp_::Var(_)
+            | Exp_::Placeholder(_) => (),
+        }
+    }
+
+    fn push(&mut self, loc: Loc, kind: ReferenceUseKind) {
+        self.uses.push(ReferenceUse { loc, kind });
+    }
+
+    /// Returns whether the value of `e` is a mutable reference, or none if it is not known to be
+    /// a reference.
+    fn reference(&self, e: &Exp) -> Option<bool> {
+        match &e.value {
+            Exp_::BorrowLocal(mutable, _)
+            | Exp_::Borrow {
+                is_mutable: mutable,
+                ..
+            } => Some(*mutable),
+            Exp_::FunctionCall(call, _) => match &call.value {
+                FunctionCall_::Builtin(Builtin::BorrowGlobal(mutable, _, _)) => Some(*mutable),
+                FunctionCall_::Builtin(Builtin::Freeze) => Some(false),
+                _ => None,
+            },
+            Exp_::Move(var) | Exp_::Copy(var) | Exp_::Var(var) => {
+                self.references.get(&var.value).cloned()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the place the value or reference `e` is rooted in, if it is known.
+    fn place(&self, e: &Exp) -> Option<Place> {
+        match &e.value {
+            Exp_::Move(var) | Exp_::Copy(var) | Exp_::Var(var) => {
+                if self.references.contains_key(&var.value) {
+                    Some(Place::Reference(var.value.clone()))
+                } else {
+                    Some(Place::Local(var.value.clone()))
+                }
+            }
+            Exp_::BorrowLocal(_, var) => Some(Place::Local(var.value.clone())),
+            Exp_::Borrow { exp, .. } => self.place(exp),
+            // The single argument of a call.
+            Exp_::ExprList(exps) if exps.len() == 1 => self.place(&exps[0]),
+            Exp_::FunctionCall(call, args) => match &call.value {
+                FunctionCall_::Builtin(Builtin::BorrowGlobal(_, name, _)) => {
+                    Some(Place::Global(name.clone()))
+                }
+                FunctionCall_::Builtin(Builtin::Freeze) => self.place(args),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
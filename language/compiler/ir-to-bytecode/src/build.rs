@@ -0,0 +1,446 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Building a set of modules that import each other, such as the standard library.
+//!
+//! The modules are compiled in the order of their imports, whatever the order they are given in,
+//! so that every module is compiled and verified against the modules it imports. A module that
+//! fails does not stop the build: the errors of all modules are collected, and only the modules
+//! importing a failed one are left out.
+//!
+//! [`compile_directory`](fn.compile_directory.html) builds the `.mvir` files of a directory tree,
+//! [`compile_directory_with`](fn.compile_directory_with.html) does so reading them through a
+//! [`SourceResolver`](../resolver/trait.SourceResolver.html), and
+//! [`compile_modules`](fn.compile_modules.html) builds modules that are already parsed.
+
+use crate::{
+    cfg::{configure_module, CfgOptions},
+    compiler::compile_module,
+    errors::BuildError,
+    imports::check_module_imports,
+    natives::check_module_natives,
+    parser::{try_parse_module_with_options, ParserOptions},
+    resolver::{Entry, FileSystem, SourceResolver},
+};
+use anyhow::Result;
+use bytecode_source_map::{source_map::ModuleSourceMap, utils::verification_error_location};
+use bytecode_verifier::{verify_module_dependencies, VerifiedModule};
+use libra_types::{
+    account_address::AccountAddress, language_storage::ModuleId, vm_error::VMStatus,
+};
+use move_ir_types::ast::{Loc, ModuleDefinition, ModuleIdent, ModuleName, QualifiedModuleIdent};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+use vm::access::ModuleAccess;
+
+/// The extension of the sources of modules.
+pub const SOURCE_EXT: &str = "mvir";
+
+/// Options of a build.
+#[derive(Clone, Debug, Default)]
+pub struct BuildOptions {
+    /// The address the modules are published at.
+    pub address: AccountAddress,
+    /// The options the sources are parsed with.
+    pub parser_options: ParserOptions,
+    /// The flags and features the `cfg` attributes of the modules are resolved against.
+    pub cfg: CfgOptions,
+    /// Compiled modules the modules can import besides each other, e.g. the standard library.
+    pub deps: Vec<VerifiedModule>,
+}
+
+/// A compiled module of a build.
+#[derive(Clone, Debug)]
+pub struct BuiltModule {
+    /// The file of the module, when built from a directory.
+    pub path: Option<PathBuf>,
+    pub module: VerifiedModule,
+    pub source_map: ModuleSourceMap<Loc>,
+}
+
+/// The outcome of a build.
+#[derive(Clone, Debug, Default)]
+pub struct Build {
+    /// The modules that compiled, in dependency order: a module only imports the modules before
+    /// it. Modules that do not import each other are in the order they were given in.
+    pub modules: Vec<BuiltModule>,
+    /// The errors of all modules, in the order they were found.
+    pub errors: Vec<BuildError>,
+}
+
+impl Build {
+    /// Whether every module compiled.
+    pub fn is_success(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns the compiled modules and their source maps, in dependency order.
+    pub fn into_modules(self) -> (Vec<VerifiedModule>, Vec<ModuleSourceMap<Loc>>) {
+        self.modules
+            .into_iter()
+            .map(|built| (built.module, built.source_map))
+            .unzip()
+    }
+}
+
+/// Builds the modules of the `.mvir` files of `dir` and its subdirectories, which are given in
+/// path order. Files that do not parse are reported as errors of the build; only failing to read
+/// the directory is an `Err`.
+pub fn compile_directory(dir: &Path, options: &BuildOptions) -> Result<Build> {
+    compile_directory_with(&FileSystem, dir, options)
+}
+
+/// Builds the modules of the `.mvir` files of `dir` and its subdirectories, as
+/// [`compile_directory`](fn.compile_directory.html) does, reading them from `resolver`. The
+/// modules at the build address that the sources import, but that are neither sources nor
+/// dependencies, are fetched from `resolver` and built too. The imported modules that are still
+/// missing are then fetched compiled from `resolver`, and used as dependencies.
+pub fn compile_directory_with(
+    resolver: &dyn SourceResolver,
+    dir: &Path,
+    options: &BuildOptions,
+) -> Result<Build> {
+    let mut modules = vec![];
+    let mut errors = vec![];
+    for path in sources(resolver, dir)? {
+        let source = resolver.read_file(&path)?;
+        match try_parse_module_with_options(&source, options.parser_options) {
+            Ok(module) => modules.push((Some(path), module)),
+            Err(err) => errors.push(BuildError {
+                path: Some(path),
+                module: None,
+                loc: err.loc,
+                message: err.message,
+            }),
+        }
+    }
+
+    let mut known: BTreeSet<ModuleName> = modules
+        .iter()
+        .map(|(_, module)| module.name.clone())
+        .chain(
+            options
+                .deps
+                .iter()
+                .filter(|dep| *dep.self_id().address() == options.address)
+                .map(|dep| ModuleName::new(dep.self_id().name().to_owned())),
+        )
+        .collect();
+    let mut next = 0;
+    while next < modules.len() {
+        let missing: Vec<ModuleName> = local_imports(&modules[next].1, options.address)
+            .filter(|name| !known.contains(*name))
+            .cloned()
+            .collect();
+        next += 1;
+        for name in missing {
+            known.insert(name.clone());
+            let id = QualifiedModuleIdent {
+                name,
+                address: options.address,
+            };
+            let source = match resolver.fetch_module(&id)? {
+                Some(source) => source,
+                None => continue,
+            };
+            match try_parse_module_with_options(&source, options.parser_options) {
+                Ok(module) => modules.push((None, module)),
+                Err(err) => errors.push(BuildError {
+                    path: None,
+                    module: Some(id.name),
+                    loc: err.loc,
+                    message: err.message,
+                }),
+            }
+        }
+    }
+
+    let mut fetched: Vec<VerifiedModule> = vec![];
+    let mut requested = BTreeSet::new();
+    for (_, module) in &modules {
+        for import in &module.imports {
+            let id = match &import.ident {
+                ModuleIdent::Transaction(name) => {
+                    ModuleId::new(options.address, name.clone().into_inner())
+                }
+                ModuleIdent::Qualified(ident) => {
+                    ModuleId::new(ident.address, ident.name.clone().into_inner())
+                }
+            };
+            let name = ModuleName::new(id.name().to_owned());
+            if (*id.address() == options.address && known.contains(&name))
+                || options.deps.iter().any(|dep| dep.self_id() == id)
+                || !requested.insert(id.clone())
+            {
+                continue;
+            }
+            let compiled = match resolver.fetch_compiled_module(&id)? {
+                Some(compiled) => compiled,
+                None => continue,
+            };
+            match VerifiedModule::new(compiled) {
+                Ok(verified) => fetched.push(verified),
+                Err((_, statuses)) => errors.push(BuildError {
+                    path: None,
+                    message: format!(
+                        "Module {} from the store does not verify: {:?}",
+                        name, statuses
+                    ),
+                    module: Some(name),
+                    loc: None,
+                }),
+            }
+        }
+    }
+    Ok(build(modules, fetched, errors, options))
+}
+
+/// Builds `modules`, which are already parsed.
+pub fn compile_modules(modules: Vec<ModuleDefinition>, options: &BuildOptions) -> Build {
+    let modules = modules.into_iter().map(|module| (None, module)).collect();
+    build(modules, vec![], vec![], options)
+}
+
+// Builds `modules` against the dependencies of `options` and `fetched`.
+fn build(
+    mut modules: Vec<(Option<PathBuf>, ModuleDefinition)>,
+    fetched: Vec<VerifiedModule>,
+    mut errors: Vec<BuildError>,
+    options: &BuildOptions,
+) -> Build {
+    // The attributes are resolved first, as imports can be conditional.
+    let mut misconfigured = BTreeSet::new();
+    for (idx, (path, module)) in modules.iter_mut().enumerate() {
+        for err in configure_module(module, &options.cfg) {
+            errors.push(BuildError {
+                path: path.clone(),
+                module: Some(module.name.clone()),
+                loc: err.loc,
+                message: err.to_string(),
+            });
+            misconfigured.insert(idx);
+        }
+    }
+    let names: Vec<_> = modules
+        .iter()
+        .map(|(_, module)| module.name.clone())
+        .collect();
+    let mut modules: Vec<_> = modules.into_iter().map(Some).collect();
+    let error = |path: &Option<PathBuf>, idx: usize, message: String| BuildError {
+        path: path.clone(),
+        module: Some(names[idx].clone()),
+        loc: None,
+        message,
+    };
+
+    // The first module of a name wins, as only one of them can be published at the address.
+    let mut indices = BTreeMap::new();
+    for (idx, slot) in modules.iter_mut().enumerate() {
+        let name = &names[idx];
+        if indices.contains_key(name) {
+            let message = format!("Duplicate module {}", name);
+            errors.push(error(&slot.as_ref().unwrap().0, idx, message));
+            *slot = None;
+        } else {
+            indices.insert(name, idx);
+        }
+    }
+    let imports: Vec<BTreeSet<usize>> = modules
+        .iter()
+        .enumerate()
+        .map(|(idx, slot)| match slot {
+            Some((_, module)) => local_imports(module, options.address)
+                .filter_map(|name| indices.get(name).copied())
+                .filter(|import| *import != idx)
+                .collect(),
+            None => BTreeSet::new(),
+        })
+        .collect();
+
+    let (order, cyclic) = dependency_order(&modules, &imports);
+    for idx in cyclic {
+        let message = if reaches(&imports, idx, idx) {
+            format!(
+                "Module {} imports itself through a cycle of imports",
+                names[idx]
+            )
+        } else {
+            format!(
+                "Module {} imports modules in a cycle of imports",
+                names[idx]
+            )
+        };
+        errors.push(error(&modules[idx].as_ref().unwrap().0, idx, message));
+    }
+
+    let mut built: Vec<BuiltModule> = vec![];
+    let mut failed = vec![false; modules.len()];
+    for idx in order {
+        let (path, module) = modules[idx].take().unwrap();
+        if misconfigured.contains(&idx) {
+            failed[idx] = true;
+            continue;
+        }
+        if let Some(import) = imports[idx].iter().find(|import| failed[**import]) {
+            let message = format!(
+                "Module {} imports module {}, which does not compile",
+                module.name, names[*import],
+            );
+            errors.push(error(&path, idx, message));
+            failed[idx] = true;
+            continue;
+        }
+        let name = module.name.clone();
+        let deps = || {
+            options
+                .deps
+                .iter()
+                .chain(&fetched)
+                .chain(built.iter().map(|b| &b.module))
+        };
+        let import_errors = check_module_imports(&module, options.address, deps());
+        let native_errors = check_module_natives(&module, options.address);
+        if !import_errors.is_empty() || !native_errors.is_empty() {
+            let located = import_errors
+                .into_iter()
+                .map(|err| (err.loc, err.message))
+                .chain(native_errors.into_iter().map(|err| (err.loc, err.message)));
+            errors.extend(located.map(|(loc, message)| BuildError {
+                path: path.clone(),
+                module: Some(name.clone()),
+                loc: Some(loc),
+                message,
+            }));
+            failed[idx] = true;
+            continue;
+        }
+        let (compiled, source_map) = match compile_module(options.address, module, deps()) {
+            Ok(compiled) => compiled,
+            Err(err) => {
+                errors.push(BuildError {
+                    path,
+                    module: Some(name),
+                    loc: None,
+                    message: format!("{:#}", err),
+                });
+                failed[idx] = true;
+                continue;
+            }
+        };
+        let statuses = match VerifiedModule::new(compiled) {
+            Ok(verified) => {
+                let statuses = verify_module_dependencies(&verified, deps());
+                if statuses.is_empty() {
+                    built.push(BuiltModule {
+                        path,
+                        module: verified,
+                        source_map,
+                    });
+                    continue;
+                }
+                statuses
+            }
+            Err((_, statuses)) => statuses,
+        };
+        errors.extend(
+            verification_errors(&source_map, &statuses).map(|(loc, message)| BuildError {
+                path: path.clone(),
+                module: Some(name.clone()),
+                loc,
+                message,
+            }),
+        );
+        failed[idx] = true;
+    }
+
+    Build {
+        modules: built,
+        errors,
+    }
+}
+
+// Returns the names of the modules `module` imports from `address`, which may be modules of the
+// build. `Transaction.M` imports a module published at the address of the importing module.
+fn local_imports(
+    module: &ModuleDefinition,
+    address: AccountAddress,
+) -> impl Iterator<Item = &ModuleName> {
+    module
+        .imports
+        .iter()
+        .filter_map(move |import| match &import.ident {
+            ModuleIdent::Transaction(name) => Some(name),
+            ModuleIdent::Qualified(ident) if ident.address == address => Some(&ident.name),
+            ModuleIdent::Qualified(_) => None,
+        })
+}
+
+// Orders the modules so that every module comes after the modules it imports, keeping modules
+// that do not import each other in their original order. Also returns the modules that cannot be
+// ordered, as they import themselves or such modules.
+fn dependency_order<T>(
+    modules: &[Option<T>],
+    imports: &[BTreeSet<usize>],
+) -> (Vec<usize>, Vec<usize>) {
+    let mut remaining: Vec<usize> = (0..modules.len())
+        .filter(|idx| modules[*idx].is_some())
+        .collect();
+    let mut ordered = vec![false; modules.len()];
+    let mut order = vec![];
+    while let Some(pos) = remaining
+        .iter()
+        .position(|idx| imports[*idx].iter().all(|import| ordered[*import]))
+    {
+        let idx = remaining.remove(pos);
+        ordered[idx] = true;
+        order.push(idx);
+    }
+    (order, remaining)
+}
+
+// Whether `to` can be reached from `from` following imports.
+fn reaches(imports: &[BTreeSet<usize>], from: usize, to: usize) -> bool {
+    let mut visited = BTreeSet::new();
+    let mut stack: Vec<usize> = imports[from].iter().copied().collect();
+    while let Some(idx) = stack.pop() {
+        if idx == to {
+            return true;
+        }
+        if visited.insert(idx) {
+            stack.extend(imports[idx].iter().copied());
+        }
+    }
+    false
+}
+
+// Locates verifier errors in the source when possible.
+fn verification_errors<'a>(
+    source_map: &'a ModuleSourceMap<Loc>,
+    statuses: &'a [VMStatus],
+) -> impl Iterator<Item = (Option<Loc>, String)> + 'a {
+    statuses.iter().map(move |status| {
+        (
+            verification_error_location(source_map, status),
+            status.to_string(),
+        )
+    })
+}
+
+// Returns the sources of modules of `dir` and its subdirectories, sorted.
+fn sources(resolver: &dyn SourceResolver, dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    for entry in resolver.list_directory(dir)? {
+        match entry {
+            Entry::Directory(path) => paths.extend(sources(resolver, &path)?),
+            Entry::File(path) => {
+                if path.extension().map_or(false, |ext| ext == SOURCE_EXT) {
+                    paths.push(path);
+                }
+            }
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
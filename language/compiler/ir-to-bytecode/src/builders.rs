@@ -0,0 +1,222 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Generation of Rust transaction builders from the ABIs of transaction scripts.
+//!
+//! For each script, the generated code has a function `encode_<name>_script` taking the arguments
+//! of `main` with their Rust types and returning the `Script` payload that runs it, so that
+//! clients do not have to put together `TransactionArgument` lists by hand. The bytecode of the
+//! scripts is embedded in the generated code.
+
+use crate::{
+    abi::{ScriptAbi, TypeAbi},
+    errors::BuilderError,
+};
+use std::collections::BTreeSet;
+
+/// Rust keywords, which must be written as raw identifiers to name arguments.
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// Identifiers that cannot be raw identifiers.
+const RESERVED: &[&str] = &["crate", "self", "Self", "super"];
+
+/// How an argument is passed: its Rust type, the `TransactionArgument` variant holding it, and
+/// the import the type needs, if any.
+struct Encoding {
+    rust_type: &'static str,
+    variant: &'static str,
+    import: Option<&'static str>,
+    wrap: fn(&str) -> String,
+}
+
+/// Returns the Rust source of the builders of `scripts`, given with their compiled bytecode. Fails
+/// if a script cannot be called by a transaction, or if a name cannot be used in Rust.
+pub fn generate_rust_builders(scripts: &[(ScriptAbi, Vec<u8>)]) -> Result<String, BuilderError> {
+    let mut imports = BTreeSet::new();
+    imports.insert("transaction::{Script, TransactionArgument}");
+    let mut functions = vec![];
+    let mut constants = vec![];
+    for (abi, code) in scripts {
+        let (function, function_imports) = builder(abi)?;
+        functions.push(function);
+        imports.extend(function_imports);
+        constants.push(code_constant(abi, code));
+    }
+
+    let mut out = String::from(
+        "// Transaction builders generated from the ABIs of transaction scripts. Do not edit.\n\n",
+    );
+    out.push_str("use libra_types::{\n");
+    for import in &imports {
+        out.push_str(&format!("    {},\n", import));
+    }
+    out.push_str("};\n");
+    for item in functions.iter().chain(&constants) {
+        out.push('\n');
+        out.push_str(item);
+    }
+    Ok(out)
+}
+
+fn builder(abi: &ScriptAbi) -> Result<(String, Vec<&'static str>), BuilderError> {
+    let script = &abi.name;
+    if !abi.type_arguments.is_empty() {
+        return Err(BuilderError::TypeArguments {
+            script: script.clone(),
+        });
+    }
+    if !is_identifier(script) {
+        return Err(invalid_name(script, script));
+    }
+
+    let mut doc = vec![format!("Encodes a call to the `{}` script.", script)];
+    if !abi.doc.is_empty() {
+        doc.push(String::new());
+        doc.extend(abi.doc.lines().map(str::to_string));
+    }
+    let documented: Vec<_> = abi.arguments.iter().filter(|a| !a.doc.is_empty()).collect();
+    if !documented.is_empty() {
+        doc.push(String::new());
+        for argument in documented {
+            doc.push(format!(
+                "* `{}`: {}",
+                argument.name,
+                argument.doc.replace('\n', " ")
+            ));
+        }
+    }
+
+    let mut imports = vec![];
+    let mut params = vec![];
+    let mut values = vec![];
+    for argument in &abi.arguments {
+        if !is_identifier(&argument.name) || RESERVED.contains(&argument.name.as_str()) {
+            return Err(invalid_name(script, &argument.name));
+        }
+        let encoding =
+            encoding(&argument.type_).ok_or_else(|| BuilderError::UnsupportedArgument {
+                script: script.clone(),
+                argument: argument.name.clone(),
+                type_: argument.type_.to_string(),
+            })?;
+        let name = rust_identifier(&argument.name);
+        imports.extend(encoding.import);
+        params.push(format!("{}: {}", name, encoding.rust_type));
+        values.push(format!(
+            "TransactionArgument::{}({})",
+            encoding.variant,
+            (encoding.wrap)(&name)
+        ));
+    }
+
+    let mut out = String::new();
+    for line in doc {
+        if line.is_empty() {
+            out.push_str("///\n");
+        } else {
+            out.push_str(&format!("/// {}\n", line));
+        }
+    }
+    out.push_str(&format!(
+        "pub fn encode_{}_script({}) -> Script {{\n",
+        script,
+        params.join(", ")
+    ));
+    out.push_str("    Script::new(\n");
+    out.push_str(&format!("        {}.to_vec(),\n", code_name(abi)));
+    if values.is_empty() {
+        out.push_str("        vec![],\n");
+    } else {
+        out.push_str("        vec![\n");
+        for value in values {
+            out.push_str(&format!("            {},\n", value));
+        }
+        out.push_str("        ],\n");
+    }
+    out.push_str("    )\n}\n");
+    Ok((out, imports))
+}
+
+/// Returns how an argument of type `ty` is passed, or `None` if transactions cannot pass it.
+fn encoding(ty: &TypeAbi) -> Option<Encoding> {
+    fn same(name: &str) -> String {
+        name.to_string()
+    }
+    fn byte_array(name: &str) -> String {
+        format!("ByteArray::new({})", name)
+    }
+    Some(match ty {
+        TypeAbi::Address => Encoding {
+            rust_type: "AccountAddress",
+            variant: "Address",
+            import: Some("account_address::AccountAddress"),
+            wrap: same,
+        },
+        TypeAbi::U64 => Encoding {
+            rust_type: "u64",
+            variant: "U64",
+            import: None,
+            wrap: same,
+        },
+        TypeAbi::Bool => Encoding {
+            rust_type: "bool",
+            variant: "Bool",
+            import: None,
+            wrap: same,
+        },
+        TypeAbi::ByteArray => Encoding {
+            rust_type: "Vec<u8>",
+            variant: "ByteArray",
+            import: Some("byte_array::ByteArray"),
+            wrap: byte_array,
+        },
+        TypeAbi::U8
+        | TypeAbi::U128
+        | TypeAbi::Struct { .. }
+        | TypeAbi::Reference { .. }
+        | TypeAbi::TypeParameter(_)
+        | TypeAbi::Tuple(_) => return None,
+    })
+}
+
+fn code_constant(abi: &ScriptAbi, code: &[u8]) -> String {
+    let mut out = format!("const {}: &[u8] = &[\n", code_name(abi));
+    for chunk in code.chunks(16) {
+        let bytes: Vec<String> = chunk.iter().map(|byte| byte.to_string()).collect();
+        out.push_str(&format!("    {},\n", bytes.join(", ")));
+    }
+    out.push_str("];\n");
+    out
+}
+
+fn code_name(abi: &ScriptAbi) -> String {
+    format!("{}_CODE", abi.name.to_uppercase())
+}
+
+/// Returns whether `name` is an ASCII Rust identifier, not counting keywords.
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name != "_"
+}
+
+fn invalid_name(script: &str, name: &str) -> BuilderError {
+    BuilderError::InvalidName {
+        script: script.to_string(),
+        name: name.to_string(),
+    }
+}
+
+fn rust_identifier(name: &str) -> String {
+    if RUST_KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.to_string()
+    }
+}
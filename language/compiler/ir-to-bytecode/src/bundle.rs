@@ -0,0 +1,229 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Bundles of compilation artifacts.
+//!
+//! A bundle, written to a `.mvb` file, packages what the compiler knows about a script or module:
+//! its bytecode, its source map, the ABI of a script, the specifications written in the source
+//! and how it was built. Tools read a single bundle instead of the `.mv`, `.mvsm` and ABI files
+//! next to each other, which can get out of sync.
+//!
+//! A bundle is a JSON object starting with a `format` and a `version`, so that readers can tell a
+//! bundle from other JSON files and reject versions they do not understand.
+
+use crate::{
+    abi::{extract_abi, ScriptAbi},
+    metadata::{read_build_info, BuildInfo},
+    parser::strip_comments,
+};
+use anyhow::{bail, Context, Result};
+use bytecode_source_map::source_map::ModuleSourceMap;
+use move_ir_types::ast::{Function, Loc, ModuleDefinition, Script};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+use vm::file_format::{CompiledModule, CompiledScript};
+
+/// The extension of bundle files.
+pub const BUNDLE_EXT: &str = "mvb";
+/// The value of the `format` field of bundles.
+pub const BUNDLE_FORMAT: &str = "move-ir-bundle";
+/// The version of the bundles this crate writes, and the only one it reads.
+pub const BUNDLE_VERSION: u64 = 1;
+
+/// Whether a bundle, or an entry of a manifest, holds a script or a module.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitKind {
+    Script,
+    Module,
+}
+
+/// The specifications of a script or module, as written in the source, each on a single line.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Specs {
+    /// The specification clauses of the functions that have some, by function name.
+    pub functions: BTreeMap<String, Vec<String>>,
+    /// The invariants of the structs that have some, by struct name.
+    pub invariants: BTreeMap<String, Vec<String>>,
+}
+
+/// A compiled script or module with its artifacts.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Bundle {
+    /// Always `BUNDLE_FORMAT`.
+    pub format: String,
+    /// The version of the format.
+    pub version: u64,
+    pub kind: UnitKind,
+    /// The serialized script or module.
+    pub bytecode: Vec<u8>,
+    pub source_map: ModuleSourceMap<Loc>,
+    /// The ABI of a script, `None` for a module.
+    pub abi: Option<ScriptAbi>,
+    pub specs: Specs,
+    /// How the script or module was built, missing from bundles written before it was recorded.
+    #[serde(default)]
+    pub build: Option<BuildInfo>,
+}
+
+impl Bundle {
+    /// Bundles a script compiled from `script`, parsed from `source`. `name` names its ABI.
+    pub fn for_script(
+        name: &str,
+        source: &str,
+        script: &Script,
+        compiled: &CompiledScript,
+        source_map: ModuleSourceMap<Loc>,
+    ) -> Result<Self> {
+        let mut bytecode = vec![];
+        compiled.serialize(&mut bytecode)?;
+        let stripped = strip_comments(source);
+        let mut specs = Specs::default();
+        add_function_specs(&mut specs, &stripped, "main", &script.main);
+        Ok(Self::new(
+            UnitKind::Script,
+            bytecode,
+            source_map,
+            Some(extract_abi(name, source, script)),
+            specs,
+        ))
+    }
+
+    /// Bundles a module compiled from `module`, parsed from `source`. The build information the
+    /// compiled module records, if any, is the one of the bundle.
+    pub fn for_module(
+        source: &str,
+        module: &ModuleDefinition,
+        compiled: &CompiledModule,
+        source_map: ModuleSourceMap<Loc>,
+    ) -> Result<Self> {
+        let mut bytecode = vec![];
+        compiled.serialize(&mut bytecode)?;
+        let stripped = strip_comments(source);
+        let mut specs = Specs::default();
+        for (name, function) in &module.functions {
+            add_function_specs(&mut specs, &stripped, name.as_inner().as_str(), function);
+        }
+        for struct_def in &module.structs {
+            let invariants: Vec<_> = struct_def
+                .value
+                .invariants
+                .iter()
+                .map(|invariant| spec_text(&stripped, invariant.span))
+                .collect();
+            if !invariants.is_empty() {
+                specs
+                    .invariants
+                    .insert(struct_def.value.name.to_string(), invariants);
+            }
+        }
+        let mut bundle = Self::new(UnitKind::Module, bytecode, source_map, None, specs);
+        bundle.build = read_build_info(compiled)?;
+        Ok(bundle)
+    }
+
+    fn new(
+        kind: UnitKind,
+        bytecode: Vec<u8>,
+        source_map: ModuleSourceMap<Loc>,
+        abi: Option<ScriptAbi>,
+        specs: Specs,
+    ) -> Self {
+        Bundle {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            kind,
+            bytecode,
+            source_map,
+            abi,
+            specs,
+            build: None,
+        }
+    }
+
+    /// Records how the bundled script or module was built.
+    pub fn with_build_info(mut self, build: BuildInfo) -> Self {
+        self.build = Some(build);
+        self
+    }
+
+    /// Deserializes the script of the bundle. Fails if it holds a module.
+    pub fn script(&self) -> Result<CompiledScript> {
+        if self.kind != UnitKind::Script {
+            bail!("The bundle holds a module, not a script");
+        }
+        CompiledScript::deserialize(&self.bytecode)
+            .map_err(|status| anyhow::format_err!("Invalid bytecode in bundle: {:?}", status))
+    }
+
+    /// Deserializes the module of the bundle. Fails if it holds a script.
+    pub fn module(&self) -> Result<CompiledModule> {
+        if self.kind != UnitKind::Module {
+            bail!("The bundle holds a script, not a module");
+        }
+        CompiledModule::deserialize(&self.bytecode)
+            .map_err(|status| anyhow::format_err!("Invalid bytecode in bundle: {:?}", status))
+    }
+
+    /// Serializes the bundle.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Deserializes a bundle, checking its format and version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Header {
+            format: String,
+            version: u64,
+        }
+
+        let header: Header = serde_json::from_slice(bytes).context("Not a bundle")?;
+        if header.format != BUNDLE_FORMAT {
+            bail!("Not a bundle: unknown format {}", header.format);
+        }
+        if header.version != BUNDLE_VERSION {
+            bail!(
+                "Unsupported bundle version {}, expected {}",
+                header.version,
+                BUNDLE_VERSION
+            );
+        }
+        Ok(serde_json::from_slice(bytes)?)
+    }
+
+    /// Writes the bundle to the file at `path`.
+    pub fn write_to_file(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_bytes()?)
+            .with_context(|| format!("Unable to write bundle {}", path.display()))
+    }
+
+    /// Reads the bundle in the file at `path`.
+    pub fn read_from_file(path: &Path) -> Result<Self> {
+        let bytes =
+            fs::read(path).with_context(|| format!("Unable to read bundle {}", path.display()))?;
+        Self::from_bytes(&bytes).with_context(|| format!("Invalid bundle {}", path.display()))
+    }
+}
+
+fn add_function_specs(specs: &mut Specs, stripped: &str, name: &str, function: &Function) {
+    let conditions: Vec<_> = function
+        .value
+        .specifications
+        .iter()
+        .map(|condition| spec_text(stripped, condition.span))
+        .collect();
+    if !conditions.is_empty() {
+        specs.functions.insert(name.to_string(), conditions);
+    }
+}
+
+// Returns the source of a specification clause, on a single line.
+fn spec_text(stripped: &str, loc: Loc) -> String {
+    stripped
+        .get(loc.start().to_usize()..loc.end().to_usize())
+        .unwrap_or("")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
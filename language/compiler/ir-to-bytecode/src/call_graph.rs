@@ -0,0 +1,329 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Call graphs across modules.
+//!
+//! The call graph of a set of parsed modules has their functions as nodes, and an edge from a
+//! function to every function and builtin it calls. Calls are resolved through the imports of the
+//! calling module, so that the functions of other modules of the set are linked to the calls to
+//! them, and the functions of modules outside the set, such as the standard library, are
+//! external nodes. Modules are identified by name, as the addresses they are published at are not
+//! part of their source.
+//!
+//! The graph tells which functions are reachable from an entry point, and which functions are
+//! recursive, directly or through other modules. It is exported as JSON, or in the DOT language
+//! of Graphviz to be drawn.
+
+use anyhow::Result;
+use move_ir_types::ast::{
+    Block_, Builtin, Cmd_, Exp, Exp_, FunctionBody, FunctionCall_, LValue_, ModuleDefinition,
+    ModuleIdent, ModuleName, Statement,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// A function, identified by the name of its module and its own.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct FunctionId {
+    pub module: String,
+    pub name: String,
+}
+
+impl FunctionId {
+    pub fn new(module: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            module: module.into(),
+            name: name.into(),
+        }
+    }
+}
+
+impl fmt::Display for FunctionId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.module, self.name)
+    }
+}
+
+/// What a function calls.
+#[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Callee {
+    Function(FunctionId),
+    /// A builtin, written as in the IR without its type arguments. The resource a builtin
+    /// accessing global storage is called on is qualified with its module, e.g.
+    /// `borrow_global<M.T>`.
+    Builtin(String),
+}
+
+impl fmt::Display for Callee {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Callee::Function(id) => id.fmt(f),
+            Callee::Builtin(name) => name.fmt(f),
+        }
+    }
+}
+
+/// An edge of the call graph.
+#[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Call {
+    pub caller: FunctionId,
+    pub callee: Callee,
+}
+
+/// The call graph of a set of modules.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CallGraph {
+    /// The functions declared by the modules, native or not.
+    pub functions: BTreeSet<FunctionId>,
+    /// The calls of the functions, each listed once however many times it is made.
+    pub calls: BTreeSet<Call>,
+}
+
+impl CallGraph {
+    /// Builds the call graph of the modules.
+    pub fn new(modules: &[ModuleDefinition]) -> Self {
+        let mut graph = Self::default();
+        for module in modules {
+            let module_name = module.name.to_string();
+            let mut aliases = BTreeMap::new();
+            aliases.insert(ModuleName::module_self(), module_name.clone());
+            for import in &module.imports {
+                let name = match &import.ident {
+                    ModuleIdent::Qualified(ident) => ident.name.to_string(),
+                    ModuleIdent::Transaction(name) => name.to_string(),
+                };
+                aliases.insert(import.alias.clone(), name);
+            }
+            for (name, function) in &module.functions {
+                let mut collector = Collector {
+                    module: &module_name,
+                    aliases: &aliases,
+                    callees: BTreeSet::new(),
+                };
+                if let FunctionBody::Move { code, .. } = &function.value.body {
+                    collector.block(code);
+                }
+                let caller = FunctionId::new(module_name.as_str(), name.to_string());
+                graph.functions.insert(caller.clone());
+                graph
+                    .calls
+                    .extend(collector.callees.into_iter().map(|callee| Call {
+                        caller: caller.clone(),
+                        callee,
+                    }));
+            }
+        }
+        graph
+    }
+
+    /// Returns whether the function is not declared by the modules of the graph.
+    pub fn is_external(&self, function: &FunctionId) -> bool {
+        !self.functions.contains(function)
+    }
+
+    /// Returns the functions called by `function`.
+    pub fn callees<'a>(&'a self, function: &'a FunctionId) -> impl Iterator<Item = &'a FunctionId> {
+        self.calls
+            .iter()
+            .filter(move |call| call.caller == *function)
+            .filter_map(|call| match &call.callee {
+                Callee::Function(callee) => Some(callee),
+                Callee::Builtin(_) => None,
+            })
+    }
+
+    /// Returns the functions reachable through one or more calls from `function`, which
+    /// includes `function` itself only if it is recursive.
+    pub fn reachable_from(&self, function: &FunctionId) -> BTreeSet<FunctionId> {
+        let mut reachable = BTreeSet::new();
+        let mut pending: Vec<&FunctionId> = self.callees(function).collect();
+        while let Some(callee) = pending.pop() {
+            if reachable.insert(callee.clone()) {
+                pending.extend(self.callees(callee));
+            }
+        }
+        reachable
+    }
+
+    /// Returns the groups of functions calling each other recursively, in order. A function
+    /// calling itself directly is a group of its own.
+    pub fn recursive_groups(&self) -> Vec<BTreeSet<FunctionId>> {
+        let reachable: BTreeMap<&FunctionId, BTreeSet<FunctionId>> = self
+            .functions
+            .iter()
+            .map(|function| (function, self.reachable_from(function)))
+            .collect();
+        let mut groups = BTreeSet::new();
+        for (function, reached) in &reachable {
+            if reached.contains(*function) {
+                let group: BTreeSet<FunctionId> = reached
+                    .iter()
+                    .filter(|other| {
+                        reachable
+                            .get(other)
+                            .map_or(false, |back| back.contains(*function))
+                    })
+                    .cloned()
+                    .collect();
+                groups.insert(group);
+            }
+        }
+        groups.into_iter().collect()
+    }
+
+    /// Prints the graph in the DOT language. External functions are dashed and builtins boxed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph calls {\n");
+        let mut external = BTreeSet::new();
+        let mut builtins = BTreeSet::new();
+        for call in &self.calls {
+            match &call.callee {
+                Callee::Function(callee) if self.is_external(callee) => {
+                    external.insert(callee);
+                }
+                Callee::Function(_) => (),
+                Callee::Builtin(name) => {
+                    builtins.insert(name);
+                }
+            }
+        }
+        for function in &self.functions {
+            dot.push_str(&format!("    \"{}\";\n", function));
+        }
+        for function in external {
+            dot.push_str(&format!("    \"{}\" [style=dashed];\n", function));
+        }
+        for builtin in builtins {
+            dot.push_str(&format!("    \"{}\" [shape=box];\n", builtin));
+        }
+        for call in &self.calls {
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\";\n",
+                call.caller, call.callee
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+}
+
+struct Collector<'a> {
+    module: &'a str,
+    aliases: &'a BTreeMap<ModuleName, String>,
+    callees: BTreeSet<Callee>,
+}
+
+impl<'a> Collector<'a> {
+    fn block(&mut self, block: &Block_) {
+        for statement in &block.stmts {
+            match statement {
+                Statement::CommandStatement(cmd) => self.cmd(&cmd.value),
+                Statement::IfElseStatement(if_else) => {
+                    self.exp(&if_else.cond);
+                    self.block(&if_else.if_block.value);
+                    if let Some(else_block) = &if_else.else_block {
+                        self.block(&else_block.value);
+                    }
+                }
+                Statement::WhileStatement(while_) => {
+                    self.exp(&while_.cond);
+                    self.block(&while_.block.value);
+                }
+                Statement::LoopStatement(loop_) => {
+                    for lvalue in &loop_.results {
+                        if let LValue_::Mutate(e) = &lvalue.value {
+                            self.exp(e);
+                        }
+                    }
+                    self.block(&loop_.block.value);
+                }
+                Statement::EmptyStatement => (),
+            }
+        }
+    }
+
+    fn cmd(&mut self, cmd: &Cmd_) {
+        match cmd {
+            Cmd_::Assign(lvalues, e) => {
+                for lvalue in lvalues {
+                    if let LValue_::Mutate(e) = &lvalue.value {
+                        self.exp(e);
+                    }
+                }
+                self.exp(e);
+            }
+            Cmd_::Unpack(_, _, _, e)
+            | Cmd_::Return(e)
+            | Cmd_::Exp(e)
+            | Cmd_::Abort(Some(e))
+            | Cmd_::Break(Some(e)) => self.exp(e),
+            Cmd_::Emit(handle, e) => {
+                self.exp(handle);
+                self.exp(e);
+            }
+            Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+        }
+    }
+
+    fn exp(&mut self, e: &Exp) {
+        match &e.value {
+            Exp_::Dereference(e) | Exp_::UnaryExp(_, e) | Exp_::Borrow { exp: e, .. } => {
+                self.exp(e)
+            }
+            Exp_::FunctionCall(call, args) => {
+                let callee = match &call.value {
+                    FunctionCall_::Builtin(builtin) => Callee::Builtin(self.builtin(builtin)),
+                    FunctionCall_::ModuleFunctionCall { module, name, .. } => {
+                        let module = self
+                            .aliases
+                            .get(module)
+                            .cloned()
+                            .unwrap_or_else(|| module.to_string());
+                        Callee::Function(FunctionId::new(module, name.to_string()))
+                    }
+                };
+                self.callees.insert(callee);
+                self.exp(args);
+            }
+            Exp_::BinopExp(lhs, _, rhs) => {
+                self.exp(lhs);
+                self.exp(rhs);
+            }
+            Exp_::Pack(_, _, fields) => {
+                for (_, e) in fields {
+                    self.exp(e);
+                }
+            }
+            Exp_::ExprList(exps) => {
+                for e in exps {
+                    self.exp(e);
+                }
+            }
+            Exp_::Value(_)
+            | Exp_::Move(_)
+            | Exp_::Copy(_)
+            | Exp_::Var(_)
+            | Exp_::BorrowLocal(_, _)
+            | Exp_::Placeholder(_) => (),
+        }
+    }
+
+    fn builtin(&self, builtin: &Builtin) -> String {
+        let (name, resource) = match builtin {
+            Builtin::Exists(resource, _) => ("exists", resource),
+            Builtin::BorrowGlobal(false, resource, _) => ("borrow_global", resource),
+            Builtin::BorrowGlobal(true, resource, _) => ("borrow_global_mut", resource),
+            Builtin::MoveFrom(resource, _) => ("move_from", resource),
+            Builtin::MoveToSender(resource, _) => ("move_to_sender", resource),
+            _ => return builtin.to_string(),
+        };
+        format!("{}<{}.{}>", name, self.module, resource)
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Conditional compilation.
+//!
+//! Imports, structs and functions can be given a `cfg` attribute, so that they are only compiled
+//! when its predicate holds, e.g. `#[cfg(test)]` for the helpers of tests or
+//! `#[cfg(feature = "testnet")]` for the variant of a function used on a network. The predicates
+//! are resolved against the flags set and the features enabled in
+//! [`CfgOptions`](struct.CfgOptions.html): the declarations whose predicate does not hold are
+//! removed from the AST, and the predicates of the others are cleared.
+//!
+//! Declarations with different predicates may have the same name, such as two variants of a
+//! function, so their names are only checked to be distinct once resolved. The compiler resolves
+//! the predicates left in the scripts and modules it is given against no flags and features, so
+//! that conditional code is never compiled by default.
+
+use crate::errors::CfgError;
+use move_ir_types::ast::{CfgPredicate, Loc, ModuleDefinition, Program, Script};
+use std::{
+    collections::{BTreeSet, HashSet},
+    mem,
+};
+
+/// The flags set and the features enabled, which `cfg` predicates are resolved against.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CfgOptions {
+    /// The flags set, e.g. `test`.
+    pub flags: BTreeSet<String>,
+    /// The features enabled, as named in `feature = "name"` predicates.
+    pub features: BTreeSet<String>,
+}
+
+impl CfgOptions {
+    /// Returns whether the predicate holds.
+    pub fn holds(&self, predicate: &CfgPredicate) -> bool {
+        match predicate {
+            CfgPredicate::Flag(name) => self.flags.contains(name),
+            CfgPredicate::Feature(name) => self.features.contains(name),
+            CfgPredicate::Not(predicate) => !self.holds(predicate),
+            CfgPredicate::All(predicates) => predicates.iter().all(|p| self.holds(p)),
+            CfgPredicate::Any(predicates) => predicates.iter().any(|p| self.holds(p)),
+        }
+    }
+
+    // Clears the predicate of a declaration, returning whether the declaration is kept.
+    fn keeps(&self, cfg: &mut Option<CfgPredicate>) -> bool {
+        match cfg.take() {
+            Some(predicate) => self.holds(&predicate),
+            None => true,
+        }
+    }
+
+    fn retain<T>(&self, items: &mut Vec<T>, cfg: fn(&mut T) -> &mut Option<CfgPredicate>) {
+        let all = mem::replace(items, vec![]);
+        *items = all
+            .into_iter()
+            .filter_map(|mut item| {
+                if self.keeps(cfg(&mut item)) {
+                    Some(item)
+                } else {
+                    None
+                }
+            })
+            .collect();
+    }
+}
+
+/// Resolves the `cfg` attributes of the modules and of the script of the program. Returns the
+/// declarations kept with the name of another.
+pub fn configure_program(program: &mut Program, options: &CfgOptions) -> Vec<CfgError> {
+    let mut errors = vec![];
+    for module in &mut program.modules {
+        errors.extend(configure_module(module, options));
+    }
+    errors.extend(configure_script(&mut program.script, options));
+    errors
+}
+
+/// Resolves the `cfg` attributes of the imports, structs and functions of the module. Returns
+/// the declarations kept with the name of another.
+pub fn configure_module(module: &mut ModuleDefinition, options: &CfgOptions) -> Vec<CfgError> {
+    options.retain(&mut module.imports, |import| &mut import.cfg);
+    options.retain(&mut module.structs, |s| &mut s.value.cfg);
+    options.retain(&mut module.functions, |(_, f)| &mut f.value.cfg);
+
+    let mut errors = vec![];
+    errors.extend(duplicates(
+        "import alias",
+        module
+            .imports
+            .iter()
+            .map(|import| (import.alias.to_string(), None)),
+    ));
+    errors.extend(duplicates(
+        "struct",
+        module
+            .structs
+            .iter()
+            .map(|s| (s.value.name.to_string(), Some(s.span))),
+    ));
+    errors.extend(duplicates(
+        "function",
+        module
+            .functions
+            .iter()
+            .map(|(name, f)| (name.to_string(), Some(f.span))),
+    ));
+    errors
+}
+
+/// Resolves the `cfg` attributes of the imports of the script. Returns the imports kept with the
+/// alias of another.
+pub fn configure_script(script: &mut Script, options: &CfgOptions) -> Vec<CfgError> {
+    options.retain(&mut script.imports, |import| &mut import.cfg);
+    duplicates(
+        "import alias",
+        script
+            .imports
+            .iter()
+            .map(|import| (import.alias.to_string(), None)),
+    )
+}
+
+fn duplicates(
+    kind: &'static str,
+    declarations: impl IntoIterator<Item = (String, Option<Loc>)>,
+) -> Vec<CfgError> {
+    let mut seen = HashSet::new();
+    declarations
+        .into_iter()
+        .filter_map(|(name, loc)| {
+            if seen.insert(name.clone()) {
+                None
+            } else {
+                Some(CfgError { loc, kind, name })
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,302 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Coalescing of local slots.
+//!
+//! Every local of a function takes a slot of its frame, even when it only lives for a few
+//! statements, as do the temporaries introduced by lowering tuples or by going through SSA form.
+//! This pass lets locals whose lifetimes do not overlap share a slot, so that large generated
+//! scripts stay under the limit on the number of locals of a function.
+//!
+//! A backward liveness analysis finds the locals that interfere: those live where the other is
+//! assigned. Locals are then visited in declaration order, and each one is renamed to a local or
+//! parameter of the same type it does not interfere with, declared before it, preferring one it
+//! is moved or copied from or to. Assignments of a local to itself that this renaming produces are
+//! removed. Locals that are borrowed anywhere in the function keep their slot, since they may be
+//! read through the reference, and so do locals that may be read before being assigned, which the
+//! verifier rejects.
+
+use crate::{
+    dataflow::{backward, BackwardTransfer, Live},
+    locals::{cmd_assigned, cmd_exps, exp_vars, lvalues_assigned, rename_block, LocalUse},
+    metrics::ReportedFunction,
+};
+use move_ir_types::ast::{
+    Block_, Cmd, Cmd_, Exp, Exp_, Function, FunctionBody, LValue, LValue_, Loc, ModuleDefinition,
+    Program, Script, Statement, Type, Var_,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// The locals of a function that share a slot with another one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CoalescedFunction {
+    pub function: ReportedFunction,
+    pub loc: Loc,
+    /// The number of slots of the function, parameters included, before coalescing.
+    pub slots_before: usize,
+    /// The number of slots of the function, parameters included, after coalescing.
+    pub slots_after: usize,
+    /// Each local removed, with the local or parameter whose slot it now uses, in declaration
+    /// order.
+    pub merged: Vec<(Var_, Var_)>,
+}
+
+impl fmt::Display for CoalescedFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "`{}` uses {} local slots instead of {}: ",
+            self.function, self.slots_after, self.slots_before
+        )?;
+        let merged: Vec<String> = self
+            .merged
+            .iter()
+            .map(|(var, into)| format!("`{}` reuses the slot of `{}`", var, into))
+            .collect();
+        write!(f, "{}", merged.join(", "))
+    }
+}
+
+/// Coalesces the locals of every module and then of the script of a program.
+pub fn coalesce_program(program: &mut Program) -> Vec<CoalescedFunction> {
+    let mut functions = vec![];
+    for module in &mut program.modules {
+        functions.extend(coalesce_module(module));
+    }
+    functions.extend(coalesce_script(&mut program.script));
+    functions
+}
+
+/// Coalesces the locals of the functions of a module, returning the functions that shrank in
+/// declaration order.
+pub fn coalesce_module(module: &mut ModuleDefinition) -> Vec<CoalescedFunction> {
+    let module_name = module.name.to_string();
+    module
+        .functions
+        .iter_mut()
+        .filter_map(|(name, function)| {
+            coalesce_function(ReportedFunction::in_module(&module_name, name), function)
+        })
+        .collect()
+}
+
+/// Coalesces the locals of the `main` function of a script.
+pub fn coalesce_script(script: &mut Script) -> Vec<CoalescedFunction> {
+    coalesce_function(ReportedFunction::main(), &mut script.main)
+        .into_iter()
+        .collect()
+}
+
+/// Locals of the same type sharing a slot, named after the first of them.
+struct Slot {
+    ty: Type,
+    vars: Vec<Var_>,
+}
+
+fn coalesce_function(
+    reported: ReportedFunction,
+    function: &mut Function,
+) -> Option<CoalescedFunction> {
+    let formals = &function.value.signature.formals;
+    let (locals, code) = match &mut function.value.body {
+        FunctionBody::Move { locals, code } => (locals, code),
+        FunctionBody::Native => return None,
+    };
+    let mut analysis = Analysis::default();
+    let entry = backward(&mut analysis, code, Live::new());
+    let params: Vec<&Var_> = formals.iter().map(|(var, _)| &var.value).collect();
+    analysis.define(&params, &mut entry.clone());
+
+    let shareable = |var: &Var_| !analysis.borrowed.contains(var);
+    let mut slots: Vec<Slot> = formals
+        .iter()
+        .filter(|(var, _)| shareable(&var.value))
+        .map(|(var, ty)| Slot {
+            ty: ty.clone(),
+            vars: vec![var.value.clone()],
+        })
+        .collect();
+    let mut renames = BTreeMap::new();
+    let mut merged = vec![];
+    let slots_before = formals.len() + locals.len();
+    locals.retain(|(var, ty)| {
+        let var = &var.value;
+        if !shareable(var) || entry.contains(var) {
+            return true;
+        }
+        let fits: Vec<usize> = (0..slots.len())
+            .filter(|i| {
+                slots[*i].ty == *ty
+                    && slots[*i]
+                        .vars
+                        .iter()
+                        .all(|other| !analysis.interfere(var, other))
+            })
+            .collect();
+        let preferred = fits.iter().find(|i| {
+            slots[**i]
+                .vars
+                .iter()
+                .any(|other| analysis.related(var, other))
+        });
+        let slot = match preferred.or_else(|| fits.first()) {
+            Some(i) => &mut slots[*i],
+            None => {
+                slots.push(Slot {
+                    ty: ty.clone(),
+                    vars: vec![var.clone()],
+                });
+                return true;
+            }
+        };
+        slot.vars.push(var.clone());
+        renames.insert(var.clone(), slot.vars[0].clone());
+        merged.push((var.clone(), slot.vars[0].clone()));
+        false
+    });
+    if merged.is_empty() {
+        return None;
+    }
+    remove_self_assignments(code, &renames);
+    rename_block(code, &renames);
+    Some(CoalescedFunction {
+        function: reported,
+        loc: function.span,
+        slots_before,
+        slots_after: formals.len() + locals.len(),
+        merged,
+    })
+}
+
+/// The backward liveness analysis finding the locals that interfere.
+#[derive(Default)]
+struct Analysis {
+    /// The pairs of locals that cannot share a slot, in both orders.
+    interferences: BTreeSet<(Var_, Var_)>,
+    /// The pairs of locals one is moved or copied to from the other, in both orders.
+    relations: BTreeSet<(Var_, Var_)>,
+    borrowed: BTreeSet<Var_>,
+}
+
+// Code in loops is visited several times with more and more live locals, so the interferences
+// found on the last visit include those of the earlier ones.
+impl BackwardTransfer for Analysis {
+    type State = Live;
+
+    fn cmd(&mut self, cmd: &Cmd, live: &mut Live) {
+        if let Cmd_::Assign(lvalues, e) = &cmd.value {
+            if let ([lvalue], Exp_::Move(src)) | ([lvalue], Exp_::Copy(src)) =
+                (lvalues.as_slice(), &e.value)
+            {
+                if let LValue_::Var(dest) = &lvalue.value {
+                    self.relate(&dest.value, &src.value);
+                }
+            }
+        }
+        self.define(&cmd_assigned(&cmd.value), live);
+        for e in cmd_exps(&cmd.value) {
+            self.reads(e, live);
+        }
+    }
+
+    fn cond(&mut self, cond: &Exp, live: &mut Live) {
+        self.reads(cond, live);
+    }
+
+    fn loop_results(&mut self, results: &[LValue], live: &mut Live) {
+        self.define(&lvalues_assigned(results), live);
+        for lvalue in results {
+            if let LValue_::Mutate(e) = &lvalue.value {
+                self.reads(e, live);
+            }
+        }
+    }
+}
+
+impl Analysis {
+    /// Assigns locals together, making them interfere with each other and with the locals live
+    /// after them.
+    fn define(&mut self, vars: &[&Var_], live: &mut Live) {
+        for (i, var) in vars.iter().enumerate() {
+            for other in live.iter().chain(vars[i + 1..].iter().cloned()) {
+                if other != *var {
+                    self.interferences.insert(((*var).clone(), other.clone()));
+                    self.interferences.insert((other.clone(), (*var).clone()));
+                }
+            }
+        }
+        for var in vars {
+            live.remove(*var);
+        }
+    }
+
+    fn relate(&mut self, var: &Var_, other: &Var_) {
+        self.relations.insert((var.clone(), other.clone()));
+        self.relations.insert((other.clone(), var.clone()));
+    }
+
+    fn interfere(&self, var: &Var_, other: &Var_) -> bool {
+        self.interferences.contains(&(var.clone(), other.clone()))
+    }
+
+    fn related(&self, var: &Var_, other: &Var_) -> bool {
+        self.relations.contains(&(var.clone(), other.clone()))
+    }
+
+    fn reads(&mut self, e: &Exp, live: &mut Live) {
+        let borrowed = &mut self.borrowed;
+        exp_vars(e, |var, use_| {
+            if use_ == LocalUse::Borrow {
+                borrowed.insert(var.clone());
+            }
+            live.insert(var.clone());
+        });
+    }
+}
+
+/// Removes the assignments of a local to another one sharing its slot, which renaming the locals
+/// to the first local of their slot turns into assignments of a local to itself.
+fn remove_self_assignments(block: &mut Block_, renames: &BTreeMap<Var_, Var_>) {
+    let mut blocks = vec![block];
+    while let Some(block) = blocks.pop() {
+        block
+            .stmts
+            .retain(|statement| !becomes_self_assignment(statement, renames));
+        for statement in &mut block.stmts {
+            match statement {
+                Statement::IfElseStatement(if_else) => {
+                    blocks.push(&mut if_else.if_block.value);
+                    if let Some(else_block) = &mut if_else.else_block {
+                        blocks.push(&mut else_block.value);
+                    }
+                }
+                Statement::WhileStatement(while_) => blocks.push(&mut while_.block.value),
+                Statement::LoopStatement(loop_) => blocks.push(&mut loop_.block.value),
+                Statement::CommandStatement(_) | Statement::EmptyStatement => (),
+            }
+        }
+    }
+}
+
+/// Returns whether a statement assigns a local to another one sharing its slot.
+fn becomes_self_assignment(statement: &Statement, renames: &BTreeMap<Var_, Var_>) -> bool {
+    let slot = |var: &Var_| renames.get(var).unwrap_or(var).clone();
+    match statement {
+        Statement::CommandStatement(cmd) => match &cmd.value {
+            Cmd_::Assign(lvalues, e) => match (lvalues.as_slice(), &e.value) {
+                ([lvalue], Exp_::Move(src)) | ([lvalue], Exp_::Copy(src)) => match &lvalue.value {
+                    LValue_::Var(dest) => {
+                        dest.value != src.value && slot(&dest.value) == slot(&src.value)
+                    }
+                    LValue_::Mutate(_) | LValue_::Pop => false,
+                },
+                _ => false,
+            },
+            _ => false,
+        },
+        _ => false,
+    }
+}
@@ -2,13 +2,19 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    acquires::resolve_acquires,
+    cfg::{configure_module, configure_script, CfgOptions},
     context::{Context, MaterializedPools},
     errors::*,
+    limits::{check_instructions, check_module_limits, check_script_limits},
+    partial::{stub_failed_functions, stub_function},
+    target::LATEST_BYTECODE_VERSION,
+    tuples::{lower_module, lower_script},
 };
 
 use anyhow::{bail, format_err, Result};
 use bytecode_source_map::source_map::{ModuleSourceMap, SourceMap};
-use libra_types::{account_address::AccountAddress, identifier::Identifier};
+use libra_types::{account_address::AccountAddress, account_config, identifier::Identifier};
 use move_ir_types::ast::{self, *};
 use std::{
     clone::Clone,
@@ -16,17 +22,29 @@ use std::{
         hash_map::Entry::{Occupied, Vacant},
         HashMap, VecDeque,
     },
+    mem,
+    sync::Arc,
 };
 use vm::{
     access::ModuleAccess,
     file_format::{
         self, Bytecode, CodeOffset, CodeUnit, CompiledModule, CompiledModuleMut, CompiledProgram,
         CompiledScript, CompiledScriptMut, FieldDefinition, FieldDefinitionIndex,
-        FunctionDefinition, FunctionSignature, Kind, LocalsSignature, MemberCount, SignatureToken,
-        StructDefinition, StructFieldInformation, StructHandleIndex, TableIndex,
+        FunctionDefinition, FunctionDefinitionIndex, FunctionHandleIndex, FunctionSignature, Kind,
+        LocalsSignature, LocalsSignatureIndex, MemberCount, SignatureToken, StructDefinition,
+        StructDefinitionIndex, StructFieldInformation, StructHandleIndex, TableIndex,
     },
 };
 
+// The modules of the standard library some builtins and commands compile to calls of, published
+// at the core code address, and the functions `to_bytes` and `emit` call. The hashing builtins call
+// the functions of their own name.
+const LCS_MODULE: &str = "LCS";
+const LCS_SERIALIZE: &str = "serialize";
+const HASH_MODULE: &str = "Hash";
+const ACCOUNT_MODULE: &str = "LibraAccount";
+const ACCOUNT_EMIT_EVENT: &str = "emit_event";
+
 macro_rules! record_src_loc {
     (local: $context:expr, $var:expr) => {{
         let source_name = (Identifier::from($var.name()), $var.span);
@@ -91,11 +109,14 @@ macro_rules! make_push_instr {
 struct LoopInfo {
     start_loc: usize,
     breaks: Vec<usize>,
+    /// The locals a `loop` assigns the value it breaks with to, `None` for a loop without a value.
+    results: Option<Vec<LValue>>,
 }
 
 // Ideally, we should capture all of this info into a CFG, but as we only have structured control
 // flow currently, it would be a bit overkill. It will be a necessity if we add arbitrary branches
-// in the IR, as is expressible in the bytecode
+// in the IR, as is expressible in the bytecode. Analyses needing the CFG of a function build it
+// with `control_flow::ControlFlowGraph`
 struct ControlFlowInfo {
     // A `break` is reachable iff it was used before a terminal node
     reachable_break: bool,
@@ -198,6 +219,9 @@ struct FunctionFrame {
     max_stack_depth: i64,
     cur_stack_depth: i64,
     loops: Vec<LoopInfo>,
+    // The temporaries of the code generated for some builtins, by type and position among the
+    // temporaries of that type one builtin uses.
+    temps: HashMap<(SignatureToken, usize), u8>,
 }
 
 impl FunctionFrame {
@@ -256,10 +280,27 @@ impl FunctionFrame {
         Ok(cur_loc_idx)
     }
 
-    fn push_loop(&mut self, start_loc: usize) -> Result<()> {
+    // Returns the `nth` temporary of type `type_`, and whether it was just defined. The code of a
+    // builtin only holds values in temporaries until it ends, so builtins share them.
+    fn get_temp(&mut self, type_: SignatureToken, nth: usize) -> Result<(u8, bool)> {
+        if let Some(idx) = self.temps.get(&(type_.clone(), nth)) {
+            return Ok((*idx, false));
+        }
+        if self.local_count >= u8::max_value() {
+            bail!("Max number of locals reached");
+        }
+        let idx = self.local_count;
+        self.local_types.0.push(type_.clone());
+        self.local_count += 1;
+        self.temps.insert((type_, nth), idx);
+        Ok((idx, true))
+    }
+
+    fn push_loop(&mut self, start_loc: usize, results: Option<Vec<LValue>>) -> Result<()> {
         self.loops.push(LoopInfo {
             start_loc,
             breaks: Vec::new(),
+            results,
         });
         Ok(())
     }
@@ -288,6 +329,13 @@ impl FunctionFrame {
         }
     }
 
+    fn get_loop_results(&self) -> Result<Option<Vec<LValue>>> {
+        match self.loops.last() {
+            Some(loop_) => Ok(loop_.results.clone()),
+            None => bail!("break outside loop"),
+        }
+    }
+
     fn get_loop_breaks(&self) -> Result<&Vec<usize>> {
         match self.loops.last() {
             Some(loop_) => Ok(&loop_.breaks),
@@ -301,6 +349,17 @@ pub fn compile_program<'a, T: 'a + ModuleAccess>(
     address: AccountAddress,
     program: Program,
     deps: impl IntoIterator<Item = &'a T>,
+) -> Result<(CompiledProgram, SourceMap<Loc>)> {
+    compile_program_for_target(address, program, deps, LATEST_BYTECODE_VERSION)
+}
+
+/// Compile a transaction program for version `target_version` of the bytecode, lowering what
+/// the newer instructions would compute to older ones where it can.
+pub fn compile_program_for_target<'a, T: 'a + ModuleAccess>(
+    address: AccountAddress,
+    program: Program,
+    deps: impl IntoIterator<Item = &'a T>,
+    target_version: u64,
 ) -> Result<(CompiledProgram, SourceMap<Loc>)> {
     let deps = deps
         .into_iter()
@@ -312,14 +371,15 @@ pub fn compile_program<'a, T: 'a + ModuleAccess>(
     for m in program.modules {
         let (module, source_map) = {
             let deps = deps.iter().copied().chain(&modules);
-            compile_module(address, m, deps)?
+            compile_module_for_target(address, m, deps, target_version)?
         };
         modules.push(module);
         source_maps.push(source_map);
     }
 
     let deps = deps.into_iter().chain(modules.iter());
-    let (script, source_map) = compile_script(address, program.script, deps)?;
+    let (script, source_map) =
+        compile_script_for_target(address, program.script, deps, target_version)?;
     source_maps.push(source_map);
     Ok((CompiledProgram { modules, script }, source_maps))
 }
@@ -330,11 +390,36 @@ pub fn compile_script<'a, T: 'a + ModuleAccess>(
     script: Script,
     dependencies: impl IntoIterator<Item = &'a T>,
 ) -> Result<(CompiledScript, ModuleSourceMap<Loc>)> {
+    compile_script_for_target(address, script, dependencies, LATEST_BYTECODE_VERSION)
+}
+
+/// Compile a transaction script for version `target_version` of the bytecode, as
+/// `compile_program_for_target` does.
+pub fn compile_script_for_target<'a, T: 'a + ModuleAccess>(
+    address: AccountAddress,
+    mut script: Script,
+    dependencies: impl IntoIterator<Item = &'a T>,
+    target_version: u64,
+) -> Result<(CompiledScript, ModuleSourceMap<Loc>)> {
+    if let Some(error) = configure_script(&mut script, &CfgOptions::default())
+        .into_iter()
+        .next()
+    {
+        return Err(error.into());
+    }
+    if let Some(error) = lower_script(&mut script).into_iter().next() {
+        return Err(error.into());
+    }
+    if let Some(error) = check_script_limits(&script).into_iter().next() {
+        return Err(error.into());
+    }
     let current_module = QualifiedModuleIdent {
         address,
         name: ModuleName::new(file_format::self_module_name().to_owned()),
     };
     let mut context = Context::new(dependencies, current_module)?;
+    context.source_map.language_version = script.language_version;
+    context.target_version = target_version;
     let self_name = ModuleName::new(ModuleName::self_name().into());
 
     compile_imports(&mut context, address, script.imports)?;
@@ -383,11 +468,72 @@ pub fn compile_module<'a, T: 'a + ModuleAccess>(
     module: ModuleDefinition,
     dependencies: impl IntoIterator<Item = &'a T>,
 ) -> Result<(CompiledModule, ModuleSourceMap<Loc>)> {
+    compile_module_for_target(address, module, dependencies, LATEST_BYTECODE_VERSION)
+}
+
+/// Compile a module for version `target_version` of the bytecode, as
+/// `compile_program_for_target` does.
+pub fn compile_module_for_target<'a, T: 'a + ModuleAccess>(
+    address: AccountAddress,
+    module: ModuleDefinition,
+    dependencies: impl IntoIterator<Item = &'a T>,
+    target_version: u64,
+) -> Result<(CompiledModule, ModuleSourceMap<Loc>)> {
+    compile_module_impl(address, module, dependencies, target_version, None)
+}
+
+/// Compile a module for version `target_version` of the bytecode, replacing the functions that
+/// fail to compile with stubs, as `partial` describes, and returning the errors of those
+/// functions. Fails only on errors outside of any function.
+pub fn compile_module_partially<'a, T: 'a + ModuleAccess>(
+    address: AccountAddress,
+    module: ModuleDefinition,
+    dependencies: impl IntoIterator<Item = &'a T>,
+    target_version: u64,
+) -> Result<(CompiledModule, ModuleSourceMap<Loc>, Vec<FunctionError>)> {
+    let mut failures = vec![];
+    let (compiled_module, source_map) = compile_module_impl(
+        address,
+        module,
+        dependencies,
+        target_version,
+        Some(&mut failures),
+    )?;
+    Ok((compiled_module, source_map, failures))
+}
+
+fn compile_module_impl<'a, T: 'a + ModuleAccess>(
+    address: AccountAddress,
+    mut module: ModuleDefinition,
+    dependencies: impl IntoIterator<Item = &'a T>,
+    target_version: u64,
+    mut failures: Option<&mut Vec<FunctionError>>,
+) -> Result<(CompiledModule, ModuleSourceMap<Loc>)> {
+    if let Some(error) = configure_module(&mut module, &CfgOptions::default())
+        .into_iter()
+        .next()
+    {
+        return Err(error.into());
+    }
+    let errors = lower_module(&mut module);
+    if let Some(error) = tolerate(&mut failures, &mut module, errors, |e| Some(e.loc)) {
+        return Err(error.into());
+    }
+    let errors = resolve_acquires(&module);
+    if let Some(error) = tolerate(&mut failures, &mut module, errors, |e| Some(e.loc())) {
+        return Err(error.into());
+    }
+    let errors = check_module_limits(&module);
+    if let Some(error) = tolerate(&mut failures, &mut module, errors, |e| e.loc) {
+        return Err(error.into());
+    }
     let current_module = QualifiedModuleIdent {
         address,
         name: module.name,
     };
     let mut context = Context::new(dependencies, current_module)?;
+    context.source_map.language_version = module.language_version;
+    context.target_version = target_version;
     let self_name = ModuleName::new(ModuleName::self_name().into());
     // Explicitly declare all imports as they will be included even if not used
     compile_imports(&mut context, address, module.imports)?;
@@ -411,7 +557,7 @@ pub fn compile_module<'a, T: 'a + ModuleAccess>(
 
     let (struct_defs, field_defs) = compile_structs(&mut context, &self_name, module.structs)?;
 
-    let function_defs = compile_functions(&mut context, &self_name, module.functions)?;
+    let function_defs = compile_functions(&mut context, &self_name, module.functions, failures)?;
 
     let (
         MaterializedPools {
@@ -440,6 +586,7 @@ pub fn compile_module<'a, T: 'a + ModuleAccess>(
         struct_defs,
         field_defs,
         function_defs,
+        metadata: vec![],
     };
     compiled_module
         .freeze()
@@ -447,6 +594,24 @@ pub fn compile_module<'a, T: 'a + ModuleAccess>(
         .map(|frozen_module| (frozen_module, source_map))
 }
 
+// Stubs the functions the errors are located in when compiling partially, returning the first
+// error that cannot be tolerated.
+fn tolerate<E: std::fmt::Display>(
+    failures: &mut Option<&mut Vec<FunctionError>>,
+    module: &mut ModuleDefinition,
+    errors: Vec<E>,
+    loc: impl Fn(&E) -> Option<Loc>,
+) -> Option<E> {
+    match failures {
+        Some(failures) => {
+            let (failed, rest) = stub_failed_functions(module, errors, loc);
+            failures.extend(failed);
+            rest.into_iter().next()
+        }
+        None => errors.into_iter().next(),
+    }
+}
+
 fn compile_imports(
     context: &mut Context,
     address: AccountAddress,
@@ -513,6 +678,10 @@ fn compile_type(context: &mut Context, ty: &Type) -> Result<SignatureToken> {
         Type::TypeParameter(ty_var) => {
             SignatureToken::TypeParameter(context.type_formal_index(ty_var)?)
         }
+        Type::Tuple(_) => bail!(
+            "tuple type {} is only allowed for locals, parameters and return values",
+            ty
+        ),
     })
 }
 
@@ -601,14 +770,51 @@ fn compile_functions(
     context: &mut Context,
     self_name: &ModuleName,
     functions: Vec<(FunctionName, Function)>,
+    failures: Option<&mut Vec<FunctionError>>,
 ) -> Result<Vec<FunctionDefinition>> {
-    functions
-        .into_iter()
-        .enumerate()
-        .map(|(func_index, (name, ast_function))| {
-            compile_function(context, self_name, name, ast_function, func_index)
-        })
-        .collect()
+    let failures = match failures {
+        Some(failures) => failures,
+        None => {
+            return functions
+                .into_iter()
+                .enumerate()
+                .map(|(func_index, (name, ast_function))| {
+                    compile_function(context, self_name, name, ast_function, func_index)
+                })
+                .collect()
+        }
+    };
+    let mut function_defs = vec![];
+    for (func_index, (name, ast_function)) in functions.into_iter().enumerate() {
+        let mut stub = ast_function.clone();
+        match compile_function(context, self_name, name.clone(), ast_function, func_index) {
+            Ok(function_def) => function_defs.push(function_def),
+            Err(err) => {
+                failures.push(FunctionError {
+                    function: name.clone(),
+                    loc: error_loc(&err).unwrap_or(stub.span),
+                    message: err.to_string(),
+                });
+                // Compile the stub from scratch, dropping what was recorded of the function.
+                context
+                    .source_map
+                    .remove_function_mapping(FunctionDefinitionIndex(func_index as TableIndex));
+                stub_function(&mut stub);
+                function_defs.push(compile_function(
+                    context, self_name, name, stub, func_index,
+                )?);
+            }
+        }
+    }
+    Ok(function_defs)
+}
+
+// The location of the errors of the compiler that carry one.
+fn error_loc(err: &anyhow::Error) -> Option<Loc> {
+    if let Some(error) = err.downcast_ref::<ArityError>() {
+        return Some(error.call);
+    }
+    err.downcast_ref::<LimitError>().and_then(|error| error.loc)
 }
 
 fn compile_function(
@@ -623,8 +829,9 @@ fn compile_function(
         function_type_formals: context,
         &ast_function.signature.type_formals
     );
-    let fh_idx = context.function_handle(self_name.clone(), name)?.1;
+    let fh_idx = context.function_handle(self_name.clone(), name.clone())?.1;
 
+    let loc = ast_function.span;
     let ast_function = ast_function.value;
 
     let flags = match ast_function.visibility {
@@ -644,7 +851,10 @@ fn compile_function(
         FunctionBody::Move { locals, code } => {
             let (m, _) = type_formals(&ast_function.signature.type_formals)?;
             context.bind_type_formals(m)?;
-            compile_function_body(context, ast_function.signature.formals, locals, code)?
+            let code =
+                compile_function_body(context, ast_function.signature.formals, locals, code)?;
+            check_instructions(&name, loc, code.code.len())?;
+            code
         }
         FunctionBody::Native => {
             for (var, _) in ast_function.signature.formals.into_iter() {
@@ -668,23 +878,21 @@ fn compile_function_body(
     block: Block_,
 ) -> Result<CodeUnit> {
     let mut function_frame = FunctionFrame::new();
-    let mut locals_signature = LocalsSignature(vec![]);
     for (var, t) in formals {
         let sig = compile_type(context, &t)?;
-        function_frame.define_local(&var, sig.clone())?;
-        locals_signature.0.push(sig);
+        function_frame.define_local(&var, sig)?;
         record_src_loc!(local: context, var);
     }
     for (var_, t) in locals {
         let sig = compile_type(context, &t)?;
-        function_frame.define_local(&var_.value, sig.clone())?;
-        locals_signature.0.push(sig);
+        function_frame.define_local(&var_.value, sig)?;
         record_src_loc!(local: context, var_);
     }
-    let sig_idx = context.locals_signature_index(locals_signature)?;
 
     let mut code = vec![];
     compile_block(context, &mut function_frame, &mut code, block)?;
+    // The locals include the temporaries defined while compiling the body.
+    let sig_idx = context.locals_signature_index(function_frame.local_types.clone())?;
     let max_stack_size = if function_frame.max_stack_depth < 0 {
         0
     } else if function_frame.max_stack_depth > i64::from(u16::max_value()) {
@@ -782,7 +990,7 @@ fn compile_while(
     make_push_instr!(context, code);
     let cond_span = while_.cond.span;
     let loop_start_loc = code.len();
-    function_frame.push_loop(loop_start_loc)?;
+    function_frame.push_loop(loop_start_loc, None)?;
     compile_expression(context, function_frame, code, while_.cond)?;
 
     let brfalse_loc = code.len();
@@ -824,7 +1032,12 @@ fn compile_loop(
 ) -> Result<ControlFlowInfo> {
     make_push_instr!(context, code);
     let loop_start_loc = code.len();
-    function_frame.push_loop(loop_start_loc)?;
+    let results = if loop_.results.is_empty() {
+        None
+    } else {
+        Some(loop_.results)
+    };
+    function_frame.push_loop(loop_start_loc, results)?;
 
     let body_cf_info = compile_block(context, function_frame, code, loop_.block.value)?;
     push_instr!(loop_.block.span, Bytecode::Branch(loop_start_loc as u16));
@@ -865,7 +1078,7 @@ fn compile_command(
             // `return` and `abort` alway makes a terminal node
             Cmd_::Abort(_) |
             Cmd_::Return(_) => (false, true),
-            Cmd_::Break => (true, false),
+            Cmd_::Break(_) => (true, false),
             _ => (false, false),
         };
     match cmd.value {
@@ -881,7 +1094,25 @@ fn compile_command(
             function_frame.pop()?;
         }
         Cmd_::Assign(lvalues, rhs_expressions) => {
-            compile_expression(context, function_frame, code, rhs_expressions)?;
+            let call = called_function(&rhs_expressions).map(|f| (rhs_expressions.span, f));
+            let values = compile_expression(context, function_frame, code, rhs_expressions)?;
+            // The signature of the callee is resolved by now: check that it returns a value for
+            // every target here, rather than leave it to the verifier to find the stack
+            // unbalanced.
+            if let (Some((call, function)), Some(first), Some(last)) =
+                (call, lvalues.first(), lvalues.last())
+            {
+                if values.len() != lvalues.len() {
+                    return Err(ArityError {
+                        lvalues: Loc::new(first.span.start(), last.span.end()),
+                        call,
+                        function,
+                        assigned: lvalues.len(),
+                        returned: values.len(),
+                    }
+                    .into());
+                }
+            }
             compile_lvalues(context, function_frame, code, lvalues)?;
         }
         Cmd_::Unpack(name, tys, bindings, e) => {
@@ -904,11 +1135,33 @@ fn compile_command(
             let loc = function_frame.get_loop_start()?;
             push_instr!(cmd.span, Bytecode::Branch(loc as u16));
         }
-        Cmd_::Break => {
+        Cmd_::Break(exp_opt) => {
+            // The value is assigned to the results of the loop before leaving it, so that the
+            // stack is empty at the end of the loop.
+            match (exp_opt, function_frame.get_loop_results()?) {
+                (None, None) => (),
+                (Some(exp), Some(results)) => {
+                    compile_expression(context, function_frame, code, *exp)?;
+                    compile_lvalues(context, function_frame, code, results)?;
+                }
+                (Some(_), None) => bail!("break with a value out of a loop without a value"),
+                (None, Some(_)) => bail!("break without a value out of a loop with a value"),
+            }
             function_frame.push_loop_break(code.len())?;
             // placeholder, to be replaced when the enclosing while is compiled
             push_instr!(cmd.span, Bytecode::Branch(0));
         }
+        Cmd_::Emit(handle, event) => {
+            compile_expression(context, function_frame, code, *handle)?;
+            let event_tys = compile_expression(context, function_frame, code, (*event).clone())?;
+            let event_ty = event_type(context, function_frame, &event.value, &event_tys)?;
+            let type_actuals_id =
+                context.locals_signature_index(LocalsSignature(vec![event_ty]))?;
+            let fh_idx = library_function(context, ACCOUNT_MODULE, ACCOUNT_EMIT_EVENT)?;
+            push_instr!(cmd.span, Bytecode::Call(fh_idx, type_actuals_id));
+            function_frame.pop()?;
+            function_frame.pop()?;
+        }
         Cmd_::Exp(e) => {
             compile_expression(context, function_frame, code, *e)?;
         }
@@ -919,6 +1172,23 @@ fn compile_command(
     })
 }
 
+// Returns the name of the module function `e` calls, if it is a call of one, possibly in
+// parentheses.
+fn called_function(mut e: &Exp) -> Option<String> {
+    loop {
+        match &e.value {
+            Exp_::FunctionCall(f, _) => match &f.value {
+                FunctionCall_::ModuleFunctionCall { module, name, .. } => {
+                    return Some(format!("{}.{}", module, name))
+                }
+                FunctionCall_::Builtin(_) => return None,
+            },
+            Exp_::ExprList(exps) if exps.len() == 1 => e = &exps[0],
+            _ => return None,
+        }
+    }
+}
+
 fn compile_lvalues(
     context: &mut Context,
     function_frame: &mut FunctionFrame,
@@ -970,6 +1240,35 @@ fn infer_int_bin_op_result_ty(
     }
 }
 
+/// The work left to compile an expression. Expressions are nested without bound in the source, so
+/// the work is kept on a stack of its own rather than on the one of the compiler.
+enum Work {
+    /// Compiles an expression, leaving the types of its results on the stack of results.
+    Compile(Exp),
+    /// Emits the instructions of an expression once its subexpressions are compiled.
+    Finish(Loc, Pending),
+}
+
+/// The instructions of an expression left to emit, given the results of its subexpressions.
+enum Pending {
+    Unary(UnaryOp),
+    /// The right operand of a binary operator, which is only compiled if the operator is not
+    /// lowered for the targeted bytecode version.
+    Rhs(BinOp, Exp),
+    Binop(BinOp),
+    Dereference,
+    Borrow(bool, Field_),
+    Pack(
+        StructDefinitionIndex,
+        LocalsSignatureIndex,
+        StructHandleIndex,
+        usize,
+    ),
+    Call(FunctionCall),
+    /// Concatenates the results of the given number of expressions.
+    List(usize),
+}
+
 fn compile_expression(
     context: &mut Context,
     function_frame: &mut FunctionFrame,
@@ -977,212 +1276,287 @@ fn compile_expression(
     exp: Exp,
 ) -> Result<VecDeque<InferredType>> {
     make_push_instr!(context, code);
-    Ok(match exp.value {
-        Exp_::Move(v) => {
-            let loc_idx = function_frame.get_local(&v.value)?;
-            let load_loc = Bytecode::MoveLoc(loc_idx);
-            push_instr!(exp.span, load_loc);
-            function_frame.push()?;
-            let loc_type = function_frame.get_local_type(loc_idx)?;
-            vec_deque![InferredType::from_signature_token(loc_type)]
-        }
-        Exp_::Copy(v) => {
-            let loc_idx = function_frame.get_local(&v.value)?;
-            let load_loc = Bytecode::CopyLoc(loc_idx);
-            push_instr!(exp.span, load_loc);
-            function_frame.push()?;
-            let loc_type = function_frame.get_local_type(loc_idx)?;
-            vec_deque![InferredType::from_signature_token(loc_type)]
-        }
-        Exp_::BorrowLocal(is_mutable, v) => {
-            let loc_idx = function_frame.get_local(&v.value)?;
-            let loc_type = function_frame.get_local_type(loc_idx)?;
-            let inner_token = Box::new(InferredType::from_signature_token(loc_type));
-            if is_mutable {
-                push_instr!(exp.span, Bytecode::MutBorrowLoc(loc_idx));
-                function_frame.push()?;
-                vec_deque![InferredType::MutableReference(inner_token)]
-            } else {
-                push_instr!(exp.span, Bytecode::ImmBorrowLoc(loc_idx));
-                function_frame.push()?;
-                vec_deque![InferredType::Reference(inner_token)]
-            }
-        }
-        Exp_::Value(cv) => match cv.value {
-            CopyableVal_::Address(address) => {
-                let addr_idx = context.address_index(address)?;
-                push_instr!(exp.span, Bytecode::LdAddr(addr_idx));
-                function_frame.push()?;
-                vec_deque![InferredType::Address]
-            }
-            CopyableVal_::U8(i) => {
-                push_instr!(exp.span, Bytecode::LdU8(i));
-                function_frame.push()?;
-                vec_deque![InferredType::U8]
+    let mut work = vec![Work::Compile(exp)];
+    let mut results: Vec<VecDeque<InferredType>> = vec![];
+    while let Some(next) = work.pop() {
+        let mut exp = match next {
+            Work::Compile(exp) => exp,
+            Work::Finish(loc, pending) => {
+                finish_expression(
+                    context,
+                    function_frame,
+                    code,
+                    loc,
+                    pending,
+                    &mut work,
+                    &mut results,
+                )?;
+                continue;
             }
-            CopyableVal_::U64(i) => {
-                push_instr!(exp.span, Bytecode::LdU64(i));
+        };
+        let pending = match &mut exp.value {
+            Exp_::Move(v) => {
+                let loc_idx = function_frame.get_local(&v.value)?;
+                let load_loc = Bytecode::MoveLoc(loc_idx);
+                push_instr!(exp.span, load_loc);
                 function_frame.push()?;
-                vec_deque![InferredType::U64]
+                let loc_type = function_frame.get_local_type(loc_idx)?;
+                results.push(vec_deque![InferredType::from_signature_token(loc_type)]);
+                continue;
             }
-            CopyableVal_::U128(i) => {
-                push_instr!(exp.span, Bytecode::LdU128(i));
+            Exp_::Copy(v) => {
+                let loc_idx = function_frame.get_local(&v.value)?;
+                let load_loc = Bytecode::CopyLoc(loc_idx);
+                push_instr!(exp.span, load_loc);
                 function_frame.push()?;
-                vec_deque![InferredType::U128]
+                let loc_type = function_frame.get_local_type(loc_idx)?;
+                results.push(vec_deque![InferredType::from_signature_token(loc_type)]);
+                continue;
             }
-            CopyableVal_::ByteArray(buf) => {
-                let buf_idx = context.byte_array_index(&buf)?;
-                push_instr!(exp.span, Bytecode::LdByteArray(buf_idx));
-                function_frame.push()?;
-                vec_deque![InferredType::ByteArray]
+            Exp_::BorrowLocal(is_mutable, v) => {
+                let loc_idx = function_frame.get_local(&v.value)?;
+                let loc_type = function_frame.get_local_type(loc_idx)?;
+                let inner_token = Box::new(InferredType::from_signature_token(loc_type));
+                if *is_mutable {
+                    push_instr!(exp.span, Bytecode::MutBorrowLoc(loc_idx));
+                    function_frame.push()?;
+                    results.push(vec_deque![InferredType::MutableReference(inner_token)]);
+                } else {
+                    push_instr!(exp.span, Bytecode::ImmBorrowLoc(loc_idx));
+                    function_frame.push()?;
+                    results.push(vec_deque![InferredType::Reference(inner_token)]);
+                }
+                continue;
             }
-            CopyableVal_::Bool(b) => {
-                push_instr! {exp.span,
-                    if b {
-                        Bytecode::LdTrue
-                    } else {
-                        Bytecode::LdFalse
+            Exp_::Value(cv) => {
+                let ty = match &mut cv.value {
+                    CopyableVal_::Address(address) => {
+                        let addr_idx = context.address_index(*address)?;
+                        push_instr!(exp.span, Bytecode::LdAddr(addr_idx));
+                        InferredType::Address
+                    }
+                    CopyableVal_::U8(i) => {
+                        push_instr!(exp.span, Bytecode::LdU8(*i));
+                        InferredType::U8
+                    }
+                    CopyableVal_::U64(i) => {
+                        push_instr!(exp.span, Bytecode::LdU64(*i));
+                        InferredType::U64
+                    }
+                    CopyableVal_::U128(i) => {
+                        push_instr!(exp.span, Bytecode::LdU128(*i));
+                        InferredType::U128
+                    }
+                    CopyableVal_::ByteArray(buf) => {
+                        // The AST is usually the only owner of the literal, which is then moved to
+                        // the pool
+                        let buf = Arc::try_unwrap(mem::take(buf)).unwrap_or_else(|buf| (*buf).clone());
+                        let buf_idx = context.byte_array_index(buf)?;
+                        push_instr!(exp.span, Bytecode::LdByteArray(buf_idx));
+                        InferredType::ByteArray
+                    }
+                    CopyableVal_::Bool(b) => {
+                        push_instr! {exp.span,
+                            if *b {
+                                Bytecode::LdTrue
+                            } else {
+                                Bytecode::LdFalse
+                            }
+                        };
+                        InferredType::Bool
                     }
                 };
                 function_frame.push()?;
-                vec_deque![InferredType::Bool]
+                results.push(vec_deque![ty]);
+                continue;
             }
-        },
-        Exp_::Pack(name, tys, fields) => {
-            let tokens = LocalsSignature(compile_types(context, &tys)?);
-            let type_actuals_id = context.locals_signature_index(tokens)?;
-            let def_idx = context.struct_definition_index(&name)?;
+            Exp_::Pack(name, tys, fields) => {
+                let tokens = LocalsSignature(compile_types(context, tys)?);
+                let type_actuals_id = context.locals_signature_index(tokens)?;
+                let def_idx = context.struct_definition_index(name)?;
+
+                let self_name = ModuleName::new(ModuleName::self_name().into());
+                let ident = QualifiedStructIdent {
+                    module: self_name,
+                    name: name.clone(),
+                };
+                let sh_idx = context.struct_handle_index(ident)?;
 
-            let self_name = ModuleName::new(ModuleName::self_name().into());
-            let ident = QualifiedStructIdent {
-                module: self_name,
-                name: name.clone(),
-            };
-            let sh_idx = context.struct_handle_index(ident)?;
-
-            let num_fields = fields.len();
-            for (field_order, (field, e)) in fields.into_iter().enumerate() {
-                // Check that the fields are specified in order matching the definition.
-                let (_, _, decl_order) = context.field(sh_idx, field.value.clone())?;
-                if field_order != decl_order {
-                    bail!("Field {} defined out of order for struct {}", field, name);
+                for (field_order, (field, _)) in fields.iter().enumerate() {
+                    // Check that the fields are specified in order matching the definition.
+                    let (_, _, decl_order) = context.field(sh_idx, field.value.clone())?;
+                    if field_order != decl_order {
+                        bail!("Field {} defined out of order for struct {}", field, name);
+                    }
                 }
-
-                compile_expression(context, function_frame, code, e)?;
+                Pending::Pack(def_idx, type_actuals_id, sh_idx, fields.len())
             }
-            push_instr!(exp.span, Bytecode::Pack(def_idx, type_actuals_id));
-            for _ in 0..num_fields {
-                function_frame.pop()?;
+            Exp_::UnaryExp(op, _) => Pending::Unary(op.clone()),
+            Exp_::BinopExp(_, op, _) => {
+                let op = op.clone();
+                let mut operands = exp.value.take_subexps();
+                let rhs = operands.pop().expect("a binary operator has two operands");
+                work.push(Work::Finish(exp.span, Pending::Rhs(op, rhs)));
+                work.extend(operands.into_iter().map(Work::Compile));
+                continue;
             }
-            function_frame.push()?;
+            Exp_::Dereference(_) => Pending::Dereference,
+            Exp_::Borrow {
+                is_mutable, field, ..
+            } => Pending::Borrow(*is_mutable, field.clone()),
+            Exp_::FunctionCall(f, _) => Pending::Call(f.clone()),
+            Exp_::ExprList(exps) => Pending::List(exps.len()),
+            Exp_::Placeholder(name) => bail!(
+                "placeholder {{{{{}}}}} was not given a value before compiling",
+                name
+            ),
+            Exp_::Var(v) => bail!(
+                "local {} is used without `move` or `copy`, which are only inferred with implicit moves",
+                v
+            ),
+        };
+        work.push(Work::Finish(exp.span, pending));
+        let subexps = exp.value.take_subexps();
+        work.extend(subexps.into_iter().rev().map(Work::Compile));
+    }
+    Ok(results
+        .pop()
+        .expect("the expression compiled leaves its results"))
+}
 
-            vec_deque![InferredType::Struct(sh_idx)]
-        }
-        Exp_::UnaryExp(op, e) => {
-            compile_expression(context, function_frame, code, *e)?;
+// Emits the instructions of an expression, whose subexpressions left their results on top of the
+// stack of results.
+fn finish_expression(
+    context: &mut Context,
+    function_frame: &mut FunctionFrame,
+    code: &mut Vec<Bytecode>,
+    loc: Loc,
+    pending: Pending,
+    work: &mut Vec<Work>,
+    results: &mut Vec<VecDeque<InferredType>>,
+) -> Result<()> {
+    make_push_instr!(context, code);
+    let mut operand = || {
+        results
+            .pop()
+            .ok_or_else(|| format_err!("Impossible no expression was compiled"))
+    };
+    let result = match pending {
+        Pending::Unary(op) => {
+            operand()?;
             match op {
                 UnaryOp::Not => {
-                    push_instr!(exp.span, Bytecode::Not);
+                    push_instr!(loc, Bytecode::Not);
                     vec_deque![InferredType::Bool]
                 }
             }
         }
-        Exp_::BinopExp(e1, op, e2) => {
-            let tys1 = compile_expression(context, function_frame, code, *e1)?;
-            let tys2 = compile_expression(context, function_frame, code, *e2)?;
-
+        Pending::Rhs(op, rhs) => {
+            let tys1 = operand()?;
+            if let Some(instrs) = shift_fallback(context, &op, &tys1, &rhs) {
+                for instr in instrs {
+                    match instr {
+                        Bytecode::LdU64(_) => function_frame.push()?,
+                        _ => function_frame.pop()?,
+                    }
+                    push_instr!(loc, instr);
+                }
+            } else {
+                work.push(Work::Finish(loc, Pending::Binop(op)));
+                work.push(Work::Compile(rhs));
+            }
+            tys1
+        }
+        Pending::Binop(op) => {
+            let tys2 = operand()?;
+            let tys1 = operand()?;
             function_frame.pop()?;
             match op {
                 BinOp::Add => {
-                    push_instr!(exp.span, Bytecode::Add);
+                    push_instr!(loc, Bytecode::Add);
                     vec_deque![infer_int_bin_op_result_ty(&tys1, &tys2)]
                 }
                 BinOp::Sub => {
-                    push_instr!(exp.span, Bytecode::Sub);
+                    push_instr!(loc, Bytecode::Sub);
                     vec_deque![infer_int_bin_op_result_ty(&tys1, &tys2)]
                 }
                 BinOp::Mul => {
-                    push_instr!(exp.span, Bytecode::Mul);
+                    push_instr!(loc, Bytecode::Mul);
                     vec_deque![infer_int_bin_op_result_ty(&tys1, &tys2)]
                 }
                 BinOp::Mod => {
-                    push_instr!(exp.span, Bytecode::Mod);
+                    push_instr!(loc, Bytecode::Mod);
                     vec_deque![infer_int_bin_op_result_ty(&tys1, &tys2)]
                 }
                 BinOp::Div => {
-                    push_instr!(exp.span, Bytecode::Div);
+                    push_instr!(loc, Bytecode::Div);
                     vec_deque![infer_int_bin_op_result_ty(&tys1, &tys2)]
                 }
                 BinOp::BitOr => {
-                    push_instr!(exp.span, Bytecode::BitOr);
+                    push_instr!(loc, Bytecode::BitOr);
                     vec_deque![infer_int_bin_op_result_ty(&tys1, &tys2)]
                 }
                 BinOp::BitAnd => {
-                    push_instr!(exp.span, Bytecode::BitAnd);
+                    push_instr!(loc, Bytecode::BitAnd);
                     vec_deque![infer_int_bin_op_result_ty(&tys1, &tys2)]
                 }
                 BinOp::Xor => {
-                    push_instr!(exp.span, Bytecode::Xor);
+                    push_instr!(loc, Bytecode::Xor);
                     vec_deque![infer_int_bin_op_result_ty(&tys1, &tys2)]
                 }
                 BinOp::Shl => {
-                    push_instr!(exp.span, Bytecode::Shl);
+                    push_instr!(loc, Bytecode::Shl);
                     tys1
                 }
                 BinOp::Shr => {
-                    push_instr!(exp.span, Bytecode::Shr);
+                    push_instr!(loc, Bytecode::Shr);
                     tys1
                 }
                 BinOp::Or => {
-                    push_instr!(exp.span, Bytecode::Or);
+                    push_instr!(loc, Bytecode::Or);
                     vec_deque![InferredType::Bool]
                 }
                 BinOp::And => {
-                    push_instr!(exp.span, Bytecode::And);
+                    push_instr!(loc, Bytecode::And);
                     vec_deque![InferredType::Bool]
                 }
                 BinOp::Eq => {
-                    push_instr!(exp.span, Bytecode::Eq);
+                    push_instr!(loc, Bytecode::Eq);
                     vec_deque![InferredType::Bool]
                 }
                 BinOp::Neq => {
-                    push_instr!(exp.span, Bytecode::Neq);
+                    push_instr!(loc, Bytecode::Neq);
                     vec_deque![InferredType::Bool]
                 }
                 BinOp::Lt => {
-                    push_instr!(exp.span, Bytecode::Lt);
+                    push_instr!(loc, Bytecode::Lt);
                     vec_deque![InferredType::Bool]
                 }
                 BinOp::Gt => {
-                    push_instr!(exp.span, Bytecode::Gt);
+                    push_instr!(loc, Bytecode::Gt);
                     vec_deque![InferredType::Bool]
                 }
                 BinOp::Le => {
-                    push_instr!(exp.span, Bytecode::Le);
+                    push_instr!(loc, Bytecode::Le);
                     vec_deque![InferredType::Bool]
                 }
                 BinOp::Ge => {
-                    push_instr!(exp.span, Bytecode::Ge);
+                    push_instr!(loc, Bytecode::Ge);
                     vec_deque![InferredType::Bool]
                 }
             }
         }
-        Exp_::Dereference(e) => {
-            let loc_type = compile_expression(context, function_frame, code, *e)?.pop_front();
-            push_instr!(exp.span, Bytecode::ReadRef);
+        Pending::Dereference => {
+            let loc_type = operand()?.pop_front();
+            push_instr!(loc, Bytecode::ReadRef);
             match loc_type {
                 Some(InferredType::MutableReference(sig_ref_token)) => vec_deque![*sig_ref_token],
                 Some(InferredType::Reference(sig_ref_token)) => vec_deque![*sig_ref_token],
                 _ => vec_deque![InferredType::Anything],
             }
         }
-        Exp_::Borrow {
-            is_mutable,
-            exp: inner_exp,
-            field,
-        } => {
-            let loc_type_opt =
-                compile_expression(context, function_frame, code, *inner_exp)?.pop_front();
+        Pending::Borrow(is_mutable, field) => {
+            let loc_type_opt = operand()?.pop_front();
             let loc_type =
                 loc_type_opt.ok_or_else(|| format_err!("Impossible no expression to borrow"))?;
             let sh_idx = loc_type.get_struct_handle()?;
@@ -1190,30 +1564,41 @@ fn compile_expression(
             function_frame.pop()?;
             let inner_token = Box::new(InferredType::from_signature_token(&field_type));
             if is_mutable {
-                push_instr!(exp.span, Bytecode::MutBorrowField(fd_idx));
+                push_instr!(loc, Bytecode::MutBorrowField(fd_idx));
                 function_frame.push()?;
                 vec_deque![InferredType::MutableReference(inner_token)]
             } else {
-                push_instr!(exp.span, Bytecode::ImmBorrowField(fd_idx));
+                push_instr!(loc, Bytecode::ImmBorrowField(fd_idx));
                 function_frame.push()?;
                 vec_deque![InferredType::Reference(inner_token)]
             }
         }
-        Exp_::FunctionCall(f, exps) => {
-            let mut actuals_tys = vec_deque![];
-            for types in compile_expression(context, function_frame, code, *exps)? {
-                actuals_tys.push_back(types);
+        Pending::Pack(def_idx, type_actuals_id, sh_idx, num_fields) => {
+            for _ in 0..num_fields {
+                operand()?;
+            }
+            push_instr!(loc, Bytecode::Pack(def_idx, type_actuals_id));
+            for _ in 0..num_fields {
+                function_frame.pop()?;
             }
+            function_frame.push()?;
+
+            vec_deque![InferredType::Struct(sh_idx)]
+        }
+        Pending::Call(f) => {
+            let actuals_tys = operand()?;
             compile_call(context, function_frame, code, f, actuals_tys)?
         }
-        Exp_::ExprList(exps) => {
+        Pending::List(len) => {
             let mut result = vec_deque![];
-            for e in exps {
-                result.append(&mut compile_expression(context, function_frame, code, e)?);
+            for tys in results.drain(results.len() - len..) {
+                result.extend(tys);
             }
             result
         }
-    })
+    };
+    results.push(result);
+    Ok(())
 }
 
 fn compile_call(
@@ -1312,7 +1697,14 @@ fn compile_call(
                     vec_deque![InferredType::U8]
                 }
                 Builtin::ToU64 => {
-                    push_instr!(call.span, Bytecode::CastU64);
+                    // A `u64` is cast to itself without the instruction on targets lacking it
+                    let is_u64 = match argument_types.front() {
+                        Some(InferredType::U64) => true,
+                        _ => false,
+                    };
+                    if !is_u64 || context.supports(&Bytecode::CastU64) {
+                        push_instr!(call.span, Bytecode::CastU64);
+                    }
                     function_frame.pop()?;
                     function_frame.push()?;
                     vec_deque![InferredType::U64]
@@ -1323,6 +1715,39 @@ fn compile_call(
                     function_frame.push()?;
                     vec_deque![InferredType::U128]
                 }
+                Builtin::ToBytes(ty) => {
+                    let tokens = LocalsSignature(compile_types(context, &[ty])?);
+                    let type_actuals_id = context.locals_signature_index(tokens)?;
+                    let fh_idx = library_function(context, LCS_MODULE, LCS_SERIALIZE)?;
+                    push_instr!(call.span, Bytecode::Call(fh_idx, type_actuals_id));
+                    function_frame.pop()?;
+                    function_frame.push()?;
+                    vec_deque![InferredType::ByteArray]
+                }
+                Builtin::Sha2_256 | Builtin::Sha3_256 => {
+                    let type_actuals_id =
+                        context.locals_signature_index(LocalsSignature(vec![]))?;
+                    let name = function.to_string();
+                    let fh_idx = library_function(context, HASH_MODULE, &name)?;
+                    push_instr!(call.span, Bytecode::Call(fh_idx, type_actuals_id));
+                    function_frame.pop()?;
+                    function_frame.push()?;
+                    vec_deque![InferredType::ByteArray]
+                }
+                Builtin::Rotl
+                | Builtin::Rotr
+                | Builtin::CountOnes
+                | Builtin::CheckedAdd
+                | Builtin::CheckedSub
+                | Builtin::SaturatingAdd
+                | Builtin::SaturatingSub => compile_integer_builtin(
+                    context,
+                    function_frame,
+                    code,
+                    call.span,
+                    &function,
+                    argument_types,
+                )?,
             }
         }
         FunctionCall_::ModuleFunctionCall {
@@ -1349,3 +1774,389 @@ fn compile_call(
         }
     })
 }
+
+// Returns the handle of the function `name` of the module `module` of the standard library, for the
+// builtins compiled to calls of library functions.
+fn library_function(
+    context: &mut Context,
+    module: &str,
+    name: &str,
+) -> Result<FunctionHandleIndex> {
+    let module = context.library_module_alias(QualifiedModuleIdent::new(
+        ModuleName::new(Identifier::new(module)?),
+        account_config::core_code_address(),
+    ))?;
+    let name = FunctionName::new(Identifier::new(name)?);
+    Ok(context.function_handle(module, name)?.1)
+}
+
+// Returns the type of the events `emit` emits. It is the declared type of the local the event is
+// moved or copied out of, or the type of the struct it packs, or else the type inferred for the
+// event, which tells neither the type actuals of a generic struct nor which type parameter a
+// type parameter is.
+fn event_type(
+    context: &mut Context,
+    function_frame: &FunctionFrame,
+    event: &Exp_,
+    tys: &VecDeque<InferredType>,
+) -> Result<SignatureToken> {
+    match event {
+        Exp_::Move(var) | Exp_::Copy(var) => {
+            let idx = function_frame.get_local(&var.value)?;
+            return Ok(function_frame.get_local_type(idx)?.clone());
+        }
+        Exp_::Pack(name, tys, _) => {
+            let ident = QualifiedStructIdent {
+                module: ModuleName::module_self(),
+                name: name.clone(),
+            };
+            return compile_type(context, &Type::Struct(ident, tys.clone()));
+        }
+        _ => (),
+    }
+    let ty = match tys.iter().collect::<Vec<_>>().as_slice() {
+        [ty] => *ty,
+        _ => bail!("`emit` expects a single event, found {} values", tys.len()),
+    };
+    Ok(match ty {
+        InferredType::Bool => SignatureToken::Bool,
+        InferredType::U8 => SignatureToken::U8,
+        InferredType::U64 => SignatureToken::U64,
+        InferredType::U128 => SignatureToken::U128,
+        InferredType::ByteArray => SignatureToken::ByteArray,
+        InferredType::Address => SignatureToken::Address,
+        InferredType::Struct(idx) if context.struct_handle_type_formals(*idx)?.is_empty() => {
+            SignatureToken::Struct(*idx, vec![])
+        }
+        _ => bail!("Cannot infer the type of the event of `emit`, move it out of a local instead"),
+    })
+}
+
+// Compiles the bit manipulation and overflow handling builtins into sequences of arithmetic and
+// bitwise instructions, with temporaries for the values they read more than once. The arguments
+// are on the stack. Branches are not an option, as the stack must be balanced at the end of each
+// block and the builtin may be called with other values on the stack.
+fn compile_integer_builtin(
+    context: &mut Context,
+    function_frame: &mut FunctionFrame,
+    code: &mut Vec<Bytecode>,
+    loc: Loc,
+    builtin: &Builtin,
+    mut argument_types: VecDeque<InferredType>,
+) -> Result<VecDeque<InferredType>> {
+    let (ty, inferred, bits) = match argument_types.pop_front() {
+        Some(InferredType::U8) => (SignatureToken::U8, InferredType::U8, 8),
+        Some(InferredType::U64) => (SignatureToken::U64, InferredType::U64, 64),
+        Some(InferredType::U128) => (SignatureToken::U128, InferredType::U128, 128),
+        _ => bail!("`{}` expects an integer argument", builtin),
+    };
+    let ones = u128::max_value() >> (128 - bits);
+    let constant = |value: u128| match bits {
+        8 => Bytecode::LdU8(value as u8),
+        64 => Bytecode::LdU64(value as u64),
+        _ => Bytecode::LdU128(value & ones),
+    };
+    let x = temp(context, function_frame, ty.clone(), 0, loc)?;
+    let mut instrs = vec![];
+    let result = match builtin {
+        Builtin::Rotl | Builtin::Rotr => {
+            let (forward, backward) = match builtin {
+                Builtin::Rotl => (Bytecode::Shl, Bytecode::Shr),
+                _ => (Bytecode::Shr, Bytecode::Shl),
+            };
+            // With m = n % bits, x rotated left is (x << m) | ((x >> (bits - 1 - m)) >> 1), as a
+            // shift by `bits` or more aborts.
+            let m = temp(context, function_frame, SignatureToken::U8, 1, loc)?;
+            instrs.extend(vec![
+                Bytecode::LdU8(bits as u8),
+                Bytecode::Mod,
+                Bytecode::StLoc(m),
+                Bytecode::StLoc(x),
+                Bytecode::CopyLoc(x),
+                Bytecode::CopyLoc(m),
+                forward,
+                Bytecode::MoveLoc(x),
+                Bytecode::LdU8((bits - 1) as u8),
+                Bytecode::MoveLoc(m),
+                Bytecode::Sub,
+                backward.clone(),
+                Bytecode::LdU8(1),
+                backward,
+                Bytecode::BitOr,
+            ]);
+            vec_deque![inferred]
+        }
+        Builtin::CheckedAdd
+        | Builtin::SaturatingAdd
+        | Builtin::CheckedSub
+        | Builtin::SaturatingSub => {
+            // Adds or subtracts the low and the high halves separately, so that no step overflows,
+            // carrying from the low half into the high one. What is carried out of the high half
+            // tells whether the result fits.
+            let half = bits / 2;
+            let low = constant(ones >> half);
+            let y = temp(context, function_frame, ty.clone(), 1, loc)?;
+            let lo = temp(context, function_frame, ty.clone(), 2, loc)?;
+            let hi = temp(context, function_frame, ty, 3, loc)?;
+            let add = match builtin {
+                Builtin::CheckedAdd | Builtin::SaturatingAdd => true,
+                _ => false,
+            };
+            instrs.extend(vec![Bytecode::StLoc(y), Bytecode::StLoc(x)]);
+            if add {
+                instrs.extend(vec![
+                    Bytecode::CopyLoc(x),
+                    low.clone(),
+                    Bytecode::BitAnd,
+                    Bytecode::CopyLoc(y),
+                    low.clone(),
+                    Bytecode::BitAnd,
+                    Bytecode::Add,
+                    Bytecode::StLoc(lo),
+                    Bytecode::MoveLoc(x),
+                    Bytecode::LdU8(half as u8),
+                    Bytecode::Shr,
+                    Bytecode::MoveLoc(y),
+                    Bytecode::LdU8(half as u8),
+                    Bytecode::Shr,
+                    Bytecode::Add,
+                    Bytecode::CopyLoc(lo),
+                    Bytecode::LdU8(half as u8),
+                    Bytecode::Shr,
+                    Bytecode::Add,
+                ]);
+            } else {
+                // Borrows 2^half for each half, which is left carried out if nothing was borrowed.
+                instrs.extend(vec![
+                    Bytecode::CopyLoc(x),
+                    low.clone(),
+                    Bytecode::BitAnd,
+                    constant(1 << half),
+                    Bytecode::Add,
+                    Bytecode::CopyLoc(y),
+                    low.clone(),
+                    Bytecode::BitAnd,
+                    Bytecode::Sub,
+                    Bytecode::StLoc(lo),
+                    Bytecode::MoveLoc(x),
+                    Bytecode::LdU8(half as u8),
+                    Bytecode::Shr,
+                    constant(1 << half),
+                    Bytecode::Add,
+                    Bytecode::CopyLoc(lo),
+                    Bytecode::LdU8(half as u8),
+                    Bytecode::Shr,
+                    Bytecode::Add,
+                    constant(1),
+                    Bytecode::Sub,
+                    Bytecode::MoveLoc(y),
+                    Bytecode::LdU8(half as u8),
+                    Bytecode::Shr,
+                    Bytecode::Sub,
+                ]);
+            }
+            // The carry out of the high half is left in x, 1 on overflow for an addition and 1
+            // without underflow for a subtraction, and the wrapped result on the stack.
+            instrs.extend(vec![
+                Bytecode::StLoc(hi),
+                Bytecode::CopyLoc(hi),
+                Bytecode::LdU8(half as u8),
+                Bytecode::Shr,
+                Bytecode::StLoc(x),
+                Bytecode::MoveLoc(hi),
+                low.clone(),
+                Bytecode::BitAnd,
+                Bytecode::LdU8(half as u8),
+                Bytecode::Shl,
+                Bytecode::MoveLoc(lo),
+                low,
+                Bytecode::BitAnd,
+                Bytecode::BitOr,
+            ]);
+            match builtin {
+                Builtin::CheckedAdd => instrs.extend(vec![
+                    constant(1),
+                    Bytecode::CopyLoc(x),
+                    Bytecode::Sub,
+                    Bytecode::Mul,
+                    Bytecode::MoveLoc(x),
+                    constant(0),
+                    Bytecode::Eq,
+                ]),
+                Builtin::SaturatingAdd => instrs.extend(vec![
+                    constant(ones),
+                    Bytecode::MoveLoc(x),
+                    Bytecode::Mul,
+                    Bytecode::BitOr,
+                ]),
+                Builtin::CheckedSub => instrs.extend(vec![
+                    Bytecode::CopyLoc(x),
+                    Bytecode::Mul,
+                    Bytecode::MoveLoc(x),
+                    constant(1),
+                    Bytecode::Eq,
+                ]),
+                _ => instrs.extend(vec![Bytecode::MoveLoc(x), Bytecode::Mul]),
+            }
+            match builtin {
+                Builtin::CheckedAdd | Builtin::CheckedSub => {
+                    vec_deque![inferred, InferredType::Bool]
+                }
+                _ => vec_deque![inferred],
+            }
+        }
+        _ => {
+            // Sums the bits of each pair, nibble, byte, and then of all the bytes. The sums never
+            // overflow, unlike multiplying the bytes by 0x0101...
+            instrs.extend(vec![
+                Bytecode::StLoc(x),
+                Bytecode::CopyLoc(x),
+                Bytecode::CopyLoc(x),
+                Bytecode::LdU8(1),
+                Bytecode::Shr,
+                constant(ones / 3),
+                Bytecode::BitAnd,
+                Bytecode::Sub,
+                Bytecode::StLoc(x),
+                Bytecode::CopyLoc(x),
+                constant(ones / 5),
+                Bytecode::BitAnd,
+                Bytecode::CopyLoc(x),
+                Bytecode::LdU8(2),
+                Bytecode::Shr,
+                constant(ones / 5),
+                Bytecode::BitAnd,
+                Bytecode::Add,
+                Bytecode::StLoc(x),
+                Bytecode::CopyLoc(x),
+                Bytecode::CopyLoc(x),
+                Bytecode::LdU8(4),
+                Bytecode::Shr,
+                Bytecode::Add,
+                constant(ones / 17),
+                Bytecode::BitAnd,
+            ]);
+            let mut shift = 8;
+            while shift < bits {
+                instrs.extend(vec![
+                    Bytecode::StLoc(x),
+                    Bytecode::CopyLoc(x),
+                    Bytecode::CopyLoc(x),
+                    Bytecode::LdU8(shift as u8),
+                    Bytecode::Shr,
+                    Bytecode::Add,
+                ]);
+                shift *= 2;
+            }
+            if bits > 8 {
+                instrs.extend(vec![constant(0xff), Bytecode::BitAnd]);
+            }
+            instrs.push(Bytecode::CastU64);
+            vec_deque![InferredType::U64]
+        }
+    };
+    if bits == 64 && !context.supports(&Bytecode::Shr) {
+        instrs = lower_u64_instrs(instrs);
+    }
+
+    make_push_instr!(context, code);
+    for instr in instrs {
+        match instr {
+            Bytecode::LdU8(_)
+            | Bytecode::LdU64(_)
+            | Bytecode::LdU128(_)
+            | Bytecode::CopyLoc(_)
+            | Bytecode::MoveLoc(_) => function_frame.push()?,
+            Bytecode::CastU64 => (),
+            _ => function_frame.pop()?,
+        }
+        push_instr!(loc, instr);
+    }
+    Ok(result)
+}
+
+// The instructions computing the shift `op` of a `u64` by the constant `amount` when the target
+// version lacks the shifts, or `None` if the shift is compiled as is. Only the first operand is
+// compiled at this point, and the `u8` amount is then never compiled.
+fn shift_fallback(
+    context: &Context,
+    op: &BinOp,
+    operand_types: &VecDeque<InferredType>,
+    amount: &Exp,
+) -> Option<Vec<Bytecode>> {
+    let shift = match op {
+        BinOp::Shl => Bytecode::Shl,
+        BinOp::Shr => Bytecode::Shr,
+        _ => return None,
+    };
+    if context.supports(&shift) {
+        return None;
+    }
+    match (operand_types.front(), &amount.value) {
+        (Some(InferredType::U64), Exp_::Value(value)) => match value.value {
+            CopyableVal_::U8(amount) => lower_u64_shift(&shift, amount),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Lowers the shift `shift` of a `u64` by `amount` to arithmetic: x >> k is x / 2^k, and x << k is
+// (x % 2^(64 - k)) * 2^k, which drops the bits shifted out before they could overflow. A shift by
+// 64 or more aborts, which arithmetic does not, so it is not lowered.
+fn lower_u64_shift(shift: &Bytecode, amount: u8) -> Option<Vec<Bytecode>> {
+    if amount >= 64 {
+        return None;
+    }
+    match shift {
+        Bytecode::Shr => Some(vec![Bytecode::LdU64(1 << amount), Bytecode::Div]),
+        Bytecode::Shl if amount == 0 => Some(vec![]),
+        Bytecode::Shl => Some(vec![
+            Bytecode::LdU64(1 << (64 - amount)),
+            Bytecode::Mod,
+            Bytecode::LdU64(1 << amount),
+            Bytecode::Mul,
+        ]),
+        _ => None,
+    }
+}
+
+// Lowers the shifts by constants and the casts to `u64` an integer builtin over `u64` values
+// compiles to, for a target lacking them. The shifts by a variable of `rotl` and `rotr` are kept.
+fn lower_u64_instrs(instrs: Vec<Bytecode>) -> Vec<Bytecode> {
+    let mut lowered = vec![];
+    let mut i = 0;
+    while i < instrs.len() {
+        if let (Bytecode::LdU8(amount), Some(shift)) = (&instrs[i], instrs.get(i + 1)) {
+            if let Some(shift_instrs) = lower_u64_shift(shift, *amount) {
+                lowered.extend(shift_instrs);
+                i += 2;
+                continue;
+            }
+        }
+        match &instrs[i] {
+            Bytecode::CastU64 => (),
+            instr => lowered.push(instr.clone()),
+        }
+        i += 1;
+    }
+    lowered
+}
+
+// Returns the `nth` temporary of type `type_`, naming it in the source map when it is defined.
+fn temp(
+    context: &mut Context,
+    function_frame: &mut FunctionFrame,
+    type_: SignatureToken,
+    nth: usize,
+    loc: Loc,
+) -> Result<u8> {
+    let (idx, defined) = function_frame.get_temp(type_, nth)?;
+    if defined {
+        let name = Identifier::new(format!("__tmp{}", idx))?;
+        context
+            .source_map
+            .add_local_mapping(context.current_function_definition_index(), (name, loc))?;
+    }
+    Ok(idx)
+}
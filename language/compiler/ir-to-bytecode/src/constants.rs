@@ -0,0 +1,608 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Constant folding and propagation.
+//!
+//! An operator whose operands are literals is folded into the literal it evaluates to, unless it
+//! would abort, as an overflow or a division by zero does. As `&&` and `||` evaluate both of their
+//! operands, they are only folded when both are literals too.
+//!
+//! A local assigned once in the whole function, to an expression folding to a literal, always
+//! holds that literal: its uses reached by the assignment on every path are replaced by it, and
+//! folded in turn. The local and its assignment are then removed if it is no longer used. Locals
+//! that are borrowed anywhere in the function are left alone, and so are byte array literals, to
+//! keep from copying them into every use.
+//!
+//! An `if` whose condition folds to a literal is replaced by the branch taken, and a `while` whose
+//! condition folds to `false` is removed. Code that is unreachable from the entry of the body is
+//! left as written.
+
+use crate::{
+    dataflow::LoopStack,
+    locals::{exp_vars, LocalUse},
+};
+use move_ir_types::ast::{
+    BinOp, Block_, Cmd_, CopyableVal_, Exp, Exp_, Function, FunctionBody, LValue, LValue_, Loc,
+    ModuleDefinition, Program, Script, Spanned, Statement, UnaryOp, Var_,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt, mem,
+};
+
+/// A branch whose condition always has the same value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FoldedBranch {
+    /// The location of the condition.
+    pub loc: Loc,
+    pub kind: BranchKind,
+    /// The value of the condition.
+    pub value: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BranchKind {
+    /// An `if` without an `else` branch.
+    If,
+    /// An `if` with an `else` branch.
+    IfElse,
+    /// A `while`, which is only folded when its condition is `false`.
+    While,
+}
+
+impl fmt::Display for FoldedBranch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "condition is always {}, ", self.value)?;
+        match (self.kind, self.value) {
+            (BranchKind::If, true) | (BranchKind::IfElse, true) => {
+                write!(f, "the `if` is replaced by its first branch")
+            }
+            (BranchKind::IfElse, false) => write!(f, "the `if` is replaced by its `else` branch"),
+            (BranchKind::If, false) => write!(f, "the `if` is removed"),
+            (BranchKind::While, _) => write!(f, "the `while` is removed"),
+        }
+    }
+}
+
+/// Folds and propagates the constants of every module and then of the script of a program.
+pub fn fold_program(program: &mut Program) -> Vec<FoldedBranch> {
+    let mut branches = vec![];
+    for module in &mut program.modules {
+        branches.extend(fold_module(module));
+    }
+    branches.extend(fold_script(&mut program.script));
+    branches
+}
+
+/// Folds and propagates the constants of the functions of a module, returning the folded
+/// branches in source order.
+pub fn fold_module(module: &mut ModuleDefinition) -> Vec<FoldedBranch> {
+    let mut branches = vec![];
+    for (_, function) in &mut module.functions {
+        branches.extend(fold_function(function));
+    }
+    branches
+}
+
+/// Folds and propagates the constants of the `main` function of a script, returning the folded
+/// branches in source order.
+pub fn fold_script(script: &mut Script) -> Vec<FoldedBranch> {
+    fold_function(&mut script.main)
+}
+
+fn fold_function(function: &mut Function) -> Vec<FoldedBranch> {
+    let formals = &function.value.signature.formals;
+    let (locals, code) = match &mut function.value.body {
+        FunctionBody::Move { locals, code } => (locals, code),
+        FunctionBody::Native => return vec![],
+    };
+    let mut assignments = Assignments::default();
+    assignments.block(code);
+    let candidates: BTreeSet<Var_> = locals
+        .iter()
+        .map(|(var, _)| &var.value)
+        .filter(|var| {
+            assignments.counts.get(*var) == Some(&1)
+                && assignments.single.contains(*var)
+                && !assignments.borrowed.contains(*var)
+                && formals.iter().all(|(formal, _)| formal.value != **var)
+        })
+        .cloned()
+        .collect();
+
+    let mut folder = Folder {
+        candidates: &candidates,
+        values: BTreeMap::new(),
+        loops: LoopStack::default(),
+        branches: vec![],
+    };
+    folder.block(code, Some(Known::new()));
+
+    let mut read = BTreeSet::new();
+    block_exps(code, &mut |e| exp_reads(e, &mut read));
+    let unused: BTreeSet<Var_> = folder
+        .values
+        .keys()
+        .filter(|var| !read.contains(*var))
+        .cloned()
+        .collect();
+    remove_assignments(code, &unused);
+    locals.retain(|(var, _)| !unused.contains(&var.value));
+
+    let mut branches = folder.branches;
+    branches.sort_by_key(|branch| branch.loc.start());
+    branches
+}
+
+/// The assignments of the locals of a function.
+#[derive(Default)]
+struct Assignments {
+    /// The number of places each local is assigned in.
+    counts: BTreeMap<Var_, usize>,
+    /// The locals assigned alone by an assignment.
+    single: BTreeSet<Var_>,
+    borrowed: BTreeSet<Var_>,
+}
+
+impl Assignments {
+    fn block(&mut self, block: &mut Block_) {
+        block_exps(block, &mut |e| exp_borrows(e, &mut self.borrowed));
+        block_lvalues(block, &mut |lvalues, single| {
+            for var in lvalues {
+                *self.counts.entry(var.clone()).or_insert(0) += 1;
+                if single {
+                    self.single.insert(var.clone());
+                }
+            }
+        });
+    }
+}
+
+/// The locals with a constant value that are assigned at a program point on every path to it.
+type Known = BTreeSet<Var_>;
+
+fn join(known: Option<Known>, other: Option<Known>) -> Option<Known> {
+    match (known, other) {
+        (Some(known), Some(other)) => Some(known.intersection(&other).cloned().collect()),
+        (known, None) => known,
+        (None, other) => other,
+    }
+}
+
+struct Folder<'a> {
+    /// The locals assigned once, to an expression that may fold to a literal.
+    candidates: &'a BTreeSet<Var_>,
+    /// The literal each candidate is assigned, once its assignment has been folded.
+    values: BTreeMap<Var_, CopyableVal_>,
+    /// The known locals at the `break`s of each enclosing loop.
+    loops: LoopStack<Option<Known>>,
+    branches: Vec<FoldedBranch>,
+}
+
+impl<'a> Folder<'a> {
+    // Each of the functions below takes the known locals before a piece of code, none if it is
+    // unreachable, and returns the known locals after it. As the candidates are assigned once,
+    // the known locals never shrink along a path, and a loop is entered with the same known
+    // locals on every iteration as on the first one.
+
+    fn block(&mut self, block: &mut Block_, known: Option<Known>) -> Option<Known> {
+        let stmts = mem::replace(&mut block.stmts, VecDeque::new());
+        let mut known = known;
+        for statement in stmts {
+            match known.take() {
+                Some(current) => known = self.statement(statement, current, &mut block.stmts),
+                None => block.stmts.push_back(statement),
+            }
+        }
+        known
+    }
+
+    /// Folds a statement, appending what it becomes to `stmts`.
+    fn statement(
+        &mut self,
+        statement: Statement,
+        known: Known,
+        stmts: &mut VecDeque<Statement>,
+    ) -> Option<Known> {
+        match statement {
+            Statement::CommandStatement(mut cmd) => {
+                let known = self.cmd(&mut cmd.value, known);
+                stmts.push_back(Statement::CommandStatement(cmd));
+                known
+            }
+            Statement::IfElseStatement(mut if_else) => {
+                self.exp(&mut if_else.cond, &known);
+                if let Some(value) = bool_value(&if_else.cond) {
+                    self.branches.push(FoldedBranch {
+                        loc: if_else.cond.span,
+                        kind: if if_else.else_block.is_some() {
+                            BranchKind::IfElse
+                        } else {
+                            BranchKind::If
+                        },
+                        value,
+                    });
+                    let taken = if value {
+                        Some(if_else.if_block)
+                    } else {
+                        if_else.else_block
+                    };
+                    return match taken {
+                        Some(mut block) => {
+                            let after = self.block(&mut block.value, Some(known));
+                            stmts.extend(block.value.stmts);
+                            after
+                        }
+                        None => Some(known),
+                    };
+                }
+                let mut after = self.block(&mut if_else.if_block.value, Some(known.clone()));
+                let else_after = match &mut if_else.else_block {
+                    Some(else_block) => self.block(&mut else_block.value, Some(known)),
+                    None => Some(known),
+                };
+                after = join(after, else_after);
+                stmts.push_back(Statement::IfElseStatement(if_else));
+                after
+            }
+            Statement::WhileStatement(mut while_) => {
+                self.exp(&mut while_.cond, &known);
+                if bool_value(&while_.cond) == Some(false) {
+                    self.branches.push(FoldedBranch {
+                        loc: while_.cond.span,
+                        kind: BranchKind::While,
+                        value: false,
+                    });
+                    return Some(known);
+                }
+                self.loops.enter(None);
+                self.block(&mut while_.block.value, Some(known.clone()));
+                self.loops.leave();
+                stmts.push_back(Statement::WhileStatement(while_));
+                // The locals assigned by the body are not known when the condition is false on
+                // the first iteration, nor are they assigned by a `break` of it.
+                Some(known)
+            }
+            Statement::LoopStatement(mut loop_) => {
+                self.loops.enter(None);
+                self.block(&mut loop_.block.value, Some(known));
+                let after = self.loops.leave();
+                stmts.push_back(Statement::LoopStatement(loop_));
+                after
+            }
+            Statement::EmptyStatement => {
+                stmts.push_back(Statement::EmptyStatement);
+                Some(known)
+            }
+        }
+    }
+
+    fn cmd(&mut self, cmd: &mut Cmd_, mut known: Known) -> Option<Known> {
+        match cmd {
+            Cmd_::Assign(lvalues, e) => {
+                self.lvalues(lvalues, &known);
+                self.exp(e, &known);
+                if let ([lvalue], Exp_::Value(value)) = (lvalues.as_slice(), &e.value) {
+                    if let LValue_::Var(var) = &lvalue.value {
+                        let is_byte_array = match &value.value {
+                            CopyableVal_::ByteArray(_) => true,
+                            _ => false,
+                        };
+                        if self.candidates.contains(&var.value) && !is_byte_array {
+                            self.values.insert(var.value.clone(), value.value.clone());
+                            known.insert(var.value.clone());
+                        }
+                    }
+                }
+                Some(known)
+            }
+            Cmd_::Unpack(_, _, _, e) | Cmd_::Exp(e) => {
+                self.exp(e, &known);
+                Some(known)
+            }
+            Cmd_::Emit(handle, e) => {
+                self.exp(handle, &known);
+                self.exp(e, &known);
+                Some(known)
+            }
+            Cmd_::Return(e) => {
+                self.exp(e, &known);
+                None
+            }
+            Cmd_::Abort(e_opt) => {
+                if let Some(e) = e_opt {
+                    self.exp(e, &known);
+                }
+                None
+            }
+            Cmd_::Break(e_opt) => {
+                if let Some(e) = e_opt {
+                    self.exp(e, &known);
+                }
+                if let Some(breaks) = self.loops.innermost_mut() {
+                    *breaks = join(breaks.take(), Some(known));
+                }
+                None
+            }
+            Cmd_::Continue => None,
+        }
+    }
+
+    fn lvalues(&mut self, lvalues: &mut [LValue], known: &Known) {
+        for lvalue in lvalues {
+            if let LValue_::Mutate(e) = &mut lvalue.value {
+                self.exp(e, known);
+            }
+        }
+    }
+
+    /// Replaces the known locals of an expression with their value, and folds it.
+    fn exp(&mut self, e: &mut Exp, known: &Known) {
+        let folded = match &mut e.value {
+            Exp_::Move(var) | Exp_::Copy(var) | Exp_::Var(var) => {
+                if known.contains(&var.value) {
+                    Some(self.values[&var.value].clone())
+                } else {
+                    None
+                }
+            }
+            Exp_::Dereference(inner) | Exp_::Borrow { exp: inner, .. } => {
+                self.exp(inner, known);
+                None
+            }
+            Exp_::UnaryExp(UnaryOp::Not, inner) => {
+                self.exp(inner, known);
+                match &inner.value {
+                    Exp_::Value(Spanned {
+                        value: CopyableVal_::Bool(value),
+                        ..
+                    }) => Some(CopyableVal_::Bool(!*value)),
+                    _ => None,
+                }
+            }
+            Exp_::BinopExp(e1, op, e2) => {
+                self.exp(e1, known);
+                self.exp(e2, known);
+                match (&e1.value, &e2.value) {
+                    (Exp_::Value(v1), Exp_::Value(v2)) => binop(op, &v1.value, &v2.value),
+                    _ => None,
+                }
+            }
+            Exp_::Pack(_, _, fields) => {
+                for (_, e) in fields {
+                    self.exp(e, known);
+                }
+                None
+            }
+            Exp_::FunctionCall(_, e) => {
+                self.exp(e, known);
+                None
+            }
+            Exp_::ExprList(exps) => {
+                for e in exps {
+                    self.exp(e, known);
+                }
+                None
+            }
+            Exp_::BorrowLocal(_, _) | Exp_::Value(_) | Exp_::Placeholder(_) => None,
+        };
+        if let Some(value) = folded {
+            e.value = Exp_::Value(Spanned {
+                span: e.span,
+                value,
+            });
+        }
+    }
+}
+
+pub(crate) fn bool_value(e: &Exp) -> Option<bool> {
+    match &e.value {
+        Exp_::Value(Spanned {
+            value: CopyableVal_::Bool(value),
+            ..
+        }) => Some(*value),
+        _ => None,
+    }
+}
+
+/// Evaluates a binary operator over literals, or returns none if it aborts or if the operands
+/// are of types it does not apply to.
+fn binop(op: &BinOp, v1: &CopyableVal_, v2: &CopyableVal_) -> Option<CopyableVal_> {
+    use CopyableVal_::*;
+    match (op, v1, v2) {
+        (BinOp::Eq, _, _) | (BinOp::Neq, _, _) => {
+            if mem::discriminant(v1) != mem::discriminant(v2) {
+                return None;
+            }
+            Some(Bool((v1 == v2) == (*op == BinOp::Eq)))
+        }
+        (BinOp::And, Bool(b1), Bool(b2)) => Some(Bool(*b1 && *b2)),
+        (BinOp::Or, Bool(b1), Bool(b2)) => Some(Bool(*b1 || *b2)),
+        (BinOp::Shl, _, U8(n)) | (BinOp::Shr, _, U8(n)) => {
+            let (i, bits) = integer(v1)?;
+            if u32::from(*n) >= bits {
+                return None;
+            }
+            let shifted = if *op == BinOp::Shl { i << *n } else { i >> *n };
+            Some(with_bits(shifted & max_value(bits), bits))
+        }
+        _ => {
+            let (i1, bits) = integer(v1)?;
+            let (i2, bits2) = integer(v2)?;
+            if bits != bits2 {
+                return None;
+            }
+            let value = match op {
+                BinOp::Add => i1.checked_add(i2)?,
+                BinOp::Sub => i1.checked_sub(i2)?,
+                BinOp::Mul => i1.checked_mul(i2)?,
+                BinOp::Div => i1.checked_div(i2)?,
+                BinOp::Mod => i1.checked_rem(i2)?,
+                BinOp::BitOr => i1 | i2,
+                BinOp::BitAnd => i1 & i2,
+                BinOp::Xor => i1 ^ i2,
+                BinOp::Lt => return Some(Bool(i1 < i2)),
+                BinOp::Gt => return Some(Bool(i1 > i2)),
+                BinOp::Le => return Some(Bool(i1 <= i2)),
+                BinOp::Ge => return Some(Bool(i1 >= i2)),
+                _ => return None,
+            };
+            if value > max_value(bits) {
+                return None;
+            }
+            Some(with_bits(value, bits))
+        }
+    }
+}
+
+/// Returns an integer literal with its number of bits.
+fn integer(value: &CopyableVal_) -> Option<(u128, u32)> {
+    match value {
+        CopyableVal_::U8(i) => Some((u128::from(*i), 8)),
+        CopyableVal_::U64(i) => Some((u128::from(*i), 64)),
+        CopyableVal_::U128(i) => Some((*i, 128)),
+        _ => None,
+    }
+}
+
+fn max_value(bits: u32) -> u128 {
+    u128::max_value() >> (128 - bits)
+}
+
+fn with_bits(value: u128, bits: u32) -> CopyableVal_ {
+    match bits {
+        8 => CopyableVal_::U8(value as u8),
+        64 => CopyableVal_::U64(value as u64),
+        _ => CopyableVal_::U128(value),
+    }
+}
+
+/// Calls `f` on each expression of a block, including the conditions of its branches and the
+/// expressions of the references it assigns through.
+fn block_exps(block: &mut Block_, f: &mut dyn FnMut(&mut Exp)) {
+    for statement in &mut block.stmts {
+        match statement {
+            Statement::CommandStatement(cmd) => match &mut cmd.value {
+                Cmd_::Assign(lvalues, e) => {
+                    lvalues_exps(lvalues, f);
+                    f(e);
+                }
+                Cmd_::Unpack(_, _, _, e)
+                | Cmd_::Return(e)
+                | Cmd_::Exp(e)
+                | Cmd_::Abort(Some(e))
+                | Cmd_::Break(Some(e)) => f(e),
+                Cmd_::Emit(handle, e) => {
+                    f(handle);
+                    f(e);
+                }
+                Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+            },
+            Statement::IfElseStatement(if_else) => {
+                f(&mut if_else.cond);
+                block_exps(&mut if_else.if_block.value, f);
+                if let Some(else_block) = &mut if_else.else_block {
+                    block_exps(&mut else_block.value, f);
+                }
+            }
+            Statement::WhileStatement(while_) => {
+                f(&mut while_.cond);
+                block_exps(&mut while_.block.value, f);
+            }
+            Statement::LoopStatement(loop_) => {
+                lvalues_exps(&mut loop_.results, f);
+                block_exps(&mut loop_.block.value, f);
+            }
+            Statement::EmptyStatement => (),
+        }
+    }
+}
+
+fn lvalues_exps(lvalues: &mut [LValue], f: &mut dyn FnMut(&mut Exp)) {
+    for lvalue in lvalues {
+        if let LValue_::Mutate(e) = &mut lvalue.value {
+            f(e);
+        }
+    }
+}
+
+/// Calls `f` with the locals each assignment, unpack or loop of a block assigns, and with whether
+/// they are assigned alone by an assignment.
+fn block_lvalues(block: &Block_, f: &mut dyn FnMut(Vec<&Var_>, bool)) {
+    fn vars(lvalues: &[LValue]) -> Vec<&Var_> {
+        lvalues
+            .iter()
+            .filter_map(|lvalue| match &lvalue.value {
+                LValue_::Var(var) => Some(&var.value),
+                LValue_::Mutate(_) | LValue_::Pop => None,
+            })
+            .collect()
+    }
+    for statement in &block.stmts {
+        match statement {
+            Statement::CommandStatement(cmd) => match &cmd.value {
+                Cmd_::Assign(lvalues, _) => f(vars(lvalues), lvalues.len() == 1),
+                Cmd_::Unpack(_, _, bindings, _) => {
+                    f(bindings.iter().map(|(_, var)| &var.value).collect(), false)
+                }
+                _ => (),
+            },
+            Statement::IfElseStatement(if_else) => {
+                block_lvalues(&if_else.if_block.value, f);
+                if let Some(else_block) = &if_else.else_block {
+                    block_lvalues(&else_block.value, f);
+                }
+            }
+            Statement::WhileStatement(while_) => block_lvalues(&while_.block.value, f),
+            Statement::LoopStatement(loop_) => {
+                f(vars(&loop_.results), false);
+                block_lvalues(&loop_.block.value, f);
+            }
+            Statement::EmptyStatement => (),
+        }
+    }
+}
+
+fn exp_reads(e: &Exp, read: &mut BTreeSet<Var_>) {
+    exp_vars(e, |var, _| {
+        read.insert(var.clone());
+    })
+}
+
+fn exp_borrows(e: &Exp, borrowed: &mut BTreeSet<Var_>) {
+    exp_vars(e, |var, use_| {
+        if use_ == LocalUse::Borrow {
+            borrowed.insert(var.clone());
+        }
+    })
+}
+
+/// Removes the assignments to the given locals, which only ever assign them a literal.
+fn remove_assignments(block: &mut Block_, vars: &BTreeSet<Var_>) {
+    block.stmts.retain(|statement| match statement {
+        Statement::CommandStatement(cmd) => match &cmd.value {
+            Cmd_::Assign(lvalues, _) => match lvalues.as_slice() {
+                [lvalue] => match &lvalue.value {
+                    LValue_::Var(var) => !vars.contains(&var.value),
+                    LValue_::Mutate(_) | LValue_::Pop => true,
+                },
+                _ => true,
+            },
+            _ => true,
+        },
+        _ => true,
+    });
+    for statement in &mut block.stmts {
+        match statement {
+            Statement::IfElseStatement(if_else) => {
+                remove_assignments(&mut if_else.if_block.value, vars);
+                if let Some(else_block) = &mut if_else.else_block {
+                    remove_assignments(&mut else_block.value, vars);
+                }
+            }
+            Statement::WhileStatement(while_) => remove_assignments(&mut while_.block.value, vars),
+            Statement::LoopStatement(loop_) => remove_assignments(&mut loop_.block.value, vars),
+            Statement::CommandStatement(_) | Statement::EmptyStatement => (),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::target::{instruction_version, LATEST_BYTECODE_VERSION};
 use anyhow::{bail, format_err, Result};
 use bytecode_source_map::source_map::ModuleSourceMap;
 use libra_types::{
@@ -13,11 +14,11 @@ use std::{clone::Clone, collections::HashMap, hash::Hash};
 use vm::{
     access::ModuleAccess,
     file_format::{
-        AddressPoolIndex, ByteArrayPoolIndex, FieldDefinitionIndex, FunctionDefinitionIndex,
-        FunctionHandle, FunctionHandleIndex, FunctionSignature, FunctionSignatureIndex,
-        IdentifierIndex, Kind, LocalsSignature, LocalsSignatureIndex, ModuleHandle,
-        ModuleHandleIndex, SignatureToken, StructDefinitionIndex, StructHandle, StructHandleIndex,
-        TableIndex, TypeSignature, TypeSignatureIndex,
+        AddressPoolIndex, ByteArrayPoolIndex, Bytecode, FieldDefinitionIndex,
+        FunctionDefinitionIndex, FunctionHandle, FunctionHandleIndex, FunctionSignature,
+        FunctionSignatureIndex, IdentifierIndex, Kind, LocalsSignature, LocalsSignatureIndex,
+        ModuleHandle, ModuleHandleIndex, SignatureToken, StructDefinitionIndex, StructHandle,
+        StructHandleIndex, TableIndex, TypeSignature, TypeSignatureIndex,
     },
 };
 
@@ -156,6 +157,10 @@ pub struct MaterializedPools {
 /// Contains all of the pools as they are built up.
 /// Specific definitions to CompiledModule or CompiledScript are not stored.
 /// However, some fields, like struct_defs and fields, are not used in CompiledScript.
+///
+/// The maps below are only ever used for lookups. Every pool entry records the index it was
+/// assigned when first added, and the pools are materialized by that index, so the compiled
+/// output never depends on hash map iteration order.
 pub struct Context<'a> {
     dependencies: HashMap<QualifiedModuleIdent, CompiledDependency<'a>>,
 
@@ -189,6 +194,9 @@ pub struct Context<'a> {
 
     // Source location mapping for this module
     pub source_map: ModuleSourceMap<Loc>,
+
+    // The version of the bytecode the code is generated for, the latest one by default
+    pub target_version: u64,
 }
 
 impl<'a> Context<'a> {
@@ -229,6 +237,7 @@ impl<'a> Context<'a> {
             type_formals: HashMap::new(),
             current_function_index: FunctionDefinitionIndex(0),
             source_map: ModuleSourceMap::new(current_module.clone()),
+            target_version: LATEST_BYTECODE_VERSION,
         };
         let self_name = ModuleName::new(ModuleName::self_name().into());
         context.declare_import(current_module, self_name)?;
@@ -236,6 +245,7 @@ impl<'a> Context<'a> {
         Ok(context)
     }
 
+    // Places every item at its recorded index, independent of the iteration order of `items`.
     fn materialize_pool<T: Clone>(
         size: usize,
         items: impl IntoIterator<Item = (T, TableIndex)>,
@@ -351,8 +361,8 @@ impl<'a> Context<'a> {
     }
 
     /// Get the byte array pool index, adds it if missing.
-    pub fn byte_array_index(&mut self, byte_array: &ByteArray) -> Result<ByteArrayPoolIndex> {
-        Ok(ByteArrayPoolIndex(get_or_add_item_ref(
+    pub fn byte_array_index(&mut self, byte_array: ByteArray) -> Result<ByteArrayPoolIndex> {
+        Ok(ByteArrayPoolIndex(get_or_add_item(
             &mut self.byte_array_pool,
             byte_array,
         )?))
@@ -403,6 +413,11 @@ impl<'a> Context<'a> {
         self.current_function_index = FunctionDefinitionIndex(index);
     }
 
+    /// Whether the target version of the bytecode has `instruction`.
+    pub fn supports(&self, instruction: &Bytecode) -> bool {
+        instruction_version(instruction) <= self.target_version
+    }
+
     pub fn current_function_definition_index(&self) -> FunctionDefinitionIndex {
         self.current_function_index
     }
@@ -434,6 +449,24 @@ impl<'a> Context<'a> {
         )?))
     }
 
+    /// Returns the alias of the module `id`, importing it under its own name if it is not
+    /// imported. Builtins compiled to calls of library functions use it to find their module.
+    pub fn library_module_alias(&mut self, id: QualifiedModuleIdent) -> Result<ModuleName> {
+        if let Some(alias) = self.aliases.get(&id) {
+            return Ok(alias.clone());
+        }
+        let alias = id.name.clone();
+        if self.modules.contains_key(&alias) {
+            bail!(
+                "Module {} is needed, but its name {} is the alias of another module",
+                id,
+                alias
+            )
+        }
+        self.declare_import(id, alias.clone())?;
+        Ok(alias)
+    }
+
     /// Given an identifier and basic "signature" information, creates a struct handle
     /// and adds it to the pool.
     pub fn declare_struct_handle_index(
@@ -569,6 +602,14 @@ impl<'a> Context<'a> {
         }
     }
 
+    /// The kinds of the type formals of the struct of a handle in the pool.
+    pub fn struct_handle_type_formals(&self, idx: StructHandleIndex) -> Result<&[Kind]> {
+        match self.struct_handles.iter().find(|(_, i)| **i == idx.0) {
+            Some((handle, _)) => Ok(&handle.type_formals),
+            None => bail!("Unbound struct handle {}", idx),
+        }
+    }
+
     fn reindex_signature_token(
         &mut self,
         dep: &QualifiedModuleIdent,
@@ -0,0 +1,612 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Control-flow graphs of function bodies.
+//!
+//! The graph of a body has basic blocks of commands as nodes, each ended by a
+//! [`Terminator`](enum.Terminator.html) saying where control goes next: to another block, to one
+//! of two blocks depending on the condition of an `if` or a `while`, or out of the function. A
+//! `while` gets a block of its own evaluating its condition, which the end of its body and its
+//! `continue`s jump back to, while the body of a `loop` starts right at its head. A `break e` of a
+//! loop with results stays the last command of its block, the value it assigns being part of it.
+//!
+//! Only the code reachable from the entry of the body is part of the graph. The graphs are
+//! exported as JSON, or in the DOT language of Graphviz, for symbolic executors and model checkers
+//! to work from the compiler's view of control flow.
+//!
+//! Analyses build on the graph of a function through
+//! [`ControlFlowGraph::for_function`](struct.ControlFlowGraph.html#method.for_function), and walk
+//! it with the successors and predecessors of its blocks, a reverse postorder, and its loops. As
+//! control flow in the IR is structured, every cycle of a graph goes through a loop header, the
+//! block every edge into the loop from outside of it goes to, and the back edges of a depth-first
+//! walk from the entry are the edges going back to their header. Code generation itself works
+//! on the structured AST directly, and doesn't build these graphs.
+
+use crate::{
+    dataflow::LoopStack,
+    formatter::{format_cmd, format_exp},
+};
+use anyhow::Result;
+use move_ir_types::ast::{
+    Block_, Cmd, Cmd_, Exp, Function, FunctionBody, Loc, ModuleDefinition, Script, Statement,
+};
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    str::FromStr,
+};
+
+/// The index of a basic block in its graph.
+pub type BlockId = usize;
+
+/// Where control goes at the end of a basic block.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Terminator {
+    /// To another block.
+    Jump(BlockId),
+    /// To `if_true` if the condition holds, and to `if_false` otherwise.
+    Branch {
+        cond: Exp,
+        if_true: BlockId,
+        if_false: BlockId,
+    },
+    /// Out of the function, with the `return` ending the block.
+    Return,
+    /// Out of the function, with the `abort` ending the block.
+    Abort,
+    /// Past the end of the body, which the compiler rejects.
+    End,
+}
+
+/// A sequence of commands executed one after the other.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasicBlock {
+    pub cmds: Vec<Cmd>,
+    pub terminator: Terminator,
+}
+
+impl BasicBlock {
+    /// Returns the blocks control may go to from this one.
+    pub fn successors(&self) -> Vec<BlockId> {
+        match &self.terminator {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                if_true, if_false, ..
+            } => vec![*if_true, *if_false],
+            Terminator::Return | Terminator::Abort | Terminator::End => vec![],
+        }
+    }
+}
+
+/// The control-flow graph of a function body.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ControlFlowGraph {
+    /// The blocks, in the order their code appears in; the entry of the body is the first.
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl ControlFlowGraph {
+    /// The block control enters the body at.
+    pub const ENTRY: BlockId = 0;
+
+    /// Builds the graph of a function body.
+    pub fn new(body: &Block_) -> Self {
+        let mut builder = Builder::default();
+        let entry = builder.new_block();
+        let end = builder.block(body, Some(entry));
+        if let Some(end) = end {
+            builder.terminate(end, Terminator::End);
+        }
+        builder.finish()
+    }
+
+    /// Builds the graph of the body of a function, or none if it is native.
+    pub fn for_function(function: &Function) -> Option<Self> {
+        match &function.value.body {
+            FunctionBody::Move { code, .. } => Some(Self::new(code)),
+            FunctionBody::Native => None,
+        }
+    }
+
+    /// Returns the blocks control may go to from a block.
+    pub fn successors(&self, id: BlockId) -> Vec<BlockId> {
+        self.blocks[id].successors()
+    }
+
+    /// Returns the blocks control may come to a block from, in increasing order. A block
+    /// branching to the same block on both sides appears once.
+    pub fn predecessors(&self, id: BlockId) -> Vec<BlockId> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| block.successors().contains(&id))
+            .map(|(pred, _)| pred)
+            .collect()
+    }
+
+    /// Returns the predecessors of every block, indexed by block.
+    pub fn all_predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut preds = vec![vec![]; self.blocks.len()];
+        for (id, block) in self.blocks.iter().enumerate() {
+            let mut succs = block.successors();
+            succs.dedup();
+            for succ in succs {
+                preds[succ].push(id);
+            }
+        }
+        preds
+    }
+
+    /// Returns the blocks in reverse postorder from the entry: every block comes before its
+    /// successors, but for the back edges to the headers of the loops it is in.
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut order = self.postorder();
+        order.reverse();
+        order
+    }
+
+    /// Returns the edges going back to the header of a loop, from the end of its body or a
+    /// `continue`, as `(source, header)` pairs in increasing order.
+    pub fn back_edges(&self) -> Vec<(BlockId, BlockId)> {
+        let mut edges = vec![];
+        let mut visited = vec![false; self.blocks.len()];
+        let mut on_stack = vec![false; self.blocks.len()];
+        // Each frame holds a block and the index of its next successor to visit.
+        let mut stack = vec![(Self::ENTRY, 0)];
+        visited[Self::ENTRY] = true;
+        on_stack[Self::ENTRY] = true;
+        while let Some((id, next)) = stack.pop() {
+            let succs = self.successors(id);
+            match succs.get(next) {
+                Some(&succ) => {
+                    stack.push((id, next + 1));
+                    if on_stack[succ] {
+                        edges.push((id, succ));
+                    } else if !visited[succ] {
+                        visited[succ] = true;
+                        on_stack[succ] = true;
+                        stack.push((succ, 0));
+                    }
+                }
+                None => on_stack[id] = false,
+            }
+        }
+        edges.sort();
+        edges.dedup();
+        edges
+    }
+
+    /// Returns the headers of the loops of the graph: the condition blocks of the `while`s, and
+    /// the first blocks of the bodies of the `loop`s that control may go back to.
+    pub fn loop_headers(&self) -> BTreeSet<BlockId> {
+        self.back_edges()
+            .into_iter()
+            .map(|(_, header)| header)
+            .collect()
+    }
+
+    /// Returns the blocks of the loop with the given header, including it: those from which one
+    /// of its back edges is reached without going through the header. Nested loops are part of
+    /// the loops around them.
+    pub fn loop_blocks(&self, header: BlockId) -> BTreeSet<BlockId> {
+        let preds = self.all_predecessors();
+        let mut blocks = BTreeSet::new();
+        blocks.insert(header);
+        let mut work: Vec<BlockId> = self
+            .back_edges()
+            .into_iter()
+            .filter(|(_, target)| *target == header)
+            .map(|(source, _)| source)
+            .collect();
+        while let Some(id) = work.pop() {
+            if blocks.insert(id) {
+                work.extend(preds[id].iter().cloned());
+            }
+        }
+        blocks
+    }
+
+    /// Returns the block holding a command, or branching on a condition, by location.
+    pub fn block_of(&self, loc: Loc) -> Option<BlockId> {
+        self.blocks.iter().position(|block| {
+            block.cmds.iter().any(|cmd| cmd.span == loc)
+                || match &block.terminator {
+                    Terminator::Branch { cond, .. } => cond.span == loc,
+                    _ => false,
+                }
+        })
+    }
+
+    fn postorder(&self) -> Vec<BlockId> {
+        let mut order = vec![];
+        let mut visited = vec![false; self.blocks.len()];
+        let mut stack = vec![(Self::ENTRY, 0)];
+        visited[Self::ENTRY] = true;
+        while let Some((id, next)) = stack.pop() {
+            match self.successors(id).get(next) {
+                Some(&succ) => {
+                    stack.push((id, next + 1));
+                    if !visited[succ] {
+                        visited[succ] = true;
+                        stack.push((succ, 0));
+                    }
+                }
+                None => order.push(id),
+            }
+        }
+        order
+    }
+
+    fn to_json_value(&self) -> Value {
+        let blocks: Vec<Value> = self
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(id, block)| {
+                let cmds: Vec<Value> = block
+                    .cmds
+                    .iter()
+                    .map(|cmd| {
+                        json!({
+                            "text": format_cmd(&cmd.value),
+                            "span": span(cmd.span),
+                        })
+                    })
+                    .collect();
+                let terminator = match &block.terminator {
+                    Terminator::Jump(target) => json!({ "kind": "jump", "target": target }),
+                    Terminator::Branch {
+                        cond,
+                        if_true,
+                        if_false,
+                    } => json!({
+                        "kind": "branch",
+                        "cond": format_exp(cond),
+                        "span": span(cond.span),
+                        "if_true": if_true,
+                        "if_false": if_false,
+                    }),
+                    Terminator::Return => json!({ "kind": "return" }),
+                    Terminator::Abort => json!({ "kind": "abort" }),
+                    Terminator::End => json!({ "kind": "end" }),
+                };
+                json!({ "id": id, "cmds": cmds, "terminator": terminator })
+            })
+            .collect();
+        json!({ "entry": Self::ENTRY, "blocks": blocks })
+    }
+
+    fn write_dot(&self, dot: &mut String, prefix: &str, indent: &str) {
+        for (id, block) in self.blocks.iter().enumerate() {
+            let mut label = format!("{}:\\l", id);
+            for cmd in &block.cmds {
+                label.push_str(&escape(&format_cmd(&cmd.value)));
+                label.push_str("\\l");
+            }
+            if let Terminator::Branch { cond, .. } = &block.terminator {
+                label.push_str(&format!("if {}\\l", escape(&format_exp(cond))));
+            }
+            dot.push_str(&format!(
+                "{}\"{}{}\" [shape=box, label=\"{}\"];\n",
+                indent, prefix, id, label
+            ));
+        }
+        for (id, block) in self.blocks.iter().enumerate() {
+            match &block.terminator {
+                Terminator::Jump(target) => dot.push_str(&format!(
+                    "{}\"{}{}\" -> \"{}{}\";\n",
+                    indent, prefix, id, prefix, target
+                )),
+                Terminator::Branch {
+                    if_true, if_false, ..
+                } => {
+                    for (target, label) in &[(if_true, "true"), (if_false, "false")] {
+                        dot.push_str(&format!(
+                            "{}\"{}{}\" -> \"{}{}\" [label=\"{}\"];\n",
+                            indent, prefix, id, prefix, target, label
+                        ));
+                    }
+                }
+                Terminator::Return | Terminator::Abort | Terminator::End => (),
+            }
+        }
+    }
+}
+
+fn span(loc: Loc) -> Value {
+    json!([loc.start().to_usize(), loc.end().to_usize()])
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The graphs of the functions of scripts and modules.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ControlFlowGraphs {
+    /// The functions with a body, in the order they are declared in, by module and name. The
+    /// `main` function of a script has no module.
+    pub functions: Vec<(Option<String>, String, ControlFlowGraph)>,
+}
+
+impl ControlFlowGraphs {
+    /// The graphs of the functions of a module. Native functions have none.
+    pub fn for_module(module: &ModuleDefinition) -> Self {
+        let functions = module
+            .functions
+            .iter()
+            .filter_map(|(name, function)| {
+                ControlFlowGraph::for_function(function)
+                    .map(|graph| (Some(module.name.to_string()), name.to_string(), graph))
+            })
+            .collect();
+        Self { functions }
+    }
+
+    /// The graph of the `main` function of a script.
+    pub fn for_script(script: &Script) -> Self {
+        let functions = ControlFlowGraph::for_function(&script.main)
+            .map(|graph| (None, "main".to_string(), graph))
+            .into_iter()
+            .collect();
+        Self { functions }
+    }
+
+    /// Adds the graphs of `other` after those of `self`.
+    pub fn extend(&mut self, other: ControlFlowGraphs) {
+        self.functions.extend(other.functions);
+    }
+
+    /// Serializes the graphs as JSON. Commands and conditions are printed as in the IR, with the
+    /// span of their source as byte offsets.
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        let functions: Vec<Value> = self
+            .functions
+            .iter()
+            .map(|(module, name, graph)| {
+                let mut function = graph.to_json_value();
+                function["module"] = json!(module);
+                function["name"] = json!(name);
+                function
+            })
+            .collect();
+        Ok(serde_json::to_vec_pretty(
+            &json!({ "functions": functions }),
+        )?)
+    }
+
+    /// Prints the graphs in the DOT language, one cluster per function.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph cfg {\n");
+        for (index, (module, name, graph)) in self.functions.iter().enumerate() {
+            let name = match module {
+                Some(module) => format!("{}.{}", module, name),
+                None => name.clone(),
+            };
+            dot.push_str(&format!("    subgraph cluster_{} {{\n", index));
+            dot.push_str(&format!("        label=\"{}\";\n", name));
+            graph.write_dot(&mut dot, &format!("{}:", name), "        ");
+            dot.push_str("    }\n");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// The formats the graphs are exported in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GraphFormat {
+    Json,
+    Dot,
+}
+
+impl GraphFormat {
+    /// The extension of the files the graphs are written to.
+    pub fn extension(self) -> &'static str {
+        match self {
+            GraphFormat::Json => "cfg.json",
+            GraphFormat::Dot => "cfg.dot",
+        }
+    }
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(GraphFormat::Json),
+            "dot" => Ok(GraphFormat::Dot),
+            _ => Err(format!(
+                "unknown graph format `{}`, expected `json` or `dot`",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for GraphFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphFormat::Json => write!(f, "json"),
+            GraphFormat::Dot => write!(f, "dot"),
+        }
+    }
+}
+
+/// Builds a graph from the structured AST. Blocks are created in the order of their code, and
+/// renumbered once the unreachable ones are removed.
+#[derive(Default)]
+struct Builder {
+    blocks: Vec<(Vec<Cmd>, Option<Terminator>)>,
+    /// The heads of the enclosing loops, with the blocks ended by their `break`s, which jump to
+    /// the exit of the loop once it is created.
+    loops: LoopStack<(BlockId, Vec<BlockId>)>,
+}
+
+impl Builder {
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push((vec![], None));
+        self.blocks.len() - 1
+    }
+
+    fn terminate(&mut self, block: BlockId, terminator: Terminator) {
+        self.blocks[block].1 = Some(terminator);
+    }
+
+    // Each of the functions below takes the block the code is added to, none if it is
+    // unreachable, and returns the block control reaches after it.
+
+    fn block(&mut self, block: &Block_, current: Option<BlockId>) -> Option<BlockId> {
+        block.stmts.iter().fold(current, |current, statement| {
+            self.statement(statement, current)
+        })
+    }
+
+    fn statement(&mut self, statement: &Statement, current: Option<BlockId>) -> Option<BlockId> {
+        // Unreachable code still gets blocks, which are removed at the end.
+        let current = match (statement, current) {
+            (Statement::EmptyStatement, _) => return current,
+            (_, Some(current)) => current,
+            (_, None) => self.new_block(),
+        };
+        match statement {
+            Statement::CommandStatement(cmd) => self.cmd(cmd, current),
+            Statement::IfElseStatement(if_else) => {
+                let if_true = self.new_block();
+                let true_end = self.block(&if_else.if_block.value, Some(if_true));
+                let if_false = self.new_block();
+                let false_end = match &if_else.else_block {
+                    Some(else_block) => self.block(&else_block.value, Some(if_false)),
+                    None => Some(if_false),
+                };
+                self.terminate(
+                    current,
+                    Terminator::Branch {
+                        cond: if_else.cond.clone(),
+                        if_true,
+                        if_false,
+                    },
+                );
+                let ends: Vec<BlockId> = true_end.into_iter().chain(false_end).collect();
+                if ends.len() < 2 {
+                    // Control goes on from the only branch that falls through, if any.
+                    return ends.into_iter().next();
+                }
+                let join = self.new_block();
+                for end in ends {
+                    self.terminate(end, Terminator::Jump(join));
+                }
+                Some(join)
+            }
+            Statement::WhileStatement(while_) => {
+                let head = self.new_block();
+                self.terminate(current, Terminator::Jump(head));
+                let body = self.new_block();
+                let exit = self.loop_body(&while_.block.value, body, head);
+                self.terminate(
+                    head,
+                    Terminator::Branch {
+                        cond: while_.cond.clone(),
+                        if_true: body,
+                        if_false: exit,
+                    },
+                );
+                Some(exit)
+            }
+            Statement::LoopStatement(loop_) => {
+                let head = self.new_block();
+                self.terminate(current, Terminator::Jump(head));
+                Some(self.loop_body(&loop_.block.value, head, head))
+            }
+            Statement::EmptyStatement => unreachable!("empty statements are skipped above"),
+        }
+    }
+
+    /// Adds the body of a loop starting at `start`, and returns the exit of the loop.
+    fn loop_body(&mut self, block: &Block_, start: BlockId, head: BlockId) -> BlockId {
+        self.loops.enter((head, vec![]));
+        if let Some(end) = self.block(block, Some(start)) {
+            self.terminate(end, Terminator::Jump(head));
+        }
+        let (_, breaks) = self.loops.leave();
+        let exit = self.new_block();
+        for block in breaks {
+            self.terminate(block, Terminator::Jump(exit));
+        }
+        exit
+    }
+
+    fn cmd(&mut self, cmd: &Cmd, current: BlockId) -> Option<BlockId> {
+        self.blocks[current].0.push(cmd.clone());
+        let terminator = match &cmd.value {
+            Cmd_::Return(_) => Terminator::Return,
+            Cmd_::Abort(_) => Terminator::Abort,
+            Cmd_::Break(_) => match self.loops.innermost_mut() {
+                Some((_, breaks)) => {
+                    breaks.push(current);
+                    return None;
+                }
+                None => Terminator::End,
+            },
+            Cmd_::Continue => match self.loops.innermost() {
+                Some((head, _)) => Terminator::Jump(*head),
+                None => Terminator::End,
+            },
+            Cmd_::Assign(_, _) | Cmd_::Unpack(_, _, _, _) | Cmd_::Emit(_, _) | Cmd_::Exp(_) => {
+                return Some(current)
+            }
+        };
+        self.terminate(current, terminator);
+        None
+    }
+
+    /// Removes the blocks unreachable from the entry, and numbers the others in order.
+    fn finish(self) -> ControlFlowGraph {
+        let blocks: Vec<BasicBlock> = self
+            .blocks
+            .into_iter()
+            .map(|(cmds, terminator)| BasicBlock {
+                cmds,
+                // Blocks are all terminated, except those of unreachable code.
+                terminator: terminator.unwrap_or(Terminator::End),
+            })
+            .collect();
+        let mut reachable = vec![false; blocks.len()];
+        let mut pending = vec![ControlFlowGraph::ENTRY];
+        while let Some(id) = pending.pop() {
+            if !reachable[id] {
+                reachable[id] = true;
+                pending.extend(blocks[id].successors());
+            }
+        }
+        let ids: BTreeMap<BlockId, BlockId> = (0..blocks.len())
+            .filter(|id| reachable[*id])
+            .enumerate()
+            .map(|(new, old)| (old, new))
+            .collect();
+        let blocks = blocks
+            .into_iter()
+            .enumerate()
+            .filter(|(id, _)| reachable[*id])
+            .map(|(_, mut block)| {
+                block.terminator = match block.terminator {
+                    Terminator::Jump(target) => Terminator::Jump(ids[&target]),
+                    Terminator::Branch {
+                        cond,
+                        if_true,
+                        if_false,
+                    } => Terminator::Branch {
+                        cond,
+                        if_true: ids[&if_true],
+                        if_false: ids[&if_false],
+                    },
+                    terminator => terminator,
+                };
+                block
+            })
+            .collect();
+        ControlFlowGraph { blocks }
+    }
+}
@@ -0,0 +1,421 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Dataflow analysis over function bodies.
+//!
+//! An analysis provides its abstract states, which form a [`Lattice`](trait.Lattice.html), and a
+//! [`Transfer`](trait.Transfer.html) function giving the effect of each command and branch
+//! condition on a state. [`forward`](fn.forward.html) then runs it over the structured AST of a
+//! function body: the states of the two branches of an `if` are joined, and the body of a `while`
+//! or `loop` is analyzed again until the state at its head no longer changes, with the states at
+//! its `continue`s flowing back to the head and those at its `break`s flowing out of it. Control
+//! does not flow past a `return`, an `abort`, a `break` or a `continue`.
+//!
+//! The lattice must not have infinite ascending chains, for loops to reach a fixed point. A state
+//! is only computed for the code reachable from the entry of the body.
+//!
+//! Analyses of what happens after a program point, such as the liveness of locals, run backward
+//! with a [`BackwardTransfer`](trait.BackwardTransfer.html) and [`backward`](fn.backward.html),
+//! from the state at the exit of the body to the one at its entry.
+
+use move_ir_types::ast::{Block_, Cmd, Cmd_, Exp, LValue, Loc, Statement, Var_};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The abstract states of an analysis.
+pub trait Lattice: Clone + PartialEq {
+    /// Joins `other` into `self`, giving the state at a point reached both with `self` and with
+    /// `other`.
+    fn join(&mut self, other: &Self);
+}
+
+impl<T: Clone + Ord> Lattice for BTreeSet<T> {
+    fn join(&mut self, other: &Self) {
+        self.extend(other.iter().cloned());
+    }
+}
+
+/// The effect of the code on the abstract state.
+pub trait Transfer {
+    type State: Lattice;
+
+    /// Updates the state with the effect of a command. This includes the evaluation of the
+    /// expressions of a `return`, `abort` or `break`, before control leaves.
+    fn cmd(&self, cmd: &Cmd, state: &mut Self::State);
+
+    /// Updates the state with the effect of evaluating the condition of an `if` or a `while`.
+    fn cond(&self, _cond: &Exp, _state: &mut Self::State) {}
+
+    /// Updates the state with the assignment of `value` to the results of a loop, when leaving
+    /// it with `break value`.
+    fn break_value(&self, _results: &[LValue], _value: &Exp, _state: &mut Self::State) {}
+}
+
+/// The loops enclosing the code being walked, innermost last, each with what the walk collects
+/// for it, such as the states its `break`s and `continue`s leave it with.
+///
+/// The analyses and passes run before the compiler checks the code, which reports a `break` or a
+/// `continue` outside of a loop. There, [`innermost`](#method.innermost) is none, and the walks
+/// either ignore them or let them leave the body.
+#[derive(Clone, Debug)]
+pub struct LoopStack<T> {
+    loops: Vec<T>,
+}
+
+impl<T> Default for LoopStack<T> {
+    fn default() -> Self {
+        Self { loops: vec![] }
+    }
+}
+
+impl<T> LoopStack<T> {
+    /// Enters a loop, with what is collected for it so far.
+    pub fn enter(&mut self, exits: T) {
+        self.loops.push(exits);
+    }
+
+    /// Leaves the innermost loop, returning what was collected for it.
+    pub fn leave(&mut self) -> T {
+        self.loops.pop().expect("the loop was entered")
+    }
+
+    /// What is collected for the innermost loop, or none outside of any loop.
+    pub fn innermost(&self) -> Option<&T> {
+        self.loops.last()
+    }
+
+    /// What is collected for the innermost loop, to add to it, or none outside of any loop.
+    pub fn innermost_mut(&mut self) -> Option<&mut T> {
+        self.loops.last_mut()
+    }
+}
+
+/// The states computed by an analysis.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dataflow<S> {
+    /// The state before each reachable command, and before each reachable condition of an `if` or
+    /// a `while`, by location.
+    pub before: BTreeMap<Loc, S>,
+    /// The joined states at the `return` commands of the body, or none if it never returns.
+    pub returns: Option<S>,
+    /// The state at the end of the body, or none if control never reaches it.
+    pub exit: Option<S>,
+}
+
+impl<S> Dataflow<S> {
+    /// Returns the state before a command or a condition, or none if it is unreachable.
+    pub fn state_before(&self, loc: Loc) -> Option<&S> {
+        self.before.get(&loc)
+    }
+}
+
+/// Runs the analysis over a function body, starting from the state at its entry.
+pub fn forward<T: Transfer>(transfer: &T, body: &Block_, entry: T::State) -> Dataflow<T::State> {
+    let mut engine = Engine {
+        transfer,
+        before: BTreeMap::new(),
+        returns: None,
+        loops: LoopStack::default(),
+    };
+    let exit = engine.block(body, Some(entry));
+    Dataflow {
+        before: engine.before,
+        returns: engine.returns,
+        exit,
+    }
+}
+
+fn join<S: Lattice>(state: &mut Option<S>, other: Option<S>) {
+    match (state.as_mut(), other) {
+        (Some(state), Some(other)) => state.join(&other),
+        (None, other) => *state = other,
+        (Some(_), None) => (),
+    }
+}
+
+/// The states leaving the loop being analyzed.
+struct LoopExits<'a, S> {
+    results: &'a [LValue],
+    breaks: Option<S>,
+    continues: Option<S>,
+}
+
+struct Engine<'a, 'b, T: Transfer> {
+    transfer: &'b T,
+    before: BTreeMap<Loc, T::State>,
+    returns: Option<T::State>,
+    loops: LoopStack<LoopExits<'a, T::State>>,
+}
+
+impl<'a, 'b, T: Transfer> Engine<'a, 'b, T> {
+    // Each of the functions below takes the state before a piece of code, none if it is
+    // unreachable, and returns the state after it.
+
+    fn block(&mut self, block: &'a Block_, state: Option<T::State>) -> Option<T::State> {
+        block
+            .stmts
+            .iter()
+            .fold(state, |state, statement| self.statement(statement, state))
+    }
+
+    fn statement(&mut self, statement: &'a Statement, state: Option<T::State>) -> Option<T::State> {
+        let mut state = state?;
+        match statement {
+            Statement::CommandStatement(cmd) => self.cmd(cmd, state),
+            Statement::IfElseStatement(if_else) => {
+                self.before.insert(if_else.cond.span, state.clone());
+                self.transfer.cond(&if_else.cond, &mut state);
+                let mut after = self.block(&if_else.if_block.value, Some(state.clone()));
+                let else_after = match &if_else.else_block {
+                    Some(else_block) => self.block(&else_block.value, Some(state)),
+                    None => Some(state),
+                };
+                join(&mut after, else_after);
+                after
+            }
+            Statement::WhileStatement(while_) => loop {
+                let mut cond = state.clone();
+                self.before.insert(while_.cond.span, cond.clone());
+                self.transfer.cond(&while_.cond, &mut cond);
+                let (body, exits) = self.loop_body(&while_.block.value, &[], cond.clone());
+                let mut head = state.clone();
+                for other in body.iter().chain(exits.continues.iter()) {
+                    head.join(other);
+                }
+                if head == state {
+                    let mut after = Some(cond);
+                    join(&mut after, exits.breaks);
+                    break after;
+                }
+                state = head;
+            },
+            Statement::LoopStatement(loop_) => loop {
+                let (body, exits) =
+                    self.loop_body(&loop_.block.value, &loop_.results, state.clone());
+                let mut head = state.clone();
+                for other in body.iter().chain(exits.continues.iter()) {
+                    head.join(other);
+                }
+                if head == state {
+                    break exits.breaks;
+                }
+                state = head;
+            },
+            Statement::EmptyStatement => Some(state),
+        }
+    }
+
+    fn loop_body(
+        &mut self,
+        block: &'a Block_,
+        results: &'a [LValue],
+        state: T::State,
+    ) -> (Option<T::State>, LoopExits<'a, T::State>) {
+        self.loops.enter(LoopExits {
+            results,
+            breaks: None,
+            continues: None,
+        });
+        let after = self.block(block, Some(state));
+        let exits = self.loops.leave();
+        (after, exits)
+    }
+
+    fn cmd(&mut self, cmd: &'a Cmd, mut state: T::State) -> Option<T::State> {
+        self.before.insert(cmd.span, state.clone());
+        self.transfer.cmd(cmd, &mut state);
+        match &cmd.value {
+            Cmd_::Return(_) => {
+                join(&mut self.returns, Some(state));
+                None
+            }
+            Cmd_::Abort(_) => None,
+            Cmd_::Break(value) => {
+                if let Some(exits) = self.loops.innermost_mut() {
+                    if let Some(value) = value {
+                        if !exits.results.is_empty() {
+                            self.transfer.break_value(exits.results, value, &mut state);
+                        }
+                    }
+                    join(&mut exits.breaks, Some(state));
+                }
+                None
+            }
+            Cmd_::Continue => {
+                if let Some(exits) = self.loops.innermost_mut() {
+                    join(&mut exits.continues, Some(state));
+                }
+                None
+            }
+            Cmd_::Assign(_, _) | Cmd_::Unpack(_, _, _, _) | Cmd_::Emit(_, _) | Cmd_::Exp(_) => {
+                Some(state)
+            }
+        }
+    }
+}
+
+/// A point where control joins.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Join {
+    /// After an `if`, located by its condition.
+    AfterIf(Loc),
+    /// At the head of a `while`, located by its condition, or of a `loop`, located by its body.
+    LoopHead(Loc),
+    /// After a loop, located as its head.
+    LoopExit(Loc),
+}
+
+/// The effect of the code on the abstract state, going backward: each function takes the state
+/// after a piece of code and updates it to the state before it.
+///
+/// Code in loops is visited several times, with states growing until the one at the head of the
+/// loop no longer changes. A transfer recording what it sees must let the last visit decide.
+pub trait BackwardTransfer {
+    type State: Lattice + Default;
+
+    /// Updates the state with the effect of a command. After a `return` or an `abort`, the state
+    /// is the one at the exit of the body; after a `break` or a `continue`, it is the state after
+    /// the loop or at its head.
+    fn cmd(&mut self, cmd: &Cmd, state: &mut Self::State);
+
+    /// Updates the state with the effect of the command at `position` in the body: for each
+    /// statement enclosing the command, its index in its block followed by the index of the block
+    /// the command is in (0 for the `if` branch or the body of a loop, 1 for the `else` branch),
+    /// then the index of the command in its block. Defaults to `cmd`.
+    fn cmd_at(&mut self, _position: &[usize], cmd: &Cmd, state: &mut Self::State) {
+        self.cmd(cmd, state)
+    }
+
+    /// Updates the state with the effect of evaluating the condition of an `if` or a `while`.
+    fn cond(&mut self, _cond: &Exp, _state: &mut Self::State) {}
+
+    /// Updates the state with the assignment of the results of a `loop`, which its `break`s make
+    /// before leaving it.
+    fn loop_results(&mut self, _results: &[LValue], _state: &mut Self::State) {}
+
+    /// Records the state where control joins: after an `if` or a loop, and at the head of a loop
+    /// once it is final.
+    fn join(&mut self, _join: Join, _state: &Self::State) {}
+}
+
+/// The locals that may be read before being written on some path from a program point, the state
+/// of the liveness analyses.
+pub type Live = BTreeSet<Var_>;
+
+/// Runs the analysis backward over a function body, starting from the state at its exit, and
+/// returns the state at its entry. The state at the head of a `loop` starts from the default
+/// state, which must be the least element of the lattice.
+pub fn backward<T: BackwardTransfer>(transfer: &mut T, body: &Block_, exit: T::State) -> T::State {
+    let mut engine = BackwardEngine {
+        transfer,
+        exit: exit.clone(),
+        loops: LoopStack::default(),
+        position: vec![],
+    };
+    engine.block(body, exit)
+}
+
+struct BackwardEngine<'a, T: BackwardTransfer> {
+    transfer: &'a mut T,
+    exit: T::State,
+    /// The states after and at the head of each enclosing loop, which `break` and `continue`
+    /// jump to.
+    loops: LoopStack<(T::State, T::State)>,
+    /// The position of the statement being visited, as passed to `cmd_at`.
+    position: Vec<usize>,
+}
+
+impl<'a, T: BackwardTransfer> BackwardEngine<'a, T> {
+    // Each of the functions below takes the state after a piece of code and returns the state
+    // before it.
+
+    fn block(&mut self, block: &Block_, after: T::State) -> T::State {
+        let mut state = after;
+        for (index, statement) in block.stmts.iter().enumerate().rev() {
+            self.position.push(index);
+            state = self.statement(statement, state);
+            self.position.pop();
+        }
+        state
+    }
+
+    /// Visits the `nth` block of the statement being visited.
+    fn nested_block(&mut self, nth: usize, block: &Block_, after: T::State) -> T::State {
+        self.position.push(nth);
+        let state = self.block(block, after);
+        self.position.pop();
+        state
+    }
+
+    fn statement(&mut self, statement: &Statement, after: T::State) -> T::State {
+        match statement {
+            Statement::CommandStatement(cmd) => {
+                let mut state = match &cmd.value {
+                    Cmd_::Return(_) | Cmd_::Abort(_) => self.exit.clone(),
+                    Cmd_::Break(_) => self
+                        .loops
+                        .innermost()
+                        .map_or_else(|| self.exit.clone(), |(after, _)| after.clone()),
+                    Cmd_::Continue => self
+                        .loops
+                        .innermost()
+                        .map_or_else(|| self.exit.clone(), |(_, head)| head.clone()),
+                    Cmd_::Assign(_, _)
+                    | Cmd_::Unpack(_, _, _, _)
+                    | Cmd_::Emit(_, _)
+                    | Cmd_::Exp(_) => after,
+                };
+                self.transfer.cmd_at(&self.position, cmd, &mut state);
+                state
+            }
+            Statement::IfElseStatement(if_else) => {
+                self.transfer.join(Join::AfterIf(if_else.cond.span), &after);
+                let mut state = self.nested_block(0, &if_else.if_block.value, after.clone());
+                match &if_else.else_block {
+                    Some(else_block) => state.join(&self.nested_block(1, &else_block.value, after)),
+                    None => state.join(&after),
+                }
+                self.transfer.cond(&if_else.cond, &mut state);
+                state
+            }
+            Statement::WhileStatement(while_) => {
+                let join = while_.cond.span;
+                self.transfer.join(Join::LoopExit(join), &after);
+                let mut head = after.clone();
+                self.transfer.cond(&while_.cond, &mut head);
+                loop {
+                    let mut state = self.loop_body(&while_.block.value, &after, &head);
+                    state.join(&after);
+                    self.transfer.cond(&while_.cond, &mut state);
+                    if state == head {
+                        self.transfer.join(Join::LoopHead(join), &head);
+                        return head;
+                    }
+                    head = state;
+                }
+            }
+            Statement::LoopStatement(loop_) => {
+                let join = loop_.block.span;
+                let mut after = after;
+                self.transfer.loop_results(&loop_.results, &mut after);
+                self.transfer.join(Join::LoopExit(join), &after);
+                let mut head = T::State::default();
+                loop {
+                    let state = self.loop_body(&loop_.block.value, &after, &head);
+                    if state == head {
+                        self.transfer.join(Join::LoopHead(join), &head);
+                        return head;
+                    }
+                    head = state;
+                }
+            }
+            Statement::EmptyStatement => after,
+        }
+    }
+
+    fn loop_body(&mut self, body: &Block_, after: &T::State, head: &T::State) -> T::State {
+        self.loops.enter((after.clone(), head.clone()));
+        let state = self.nested_block(0, body, head.clone());
+        self.loops.leave();
+        state
+    }
+}
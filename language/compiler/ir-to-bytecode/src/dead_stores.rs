@@ -0,0 +1,173 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Detection of dead stores: assignments to locals whose value is never read because it is
+//! overwritten first, or because the function never reads it again.
+//!
+//! This is a backward liveness analysis over the structured AST. Branches and loops are handled
+//! conservatively: a store is only reported if its value is dead on every path. Locals that are
+//! borrowed anywhere in the function are never reported, since they may be read through the
+//! reference. Bindings introduced by unpacking a struct are not reported either, as unpacking into
+//! unused locals is the only way to destroy a struct.
+
+use crate::{
+    dataflow::{backward, BackwardTransfer, Lattice},
+    locals::{cmd_exps, exp_vars, LocalUse},
+};
+use move_ir_types::ast::{
+    Cmd, Cmd_, Exp, FunctionBody, Function_, LValue, LValue_, Loc, ModuleDefinition, Script, Var_,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// An assignment whose value is never read.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeadStore {
+    /// The local being assigned.
+    pub var: Var_,
+    /// The location of the dead assignment.
+    pub loc: Loc,
+    /// The location of the assignment that overwrites the value on every path, if there is one.
+    /// `None` means the value is simply never read again.
+    pub overwritten_at: Option<Loc>,
+}
+
+impl fmt::Display for DeadStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.overwritten_at {
+            Some(_) => write!(
+                f,
+                "value assigned to `{}` is overwritten before being read",
+                self.var
+            ),
+            None => write!(f, "value assigned to `{}` is never read", self.var),
+        }
+    }
+}
+
+/// Returns the dead stores of every function in the module, in source order.
+pub fn module_dead_stores(module: &ModuleDefinition) -> Vec<DeadStore> {
+    module
+        .functions
+        .iter()
+        .flat_map(|(_, function)| function_dead_stores(&function.value))
+        .collect()
+}
+
+/// Returns the dead stores of the script's `main` function, in source order.
+pub fn script_dead_stores(script: &Script) -> Vec<DeadStore> {
+    function_dead_stores(&script.main.value)
+}
+
+/// Returns the dead stores of a function, in source order.
+pub fn function_dead_stores(function: &Function_) -> Vec<DeadStore> {
+    let code = match &function.body {
+        FunctionBody::Move { code, .. } => code,
+        FunctionBody::Native => return vec![],
+    };
+    let mut analysis = Analysis::default();
+    backward(&mut analysis, code, State::default());
+    let borrowed = analysis.borrowed;
+    let mut dead = analysis.dead;
+    dead.retain(|store| !borrowed.contains(&store.var));
+    dead.sort_by_key(|store| store.loc.start());
+    dead
+}
+
+/// The liveness information at a program point.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct State {
+    /// Locals that may be read before being written on some path from here.
+    live: BTreeSet<Var_>,
+    /// Locals that are written before being read on every path from here, with the location of
+    /// that write.
+    next_store: BTreeMap<Var_, Loc>,
+}
+
+impl Lattice for State {
+    fn join(&mut self, other: &State) {
+        self.live.extend(other.live.iter().cloned());
+        self.next_store = self
+            .next_store
+            .iter()
+            .filter(|(var, loc)| other.next_store.get(*var) == Some(*loc))
+            .map(|(var, loc)| (var.clone(), *loc))
+            .collect();
+    }
+}
+
+#[derive(Default)]
+struct Analysis {
+    borrowed: BTreeSet<Var_>,
+    dead: Vec<DeadStore>,
+}
+
+impl BackwardTransfer for Analysis {
+    type State = State;
+
+    fn cmd(&mut self, cmd: &Cmd, state: &mut State) {
+        match &cmd.value {
+            Cmd_::Assign(lvalues, _) => self.assign(lvalues, state),
+            Cmd_::Unpack(_, _, bindings, _) => {
+                for (_, var) in bindings {
+                    self.store(&var.value, var.span, state, false);
+                }
+            }
+            _ => (),
+        }
+        for e in cmd_exps(&cmd.value) {
+            self.reads(e, state);
+        }
+    }
+
+    fn cond(&mut self, cond: &Exp, state: &mut State) {
+        self.reads(cond, state);
+    }
+
+    fn loop_results(&mut self, results: &[LValue], state: &mut State) {
+        self.assign(results, state);
+        for lvalue in results {
+            if let LValue_::Mutate(e) = &lvalue.value {
+                self.reads(e, state);
+            }
+        }
+    }
+}
+
+impl Analysis {
+    fn assign(&mut self, lvalues: &[LValue], state: &mut State) {
+        for lvalue in lvalues {
+            if let LValue_::Var(var) = &lvalue.value {
+                self.store(&var.value, lvalue.span, state, true);
+            }
+        }
+    }
+
+    fn store(&mut self, var: &Var_, loc: Loc, state: &mut State, report: bool) {
+        // The same store is visited several times when it is in a loop; the last visit, made
+        // with the final state of the loop, decides.
+        self.dead.retain(|store| store.loc != loc);
+        if report && !state.live.contains(var) {
+            self.dead.push(DeadStore {
+                var: var.clone(),
+                loc,
+                overwritten_at: state.next_store.get(var).cloned(),
+            });
+        }
+        state.live.remove(var);
+        state.next_store.insert(var.clone(), loc);
+    }
+
+    fn reads(&mut self, e: &Exp, state: &mut State) {
+        let borrowed = &mut self.borrowed;
+        exp_vars(e, |var, use_| {
+            if use_ == LocalUse::Borrow {
+                borrowed.insert(var.clone());
+            }
+            state.live.insert(var.clone());
+            state.next_store.remove(var);
+        });
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Manifests of what compiled scripts and modules depend on.
+//!
+//! A dependency manifest lists the modules a compiled script or module refers to, with the
+//! structs and functions of each it uses, and the language version its source declares. It is
+//! read off the handles of the compiled output rather than the imports of the source, so it also
+//! covers the library functions that builtins such as `emit` compile to. Imported modules are
+//! listed even if unused, as the compiled unit still refers to them. Deployment tools check it
+//! against what is published on chain, and reviewers use it as the list of code a script or
+//! module trusts.
+
+use anyhow::Result;
+use libra_types::account_address::AccountAddress;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use vm::{
+    file_format::{
+        CompiledModule, CompiledScript, FunctionHandle, ModuleHandle, ModuleHandleIndex,
+        StructHandle,
+    },
+    internals::ModuleIndex,
+};
+
+/// A module a compiled unit depends on, with the parts of it the unit uses.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ModuleDependency {
+    /// The id of the module, as `<address>.<name>`.
+    pub id: String,
+    /// The address the module is published at, in hex prefixed with `0x`.
+    pub address: String,
+    pub name: String,
+    /// The names of the structs of the module used, in the order of their handles.
+    pub structs: Vec<String>,
+    /// The names of the functions of the module called, in the order of their handles.
+    pub functions: Vec<String>,
+}
+
+/// What a compiled script or module depends on.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DependencyManifest {
+    /// The language version declared by a pragma at the start of the source, if any.
+    pub language_version: Option<u64>,
+    /// The distinct addresses of the modules depended on, in order, in hex prefixed with `0x`.
+    pub addresses: Vec<String>,
+    /// The modules depended on, in the order of their handles.
+    pub modules: Vec<ModuleDependency>,
+}
+
+impl DependencyManifest {
+    /// The dependencies of a compiled script, whose source declares `language_version`.
+    pub fn for_script(script: &CompiledScript, language_version: Option<u64>) -> Self {
+        let script = script.as_inner();
+        Self::new(
+            language_version,
+            &script.module_handles,
+            &script.struct_handles,
+            &script.function_handles,
+            |idx| script.identifiers[idx].as_str(),
+            |idx| script.address_pool[idx],
+        )
+    }
+
+    /// The dependencies of a compiled module, whose source declares `language_version`.
+    pub fn for_module(module: &CompiledModule, language_version: Option<u64>) -> Self {
+        let module = module.as_inner();
+        Self::new(
+            language_version,
+            &module.module_handles,
+            &module.struct_handles,
+            &module.function_handles,
+            |idx| module.identifiers[idx].as_str(),
+            |idx| module.address_pool[idx],
+        )
+    }
+
+    fn new<'a>(
+        language_version: Option<u64>,
+        module_handles: &[ModuleHandle],
+        struct_handles: &[StructHandle],
+        function_handles: &[FunctionHandle],
+        identifier: impl Fn(usize) -> &'a str,
+        address: impl Fn(usize) -> AccountAddress,
+    ) -> Self {
+        // The first handle is the one of the unit itself.
+        let self_idx = ModuleHandleIndex::new(CompiledModule::IMPLEMENTED_MODULE_INDEX);
+        let mut addresses = vec![];
+        let mut seen = BTreeSet::new();
+        let mut modules = vec![];
+        for (idx, handle) in module_handles.iter().enumerate() {
+            let module_idx = ModuleHandleIndex::new(idx as u16);
+            if module_idx == self_idx {
+                continue;
+            }
+            let module_address = format!("0x{:x}", address(handle.address.into_index()));
+            let name = identifier(handle.name.into_index()).to_string();
+            if seen.insert(module_address.clone()) {
+                addresses.push(module_address.clone());
+            }
+            let structs = struct_handles
+                .iter()
+                .filter(|handle| handle.module == module_idx)
+                .map(|handle| identifier(handle.name.into_index()).to_string())
+                .collect();
+            let functions = function_handles
+                .iter()
+                .filter(|handle| handle.module == module_idx)
+                .map(|handle| identifier(handle.name.into_index()).to_string())
+                .collect();
+            modules.push(ModuleDependency {
+                id: format!("{}.{}", module_address, name),
+                address: module_address,
+                name,
+                structs,
+                functions,
+            });
+        }
+        DependencyManifest {
+            language_version,
+            addresses,
+            modules,
+        }
+    }
+
+    /// Serializes the manifest.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+}
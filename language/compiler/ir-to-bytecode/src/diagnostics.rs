@@ -0,0 +1,93 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! The diagnostics reported by the front-ends embedding the compiler: the WebAssembly and C
+//! bindings and the compiler daemon.
+//!
+//! Diagnostics serialize to `{"line": ..., "column": ..., "message": ..., "fix": ...}` objects,
+//! where the line and column are 1-based and `null` when the compiler cannot tell where the
+//! problem is. Syntax errors the parser knows how to fix, e.g. `a < b < c`, have a `fix`: a
+//! `{"line", "column", "end_line", "end_column", "replacement"}` object, replacing the code up to
+//! the end position exclusive with `replacement`. It is `null` for the other diagnostics.
+
+use crate::errors::SyntaxError;
+use bytecode_source_map::utils::line_and_column;
+use libra_types::account_address::AccountAddress;
+use move_ir_types::ast::Loc;
+use serde::Serialize;
+
+/// A problem found in a source.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Diagnostic {
+    /// The 1-based line of the problem, if known.
+    pub line: Option<usize>,
+    /// The 1-based column of the problem, if known.
+    pub column: Option<usize>,
+    pub message: String,
+    /// An edit fixing the problem, if the parser knows one.
+    pub fix: Option<DiagnosticFix>,
+}
+
+/// An edit fixing a problem, with 1-based lines and columns.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct DiagnosticFix {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub replacement: String,
+}
+
+impl Diagnostic {
+    /// A problem at the start of `loc` in `source`.
+    pub fn located(source: &str, loc: Loc, message: String) -> Self {
+        let (line, column) = line_and_column(source, loc.start());
+        Diagnostic {
+            line: Some(line),
+            column: Some(column),
+            message,
+            fix: None,
+        }
+    }
+
+    /// A problem the compiler cannot locate.
+    pub fn unlocated(message: String) -> Self {
+        Diagnostic {
+            line: None,
+            column: None,
+            message,
+            fix: None,
+        }
+    }
+
+    pub fn from_syntax_error(source: &str, err: SyntaxError) -> Self {
+        let position = err.loc.map(|loc| line_and_column(source, loc.start()));
+        let fix = err.fix.map(|fix| {
+            let (line, column) = line_and_column(source, fix.loc.start());
+            let (end_line, end_column) = line_and_column(source, fix.loc.end());
+            DiagnosticFix {
+                line,
+                column,
+                end_line,
+                end_column,
+                replacement: fix.replacement,
+            }
+        });
+        Diagnostic {
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+            message: err.message,
+            fix,
+        }
+    }
+}
+
+/// Parses an address given as a `0x` literal, as in the IR, or as 64 hex digits.
+pub fn parse_address(address: &str) -> Result<AccountAddress, Diagnostic> {
+    let parsed = if address.starts_with("0x") {
+        AccountAddress::from_hex_literal(address)
+    } else {
+        address.parse()
+    };
+    parsed.map_err(|err| Diagnostic::unlocated(format!("Invalid address {}: {}", address, err)))
+}
@@ -0,0 +1,222 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Transaction directives of functional tests.
+//!
+//! A functional test is a sequence of transactions separated by `//! new-transaction` lines. The
+//! `//!` lines of a transaction configure how it is run, e.g.:
+//!
+//! ```text
+//! //! sender: alice
+//! //! args: 42, {{bob}}
+//! //! max-gas: 1000
+//! //! no-run: runtime
+//! ```
+//!
+//! and [annotations](../annotations/index.html) state its expected outcome. All whitespace of a
+//! directive is ignored. [`parse_test_directives`](fn.parse_test_directives.html) collects them
+//! into [`TestDirectives`](struct.TestDirectives.html), so that test harnesses share one parser.
+
+use crate::{annotations::Annotation, errors::AnnotationError};
+use anyhow::{bail, Error, Result};
+use libra_types::transaction::{parse_as_transaction_argument, TransactionArgument};
+use std::{collections::BTreeSet, str::FromStr};
+
+/// A step in the pipeline a transaction goes through, which a test can skip with `no-run`.
+//  Ord is derived as we need to be able to determine if one stage is before another.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
+pub enum Stage {
+    Compiler,
+    Verifier,
+    Serializer,
+    Runtime,
+}
+
+impl FromStr for Stage {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "compiler" => Ok(Stage::Compiler),
+            "verifier" => Ok(Stage::Verifier),
+            "serializer" => Ok(Stage::Serializer),
+            "runtime" => Ok(Stage::Runtime),
+            _ => bail!("unrecognized stage '{:?}'", s),
+        }
+    }
+}
+
+/// A transaction argument, with the addresses of accounts still to be resolved.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Argument {
+    /// `{{name}}`: the address of the account `name`.
+    AddressOf(String),
+    SelfContained(TransactionArgument),
+}
+
+impl FromStr for Argument {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(arg) = parse_as_transaction_argument(s) {
+            return Ok(Argument::SelfContained(arg));
+        }
+        if s.starts_with("{{") && s.ends_with("}}") {
+            return Ok(Argument::AddressOf(s[2..s.len() - 2].to_string()));
+        }
+        bail!("failed to parse '{}' as argument", s)
+    }
+}
+
+/// A `//!` line configuring a transaction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TestDirective {
+    /// `//! no-run: <stage>, ...`
+    DisableStages(Vec<Stage>),
+    /// `//! sender: <account>`, with the name of the account in lowercase.
+    Sender(String),
+    /// `//! args: <argument>, ...`
+    Arguments(Vec<Argument>),
+    /// `//! max-gas: <amount>`
+    MaxGas(u64),
+    /// `//! sequence-number: <number>`
+    SequenceNumber(u64),
+    /// `//! expiration-time: <seconds>`
+    ExpirationTime(u64),
+}
+
+impl FromStr for TestDirective {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.split_whitespace().collect::<String>();
+        if !s.starts_with("//!") {
+            bail!("txn config entry must start with //!");
+        }
+        let s = &s[3..];
+        let (key, value) = match s.find(':') {
+            Some(colon) => (&s[..colon], &s[colon + 1..]),
+            None => bail!("failed to parse '{}' as transaction config entry", s),
+        };
+        let list = || value.split(',').map(|s| s.trim()).filter(|s| !s.is_empty());
+        Ok(match key {
+            "sender" if value.is_empty() => bail!("sender cannot be empty"),
+            "sender" => TestDirective::Sender(value.to_ascii_lowercase()),
+            "args" => TestDirective::Arguments(list().map(str::parse).collect::<Result<_>>()?),
+            "no-run" => {
+                TestDirective::DisableStages(list().map(str::parse).collect::<Result<_>>()?)
+            }
+            "max-gas" => TestDirective::MaxGas(value.parse()?),
+            "sequence-number" => TestDirective::SequenceNumber(value.parse()?),
+            "expiration-time" => TestDirective::ExpirationTime(value.parse()?),
+            _ => bail!("failed to parse '{}' as transaction config entry", s),
+        })
+    }
+}
+
+impl TestDirective {
+    /// Parses a line as a directive. Returns `None` if the line is not a `//!` line.
+    pub fn try_parse(s: &str) -> Result<Option<Self>> {
+        if s.starts_with("//!") {
+            Ok(Some(s.parse::<TestDirective>()?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Checks whether a line denotes the start of a new transaction.
+pub fn is_new_transaction(s: &str) -> bool {
+    let s = s.trim();
+    if !s.starts_with("//!") {
+        return false;
+    }
+    s[3..].trim_start() == "new-transaction"
+}
+
+/// The configuration and expected outcome of a transaction of a functional test.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TestDirectives {
+    pub disabled_stages: BTreeSet<Stage>,
+    /// The name of the sending account, `None` for the default account.
+    pub sender: Option<String>,
+    pub arguments: Option<Vec<Argument>>,
+    pub max_gas: Option<u64>,
+    pub sequence_number: Option<u64>,
+    /// The expiration time, in seconds.
+    pub expiration_time: Option<u64>,
+    /// The expected outcome of the transaction, from its annotations.
+    pub expected: Vec<Annotation>,
+}
+
+impl TestDirectives {
+    /// Collects directives, failing if one is given twice or if a stage is disabled twice.
+    pub fn build(directives: &[TestDirective]) -> Result<Self> {
+        let mut result = TestDirectives::default();
+        for directive in directives {
+            result.add(directive.clone())?;
+        }
+        Ok(result)
+    }
+
+    /// Adds a directive, failing if it was already given or if it disables a stage twice.
+    pub fn add(&mut self, directive: TestDirective) -> Result<()> {
+        match directive {
+            TestDirective::Sender(name) => set_once(&mut self.sender, name, "sender"),
+            TestDirective::Arguments(args) => {
+                set_once(&mut self.arguments, args, "transaction arguments")
+            }
+            TestDirective::DisableStages(stages) => {
+                for stage in stages {
+                    if !self.disabled_stages.insert(stage) {
+                        bail!("duplicate stage '{:?}' in black list", stage);
+                    }
+                }
+                Ok(())
+            }
+            TestDirective::MaxGas(n) => set_once(&mut self.max_gas, n, "max gas amount"),
+            TestDirective::SequenceNumber(n) => {
+                set_once(&mut self.sequence_number, n, "sequence number")
+            }
+            TestDirective::ExpirationTime(n) => {
+                set_once(&mut self.expiration_time, n, "expiration time")
+            }
+        }
+    }
+
+    #[inline]
+    pub fn is_stage_disabled(&self, stage: Stage) -> bool {
+        self.disabled_stages.contains(&stage)
+    }
+}
+
+fn set_once<T>(slot: &mut Option<T>, value: T, what: &str) -> Result<()> {
+    if slot.is_some() {
+        bail!("{} already set", what);
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+/// Returns the directives and annotations of a transaction of a functional test, given its
+/// text. Lines other than `//!` lines are ignored, and a `//!` line that is neither a directive
+/// nor an annotation is an error.
+pub fn parse_test_directives(text: &str) -> std::result::Result<TestDirectives, AnnotationError> {
+    let mut result = TestDirectives::default();
+    for (idx, line) in text.lines().enumerate() {
+        let error = |message: String| AnnotationError {
+            line: idx + 1,
+            message,
+        };
+        if let Some(annotation) = Annotation::parse_line(line).map_err(error)? {
+            result.expected.push(annotation);
+        } else if let Some(directive) =
+            TestDirective::try_parse(line.trim()).map_err(|err| error(err.to_string()))?
+        {
+            result
+                .add(directive)
+                .map_err(|err| error(err.to_string()))?;
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,218 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Generation of Markdown reference documentation for modules.
+//!
+//! The documentation of a module lists its structs with their fields and invariants, and its
+//! functions with their signatures, `acquires` lists and specification clauses. The AST does not
+//! keep comments, so the documentation of an item is recovered from the source: it is the block
+//! of comment lines directly above the item. A field can also be documented by a comment at the
+//! end of its line.
+
+use crate::{
+    formatter::{type_formals, type_text},
+    parser::{parse_module, strip_comments},
+};
+use anyhow::Result;
+use move_ir_types::ast::{
+    Function, FunctionBody, FunctionName, FunctionVisibility, Loc, ModuleDefinition,
+    StructDefinition, StructDefinitionFields,
+};
+
+/// Parses the source of a module and returns its documentation.
+pub fn module_documentation(source: &str) -> Result<String> {
+    let module = parse_module(source)?;
+    Ok(render_module(source, &module))
+}
+
+/// Returns the documentation of a module parsed from `source`.
+pub fn render_module(source: &str, module: &ModuleDefinition) -> String {
+    let renderer = Renderer {
+        source,
+        stripped: strip_comments(source),
+    };
+    let mut out = vec![format!("# Module `{}`", module.name)];
+    let start = renderer.stripped.len() - renderer.stripped.trim_start().len();
+    out.extend(renderer.doc_paragraph(start));
+
+    if !module.structs.is_empty() {
+        out.push("## Structs".to_string());
+        for struct_def in &module.structs {
+            out.extend(renderer.struct_definition(struct_def));
+        }
+    }
+    if !module.functions.is_empty() {
+        out.push("## Functions".to_string());
+        for (name, function) in &module.functions {
+            out.extend(renderer.function(name, function));
+        }
+    }
+    let mut doc = out.join("\n\n");
+    doc.push('\n');
+    doc
+}
+
+struct Renderer<'a> {
+    source: &'a str,
+    /// The source with comments blanked out, which is what the AST locations refer to.
+    stripped: String,
+}
+
+impl<'a> Renderer<'a> {
+    fn doc_before(&self, pos: usize) -> Vec<&'a str> {
+        doc_before(self.source, pos)
+    }
+
+    fn doc_after(&self, pos: usize) -> Option<&'a str> {
+        doc_after(self.source, pos)
+    }
+
+    /// Returns the documentation above the item starting at `pos` as a paragraph, if there is
+    /// any.
+    fn doc_paragraph(&self, pos: usize) -> Option<String> {
+        let doc = self.doc_before(pos);
+        if doc.is_empty() {
+            None
+        } else {
+            Some(doc.join("\n"))
+        }
+    }
+
+    /// Returns the source of a specification clause, on a single line.
+    fn spec_text(&self, loc: Loc) -> String {
+        self.stripped[loc.start().to_usize()..loc.end().to_usize()]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn struct_definition(&self, struct_def: &StructDefinition) -> Vec<String> {
+        let s = &struct_def.value;
+        let kind = if s.is_nominal_resource {
+            "resource"
+        } else {
+            "struct"
+        };
+        let mut out = vec![format!(
+            "### {} `{}{}`",
+            kind,
+            s.name,
+            type_formals(&s.type_formals)
+        )];
+        out.extend(self.doc_paragraph(struct_def.span.start().to_usize()));
+        match &s.fields {
+            StructDefinitionFields::Native => out.push("Native struct.".to_string()),
+            StructDefinitionFields::Move { fields } if fields.is_empty() => {
+                out.push("No fields.".to_string())
+            }
+            StructDefinitionFields::Move { fields } => {
+                let mut table = vec![
+                    "| Field | Type | Description |".to_string(),
+                    "|-------|------|-------------|".to_string(),
+                ];
+                for (field, ty) in fields {
+                    let mut doc = self.doc_before(field.span.start().to_usize());
+                    doc.extend(self.doc_after(field.span.start().to_usize()));
+                    table.push(format!(
+                        "| `{}` | `{}` | {} |",
+                        field.value,
+                        type_text(ty),
+                        doc.join(" ").replace('|', "\\|")
+                    ));
+                }
+                out.push(table.join("\n"));
+            }
+        }
+        if !s.invariants.is_empty() {
+            out.push("Invariants:".to_string());
+            out.push(self.spec_list(s.invariants.iter().map(|i| i.span)));
+        }
+        out
+    }
+
+    fn function(&self, name: &FunctionName, function: &Function) -> Vec<String> {
+        let f = &function.value;
+        let mut out = vec![format!("### Function `{}`", name)];
+
+        let mut signature = String::new();
+        if let FunctionBody::Native = f.body {
+            signature.push_str("native ");
+        }
+        if let FunctionVisibility::Public = f.visibility {
+            signature.push_str("public ");
+        }
+        let formals: Vec<String> = f
+            .signature
+            .formals
+            .iter()
+            .map(|(var, ty)| format!("{}: {}", var.value, type_text(ty)))
+            .collect();
+        signature.push_str(&format!(
+            "{}{}({})",
+            name,
+            type_formals(&f.signature.type_formals),
+            formals.join(", ")
+        ));
+        if !f.signature.return_type.is_empty() {
+            let return_types: Vec<String> = f.signature.return_type.iter().map(type_text).collect();
+            signature.push_str(&format!(": {}", return_types.join(" * ")));
+        }
+        out.push(format!("```\n{}\n```", signature));
+
+        out.extend(self.doc_paragraph(function.span.start().to_usize()));
+        if !f.acquires.is_empty() {
+            let acquires: Vec<String> = f.acquires.iter().map(|s| format!("`{}`", s)).collect();
+            out.push(format!("Acquires: {}", acquires.join(", ")));
+        }
+        if !f.specifications.is_empty() {
+            out.push("Specification:".to_string());
+            out.push(self.spec_list(f.specifications.iter().map(|c| c.span)));
+        }
+        out
+    }
+
+    fn spec_list(&self, locs: impl Iterator<Item = Loc>) -> String {
+        locs.map(|loc| format!("- `{}`", self.spec_text(loc)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Returns the comment lines of `source` directly above the item starting at `pos`, without their
+/// `//`. Items that do not start their line have none.
+pub(crate) fn doc_before(source: &str, pos: usize) -> Vec<&str> {
+    let line_start = source[..pos].rfind('\n').map_or(0, |idx| idx + 1);
+    if !source[line_start..pos].trim().is_empty() {
+        return vec![];
+    }
+    let mut doc: Vec<&str> = source[..line_start]
+        .lines()
+        .rev()
+        .map(str::trim)
+        .take_while(|line| line.starts_with("//"))
+        .map(comment_text)
+        .collect();
+    doc.reverse();
+    doc
+}
+
+/// Returns the comment at the end of the line of the declaration `name: type` starting at `pos`,
+/// without its `//`, if the declaration is the only one on its line.
+pub(crate) fn doc_after(source: &str, pos: usize) -> Option<&str> {
+    let rest = source[pos..].lines().next().unwrap_or("");
+    let idx = rest.find("//")?;
+    if rest[..idx].matches(':').count() > 1 {
+        return None;
+    }
+    Some(comment_text(&rest[idx..]))
+}
+
+fn comment_text(comment: &str) -> &str {
+    let text = comment.trim_start_matches('/');
+    let text = if text.starts_with(' ') {
+        &text[1..]
+    } else {
+        text
+    };
+    text.trim_end()
+}
@@ -1,7 +1,10 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use libra_types::vm_error::VMStatus;
+use crate::limits::Limit;
+use libra_types::{identifier::Identifier, vm_error::VMStatus};
+use move_ir_types::ast::{FunctionName, Kind, Loc, ModuleName, StructName, TypeVar_};
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Clone, Debug, Eq, Error, Ord, PartialEq, PartialOrd)]
@@ -9,3 +12,257 @@ pub enum InternalCompilerError {
     #[error("Post-compile bounds check errors: {0:?}")]
     BoundsCheckErrors(Vec<VMStatus>),
 }
+
+/// An error found by the type checker, located in the IR source.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{message}")]
+pub struct TypeError {
+    /// The location of the offending code.
+    pub loc: Loc,
+    /// What is wrong with it.
+    pub message: String,
+}
+
+/// A use of an imported module that does not match its compiled form, located in the IR source.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{message}")]
+pub struct ImportError {
+    /// The location of the offending code.
+    pub loc: Loc,
+    /// What is wrong with it.
+    pub message: String,
+}
+
+/// A misuse of a tuple type or value, located in the IR source.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{message}")]
+pub struct TupleError {
+    /// The location of the offending code.
+    pub loc: Loc,
+    /// What is wrong with it.
+    pub message: String,
+}
+
+/// A multi-value assignment of a call returning a different number of values, located in the IR
+/// source.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("cannot assign {returned} values returned by `{function}` to {assigned} targets")]
+pub struct ArityError {
+    /// The location of the assigned locals and references, from the first to the last.
+    pub lvalues: Loc,
+    /// The location of the call.
+    pub call: Loc,
+    /// The called function, qualified by the alias of its module.
+    pub function: String,
+    /// The number of locals and references assigned.
+    pub assigned: usize,
+    /// The number of values the function returns.
+    pub returned: usize,
+}
+
+/// A native function declaration that does not match the implementation of the VM, located in the
+/// IR source.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{message}")]
+pub struct NativeError {
+    /// The location of the offending declaration.
+    pub loc: Loc,
+    /// What is wrong with it.
+    pub message: String,
+}
+
+/// A declaration exceeding a limit of the binary format, located in the IR source when the
+/// declaration has a location.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error(
+    "{} has {} {}, more than the {} the binary format allows",
+    .declaration,
+    .count,
+    .limit,
+    .limit.max()
+)]
+pub struct LimitError {
+    /// The location of the offending declaration, unless it is a module, which has none.
+    pub loc: Option<Loc>,
+    /// The offending declaration, e.g. "function `f`".
+    pub declaration: String,
+    /// The limit exceeded.
+    pub limit: Limit,
+    /// The number of items the declaration has.
+    pub count: usize,
+}
+
+/// Declarations of a module with the same name, kept together by their `cfg` attributes,
+/// located in the IR source when the declaration has a location.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("duplicate {kind} {name} for the enabled cfg flags and features")]
+pub struct CfgError {
+    /// The location of the second declaration, unless it is an import, which has none.
+    pub loc: Option<Loc>,
+    /// What the name is for, e.g. "function".
+    pub kind: &'static str,
+    pub name: String,
+}
+
+/// An error found by the parser, located in the IR source when the parser knows where it is.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{message}")]
+pub struct SyntaxError {
+    /// The location of the offending code.
+    pub loc: Option<Loc>,
+    /// What is wrong with it.
+    pub message: String,
+    /// An edit fixing it, when the parser knows one.
+    pub fix: Option<Fix>,
+}
+
+/// An edit of the IR source fixing an error, which tools can apply as is.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Fix {
+    /// The location of the code to replace.
+    pub loc: Loc,
+    /// The code to replace it with.
+    pub replacement: String,
+}
+
+/// An error building a set of modules, located in the source of a module when the compiler knows
+/// where it is.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{message}")]
+pub struct BuildError {
+    /// The file of the offending module, when built from a directory.
+    pub path: Option<PathBuf>,
+    /// The offending module, unless the error is in a file that does not parse.
+    pub module: Option<ModuleName>,
+    /// The location of the offending code.
+    pub loc: Option<Loc>,
+    /// What is wrong with it.
+    pub message: String,
+}
+
+/// An error in a function left out of a partially compiled module, located in the IR source.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("in `{function}`: {message}")]
+pub struct FunctionError {
+    /// The function that failed to compile.
+    pub function: FunctionName,
+    /// The location of the offending code, or of the function when the compiler does not know
+    /// where in it the error is.
+    pub loc: Loc,
+    /// What is wrong with it.
+    pub message: String,
+}
+
+/// A construct of the compiled code that needs a newer version of the bytecode than the target
+/// one, located in the IR source.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{construct} needs bytecode version {needed}, but the target version is {target}")]
+pub struct TargetError {
+    /// The location of the offending code.
+    pub loc: Loc,
+    /// The offending construct, e.g. "instruction `Shl`".
+    pub construct: String,
+    /// The first version of the bytecode with the construct.
+    pub needed: u64,
+    /// The version targeted.
+    pub target: u64,
+}
+
+/// An error substituting the placeholders of a script.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum PlaceholderError {
+    /// A placeholder of the script was not given a value.
+    #[error("no value for placeholder {{{{{name}}}}}")]
+    Missing { loc: Loc, name: Identifier },
+    /// A value was given for a placeholder the script does not have.
+    #[error("no placeholder {{{{{name}}}}} in the script")]
+    Unused { name: Identifier },
+}
+
+/// An error specializing a generic script with type arguments.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum SpecializeError {
+    /// The number of type arguments differs from the number of type parameters of the script.
+    #[error("the script has {expected} type parameters, but {given} type arguments were given")]
+    Arity { expected: usize, given: usize },
+    /// A type argument is a reference or mentions a type parameter.
+    #[error("type parameter `{name}` is given `{type_}`, which is not a concrete type")]
+    NotConcrete { name: TypeVar_, type_: String },
+    /// A type argument of a primitive type is given for a resource type parameter.
+    #[error("type parameter `{name}` has kind `{kind}`, but is given `{type_}`")]
+    KindMismatch {
+        name: TypeVar_,
+        kind: Kind,
+        type_: String,
+    },
+}
+
+/// An error generating the transaction builder of a script.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum BuilderError {
+    /// The name of the script or of an argument cannot be used in Rust.
+    #[error("script {script}: `{name}` is not a valid Rust identifier")]
+    InvalidName { script: String, name: String },
+    /// An argument has a type that transactions cannot pass.
+    #[error("script {script}: transactions cannot pass argument `{argument}` of type {type_}")]
+    UnsupportedArgument {
+        script: String,
+        argument: String,
+        type_: String,
+    },
+    /// The script has type arguments, which transactions cannot pass.
+    #[error("script {script}: transactions cannot pass type arguments")]
+    TypeArguments { script: String },
+}
+
+/// A malformed annotation in the header of a source.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("line {line}: {message}")]
+pub struct AnnotationError {
+    /// The 1-based line of the annotation.
+    pub line: usize,
+    /// What is wrong with it.
+    pub message: String,
+}
+
+/// An entry of an `acquires` list that does not name a resource declared in the module.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum AcquiresError {
+    /// The entry names a struct of the module, which cannot be stored in global storage.
+    #[error("function {function} acquires {name}, which is a struct and not a resource")]
+    NotAResource {
+        /// The location of the function header.
+        loc: Loc,
+        function: FunctionName,
+        name: StructName,
+        /// The resource of the module with the closest name, if any is close.
+        suggestion: Option<StructName>,
+    },
+    /// The entry names nothing declared in the module.
+    #[error("function {function} acquires {name}, which is not declared in the module")]
+    Unknown {
+        /// The location of the function header.
+        loc: Loc,
+        function: FunctionName,
+        name: StructName,
+        /// The resource of the module with the closest name, if any is close.
+        suggestion: Option<StructName>,
+    },
+}
+
+impl AcquiresError {
+    /// The location of the header of the function with the invalid entry.
+    pub fn loc(&self) -> Loc {
+        match self {
+            AcquiresError::NotAResource { loc, .. } | AcquiresError::Unknown { loc, .. } => *loc,
+        }
+    }
+
+    /// The resource the entry was probably meant to name.
+    pub fn suggestion(&self) -> Option<&StructName> {
+        match self {
+            AcquiresError::NotAResource { suggestion, .. }
+            | AcquiresError::Unknown { suggestion, .. } => suggestion.as_ref(),
+        }
+    }
+}
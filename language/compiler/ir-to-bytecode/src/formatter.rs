@@ -0,0 +1,1164 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A source formatter for the IR.
+//!
+//! The formatter parses a script or module and prints it back in a canonical layout: one
+//! statement per line, four space indentation, single spaces around binary operators and after
+//! commas, and argument lists that do not fit in the maximum width broken into one argument per
+//! line with a trailing comma. Blank lines between items are kept, collapsed to at most one.
+//!
+//! The AST does not keep comments, so they are recovered from the source and attached to the
+//! item that follows them, or to the end of the line they trail. Comments in the middle of a
+//! statement are moved above it. Specification clauses are copied from the source with their
+//! whitespace normalized, and literals keep their original spelling.
+//!
+//! An AST can also be printed on its own, without the source it was parsed from. It is then
+//! printed without comments, with literals in their canonical spelling, and without the
+//! specification clauses, invariants and synthetics, which are only ever copied from the source.
+
+use crate::parser::{parse_script_or_module, strip_comments};
+use anyhow::Result;
+use libra_types::account_address::AccountAddress;
+use move_ir_types::ast::{
+    BinOp, Block, Builtin, CfgPredicate, Cmd_, CopyableVal, CopyableVal_, Exp, Exp_, FunctionBody,
+    FunctionCall_, FunctionName, FunctionVisibility, Function_, IfElse, ImportDefinition,
+    InlineHint, Kind, LValue, LValue_, Loc, ModuleDefinition, ModuleIdent, Script, ScriptOrModule,
+    Statement, StructDefinition, StructDefinitionFields, Type, TypeVar, UnaryOp,
+};
+use std::collections::VecDeque;
+
+/// Options controlling the layout of formatted source.
+#[derive(Clone, Debug)]
+pub struct FormatOptions {
+    /// The number of spaces per indentation level.
+    pub indent_width: usize,
+    /// The width lines are kept within where the formatter knows how to break them.
+    pub max_width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            max_width: 100,
+        }
+    }
+}
+
+/// Formats the source of a script or a module.
+///
+/// Fails if the source does not parse.
+pub fn format_source(source: &str, options: &FormatOptions) -> Result<String> {
+    let script_or_module = parse_script_or_module(source)?;
+    let mut printer = Printer::new(source, options);
+    match &script_or_module {
+        ScriptOrModule::Script(script) => printer.script(script),
+        ScriptOrModule::Module(module) => printer.module(module),
+    }
+    Ok(printer.finish())
+}
+
+/// Prints a module from its AST alone.
+pub fn format_module(module: &ModuleDefinition, options: &FormatOptions) -> String {
+    let mut printer = Printer::new("", options);
+    printer.module(module);
+    printer.finish()
+}
+
+/// Prints a script from its AST alone.
+pub fn format_script(script: &Script, options: &FormatOptions) -> String {
+    let mut printer = Printer::new("", options);
+    printer.script(script);
+    printer.finish()
+}
+
+/// Prints an expression from its AST alone, on a single line.
+pub fn format_exp(e: &Exp) -> String {
+    Printer::new("", &FormatOptions::default()).flat(e)
+}
+
+/// Prints a command from its AST alone, on a single line.
+pub fn format_cmd(cmd: &Cmd_) -> String {
+    let options = FormatOptions {
+        max_width: usize::max_value(),
+        ..FormatOptions::default()
+    };
+    Printer::new("", &options).cmd(cmd)
+}
+
+struct Comment {
+    start: usize,
+    end: usize,
+    text: String,
+    /// Whether the comment is the only thing on its line.
+    own_line: bool,
+}
+
+fn collect_comments(source: &str) -> Vec<Comment> {
+    let mut comments = vec![];
+    let mut offset = 0;
+    for line in source.split('\n') {
+        if let Some(idx) = line.find("//") {
+            let text = line[idx..].trim_end();
+            comments.push(Comment {
+                start: offset + idx,
+                end: offset + idx + text.len(),
+                text: text.to_string(),
+                own_line: line[..idx].trim().is_empty(),
+            });
+        }
+        offset += line.len() + 1;
+    }
+    comments
+}
+
+struct Printer<'a> {
+    source: &'a str,
+    /// The source with comments blanked out, which is what the AST locations refer to.
+    stripped: String,
+    options: &'a FormatOptions,
+    comments: Vec<Comment>,
+    next_comment: usize,
+    /// The current indentation, in spaces.
+    indent: usize,
+    /// The source position just after the last item or comment printed.
+    last_pos: usize,
+    out: String,
+}
+
+impl<'a> Printer<'a> {
+    fn new(source: &'a str, options: &'a FormatOptions) -> Self {
+        Self {
+            source,
+            stripped: strip_comments(source),
+            options,
+            comments: collect_comments(source),
+            next_comment: 0,
+            indent: 0,
+            last_pos: 0,
+            out: String::new(),
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.comments_before(usize::max_value());
+        let len = self.out.trim_end().len();
+        self.out.truncate(len);
+        self.out.push('\n');
+        self.out
+    }
+
+    //**********************************************************************************************
+    // Lines and comments
+    //**********************************************************************************************
+
+    fn line(&mut self, text: &str) {
+        if !text.is_empty() {
+            self.out.push_str(&" ".repeat(self.indent));
+            self.out.push_str(text);
+        }
+        self.out.push('\n');
+    }
+
+    fn at_block_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with("{\n") || self.out.ends_with("\n\n")
+    }
+
+    /// Keeps a blank line between the last item printed and the one at `pos`, if the source has
+    /// one.
+    fn blank_line_before(&mut self, pos: usize) {
+        if self.at_block_start() || pos <= self.last_pos {
+            return;
+        }
+        // Without the source, there are no blank lines to keep.
+        let between = match self.source.get(self.last_pos..pos) {
+            Some(between) => between,
+            None => return,
+        };
+        let lines: Vec<&str> = between.split('\n').collect();
+        // The first and last pieces are the ends of the lines the two items are on.
+        if lines.len() > 2
+            && lines[1..lines.len() - 1]
+                .iter()
+                .any(|l| l.trim().is_empty())
+        {
+            self.out.push('\n');
+        }
+    }
+
+    /// Prints, each on its own line, the comments that start before `pos`.
+    fn comments_before(&mut self, pos: usize) {
+        while self.next_comment < self.comments.len()
+            && self.comments[self.next_comment].start < pos
+        {
+            let (start, end) = {
+                let comment = &self.comments[self.next_comment];
+                (comment.start, comment.end)
+            };
+            self.blank_line_before(start);
+            let text = self.comments[self.next_comment].text.clone();
+            self.line(&text);
+            self.last_pos = end;
+            self.next_comment += 1;
+        }
+    }
+
+    /// Prints what comes before an item starting at `pos`: its leading comments and the blank
+    /// line separating it from the previous item.
+    fn leading(&mut self, pos: usize) {
+        self.comments_before(pos);
+        self.blank_line_before(pos);
+    }
+
+    /// Prepares for an item printed on a single line: leading comments, and then the comments
+    /// inside it, which have nowhere else to go.
+    fn begin(&mut self, loc: Loc) {
+        self.leading(loc.start().to_usize());
+        self.comments_before(loc.end().to_usize());
+    }
+
+    /// Appends the comment trailing the item ending at `end` to the line just printed.
+    fn trailing_comment(&mut self, end: usize) {
+        if let Some(comment) = self.comments.get(self.next_comment) {
+            if !comment.own_line
+                && comment.start >= end
+                && !self.source[end..comment.start].contains('\n')
+            {
+                self.out.pop();
+                self.out.push(' ');
+                self.out.push_str(&comment.text);
+                self.out.push('\n');
+                self.last_pos = comment.end;
+                self.next_comment += 1;
+                return;
+            }
+        }
+        self.last_pos = std::cmp::max(self.last_pos, end);
+    }
+
+    /// Returns the source of a specification clause, on a single line. The text is empty when
+    /// printing without the source.
+    fn spec_text(&self, loc: Loc) -> String {
+        self.stripped
+            .get(loc.start().to_usize()..loc.end().to_usize())
+            .unwrap_or("")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Returns the position of the first occurrence of `keyword` at or after `from`.
+    fn find_keyword(&self, keyword: &str, from: usize) -> Option<usize> {
+        let mut pos = from;
+        while let Some(idx) = self.stripped.get(pos..).and_then(|rest| rest.find(keyword)) {
+            let start = pos + idx;
+            let end = start + keyword.len();
+            let is_ident_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+            let before = self.stripped[..start].chars().next_back();
+            let after = self.stripped[end..].chars().next();
+            if !before.map_or(false, is_ident_char) && !after.map_or(false, is_ident_char) {
+                return Some(start);
+            }
+            pos = end;
+        }
+        None
+    }
+
+    //**********************************************************************************************
+    // Scripts and modules
+    //**********************************************************************************************
+
+    fn script(&mut self, script: &Script) {
+        let pragma_end = self.language_pragma(script.language_version);
+        let end = self.imports(&script.imports, pragma_end);
+        let main_start = self.find_keyword("main", end).unwrap_or(end);
+        self.leading(main_start);
+        let function = &script.main.value;
+        let main = format!("main{}", type_formals(&function.signature.type_formals));
+        let suffix = return_type(&function.signature.return_type);
+        let formals = self.formals(function, main.len() + suffix.len());
+        let header = format!("{}({}){}", main, formals, suffix);
+        self.function_body(header, function, script.main.span);
+    }
+
+    fn module(&mut self, module: &ModuleDefinition) {
+        let pragma_end = self.language_pragma(module.language_version);
+        let rest = &self.stripped[pragma_end..];
+        let start = pragma_end + rest.len() - rest.trim_start().len();
+        self.leading(start);
+        self.line(&format!("module {} {{", module.name));
+        self.indent += self.options.indent_width;
+        self.last_pos = start;
+
+        let body_start = self.stripped[start..]
+            .find('{')
+            .map_or(start, |idx| start + idx + 1);
+        self.imports(&module.imports, body_start);
+        for synthetic in &module.synthetics {
+            let text = self.spec_text(synthetic.span);
+            if text.is_empty() {
+                continue;
+            }
+            self.begin(synthetic.span);
+            self.line(&text);
+            self.trailing_comment(synthetic.span.end().to_usize());
+        }
+        for struct_def in &module.structs {
+            self.struct_definition(struct_def);
+        }
+        for (name, function) in &module.functions {
+            let start = function.span.start().to_usize();
+            self.attributes(
+                &function.value.cfg,
+                function.value.inline,
+                &function.value.annotations,
+                start,
+            );
+            self.leading(start);
+            let header = self.function_header(name, &function.value);
+            self.function_body(header, &function.value, function.span);
+        }
+
+        // The closing brace of the module is the last token of the source.
+        let close = self.stripped.trim_end().len().saturating_sub(1);
+        self.comments_before(close);
+        self.indent -= self.options.indent_width;
+        self.line("}");
+        self.trailing_comment(close + 1);
+    }
+
+    /// Prints the language pragma starting the source, if there is one, and returns the position
+    /// after it.
+    fn language_pragma(&mut self, language_version: Option<u64>) -> usize {
+        let version = match language_version {
+            Some(version) => version,
+            None => return 0,
+        };
+        let start = self.find_keyword("pragma", 0).unwrap_or(0);
+        let end = self.stripped[start..]
+            .find(';')
+            .map_or(start, |idx| start + idx + 1);
+        self.leading(start);
+        self.comments_before(end);
+        self.line(&format!("pragma language {};", version));
+        self.trailing_comment(end);
+        end
+    }
+
+    /// Prints the attributes of the declaration starting at `start`, if it has any, after what
+    /// comes before the first of them.
+    fn attributes(
+        &mut self,
+        cfg: &Option<CfgPredicate>,
+        inline: Option<InlineHint>,
+        annotations: &[(String, String)],
+        start: usize,
+    ) {
+        let mut attributes = vec![];
+        if let Some(predicate) = cfg {
+            attributes.push(format!("#[cfg({})]", predicate));
+        }
+        if let Some(hint) = inline {
+            attributes.push(format!("#[{}]", hint));
+        }
+        if !annotations.is_empty() {
+            let entries: Vec<_> = annotations
+                .iter()
+                .map(|(key, value)| format!("{} = \"{}\"", key, value))
+                .collect();
+            attributes.push(format!("#[annotate({})]", entries.join(", ")));
+        }
+        if attributes.is_empty() {
+            return;
+        }
+        let attribute_start = self
+            .stripped
+            .get(self.last_pos..start)
+            .and_then(|between| between.find("#["))
+            .map_or(start, |idx| self.last_pos + idx);
+        self.leading(attribute_start);
+        for attribute in &attributes {
+            self.line(attribute);
+        }
+        // The declaration follows the attributes, even if the source has a blank line between.
+        self.last_pos = std::cmp::max(self.last_pos, start);
+    }
+
+    /// Prints the imports, the first of which is at or after `from`, and returns the position
+    /// after the last one.
+    fn imports(&mut self, imports: &[ImportDefinition], from: usize) -> usize {
+        let mut pos = from;
+        for import in imports {
+            let start = self.find_keyword("import", pos).unwrap_or(pos);
+            let end = self.stripped[start..]
+                .find(';')
+                .map_or(start, |idx| start + idx + 1);
+            self.attributes(&import.cfg, None, &[], start);
+            self.leading(start);
+            self.comments_before(end);
+            self.line(&import_text(import));
+            self.trailing_comment(end);
+            pos = end;
+        }
+        pos
+    }
+
+    fn struct_definition(&mut self, struct_def: &StructDefinition) {
+        let start = struct_def.span.start().to_usize();
+        let end = struct_def.span.end().to_usize();
+        let s = &struct_def.value;
+        self.attributes(&s.cfg, None, &[], start);
+        self.leading(start);
+        let header = format!(
+            "{} {}{}",
+            if s.is_nominal_resource {
+                "resource"
+            } else {
+                "struct"
+            },
+            s.name,
+            type_formals(&s.type_formals)
+        );
+        let fields = match &s.fields {
+            StructDefinitionFields::Native => {
+                self.comments_before(end);
+                self.line(&format!("native {};", header));
+                self.trailing_comment(end);
+                return;
+            }
+            StructDefinitionFields::Move { fields } => fields,
+        };
+        if fields.is_empty()
+            && s.invariants
+                .iter()
+                .all(|invariant| self.spec_text(invariant.span).is_empty())
+        {
+            self.comments_before(end);
+            self.line(&format!("{} {{}}", header));
+            self.trailing_comment(end);
+            return;
+        }
+
+        self.line(&format!("{} {{", header));
+        self.indent += self.options.indent_width;
+        for (field, ty) in fields {
+            self.begin(field.span);
+            self.line(&format!("{}: {},", field.value, type_text(ty)));
+            self.trailing_comment(field.span.end().to_usize());
+        }
+        for invariant in &s.invariants {
+            let text = self.spec_text(invariant.span);
+            if text.is_empty() {
+                continue;
+            }
+            self.begin(invariant.span);
+            self.line(&format!("{},", text));
+            self.trailing_comment(invariant.span.end().to_usize());
+        }
+        self.comments_before(end.saturating_sub(1));
+        self.indent -= self.options.indent_width;
+        self.line("}");
+        self.trailing_comment(end);
+    }
+
+    /// Returns the signature of a module function, up to but excluding the specifications.
+    fn function_header(&self, name: &FunctionName, function: &Function_) -> String {
+        let mut prefix = String::new();
+        if let FunctionBody::Native = function.body {
+            prefix.push_str("native ");
+        }
+        if let FunctionVisibility::Public = function.visibility {
+            prefix.push_str("public ");
+        }
+        prefix.push_str(name.as_inner().as_str());
+        prefix.push_str(&type_formals(&function.signature.type_formals));
+
+        let mut suffix = return_type(&function.signature.return_type);
+        if !function.acquires.is_empty() {
+            suffix.push_str(" acquires ");
+            let acquires: Vec<String> = function.acquires.iter().map(|s| s.to_string()).collect();
+            suffix.push_str(&acquires.join(", "));
+        }
+
+        let formals = self.formals(function, prefix.len() + suffix.len());
+        format!("{}({}){}", prefix, formals, suffix)
+    }
+
+    /// Returns the formal parameters, broken one per line if the signature would otherwise be
+    /// wider than the maximum width. `others` is the width of the rest of the signature.
+    fn formals(&self, function: &Function_, others: usize) -> String {
+        let formals: Vec<String> = function
+            .signature
+            .formals
+            .iter()
+            .map(|(var, ty)| format!("{}: {}", var.value, type_text(ty)))
+            .collect();
+        let flat = formals.join(", ");
+        // The extra width accounts for the parentheses and the opening brace.
+        if formals.is_empty() || self.indent + others + flat.len() + 4 <= self.options.max_width {
+            return flat;
+        }
+        self.broken_list(formals, self.indent)
+    }
+
+    fn function_body(&mut self, header: String, function: &Function_, loc: Loc) {
+        let end = loc.end().to_usize();
+        let specs: Vec<String> = function
+            .specifications
+            .iter()
+            .map(|spec| self.spec_text(spec.span))
+            .filter(|spec| !spec.is_empty())
+            .collect();
+        let (locals, code) = match &function.body {
+            FunctionBody::Native => {
+                if specs.is_empty() {
+                    self.line(&format!("{};", header));
+                } else {
+                    self.line(&header);
+                    self.indent += self.options.indent_width;
+                    let last = specs.len() - 1;
+                    for (i, spec) in specs.iter().enumerate() {
+                        self.line(&if i == last {
+                            format!("{};", spec)
+                        } else {
+                            spec.clone()
+                        });
+                    }
+                    self.indent -= self.options.indent_width;
+                }
+                self.trailing_comment(end);
+                return;
+            }
+            FunctionBody::Move { locals, code } => (locals, code),
+        };
+        if specs.is_empty() {
+            self.line(&format!("{} {{", header));
+        } else {
+            self.line(&header);
+            self.indent += self.options.indent_width;
+            for spec in &specs {
+                self.line(spec);
+            }
+            self.indent -= self.options.indent_width;
+            self.line("{");
+        }
+
+        self.indent += self.options.indent_width;
+        for (var, ty) in locals {
+            self.begin(var.span);
+            self.line(&format!("let {}: {};", var.value, type_text(ty)));
+            self.trailing_comment(var.span.end().to_usize());
+        }
+        self.statements(&code.stmts);
+        self.comments_before(end.saturating_sub(1));
+        self.indent -= self.options.indent_width;
+        self.line("}");
+        self.trailing_comment(end);
+    }
+
+    //**********************************************************************************************
+    // Statements
+    //**********************************************************************************************
+
+    fn statements(&mut self, statements: &VecDeque<Statement>) {
+        let mut statements = statements.iter().peekable();
+        while let Some(statement) = statements.next() {
+            if let Statement::IfElseStatement(if_else) = statement {
+                if let Some((cond, code)) = as_assert(if_else) {
+                    // `assert(..)` is not a command, so the semicolon after it is an empty
+                    // statement.
+                    let has_semicolon = match statements.peek() {
+                        Some(Statement::EmptyStatement) => {
+                            statements.next();
+                            true
+                        }
+                        _ => false,
+                    };
+                    self.assert(cond, code, has_semicolon);
+                    continue;
+                }
+            }
+            self.statement(statement);
+        }
+    }
+
+    fn assert(&mut self, cond: &Exp, code: &Exp, has_semicolon: bool) {
+        self.begin(Loc::new(cond.span.start(), code.span.end()));
+        let end = if has_semicolon { ");" } else { ")" };
+        let flat = format!("assert({}, {}{}", self.flat(cond), self.flat(code), end);
+        if self.indent + flat.len() <= self.options.max_width {
+            self.line(&flat);
+        } else {
+            // The arguments of `assert` cannot have a trailing comma.
+            let inner = self.indent + self.options.indent_width;
+            let spaces = " ".repeat(inner);
+            self.line(&format!(
+                "assert(\n{}{},\n{}{}\n{}{}",
+                spaces,
+                self.exp(cond, inner, inner),
+                spaces,
+                self.exp(code, inner, inner),
+                " ".repeat(self.indent),
+                end
+            ));
+        }
+        self.trailing_comment(code.span.end().to_usize());
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::CommandStatement(cmd) => {
+                self.begin(cmd.span);
+                let text = self.cmd(&cmd.value);
+                self.line(&text);
+                self.trailing_comment(cmd.span.end().to_usize());
+            }
+            Statement::IfElseStatement(if_else) => {
+                if let Some((cond, code)) = as_assert(if_else) {
+                    self.assert(cond, code, false);
+                    return;
+                }
+                self.begin(if_else.cond.span);
+                let cond = self.exp(&if_else.cond, self.indent + "if (".len(), self.indent);
+                self.line(&format!("if ({}) {{", cond));
+                self.block_body(&if_else.if_block);
+                match &if_else.else_block {
+                    None => self.close_block(&if_else.if_block),
+                    Some(else_block) => {
+                        self.line("} else {");
+                        self.block_body(else_block);
+                        self.close_block(else_block);
+                    }
+                }
+            }
+            Statement::WhileStatement(while_) => {
+                self.begin(while_.cond.span);
+                let cond = self.exp(&while_.cond, self.indent + "while (".len(), self.indent);
+                self.line(&format!("while ({}) {{", cond));
+                self.block_body(&while_.block);
+                self.close_block(&while_.block);
+            }
+            Statement::LoopStatement(loop_) => {
+                self.leading(loop_.block.span.start().to_usize());
+                if loop_.results.is_empty() {
+                    self.line("loop {");
+                } else {
+                    self.line(&format!("{} = loop {{", self.lvalues(&loop_.results)));
+                }
+                self.block_body(&loop_.block);
+                if loop_.results.is_empty() {
+                    self.close_block(&loop_.block);
+                } else {
+                    self.line("};");
+                    self.trailing_comment(loop_.block.span.end().to_usize());
+                }
+            }
+            Statement::EmptyStatement => self.line(";"),
+        }
+    }
+
+    /// Prints the statements of a block, and the comments before its closing brace.
+    fn block_body(&mut self, block: &Block) {
+        self.indent += self.options.indent_width;
+        self.statements(&block.value.stmts);
+        self.comments_before(block.span.end().to_usize().saturating_sub(1));
+        self.indent -= self.options.indent_width;
+    }
+
+    fn close_block(&mut self, block: &Block) {
+        self.line("}");
+        self.trailing_comment(block.span.end().to_usize());
+    }
+
+    /// Returns the command with its semicolon. Expressions are laid out with one column
+    /// reserved for the semicolon.
+    fn cmd(&self, cmd: &Cmd_) -> String {
+        let col = self.indent;
+        match cmd {
+            Cmd_::Assign(lvalues, e) => {
+                if lvalues.is_empty() {
+                    return format!("{};", self.exp(e, col + 1, self.indent));
+                }
+                let lhs = self.lvalues(lvalues);
+                format!("{}{};", lhs, self.rhs(e, col + lhs.len()))
+            }
+            Cmd_::Unpack(name, tys, bindings, e) => {
+                let bindings: Vec<String> = bindings
+                    .iter()
+                    .map(|(field, var)| {
+                        if field.value.name() == var.value.name() {
+                            field.value.to_string()
+                        } else {
+                            format!("{}: {}", field.value, var.value)
+                        }
+                    })
+                    .collect();
+                let pattern = format!("{}{}", name, type_actuals(tys));
+                let rhs = self.flat(e);
+                let flat = format!("{} {{ {} }} = {};", pattern, bindings.join(", "), rhs);
+                if bindings.is_empty() || col + flat.len() <= self.options.max_width {
+                    return flat;
+                }
+                let bindings = self.broken_braces(bindings, self.indent);
+                let rhs = self.rhs(e, self.indent + 1);
+                format!("{} {}{};", pattern, bindings, rhs)
+            }
+            Cmd_::Abort(None) => "abort;".to_string(),
+            Cmd_::Abort(Some(e)) => match &e.value {
+                Exp_::ExprList(_) => format!("abort{};", self.exp(e, col + 6, self.indent)),
+                _ => format!("abort {};", self.exp(e, col + 7, self.indent)),
+            },
+            Cmd_::Return(e) => match &e.value {
+                Exp_::ExprList(exps) if exps.is_empty() => "return;".to_string(),
+                Exp_::ExprList(exps) => format!("return {};", self.sequence(exps, col + 7)),
+                _ => format!("return {};", self.exp(e, col + 8, self.indent)),
+            },
+            Cmd_::Break(None) => "break;".to_string(),
+            Cmd_::Break(Some(e)) => format!("break {};", self.exp(e, col + 7, self.indent)),
+            Cmd_::Continue => "continue;".to_string(),
+            Cmd_::Emit(handle, e) => {
+                let handle = self.exp(handle, col + 5, self.indent);
+                let col = last_line_col(&handle, col + 5) + 2;
+                format!("emit {}, {};", handle, self.exp(e, col, self.indent))
+            }
+            Cmd_::Exp(e) => format!("{};", self.exp(e, col + 1, self.indent)),
+        }
+    }
+
+    fn lvalues(&self, lvalues: &[LValue]) -> String {
+        lvalues
+            .iter()
+            .map(|lvalue| match &lvalue.value {
+                LValue_::Var(var) => var.value.to_string(),
+                LValue_::Mutate(e) => format!("*{}", self.flat(e)),
+                LValue_::Pop => "_".to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Returns ` = ` followed by the right hand side of an assignment whose left hand side ends
+    /// at column `col`. The right hand side moves to the next line if it fits there but not after
+    /// the `=`.
+    fn rhs(&self, e: &Exp, col: usize) -> String {
+        let text = self.exp(e, col + 4, self.indent);
+        let first_line = text.split('\n').next().unwrap_or("");
+        if col + 3 + first_line.len() < self.options.max_width {
+            return format!(" = {}", text);
+        }
+        let inner = self.indent + self.options.indent_width;
+        format!(" =\n{}{}", " ".repeat(inner), self.exp(e, inner + 1, inner))
+    }
+
+    //**********************************************************************************************
+    // Expressions
+    //**********************************************************************************************
+
+    /// Returns the expression on a single line.
+    fn flat(&self, e: &Exp) -> String {
+        match &e.value {
+            Exp_::Dereference(inner) => prefixed("*", self.unary_operand(inner, None)),
+            Exp_::UnaryExp(UnaryOp::Not, inner) => prefixed("!", self.unary_operand(inner, None)),
+            Exp_::BinopExp(lhs, op, rhs) => format!(
+                "{} {} {}",
+                self.binop_operand(lhs, op, false, None),
+                op,
+                self.binop_operand(rhs, op, true, None)
+            ),
+            Exp_::Value(v) => self.value(v),
+            Exp_::Pack(name, tys, fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(field, e)| format!("{}: {}", field.value, self.flat(e)))
+                    .collect();
+                if fields.is_empty() {
+                    format!("{}{} {{}}", name, type_actuals(tys))
+                } else {
+                    format!("{}{} {{ {} }}", name, type_actuals(tys), fields.join(", "))
+                }
+            }
+            Exp_::Borrow {
+                is_mutable,
+                exp,
+                field,
+            } => format!(
+                "{}.{}",
+                prefixed(borrow_prefix(*is_mutable), self.unary_operand(exp, None)),
+                field
+            ),
+            Exp_::Move(var) => format!("move({})", var.value),
+            Exp_::Copy(var) => format!("copy({})", var.value),
+            Exp_::Var(var) => var.value.to_string(),
+            Exp_::BorrowLocal(is_mutable, var) => {
+                format!("{}{}", borrow_prefix(*is_mutable), var.value)
+            }
+            Exp_::FunctionCall(f, args) => match &args.value {
+                Exp_::ExprList(exps) => {
+                    format!("{}({})", call_name(&f.value), self.flat_list(exps))
+                }
+                _ => format!("{} {}", call_name(&f.value), self.flat(args)),
+            },
+            Exp_::ExprList(exps) => format!("({})", self.flat_list(exps)),
+            Exp_::Placeholder(name) => format!("{{{{{}}}}}", name),
+        }
+    }
+
+    fn flat_list(&self, exps: &[Exp]) -> String {
+        exps.iter()
+            .map(|e| self.flat(e))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Returns the expression starting at column `col`, broken over several lines if it does not
+    /// fit. Continuation lines are indented relative to `indent`.
+    fn exp(&self, e: &Exp, col: usize, indent: usize) -> String {
+        let flat = self.flat(e);
+        if col + flat.len() <= self.options.max_width {
+            return flat;
+        }
+        match &e.value {
+            Exp_::FunctionCall(f, args) => match &args.value {
+                Exp_::ExprList(exps) if !exps.is_empty() => format!(
+                    "{}({})",
+                    call_name(&f.value),
+                    self.broken_exps(exps, indent)
+                ),
+                _ => flat,
+            },
+            Exp_::ExprList(exps) if exps.len() == 1 => {
+                format!("({})", self.exp(&exps[0], col + 1, indent))
+            }
+            Exp_::ExprList(exps) if !exps.is_empty() => {
+                format!("({})", self.broken_exps(exps, indent))
+            }
+            Exp_::Pack(name, tys, fields) if !fields.is_empty() => {
+                let inner = indent + self.options.indent_width;
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(field, e)| {
+                        let prefix = format!("{}: ", field.value);
+                        let e = self.exp(e, inner + prefix.len(), inner);
+                        format!("{}{}", prefix, e)
+                    })
+                    .collect();
+                format!(
+                    "{}{} {}",
+                    name,
+                    type_actuals(tys),
+                    self.broken_braces(fields, indent)
+                )
+            }
+            Exp_::BinopExp(lhs, op, rhs) => {
+                let lhs = self.binop_operand(lhs, op, false, Some((col, indent)));
+                let col = last_line_col(&lhs, col) + op.to_string().len() + 2;
+                let rhs = self.binop_operand(rhs, op, true, Some((col, indent)));
+                format!("{} {} {}", lhs, op, rhs)
+            }
+            Exp_::Dereference(inner) => {
+                prefixed("*", self.unary_operand(inner, Some((col + 1, indent))))
+            }
+            Exp_::UnaryExp(UnaryOp::Not, inner) => {
+                prefixed("!", self.unary_operand(inner, Some((col + 1, indent))))
+            }
+            Exp_::Borrow {
+                is_mutable,
+                exp,
+                field,
+            } => {
+                let prefix = borrow_prefix(*is_mutable);
+                let operand = self.unary_operand(exp, Some((col + prefix.len(), indent)));
+                format!("{}.{}", prefixed(prefix, operand), field)
+            }
+            _ => flat,
+        }
+    }
+
+    /// Lays out `e` flat if `pos` is `None`, and starting at the given column and indentation
+    /// otherwise.
+    fn layout(&self, e: &Exp, pos: Option<(usize, usize)>) -> String {
+        match pos {
+            None => self.flat(e),
+            Some((col, indent)) => self.exp(e, col, indent),
+        }
+    }
+
+    fn binop_operand(
+        &self,
+        e: &Exp,
+        parent: &BinOp,
+        is_rhs: bool,
+        pos: Option<(usize, usize)>,
+    ) -> String {
+        let needs_parens = match &e.value {
+            Exp_::BinopExp(_, op, _) => {
+                precedence(op) < precedence(parent)
+                    || (is_rhs && precedence(op) == precedence(parent))
+                    // The parser rejects `a < b < c`
+                    || (is_ordering(op) && is_ordering(parent))
+            }
+            _ => false,
+        };
+        if needs_parens {
+            format!(
+                "({})",
+                self.layout(e, pos.map(|(col, indent)| (col + 1, indent)))
+            )
+        } else {
+            self.layout(e, pos)
+        }
+    }
+
+    fn unary_operand(&self, e: &Exp, pos: Option<(usize, usize)>) -> String {
+        match &e.value {
+            Exp_::BinopExp(..) => format!(
+                "({})",
+                self.layout(e, pos.map(|(col, indent)| (col + 1, indent)))
+            ),
+            _ => self.layout(e, pos),
+        }
+    }
+
+    fn value(&self, v: &CopyableVal) -> String {
+        // Keep the literal as written, e.g. with its type suffix or a short address.
+        let (start, end) = (v.span.start().to_usize(), v.span.end().to_usize());
+        if start < end {
+            if let Some(text) = self.stripped.get(start..end) {
+                return text.to_string();
+            }
+        }
+        match &v.value {
+            CopyableVal_::Address(address) => short_address(address),
+            CopyableVal_::U8(n) => format!("{}u8", n),
+            CopyableVal_::U64(n) => n.to_string(),
+            CopyableVal_::U128(n) => format!("{}u128", n),
+            CopyableVal_::Bool(b) => b.to_string(),
+            CopyableVal_::ByteArray(bytes) => format!(
+                "h\"{}\"",
+                bytes
+                    .as_bytes()
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>()
+            ),
+        }
+    }
+
+    /// Returns the expressions separated by commas on one line, starting at column `col`, each
+    /// broken over several lines if needed.
+    fn sequence(&self, exps: &[Exp], col: usize) -> String {
+        let mut text = String::new();
+        for (i, e) in exps.iter().enumerate() {
+            if i > 0 {
+                text.push_str(", ");
+            }
+            let col = last_line_col(&text, col);
+            text.push_str(&self.exp(e, col, self.indent));
+        }
+        text
+    }
+
+    fn broken_exps(&self, exps: &[Exp], indent: usize) -> String {
+        let inner = indent + self.options.indent_width;
+        let items = exps.iter().map(|e| self.exp(e, inner, inner)).collect();
+        self.broken_list(items, indent)
+    }
+
+    /// Returns the items one per line with a trailing comma, for use between parentheses.
+    fn broken_list(&self, items: Vec<String>, indent: usize) -> String {
+        let inner = " ".repeat(indent + self.options.indent_width);
+        let mut text = "\n".to_string();
+        for item in items {
+            text.push_str(&inner);
+            text.push_str(&item);
+            text.push_str(",\n");
+        }
+        text.push_str(&" ".repeat(indent));
+        text
+    }
+
+    /// Returns the items one per line with a trailing comma, between braces.
+    fn broken_braces(&self, items: Vec<String>, indent: usize) -> String {
+        format!("{{{}}}", self.broken_list(items, indent))
+    }
+}
+
+//**************************************************************************************************
+// Helpers
+//**************************************************************************************************
+
+/// Recognizes the desugaring of `assert(cond, code)`, which shares its locations with the
+/// condition and the code.
+fn as_assert(if_else: &IfElse) -> Option<(&Exp, &Exp)> {
+    if if_else.else_block.is_some() || if_else.if_block.value.stmts.len() != 1 {
+        return None;
+    }
+    let cond = match &if_else.cond.value {
+        Exp_::UnaryExp(UnaryOp::Not, cond) if cond.span == if_else.cond.span => cond,
+        _ => return None,
+    };
+    match if_else.if_block.value.stmts.front() {
+        Some(Statement::CommandStatement(cmd)) => match &cmd.value {
+            Cmd_::Abort(Some(code))
+                if cmd.span == if_else.if_block.span && code.span == cmd.span =>
+            {
+                Some((cond, code))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn import_text(import: &ImportDefinition) -> String {
+    let ident = match &import.ident {
+        ModuleIdent::Transaction(name) => format!("Transaction.{}", name),
+        ModuleIdent::Qualified(ident) => {
+            format!("{}.{}", short_address(&ident.address), ident.name)
+        }
+    };
+    if &import.alias == import.ident.name() {
+        format!("import {};", ident)
+    } else {
+        format!("import {} as {};", ident, import.alias)
+    }
+}
+
+fn short_address(address: &AccountAddress) -> String {
+    let hex = format!("{:x}", address);
+    let digits = hex.trim_start_matches('0');
+    format!("0x{}", if digits.is_empty() { "0" } else { digits })
+}
+
+pub(crate) fn type_formals(formals: &[(TypeVar, Kind)]) -> String {
+    if formals.is_empty() {
+        return String::new();
+    }
+    let formals: Vec<String> = formals
+        .iter()
+        .map(|(var, kind)| match kind {
+            Kind::All => var.value.to_string(),
+            _ => format!("{}: {}", var.value, kind),
+        })
+        .collect();
+    format!("<{}>", formals.join(", "))
+}
+
+fn return_type(tys: &[Type]) -> String {
+    if tys.is_empty() {
+        String::new()
+    } else {
+        let tys: Vec<String> = tys.iter().map(type_text).collect();
+        format!(": {}", tys.join(" * "))
+    }
+}
+
+fn type_actuals(tys: &[Type]) -> String {
+    if tys.is_empty() {
+        String::new()
+    } else {
+        let tys: Vec<String> = tys.iter().map(type_text).collect();
+        format!("<{}>", tys.join(", "))
+    }
+}
+
+pub(crate) fn type_text(ty: &Type) -> String {
+    match ty {
+        Type::Address => "address".to_string(),
+        Type::U8 => "u8".to_string(),
+        Type::U64 => "u64".to_string(),
+        Type::U128 => "u128".to_string(),
+        Type::Bool => "bool".to_string(),
+        Type::ByteArray => "bytearray".to_string(),
+        Type::Struct(ident, tys) => format!("{}{}", ident, type_actuals(tys)),
+        Type::Reference(is_mutable, ty) => {
+            format!("{}{}", borrow_prefix(*is_mutable), type_text(ty))
+        }
+        Type::TypeParameter(var) => var.to_string(),
+        Type::Tuple(tys) => {
+            let tys: Vec<String> = tys.iter().map(type_text).collect();
+            format!("({})", tys.join(", "))
+        }
+    }
+}
+
+fn call_name(call: &FunctionCall_) -> String {
+    let (name, struct_name, tys) = match call {
+        FunctionCall_::ModuleFunctionCall {
+            module,
+            name,
+            type_actuals: tys,
+        } => return format!("{}.{}{}", module, name, type_actuals(tys)),
+        FunctionCall_::Builtin(builtin) => match builtin {
+            Builtin::Exists(s, tys) => ("exists", s, tys),
+            Builtin::BorrowGlobal(false, s, tys) => ("borrow_global", s, tys),
+            Builtin::BorrowGlobal(true, s, tys) => ("borrow_global_mut", s, tys),
+            Builtin::MoveFrom(s, tys) => ("move_from", s, tys),
+            Builtin::MoveToSender(s, tys) => ("move_to_sender", s, tys),
+            Builtin::GetTxnSender => return "get_txn_sender".to_string(),
+            Builtin::Freeze => return "freeze".to_string(),
+            Builtin::ToU8 => return "to_u8".to_string(),
+            Builtin::ToU64 => return "to_u64".to_string(),
+            Builtin::ToU128 => return "to_u128".to_string(),
+            Builtin::Rotl => return "rotl".to_string(),
+            Builtin::Rotr => return "rotr".to_string(),
+            Builtin::CountOnes => return "count_ones".to_string(),
+            Builtin::CheckedAdd => return "checked_add".to_string(),
+            Builtin::CheckedSub => return "checked_sub".to_string(),
+            Builtin::SaturatingAdd => return "saturating_add".to_string(),
+            Builtin::SaturatingSub => return "saturating_sub".to_string(),
+            Builtin::Sha2_256 => return "sha2_256".to_string(),
+            Builtin::Sha3_256 => return "sha3_256".to_string(),
+            Builtin::ToBytes(ty) => return format!("to_bytes<{}>", type_text(ty)),
+        },
+    };
+    format!("{}<{}{}>", name, struct_name, type_actuals(tys))
+}
+
+fn borrow_prefix(is_mutable: bool) -> &'static str {
+    if is_mutable {
+        "&mut "
+    } else {
+        "&"
+    }
+}
+
+/// Applies a prefix operator, keeping `&` `&` from being read as `&&`.
+fn prefixed(prefix: &str, operand: String) -> String {
+    if prefix == "&" && operand.starts_with('&') {
+        format!("& {}", operand)
+    } else {
+        format!("{}{}", prefix, operand)
+    }
+}
+
+/// The column after `text`, printed starting at column `col`.
+fn last_line_col(text: &str, col: usize) -> usize {
+    match text.rfind('\n') {
+        Some(idx) => text.len() - idx - 1,
+        None => col + text.len(),
+    }
+}
+
+/// Mirrors the operator precedence of the parser.
+fn is_ordering(op: &BinOp) -> bool {
+    match op {
+        BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => true,
+        _ => false,
+    }
+}
+
+fn precedence(op: &BinOp) -> u32 {
+    match op {
+        BinOp::Or => 2,
+        BinOp::And => 3,
+        BinOp::Eq | BinOp::Neq | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => 4,
+        BinOp::BitOr => 5,
+        BinOp::Xor => 6,
+        BinOp::BitAnd => 7,
+        BinOp::Shl | BinOp::Shr => 8,
+        BinOp::Add | BinOp::Sub => 9,
+        BinOp::Mul | BinOp::Div | BinOp::Mod => 10,
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Automatic freezing of mutable references.
+//!
+//! References are not subtypes of each other in the bytecode: a `&mut T` given where a `&T` is
+//! expected is rejected by the verifier, and has to be converted with `freeze` first. This pass
+//! inserts these `freeze` calls into the AST instead, and returns where it did so.
+//!
+//! The coercions are found by the type checker, so only values whose type it knows are frozen:
+//! arguments of builtins and of functions of the current module, returned values, and values
+//! assigned to locals or breaking out of loops.
+
+use crate::type_checker::{module_freezes, script_freezes};
+use move_ir_types::ast::{
+    Block_, Builtin, Cmd_, Exp, Exp_, FunctionBody, FunctionCall_, LValue_, Loc, ModuleDefinition,
+    Program, Script, Spanned, Statement, Type,
+};
+use std::{fmt, mem};
+
+/// A `freeze` inserted around an expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Freeze {
+    /// The location of the frozen expression.
+    pub loc: Loc,
+    /// The type of the referenced value.
+    pub ty: Type,
+}
+
+impl fmt::Display for Freeze {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`&mut {}` frozen to `&{}`", self.ty, self.ty)
+    }
+}
+
+/// Freezes the mutable references given where immutable ones are expected in every module and
+/// then in the script of a program.
+pub fn freeze_program(program: &mut Program) -> Vec<Freeze> {
+    let mut freezes = vec![];
+    for module in &mut program.modules {
+        freezes.extend(freeze_module(module));
+    }
+    freezes.extend(freeze_script(&mut program.script));
+    freezes
+}
+
+/// Freezes the mutable references given where immutable ones are expected in the functions of a
+/// module, returning the inserted freezes in source order.
+pub fn freeze_module(module: &mut ModuleDefinition) -> Vec<Freeze> {
+    let freezes = into_freezes(module_freezes(module));
+    let mut pending: Vec<Loc> = freezes.iter().map(|freeze| freeze.loc).collect();
+    for (_, function) in &mut module.functions {
+        if let FunctionBody::Move { code, .. } = &mut function.value.body {
+            freeze_block(code, &mut pending);
+        }
+    }
+    freezes
+}
+
+/// Freezes the mutable references given where immutable ones are expected in the `main` function
+/// of a script, returning the inserted freezes in source order.
+pub fn freeze_script(script: &mut Script) -> Vec<Freeze> {
+    let freezes = into_freezes(script_freezes(script));
+    let mut pending: Vec<Loc> = freezes.iter().map(|freeze| freeze.loc).collect();
+    if let FunctionBody::Move { code, .. } = &mut script.main.value.body {
+        freeze_block(code, &mut pending);
+    }
+    freezes
+}
+
+fn into_freezes(sites: Vec<(Loc, Type)>) -> Vec<Freeze> {
+    let mut freezes: Vec<Freeze> = sites
+        .into_iter()
+        .map(|(loc, ty)| Freeze { loc, ty })
+        .collect();
+    freezes.sort_by_key(|freeze| freeze.loc.start());
+    freezes
+}
+
+fn freeze_block(block: &mut Block_, pending: &mut Vec<Loc>) {
+    for statement in &mut block.stmts {
+        match statement {
+            Statement::CommandStatement(cmd) => freeze_cmd(&mut cmd.value, pending),
+            Statement::IfElseStatement(if_else) => {
+                freeze_exp(&mut if_else.cond, pending);
+                freeze_block(&mut if_else.if_block.value, pending);
+                if let Some(else_block) = &mut if_else.else_block {
+                    freeze_block(&mut else_block.value, pending);
+                }
+            }
+            Statement::WhileStatement(while_) => {
+                freeze_exp(&mut while_.cond, pending);
+                freeze_block(&mut while_.block.value, pending);
+            }
+            Statement::LoopStatement(loop_) => {
+                for lvalue in &mut loop_.results {
+                    if let LValue_::Mutate(e) = &mut lvalue.value {
+                        freeze_exp(e, pending);
+                    }
+                }
+                freeze_block(&mut loop_.block.value, pending);
+            }
+            Statement::EmptyStatement => (),
+        }
+    }
+}
+
+fn freeze_cmd(cmd: &mut Cmd_, pending: &mut Vec<Loc>) {
+    match cmd {
+        Cmd_::Assign(lvalues, e) => {
+            for lvalue in lvalues {
+                if let LValue_::Mutate(e) = &mut lvalue.value {
+                    freeze_exp(e, pending);
+                }
+            }
+            freeze_exp(e, pending);
+        }
+        Cmd_::Unpack(_, _, _, e)
+        | Cmd_::Return(e)
+        | Cmd_::Exp(e)
+        | Cmd_::Abort(Some(e))
+        | Cmd_::Break(Some(e)) => freeze_exp(e, pending),
+        Cmd_::Emit(handle, e) => {
+            freeze_exp(handle, pending);
+            freeze_exp(e, pending);
+        }
+        Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+    }
+}
+
+// The subexpressions are frozen first: an expression sharing its location with the one it is
+// nested in is then the one frozen, as it is the one the checker located.
+fn freeze_exp(e: &mut Exp, pending: &mut Vec<Loc>) {
+    match &mut e.value {
+        Exp_::Dereference(e)
+        | Exp_::UnaryExp(_, e)
+        | Exp_::Borrow { exp: e, .. }
+        | Exp_::FunctionCall(_, e) => freeze_exp(e, pending),
+        Exp_::BinopExp(lhs, _, rhs) => {
+            freeze_exp(lhs, pending);
+            freeze_exp(rhs, pending);
+        }
+        Exp_::Pack(_, _, fields) => {
+            for (_, e) in fields {
+                freeze_exp(e, pending);
+            }
+        }
+        Exp_::ExprList(exps) => {
+            for e in exps {
+                freeze_exp(e, pending);
+            }
+        }
+        Exp_::Value(_)
+        | Exp_::Move(_)
+        | Exp_::Copy(_)
+        | Exp_::Var(_)
+        | Exp_::BorrowLocal(_, _)
+        | Exp_::Placeholder(_) => (),
+    }
+    if let Some(idx) = pending.iter().position(|loc| *loc == e.span) {
+        pending.remove(idx);
+        let span = e.span;
+        let frozen = mem::replace(&mut e.value, Exp_::ExprList(vec![]));
+        e.value = Exp_::FunctionCall(
+            Spanned {
+                span,
+                value: FunctionCall_::Builtin(Builtin::Freeze),
+            },
+            Box::new(Spanned {
+                span,
+                value: Exp_::ExprList(vec![Spanned {
+                    span,
+                    value: frozen,
+                }]),
+            }),
+        );
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Golden tests of the parser.
+//!
+//! A golden test is a `.mvir` source next to a `.exp` file holding what the parser is expected
+//! to make of it: the script or module printed back by the formatter, or the syntax error,
+//! located by line and column. [`run_parser_golden_tests`](fn.run_parser_golden_tests.html) runs
+//! every test of a directory tree, so that a conformance suite only needs a test calling it.
+//! When the `UPDATE_BASELINE` environment variable is set, the `.exp` files are rewritten with
+//! the current output instead.
+
+use crate::{
+    formatter::{format_module, format_script, FormatOptions},
+    parser::try_parse_script_or_module,
+};
+use anyhow::{Context, Result};
+use bytecode_source_map::utils::line_and_column;
+use move_ir_types::ast::ScriptOrModule;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// The extension of the sources of golden tests.
+pub const SOURCE_EXT: &str = "mvir";
+/// The extension of the expected outputs of golden tests.
+pub const EXP_EXT: &str = "exp";
+/// The environment variable asking for the expected outputs to be updated.
+pub const UPDATE_BASELINE: &str = "UPDATE_BASELINE";
+
+/// A golden test whose output differs from the expected one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Mismatch {
+    /// The source of the test.
+    pub path: PathBuf,
+    /// The expected output, or `None` if the test has no `.exp` file.
+    pub expected: Option<String>,
+    /// The output of the parser.
+    pub actual: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.expected {
+            Some(expected) => write!(
+                f,
+                "{}: expected\n{}\nbut got\n{}",
+                self.path.display(),
+                expected,
+                self.actual
+            ),
+            None => write!(
+                f,
+                "{}: no .{} file, got\n{}",
+                self.path.display(),
+                EXP_EXT,
+                self.actual
+            ),
+        }
+    }
+}
+
+/// Returns what the parser makes of `source`: the script or module printed back from its AST, or
+/// the syntax error preceded by its line and column.
+pub fn parser_output(source: &str) -> String {
+    let options = FormatOptions::default();
+    match try_parse_script_or_module(source) {
+        Ok(ScriptOrModule::Script(script)) => format_script(&script, &options),
+        Ok(ScriptOrModule::Module(module)) => format_module(&module, &options),
+        Err(error) => match error.loc {
+            Some(loc) => {
+                let (line, column) = line_and_column(source, loc.start());
+                format!("error: {}:{}: {}\n", line, column, error.message)
+            }
+            None => format!("error: {}\n", error.message),
+        },
+    }
+}
+
+/// Whether the `UPDATE_BASELINE` environment variable is set to something other than `0` or
+/// `false`.
+pub fn update_baseline_requested() -> bool {
+    match std::env::var(UPDATE_BASELINE) {
+        Ok(value) => value != "0" && value != "false",
+        Err(_) => false,
+    }
+}
+
+/// Runs the golden tests of `dir` and its subdirectories, in path order, and returns those whose
+/// output differs from their `.exp` file. With `update`, the `.exp` files are written with the
+/// output instead, and there are no mismatches.
+pub fn run_parser_golden_tests(dir: &Path, update: bool) -> Result<Vec<Mismatch>> {
+    let mut mismatches = vec![];
+    for path in sources(dir)? {
+        let source = fs::read_to_string(&path)
+            .with_context(|| format!("Unable to read {}", path.display()))?;
+        let actual = parser_output(&source);
+        let exp_path = path.with_extension(EXP_EXT);
+        if update {
+            fs::write(&exp_path, &actual)
+                .with_context(|| format!("Unable to write {}", exp_path.display()))?;
+            continue;
+        }
+        let expected = fs::read_to_string(&exp_path).ok();
+        if expected.as_ref() != Some(&actual) {
+            mismatches.push(Mismatch {
+                path,
+                expected,
+                actual,
+            });
+        }
+    }
+    Ok(mismatches)
+}
+
+// Returns the sources of the tests of `dir` and its subdirectories, sorted.
+fn sources(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    let entries = fs::read_dir(dir).with_context(|| format!("Unable to read {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            paths.extend(sources(&path)?);
+        } else if path.extension().map_or(false, |ext| ext == SOURCE_EXT) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
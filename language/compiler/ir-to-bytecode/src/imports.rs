@@ -0,0 +1,350 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checking the uses of imported modules against their compiled form.
+//!
+//! When an imported module is only available compiled, e.g. fetched from a snapshot of the
+//! modules published on chain, the compiler can only tell that a use of it is wrong by failing on
+//! an unbound name or, for a wrong number of arguments, by the bytecode failing to verify. These
+//! checks report such uses where they are in the source instead: calls to functions the module
+//! does not define or does not make public, with the wrong number of type arguments or of
+//! arguments, and types naming structs it does not define or with the wrong number of type
+//! arguments.
+
+use crate::errors::ImportError;
+use libra_types::{account_address::AccountAddress, language_storage::ModuleId};
+use move_ir_types::ast::{
+    Block_, Builtin, Cmd_, Exp, Exp_, Function, FunctionBody, FunctionCall_, FunctionName,
+    ImportDefinition, LValue_, Loc, ModuleDefinition, ModuleIdent, ModuleName, Script, Statement,
+    StructDefinitionFields, StructName, Type,
+};
+use std::collections::BTreeMap;
+use vm::access::ModuleAccess;
+
+/// Checks the uses of the modules `module` imports that are among `deps`, for a module published
+/// at `address`. Uses of other modules are not checked.
+pub fn check_module_imports<'a, T: 'a + ModuleAccess>(
+    module: &ModuleDefinition,
+    address: AccountAddress,
+    deps: impl IntoIterator<Item = &'a T>,
+) -> Vec<ImportError> {
+    let mut checker = Checker::new(&module.imports, address, deps);
+    for struct_def in &module.structs {
+        if let StructDefinitionFields::Move { fields } = &struct_def.value.fields {
+            for (_, ty) in fields {
+                checker.check_type(struct_def.span, ty);
+            }
+        }
+    }
+    for (_, function) in &module.functions {
+        checker.function(function);
+    }
+    checker.errors
+}
+
+/// Checks the uses of the modules `script` imports that are among `deps`, for a script sent by
+/// `address`. Uses of other modules are not checked.
+pub fn check_script_imports<'a, T: 'a + ModuleAccess>(
+    script: &Script,
+    address: AccountAddress,
+    deps: impl IntoIterator<Item = &'a T>,
+) -> Vec<ImportError> {
+    let mut checker = Checker::new(&script.imports, address, deps);
+    checker.function(&script.main);
+    checker.errors
+}
+
+struct Checker<'a, T> {
+    /// The compiled modules of the imports, by alias.
+    modules: BTreeMap<ModuleName, &'a T>,
+    errors: Vec<ImportError>,
+}
+
+impl<'a, T: ModuleAccess> Checker<'a, T> {
+    fn new(
+        imports: &[ImportDefinition],
+        address: AccountAddress,
+        deps: impl IntoIterator<Item = &'a T>,
+    ) -> Self {
+        let deps: BTreeMap<ModuleId, &'a T> =
+            deps.into_iter().map(|dep| (dep.self_id(), dep)).collect();
+        let modules = imports
+            .iter()
+            .filter_map(|import| {
+                let id = match &import.ident {
+                    ModuleIdent::Transaction(name) => {
+                        ModuleId::new(address, name.clone().into_inner())
+                    }
+                    ModuleIdent::Qualified(ident) => {
+                        ModuleId::new(ident.address, ident.name.clone().into_inner())
+                    }
+                };
+                deps.get(&id).map(|dep| (import.alias.clone(), *dep))
+            })
+            .collect();
+        Checker {
+            modules,
+            errors: vec![],
+        }
+    }
+
+    fn error(&mut self, loc: Loc, message: String) {
+        self.errors.push(ImportError { loc, message });
+    }
+
+    fn function(&mut self, function: &Function) {
+        let loc = function.span;
+        let signature = &function.value.signature;
+        for ty in signature
+            .formals
+            .iter()
+            .map(|(_, ty)| ty)
+            .chain(&signature.return_type)
+        {
+            self.check_type(loc, ty);
+        }
+        if let FunctionBody::Move { locals, code } = &function.value.body {
+            for (_, ty) in locals {
+                self.check_type(loc, ty);
+            }
+            self.block(code);
+        }
+    }
+
+    fn check_type(&mut self, loc: Loc, ty: &Type) {
+        match ty {
+            Type::Struct(ident, tys) => {
+                self.struct_use(loc, &ident.module, &ident.name, tys.len());
+                for ty in tys {
+                    self.check_type(loc, ty);
+                }
+            }
+            Type::Reference(_, ty) => self.check_type(loc, ty),
+            Type::Tuple(tys) => {
+                for ty in tys {
+                    self.check_type(loc, ty);
+                }
+            }
+            Type::Address
+            | Type::U8
+            | Type::U64
+            | Type::U128
+            | Type::Bool
+            | Type::ByteArray
+            | Type::TypeParameter(_) => (),
+        }
+    }
+
+    fn struct_use(&mut self, loc: Loc, module: &ModuleName, name: &StructName, arity: usize) {
+        let compiled = match self.modules.get(module) {
+            Some(compiled) => *compiled,
+            None => return,
+        };
+        let handle = compiled
+            .struct_defs()
+            .iter()
+            .map(|def| compiled.struct_handle_at(def.struct_handle))
+            .find(|handle| compiled.identifier_at(handle.name) == name.as_inner());
+        match handle {
+            None => self.error(loc, format!("unbound struct `{}.{}`", module, name)),
+            Some(handle) if handle.type_formals.len() != arity => self.error(
+                loc,
+                format!(
+                    "`{}.{}` expects {} type arguments, found {}",
+                    module,
+                    name,
+                    handle.type_formals.len(),
+                    arity
+                ),
+            ),
+            Some(_) => (),
+        }
+    }
+
+    fn call(
+        &mut self,
+        loc: Loc,
+        module: &ModuleName,
+        name: &FunctionName,
+        type_arity: usize,
+        arity: Option<usize>,
+    ) {
+        let compiled = match self.modules.get(module) {
+            Some(compiled) => *compiled,
+            None => return,
+        };
+        let def = compiled.function_defs().iter().find(|def| {
+            let handle = compiled.function_handle_at(def.function);
+            compiled.identifier_at(handle.name) == name.as_inner()
+        });
+        let def = match def {
+            Some(def) => def,
+            None => {
+                self.error(loc, format!("unbound function `{}.{}`", module, name));
+                return;
+            }
+        };
+        if !def.is_public() {
+            self.error(loc, format!("function `{}.{}` is not public", module, name));
+            return;
+        }
+        let signature =
+            compiled.function_signature_at(compiled.function_handle_at(def.function).signature);
+        if signature.type_formals.len() != type_arity {
+            self.error(
+                loc,
+                format!(
+                    "`{}.{}` expects {} type arguments, found {}",
+                    module,
+                    name,
+                    signature.type_formals.len(),
+                    type_arity
+                ),
+            );
+        }
+        match arity {
+            Some(arity) if arity != signature.arg_types.len() => self.error(
+                loc,
+                format!(
+                    "`{}.{}` expects {} arguments, found {}",
+                    module,
+                    name,
+                    signature.arg_types.len(),
+                    arity
+                ),
+            ),
+            _ => (),
+        }
+    }
+
+    fn block(&mut self, block: &Block_) {
+        for statement in &block.stmts {
+            match statement {
+                Statement::CommandStatement(cmd) => match &cmd.value {
+                    Cmd_::Assign(lvalues, e) => {
+                        for lvalue in lvalues {
+                            if let LValue_::Mutate(e) = &lvalue.value {
+                                self.exp(e);
+                            }
+                        }
+                        self.exp(e);
+                    }
+                    Cmd_::Unpack(_, tys, _, e) => {
+                        for ty in tys {
+                            self.check_type(cmd.span, ty);
+                        }
+                        self.exp(e);
+                    }
+                    Cmd_::Return(e)
+                    | Cmd_::Abort(Some(e))
+                    | Cmd_::Break(Some(e))
+                    | Cmd_::Exp(e) => self.exp(e),
+                    Cmd_::Emit(handle, e) => {
+                        self.exp(handle);
+                        self.exp(e);
+                    }
+                    Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+                },
+                Statement::IfElseStatement(if_else) => {
+                    self.exp(&if_else.cond);
+                    self.block(&if_else.if_block.value);
+                    if let Some(else_block) = &if_else.else_block {
+                        self.block(&else_block.value);
+                    }
+                }
+                Statement::WhileStatement(while_) => {
+                    self.exp(&while_.cond);
+                    self.block(&while_.block.value);
+                }
+                Statement::LoopStatement(loop_) => {
+                    for lvalue in &loop_.results {
+                        if let LValue_::Mutate(e) = &lvalue.value {
+                            self.exp(e);
+                        }
+                    }
+                    self.block(&loop_.block.value);
+                }
+                Statement::EmptyStatement => (),
+            }
+        }
+    }
+
+    fn exp(&mut self, e: &Exp) {
+        match &e.value {
+            Exp_::Dereference(e) | Exp_::UnaryExp(_, e) | Exp_::Borrow { exp: e, .. } => {
+                self.exp(e)
+            }
+            Exp_::BinopExp(lhs, _, rhs) => {
+                self.exp(lhs);
+                self.exp(rhs);
+            }
+            Exp_::Pack(_, tys, fields) => {
+                for ty in tys {
+                    self.check_type(e.span, ty);
+                }
+                for (_, e) in fields {
+                    self.exp(e);
+                }
+            }
+            Exp_::FunctionCall(call, args) => {
+                match &call.value {
+                    FunctionCall_::Builtin(builtin) => {
+                        if let Some(tys) = builtin_type_actuals(builtin) {
+                            for ty in tys {
+                                self.check_type(e.span, ty);
+                            }
+                        }
+                    }
+                    FunctionCall_::ModuleFunctionCall {
+                        module,
+                        name,
+                        type_actuals,
+                    } => {
+                        for ty in type_actuals {
+                            self.check_type(e.span, ty);
+                        }
+                        self.call(e.span, module, name, type_actuals.len(), arity(args));
+                    }
+                }
+                self.exp(args);
+            }
+            Exp_::ExprList(exps) => {
+                for e in exps {
+                    self.exp(e);
+                }
+            }
+            Exp_::Value(_)
+            | Exp_::Move(_)
+            | Exp_::Copy(_)
+            | Exp_::Var(_)
+            | Exp_::BorrowLocal(_, _)
+            | Exp_::Placeholder(_) => (),
+        }
+    }
+}
+
+fn builtin_type_actuals(builtin: &Builtin) -> Option<&[Type]> {
+    match builtin {
+        Builtin::Exists(_, tys)
+        | Builtin::BorrowGlobal(_, _, tys)
+        | Builtin::MoveFrom(_, tys)
+        | Builtin::MoveToSender(_, tys) => Some(tys),
+        Builtin::ToBytes(ty) => Some(std::slice::from_ref(ty)),
+        _ => None,
+    }
+}
+
+// Returns the number of values passed by the arguments of a call, or `None` if an argument is a
+// call of a module function, which may return any number of values.
+fn arity(args: &Exp) -> Option<usize> {
+    match &args.value {
+        Exp_::ExprList(exps) => exps.iter().map(arity).sum(),
+        Exp_::FunctionCall(call, _) => match &call.value {
+            FunctionCall_::Builtin(Builtin::CheckedAdd)
+            | FunctionCall_::Builtin(Builtin::CheckedSub) => Some(2),
+            FunctionCall_::Builtin(_) => Some(1),
+            FunctionCall_::ModuleFunctionCall { .. } => None,
+        },
+        _ => Some(1),
+    }
+}
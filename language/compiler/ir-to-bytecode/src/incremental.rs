@@ -0,0 +1,311 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Parsing a source again as it is edited, for editors.
+//!
+//! A [`Document`](struct.Document.html) holds the text of a source, split into parts that parse
+//! independently: each module of a program listing its modules after `modules:` is a part, and the
+//! last one also holds `script:` and the script. Any other source is a single part. When the text
+//! is edited, only the parts the edit touches are parsed again, while the others are kept, moved
+//! by the edit. Finding the parts is a scan of the bytes of the source for top-level `module`
+//! keywords, much faster than parsing it, and comments are only stripped again on the lines of
+//! the edit.
+//!
+//! The diagnostics of an edit are streamed: the syntax errors of each part parsed again are
+//! reported as soon as it is parsed, with a
+//! [`DiagnosticsUpdate::Region`](enum.DiagnosticsUpdate.html#variant.Region), and the errors of
+//! the whole source once it is type checked, with a
+//! [`DiagnosticsUpdate::Complete`](enum.DiagnosticsUpdate.html#variant.Complete). Type errors are
+//! kept with the parts too, so only the parts parsed again are type checked.
+
+use crate::{
+    errors::{Fix, SyntaxError, TypeError},
+    parser::{strip_comments, try_parse_source_header, try_parse_source_part, ParserOptions},
+    type_checker::{check_module, check_script},
+};
+use anyhow::{bail, Result};
+use codespan::{ByteIndex, Span};
+use ir_to_bytecode_syntax::syntax::{top_level_module_starts, SourceHeader};
+use move_ir_types::ast::{Loc, ModuleDefinition, Script};
+use std::{collections::HashMap, mem};
+
+/// The replacement of the text between the byte offsets `start` and `end` by `text`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// New diagnostics of a document, given as it is parsed and checked after an edit. Locations are
+/// offsets in the edited text.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DiagnosticsUpdate {
+    /// The syntax errors between the byte offsets `start` and `end`, a part of the source that was
+    /// parsed again. They replace the errors previously reported there.
+    Region {
+        start: usize,
+        end: usize,
+        errors: Vec<SyntaxError>,
+    },
+    /// All the errors of the source, once it is parsed and type checked. They replace all the
+    /// errors previously reported.
+    Complete {
+        syntax_errors: Vec<SyntaxError>,
+        type_errors: Vec<TypeError>,
+    },
+}
+
+// A part of the source. Its modules and script, and its errors, are located relative to its start.
+#[derive(Clone, Debug)]
+struct Part {
+    start: usize,
+    end: usize,
+    last: bool,
+    parsed: std::result::Result<(Vec<ModuleDefinition>, Option<Script>), SyntaxError>,
+    type_errors: Option<Vec<TypeError>>,
+}
+
+impl Part {
+    fn type_errors(&mut self) -> &[TypeError] {
+        if self.type_errors.is_none() {
+            let mut errors = vec![];
+            if let Ok((modules, script)) = &self.parsed {
+                for module in modules {
+                    errors.extend(check_module(module));
+                }
+                if let Some(script) = script {
+                    errors.extend(check_script(script));
+                }
+            }
+            self.type_errors = Some(errors);
+        }
+        self.type_errors.as_ref().unwrap()
+    }
+}
+
+/// The text of a source being edited, with its parsed parts. See the
+/// [module documentation](index.html).
+#[derive(Clone, Debug)]
+pub struct Document {
+    options: ParserOptions,
+    text: String,
+    // The text with its comments replaced by spaces, as the parser reads it.
+    stripped: String,
+    header: std::result::Result<SourceHeader, SyntaxError>,
+    parts: Vec<Part>,
+}
+
+impl Document {
+    /// Creates a document holding `text`, parsed and type checked with `options`.
+    pub fn new(text: impl Into<String>, options: ParserOptions) -> Self {
+        let text = text.into();
+        let stripped = strip_comments(&text);
+        let mut document = Document {
+            options,
+            text,
+            stripped,
+            header: Err(SyntaxError {
+                loc: None,
+                message: String::new(),
+                fix: None,
+            }),
+            parts: vec![],
+        };
+        document.parse(HashMap::new(), &mut |_| ());
+        document
+    }
+
+    /// The current text of the document.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The syntax errors of the document.
+    pub fn syntax_errors(&self) -> Vec<SyntaxError> {
+        match &self.header {
+            Ok(_) => self
+                .parts
+                .iter()
+                .filter_map(|part| match &part.parsed {
+                    Ok(_) => None,
+                    Err(err) => Some(move_syntax_error(err.clone(), part.start)),
+                })
+                .collect(),
+            Err(err) => vec![err.clone()],
+        }
+    }
+
+    /// The type errors of the document, in the parts that parse.
+    pub fn type_errors(&mut self) -> Vec<TypeError> {
+        let mut errors = vec![];
+        for part in &mut self.parts {
+            let start = part.start;
+            errors.extend(
+                part.type_errors()
+                    .iter()
+                    .map(|err| move_type_error(err.clone(), start)),
+            );
+        }
+        errors
+    }
+
+    /// Applies `edit` to the text, passing the new diagnostics to `on_update` as the parts the edit
+    /// touches are parsed again, and then once the whole document is type checked. Fails if the
+    /// edit is outside the text or does not start and end at character boundaries, leaving the
+    /// document unchanged.
+    pub fn edit(
+        &mut self,
+        edit: &TextEdit,
+        mut on_update: impl FnMut(DiagnosticsUpdate),
+    ) -> Result<()> {
+        let TextEdit { start, end, text } = edit;
+        let (start, end) = (*start, *end);
+        if start > end
+            || end > self.text.len()
+            || !self.text.is_char_boundary(start)
+            || !self.text.is_char_boundary(end)
+        {
+            bail!(
+                "Invalid edit of {}..{} in a text of {} bytes",
+                start,
+                end,
+                self.text.len()
+            );
+        }
+        let new_end = start + text.len();
+        self.text.replace_range(start..end, text);
+
+        // Comments end with their line, so only the lines of the edit are stripped again.
+        let line_start = self.text[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.text[new_end..]
+            .find('\n')
+            .map_or(self.text.len(), |i| new_end + i + 1);
+        let old_line_end = line_end + end - new_end;
+        let stripped_lines = strip_comments(&self.text[line_start..line_end]);
+        self.stripped
+            .replace_range(line_start..old_line_end, &stripped_lines);
+
+        // Keep the parts that do not overlap the edit, by their range in the edited text.
+        let old_header = self.header.clone();
+        let mut kept = HashMap::new();
+        for part in mem::replace(&mut self.parts, vec![]) {
+            if part.end <= start {
+                kept.insert((part.start, part.end, part.last), part);
+            } else if part.start >= end {
+                let moved = Part {
+                    start: part.start + new_end - end,
+                    end: part.end + new_end - end,
+                    ..part
+                };
+                kept.insert((moved.start, moved.end, moved.last), moved);
+            }
+        }
+        self.parse(kept, &mut |part| {
+            on_update(DiagnosticsUpdate::Region {
+                start: part.start,
+                end: part.end,
+                errors: part
+                    .parsed
+                    .as_ref()
+                    .err()
+                    .map(|err| move_syntax_error(err.clone(), part.start))
+                    .into_iter()
+                    .collect(),
+            })
+        });
+        if let Err(err) = &self.header {
+            if old_header.as_ref().err() != Some(err) {
+                on_update(DiagnosticsUpdate::Region {
+                    start: 0,
+                    end: self.text.len(),
+                    errors: vec![err.clone()],
+                });
+            }
+        }
+        on_update(DiagnosticsUpdate::Complete {
+            syntax_errors: self.syntax_errors(),
+            type_errors: self.type_errors(),
+        });
+        Ok(())
+    }
+
+    // Splits the text into parts, taking the ones in `kept` if the header did not change and
+    // parsing the others, which are passed to `on_parsed`.
+    fn parse(
+        &mut self,
+        mut kept: HashMap<(usize, usize, bool), Part>,
+        on_parsed: &mut dyn FnMut(&Part),
+    ) {
+        let header = try_parse_source_header(&self.stripped, self.options);
+        let header_changed = match (&header, &self.header) {
+            (Ok(new), Ok(old)) => {
+                (new.options, new.language_version, new.lists_modules)
+                    != (old.options, old.language_version, old.lists_modules)
+            }
+            _ => true,
+        };
+        if header_changed {
+            kept.clear();
+        }
+        self.header = header;
+        let header = match &self.header {
+            Ok(header) => *header,
+            Err(_) => return,
+        };
+
+        let mut bounds = vec![header.end];
+        if header.lists_modules {
+            bounds.extend(
+                top_level_module_starts(&self.stripped, header.end)
+                    .into_iter()
+                    .filter(|start| *start > header.end),
+            );
+        }
+        bounds.push(self.text.len());
+        for (i, range) in bounds.windows(2).enumerate() {
+            let (start, end) = (range[0], range[1]);
+            let last = i + 2 == bounds.len();
+            let part = match kept.remove(&(start, end, last)) {
+                Some(part) => part,
+                None => {
+                    let part = Part {
+                        start,
+                        end,
+                        last,
+                        parsed: try_parse_source_part(&self.text[start..end], &header, last),
+                        type_errors: None,
+                    };
+                    on_parsed(&part);
+                    part
+                }
+            };
+            self.parts.push(part);
+        }
+    }
+}
+
+fn move_loc(loc: Loc, offset: usize) -> Loc {
+    Span::new(
+        ByteIndex(loc.start().0 + offset as u32),
+        ByteIndex(loc.end().0 + offset as u32),
+    )
+}
+
+fn move_syntax_error(err: SyntaxError, offset: usize) -> SyntaxError {
+    SyntaxError {
+        loc: err.loc.map(|loc| move_loc(loc, offset)),
+        message: err.message,
+        fix: err.fix.map(|fix| Fix {
+            loc: move_loc(fix.loc, offset),
+            replacement: fix.replacement,
+        }),
+    }
+}
+
+fn move_type_error(err: TypeError, offset: usize) -> TypeError {
+    TypeError {
+        loc: move_loc(err.loc, offset),
+        message: err.message,
+    }
+}
@@ -0,0 +1,615 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Inlining of small private functions.
+//!
+//! A call of a function of the same module is replaced by the body of the function when it is
+//! internal, neither generic nor recursive, and small: it has at most `MAX_INLINED_SIZE`
+//! statements and expressions. An `#[inline]` attribute inlines the calls of a function whatever
+//! its size, and `#[never_inline]` keeps them. Inlining trades the size of the bytecode for fewer
+//! call frames.
+//!
+//! Only the calls making up a whole statement are inlined: a call whose results are assigned or
+//! returned, or one returning nothing. The arguments are assigned to new locals standing for the
+//! parameters, followed by the body with its locals renamed, and its final `return` becomes what
+//! the call was. Functions returning anywhere else are not inlined. Neither are functions which
+//! could leave a reference in one of their locals, as it would keep its borrow alive in the
+//! caller: their locals and parameters of a reference type must be moved exactly once, outside of
+//! any branch or loop, and not assigned afterwards.
+//!
+//! A function is inlined into its callers once the calls it makes are, so that the calls nested in
+//! an inlined body are inlined too.
+
+use crate::{
+    call_graph::{CallGraph, FunctionId},
+    locals::{cmd_assigned, cmd_exps, exp_vars, lvalues_assigned, rename_block, LocalUse},
+};
+use move_ir_types::ast::{
+    visit_exp, Block_, Cmd_, Exp, Exp_, FunctionBody, FunctionCall_, FunctionName,
+    FunctionVisibility, Function_, InlineHint, LValue, LValue_, Loc, ModuleDefinition, ModuleName,
+    Program, Spanned, Statement, Type, Var, Var_,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, mem, slice,
+};
+
+/// The largest number of statements and expressions in the body of a function whose calls are
+/// inlined without an `#[inline]` attribute.
+pub const MAX_INLINED_SIZE: usize = 24;
+
+/// A call replaced by the body of the function it calls.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InlinedCall {
+    /// The location of the statement making the call.
+    pub loc: Loc,
+    pub caller: FunctionName,
+    pub callee: FunctionName,
+}
+
+impl fmt::Display for InlinedCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the call of `{}` is inlined into `{}`",
+            self.callee, self.caller
+        )
+    }
+}
+
+/// A function with an `#[inline]` attribute whose calls cannot be inlined.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NotInlined {
+    /// The location of the function.
+    pub loc: Loc,
+    pub name: FunctionName,
+    pub reason: NotInlinedReason,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NotInlinedReason {
+    Public,
+    Native,
+    Generic,
+    Recursive,
+    /// The function returns before the end of its body.
+    EarlyReturn,
+    /// The function could leave a reference in one of its locals.
+    References,
+}
+
+impl fmt::Display for NotInlined {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}` has an `#[inline]` attribute but ", self.name)?;
+        match self.reason {
+            NotInlinedReason::Public => write!(f, "is public"),
+            NotInlinedReason::Native => write!(f, "is native"),
+            NotInlinedReason::Generic => write!(f, "is generic"),
+            NotInlinedReason::Recursive => write!(f, "is recursive"),
+            NotInlinedReason::EarlyReturn => write!(f, "returns before the end of its body"),
+            NotInlinedReason::References => {
+                write!(f, "could leave a reference in one of its locals")
+            }
+        }
+    }
+}
+
+/// The calls inlined and the functions with an `#[inline]` attribute that could not be.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Inlining {
+    /// The inlined calls, in source order.
+    pub calls: Vec<InlinedCall>,
+    /// The functions that could not be inlined, in source order.
+    pub not_inlined: Vec<NotInlined>,
+}
+
+impl Inlining {
+    fn extend(&mut self, other: Inlining) {
+        self.calls.extend(other.calls);
+        self.not_inlined.extend(other.not_inlined);
+    }
+}
+
+/// Inlines the small private functions of every module of a program. The script is left alone, as
+/// it can only call public functions.
+pub fn inline_program(program: &mut Program) -> Inlining {
+    let mut inlining = Inlining::default();
+    for module in &mut program.modules {
+        inlining.extend(inline_module(module));
+    }
+    inlining
+}
+
+/// Inlines the calls of the small private functions of a module made by its functions.
+pub fn inline_module(module: &mut ModuleDefinition) -> Inlining {
+    let module_name = module.name.to_string();
+    let graph = CallGraph::new(slice::from_ref(module));
+    let mut order = vec![];
+    let mut visited = BTreeSet::new();
+    for (name, _) in &module.functions {
+        post_order(
+            &graph,
+            FunctionId::new(module_name.as_str(), name.to_string()),
+            &mut visited,
+            &mut order,
+        );
+    }
+
+    let mut inlining = Inlining::default();
+    // The functions whose calls are inlined, with the calls they make already inlined.
+    let mut callees: BTreeMap<FunctionName, Function_> = BTreeMap::new();
+    for id in order {
+        let (name, function) = match module
+            .functions
+            .iter_mut()
+            .find(|(name, _)| name.as_inner().as_str() == id.name)
+        {
+            Some(entry) => entry,
+            None => continue,
+        };
+        inlining
+            .calls
+            .extend(inline_calls(name, &mut function.value, &callees));
+        if function.value.inline == Some(InlineHint::NeverInline) {
+            continue;
+        }
+        let recursive = graph.reachable_from(&id).contains(&id);
+        match inlinable(&function.value, recursive) {
+            Ok(()) => {
+                if function.value.inline == Some(InlineHint::Inline)
+                    || body_size(&function.value.body) <= MAX_INLINED_SIZE
+                {
+                    callees.insert(name.clone(), function.value.clone());
+                }
+            }
+            Err(reason) => {
+                if function.value.inline == Some(InlineHint::Inline) {
+                    inlining.not_inlined.push(NotInlined {
+                        loc: function.span,
+                        name: name.clone(),
+                        reason,
+                    })
+                }
+            }
+        }
+    }
+    inlining.calls.sort_by_key(|call| call.loc.start());
+    inlining
+        .not_inlined
+        .sort_by_key(|function| function.loc.start());
+    inlining
+}
+
+/// Adds the functions of the module reachable from `function` to `order`, each after the ones it
+/// calls, except in recursive calls.
+fn post_order(
+    graph: &CallGraph,
+    function: FunctionId,
+    visited: &mut BTreeSet<FunctionId>,
+    order: &mut Vec<FunctionId>,
+) {
+    if !visited.insert(function.clone()) {
+        return;
+    }
+    let callees: Vec<FunctionId> = graph
+        .callees(&function)
+        .filter(|callee| callee.module == function.module)
+        .cloned()
+        .collect();
+    for callee in callees {
+        post_order(graph, callee, visited, order);
+    }
+    order.push(function);
+}
+
+/// Returns whether the calls of a function can be inlined, whatever its size.
+fn inlinable(function: &Function_, recursive: bool) -> Result<(), NotInlinedReason> {
+    if function.visibility == FunctionVisibility::Public {
+        return Err(NotInlinedReason::Public);
+    }
+    let (locals, code) = match &function.body {
+        FunctionBody::Move { locals, code } => (locals, code),
+        FunctionBody::Native => return Err(NotInlinedReason::Native),
+    };
+    if !function.signature.type_formals.is_empty() {
+        return Err(NotInlinedReason::Generic);
+    }
+    if recursive {
+        return Err(NotInlinedReason::Recursive);
+    }
+    let ends_with_return = match code.stmts.back() {
+        Some(Statement::CommandStatement(cmd)) => {
+            if let Cmd_::Return(_) = &cmd.value {
+                true
+            } else {
+                false
+            }
+        }
+        _ => false,
+    };
+    if !ends_with_return || count_returns(code) != 1 {
+        return Err(NotInlinedReason::EarlyReturn);
+    }
+    if !releases_references(&function.signature.formals, locals, code) {
+        return Err(NotInlinedReason::References);
+    }
+    Ok(())
+}
+
+fn count_returns(block: &Block_) -> usize {
+    block
+        .stmts
+        .iter()
+        .map(|statement| match statement {
+            Statement::CommandStatement(cmd) => {
+                if let Cmd_::Return(_) = &cmd.value {
+                    1
+                } else {
+                    0
+                }
+            }
+            Statement::IfElseStatement(if_else) => {
+                count_returns(&if_else.if_block.value)
+                    + if_else
+                        .else_block
+                        .as_ref()
+                        .map_or(0, |else_block| count_returns(&else_block.value))
+            }
+            Statement::WhileStatement(while_) => count_returns(&while_.block.value),
+            Statement::LoopStatement(loop_) => count_returns(&loop_.block.value),
+            Statement::EmptyStatement => 0,
+        })
+        .sum()
+}
+
+/// The state of a local of a reference type along the body of a function.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Reference {
+    Unassigned,
+    Assigned,
+    Moved,
+}
+
+/// Returns whether every parameter and local of a reference type is moved exactly once by the
+/// statements at the top of the body, and is neither used otherwise nor assigned afterwards.
+fn releases_references(formals: &[(Var, Type)], locals: &[(Var, Type)], code: &Block_) -> bool {
+    let mut references: BTreeMap<Var_, Reference> = BTreeMap::new();
+    for (vars, initial) in &[
+        (formals, Reference::Assigned),
+        (locals, Reference::Unassigned),
+    ] {
+        for (var, ty) in vars.iter() {
+            if let Type::Reference(_, _) = ty {
+                references.insert(var.value.clone(), *initial);
+            }
+        }
+    }
+    if references.is_empty() {
+        return true;
+    }
+    for statement in &code.stmts {
+        let mut exps = vec![];
+        let mut assigned = vec![];
+        let mut nested = vec![];
+        match statement {
+            Statement::CommandStatement(cmd) => {
+                exps.extend(cmd_exps(&cmd.value));
+                assigned = cmd_assigned(&cmd.value);
+            }
+            Statement::IfElseStatement(if_else) => {
+                exps.push(&if_else.cond);
+                nested.push(&if_else.if_block.value);
+                if let Some(else_block) = &if_else.else_block {
+                    nested.push(&else_block.value);
+                }
+            }
+            Statement::WhileStatement(_) | Statement::LoopStatement(_) => {
+                let mut used = BTreeSet::new();
+                statement_vars(statement, &mut used);
+                if used.iter().any(|var| references.contains_key(var)) {
+                    return false;
+                }
+            }
+            Statement::EmptyStatement => (),
+        }
+        for block in nested {
+            let mut used = BTreeSet::new();
+            for statement in &block.stmts {
+                statement_vars(statement, &mut used);
+            }
+            if used.iter().any(|var| references.contains_key(var)) {
+                return false;
+            }
+        }
+        let mut released = true;
+        for e in exps {
+            exp_vars(e, |var, use_| {
+                if let Some(state) = references.get_mut(var) {
+                    if use_ == LocalUse::Move && *state == Reference::Assigned {
+                        *state = Reference::Moved;
+                    } else {
+                        released = false;
+                    }
+                }
+            });
+        }
+        for var in assigned {
+            if let Some(state) = references.get_mut(var) {
+                if *state == Reference::Moved {
+                    released = false;
+                }
+                *state = Reference::Assigned;
+            }
+        }
+        if !released {
+            return false;
+        }
+    }
+    references
+        .values()
+        .all(|state| *state != Reference::Assigned)
+}
+
+/// Adds the locals a statement uses or assigns to `vars`.
+fn statement_vars<'a>(statement: &'a Statement, vars: &mut BTreeSet<&'a Var_>) {
+    fn block_vars<'a>(block: &'a Block_, vars: &mut BTreeSet<&'a Var_>) {
+        for statement in &block.stmts {
+            statement_vars(statement, vars);
+        }
+    }
+    match statement {
+        Statement::CommandStatement(cmd) => {
+            for e in cmd_exps(&cmd.value) {
+                exp_vars(e, |var, _| {
+                    vars.insert(var);
+                });
+            }
+            vars.extend(cmd_assigned(&cmd.value));
+        }
+        Statement::IfElseStatement(if_else) => {
+            exp_vars(&if_else.cond, |var, _| {
+                vars.insert(var);
+            });
+            block_vars(&if_else.if_block.value, vars);
+            if let Some(else_block) = &if_else.else_block {
+                block_vars(&else_block.value, vars);
+            }
+        }
+        Statement::WhileStatement(while_) => {
+            exp_vars(&while_.cond, |var, _| {
+                vars.insert(var);
+            });
+            block_vars(&while_.block.value, vars);
+        }
+        Statement::LoopStatement(loop_) => {
+            vars.extend(lvalues_assigned(&loop_.results));
+            block_vars(&loop_.block.value, vars);
+        }
+        Statement::EmptyStatement => (),
+    }
+}
+
+/// The number of statements and expressions in the body of a function.
+fn body_size(body: &FunctionBody) -> usize {
+    match body {
+        FunctionBody::Move { code, .. } => block_size(code),
+        FunctionBody::Native => 0,
+    }
+}
+
+fn block_size(block: &Block_) -> usize {
+    block
+        .stmts
+        .iter()
+        .map(|statement| match statement {
+            Statement::CommandStatement(cmd) => {
+                1 + cmd_exps(&cmd.value)
+                    .into_iter()
+                    .map(exp_size)
+                    .sum::<usize>()
+            }
+            Statement::IfElseStatement(if_else) => {
+                1 + exp_size(&if_else.cond)
+                    + block_size(&if_else.if_block.value)
+                    + if_else
+                        .else_block
+                        .as_ref()
+                        .map_or(0, |else_block| block_size(&else_block.value))
+            }
+            Statement::WhileStatement(while_) => {
+                1 + exp_size(&while_.cond) + block_size(&while_.block.value)
+            }
+            Statement::LoopStatement(loop_) => 1 + block_size(&loop_.block.value),
+            Statement::EmptyStatement => 0,
+        })
+        .sum()
+}
+
+fn exp_size(e: &Exp) -> usize {
+    let mut size = 0;
+    visit_exp(e, |_| size += 1);
+    size
+}
+
+/// Inlines the calls of `callees` made by a function, returning them.
+fn inline_calls(
+    name: &FunctionName,
+    function: &mut Function_,
+    callees: &BTreeMap<FunctionName, Function_>,
+) -> Vec<InlinedCall> {
+    let formals = &function.signature.formals;
+    let (locals, code) = match &mut function.body {
+        FunctionBody::Move { locals, code } => (locals, code),
+        FunctionBody::Native => return vec![],
+    };
+    let mut inliner = Inliner {
+        caller: name,
+        callees,
+        names: formals
+            .iter()
+            .chain(locals.iter())
+            .map(|(var, _)| var.value.clone())
+            .collect(),
+        locals: vec![],
+        calls: vec![],
+    };
+    inliner.block(code);
+    locals.extend(inliner.locals);
+    inliner.calls
+}
+
+struct Inliner<'a> {
+    caller: &'a FunctionName,
+    callees: &'a BTreeMap<FunctionName, Function_>,
+    /// The names of the locals of the caller, including the ones added.
+    names: BTreeSet<Var_>,
+    /// The locals added to the caller.
+    locals: Vec<(Var, Type)>,
+    calls: Vec<InlinedCall>,
+}
+
+impl<'a> Inliner<'a> {
+    fn block(&mut self, block: &mut Block_) {
+        for mut statement in mem::take(&mut block.stmts) {
+            match &mut statement {
+                Statement::CommandStatement(cmd) => {
+                    if let Some(inlined) = self.inline(&cmd.value, cmd.span) {
+                        block.stmts.extend(inlined);
+                        continue;
+                    }
+                }
+                Statement::IfElseStatement(if_else) => {
+                    self.block(&mut if_else.if_block.value);
+                    if let Some(else_block) = &mut if_else.else_block {
+                        self.block(&mut else_block.value);
+                    }
+                }
+                Statement::WhileStatement(while_) => self.block(&mut while_.block.value),
+                Statement::LoopStatement(loop_) => self.block(&mut loop_.block.value),
+                Statement::EmptyStatement => (),
+            }
+            block.stmts.push_back(statement);
+        }
+    }
+
+    /// Returns the statements replacing a command, if it is a call to inline.
+    fn inline(&mut self, cmd: &Cmd_, loc: Loc) -> Option<Vec<Statement>> {
+        let call = match cmd {
+            Cmd_::Assign(_, e) => e,
+            Cmd_::Return(e) | Cmd_::Exp(e) => &**e,
+            _ => return None,
+        };
+        let (callee_name, args) = match &call.value {
+            Exp_::FunctionCall(f, args) => match &f.value {
+                FunctionCall_::ModuleFunctionCall { module, name, .. }
+                    if *module == ModuleName::module_self() =>
+                {
+                    (name, args)
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let callees = self.callees;
+        let callee = callees.get(callee_name)?;
+        let (locals, code) = match &callee.body {
+            FunctionBody::Move { locals, code } => (locals, code),
+            FunctionBody::Native => return None,
+        };
+        if let Cmd_::Exp(_) = cmd {
+            if !callee.signature.return_type.is_empty() {
+                return None;
+            }
+        }
+
+        let mut renaming = BTreeMap::new();
+        for (var, ty) in callee.signature.formals.iter().chain(locals) {
+            let fresh = self.fresh(&var.value);
+            renaming.insert(var.value.clone(), fresh.clone());
+            let local = Spanned {
+                span: var.span,
+                value: fresh,
+            };
+            self.locals.push((local, ty.clone()));
+        }
+        let params: Vec<LValue> = callee
+            .signature
+            .formals
+            .iter()
+            .map(|(var, _)| {
+                let renamed = Spanned {
+                    span: var.span,
+                    value: renaming[&var.value].clone(),
+                };
+                Spanned {
+                    span: var.span,
+                    value: LValue_::Var(renamed),
+                }
+            })
+            .collect();
+        let statement = |cmd| {
+            Statement::CommandStatement(Spanned {
+                span: loc,
+                value: cmd,
+            })
+        };
+
+        let mut stmts = vec![];
+        match &args.value {
+            Exp_::ExprList(exps) if exps.len() == params.len() => {
+                for (param, e) in params.into_iter().zip(exps) {
+                    stmts.push(statement(Cmd_::Assign(vec![param], e.clone())));
+                }
+            }
+            _ => stmts.push(statement(Cmd_::Assign(params, args.as_ref().clone()))),
+        }
+        let mut body = code.clone();
+        rename_block(&mut body, &renaming);
+        let result = match body.stmts.pop_back() {
+            Some(Statement::CommandStatement(Spanned {
+                value: Cmd_::Return(e),
+                ..
+            })) => e,
+            _ => unreachable!("the functions inlined end with their only return"),
+        };
+        stmts.extend(body.stmts);
+        match cmd {
+            Cmd_::Assign(lvalues, _) => {
+                stmts.push(statement(Cmd_::Assign(lvalues.clone(), *result)))
+            }
+            Cmd_::Return(_) => stmts.push(statement(Cmd_::Return(result))),
+            _ => {
+                let returns_nothing = match &result.value {
+                    Exp_::ExprList(exps) => exps.is_empty(),
+                    _ => false,
+                };
+                if !returns_nothing {
+                    stmts.push(statement(Cmd_::Exp(result)));
+                }
+            }
+        }
+        self.calls.push(InlinedCall {
+            loc,
+            caller: self.caller.clone(),
+            callee: callee_name.clone(),
+        });
+        Some(stmts)
+    }
+
+    /// Returns an unused name for a local of an inlined function: its own name if the caller has
+    /// no local of that name, or its name followed by a number.
+    fn fresh(&mut self, var: &Var_) -> Var_ {
+        if self.names.insert(var.clone()) {
+            return var.clone();
+        }
+        let mut index = 1;
+        loop {
+            let name = Var_::parse(format!("{}_{}", var, index))
+                .expect("a local followed by a number is a local");
+            if self.names.insert(name.clone()) {
+                return name;
+            }
+            index += 1;
+        }
+    }
+}
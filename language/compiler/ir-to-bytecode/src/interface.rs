@@ -0,0 +1,78 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Generation of module interfaces.
+//!
+//! The interface of a module declares what other modules can use from it, and nothing else: its
+//! structs, as native structs without their fields, and its public functions, as native functions
+//! keeping their signatures and `acquires` lists. Internal functions, invariants, specifications
+//! and synthetics are dropped, as are the imports only they used.
+//!
+//! An interface compiles on its own, so that modules depending on a module can be compiled
+//! against its interface, without the implementation being published or compiled again. Its
+//! natives are not provided by the VM, so an interface is only a compile-time dependency: it does
+//! not pass the verification of dependencies and cannot be published.
+
+use move_ir_types::ast::{
+    FunctionBody, FunctionVisibility, ModuleDefinition, ModuleName, StructDefinitionFields, Type,
+};
+use std::collections::BTreeSet;
+
+/// Returns the interface of `module`.
+pub fn module_interface(module: &ModuleDefinition) -> ModuleDefinition {
+    let mut interface = module.clone();
+    for struct_def in &mut interface.structs {
+        struct_def.value.fields = StructDefinitionFields::Native;
+        struct_def.value.invariants.clear();
+    }
+    interface
+        .functions
+        .retain(|(_, function)| function.value.visibility == FunctionVisibility::Public);
+    for (_, function) in &mut interface.functions {
+        function.value.body = FunctionBody::Native;
+        function.value.specifications.clear();
+    }
+    interface.synthetics.clear();
+
+    let mut used = BTreeSet::new();
+    for (_, function) in &interface.functions {
+        let signature = &function.value.signature;
+        for ty in signature
+            .formals
+            .iter()
+            .map(|(_, ty)| ty)
+            .chain(&signature.return_type)
+        {
+            used_modules(ty, &mut used);
+        }
+    }
+    interface
+        .imports
+        .retain(|import| used.contains(&import.alias));
+    interface
+}
+
+/// Adds the modules the struct types in `ty` are declared in to `used`.
+fn used_modules(ty: &Type, used: &mut BTreeSet<ModuleName>) {
+    match ty {
+        Type::Struct(ident, tys) => {
+            used.insert(ident.module.clone());
+            for ty in tys {
+                used_modules(ty, used);
+            }
+        }
+        Type::Reference(_, ty) => used_modules(ty, used),
+        Type::Tuple(tys) => {
+            for ty in tys {
+                used_modules(ty, used);
+            }
+        }
+        Type::Address
+        | Type::U8
+        | Type::U64
+        | Type::U128
+        | Type::Bool
+        | Type::ByteArray
+        | Type::TypeParameter(_) => (),
+    }
+}
@@ -5,9 +5,55 @@
 
 extern crate log;
 
+pub mod abi;
+pub mod acquires;
+pub mod annotations;
+pub mod attestation;
+pub mod borrows;
+pub mod build;
+pub mod builders;
+pub mod bundle;
+pub mod call_graph;
+pub mod cfg;
+pub mod coalesce;
 pub mod compiler;
+pub mod constants;
 mod context;
+pub mod control_flow;
+pub mod dataflow;
+pub mod dead_stores;
+pub mod dependencies;
+pub mod diagnostics;
+pub mod directives;
+pub mod docgen;
 pub mod errors;
+pub mod formatter;
+pub mod freeze;
+pub mod golden;
+pub mod imports;
+pub mod incremental;
+pub mod inline;
+pub mod interface;
+pub mod limits;
+pub mod lint;
+mod locals;
+pub mod metadata;
+pub mod metrics;
+pub mod moves;
+pub mod mutation;
+pub mod natives;
 pub mod parser;
+pub mod partial;
+pub mod placeholders;
+pub mod plugins;
+pub mod relocate;
+pub mod rename;
+pub mod resolver;
+pub mod specialize;
+pub mod ssa;
+pub mod taint;
+pub mod target;
+pub mod tuples;
+pub mod type_checker;
 
 // Unit tests for this crate are in the parent "compiler" crate.
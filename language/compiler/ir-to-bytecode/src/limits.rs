@@ -0,0 +1,209 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Limits of the binary format.
+//!
+//! The tables of a compiled module are indexed by 16-bit integers and the locals of a function by
+//! 8-bit ones, so declarations with too many items cannot be serialized. The declarations of the
+//! AST are checked against those limits before compiling, so that the error points at the
+//! offending declaration and says how many items it has, instead of failing late in the compiler
+//! or the serializer with no location.
+//!
+//! The locals checked are the parameters and the declared locals of each function; the compiler
+//! adds temporaries to them, and reports the number of instructions of a body once it is
+//! generated.
+
+use crate::errors::LimitError;
+use move_ir_types::ast::{
+    Function, FunctionBody, FunctionName, Loc, ModuleDefinition, Program, Script,
+    StructDefinitionFields,
+};
+use std::fmt;
+use vm::file_format::{CodeOffset, LocalIndex, MemberCount, TableIndex};
+
+/// A limit of the binary format on the number of items of a declaration.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Limit {
+    /// The parameters and locals of a function.
+    Locals,
+    /// The fields of a struct.
+    Fields,
+    /// The type parameters of a struct or function.
+    TypeParameters,
+    /// The modules a module or script refers to, including itself.
+    ModuleHandles,
+    /// The structs a module declares.
+    StructDefinitions,
+    /// The functions a module declares.
+    FunctionDefinitions,
+    /// The instructions of the body of a function.
+    Instructions,
+}
+
+impl Limit {
+    /// The largest number of items the binary format allows.
+    pub fn max(self) -> usize {
+        match self {
+            Limit::Locals => LocalIndex::max_value() as usize,
+            Limit::Fields => MemberCount::max_value() as usize,
+            Limit::Instructions => CodeOffset::max_value() as usize,
+            Limit::TypeParameters
+            | Limit::ModuleHandles
+            | Limit::StructDefinitions
+            | Limit::FunctionDefinitions => TableIndex::max_value() as usize,
+        }
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Limit::Locals => "parameters and locals",
+            Limit::Fields => "fields",
+            Limit::TypeParameters => "type parameters",
+            Limit::ModuleHandles => "module handles",
+            Limit::StructDefinitions => "struct definitions",
+            Limit::FunctionDefinitions => "function definitions",
+            Limit::Instructions => "instructions",
+        })
+    }
+}
+
+/// Checks the modules and the script of the program against the limits of the binary format.
+/// Returns the declarations exceeding one.
+pub fn check_program_limits(program: &Program) -> Vec<LimitError> {
+    let mut errors = vec![];
+    for module in &program.modules {
+        errors.extend(check_module_limits(module));
+    }
+    errors.extend(check_script_limits(&program.script));
+    errors
+}
+
+/// Checks the module and its structs and functions against the limits of the binary format.
+/// Returns the declarations exceeding one.
+pub fn check_module_limits(module: &ModuleDefinition) -> Vec<LimitError> {
+    let mut errors = vec![];
+    let declaration = format!("module `{}`", module.name);
+    // The module refers to itself.
+    check(
+        &mut errors,
+        None,
+        &declaration,
+        Limit::ModuleHandles,
+        module.imports.len() + 1,
+    );
+    check(
+        &mut errors,
+        module
+            .structs
+            .get(Limit::StructDefinitions.max())
+            .map(|s| s.span),
+        &declaration,
+        Limit::StructDefinitions,
+        module.structs.len(),
+    );
+    check(
+        &mut errors,
+        module
+            .functions
+            .get(Limit::FunctionDefinitions.max())
+            .map(|(_, f)| f.span),
+        &declaration,
+        Limit::FunctionDefinitions,
+        module.functions.len(),
+    );
+    for s in &module.structs {
+        let declaration = format!("struct `{}`", s.value.name);
+        check(
+            &mut errors,
+            Some(s.span),
+            &declaration,
+            Limit::TypeParameters,
+            s.value.type_formals.len(),
+        );
+        if let StructDefinitionFields::Move { fields } = &s.value.fields {
+            check(
+                &mut errors,
+                Some(s.span),
+                &declaration,
+                Limit::Fields,
+                fields.len(),
+            );
+        }
+    }
+    for (name, function) in &module.functions {
+        check_function(&mut errors, name, function);
+    }
+    errors
+}
+
+/// Checks the script and its `main` function against the limits of the binary format. Returns the
+/// declarations exceeding one.
+pub fn check_script_limits(script: &Script) -> Vec<LimitError> {
+    let mut errors = vec![];
+    // The script refers to its own module.
+    check(
+        &mut errors,
+        None,
+        "script",
+        Limit::ModuleHandles,
+        script.imports.len() + 1,
+    );
+    check_function(&mut errors, "main", &script.main);
+    errors
+}
+
+/// Checks the number of instructions generated for the body of a function.
+pub(crate) fn check_instructions(
+    name: &FunctionName,
+    loc: Loc,
+    count: usize,
+) -> Result<(), LimitError> {
+    let mut errors = vec![];
+    check(
+        &mut errors,
+        Some(loc),
+        &format!("function `{}`", name),
+        Limit::Instructions,
+        count,
+    );
+    errors.pop().map_or(Ok(()), Err)
+}
+
+fn check_function(errors: &mut Vec<LimitError>, name: impl fmt::Display, function: &Function) {
+    let declaration = format!("function `{}`", name);
+    check(
+        errors,
+        Some(function.span),
+        &declaration,
+        Limit::TypeParameters,
+        function.value.signature.type_formals.len(),
+    );
+    if let FunctionBody::Move { locals, .. } = &function.value.body {
+        check(
+            errors,
+            Some(function.span),
+            &declaration,
+            Limit::Locals,
+            function.value.signature.formals.len() + locals.len(),
+        );
+    }
+}
+
+fn check(
+    errors: &mut Vec<LimitError>,
+    loc: Option<Loc>,
+    declaration: &str,
+    limit: Limit,
+    count: usize,
+) {
+    if count > limit.max() {
+        errors.push(LimitError {
+            loc,
+            declaration: declaration.to_string(),
+            limit,
+            count,
+        });
+    }
+}
@@ -0,0 +1,580 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A pluggable linter for Move IR sources.
+//!
+//! A lint rule implements [`Rule`](trait.Rule.html), overriding the hooks for the parts of the
+//! AST it is interested in. The [`Linter`](struct.Linter.html) walks every module and script,
+//! calls the hooks of each enabled rule and attaches the rule's configured severity to the
+//! reports it produces. Rules start at their default severity, which can be overridden per rule
+//! to silence it (`allow`) or to make it fail the build (`deny`).
+
+use crate::{acquires::acquires_diff, formatter::format_exp, parser::FUTURE_KEYWORDS};
+use anyhow::{bail, Result};
+use libra_types::identifier::{IdentStr, Identifier};
+use move_ir_types::ast::{
+    BinOp, Block_, Cmd, Cmd_, CopyableVal_, Exp, Exp_, Function, FunctionBody, FunctionName,
+    ImportDefinition, LValue_, Loc, ModuleDefinition, ModuleName, Program, Script, Statement,
+    StructDefinitionFields, Var_,
+};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// How a lint is treated.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    /// The rule is not run
+    Allow,
+    /// Violations are reported as warnings
+    Warn,
+    /// Violations are reported as errors
+    Deny,
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "allow" => Ok(Severity::Allow),
+            "warn" => Ok(Severity::Warn),
+            "deny" => Ok(Severity::Deny),
+            _ => bail!("unknown lint severity `{}`", s),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Allow => write!(f, "allow"),
+            Severity::Warn => write!(f, "warning"),
+            Severity::Deny => write!(f, "error"),
+        }
+    }
+}
+
+/// A violation found by a rule.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Report {
+    /// The location of the violation.
+    pub loc: Loc,
+    /// What is wrong.
+    pub message: String,
+    /// Related locations, each with an explanation.
+    pub notes: Vec<(Loc, String)>,
+}
+
+impl Report {
+    /// A report without notes.
+    pub fn new(loc: Loc, message: impl Into<String>) -> Self {
+        Report {
+            loc,
+            message: message.into(),
+            notes: vec![],
+        }
+    }
+
+    /// Attaches a note pointing at a related location.
+    pub fn with_note(mut self, loc: Loc, note: impl Into<String>) -> Self {
+        self.notes.push((loc, note.into()));
+        self
+    }
+}
+
+/// A report of an enabled rule, along with the severity configured for it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Lint {
+    /// The name of the rule that produced the report.
+    pub rule: &'static str,
+    /// Either `Warn` or `Deny`.
+    pub severity: Severity,
+    /// What the rule found.
+    pub report: Report,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} [{}]",
+            self.severity, self.report.message, self.rule
+        )
+    }
+}
+
+/// The context a function is linted in.
+pub struct FunctionContext<'a> {
+    /// The name of the function, `main` for scripts.
+    pub name: &'a FunctionName,
+    /// The imports of the enclosing module or script.
+    pub imports: &'a [ImportDefinition],
+}
+
+/// A lint rule. Every hook does nothing by default.
+pub trait Rule {
+    /// The name used to configure the rule, in snake case.
+    fn name(&self) -> &'static str;
+
+    /// A one line description of what the rule reports.
+    fn description(&self) -> &'static str;
+
+    /// The severity of the rule unless configured otherwise.
+    fn default_severity(&self) -> Severity {
+        Severity::Warn
+    }
+
+    /// Called once for every module, before its functions.
+    fn check_module(&self, _module: &ModuleDefinition, _reports: &mut Vec<Report>) {}
+
+    /// Called for every function, including `main` and native functions, before its body.
+    fn check_function(
+        &self,
+        _context: &FunctionContext,
+        _function: &Function,
+        _reports: &mut Vec<Report>,
+    ) {
+    }
+
+    /// Called for every command of a function body.
+    fn check_cmd(&self, _cmd: &Cmd, _reports: &mut Vec<Report>) {}
+
+    /// Called for every expression of a function body, outer expressions first.
+    fn check_exp(&self, _exp: &Exp, _reports: &mut Vec<Report>) {}
+}
+
+/// Runs a set of rules, each at its configured severity.
+pub struct Linter {
+    rules: Vec<(Box<dyn Rule>, Severity)>,
+}
+
+impl Default for Linter {
+    /// A linter running every built-in rule at its default severity.
+    fn default() -> Self {
+        let mut linter = Linter::empty();
+        linter.add_rule(Box::new(UnusedAcquires));
+        linter.add_rule(Box::new(MagicAbortCodes));
+        linter.add_rule(Box::new(ShadowedLocals));
+        linter.add_rule(Box::new(ReservedNames::default()));
+        linter.add_rule(Box::new(MixedLogicalOperators));
+        linter
+    }
+}
+
+impl Linter {
+    /// A linter without any rule.
+    pub fn empty() -> Self {
+        Linter { rules: vec![] }
+    }
+
+    /// Adds a rule at its default severity.
+    pub fn add_rule(&mut self, rule: Box<dyn Rule>) {
+        let severity = rule.default_severity();
+        self.rules.push((rule, severity));
+    }
+
+    /// The rules of the linter, along with their configured severity.
+    pub fn rules(&self) -> impl Iterator<Item = (&dyn Rule, Severity)> {
+        self.rules
+            .iter()
+            .map(|(rule, severity)| (rule.as_ref(), *severity))
+    }
+
+    /// Overrides the severity of the rule called `name`.
+    pub fn set_severity(&mut self, name: &str, severity: Severity) -> Result<()> {
+        match self.rules.iter_mut().find(|(rule, _)| rule.name() == name) {
+            Some((_, configured)) => {
+                *configured = severity;
+                Ok(())
+            }
+            None => bail!("unknown lint rule `{}`", name),
+        }
+    }
+
+    /// Lints a module, returning the lints in source order.
+    pub fn lint_module(&self, module: &ModuleDefinition) -> Vec<Lint> {
+        self.run(|rule, reports| {
+            rule.check_module(module, reports);
+            for (name, function) in &module.functions {
+                let context = FunctionContext {
+                    name,
+                    imports: &module.imports,
+                };
+                walk_function(rule, &context, function, reports);
+            }
+        })
+    }
+
+    /// Lints a script, returning the lints in source order.
+    pub fn lint_script(&self, script: &Script) -> Vec<Lint> {
+        let name = FunctionName::new(Identifier::new("main").unwrap());
+        let context = FunctionContext {
+            name: &name,
+            imports: &script.imports,
+        };
+        self.run(|rule, reports| walk_function(rule, &context, &script.main, reports))
+    }
+
+    /// Lints the modules and the script of a program, in that order.
+    pub fn lint_program(&self, program: &Program) -> Vec<Lint> {
+        let mut lints: Vec<Lint> = program
+            .modules
+            .iter()
+            .flat_map(|module| self.lint_module(module))
+            .collect();
+        lints.extend(self.lint_script(&program.script));
+        lints
+    }
+
+    fn run(&self, walk: impl Fn(&dyn Rule, &mut Vec<Report>)) -> Vec<Lint> {
+        let mut lints = vec![];
+        for (rule, severity) in &self.rules {
+            if *severity == Severity::Allow {
+                continue;
+            }
+            let mut reports = vec![];
+            walk(rule.as_ref(), &mut reports);
+            lints.extend(reports.into_iter().map(|report| Lint {
+                rule: rule.name(),
+                severity: *severity,
+                report,
+            }));
+        }
+        lints.sort_by_key(|lint| lint.report.loc.start());
+        lints
+    }
+}
+
+fn walk_function(
+    rule: &dyn Rule,
+    context: &FunctionContext,
+    function: &Function,
+    reports: &mut Vec<Report>,
+) {
+    rule.check_function(context, function, reports);
+    if let FunctionBody::Move { code, .. } = &function.value.body {
+        walk_block(rule, code, reports);
+    }
+}
+
+fn walk_block(rule: &dyn Rule, block: &Block_, reports: &mut Vec<Report>) {
+    for statement in &block.stmts {
+        match statement {
+            Statement::CommandStatement(cmd) => walk_cmd(rule, cmd, reports),
+            Statement::IfElseStatement(if_else) => {
+                walk_exp(rule, &if_else.cond, reports);
+                walk_block(rule, &if_else.if_block.value, reports);
+                if let Some(else_block) = &if_else.else_block {
+                    walk_block(rule, &else_block.value, reports);
+                }
+            }
+            Statement::WhileStatement(while_) => {
+                walk_exp(rule, &while_.cond, reports);
+                walk_block(rule, &while_.block.value, reports);
+            }
+            Statement::LoopStatement(loop_) => {
+                for lvalue in &loop_.results {
+                    if let LValue_::Mutate(e) = &lvalue.value {
+                        walk_exp(rule, e, reports);
+                    }
+                }
+                walk_block(rule, &loop_.block.value, reports);
+            }
+            Statement::EmptyStatement => (),
+        }
+    }
+}
+
+fn walk_cmd(rule: &dyn Rule, cmd: &Cmd, reports: &mut Vec<Report>) {
+    rule.check_cmd(cmd, reports);
+    match &cmd.value {
+        Cmd_::Assign(lvalues, e) => {
+            for lvalue in lvalues {
+                if let LValue_::Mutate(e) = &lvalue.value {
+                    walk_exp(rule, e, reports);
+                }
+            }
+            walk_exp(rule, e, reports);
+        }
+        Cmd_::Unpack(_, _, _, e)
+        | Cmd_::Return(e)
+        | Cmd_::Abort(Some(e))
+        | Cmd_::Break(Some(e))
+        | Cmd_::Exp(e) => walk_exp(rule, e, reports),
+        Cmd_::Emit(handle, e) => {
+            walk_exp(rule, handle, reports);
+            walk_exp(rule, e, reports);
+        }
+        Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+    }
+}
+
+fn walk_exp(rule: &dyn Rule, e: &Exp, reports: &mut Vec<Report>) {
+    rule.check_exp(e, reports);
+    match &e.value {
+        Exp_::Dereference(e) | Exp_::UnaryExp(_, e) | Exp_::Borrow { exp: e, .. } => {
+            walk_exp(rule, e, reports)
+        }
+        Exp_::BinopExp(lhs, _, rhs) => {
+            walk_exp(rule, lhs, reports);
+            walk_exp(rule, rhs, reports);
+        }
+        Exp_::Pack(_, _, fields) => {
+            for (_, e) in fields {
+                walk_exp(rule, e, reports);
+            }
+        }
+        Exp_::FunctionCall(_, e) => walk_exp(rule, e, reports),
+        Exp_::ExprList(exps) => {
+            for e in exps {
+                walk_exp(rule, e, reports);
+            }
+        }
+        Exp_::Value(_)
+        | Exp_::Move(_)
+        | Exp_::Copy(_)
+        | Exp_::Var(_)
+        | Exp_::BorrowLocal(_, _)
+        | Exp_::Placeholder(_) => (),
+    }
+}
+
+//**************************************************************************************************
+// Built-in rules
+//**************************************************************************************************
+
+/// Reports entries of an `acquires` list that the function never acquires.
+pub struct UnusedAcquires;
+
+impl Rule for UnusedAcquires {
+    fn name(&self) -> &'static str {
+        "unused_acquires"
+    }
+
+    fn description(&self) -> &'static str {
+        "`acquires` entries for resources the function never acquires"
+    }
+
+    fn check_module(&self, module: &ModuleDefinition, reports: &mut Vec<Report>) {
+        for diff in acquires_diff(module) {
+            for name in &diff.extraneous {
+                reports.push(Report::new(
+                    diff.loc,
+                    format!(
+                        "function `{}` declares `acquires {}` but never acquires it",
+                        diff.function, name
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// Reports abort codes written as integer literals, including the codes of `assert`. The IR has
+/// no named constants, so this rule is only enabled on request, by code bases that produce their
+/// codes with functions.
+pub struct MagicAbortCodes;
+
+impl Rule for MagicAbortCodes {
+    fn name(&self) -> &'static str {
+        "magic_abort_codes"
+    }
+
+    fn description(&self) -> &'static str {
+        "abort codes written as integer literals"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Allow
+    }
+
+    fn check_cmd(&self, cmd: &Cmd, reports: &mut Vec<Report>) {
+        let mut code = match &cmd.value {
+            Cmd_::Abort(Some(code)) => code.as_ref(),
+            _ => return,
+        };
+        // `abort(42)` parses as a list of one expression.
+        while let Exp_::ExprList(exps) = &code.value {
+            if exps.len() != 1 {
+                return;
+            }
+            code = &exps[0];
+        }
+        if let Exp_::Value(v) = &code.value {
+            if let CopyableVal_::U64(value) = &v.value {
+                reports.push(Report::new(
+                    code.span,
+                    format!("abort code `{}` is a magic number", value),
+                ));
+            }
+        }
+    }
+}
+
+/// Reports formals and locals that reuse the name of an earlier formal or local of the same
+/// function, or the alias of an imported module.
+pub struct ShadowedLocals;
+
+impl Rule for ShadowedLocals {
+    fn name(&self) -> &'static str {
+        "shadowed_locals"
+    }
+
+    fn description(&self) -> &'static str {
+        "formals and locals reusing the name of another local or of a module"
+    }
+
+    fn check_function(
+        &self,
+        context: &FunctionContext,
+        function: &Function,
+        reports: &mut Vec<Report>,
+    ) {
+        let mut declared: BTreeMap<&Var_, Loc> = BTreeMap::new();
+        let formals = function.value.signature.formals.iter().map(|(var, _)| var);
+        let locals = match &function.value.body {
+            FunctionBody::Move { locals, .. } => &locals[..],
+            FunctionBody::Native => &[],
+        };
+        for var in formals.chain(locals.iter().map(|(var, _)| var)) {
+            if let Some(previous) = declared.get(&var.value) {
+                reports.push(
+                    Report::new(
+                        var.span,
+                        format!(
+                            "`{}` shadows a local of `{}` with the same name",
+                            var.value, context.name
+                        ),
+                    )
+                    .with_note(*previous, "previously declared here"),
+                );
+                continue;
+            }
+            declared.insert(&var.value, var.span);
+            let is_module = context
+                .imports
+                .iter()
+                .map(|import| import.alias.as_inner())
+                .chain(std::iter::once(ModuleName::self_name()))
+                .any(|alias| alias == var.value.name());
+            if is_module {
+                reports.push(Report::new(
+                    var.span,
+                    format!("`{}` shadows the module with the same name", var.value),
+                ));
+            }
+        }
+    }
+}
+
+/// Reports declarations named after a reserved name, by default one of the future keywords. This
+/// gives code bases time to rename them before the names are rejected by the parser, with
+/// `ParserOptions::reserved_names`.
+pub struct ReservedNames {
+    /// The names to report.
+    pub names: &'static [&'static str],
+}
+
+impl Default for ReservedNames {
+    fn default() -> Self {
+        ReservedNames {
+            names: FUTURE_KEYWORDS,
+        }
+    }
+}
+
+impl ReservedNames {
+    fn check(&self, name: &IdentStr, loc: Loc, reports: &mut Vec<Report>) {
+        if self.names.contains(&name.as_str()) {
+            reports.push(Report::new(loc, format!("`{}` is a reserved name", name)));
+        }
+    }
+}
+
+impl Rule for ReservedNames {
+    fn name(&self) -> &'static str {
+        "reserved_names"
+    }
+
+    fn description(&self) -> &'static str {
+        "declarations named after a name reserved for a future keyword"
+    }
+
+    fn check_module(&self, module: &ModuleDefinition, reports: &mut Vec<Report>) {
+        for struct_def in &module.structs {
+            let def = &struct_def.value;
+            self.check(def.name.as_inner(), struct_def.span, reports);
+            for (type_var, _) in &def.type_formals {
+                self.check(type_var.value.name(), type_var.span, reports);
+            }
+            if let StructDefinitionFields::Move { fields } = &def.fields {
+                for (field, _) in fields {
+                    self.check(field.value.name(), field.span, reports);
+                }
+            }
+        }
+    }
+
+    fn check_function(
+        &self,
+        context: &FunctionContext,
+        function: &Function,
+        reports: &mut Vec<Report>,
+    ) {
+        self.check(context.name.as_inner(), function.span, reports);
+        let signature = &function.value.signature;
+        for (type_var, _) in &signature.type_formals {
+            self.check(type_var.value.name(), type_var.span, reports);
+        }
+        let locals = match &function.value.body {
+            FunctionBody::Move { locals, .. } => &locals[..],
+            FunctionBody::Native => &[],
+        };
+        for (var, _) in signature.formals.iter().chain(locals) {
+            self.check(var.value.name(), var.span, reports);
+        }
+    }
+}
+
+/// Reports `&&` expressions used as an operand of `||` without parentheses. `&&` binds more
+/// tightly, which is not what authors used to languages giving both the same precedence expect,
+/// so the report shows the grouping the parser chose.
+pub struct MixedLogicalOperators;
+
+impl Rule for MixedLogicalOperators {
+    fn name(&self) -> &'static str {
+        "mixed_logical_operators"
+    }
+
+    fn description(&self) -> &'static str {
+        "`&&` and `||` mixed in one expression without parentheses"
+    }
+
+    fn check_exp(&self, exp: &Exp, reports: &mut Vec<Report>) {
+        let (lhs, rhs) = match &exp.value {
+            Exp_::BinopExp(lhs, BinOp::Or, rhs) => (lhs, rhs),
+            _ => return,
+        };
+        // A parenthesized operand is an `ExprList`, so only the implicit groupings match.
+        let is_and = |e: &Exp| match &e.value {
+            Exp_::BinopExp(_, BinOp::And, _) => true,
+            _ => false,
+        };
+        let operand = |e: &Exp| {
+            if is_and(e) {
+                format!("({})", format_exp(e))
+            } else {
+                format_exp(e)
+            }
+        };
+        let grouping = format!("{} || {}", operand(lhs), operand(rhs));
+        for e in [lhs, rhs].iter().filter(|e| is_and(e)) {
+            reports.push(Report::new(
+                e.span,
+                format!(
+                    "`&&` binds more tightly than `||`, this is parsed as `{}`",
+                    grouping
+                ),
+            ));
+        }
+    }
+}
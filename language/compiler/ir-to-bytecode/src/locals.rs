@@ -0,0 +1,148 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! The locals the code of a function reads and assigns, for the passes over function bodies.
+
+use move_ir_types::ast::{
+    visit_exp, visit_exp_mut, Block_, Cmd_, Exp, Exp_, LValue, LValue_, Statement, Var_,
+};
+use std::collections::BTreeMap;
+
+/// How an expression uses a local.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum LocalUse {
+    Move,
+    Copy,
+    /// A bare use, as in `x + 1`, which is a move or a copy.
+    Bare,
+    Borrow,
+}
+
+/// Calls `f` on each local an expression uses, in evaluation order, with how it uses it.
+pub(crate) fn exp_vars<'a>(e: &'a Exp, mut f: impl FnMut(&'a Var_, LocalUse)) {
+    visit_exp(e, |e| match &e.value {
+        Exp_::Move(var) => f(&var.value, LocalUse::Move),
+        Exp_::Copy(var) => f(&var.value, LocalUse::Copy),
+        Exp_::Var(var) => f(&var.value, LocalUse::Bare),
+        Exp_::BorrowLocal(_, var) => f(&var.value, LocalUse::Borrow),
+        _ => (),
+    });
+}
+
+/// Calls `f` on each local an expression uses, to rename it.
+pub(crate) fn exp_vars_mut(e: &mut Exp, mut f: impl FnMut(&mut Var_)) {
+    visit_exp_mut(e, |e| match &mut e.value {
+        Exp_::Move(var) | Exp_::Copy(var) | Exp_::Var(var) | Exp_::BorrowLocal(_, var) => {
+            f(&mut var.value)
+        }
+        _ => (),
+    });
+}
+
+/// Returns the expressions a command evaluates, in evaluation order: the value it assigns comes
+/// before the references it assigns through.
+pub(crate) fn cmd_exps(cmd: &Cmd_) -> Vec<&Exp> {
+    match cmd {
+        Cmd_::Assign(lvalues, e) => Some(e)
+            .into_iter()
+            .chain(lvalues.iter().filter_map(|lvalue| match &lvalue.value {
+                LValue_::Mutate(e) => Some(e),
+                LValue_::Var(_) | LValue_::Pop => None,
+            }))
+            .collect(),
+        Cmd_::Unpack(_, _, _, e)
+        | Cmd_::Return(e)
+        | Cmd_::Exp(e)
+        | Cmd_::Abort(Some(e))
+        | Cmd_::Break(Some(e)) => vec![e],
+        Cmd_::Emit(handle, e) => vec![handle, e],
+        Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => vec![],
+    }
+}
+
+/// Returns the locals a command assigns.
+pub(crate) fn cmd_assigned(cmd: &Cmd_) -> Vec<&Var_> {
+    match cmd {
+        Cmd_::Assign(lvalues, _) => lvalues_assigned(lvalues),
+        Cmd_::Unpack(_, _, bindings, _) => bindings.iter().map(|(_, var)| &var.value).collect(),
+        _ => vec![],
+    }
+}
+
+/// Returns the locals a list of lvalues assigns, leaving out the references it assigns through.
+pub(crate) fn lvalues_assigned(lvalues: &[LValue]) -> Vec<&Var_> {
+    lvalues
+        .iter()
+        .filter_map(|lvalue| match &lvalue.value {
+            LValue_::Var(var) => Some(&var.value),
+            LValue_::Mutate(_) | LValue_::Pop => None,
+        })
+        .collect()
+}
+
+/// Calls `f` on each local a block uses or assigns, to rename it.
+pub(crate) fn block_vars_mut(block: &mut Block_, f: &mut dyn FnMut(&mut Var_)) {
+    let mut blocks = vec![block];
+    while let Some(block) = blocks.pop() {
+        for statement in &mut block.stmts {
+            match statement {
+                Statement::CommandStatement(cmd) => match &mut cmd.value {
+                    Cmd_::Assign(lvalues, e) => {
+                        lvalues_vars_mut(lvalues, f);
+                        exp_vars_mut(e, |var| f(var));
+                    }
+                    Cmd_::Unpack(_, _, bindings, e) => {
+                        for (_, var) in bindings {
+                            f(&mut var.value);
+                        }
+                        exp_vars_mut(e, |var| f(var));
+                    }
+                    Cmd_::Return(e)
+                    | Cmd_::Exp(e)
+                    | Cmd_::Abort(Some(e))
+                    | Cmd_::Break(Some(e)) => exp_vars_mut(e, |var| f(var)),
+                    Cmd_::Emit(handle, e) => {
+                        exp_vars_mut(handle, |var| f(var));
+                        exp_vars_mut(e, |var| f(var));
+                    }
+                    Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+                },
+                Statement::IfElseStatement(if_else) => {
+                    exp_vars_mut(&mut if_else.cond, |var| f(var));
+                    blocks.push(&mut if_else.if_block.value);
+                    if let Some(else_block) = &mut if_else.else_block {
+                        blocks.push(&mut else_block.value);
+                    }
+                }
+                Statement::WhileStatement(while_) => {
+                    exp_vars_mut(&mut while_.cond, |var| f(var));
+                    blocks.push(&mut while_.block.value);
+                }
+                Statement::LoopStatement(loop_) => {
+                    lvalues_vars_mut(&mut loop_.results, f);
+                    blocks.push(&mut loop_.block.value);
+                }
+                Statement::EmptyStatement => (),
+            }
+        }
+    }
+}
+
+fn lvalues_vars_mut(lvalues: &mut [LValue], f: &mut dyn FnMut(&mut Var_)) {
+    for lvalue in lvalues {
+        match &mut lvalue.value {
+            LValue_::Var(var) => f(&mut var.value),
+            LValue_::Mutate(e) => exp_vars_mut(e, |var| f(var)),
+            LValue_::Pop => (),
+        }
+    }
+}
+
+/// Renames the locals of a block that `renames` maps to another name.
+pub(crate) fn rename_block(block: &mut Block_, renames: &BTreeMap<Var_, Var_>) {
+    block_vars_mut(block, &mut |var| {
+        if let Some(into) = renames.get(var) {
+            *var = into.clone();
+        }
+    });
+}
@@ -0,0 +1,153 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Metadata of compiled modules.
+//!
+//! The entries of the `annotate` attributes of procedures, such as audit tags or version markers,
+//! only describe the source unless their key is selected, in which case they are embedded in the
+//! compiled module, so that the binary published on chain carries them and tools can read them
+//! back from it with [`read_metadata`](fn.read_metadata.html).
+//!
+//! Modules can also record how they were built: the IR language version their source declares,
+//! the version of the compiler and the options it was given. Tools such as decompilers read it
+//! back with [`read_build_info`](fn.read_build_info.html) to pick the behavior matching the
+//! compiler that produced the module.
+//!
+//! The metadata is kept in the `METADATA` table of the binary format, as JSON values under the
+//! keys [`ANNOTATIONS_KEY`](constant.ANNOTATIONS_KEY.html) and
+//! [`BUILD_INFO_KEY`](constant.BUILD_INFO_KEY.html). The VM never reads the table, so modules
+//! with metadata run the same as modules without.
+
+use crate::errors::InternalCompilerError;
+use anyhow::{Context, Result};
+use libra_types::{byte_array::ByteArray, identifier::Identifier};
+use move_ir_types::ast::ModuleDefinition;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use vm::{
+    access::ModuleAccess,
+    file_format::{CompiledModule, MetadataEntry},
+};
+
+/// The key of the metadata entry holding the selected entries of the `annotate` attributes.
+pub const ANNOTATIONS_KEY: &str = "annotations";
+
+/// The key of the metadata entry holding the build information.
+pub const BUILD_INFO_KEY: &str = "build";
+
+/// The version of the compiler recorded in build information.
+pub const COMPILER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The metadata embedded in a compiled module.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Metadata {
+    /// The selected entries of the `annotate` attributes, by function and key.
+    #[serde(default)]
+    pub annotations: BTreeMap<String, BTreeMap<String, String>>,
+    /// How the module was built, if it was asked to be recorded.
+    #[serde(default)]
+    pub build: Option<BuildInfo>,
+}
+
+impl Metadata {
+    /// Whether there is nothing to embed.
+    pub fn is_empty(&self) -> bool {
+        self.annotations.is_empty() && self.build.is_none()
+    }
+}
+
+/// How a script or module was compiled.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BuildInfo {
+    /// The IR language version the source declares, `None` if it declares none.
+    pub language_version: Option<u64>,
+    /// The version of the compiler, `COMPILER_VERSION` when built by this crate.
+    pub compiler_version: String,
+    /// The options the compiler was given, sorted, e.g. `fold_constants` or `cfg=test`.
+    pub options: Vec<String>,
+}
+
+impl BuildInfo {
+    /// The build information of a source declaring `language_version`, compiled by this crate
+    /// with `options`.
+    pub fn new(language_version: Option<u64>, options: impl IntoIterator<Item = String>) -> Self {
+        let mut options: Vec<_> = options.into_iter().collect();
+        options.sort();
+        options.dedup();
+        BuildInfo {
+            language_version,
+            compiler_version: COMPILER_VERSION.to_string(),
+            options,
+        }
+    }
+}
+
+/// Collects the entries of the `annotate` attributes of the functions of the module whose key is
+/// one of `keys`.
+pub fn module_metadata(module: &ModuleDefinition, keys: &BTreeSet<String>) -> Metadata {
+    let mut metadata = Metadata::default();
+    for (name, function) in &module.functions {
+        let selected: BTreeMap<String, String> = function
+            .value
+            .annotations
+            .iter()
+            .filter(|(key, _)| keys.contains(key))
+            .cloned()
+            .collect();
+        if !selected.is_empty() {
+            metadata.annotations.insert(name.to_string(), selected);
+        }
+    }
+    metadata
+}
+
+/// Adds the metadata to the `METADATA` table of the compiled module, leaving the module unchanged
+/// if the metadata is empty. Fails if the module already has metadata under the same keys.
+pub fn embed_metadata(module: CompiledModule, metadata: &Metadata) -> Result<CompiledModule> {
+    let mut entries = vec![];
+    if !metadata.annotations.is_empty() {
+        entries.push(metadata_entry(
+            ANNOTATIONS_KEY,
+            serde_json::to_vec(&metadata.annotations)?,
+        )?);
+    }
+    if let Some(build) = &metadata.build {
+        entries.push(metadata_entry(BUILD_INFO_KEY, serde_json::to_vec(build)?)?);
+    }
+    if entries.is_empty() {
+        return Ok(module);
+    }
+    let mut module = module.into_inner();
+    module.metadata.extend(entries);
+    Ok(module
+        .freeze()
+        .map_err(InternalCompilerError::BoundsCheckErrors)?)
+}
+
+fn metadata_entry(key: &str, value: Vec<u8>) -> Result<MetadataEntry> {
+    Ok(MetadataEntry {
+        key: Identifier::new(key)?,
+        value: ByteArray::new(value),
+    })
+}
+
+/// Reads the metadata embedded in the compiled module, empty if it has none.
+pub fn read_metadata(module: &CompiledModule) -> Result<Metadata> {
+    let mut metadata = Metadata::default();
+    if let Some(value) = module.metadata_value(&Identifier::new(ANNOTATIONS_KEY)?) {
+        metadata.annotations =
+            serde_json::from_slice(value.as_bytes()).context("Invalid annotations metadata")?;
+    }
+    metadata.build = read_build_info(module)?;
+    Ok(metadata)
+}
+
+/// Reads the build information embedded in the compiled module, if it has some.
+pub fn read_build_info(module: &CompiledModule) -> Result<Option<BuildInfo>> {
+    match module.metadata_value(&Identifier::new(BUILD_INFO_KEY)?) {
+        Some(value) => Ok(Some(
+            serde_json::from_slice(value.as_bytes()).context("Invalid build metadata")?,
+        )),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Complexity metrics of functions.
+//!
+//! The metrics are read off the AST of each function with a body, to point reviewers at the
+//! functions that most deserve their attention:
+//!
+//! - the number of statements, counting those of nested blocks;
+//! - the cyclomatic complexity, one more than the number of decisions: each `if`, `while` and
+//!   `loop`, and each `&&` and `||`, which branch past their right operand;
+//! - the maximal nesting depth of blocks, the body of the function being at depth zero;
+//! - the number of accesses to global storage, through `exists`, `borrow_global`, `move_from`
+//!   and `move_to_sender`;
+//! - the number of generic instantiations, i.e. of calls, packs, unpacks and global accesses
+//!   given type arguments.
+
+use anyhow::Result;
+use move_ir_types::ast::{
+    BinOp, Block_, Builtin, Cmd_, Exp, Exp_, Function, FunctionBody, FunctionCall_, LValue_,
+    ModuleDefinition, Script, Statement,
+};
+use serde::{Deserialize, Serialize};
+use std::{cmp, fmt};
+
+/// A function with a body, as the reports on each function name it: the metrics, the borrows and
+/// the coalesced locals.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ReportedFunction {
+    /// The module of the function, or none for the `main` function of a script.
+    pub module: Option<String>,
+    pub name: String,
+}
+
+impl ReportedFunction {
+    /// The function `name` of the module `module`.
+    pub fn in_module(module: impl ToString, name: impl ToString) -> Self {
+        Self {
+            module: Some(module.to_string()),
+            name: name.to_string(),
+        }
+    }
+
+    /// The `main` function of a script.
+    pub fn main() -> Self {
+        Self {
+            module: None,
+            name: "main".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ReportedFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.module {
+            Some(module) => write!(f, "{}.{}", module, self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// The metrics of a function.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FunctionMetrics {
+    #[serde(flatten)]
+    pub function: ReportedFunction,
+    pub statements: usize,
+    pub cyclomatic_complexity: usize,
+    pub max_nesting_depth: usize,
+    pub global_accesses: usize,
+    pub generic_instantiations: usize,
+}
+
+/// The metrics of the functions of scripts and modules.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Metrics {
+    /// The functions with a body, in the order they are declared in.
+    pub functions: Vec<FunctionMetrics>,
+}
+
+impl Metrics {
+    /// The metrics of the functions of a module. Native functions have none.
+    pub fn for_module(module: &ModuleDefinition) -> Self {
+        let functions = module
+            .functions
+            .iter()
+            .filter_map(|(name, function)| {
+                function_metrics(ReportedFunction::in_module(&module.name, name), function)
+            })
+            .collect();
+        Self { functions }
+    }
+
+    /// The metrics of the `main` function of a script.
+    pub fn for_script(script: &Script) -> Self {
+        let functions = function_metrics(ReportedFunction::main(), &script.main)
+            .into_iter()
+            .collect();
+        Self { functions }
+    }
+
+    /// Adds the metrics of the functions of `other` after those of `self`.
+    pub fn extend(&mut self, other: Metrics) {
+        self.functions.extend(other.functions);
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+}
+
+fn function_metrics(reported: ReportedFunction, function: &Function) -> Option<FunctionMetrics> {
+    match &function.value.body {
+        FunctionBody::Move { code, .. } => {
+            let mut metrics = FunctionMetrics {
+                function: reported,
+                cyclomatic_complexity: 1,
+                ..FunctionMetrics::default()
+            };
+            metrics.block(code, 0);
+            Some(metrics)
+        }
+        FunctionBody::Native => None,
+    }
+}
+
+impl FunctionMetrics {
+    fn block(&mut self, block: &Block_, depth: usize) {
+        self.max_nesting_depth = cmp::max(self.max_nesting_depth, depth);
+        for statement in &block.stmts {
+            match statement {
+                Statement::CommandStatement(cmd) => {
+                    self.statements += 1;
+                    self.cmd(&cmd.value);
+                }
+                Statement::IfElseStatement(if_else) => {
+                    self.statements += 1;
+                    self.cyclomatic_complexity += 1;
+                    self.exp(&if_else.cond);
+                    self.block(&if_else.if_block.value, depth + 1);
+                    if let Some(else_block) = &if_else.else_block {
+                        self.block(&else_block.value, depth + 1);
+                    }
+                }
+                Statement::WhileStatement(while_) => {
+                    self.statements += 1;
+                    self.cyclomatic_complexity += 1;
+                    self.exp(&while_.cond);
+                    self.block(&while_.block.value, depth + 1);
+                }
+                Statement::LoopStatement(loop_) => {
+                    self.statements += 1;
+                    self.cyclomatic_complexity += 1;
+                    for lvalue in &loop_.results {
+                        if let LValue_::Mutate(e) = &lvalue.value {
+                            self.exp(e);
+                        }
+                    }
+                    self.block(&loop_.block.value, depth + 1);
+                }
+                Statement::EmptyStatement => (),
+            }
+        }
+    }
+
+    fn cmd(&mut self, cmd: &Cmd_) {
+        match cmd {
+            Cmd_::Assign(lvalues, e) => {
+                for lvalue in lvalues {
+                    if let LValue_::Mutate(e) = &lvalue.value {
+                        self.exp(e);
+                    }
+                }
+                self.exp(e);
+            }
+            Cmd_::Unpack(_, type_actuals, _, e) => {
+                self.instantiation(type_actuals);
+                self.exp(e);
+            }
+            Cmd_::Return(e) | Cmd_::Exp(e) | Cmd_::Abort(Some(e)) | Cmd_::Break(Some(e)) => {
+                self.exp(e)
+            }
+            Cmd_::Emit(handle, e) => {
+                self.exp(handle);
+                self.exp(e);
+            }
+            Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+        }
+    }
+
+    fn exp(&mut self, e: &Exp) {
+        match &e.value {
+            Exp_::Dereference(e) | Exp_::UnaryExp(_, e) | Exp_::Borrow { exp: e, .. } => {
+                self.exp(e)
+            }
+            Exp_::BinopExp(lhs, op, rhs) => {
+                if let BinOp::And | BinOp::Or = op {
+                    self.cyclomatic_complexity += 1;
+                }
+                self.exp(lhs);
+                self.exp(rhs);
+            }
+            Exp_::Pack(_, type_actuals, fields) => {
+                self.instantiation(type_actuals);
+                for (_, e) in fields {
+                    self.exp(e);
+                }
+            }
+            Exp_::FunctionCall(call, args) => {
+                match &call.value {
+                    FunctionCall_::Builtin(Builtin::Exists(_, type_actuals))
+                    | FunctionCall_::Builtin(Builtin::BorrowGlobal(_, _, type_actuals))
+                    | FunctionCall_::Builtin(Builtin::MoveFrom(_, type_actuals))
+                    | FunctionCall_::Builtin(Builtin::MoveToSender(_, type_actuals)) => {
+                        self.global_accesses += 1;
+                        self.instantiation(type_actuals);
+                    }
+                    FunctionCall_::ModuleFunctionCall { type_actuals, .. } => {
+                        self.instantiation(type_actuals)
+                    }
+                    FunctionCall_::Builtin(_) => (),
+                }
+                self.exp(args);
+            }
+            Exp_::ExprList(exps) => {
+                for e in exps {
+                    self.exp(e);
+                }
+            }
+            Exp_::Value(_)
+            | Exp_::Move(_)
+            | Exp_::Copy(_)
+            | Exp_::Var(_)
+            | Exp_::BorrowLocal(_, _)
+            | Exp_::Placeholder(_) => (),
+        }
+    }
+
+    fn instantiation<T>(&mut self, type_actuals: &[T]) {
+        if !type_actuals.is_empty() {
+            self.generic_instantiations += 1;
+        }
+    }
+}
@@ -0,0 +1,295 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Inference of moves and copies of locals.
+//!
+//! A local can be used bare, as in `x + 1`, instead of with `move(x)` or `copy(x)`. This pass
+//! decides which of the two each bare use is, and rewrites it accordingly. As for dead stores, a
+//! backward liveness analysis finds whether the local may be read again on some path after the
+//! use: the use is a copy if it may, and a move otherwise. A local whose type cannot be copied, a
+//! resource or a type parameter that may be one, is always moved. A local that is borrowed
+//! anywhere in the function is copied whenever its type allows it, since the reference may still
+//! be in use. Explicit moves and copies are left as written.
+//!
+//! Only the current compilation unit is known, and structs of other modules are assumed to be
+//! copyable. A local holding a resource of another module is then still moved at its last use,
+//! unless it is borrowed, in which case `move(x)` has to be written.
+
+use crate::{
+    dataflow::{backward, BackwardTransfer, Live},
+    locals::{cmd_assigned, cmd_exps, lvalues_assigned},
+};
+use move_ir_types::ast::{
+    visit_exp, visit_exp_mut, Block_, Cmd, Cmd_, Exp, Exp_, FunctionBody, Function_, Kind, LValue,
+    LValue_, Loc, ModuleDefinition, ModuleName, Program, Script, Statement, StructName, Type,
+    TypeVar, Var_,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// A bare use of a local that was made a move.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InferredMove {
+    /// The location of the use.
+    pub loc: Loc,
+    /// The local moved.
+    pub var: Var_,
+    /// Why it is moved rather than copied.
+    pub reason: MoveReason,
+}
+
+/// Why a bare use of a local is a move.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MoveReason {
+    /// The local is not read again after the use.
+    LastUse,
+    /// The type of the local cannot be copied.
+    NotCopyable,
+}
+
+impl fmt::Display for InferredMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.reason {
+            MoveReason::LastUse => write!(f, "`{}` moved, as it is not used afterwards", self.var),
+            MoveReason::NotCopyable => {
+                write!(f, "`{}` moved, as its type cannot be copied", self.var)
+            }
+        }
+    }
+}
+
+/// Decides between a move and a copy for the bare uses of locals in every module and then in the
+/// script of a program.
+pub fn resolve_program(program: &mut Program) -> Vec<InferredMove> {
+    let mut moves = vec![];
+    for module in &mut program.modules {
+        moves.extend(resolve_module(module));
+    }
+    moves.extend(resolve_script(&mut program.script));
+    moves
+}
+
+/// Decides between a move and a copy for the bare uses of locals in the functions of a module,
+/// returning the moves in source order.
+pub fn resolve_module(module: &mut ModuleDefinition) -> Vec<InferredMove> {
+    let resources: BTreeSet<StructName> = module
+        .structs
+        .iter()
+        .filter(|s| s.value.is_nominal_resource)
+        .map(|s| s.value.name.clone())
+        .collect();
+    let mut moves = vec![];
+    for (_, function) in &mut module.functions {
+        moves.extend(resolve_function(&mut function.value, &resources));
+    }
+    moves
+}
+
+/// Decides between a move and a copy for the bare uses of locals in the `main` function of a
+/// script, returning the moves in source order.
+pub fn resolve_script(script: &mut Script) -> Vec<InferredMove> {
+    resolve_function(&mut script.main.value, &BTreeSet::new())
+}
+
+fn resolve_function(
+    function: &mut Function_,
+    resources: &BTreeSet<StructName>,
+) -> Vec<InferredMove> {
+    let (locals, code) = match &mut function.body {
+        FunctionBody::Move { locals, code } => (locals, code),
+        FunctionBody::Native => return vec![],
+    };
+    let mut analysis = Analysis::default();
+    backward(&mut analysis, code, Live::new());
+
+    let kinds = Kinds {
+        resources,
+        type_formals: &function.signature.type_formals,
+    };
+    let types: BTreeMap<&Var_, &Type> = function
+        .signature
+        .formals
+        .iter()
+        .chain(locals.iter())
+        .map(|(var, ty)| (&var.value, ty))
+        .collect();
+    let mut moves = vec![];
+    let mut decisions = BTreeMap::new();
+    for (loc, (var, live_after)) in analysis.uses {
+        // Unbound locals are left to the compiler to report.
+        let copyable = types.get(&var).map_or(true, |ty| kinds.is_copyable(ty));
+        let reason = if !copyable {
+            Some(MoveReason::NotCopyable)
+        } else if !live_after && !analysis.borrowed.contains(&var) {
+            Some(MoveReason::LastUse)
+        } else {
+            None
+        };
+        decisions.insert(loc, reason.is_some());
+        if let Some(reason) = reason {
+            moves.push(InferredMove { loc, var, reason });
+        }
+    }
+    resolve_block(code, &decisions);
+    moves.sort_by_key(|inferred| inferred.loc.start());
+    moves
+}
+
+/// The kinds of the types a function can name.
+struct Kinds<'a> {
+    resources: &'a BTreeSet<StructName>,
+    type_formals: &'a [(TypeVar, Kind)],
+}
+
+impl<'a> Kinds<'a> {
+    fn is_copyable(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Address
+            | Type::U8
+            | Type::U64
+            | Type::U128
+            | Type::Bool
+            | Type::ByteArray
+            | Type::Reference(_, _) => true,
+            Type::TypeParameter(type_var) => self
+                .type_formals
+                .iter()
+                .any(|(formal, kind)| &formal.value == type_var && *kind == Kind::Unrestricted),
+            Type::Struct(ident, type_actuals) => {
+                let is_resource = ident.module.as_inner() == ModuleName::self_name()
+                    && self.resources.contains(&ident.name);
+                !is_resource && type_actuals.iter().all(|ty| self.is_copyable(ty))
+            }
+            Type::Tuple(tys) => tys.iter().all(|ty| self.is_copyable(ty)),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Analysis {
+    borrowed: BTreeSet<Var_>,
+    /// The bare uses of locals, with whether the local is live after the use. Code in loops is
+    /// visited several times; the last visit, made with the final live locals of the loop,
+    /// decides.
+    uses: BTreeMap<Loc, (Var_, bool)>,
+}
+
+impl BackwardTransfer for Analysis {
+    type State = Live;
+
+    fn cmd(&mut self, cmd: &Cmd, live: &mut Live) {
+        for var in cmd_assigned(&cmd.value) {
+            live.remove(var);
+        }
+        for e in cmd_exps(&cmd.value).into_iter().rev() {
+            self.reads(e, live);
+        }
+    }
+
+    fn cond(&mut self, cond: &Exp, live: &mut Live) {
+        self.reads(cond, live);
+    }
+
+    fn loop_results(&mut self, results: &[LValue], live: &mut Live) {
+        for var in lvalues_assigned(results) {
+            live.remove(var);
+        }
+        for lvalue in results.iter().rev() {
+            if let LValue_::Mutate(e) = &lvalue.value {
+                self.reads(e, live);
+            }
+        }
+    }
+}
+
+impl Analysis {
+    // The uses are visited in the reverse of their evaluation order, so that a local used twice
+    // in an expression is only moved by its last use.
+    fn reads(&mut self, e: &Exp, live: &mut Live) {
+        let mut uses = vec![];
+        visit_exp(e, |e| match &e.value {
+            Exp_::Move(var) | Exp_::Copy(var) | Exp_::Var(var) | Exp_::BorrowLocal(_, var) => {
+                uses.push((e, var))
+            }
+            _ => (),
+        });
+        for (e, var) in uses.into_iter().rev() {
+            match &e.value {
+                Exp_::Var(_) => {
+                    let live_after = live.contains(&var.value);
+                    self.uses.insert(e.span, (var.value.clone(), live_after));
+                }
+                Exp_::BorrowLocal(_, _) => {
+                    self.borrowed.insert(var.value.clone());
+                }
+                _ => (),
+            }
+            live.insert(var.value.clone());
+        }
+    }
+}
+
+/// Replaces each bare use of a local with a move if `decisions` says so, and a copy otherwise.
+fn resolve_block(block: &mut Block_, decisions: &BTreeMap<Loc, bool>) {
+    let mut resolve = |e: &mut Exp| {
+        visit_exp_mut(e, |e| {
+            if let Exp_::Var(var) = &e.value {
+                let var = var.clone();
+                e.value = if decisions.get(&e.span) == Some(&true) {
+                    Exp_::Move(var)
+                } else {
+                    Exp_::Copy(var)
+                };
+            }
+        })
+    };
+    let mut blocks = vec![block];
+    while let Some(block) = blocks.pop() {
+        for statement in &mut block.stmts {
+            match statement {
+                Statement::CommandStatement(cmd) => match &mut cmd.value {
+                    Cmd_::Assign(lvalues, e) => {
+                        lvalues_exps(lvalues, &mut resolve);
+                        resolve(e);
+                    }
+                    Cmd_::Unpack(_, _, _, e)
+                    | Cmd_::Return(e)
+                    | Cmd_::Exp(e)
+                    | Cmd_::Abort(Some(e))
+                    | Cmd_::Break(Some(e)) => resolve(e),
+                    Cmd_::Emit(handle, e) => {
+                        resolve(handle);
+                        resolve(e);
+                    }
+                    Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+                },
+                Statement::IfElseStatement(if_else) => {
+                    resolve(&mut if_else.cond);
+                    blocks.push(&mut if_else.if_block.value);
+                    if let Some(else_block) = &mut if_else.else_block {
+                        blocks.push(&mut else_block.value);
+                    }
+                }
+                Statement::WhileStatement(while_) => {
+                    resolve(&mut while_.cond);
+                    blocks.push(&mut while_.block.value);
+                }
+                Statement::LoopStatement(loop_) => {
+                    lvalues_exps(&mut loop_.results, &mut resolve);
+                    blocks.push(&mut loop_.block.value);
+                }
+                Statement::EmptyStatement => (),
+            }
+        }
+    }
+}
+
+fn lvalues_exps(lvalues: &mut [LValue], f: &mut dyn FnMut(&mut Exp)) {
+    for lvalue in lvalues {
+        if let LValue_::Mutate(e) = &mut lvalue.value {
+            f(e);
+        }
+    }
+}
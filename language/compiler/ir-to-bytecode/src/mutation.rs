@@ -0,0 +1,288 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Mutation of scripts and modules, to evaluate test suites.
+//!
+//! A mutant is a copy of the source with one small change that a good test suite notices: a
+//! binary operator replaced with a related one (`+` with `-`, `<` with `<=`, `&&` with `||`...),
+//! the condition of an `if`, `while` or `assert` negated, or an integer literal moved by one. A
+//! mutant that passes the tests points at behavior they do not check.
+//!
+//! Mutants are printed from the mutated AST, so they are formatted canonically and have no
+//! comments or specifications.
+
+use crate::{
+    formatter::{format_module, format_script, FormatOptions},
+    parser::parse_script_or_module,
+};
+use anyhow::Result;
+use move_ir_types::ast::{
+    BinOp, Block_, Cmd_, CopyableVal_, Exp, Exp_, FunctionBody, LValue_, Loc, ScriptOrModule,
+    Spanned, Statement, UnaryOp,
+};
+
+/// A kind of mutation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MutationKind {
+    /// A binary operator replaced with a related one.
+    SwapOperator,
+    /// A condition negated.
+    NegateCondition,
+    /// An integer literal incremented or decremented.
+    ShiftLiteral,
+}
+
+/// A mutated version of a source.
+#[derive(Clone, Debug)]
+pub struct Mutant {
+    pub kind: MutationKind,
+    /// The location of the mutated code in the original source.
+    pub loc: Loc,
+    /// What was changed, e.g. "replace `+` with `-`".
+    pub description: String,
+    /// The source of the mutant.
+    pub source: String,
+}
+
+/// Parses a script or module and returns all its mutants, in the order of the code they mutate.
+pub fn mutants(source: &str) -> Result<Vec<Mutant>> {
+    let original = parse_script_or_module(source)?;
+    let options = FormatOptions::default();
+    let mut mutants = vec![];
+    loop {
+        let mut mutated = original.clone();
+        let mut visitor = Visitor {
+            target: mutants.len(),
+            seen: 0,
+            applied: None,
+        };
+        match &mut mutated {
+            ScriptOrModule::Script(script) => visitor.function_body(&mut script.main.value.body),
+            ScriptOrModule::Module(module) => {
+                for (_, function) in &mut module.functions {
+                    visitor.function_body(&mut function.value.body);
+                }
+            }
+        }
+        let (kind, loc, description) = match visitor.applied {
+            Some(applied) => applied,
+            None => break,
+        };
+        let source = match &mutated {
+            ScriptOrModule::Script(script) => format_script(script, &options),
+            ScriptOrModule::Module(module) => format_module(module, &options),
+        };
+        mutants.push(Mutant {
+            kind,
+            loc,
+            description,
+            source,
+        });
+    }
+    Ok(mutants)
+}
+
+/// Returns the operator a binary operator is replaced with.
+fn swapped(op: &BinOp) -> BinOp {
+    match op {
+        BinOp::Add => BinOp::Sub,
+        BinOp::Sub => BinOp::Add,
+        BinOp::Mul => BinOp::Div,
+        BinOp::Div => BinOp::Mul,
+        BinOp::Mod => BinOp::Div,
+        BinOp::BitOr => BinOp::BitAnd,
+        BinOp::BitAnd => BinOp::BitOr,
+        BinOp::Xor => BinOp::BitOr,
+        BinOp::Shl => BinOp::Shr,
+        BinOp::Shr => BinOp::Shl,
+        BinOp::And => BinOp::Or,
+        BinOp::Or => BinOp::And,
+        BinOp::Eq => BinOp::Neq,
+        BinOp::Neq => BinOp::Eq,
+        BinOp::Lt => BinOp::Le,
+        BinOp::Le => BinOp::Lt,
+        BinOp::Gt => BinOp::Ge,
+        BinOp::Ge => BinOp::Gt,
+    }
+}
+
+/// Returns the literals an integer literal is replaced with: the next and the previous values,
+/// when they are in range.
+fn shifted(value: &CopyableVal_) -> Vec<CopyableVal_> {
+    match value {
+        CopyableVal_::U8(n) => vec![n.checked_add(1), n.checked_sub(1)]
+            .into_iter()
+            .flatten()
+            .map(CopyableVal_::U8)
+            .collect(),
+        CopyableVal_::U64(n) => vec![n.checked_add(1), n.checked_sub(1)]
+            .into_iter()
+            .flatten()
+            .map(CopyableVal_::U64)
+            .collect(),
+        CopyableVal_::U128(n) => vec![n.checked_add(1), n.checked_sub(1)]
+            .into_iter()
+            .flatten()
+            .map(CopyableVal_::U128)
+            .collect(),
+        CopyableVal_::Address(_) | CopyableVal_::Bool(_) | CopyableVal_::ByteArray(_) => vec![],
+    }
+}
+
+fn literal_text(value: &CopyableVal_) -> String {
+    match value {
+        CopyableVal_::U8(n) => format!("{}u8", n),
+        CopyableVal_::U64(n) => n.to_string(),
+        CopyableVal_::U128(n) => format!("{}u128", n),
+        CopyableVal_::Address(_) | CopyableVal_::Bool(_) | CopyableVal_::ByteArray(_) => {
+            unreachable!("only integer literals are shifted")
+        }
+    }
+}
+
+/// Walks the code in source order, counting the mutations it could make, and makes the one
+/// numbered `target`.
+struct Visitor {
+    target: usize,
+    seen: usize,
+    applied: Option<(MutationKind, Loc, String)>,
+}
+
+impl Visitor {
+    /// Returns whether the next possible mutation is the one to make.
+    fn next_is_target(&mut self) -> bool {
+        let is_target = self.applied.is_none() && self.seen == self.target;
+        self.seen += 1;
+        is_target
+    }
+
+    fn apply(&mut self, kind: MutationKind, loc: Loc, description: String) {
+        self.applied = Some((kind, loc, description));
+    }
+
+    fn function_body(&mut self, body: &mut FunctionBody) {
+        if let FunctionBody::Move { code, .. } = body {
+            self.block(code)
+        }
+    }
+
+    fn block(&mut self, block: &mut Block_) {
+        for statement in &mut block.stmts {
+            match statement {
+                Statement::CommandStatement(cmd) => self.cmd(&mut cmd.value),
+                Statement::IfElseStatement(if_else) => {
+                    self.condition(&mut if_else.cond);
+                    self.block(&mut if_else.if_block.value);
+                    if let Some(else_block) = &mut if_else.else_block {
+                        self.block(&mut else_block.value);
+                    }
+                }
+                Statement::WhileStatement(while_) => {
+                    self.condition(&mut while_.cond);
+                    self.block(&mut while_.block.value);
+                }
+                Statement::LoopStatement(loop_) => {
+                    for lvalue in &mut loop_.results {
+                        if let LValue_::Mutate(e) = &mut lvalue.value {
+                            self.exp(e);
+                        }
+                    }
+                    self.block(&mut loop_.block.value);
+                }
+                Statement::EmptyStatement => (),
+            }
+        }
+    }
+
+    /// Visits the condition of an `if`, `while` or `assert`, which can be negated. The negation
+    /// keeps the location of the condition, so that a negated `assert` is still printed as one.
+    fn condition(&mut self, cond: &mut Exp) {
+        if self.next_is_target() {
+            let span = cond.span;
+            let inner = std::mem::replace(cond, Spanned::no_loc(Exp_::ExprList(vec![])));
+            *cond = Spanned {
+                span,
+                value: Exp_::UnaryExp(UnaryOp::Not, Box::new(inner)),
+            };
+            self.apply(
+                MutationKind::NegateCondition,
+                span,
+                "negate condition".to_string(),
+            );
+            return;
+        }
+        self.exp(cond);
+    }
+
+    fn cmd(&mut self, cmd: &mut Cmd_) {
+        match cmd {
+            Cmd_::Assign(lvalues, e) => {
+                for lvalue in lvalues {
+                    if let LValue_::Mutate(e) = &mut lvalue.value {
+                        self.exp(e);
+                    }
+                }
+                self.exp(e);
+            }
+            Cmd_::Unpack(_, _, _, e)
+            | Cmd_::Return(e)
+            | Cmd_::Exp(e)
+            | Cmd_::Abort(Some(e))
+            | Cmd_::Break(Some(e)) => self.exp(e),
+            Cmd_::Emit(handle, e) => {
+                self.exp(handle);
+                self.exp(e);
+            }
+            Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+        }
+    }
+
+    fn exp(&mut self, e: &mut Exp) {
+        let span = e.span;
+        match &mut e.value {
+            Exp_::Dereference(e)
+            | Exp_::UnaryExp(_, e)
+            | Exp_::Borrow { exp: e, .. }
+            | Exp_::FunctionCall(_, e) => self.exp(e),
+            Exp_::BinopExp(lhs, op, rhs) => {
+                self.exp(lhs);
+                if self.next_is_target() {
+                    let new_op = swapped(op);
+                    let description = format!("replace `{}` with `{}`", op, new_op);
+                    *op = new_op;
+                    self.apply(MutationKind::SwapOperator, span, description);
+                }
+                self.exp(rhs);
+            }
+            Exp_::Pack(_, _, fields) => {
+                for (_, e) in fields {
+                    self.exp(e);
+                }
+            }
+            Exp_::ExprList(exps) => {
+                for e in exps {
+                    self.exp(e);
+                }
+            }
+            Exp_::Value(value) => {
+                for new_value in shifted(&value.value) {
+                    if self.next_is_target() {
+                        let description = format!(
+                            "replace {} with {}",
+                            literal_text(&value.value),
+                            literal_text(&new_value)
+                        );
+                        value.value = new_value;
+                        self.apply(MutationKind::ShiftLiteral, span, description);
+                        break;
+                    }
+                }
+            }
+            Exp_::Move(_)
+            | Exp_::Copy(_)
+            | Exp_::Var(_)
+            | Exp_::BorrowLocal(_, _)
+            | Exp_::Placeholder(_) => (),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checking the native functions a module declares against the ones the VM implements.
+//!
+//! A native function is bound by the VM to the function of its native table with the same module
+//! address, module name and function name. A declaration that does not match an implementation
+//! compiles, but only fails to verify when the module is loaded, and the verifier does not tell
+//! what is wrong with it. These checks report such declarations where they are in the source
+//! instead: natives the VM does not implement, and natives declared with a different number of
+//! arguments or of return values, or with type parameters of different kinds.
+
+use crate::errors::NativeError;
+use libra_types::{
+    account_address::AccountAddress, account_config, identifier::IdentStr,
+    language_storage::ModuleId,
+};
+use move_ir_types::ast::{self, FunctionBody, ModuleDefinition};
+use vm::file_format::Kind;
+use vm_runtime_types::native_functions::dispatch::resolve_native_function;
+
+/// Checks the native functions of `module`, for a module published at `address`.
+pub fn check_module_natives(
+    module: &ModuleDefinition,
+    address: AccountAddress,
+) -> Vec<NativeError> {
+    let mut errors = vec![];
+    let module_name = module.name.as_inner();
+    let module_id = ModuleId::new(address, module_name.to_owned());
+    for (name, function) in &module.functions {
+        if function.value.body != FunctionBody::Native {
+            continue;
+        }
+        let loc = function.span;
+        let signature = &function.value.signature;
+        let expected = match resolve_native_function(&module_id, name.as_inner()) {
+            Some(native) => &native.expected_signature,
+            None => {
+                errors.push(NativeError {
+                    loc,
+                    message: missing_native(address, module_name, name.as_inner()),
+                });
+                continue;
+            }
+        };
+        if signature.formals.len() != expected.arg_types.len() {
+            errors.push(NativeError {
+                loc,
+                message: format!(
+                    "native function `{}` takes {} arguments, but is declared with {}",
+                    name,
+                    expected.arg_types.len(),
+                    signature.formals.len()
+                ),
+            });
+        }
+        if signature.return_type.len() != expected.return_types.len() {
+            errors.push(NativeError {
+                loc,
+                message: format!(
+                    "native function `{}` returns {} values, but is declared to return {}",
+                    name,
+                    expected.return_types.len(),
+                    signature.return_type.len()
+                ),
+            });
+        }
+        if signature.type_formals.len() != expected.type_formals.len() {
+            errors.push(NativeError {
+                loc,
+                message: format!(
+                    "native function `{}` has {} type parameters, but is declared with {}",
+                    name,
+                    expected.type_formals.len(),
+                    signature.type_formals.len()
+                ),
+            });
+            continue;
+        }
+        for ((type_var, declared), expected) in
+            signature.type_formals.iter().zip(&expected.type_formals)
+        {
+            let expected = ast_kind(*expected);
+            if *declared != expected {
+                errors.push(NativeError {
+                    loc,
+                    message: format!(
+                        "type parameter `{}` of native function `{}` has kind `{}`, but is \
+                         declared with kind `{}`",
+                        type_var.value, name, expected, declared
+                    ),
+                });
+            }
+        }
+    }
+    errors
+}
+
+// The message for a native the VM does not implement, pointing at the standard library when the
+// VM implements a native of the same name there.
+fn missing_native(address: AccountAddress, module: &IdentStr, name: &IdentStr) -> String {
+    let message = format!(
+        "the VM implements no native function `{}.{}` for modules published at {}",
+        module, name, address
+    );
+    let core_address = account_config::core_code_address();
+    let core_id = ModuleId::new(core_address, module.to_owned());
+    if address != core_address && resolve_native_function(&core_id, name).is_some() {
+        format!(
+            "{}; it implements the one published at {}",
+            message, core_address
+        )
+    } else {
+        message
+    }
+}
+
+fn ast_kind(kind: Kind) -> ast::Kind {
+    match kind {
+        Kind::All => ast::Kind::All,
+        Kind::Resource => ast::Kind::Resource,
+        Kind::Unrestricted => ast::Kind::Unrestricted,
+    }
+}
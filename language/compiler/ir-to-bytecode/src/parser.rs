@@ -1,10 +1,11 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::errors::{Fix, SyntaxError};
 use anyhow::{bail, Result};
 use codespan::{ByteIndex, CodeMap, Span};
 use codespan_reporting::{emit, termcolor::Buffer, Diagnostic, Label, Severity};
-use ir_to_bytecode_syntax::syntax::{self, ParseError};
+use ir_to_bytecode_syntax::syntax::{self, ParseError, SourceHeader};
 use libra_types::account_address::AccountAddress;
 use move_ir_types::ast;
 use std::{
@@ -12,6 +13,10 @@ use std::{
     hash::{Hash, Hasher},
 };
 
+pub use ir_to_bytecode_syntax::syntax::{
+    ParserOptions, DEFAULT_MAX_EXP_DEPTH, FUTURE_KEYWORDS, LATEST_LANGUAGE_VERSION,
+};
+
 /// Determine if a character is an allowed eye-visible (printable) character.
 ///
 /// The only allowed printable characters are the printable ascii characters (SPACE through ~) and
@@ -53,7 +58,7 @@ fn verify_string(string: &str) -> Result<()> {
         })
 }
 
-fn strip_comments(source: &str) -> String {
+pub(crate) fn strip_comments(source: &str) -> String {
     const SLASH: char = '/';
     const SPACE: char = ' ';
 
@@ -86,6 +91,124 @@ pub fn parse_script_or_module(s: &str) -> Result<ast::ScriptOrModule> {
     syntax::parse_script_or_module_string(stripped_string).or_else(|e| handle_error(e, s))
 }
 
+/// Given the raw input of a file, creates a `ScriptOrModule` enum without printing anything.
+/// Fails with a `SyntaxError` located in the source if the text cannot be parsed.
+pub fn try_parse_script_or_module(
+    s: &str,
+) -> std::result::Result<ast::ScriptOrModule, SyntaxError> {
+    check_permitted_chars(s)?;
+    syntax::parse_script_or_module_string(&strip_comments(s)).map_err(to_syntax_error)
+}
+
+/// Given the raw input of a file, creates a single `ModuleDefinition` struct, parsing it with
+/// `options`, without printing anything.
+/// Fails with a `SyntaxError` located in the source if the text cannot be parsed.
+pub fn try_parse_module_with_options(
+    s: &str,
+    options: ParserOptions,
+) -> std::result::Result<ast::ModuleDefinition, SyntaxError> {
+    check_permitted_chars(s)?;
+    syntax::parse_module_string_with_options(&strip_comments(s), options).map_err(to_syntax_error)
+}
+
+/// Parses the header of a source with its comments stripped, for the incremental parser.
+pub(crate) fn try_parse_source_header(
+    stripped: &str,
+    options: ParserOptions,
+) -> std::result::Result<SourceHeader, SyntaxError> {
+    syntax::parse_source_header(stripped, options).map_err(to_syntax_error)
+}
+
+/// Parses a part of a source following `header`, for the incremental parser. Locations are offsets
+/// in `s`, the raw text of the part.
+pub(crate) fn try_parse_source_part(
+    s: &str,
+    header: &SourceHeader,
+    last: bool,
+) -> std::result::Result<(Vec<ast::ModuleDefinition>, Option<ast::Script>), SyntaxError> {
+    check_permitted_chars(s)?;
+    syntax::parse_source_part(&strip_comments(s), header, last).map_err(to_syntax_error)
+}
+
+fn check_permitted_chars(s: &str) -> std::result::Result<(), SyntaxError> {
+    match s.char_indices().find(|(_, c)| !is_permitted_char(*c)) {
+        Some((pos, chr)) => Err(SyntaxError {
+            loc: Some(Span::new(
+                ByteIndex(pos as u32),
+                ByteIndex((pos + chr.len_utf8()) as u32),
+            )),
+            message: format!("Invalid character {:?}", chr),
+            fix: None,
+        }),
+        None => Ok(()),
+    }
+}
+
+fn to_syntax_error(e: ParseError<usize, anyhow::Error>) -> SyntaxError {
+    match e {
+        ParseError::InvalidToken { location } => SyntaxError {
+            loc: Some(Span::new(
+                ByteIndex(location as u32),
+                ByteIndex(location as u32),
+            )),
+            message: "Invalid token".to_string(),
+            fix: None,
+        },
+        ParseError::User { error } => SyntaxError {
+            loc: None,
+            message: error.to_string(),
+            fix: None,
+        },
+        ParseError::InvalidName { name, loc } => SyntaxError {
+            loc: Some(loc),
+            message: format!("Invalid name {}", name),
+            fix: None,
+        },
+        ParseError::Duplicate {
+            kind, name, second, ..
+        } => SyntaxError {
+            loc: Some(second),
+            message: format!("Duplicate {} {}", kind, name),
+            fix: None,
+        },
+        ParseError::ReservedName { name, loc } => SyntaxError {
+            loc: Some(loc),
+            message: format!("Reserved name {}", name),
+            fix: None,
+        },
+        ParseError::Deprecated {
+            construct,
+            advice,
+            loc,
+        } => SyntaxError {
+            loc: Some(loc),
+            message: format!("Deprecated {}: {}", construct, advice),
+            fix: None,
+        },
+        ParseError::UnsupportedLanguageVersion { version, loc } => SyntaxError {
+            loc: Some(loc),
+            message: format!("Unsupported language version {}", version),
+            fix: None,
+        },
+        ParseError::InvalidByteArray { message, loc }
+        | ParseError::InvalidAttribute { message, loc } => SyntaxError {
+            loc: Some(loc),
+            message,
+            fix: None,
+        },
+        ParseError::ChainedComparison { loc, replacement } => SyntaxError {
+            loc: Some(loc),
+            message: format!("Chained comparison, did you mean `{}`?", replacement),
+            fix: Some(Fix { loc, replacement }),
+        },
+        ParseError::TooDeeplyNested { loc, max_depth } => SyntaxError {
+            loc: Some(loc),
+            message: format!("Expression nested deeper than {}", max_depth),
+            fix: None,
+        },
+    }
+}
+
 /// Given the raw input of a file, creates a `Program` struct
 /// Fails with `Err(_)` if the text cannot be parsed
 pub fn parse_program(program_str: &str) -> Result<ast::Program> {
@@ -93,6 +216,39 @@ pub fn parse_program(program_str: &str) -> Result<ast::Program> {
     syntax::parse_program_string(stripped_string).or_else(|e| handle_error(e, stripped_string))
 }
 
+/// Given the raw input of a file, creates a `Program` struct, parsing it with `options`
+/// Fails with `Err(_)` if the text cannot be parsed
+pub fn parse_program_with_options(
+    program_str: &str,
+    options: ParserOptions,
+) -> Result<ast::Program> {
+    let stripped_string = &strip_comments_and_verify(program_str)?;
+    syntax::parse_program_string_with_options(stripped_string, options)
+        .or_else(|e| handle_error(e, stripped_string))
+}
+
+/// Given the raw input of a file, passes each module of the program to `on_module` as soon as it
+/// is parsed, and returns the script of the program. This lets modules be compiled while the rest
+/// of the program is parsed, without holding the whole `Program` in memory.
+/// Fails with `Err(_)` if the text cannot be parsed, or with the error of `on_module` if it fails
+pub fn parse_program_streaming<F>(program_str: &str, mut on_module: F) -> Result<ast::Script>
+where
+    F: FnMut(ast::ModuleDefinition) -> Result<()>,
+{
+    let stripped_string = &strip_comments_and_verify(program_str)?;
+    let mut module_failed = false;
+    syntax::parse_program_string_streaming(stripped_string, |module| {
+        on_module(module).map_err(|err| {
+            module_failed = true;
+            err
+        })
+    })
+    .or_else(|e| match e {
+        ParseError::User { error } if module_failed => Err(error),
+        e => handle_error(e, stripped_string),
+    })
+}
+
 /// Given the raw input of a file, creates a `Script` struct
 /// Fails with `Err(_)` if the text cannot be parsed
 pub fn parse_script(script_str: &str) -> Result<ast::Script> {
@@ -107,6 +263,18 @@ pub fn parse_module(modules_str: &str) -> Result<ast::ModuleDefinition> {
     syntax::parse_module_string(stripped_string).or_else(|e| handle_error(e, stripped_string))
 }
 
+/// Given the raw input of a file, creates a single `ModuleDefinition` struct, parsing it with
+/// `options`
+/// Fails with `Err(_)` if the text cannot be parsed
+pub fn parse_module_with_options(
+    modules_str: &str,
+    options: ParserOptions,
+) -> Result<ast::ModuleDefinition> {
+    let stripped_string = &strip_comments_and_verify(modules_str)?;
+    syntax::parse_module_string_with_options(stripped_string, options)
+        .or_else(|e| handle_error(e, stripped_string))
+}
+
 /// Given the raw input of a file, creates a single `Cmd_` struct
 /// Fails with `Err(_)` if the text cannot be parsed
 pub fn parse_cmd_(cmd_str: &str, _sender_address: AccountAddress) -> Result<ast::Cmd_> {
@@ -132,6 +300,83 @@ fn handle_error<'input, T>(
             emit(&mut buffer, &code, &error).unwrap();
             std::str::from_utf8(buffer.as_slice()).unwrap().to_string()
         }
+        ParseError::InvalidName { name, loc } => {
+            let error = Diagnostic::new(Severity::Error, format!("Invalid name {}", name))
+                .with_label(Label::new_primary(*loc));
+            let mut buffer = Buffer::no_color();
+            emit(&mut buffer, &code, &error).unwrap();
+            std::str::from_utf8(buffer.as_slice()).unwrap().to_string()
+        }
+        ParseError::Duplicate {
+            kind,
+            name,
+            first,
+            second,
+        } => {
+            let error = Diagnostic::new(Severity::Error, format!("Duplicate {} {}", kind, name))
+                .with_label(Label::new_primary(*second).with_message("Redeclared here"))
+                .with_label(Label::new_secondary(*first).with_message("Previously declared here"));
+            let mut buffer = Buffer::no_color();
+            emit(&mut buffer, &code, &error).unwrap();
+            std::str::from_utf8(buffer.as_slice()).unwrap().to_string()
+        }
+        ParseError::ReservedName { name, loc } => {
+            let error = Diagnostic::new(Severity::Error, format!("Reserved name {}", name))
+                .with_label(Label::new_primary(*loc));
+            let mut buffer = Buffer::no_color();
+            emit(&mut buffer, &code, &error).unwrap();
+            std::str::from_utf8(buffer.as_slice()).unwrap().to_string()
+        }
+        ParseError::Deprecated {
+            construct,
+            advice,
+            loc,
+        } => {
+            let error = Diagnostic::new(Severity::Error, format!("Deprecated {}", construct))
+                .with_label(Label::new_primary(*loc).with_message(*advice));
+            let mut buffer = Buffer::no_color();
+            emit(&mut buffer, &code, &error).unwrap();
+            std::str::from_utf8(buffer.as_slice()).unwrap().to_string()
+        }
+        ParseError::UnsupportedLanguageVersion { version, loc } => {
+            let error = Diagnostic::new(
+                Severity::Error,
+                format!("Unsupported language version {}", version),
+            )
+            .with_label(
+                Label::new_primary(*loc)
+                    .with_message(format!("The latest version is {}", LATEST_LANGUAGE_VERSION)),
+            );
+            let mut buffer = Buffer::no_color();
+            emit(&mut buffer, &code, &error).unwrap();
+            std::str::from_utf8(buffer.as_slice()).unwrap().to_string()
+        }
+        ParseError::InvalidByteArray { message, loc }
+        | ParseError::InvalidAttribute { message, loc } => {
+            let error = Diagnostic::new(Severity::Error, message.clone())
+                .with_label(Label::new_primary(*loc));
+            let mut buffer = Buffer::no_color();
+            emit(&mut buffer, &code, &error).unwrap();
+            std::str::from_utf8(buffer.as_slice()).unwrap().to_string()
+        }
+        ParseError::ChainedComparison { loc, replacement } => {
+            let error = Diagnostic::new(Severity::Error, "Chained comparison").with_label(
+                Label::new_primary(*loc).with_message(format!("did you mean `{}`?", replacement)),
+            );
+            let mut buffer = Buffer::no_color();
+            emit(&mut buffer, &code, &error).unwrap();
+            std::str::from_utf8(buffer.as_slice()).unwrap().to_string()
+        }
+        ParseError::TooDeeplyNested { loc, max_depth } => {
+            let error = Diagnostic::new(Severity::Error, "Expression nested too deeply")
+                .with_label(
+                    Label::new_primary(*loc)
+                        .with_message(format!("The maximal depth is {}", max_depth)),
+                );
+            let mut buffer = Buffer::no_color();
+            emit(&mut buffer, &code, &error).unwrap();
+            std::str::from_utf8(buffer.as_slice()).unwrap().to_string()
+        }
         _ => format!("{}", e),
     };
     println!("{}", msg);
@@ -167,6 +412,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn try_parse_locates_errors() {
+        use codespan::{ByteIndex, Span};
+
+        assert!(super::try_parse_script_or_module("main() { return; }").is_ok());
+
+        let err = super::try_parse_script_or_module("main() {\n    return\n}").unwrap_err();
+        assert_eq!(err.loc, Some(Span::new(ByteIndex(20), ByteIndex(20))));
+
+        let err = super::try_parse_script_or_module("main() {\r\n}").unwrap_err();
+        assert_eq!(err.loc, Some(Span::new(ByteIndex(8), ByteIndex(9))));
+    }
+
     #[test]
     fn test_strip_comments() {
         let mut good_chars = (0x20..=0x7E).map(|x: u8| x as char).collect::<String>();
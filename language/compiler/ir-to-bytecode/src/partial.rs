@@ -0,0 +1,82 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Error-tolerant compilation of modules.
+//!
+//! When a function of a large module fails to compile, the other functions can still be compiled,
+//! so that every error of the module is reported at once instead of one per run. The functions
+//! that fail are replaced by a stub aborting with
+//! [`FAILED_FUNCTION_ABORT_CODE`](constant.FAILED_FUNCTION_ABORT_CODE.html): the stub keeps the
+//! signature of the function, so that its callers still compile, but acquires nothing. With the
+//! bytecode verifier on, a caller whose `acquires` list was only needed by the failed function can
+//! then fail to verify; inferring the `acquires` lists avoids that for the errors found before
+//! bytecode is generated.
+//!
+//! [`stub_failed_functions`](fn.stub_failed_functions.html) stubs the functions the errors of a
+//! pass over the AST are located in, and
+//! [`compile_module_partially`](../compiler/fn.compile_module_partially.html) the functions whose
+//! bytecode cannot be generated.
+
+use crate::errors::FunctionError;
+use move_ir_types::ast::{
+    Block_, Cmd_, Exp_, Function, FunctionBody, Loc, ModuleDefinition, Spanned, Statement,
+};
+use std::fmt;
+
+/// The code the stub of a function that failed to compile aborts with.
+pub const FAILED_FUNCTION_ABORT_CODE: u64 = u64::max_value();
+
+/// Replaces the body of `function` with one aborting with `FAILED_FUNCTION_ABORT_CODE`, and
+/// clears its `acquires` list and specifications.
+pub fn stub_function(function: &mut Function) {
+    let abort = Cmd_::Abort(Some(Box::new(Exp_::u64(FAILED_FUNCTION_ABORT_CODE))));
+    let function = &mut function.value;
+    function.body = FunctionBody::Move {
+        locals: vec![],
+        code: Block_::new(vec![Statement::cmd(Spanned::no_loc(abort))]),
+    };
+    function.acquires.clear();
+    function.specifications.clear();
+}
+
+/// Stubs the functions of `module` that `errors` are located in, returning the errors of the
+/// functions stubbed, in order, and the errors located outside of any function, which cannot be
+/// tolerated.
+pub fn stub_failed_functions<E: fmt::Display>(
+    module: &mut ModuleDefinition,
+    errors: Vec<E>,
+    loc: impl Fn(&E) -> Option<Loc>,
+) -> (Vec<FunctionError>, Vec<E>) {
+    let mut failed = vec![];
+    let mut rest = vec![];
+    let mut stubbed = vec![false; module.functions.len()];
+    for error in errors {
+        let located = loc(&error).and_then(|loc| {
+            module
+                .functions
+                .iter()
+                .position(|(_, function)| contains(function.span, loc))
+                .map(|index| (index, loc))
+        });
+        match located {
+            Some((index, loc)) => {
+                let (name, function) = &mut module.functions[index];
+                failed.push(FunctionError {
+                    function: name.clone(),
+                    loc,
+                    message: error.to_string(),
+                });
+                if !stubbed[index] {
+                    stub_function(function);
+                    stubbed[index] = true;
+                }
+            }
+            None => rest.push(error),
+        }
+    }
+    (failed, rest)
+}
+
+fn contains(outer: Loc, inner: Loc) -> bool {
+    outer.start() <= inner.start() && inner.end() <= outer.end()
+}
@@ -0,0 +1,136 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Named placeholders in transaction scripts.
+//!
+//! A script can leave values out by writing `{{name}}` in their place, e.g.
+//! `LibraAccount.pay_from_sender({{recipient}}, {{amount}})`. The parsed script is a template
+//! which is instantiated by substituting typed values for its placeholders, instead of splicing
+//! text into the source. The compiler then checks the values like any other literal, e.g. that
+//! `{{amount}}` was given a `u64`.
+
+use crate::errors::PlaceholderError;
+use libra_types::identifier::Identifier;
+use move_ir_types::ast::{
+    visit_exp_mut, Block_, Cmd_, CopyableVal_, Exp, Exp_, FunctionBody, LValue_, Loc, Script,
+    Spanned, Statement,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Returns the names of the placeholders of the script.
+pub fn placeholders(script: &Script) -> BTreeSet<Identifier> {
+    let mut script = script.clone();
+    let mut names = BTreeSet::new();
+    visit_script(&mut script, &mut |name, _| {
+        names.insert(name.clone());
+        None
+    });
+    names
+}
+
+/// Replaces every placeholder of the script with its value in `values`. Fails if a placeholder
+/// has no value, or if a value is given for a placeholder the script does not have.
+pub fn substitute_placeholders(
+    script: &mut Script,
+    values: &BTreeMap<Identifier, CopyableVal_>,
+) -> Result<(), PlaceholderError> {
+    let mut missing = None;
+    let mut used = BTreeSet::new();
+    visit_script(script, &mut |name, loc| {
+        used.insert(name.clone());
+        match values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => {
+                if missing.is_none() {
+                    missing = Some(PlaceholderError::Missing {
+                        loc,
+                        name: name.clone(),
+                    });
+                }
+                None
+            }
+        }
+    });
+    if let Some(err) = missing {
+        return Err(err);
+    }
+    match values.keys().find(|name| !used.contains(*name)) {
+        Some(name) => Err(PlaceholderError::Unused { name: name.clone() }),
+        None => Ok(()),
+    }
+}
+
+/// Calls `f` on every placeholder, replacing it with the value `f` returns, if any.
+type Visitor<'a> = dyn FnMut(&Identifier, Loc) -> Option<CopyableVal_> + 'a;
+
+fn visit_script(script: &mut Script, f: &mut Visitor) {
+    if let FunctionBody::Move { code, .. } = &mut script.main.value.body {
+        visit_block(code, f)
+    }
+}
+
+fn visit_block(block: &mut Block_, f: &mut Visitor) {
+    for statement in &mut block.stmts {
+        match statement {
+            Statement::CommandStatement(cmd) => visit_cmd(&mut cmd.value, f),
+            Statement::IfElseStatement(if_else) => {
+                visit_exp(&mut if_else.cond, f);
+                visit_block(&mut if_else.if_block.value, f);
+                if let Some(else_block) = &mut if_else.else_block {
+                    visit_block(&mut else_block.value, f);
+                }
+            }
+            Statement::WhileStatement(while_) => {
+                visit_exp(&mut while_.cond, f);
+                visit_block(&mut while_.block.value, f);
+            }
+            Statement::LoopStatement(loop_) => {
+                for lvalue in &mut loop_.results {
+                    if let LValue_::Mutate(e) = &mut lvalue.value {
+                        visit_exp(e, f);
+                    }
+                }
+                visit_block(&mut loop_.block.value, f);
+            }
+            Statement::EmptyStatement => (),
+        }
+    }
+}
+
+fn visit_cmd(cmd: &mut Cmd_, f: &mut Visitor) {
+    match cmd {
+        Cmd_::Assign(lvalues, e) => {
+            for lvalue in lvalues {
+                if let LValue_::Mutate(e) = &mut lvalue.value {
+                    visit_exp(e, f);
+                }
+            }
+            visit_exp(e, f);
+        }
+        Cmd_::Unpack(_, _, _, e)
+        | Cmd_::Return(e)
+        | Cmd_::Exp(e)
+        | Cmd_::Abort(Some(e))
+        | Cmd_::Break(Some(e)) => visit_exp(e, f),
+        Cmd_::Emit(handle, e) => {
+            visit_exp(handle, f);
+            visit_exp(e, f);
+        }
+        Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+    }
+}
+
+fn visit_exp(e: &mut Exp, f: &mut Visitor) {
+    visit_exp_mut(e, |e| {
+        let value = match &e.value {
+            Exp_::Placeholder(name) => f(name, e.span),
+            _ => None,
+        };
+        if let Some(value) = value {
+            e.value = Exp_::Value(Spanned {
+                span: e.span,
+                value,
+            });
+        }
+    });
+}
@@ -0,0 +1,165 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Passes supplied by the users of the compiler.
+//!
+//! A plugin implements [`Plugin`](trait.Plugin.html), overriding the hooks it needs, and is
+//! registered with [`Plugins`](struct.Plugins.html), which the compiler runs at two points of the
+//! pipeline: once the source is parsed and its `cfg` attributes resolved, before any pass of the
+//! compiler, and once every pass has run, just before bytecode is generated. Both hooks can
+//! rewrite the AST and report violations of a policy, such as a banned builtin or a public
+//! function without specifications, so that an organization can enforce its own rules without
+//! forking the compiler.
+//!
+//! Reports are [`Lint`](../lint/struct.Lint.html)s named after the plugin, at the severity the
+//! plugin chooses. Before being kept, every report goes through the diagnostic filter of every
+//! plugin, which can drop it or change its severity. Denied reports fail compilation; warnings
+//! are kept for the caller to read with
+//! [`Plugins::take_warnings`](struct.Plugins.html#method.take_warnings).
+
+use crate::lint::{Lint, Report, Severity};
+use move_ir_types::ast::{ModuleDefinition, Program, Script};
+use std::{
+    fmt, mem,
+    sync::{Arc, Mutex},
+};
+
+/// A user-supplied pass. Every hook does nothing by default.
+///
+/// Plugins are shared by the clones of the compiler options, which can be sent to other threads.
+pub trait Plugin: Send + Sync {
+    /// The name of the plugin, which its reports carry.
+    fn name(&self) -> &'static str;
+
+    /// The severity of the reports of the plugin. Policies are enforced by default.
+    fn severity(&self) -> Severity {
+        Severity::Deny
+    }
+
+    /// Called for every module once parsed, before the passes of the compiler.
+    fn after_parse_module(&self, _module: &mut ModuleDefinition, _reports: &mut Vec<Report>) {}
+
+    /// Called for the script once parsed, before the passes of the compiler.
+    fn after_parse_script(&self, _script: &mut Script, _reports: &mut Vec<Report>) {}
+
+    /// Called for every module once the passes of the compiler have run, just before its bytecode
+    /// is generated.
+    fn before_emit_module(&self, _module: &mut ModuleDefinition, _reports: &mut Vec<Report>) {}
+
+    /// Called for the script once the passes of the compiler have run, just before its bytecode
+    /// is generated.
+    fn before_emit_script(&self, _script: &mut Script, _reports: &mut Vec<Report>) {}
+
+    /// Called for every report of every plugin. Returns whether to keep it, possibly at another
+    /// severity; reports set to `Allow` are dropped too.
+    fn filter_diagnostic(&self, _diagnostic: &mut Lint) -> bool {
+        true
+    }
+}
+
+/// The plugins to run, in the order they were added, and the warnings they reported.
+///
+/// Clones share the warnings, so that a caller keeping a clone can read the warnings reported
+/// while compiling with another.
+#[derive(Clone, Default)]
+pub struct Plugins {
+    plugins: Vec<Arc<dyn Plugin>>,
+    warnings: Arc<Mutex<Vec<Lint>>>,
+}
+
+impl fmt::Debug for Plugins {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.plugins.iter().map(|plugin| plugin.name()))
+            .finish()
+    }
+}
+
+impl Plugins {
+    /// Adds a plugin, run after the ones already added.
+    pub fn add_plugin(&mut self, plugin: Box<dyn Plugin>) {
+        self.plugins.push(plugin.into());
+    }
+
+    /// Whether no plugin was added.
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Runs the post-parse hooks over the module. Returns the denied reports.
+    pub fn after_parse_module(&self, module: &mut ModuleDefinition) -> Vec<Lint> {
+        self.run(|plugin, reports| plugin.after_parse_module(module, reports))
+    }
+
+    /// Runs the post-parse hooks over the modules and the script of the program, in that order.
+    /// Returns the denied reports.
+    pub fn after_parse_program(&self, program: &mut Program) -> Vec<Lint> {
+        let mut errors = vec![];
+        for module in &mut program.modules {
+            errors.extend(self.after_parse_module(module));
+        }
+        let script = &mut program.script;
+        errors.extend(self.run(|plugin, reports| plugin.after_parse_script(script, reports)));
+        errors
+    }
+
+    /// Runs the pre-emission hooks over the module. Returns the denied reports.
+    pub fn before_emit_module(&self, module: &mut ModuleDefinition) -> Vec<Lint> {
+        self.run(|plugin, reports| plugin.before_emit_module(module, reports))
+    }
+
+    /// Runs the pre-emission hooks over the modules and the script of the program, in that
+    /// order. Returns the denied reports.
+    pub fn before_emit_program(&self, program: &mut Program) -> Vec<Lint> {
+        let mut errors = vec![];
+        for module in &mut program.modules {
+            errors.extend(self.before_emit_module(module));
+        }
+        let script = &mut program.script;
+        errors.extend(self.run(|plugin, reports| plugin.before_emit_script(script, reports)));
+        errors
+    }
+
+    /// Passes the diagnostics through the filters of the plugins, returning the ones kept.
+    pub fn filter(&self, diagnostics: Vec<Lint>) -> Vec<Lint> {
+        diagnostics
+            .into_iter()
+            .filter_map(|mut diagnostic| {
+                let kept = self
+                    .plugins
+                    .iter()
+                    .all(|plugin| plugin.filter_diagnostic(&mut diagnostic));
+                if kept && diagnostic.severity != Severity::Allow {
+                    Some(diagnostic)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the warnings reported since the last call, in the order they were reported.
+    pub fn take_warnings(&self) -> Vec<Lint> {
+        mem::replace(&mut *self.warnings.lock().unwrap(), vec![])
+    }
+
+    // Runs a hook of every plugin, keeping the warnings and returning the errors.
+    fn run(&self, mut hook: impl FnMut(&dyn Plugin, &mut Vec<Report>)) -> Vec<Lint> {
+        let mut diagnostics = vec![];
+        for plugin in &self.plugins {
+            let mut reports = vec![];
+            hook(plugin.as_ref(), &mut reports);
+            diagnostics.extend(reports.into_iter().map(|report| Lint {
+                rule: plugin.name(),
+                severity: plugin.severity(),
+                report,
+            }));
+        }
+        let (errors, warnings): (Vec<_>, Vec<_>) = self
+            .filter(diagnostics)
+            .into_iter()
+            .partition(|diagnostic| diagnostic.severity == Severity::Deny);
+        self.warnings.lock().unwrap().extend(warnings);
+        errors
+    }
+}
@@ -0,0 +1,273 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Address relocation.
+//!
+//! Modules are usually developed against placeholder addresses such as `0x0`, and then deployed
+//! under different addresses on each network. This pass rewrites the account addresses of a
+//! parsed script or module according to a map from the old addresses to the new ones: the
+//! addresses of the imports, the address literals in function bodies, and the addresses in
+//! specifications. It returns every rewritten address, so that the retargeting can be reviewed.
+
+use anyhow::{bail, Result};
+use libra_types::account_address::AccountAddress;
+use move_ir_types::{
+    ast::{
+        visit_exp_mut, Block_, Cmd_, CopyableVal_, Exp, Exp_, FunctionBody, ImportDefinition,
+        LValue_, Loc, ModuleDefinition, ModuleIdent, ModuleName, Program, Script, Statement,
+    },
+    spec_language_ast::{Condition_, SpecExp, StorageLocation},
+};
+use std::{collections::BTreeMap, fmt};
+
+/// Where a relocated address was written.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RelocationSite {
+    /// The address of the import of this module. Imports have no location.
+    Import(ModuleName),
+    /// An address literal, in an expression or in a specification at this location.
+    Literal(Loc),
+}
+
+/// An address rewritten by the relocation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Relocation {
+    pub site: RelocationSite,
+    pub from: AccountAddress,
+    pub to: AccountAddress,
+}
+
+impl Relocation {
+    /// The location of the rewritten literal, if the address was not the one of an import.
+    pub fn loc(&self) -> Option<Loc> {
+        match &self.site {
+            RelocationSite::Import(_) => None,
+            RelocationSite::Literal(loc) => Some(*loc),
+        }
+    }
+}
+
+impl fmt::Display for Relocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.site {
+            RelocationSite::Import(name) => write!(
+                f,
+                "import of {} relocated from {} to {}",
+                name, self.from, self.to
+            ),
+            RelocationSite::Literal(_) => {
+                write!(f, "address {} relocated to {}", self.from, self.to)
+            }
+        }
+    }
+}
+
+/// Parses a relocation written `0xold=0xnew`, with the addresses written as in the IR.
+pub fn parse_relocation(s: &str) -> Result<(AccountAddress, AccountAddress)> {
+    let parts: Vec<&str> = s.splitn(2, '=').collect();
+    if parts.len() != 2 {
+        bail!("Invalid relocation {}, expected `0xold=0xnew`", s);
+    }
+    Ok((parse_address(parts[0])?, parse_address(parts[1])?))
+}
+
+fn parse_address(s: &str) -> Result<AccountAddress> {
+    if !s.starts_with("0x") || s.len() == 2 {
+        bail!("Invalid address {}, expected an address such as `0x1`", s);
+    }
+    AccountAddress::from_hex_literal(s)
+}
+
+/// Relocates the addresses of every module and then of the script of a program.
+pub fn relocate_program(
+    program: &mut Program,
+    addresses: &BTreeMap<AccountAddress, AccountAddress>,
+) -> Vec<Relocation> {
+    let mut relocations = vec![];
+    for module in &mut program.modules {
+        relocations.extend(relocate_module(module, addresses));
+    }
+    relocations.extend(relocate_script(&mut program.script, addresses));
+    relocations
+}
+
+/// Relocates the addresses of the imports, functions and specifications of a module, returning
+/// the rewritten addresses, those of the imports first.
+pub fn relocate_module(
+    module: &mut ModuleDefinition,
+    addresses: &BTreeMap<AccountAddress, AccountAddress>,
+) -> Vec<Relocation> {
+    let mut relocator = Relocator {
+        addresses,
+        relocations: vec![],
+    };
+    relocator.imports(&mut module.imports);
+    for struct_def in &mut module.structs {
+        for invariant in &mut struct_def.value.invariants {
+            relocator.spec_exp(&mut invariant.value.condition, invariant.span);
+        }
+    }
+    for (_, function) in &mut module.functions {
+        for condition in &mut function.value.specifications {
+            let loc = condition.span;
+            match &mut condition.value {
+                Condition_::Ensures(e)
+                | Condition_::Requires(e)
+                | Condition_::AbortsIf(e)
+                | Condition_::SucceedsIf(e) => relocator.spec_exp(e, loc),
+            }
+        }
+        if let FunctionBody::Move { code, .. } = &mut function.value.body {
+            relocator.block(code);
+        }
+    }
+    relocator.relocations
+}
+
+/// Relocates the addresses of the imports and of the `main` function of a script, returning the
+/// rewritten addresses, those of the imports first.
+pub fn relocate_script(
+    script: &mut Script,
+    addresses: &BTreeMap<AccountAddress, AccountAddress>,
+) -> Vec<Relocation> {
+    let mut relocator = Relocator {
+        addresses,
+        relocations: vec![],
+    };
+    relocator.imports(&mut script.imports);
+    if let FunctionBody::Move { code, .. } = &mut script.main.value.body {
+        relocator.block(code);
+    }
+    relocator.relocations
+}
+
+struct Relocator<'a> {
+    addresses: &'a BTreeMap<AccountAddress, AccountAddress>,
+    relocations: Vec<Relocation>,
+}
+
+impl<'a> Relocator<'a> {
+    fn relocate(&mut self, address: &mut AccountAddress, site: RelocationSite) {
+        if let Some(to) = self.addresses.get(address) {
+            self.relocations.push(Relocation {
+                site,
+                from: *address,
+                to: *to,
+            });
+            *address = *to;
+        }
+    }
+
+    fn imports(&mut self, imports: &mut [ImportDefinition]) {
+        for import in imports {
+            if let ModuleIdent::Qualified(ident) = &mut import.ident {
+                let site = RelocationSite::Import(ident.name.clone());
+                self.relocate(&mut ident.address, site);
+            }
+        }
+    }
+
+    fn block(&mut self, block: &mut Block_) {
+        for statement in &mut block.stmts {
+            match statement {
+                Statement::CommandStatement(cmd) => self.cmd(&mut cmd.value),
+                Statement::IfElseStatement(if_else) => {
+                    self.exp(&mut if_else.cond);
+                    self.block(&mut if_else.if_block.value);
+                    if let Some(else_block) = &mut if_else.else_block {
+                        self.block(&mut else_block.value);
+                    }
+                }
+                Statement::WhileStatement(while_) => {
+                    self.exp(&mut while_.cond);
+                    self.block(&mut while_.block.value);
+                }
+                Statement::LoopStatement(loop_) => {
+                    for lvalue in &mut loop_.results {
+                        if let LValue_::Mutate(e) = &mut lvalue.value {
+                            self.exp(e);
+                        }
+                    }
+                    self.block(&mut loop_.block.value);
+                }
+                Statement::EmptyStatement => (),
+            }
+        }
+    }
+
+    fn cmd(&mut self, cmd: &mut Cmd_) {
+        match cmd {
+            Cmd_::Assign(lvalues, e) => {
+                for lvalue in lvalues {
+                    if let LValue_::Mutate(e) = &mut lvalue.value {
+                        self.exp(e);
+                    }
+                }
+                self.exp(e);
+            }
+            Cmd_::Unpack(_, _, _, e)
+            | Cmd_::Return(e)
+            | Cmd_::Exp(e)
+            | Cmd_::Abort(Some(e))
+            | Cmd_::Break(Some(e)) => self.exp(e),
+            Cmd_::Emit(handle, e) => {
+                self.exp(handle);
+                self.exp(e);
+            }
+            Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+        }
+    }
+
+    fn exp(&mut self, e: &mut Exp) {
+        visit_exp_mut(e, |e| {
+            if let Exp_::Value(value) = &mut e.value {
+                if let CopyableVal_::Address(address) = &mut value.value {
+                    self.relocate(address, RelocationSite::Literal(value.span));
+                }
+            }
+        });
+    }
+
+    // Specification expressions have no location of their own, so their addresses are located at
+    // the condition or invariant they are in.
+    fn spec_exp(&mut self, e: &mut SpecExp, loc: Loc) {
+        match e {
+            SpecExp::Constant(CopyableVal_::Address(address)) => {
+                self.relocate(address, RelocationSite::Literal(loc))
+            }
+            SpecExp::Constant(_) => (),
+            SpecExp::StorageLocation(location)
+            | SpecExp::Dereference(location)
+            | SpecExp::Reference(location) => self.storage_location(location, loc),
+            SpecExp::GlobalExists { address, .. } => self.storage_location(address, loc),
+            SpecExp::Not(e) | SpecExp::Old(e) => self.spec_exp(e, loc),
+            SpecExp::Binop(lhs, _, rhs) => {
+                self.spec_exp(lhs, loc);
+                self.spec_exp(rhs, loc);
+            }
+            SpecExp::Call(_, args) => {
+                for arg in args {
+                    self.spec_exp(arg, loc);
+                }
+            }
+            SpecExp::IfElse(cond, if_exp, else_exp) => {
+                self.spec_exp(cond, loc);
+                self.spec_exp(if_exp, loc);
+                self.spec_exp(else_exp, loc);
+            }
+        }
+    }
+
+    fn storage_location(&mut self, location: &mut StorageLocation, loc: Loc) {
+        match location {
+            StorageLocation::Address(address) => {
+                self.relocate(address, RelocationSite::Literal(loc))
+            }
+            StorageLocation::GlobalResource { address, .. } => self.storage_location(address, loc),
+            StorageLocation::AccessPath { base, .. } => self.storage_location(base, loc),
+            StorageLocation::Formal(_)
+            | StorageLocation::TxnSenderAddress
+            | StorageLocation::Ret(_) => (),
+        }
+    }
+}
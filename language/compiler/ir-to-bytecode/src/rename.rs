@@ -0,0 +1,496 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Renaming of identifiers.
+//!
+//! This pass renames the locals and parameters of every function, the internal functions of a
+//! module and the fields of its structs, consistently across their declarations, uses and
+//! specifications. The names other modules can refer to are kept: the names of structs, of
+//! public functions, and of native functions, which the VM binds by name. The new names follow a
+//! [`RenameScheme`](enum.RenameScheme.html): the shortest names available, to make the compiled
+//! module smaller, or names that tell nothing of the original ones, to share a repro case
+//! without sharing the code it was reduced from. The pass returns a
+//! [`RenameMap`](struct.RenameMap.html) from the original names to the new ones.
+
+use crate::parser::FUTURE_KEYWORDS;
+use anyhow::{bail, Result};
+use libra_types::identifier::Identifier;
+use move_ir_types::{
+    ast::{
+        Block_, Cmd_, Exp, Exp_, Field_, FunctionBody, FunctionCall_, FunctionName,
+        FunctionVisibility, Function_, LValue_, ModuleDefinition, ModuleName, Script, Statement,
+        StructDefinitionFields, Var, Var_,
+    },
+    spec_language_ast::{Condition_, SpecExp, StorageLocation},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    mem,
+    str::FromStr,
+};
+
+/// The keywords of the IR, which cannot be used as names.
+const KEYWORDS: &[&str] = &[
+    "abort",
+    "aborts_if",
+    "acquires",
+    "address",
+    "as",
+    "bool",
+    "break",
+    "bytearray",
+    "continue",
+    "copy",
+    "else",
+    "ensures",
+    "false",
+    "freeze",
+    "get_txn_sender",
+    "global",
+    "global_exists",
+    "if",
+    "import",
+    "invariant",
+    "let",
+    "loop",
+    "main",
+    "module",
+    "move",
+    "native",
+    "old",
+    "public",
+    "requires",
+    "resource",
+    "return",
+    "struct",
+    "succeeds_if",
+    "synthetic",
+    "true",
+    "txn_sender",
+    "unrestricted",
+    "while",
+];
+
+/// How new names are chosen.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RenameScheme {
+    /// The shortest names available, in order: `a`, `b`, ..., `z`, `aa`, `ab`, ...
+    Minify,
+    /// The original names with this prefix.
+    Prefix(String),
+    /// Numbered names of the kind of what is renamed, e.g. `local0`, `fun1` or `field2`.
+    Obfuscate,
+}
+
+impl FromStr for RenameScheme {
+    type Err = anyhow::Error;
+
+    /// Parses `minify`, `obfuscate` or `prefix:<prefix>`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "minify" => Ok(RenameScheme::Minify),
+            "obfuscate" => Ok(RenameScheme::Obfuscate),
+            _ if s.starts_with("prefix:") => {
+                let prefix = &s["prefix:".len()..];
+                if prefix.is_empty() || !Identifier::is_valid(format!("{}a", prefix)) {
+                    bail!("Invalid prefix `{}`, expected the start of a name", prefix);
+                }
+                Ok(RenameScheme::Prefix(prefix.to_string()))
+            }
+            _ => bail!(
+                "Invalid rename scheme {}, expected `minify`, `obfuscate` or `prefix:<prefix>`",
+                s
+            ),
+        }
+    }
+}
+
+/// The original names of what was renamed, mapped to the new names.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RenameMap {
+    /// The internal functions of the module.
+    pub functions: BTreeMap<String, String>,
+    /// The fields of the structs of the module, which are renamed alike in every struct.
+    pub fields: BTreeMap<String, String>,
+    /// The locals and parameters of each function, by original function name.
+    pub locals: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl RenameMap {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+}
+
+/// Renames the locals and parameters of the functions, the internal functions and the fields of
+/// a module.
+pub fn rename_module(module: &mut ModuleDefinition, scheme: &RenameScheme) -> RenameMap {
+    let mut map = RenameMap::default();
+
+    // Public and native functions keep their names, so the new names must be distinct from them.
+    let (kept, renamed): (Vec<_>, Vec<_>) =
+        module.functions.iter().partition(|(_, f)| !is_internal(f));
+    let mut functions = Namer::new(scheme, "fun", kept.iter().map(|(name, _)| name.to_string()));
+    for (name, _) in renamed {
+        map.functions
+            .insert(name.to_string(), functions.fresh(name.as_inner().as_str()));
+    }
+    let mut fields = Namer::new(scheme, "field", vec![]);
+    for struct_def in &module.structs {
+        if let StructDefinitionFields::Move { fields: defs } = &struct_def.value.fields {
+            for (field, _) in defs {
+                let name = field.value.name().as_str();
+                if !map.fields.contains_key(name) {
+                    map.fields.insert(name.to_string(), fields.fresh(name));
+                }
+            }
+        }
+    }
+
+    let mut renamer = Renamer {
+        functions: &map.functions,
+        fields: &map.fields,
+        locals: BTreeMap::new(),
+    };
+    for struct_def in &mut module.structs {
+        if let StructDefinitionFields::Move { fields } = &mut struct_def.value.fields {
+            for (field, _) in fields {
+                renamer.field(&mut field.value);
+            }
+        }
+        // The names of an invariant are the fields of the struct.
+        let fields = renamer.fields;
+        for invariant in &mut struct_def.value.invariants {
+            Renamer::spec_exp(&mut invariant.value.condition, fields, fields);
+        }
+    }
+    for (name, function) in &mut module.functions {
+        let original = name.to_string();
+        renamer.locals = locals(&function.value, scheme);
+        renamer.function(&mut function.value);
+        if let Some(new_name) = map.functions.get(&original) {
+            *name = function_name(new_name);
+        }
+        let locals = mem::replace(&mut renamer.locals, BTreeMap::new());
+        if !locals.is_empty() {
+            map.locals.insert(original, locals);
+        }
+    }
+    map
+}
+
+/// Renames the locals and parameters of the `main` function of a script.
+pub fn rename_script(script: &mut Script, scheme: &RenameScheme) -> RenameMap {
+    let mut map = RenameMap::default();
+    let empty = BTreeMap::new();
+    let mut renamer = Renamer {
+        functions: &empty,
+        fields: &empty,
+        locals: locals(&script.main.value, scheme),
+    };
+    renamer.function(&mut script.main.value);
+    if !renamer.locals.is_empty() {
+        map.locals.insert("main".to_string(), renamer.locals);
+    }
+    map
+}
+
+fn is_internal(function: &Function_) -> bool {
+    function.visibility == FunctionVisibility::Internal
+        && match function.body {
+            FunctionBody::Move { .. } => true,
+            FunctionBody::Native => false,
+        }
+}
+
+fn function_name(name: &str) -> FunctionName {
+    FunctionName::new(Identifier::new(name).unwrap())
+}
+
+/// The new names of the parameters and locals of a function, in order of declaration.
+fn locals(function: &Function_, scheme: &RenameScheme) -> BTreeMap<String, String> {
+    let mut namer = Namer::new(scheme, "local", vec![]);
+    let mut locals = BTreeMap::new();
+    let mut declared: Vec<&Var> = function
+        .signature
+        .formals
+        .iter()
+        .map(|(var, _)| var)
+        .collect();
+    if let FunctionBody::Move { locals, .. } = &function.body {
+        declared.extend(locals.iter().map(|(var, _)| var));
+    }
+    for var in declared {
+        let name = var.value.name().as_str();
+        if !locals.contains_key(name) {
+            locals.insert(name.to_string(), namer.fresh(name));
+        }
+    }
+    locals
+}
+
+/// Chooses new names of one kind, distinct from each other and from the names kept.
+struct Namer<'a> {
+    scheme: &'a RenameScheme,
+    kind: &'static str,
+    taken: BTreeSet<String>,
+    next: usize,
+}
+
+impl<'a> Namer<'a> {
+    fn new(
+        scheme: &'a RenameScheme,
+        kind: &'static str,
+        kept: impl IntoIterator<Item = String>,
+    ) -> Self {
+        Self {
+            scheme,
+            kind,
+            taken: kept.into_iter().collect(),
+            next: 0,
+        }
+    }
+
+    fn fresh(&mut self, original: &str) -> String {
+        let mut suffix = 0;
+        loop {
+            let name = match self.scheme {
+                RenameScheme::Minify => {
+                    self.next += 1;
+                    minified(self.next - 1)
+                }
+                RenameScheme::Obfuscate => {
+                    self.next += 1;
+                    format!("{}{}", self.kind, self.next - 1)
+                }
+                RenameScheme::Prefix(prefix) => {
+                    suffix += 1;
+                    if suffix == 1 {
+                        format!("{}{}", prefix, original)
+                    } else {
+                        format!("{}{}{}", prefix, original, suffix - 1)
+                    }
+                }
+            };
+            let reserved =
+                KEYWORDS.contains(&name.as_str()) || FUTURE_KEYWORDS.contains(&name.as_str());
+            if !reserved && self.taken.insert(name.clone()) {
+                return name;
+            }
+        }
+    }
+}
+
+/// Returns the `idx`th name of `a`, `b`, ..., `z`, `aa`, `ab`, ...
+fn minified(mut idx: usize) -> String {
+    let mut name = vec![];
+    loop {
+        name.push(b'a' + (idx % 26) as u8);
+        if idx < 26 {
+            break;
+        }
+        idx = idx / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap()
+}
+
+struct Renamer<'a> {
+    functions: &'a BTreeMap<String, String>,
+    fields: &'a BTreeMap<String, String>,
+    locals: BTreeMap<String, String>,
+}
+
+impl<'a> Renamer<'a> {
+    fn function(&mut self, function: &mut Function_) {
+        for (var, _) in &mut function.signature.formals {
+            self.var(var);
+        }
+        // The names of a specification are the parameters of the function.
+        for condition in &mut function.specifications {
+            match &mut condition.value {
+                Condition_::Ensures(e)
+                | Condition_::Requires(e)
+                | Condition_::AbortsIf(e)
+                | Condition_::SucceedsIf(e) => Self::spec_exp(e, &self.locals, self.fields),
+            }
+        }
+        if let FunctionBody::Move { locals, code } = &mut function.body {
+            for (var, _) in locals {
+                self.var(var);
+            }
+            self.block(code);
+        }
+    }
+
+    fn var(&self, var: &mut Var) {
+        if let Some(name) = self.locals.get(var.value.name().as_str()) {
+            var.value = Var_::new(Identifier::new(name.as_str()).unwrap());
+        }
+    }
+
+    fn field(&self, field: &mut Field_) {
+        if let Some(name) = self.fields.get(field.name().as_str()) {
+            *field = Field_::new(Identifier::new(name.as_str()).unwrap());
+        }
+    }
+
+    fn block(&mut self, block: &mut Block_) {
+        for statement in &mut block.stmts {
+            match statement {
+                Statement::CommandStatement(cmd) => self.cmd(&mut cmd.value),
+                Statement::IfElseStatement(if_else) => {
+                    self.exp(&mut if_else.cond);
+                    self.block(&mut if_else.if_block.value);
+                    if let Some(else_block) = &mut if_else.else_block {
+                        self.block(&mut else_block.value);
+                    }
+                }
+                Statement::WhileStatement(while_) => {
+                    self.exp(&mut while_.cond);
+                    self.block(&mut while_.block.value);
+                }
+                Statement::LoopStatement(loop_) => {
+                    for lvalue in &mut loop_.results {
+                        self.lvalue(&mut lvalue.value);
+                    }
+                    self.block(&mut loop_.block.value);
+                }
+                Statement::EmptyStatement => (),
+            }
+        }
+    }
+
+    fn lvalue(&mut self, lvalue: &mut LValue_) {
+        match lvalue {
+            LValue_::Var(var) => self.var(var),
+            LValue_::Mutate(e) => self.exp(e),
+            LValue_::Pop => (),
+        }
+    }
+
+    fn cmd(&mut self, cmd: &mut Cmd_) {
+        match cmd {
+            Cmd_::Assign(lvalues, e) => {
+                for lvalue in lvalues {
+                    self.lvalue(&mut lvalue.value);
+                }
+                self.exp(e);
+            }
+            Cmd_::Unpack(_, _, bindings, e) => {
+                for (field, var) in bindings {
+                    self.field(&mut field.value);
+                    self.var(var);
+                }
+                self.exp(e);
+            }
+            Cmd_::Return(e) | Cmd_::Exp(e) | Cmd_::Abort(Some(e)) | Cmd_::Break(Some(e)) => {
+                self.exp(e)
+            }
+            Cmd_::Emit(handle, e) => {
+                self.exp(handle);
+                self.exp(e);
+            }
+            Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+        }
+    }
+
+    fn exp(&mut self, e: &mut Exp) {
+        match &mut e.value {
+            Exp_::Dereference(e) | Exp_::UnaryExp(_, e) => self.exp(e),
+            Exp_::Borrow { exp, field, .. } => {
+                self.exp(exp);
+                self.field(field);
+            }
+            Exp_::FunctionCall(call, e) => {
+                if let FunctionCall_::ModuleFunctionCall { module, name, .. } = &mut call.value {
+                    if module.as_inner() == ModuleName::self_name() {
+                        if let Some(new_name) = self.functions.get(name.as_inner().as_str()) {
+                            *name = function_name(new_name);
+                        }
+                    }
+                }
+                self.exp(e);
+            }
+            Exp_::BinopExp(lhs, _, rhs) => {
+                self.exp(lhs);
+                self.exp(rhs);
+            }
+            Exp_::Pack(_, _, fields) => {
+                for (field, e) in fields {
+                    self.field(&mut field.value);
+                    self.exp(e);
+                }
+            }
+            Exp_::ExprList(exps) => {
+                for e in exps {
+                    self.exp(e);
+                }
+            }
+            Exp_::Move(var) | Exp_::Copy(var) | Exp_::Var(var) | Exp_::BorrowLocal(_, var) => {
+                self.var(var)
+            }
+            Exp_::Value(_) | Exp_::Placeholder(_) => (),
+        }
+    }
+
+    // The names of a specification are looked up in `names`, and the fields of its access paths
+    // in `fields`.
+    fn spec_exp(
+        e: &mut SpecExp,
+        names: &BTreeMap<String, String>,
+        fields: &BTreeMap<String, String>,
+    ) {
+        match e {
+            SpecExp::Constant(_) => (),
+            SpecExp::StorageLocation(location)
+            | SpecExp::Dereference(location)
+            | SpecExp::Reference(location) => Self::storage_location(location, names, fields),
+            SpecExp::GlobalExists { address, .. } => Self::storage_location(address, names, fields),
+            SpecExp::Not(e) | SpecExp::Old(e) => Self::spec_exp(e, names, fields),
+            SpecExp::Binop(lhs, _, rhs) => {
+                Self::spec_exp(lhs, names, fields);
+                Self::spec_exp(rhs, names, fields);
+            }
+            SpecExp::Call(_, args) => {
+                for arg in args {
+                    Self::spec_exp(arg, names, fields);
+                }
+            }
+            SpecExp::IfElse(cond, if_exp, else_exp) => {
+                Self::spec_exp(cond, names, fields);
+                Self::spec_exp(if_exp, names, fields);
+                Self::spec_exp(else_exp, names, fields);
+            }
+        }
+    }
+
+    fn storage_location(
+        location: &mut StorageLocation,
+        names: &BTreeMap<String, String>,
+        fields: &BTreeMap<String, String>,
+    ) {
+        match location {
+            StorageLocation::Formal(name) => {
+                if let Some(new_name) = names.get(name.as_str()) {
+                    *name = new_name.clone();
+                }
+            }
+            StorageLocation::GlobalResource { address, .. } => {
+                Self::storage_location(address, names, fields)
+            }
+            StorageLocation::AccessPath { base, fields: path } => {
+                Self::storage_location(base, names, fields);
+                for field in path {
+                    if let Some(new_name) = fields.get(field.name().as_str()) {
+                        *field = Field_::new(Identifier::new(new_name.as_str()).unwrap());
+                    }
+                }
+            }
+            StorageLocation::TxnSenderAddress
+            | StorageLocation::Address(_)
+            | StorageLocation::Ret(_) => (),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Where sources come from.
+//!
+//! Drivers compiling several files, such as
+//! [`compile_directory`](../build/fn.compile_directory.html), read them through a
+//! [`SourceResolver`](trait.SourceResolver.html) rather than from the file system, so that
+//! embedders can compile sources held in memory, in archives or in a remote store, and tests do
+//! not need to touch the disk. [`FileSystem`](struct.FileSystem.html) reads files from the disk
+//! and [`InMemory`](struct.InMemory.html) from a map.
+//!
+//! A resolver can also provide imported modules that are only available compiled, e.g. from a
+//! snapshot of the modules published on chain. [`WithModuleStore`](struct.WithModuleStore.html)
+//! adds a [`ModuleStore`](trait.ModuleStore.html) of compiled modules to a resolver of sources.
+
+use anyhow::{bail, Context, Result};
+use libra_types::language_storage::ModuleId;
+use move_ir_types::ast::QualifiedModuleIdent;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    hash::BuildHasher,
+    path::{Component, Path, PathBuf},
+};
+use vm::file_format::CompiledModule;
+
+/// An entry of a directory.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Entry {
+    File(PathBuf),
+    Directory(PathBuf),
+}
+
+/// A store of sources.
+pub trait SourceResolver {
+    /// Returns the content of the file at `path`.
+    fn read_file(&self, path: &Path) -> Result<String>;
+
+    /// Returns the files and subdirectories of the directory at `path`, in no particular order.
+    fn list_directory(&self, path: &Path) -> Result<Vec<Entry>>;
+
+    /// Returns the source of the module `id`, or `None` if the store does not know it. Drivers
+    /// fetch the modules their sources import when they are not among the sources.
+    fn fetch_module(&self, _id: &QualifiedModuleIdent) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Returns the compiled module `id`, or `None` if the store does not know it. Drivers fetch
+    /// the modules their sources import when they are neither among the sources nor available as
+    /// source, and check the uses of them against their compiled signatures.
+    fn fetch_compiled_module(&self, _id: &ModuleId) -> Result<Option<CompiledModule>> {
+        Ok(None)
+    }
+
+    /// Returns a path identifying the file at `path`, the same whatever the path taken to it.
+    /// Defaults to `path` with its `.` and `..` components resolved.
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf> {
+        Ok(normalize(path))
+    }
+}
+
+/// Sources read from the file system.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FileSystem;
+
+impl SourceResolver for FileSystem {
+    fn read_file(&self, path: &Path) -> Result<String> {
+        fs::read_to_string(path).with_context(|| format!("Unable to read file: {:?}", path))
+    }
+
+    fn list_directory(&self, path: &Path) -> Result<Vec<Entry>> {
+        let entries =
+            fs::read_dir(path).with_context(|| format!("Unable to read {}", path.display()))?;
+        let mut result = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            result.push(if path.is_dir() {
+                Entry::Directory(path)
+            } else {
+                Entry::File(path)
+            });
+        }
+        Ok(result)
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf> {
+        fs::canonicalize(path).with_context(|| format!("Unable to read file: {:?}", path))
+    }
+}
+
+/// Sources held in memory. Paths are compared after resolving their `.` and `..` components, and
+/// a directory exists as long as a file is in it.
+#[derive(Clone, Debug, Default)]
+pub struct InMemory {
+    files: BTreeMap<PathBuf, String>,
+    modules: BTreeMap<QualifiedModuleIdent, String>,
+}
+
+impl InMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file, replacing any file at the same path.
+    pub fn add_file(&mut self, path: impl AsRef<Path>, text: impl Into<String>) {
+        self.files.insert(normalize(path.as_ref()), text.into());
+    }
+
+    /// Adds the source of a module to fetch by its id.
+    pub fn add_module(&mut self, id: QualifiedModuleIdent, text: impl Into<String>) {
+        self.modules.insert(id, text.into());
+    }
+}
+
+impl SourceResolver for InMemory {
+    fn read_file(&self, path: &Path) -> Result<String> {
+        match self.files.get(&normalize(path)) {
+            Some(text) => Ok(text.clone()),
+            None => bail!("Unable to read file: {:?}", path),
+        }
+    }
+
+    fn list_directory(&self, path: &Path) -> Result<Vec<Entry>> {
+        let dir = normalize(path);
+        let mut entries = BTreeSet::new();
+        for file in self.files.keys() {
+            let relative = match file.strip_prefix(&dir) {
+                Ok(relative) => relative,
+                Err(_) => continue,
+            };
+            let mut components = relative.components();
+            if let Some(first) = components.next() {
+                let child = dir.join(first);
+                entries.insert(if components.next().is_some() {
+                    Entry::Directory(child)
+                } else {
+                    Entry::File(child)
+                });
+            }
+        }
+        Ok(entries.into_iter().collect())
+    }
+
+    fn fetch_module(&self, id: &QualifiedModuleIdent) -> Result<Option<String>> {
+        Ok(self.modules.get(id).cloned())
+    }
+}
+
+/// A store of compiled modules.
+pub trait ModuleStore {
+    /// Returns the module `id`, or `None` if there is no such module.
+    fn get_module(&self, id: &ModuleId) -> Result<Option<CompiledModule>>;
+}
+
+impl ModuleStore for BTreeMap<ModuleId, CompiledModule> {
+    fn get_module(&self, id: &ModuleId) -> Result<Option<CompiledModule>> {
+        Ok(self.get(id).cloned())
+    }
+}
+
+impl<S: BuildHasher> ModuleStore for HashMap<ModuleId, CompiledModule, S> {
+    fn get_module(&self, id: &ModuleId) -> Result<Option<CompiledModule>> {
+        Ok(self.get(id).cloned())
+    }
+}
+
+/// A resolver reading sources from `sources` and fetching the compiled modules it does not have
+/// as source from `store`.
+#[derive(Clone, Debug, Default)]
+pub struct WithModuleStore<R, S> {
+    pub sources: R,
+    pub store: S,
+}
+
+impl<R: SourceResolver, S: ModuleStore> SourceResolver for WithModuleStore<R, S> {
+    fn read_file(&self, path: &Path) -> Result<String> {
+        self.sources.read_file(path)
+    }
+
+    fn list_directory(&self, path: &Path) -> Result<Vec<Entry>> {
+        self.sources.list_directory(path)
+    }
+
+    fn fetch_module(&self, id: &QualifiedModuleIdent) -> Result<Option<String>> {
+        self.sources.fetch_module(id)
+    }
+
+    fn fetch_compiled_module(&self, id: &ModuleId) -> Result<Option<CompiledModule>> {
+        match self.sources.fetch_compiled_module(id)? {
+            Some(module) => Ok(Some(module)),
+            None => self.store.get_module(id),
+        }
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf> {
+        self.sources.canonicalize(path)
+    }
+}
+
+// Resolves the `.` and `..` components of `path`, without looking at the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if !result.pop() {
+                    result.push(component);
+                }
+            }
+            _ => result.push(component),
+        }
+    }
+    result
+}
@@ -0,0 +1,171 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Specialization of generic scripts.
+//!
+//! A script declaring type parameters, e.g. `main<Currency: resource>(payee: address)`, is a
+//! template for a family of scripts. Specializing it substitutes concrete types for its type
+//! parameters everywhere in `main`, so that the template compiles to a script which is not
+//! generic, e.g. one per currency. The structs given as type arguments must be reachable through
+//! the imports of the script.
+
+use crate::{errors::SpecializeError, type_checker::subst};
+use move_ir_types::ast::{
+    visit_exp_mut, Block_, Builtin, Cmd_, Exp, Exp_, FunctionBody, FunctionCall_, Kind, LValue_,
+    Script, Statement, Type, TypeVar,
+};
+use std::mem;
+
+/// Substitutes `type_args` for the type parameters of the script's `main`, leaving it without
+/// type parameters. Fails if there is not one argument per type parameter, or if an argument is
+/// not a concrete type of a kind the type parameter accepts.
+pub fn specialize_script(script: &mut Script, type_args: &[Type]) -> Result<(), SpecializeError> {
+    let signature = &mut script.main.value.signature;
+    if signature.type_formals.len() != type_args.len() {
+        return Err(SpecializeError::Arity {
+            expected: signature.type_formals.len(),
+            given: type_args.len(),
+        });
+    }
+    for ((name, kind), ty) in signature.type_formals.iter().zip(type_args) {
+        if !is_concrete(ty) {
+            return Err(SpecializeError::NotConcrete {
+                name: name.value.clone(),
+                type_: ty.to_string(),
+            });
+        }
+        if *kind == Kind::Resource && !is_struct(ty) {
+            return Err(SpecializeError::KindMismatch {
+                name: name.value.clone(),
+                kind: kind.clone(),
+                type_: ty.to_string(),
+            });
+        }
+    }
+
+    let type_formals = mem::replace(&mut signature.type_formals, vec![]);
+    let specializer = Specializer {
+        type_formals: &type_formals,
+        type_args,
+    };
+    for (_, ty) in &mut signature.formals {
+        specializer.ty(ty);
+    }
+    for ty in &mut signature.return_type {
+        specializer.ty(ty);
+    }
+    if let FunctionBody::Move { locals, code } = &mut script.main.value.body {
+        for (_, ty) in locals {
+            specializer.ty(ty);
+        }
+        specializer.block(code);
+    }
+    Ok(())
+}
+
+// Whether a type argument can be given for a type parameter: a type without references or type
+// parameters.
+fn is_concrete(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(_, _) | Type::TypeParameter(_) => false,
+        Type::Struct(_, tys) => tys.iter().all(is_concrete),
+        _ => true,
+    }
+}
+
+// The primitive types are never resources, while the kind of a struct is only known from its
+// declaration, which is left to the verifier.
+fn is_struct(ty: &Type) -> bool {
+    match ty {
+        Type::Struct(_, _) => true,
+        _ => false,
+    }
+}
+
+struct Specializer<'a> {
+    type_formals: &'a [(TypeVar, Kind)],
+    type_args: &'a [Type],
+}
+
+impl<'a> Specializer<'a> {
+    fn ty(&self, ty: &mut Type) {
+        *ty = subst(ty, self.type_formals, self.type_args);
+    }
+
+    fn tys(&self, tys: &mut [Type]) {
+        for ty in tys {
+            self.ty(ty);
+        }
+    }
+
+    fn block(&self, block: &mut Block_) {
+        for statement in &mut block.stmts {
+            match statement {
+                Statement::CommandStatement(cmd) => self.cmd(&mut cmd.value),
+                Statement::IfElseStatement(if_else) => {
+                    self.exp(&mut if_else.cond);
+                    self.block(&mut if_else.if_block.value);
+                    if let Some(else_block) = &mut if_else.else_block {
+                        self.block(&mut else_block.value);
+                    }
+                }
+                Statement::WhileStatement(while_) => {
+                    self.exp(&mut while_.cond);
+                    self.block(&mut while_.block.value);
+                }
+                Statement::LoopStatement(loop_) => {
+                    for lvalue in &mut loop_.results {
+                        if let LValue_::Mutate(e) = &mut lvalue.value {
+                            self.exp(e);
+                        }
+                    }
+                    self.block(&mut loop_.block.value);
+                }
+                Statement::EmptyStatement => (),
+            }
+        }
+    }
+
+    fn cmd(&self, cmd: &mut Cmd_) {
+        match cmd {
+            Cmd_::Assign(lvalues, e) => {
+                for lvalue in lvalues {
+                    if let LValue_::Mutate(e) = &mut lvalue.value {
+                        self.exp(e);
+                    }
+                }
+                self.exp(e);
+            }
+            Cmd_::Unpack(_, tys, _, e) => {
+                self.tys(tys);
+                self.exp(e);
+            }
+            Cmd_::Return(e) | Cmd_::Exp(e) | Cmd_::Abort(Some(e)) | Cmd_::Break(Some(e)) => {
+                self.exp(e)
+            }
+            Cmd_::Emit(handle, e) => {
+                self.exp(handle);
+                self.exp(e);
+            }
+            Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+        }
+    }
+
+    fn exp(&self, e: &mut Exp) {
+        visit_exp_mut(e, |e| match &mut e.value {
+            Exp_::FunctionCall(call, _) => match &mut call.value {
+                FunctionCall_::ModuleFunctionCall { type_actuals, .. } => self.tys(type_actuals),
+                FunctionCall_::Builtin(builtin) => match builtin {
+                    Builtin::Exists(_, tys)
+                    | Builtin::BorrowGlobal(_, _, tys)
+                    | Builtin::MoveFrom(_, tys)
+                    | Builtin::MoveToSender(_, tys) => self.tys(tys),
+                    Builtin::ToBytes(ty) => self.ty(ty),
+                    _ => (),
+                },
+            },
+            Exp_::Pack(_, tys, _) => self.tys(tys),
+            _ => (),
+        })
+    }
+}
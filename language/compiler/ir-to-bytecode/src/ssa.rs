@@ -0,0 +1,836 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Static single assignment form of function bodies.
+//!
+//! In SSA form, every local is assigned in a single place: each assignment of an original local
+//! or parameter defines a new version of it, named after it, and each use reads the version
+//! reaching it. Where control joins with different versions of a local, a [`Phi`](struct.Phi.html)
+//! defines a version taking the value of the one control comes with: after an `if`, at the head of
+//! a loop, and after a loop left by `break`s. Phis are only placed for the locals that are live
+//! where control joins, so that the argument of a phi is available on every edge it comes from.
+//!
+//! The SSA form is still a structured AST, which [`from_ssa`](fn.from_ssa.html) turns back into a
+//! body the compiler accepts by replacing each phi with assignments on the edges it joins: at the
+//! end of the branches of an `if`, before a loop and at the end of its body, and before its
+//! `continue`s and `break`s. A `while` is left with the versions at its head when its condition
+//! is false, so the versions defined after it are merged with the ones at its head. The
+//! assignments move the version they read unless it is used afterwards, in which case they copy
+//! it. Passes working on the SSA form, run with [`transform_module`](fn.transform_module.html),
+//! must keep the versions merged by a `while` from being used after it.
+//!
+//! Locals that are borrowed anywhere in the function keep their name and are not put in SSA form,
+//! since they can be assigned through a reference. Code unreachable from the entry of the body is
+//! left out.
+
+use crate::{
+    dataflow::{backward, BackwardTransfer, Live, LoopStack},
+    locals::{
+        block_vars_mut, cmd_assigned, cmd_exps, exp_vars, exp_vars_mut, lvalues_assigned, LocalUse,
+    },
+};
+use move_ir_types::ast::{
+    Block_, Cmd, Cmd_, Exp, Exp_, FunctionBody, Function_, LValue, LValue_, Loc, ModuleDefinition,
+    Program, Script, Spanned, Statement, Type, Var, Var_,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    mem,
+};
+
+pub use crate::dataflow::Join;
+
+/// Where control comes to a join from.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Edge {
+    /// The end of the first branch of an `if`.
+    Then,
+    /// The end of the `else` branch of an `if`, or its condition being false if it has none.
+    Else,
+    /// The code before a loop.
+    Entry,
+    /// The end of the body of a loop.
+    BodyEnd,
+    /// The `continue` at the given location.
+    Continue(Loc),
+    /// The `break` at the given location.
+    Break(Loc),
+    /// The condition of a `while` being false.
+    CondFalse,
+}
+
+/// A version of a local defined where control joins, taking the value of the version control
+/// comes with.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Phi {
+    pub dest: Var_,
+    /// The version of the local on each edge it is available on.
+    pub args: Vec<(Edge, Var_)>,
+}
+
+/// A function body in SSA form.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SsaFunction {
+    /// The locals of the body: the versions of the original locals and parameters, and the locals
+    /// that are not in SSA form.
+    pub locals: Vec<(Var, Type)>,
+    pub code: Block_,
+    /// The phis of each join point.
+    pub phis: BTreeMap<Join, Vec<Phi>>,
+    /// The original local or parameter of each version. The parameters are their own version at
+    /// the entry of the body.
+    pub versions: BTreeMap<Var_, Var_>,
+}
+
+/// Converts every function of the module with a body to SSA form, runs `pass` over it, and
+/// converts it back.
+pub fn transform_module(module: &mut ModuleDefinition, mut pass: impl FnMut(&mut SsaFunction)) {
+    for (_, function) in &mut module.functions {
+        transform_function(&mut function.value, &mut pass);
+    }
+}
+
+/// Converts the `main` function of the script to SSA form, runs `pass` over it, and converts it
+/// back.
+pub fn transform_script(script: &mut Script, mut pass: impl FnMut(&mut SsaFunction)) {
+    transform_function(&mut script.main.value, &mut pass);
+}
+
+/// Runs `pass` over the SSA form of every function of a program, in its modules and then in its
+/// script.
+pub fn transform_program(program: &mut Program, mut pass: impl FnMut(&mut SsaFunction)) {
+    for module in &mut program.modules {
+        transform_module(module, &mut pass);
+    }
+    transform_script(&mut program.script, &mut pass);
+}
+
+fn transform_function(function: &mut Function_, pass: &mut impl FnMut(&mut SsaFunction)) {
+    if let Some(mut ssa) = to_ssa(function) {
+        pass(&mut ssa);
+        from_ssa(ssa, function);
+    }
+}
+
+/// Converts the body of a function to SSA form, or returns none if it is native.
+pub fn to_ssa(function: &Function_) -> Option<SsaFunction> {
+    let (locals, code) = match &function.body {
+        FunctionBody::Move { locals, code } => (locals, code),
+        FunctionBody::Native => return None,
+    };
+    let formals = &function.signature.formals;
+    let mut code = code.clone();
+    let mut borrowed = BTreeSet::new();
+    block_borrows(&code, &mut borrowed);
+    let types: BTreeMap<Var_, Type> = formals
+        .iter()
+        .chain(locals.iter())
+        .map(|(var, ty)| (var.value.clone(), ty.clone()))
+        .collect();
+    let renamed: BTreeSet<Var_> = types
+        .keys()
+        .filter(|var| !borrowed.contains(*var))
+        .cloned()
+        .collect();
+
+    let mut liveness = Liveness::default();
+    backward(&mut liveness, &code, Live::new());
+
+    let entry: Versions = formals
+        .iter()
+        .map(|(var, _)| &var.value)
+        .filter(|var| renamed.contains(*var))
+        .map(|var| (var.clone(), var.clone()))
+        .collect();
+    let mut renamer = Renamer {
+        names: Names::new(types.keys()),
+        types,
+        renamed: &renamed,
+        live: liveness.joins,
+        locals: vec![],
+        versions: entry.clone(),
+        phis: BTreeMap::new(),
+        unversioned: BTreeSet::new(),
+        loops: LoopStack::default(),
+    };
+    renamer.block(&mut code, Some(entry));
+
+    // The renamed locals only remain for their uses without an assignment reaching them, which
+    // the compiler rejects.
+    let mut ssa_locals: Vec<(Var, Type)> = locals
+        .iter()
+        .filter(|(var, _)| {
+            !renamed.contains(&var.value) || renamer.unversioned.contains(&var.value)
+        })
+        .cloned()
+        .collect();
+    ssa_locals.extend(renamer.locals);
+    Some(SsaFunction {
+        locals: ssa_locals,
+        code,
+        phis: renamer.phis,
+        versions: renamer.versions,
+    })
+}
+
+/// Converts a body in SSA form back, replacing the phis with assignments, and makes it the body
+/// of the function.
+pub fn from_ssa(ssa: SsaFunction, function: &mut Function_) {
+    let SsaFunction {
+        locals,
+        mut code,
+        phis,
+        ..
+    } = ssa;
+
+    // The versions after a `while` are merged with the ones at its head.
+    let mut merged = BTreeMap::new();
+    for phi in phis
+        .iter()
+        .filter(|(join, _)| match join {
+            Join::LoopExit(_) => true,
+            Join::AfterIf(_) | Join::LoopHead(_) => false,
+        })
+        .flat_map(|(_, phis)| phis)
+    {
+        if let Some((_, head)) = phi.args.iter().find(|(edge, _)| *edge == Edge::CondFalse) {
+            merged.insert(phi.dest.clone(), head.clone());
+        }
+    }
+    let resolve = |var: &Var_| {
+        let mut var = var;
+        while let Some(other) = merged.get(var) {
+            var = other;
+        }
+        var.clone()
+    };
+
+    let mut copies: BTreeMap<(Join, Edge), Vec<(Var_, Var_)>> = BTreeMap::new();
+    for (join, phis) in &phis {
+        for phi in phis {
+            let dest = resolve(&phi.dest);
+            for (edge, arg) in &phi.args {
+                let arg = resolve(arg);
+                if *edge != Edge::CondFalse && arg != dest {
+                    copies
+                        .entry((*join, *edge))
+                        .or_default()
+                        .push((dest.clone(), arg));
+                }
+            }
+        }
+    }
+    let mut lowering = Lowering {
+        names: Names::new(
+            function
+                .signature
+                .formals
+                .iter()
+                .chain(locals.iter())
+                .map(|(var, _)| &var.value),
+        ),
+        types: locals
+            .iter()
+            .map(|(var, ty)| (var.value.clone(), ty.clone()))
+            .collect(),
+        copies,
+        temps: vec![],
+        loops: LoopStack::default(),
+    };
+    lowering.block(&mut code);
+    block_vars_mut(&mut code, &mut |var| *var = resolve(var));
+
+    let mut liveness = Liveness {
+        copies: phis
+            .values()
+            .flatten()
+            .map(|phi| resolve(&phi.dest))
+            .chain(lowering.temps.iter().map(|(var, _)| var.value.clone()))
+            .collect(),
+        ..Liveness::default()
+    };
+    backward(&mut liveness, &code, Live::new());
+    resolve_copies(&mut code, &mut vec![], &liveness.copied);
+
+    let mut locals: Vec<(Var, Type)> = locals
+        .into_iter()
+        .filter(|(var, _)| !merged.contains_key(&var.value))
+        .collect();
+    locals.extend(lowering.temps);
+    function.body = FunctionBody::Move { locals, code };
+}
+
+/// The version of each local in SSA form at a program point.
+type Versions = BTreeMap<Var_, Var_>;
+
+/// The names of the locals of a function, to name new ones after them.
+struct Names {
+    taken: BTreeSet<Var_>,
+}
+
+impl Names {
+    fn new<'a>(taken: impl Iterator<Item = &'a Var_>) -> Self {
+        Self {
+            taken: taken.cloned().collect(),
+        }
+    }
+
+    fn fresh(&mut self, var: &Var_) -> Var_ {
+        let mut index = 1;
+        loop {
+            let name = Var_::parse(format!("{}_{}", var, index))
+                .expect("a local followed by a number is a local");
+            if self.taken.insert(name.clone()) {
+                return name;
+            }
+            index += 1;
+        }
+    }
+}
+
+/// The versions control leaves a loop being renamed with.
+#[derive(Default)]
+struct LoopEdges {
+    continues: Vec<(Edge, Versions)>,
+    breaks: Vec<(Edge, Versions)>,
+}
+
+struct Renamer<'a> {
+    names: Names,
+    types: BTreeMap<Var_, Type>,
+    /// The locals put in SSA form.
+    renamed: &'a BTreeSet<Var_>,
+    /// The live locals at each join point.
+    live: BTreeMap<Join, Live>,
+    locals: Vec<(Var, Type)>,
+    versions: BTreeMap<Var_, Var_>,
+    phis: BTreeMap<Join, Vec<Phi>>,
+    /// The renamed locals used with no assignment reaching the use.
+    unversioned: BTreeSet<Var_>,
+    loops: LoopStack<LoopEdges>,
+}
+
+impl<'a> Renamer<'a> {
+    // Each of the functions below takes the versions before a piece of code, none if it is
+    // unreachable, and returns the versions after it.
+
+    fn block(&mut self, block: &mut Block_, versions: Option<Versions>) -> Option<Versions> {
+        let stmts = mem::replace(&mut block.stmts, VecDeque::new());
+        let mut versions = versions;
+        for mut statement in stmts {
+            let current = match versions {
+                Some(current) => current,
+                None => break,
+            };
+            versions = self.statement(&mut statement, current);
+            block.stmts.push_back(statement);
+        }
+        versions
+    }
+
+    fn statement(&mut self, statement: &mut Statement, versions: Versions) -> Option<Versions> {
+        match statement {
+            Statement::CommandStatement(cmd) => self.cmd(&mut cmd.value, cmd.span, versions),
+            Statement::IfElseStatement(if_else) => {
+                self.exp(&mut if_else.cond, &versions);
+                let if_versions = self.block(&mut if_else.if_block.value, Some(versions.clone()));
+                let else_versions = match &mut if_else.else_block {
+                    Some(else_block) => self.block(&mut else_block.value, Some(versions)),
+                    None => Some(versions),
+                };
+                let incoming = vec![(Edge::Then, if_versions), (Edge::Else, else_versions)];
+                self.join(Join::AfterIf(if_else.cond.span), incoming)
+            }
+            Statement::WhileStatement(while_) => {
+                let head = while_.cond.span;
+                let versions = self.loop_head(head, &while_.block.value, versions);
+                self.exp(&mut while_.cond, &versions);
+                let edges = self.loop_body(head, &mut while_.block.value, versions.clone());
+                let mut exits = vec![(Edge::CondFalse, Some(versions))];
+                exits.extend(edges.breaks.into_iter().map(|(edge, v)| (edge, Some(v))));
+                self.join(Join::LoopExit(head), exits)
+            }
+            Statement::LoopStatement(loop_) => {
+                let head = loop_.block.span;
+                let versions = self.loop_head(head, &loop_.block.value, versions);
+                let edges = self.loop_body(head, &mut loop_.block.value, versions);
+                let exits = edges
+                    .breaks
+                    .into_iter()
+                    .map(|(edge, v)| (edge, Some(v)))
+                    .collect();
+                let mut versions = self.join(Join::LoopExit(head), exits)?;
+                // The results are assigned when leaving the loop.
+                self.assign(&mut loop_.results, &mut versions);
+                Some(versions)
+            }
+            Statement::EmptyStatement => Some(versions),
+        }
+    }
+
+    /// Defines the versions at the head of a loop of the live locals its body assigns.
+    fn loop_head(&mut self, head: Loc, body: &Block_, mut versions: Versions) -> Versions {
+        let mut assigned = BTreeSet::new();
+        block_assigns(body, &mut assigned);
+        let live = self.live.get(&Join::LoopHead(head)).cloned();
+        let renamed = self.renamed;
+        let mut phis = vec![];
+        for var in assigned
+            .iter()
+            .filter(|var| renamed.contains(*var))
+            .filter(|var| live.as_ref().map_or(false, |live| live.contains(*var)))
+        {
+            let dest = self.define(var);
+            let args = versions
+                .get(var)
+                .map(|arg| (Edge::Entry, arg.clone()))
+                .into_iter()
+                .collect();
+            versions.insert(var.clone(), dest.clone());
+            phis.push(Phi { dest, args });
+        }
+        if !phis.is_empty() {
+            self.phis.insert(Join::LoopHead(head), phis);
+        }
+        versions
+    }
+
+    /// Renames the body of a loop, completing the phis at its head with the versions control goes
+    /// back to it with, and returns the edges leaving the loop.
+    fn loop_body(&mut self, head: Loc, body: &mut Block_, versions: Versions) -> LoopEdges {
+        self.loops.enter(LoopEdges::default());
+        let end = self.block(body, Some(versions));
+        let mut edges = self.loops.leave();
+        let latches: Vec<(Edge, Versions)> = end
+            .map(|end| (Edge::BodyEnd, end))
+            .into_iter()
+            .chain(mem::replace(&mut edges.continues, vec![]))
+            .collect();
+        if let Some(phis) = self.phis.get_mut(&Join::LoopHead(head)) {
+            for phi in phis {
+                let var = &self.versions[&phi.dest];
+                for (edge, versions) in &latches {
+                    if let Some(arg) = versions.get(var) {
+                        phi.args.push((*edge, arg.clone()));
+                    }
+                }
+            }
+        }
+        edges
+    }
+
+    /// Joins the versions control comes to a join point with, defining a version for each live
+    /// local it comes with different versions of.
+    fn join(&mut self, join: Join, incoming: Vec<(Edge, Option<Versions>)>) -> Option<Versions> {
+        let incoming: Vec<(Edge, Versions)> = incoming
+            .into_iter()
+            .filter_map(|(edge, versions)| versions.map(|versions| (edge, versions)))
+            .collect();
+        // The versions of the locals that are not live are never read, so any will do.
+        let mut versions = incoming.first()?.1.clone();
+        let live = self.live.get(&join).cloned().unwrap_or_default();
+        let renamed = self.renamed;
+        let mut phis = vec![];
+        for var in live.iter().filter(|var| renamed.contains(*var)) {
+            let args: Vec<(Edge, Var_)> = incoming
+                .iter()
+                .filter_map(|(edge, versions)| versions.get(var).map(|arg| (*edge, arg.clone())))
+                .collect();
+            let first = match args.first() {
+                Some((_, first)) => first.clone(),
+                None => continue,
+            };
+            if args.iter().all(|(_, arg)| *arg == first) {
+                versions.insert(var.clone(), first);
+            } else {
+                let dest = self.define(var);
+                versions.insert(var.clone(), dest.clone());
+                phis.push(Phi { dest, args });
+            }
+        }
+        if !phis.is_empty() {
+            self.phis.insert(join, phis);
+        }
+        Some(versions)
+    }
+
+    fn cmd(&mut self, cmd: &mut Cmd_, loc: Loc, mut versions: Versions) -> Option<Versions> {
+        match cmd {
+            Cmd_::Assign(lvalues, e) => {
+                self.exp(e, &versions);
+                self.assign(lvalues, &mut versions);
+            }
+            Cmd_::Unpack(_, _, bindings, e) => {
+                self.exp(e, &versions);
+                for (_, var) in bindings {
+                    self.def(&mut var.value, &mut versions);
+                }
+            }
+            Cmd_::Abort(e_opt) => {
+                if let Some(e) = e_opt {
+                    self.exp(e, &versions);
+                }
+                return None;
+            }
+            Cmd_::Return(e) => {
+                self.exp(e, &versions);
+                return None;
+            }
+            Cmd_::Break(e_opt) => {
+                if let Some(e) = e_opt {
+                    self.exp(e, &versions);
+                }
+                if let Some(edges) = self.loops.innermost_mut() {
+                    edges.breaks.push((Edge::Break(loc), versions));
+                }
+                return None;
+            }
+            Cmd_::Continue => {
+                if let Some(edges) = self.loops.innermost_mut() {
+                    edges.continues.push((Edge::Continue(loc), versions));
+                }
+                return None;
+            }
+            Cmd_::Emit(handle, e) => {
+                self.exp(handle, &versions);
+                self.exp(e, &versions);
+            }
+            Cmd_::Exp(e) => self.exp(e, &versions),
+        }
+        Some(versions)
+    }
+
+    fn assign(&mut self, lvalues: &mut [LValue], versions: &mut Versions) {
+        for lvalue in lvalues.iter_mut() {
+            if let LValue_::Mutate(e) = &mut lvalue.value {
+                self.exp(e, versions);
+            }
+        }
+        for lvalue in lvalues {
+            if let LValue_::Var(var) = &mut lvalue.value {
+                self.def(&mut var.value, versions);
+            }
+        }
+    }
+
+    fn def(&mut self, var: &mut Var_, versions: &mut Versions) {
+        if self.renamed.contains(var) {
+            let version = self.define(var);
+            versions.insert(var.clone(), version.clone());
+            *var = version;
+        }
+    }
+
+    fn define(&mut self, var: &Var_) -> Var_ {
+        let version = self.names.fresh(var);
+        self.versions.insert(version.clone(), var.clone());
+        self.locals
+            .push((Spanned::no_loc(version.clone()), self.types[var].clone()));
+        version
+    }
+
+    fn exp(&mut self, e: &mut Exp, versions: &Versions) {
+        let renamed = self.renamed;
+        let unversioned = &mut self.unversioned;
+        exp_vars_mut(e, |var| {
+            if let Some(version) = versions.get(var) {
+                *var = version.clone();
+            } else if renamed.contains(var) {
+                unversioned.insert(var.clone());
+            }
+        });
+    }
+}
+
+struct Lowering {
+    names: Names,
+    types: BTreeMap<Var_, Type>,
+    /// The assignments replacing the phis, by the join point and edge they are on.
+    copies: BTreeMap<(Join, Edge), Vec<(Var_, Var_)>>,
+    temps: Vec<(Var, Type)>,
+    /// The head of each enclosing loop.
+    loops: LoopStack<Loc>,
+}
+
+impl Lowering {
+    fn block(&mut self, block: &mut Block_) {
+        let stmts = mem::replace(&mut block.stmts, VecDeque::new());
+        for mut statement in stmts {
+            match &mut statement {
+                Statement::CommandStatement(cmd) => {
+                    if let Some(head) = self.loops.innermost().cloned() {
+                        match &cmd.value {
+                            Cmd_::Break(_) => self.emit(
+                                &mut block.stmts,
+                                (Join::LoopExit(head), Edge::Break(cmd.span)),
+                            ),
+                            Cmd_::Continue => self.emit(
+                                &mut block.stmts,
+                                (Join::LoopHead(head), Edge::Continue(cmd.span)),
+                            ),
+                            _ => (),
+                        }
+                    }
+                }
+                Statement::IfElseStatement(if_else) => {
+                    let join = Join::AfterIf(if_else.cond.span);
+                    self.block(&mut if_else.if_block.value);
+                    self.emit(&mut if_else.if_block.value.stmts, (join, Edge::Then));
+                    if if_else.else_block.is_none() && self.copies.contains_key(&(join, Edge::Else))
+                    {
+                        if_else.else_block = Some(Spanned::no_loc(Block_ {
+                            stmts: VecDeque::new(),
+                        }));
+                    }
+                    if let Some(else_block) = &mut if_else.else_block {
+                        self.block(&mut else_block.value);
+                        self.emit(&mut else_block.value.stmts, (join, Edge::Else));
+                    }
+                }
+                Statement::WhileStatement(while_) => {
+                    let head = while_.cond.span;
+                    self.loop_(head, &mut block.stmts, &mut while_.block.value);
+                }
+                Statement::LoopStatement(loop_) => {
+                    let head = loop_.block.span;
+                    self.loop_(head, &mut block.stmts, &mut loop_.block.value);
+                }
+                Statement::EmptyStatement => (),
+            }
+            block.stmts.push_back(statement);
+        }
+    }
+
+    fn loop_(&mut self, head: Loc, before: &mut VecDeque<Statement>, body: &mut Block_) {
+        self.emit(before, (Join::LoopHead(head), Edge::Entry));
+        self.loops.enter(head);
+        self.block(body);
+        self.loops.leave();
+        self.emit(&mut body.stmts, (Join::LoopHead(head), Edge::BodyEnd));
+    }
+
+    /// Appends the assignments on an edge, which are made in parallel.
+    fn emit(&mut self, stmts: &mut VecDeque<Statement>, edge: (Join, Edge)) {
+        let copies = match self.copies.get(&edge) {
+            Some(copies) => copies.clone(),
+            None => return,
+        };
+        let dests: BTreeSet<&Var_> = copies.iter().map(|(dest, _)| dest).collect();
+        if copies.iter().any(|(_, src)| dests.contains(src)) {
+            // Go through temporaries, so that no assignment reads a local another one has
+            // already assigned.
+            let temps: Vec<Var_> = copies
+                .iter()
+                .map(|(dest, src)| {
+                    let temp = self.names.fresh(dest);
+                    self.temps
+                        .push((Spanned::no_loc(temp.clone()), self.types[dest].clone()));
+                    stmts.push_back(copy(&temp, src));
+                    temp
+                })
+                .collect();
+            for ((dest, _), temp) in copies.iter().zip(&temps) {
+                stmts.push_back(copy(dest, temp));
+            }
+        } else {
+            for (dest, src) in &copies {
+                stmts.push_back(copy(dest, src));
+            }
+        }
+    }
+}
+
+/// Returns `dest = move(src);`, which liveness turns into a copy if `src` is used afterwards.
+fn copy(dest: &Var_, src: &Var_) -> Statement {
+    let lvalue = Spanned::no_loc(LValue_::Var(Spanned::no_loc(dest.clone())));
+    let src = Spanned::no_loc(Exp_::Move(Spanned::no_loc(src.clone())));
+    Statement::CommandStatement(Spanned::no_loc(Cmd_::Assign(vec![lvalue], src)))
+}
+
+#[derive(Default)]
+struct Liveness {
+    /// The live locals at each join point.
+    joins: BTreeMap<Join, Live>,
+    /// The locals assigned by the assignments replacing phis, which copy their source if it is
+    /// live after them and move it otherwise.
+    copies: BTreeSet<Var_>,
+    /// Whether each of these assignments copies its source. They have no location, and are found
+    /// by their position in the body instead. Code in loops is visited several times; the last
+    /// visit, made with the final live locals of the loop, decides.
+    copied: BTreeMap<Vec<usize>, bool>,
+}
+
+impl BackwardTransfer for Liveness {
+    type State = Live;
+
+    fn cmd_at(&mut self, position: &[usize], cmd: &Cmd, live: &mut Live) {
+        if let Cmd_::Assign(lvalues, e) = &cmd.value {
+            if let ([lvalue], Exp_::Move(src)) | ([lvalue], Exp_::Copy(src)) =
+                (lvalues.as_slice(), &e.value)
+            {
+                match &lvalue.value {
+                    LValue_::Var(dest) if self.copies.contains(&dest.value) => {
+                        self.copied
+                            .insert(position.to_vec(), live.contains(&src.value));
+                    }
+                    _ => (),
+                }
+            }
+        }
+        self.cmd(cmd, live)
+    }
+
+    fn cmd(&mut self, cmd: &Cmd, live: &mut Live) {
+        for var in cmd_assigned(&cmd.value) {
+            live.remove(var);
+        }
+        for e in cmd_exps(&cmd.value) {
+            reads(e, live);
+        }
+    }
+
+    fn cond(&mut self, cond: &Exp, live: &mut Live) {
+        reads(cond, live);
+    }
+
+    fn loop_results(&mut self, results: &[LValue], live: &mut Live) {
+        for var in lvalues_assigned(results) {
+            live.remove(var);
+        }
+        for lvalue in results {
+            if let LValue_::Mutate(e) = &lvalue.value {
+                reads(e, live);
+            }
+        }
+    }
+
+    fn join(&mut self, join: Join, live: &Live) {
+        self.joins.insert(join, live.clone());
+    }
+}
+
+fn reads(e: &Exp, live: &mut Live) {
+    exp_vars(e, |var, _| {
+        live.insert(var.clone());
+    });
+}
+
+/// Makes the assignments replacing phis copy or move their source, as liveness decided. The
+/// positions of the commands of the block start with `position`, as `BackwardTransfer::cmd_at`
+/// describes.
+fn resolve_copies(
+    block: &mut Block_,
+    position: &mut Vec<usize>,
+    copied: &BTreeMap<Vec<usize>, bool>,
+) {
+    for (index, statement) in block.stmts.iter_mut().enumerate() {
+        position.push(index);
+        match statement {
+            Statement::CommandStatement(cmd) => {
+                let copy = copied.get(position.as_slice());
+                if let (Some(copy), Cmd_::Assign(_, e)) = (copy, &mut cmd.value) {
+                    if let Exp_::Move(src) | Exp_::Copy(src) = &e.value {
+                        let src = src.clone();
+                        e.value = if *copy {
+                            Exp_::Copy(src)
+                        } else {
+                            Exp_::Move(src)
+                        };
+                    }
+                }
+            }
+            Statement::IfElseStatement(if_else) => {
+                nested_resolve_copies(0, &mut if_else.if_block.value, position, copied);
+                if let Some(else_block) = &mut if_else.else_block {
+                    nested_resolve_copies(1, &mut else_block.value, position, copied);
+                }
+            }
+            Statement::WhileStatement(while_) => {
+                nested_resolve_copies(0, &mut while_.block.value, position, copied)
+            }
+            Statement::LoopStatement(loop_) => {
+                nested_resolve_copies(0, &mut loop_.block.value, position, copied)
+            }
+            Statement::EmptyStatement => (),
+        }
+        position.pop();
+    }
+}
+
+/// Resolves the copies of the `nth` block of the statement at `position`.
+fn nested_resolve_copies(
+    nth: usize,
+    block: &mut Block_,
+    position: &mut Vec<usize>,
+    copied: &BTreeMap<Vec<usize>, bool>,
+) {
+    position.push(nth);
+    resolve_copies(block, position, copied);
+    position.pop();
+}
+
+/// Collects the locals a block borrows.
+fn block_borrows(block: &Block_, borrowed: &mut BTreeSet<Var_>) {
+    let mut borrows = |e: &Exp| {
+        exp_vars(e, |var, use_| {
+            if use_ == LocalUse::Borrow {
+                borrowed.insert(var.clone());
+            }
+        })
+    };
+    let mut blocks = vec![block];
+    while let Some(block) = blocks.pop() {
+        for statement in &block.stmts {
+            match statement {
+                Statement::CommandStatement(cmd) => {
+                    for e in cmd_exps(&cmd.value) {
+                        borrows(e);
+                    }
+                }
+                Statement::IfElseStatement(if_else) => {
+                    borrows(&if_else.cond);
+                    blocks.push(&if_else.if_block.value);
+                    if let Some(else_block) = &if_else.else_block {
+                        blocks.push(&else_block.value);
+                    }
+                }
+                Statement::WhileStatement(while_) => {
+                    borrows(&while_.cond);
+                    blocks.push(&while_.block.value);
+                }
+                Statement::LoopStatement(loop_) => {
+                    for lvalue in &loop_.results {
+                        if let LValue_::Mutate(e) = &lvalue.value {
+                            borrows(e);
+                        }
+                    }
+                    blocks.push(&loop_.block.value);
+                }
+                Statement::EmptyStatement => (),
+            }
+        }
+    }
+}
+
+/// Collects the locals a block assigns.
+fn block_assigns(block: &Block_, assigned: &mut BTreeSet<Var_>) {
+    for statement in &block.stmts {
+        match statement {
+            Statement::CommandStatement(cmd) => {
+                assigned.extend(cmd_assigned(&cmd.value).into_iter().cloned())
+            }
+            Statement::IfElseStatement(if_else) => {
+                block_assigns(&if_else.if_block.value, assigned);
+                if let Some(else_block) = &if_else.else_block {
+                    block_assigns(&else_block.value, assigned);
+                }
+            }
+            Statement::WhileStatement(while_) => block_assigns(&while_.block.value, assigned),
+            Statement::LoopStatement(loop_) => {
+                assigned.extend(lvalues_assigned(&loop_.results).into_iter().cloned());
+                block_assigns(&loop_.block.value, assigned);
+            }
+            Statement::EmptyStatement => (),
+        }
+    }
+}
@@ -0,0 +1,349 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Taint analysis of the values coming from transactions.
+//!
+//! The parameters of a function are tainted as transaction arguments, since a transaction can pass
+//! any value to a script, and to a module through its public functions. The values read from
+//! global storage, through `borrow_global` or `move_from`, are tainted as stored values. Taint
+//! flows through assignments, unpacks, references and calls, whose results are tainted by their
+//! arguments, using the [forward dataflow engine](../dataflow/index.html). An assignment to a
+//! local replaces its taint, while a write through a reference adds to the taint of the local
+//! holding it.
+//!
+//! Two kinds of flows are reported:
+//! - a transaction argument moved to the sender's storage with `move_to_sender`;
+//! - arithmetic combining a transaction argument with a stored value, such as a balance being
+//!   credited or debited by an amount given by the sender.
+//!
+//! The analysis is intraprocedural: the parameters of internal functions are tainted as if they
+//! came from transactions too.
+
+use crate::{
+    dataflow::{forward, Dataflow, Lattice, Transfer},
+    locals::cmd_exps,
+};
+use move_ir_types::ast::{
+    BinOp, Block_, Builtin, Cmd, Cmd_, Exp, Exp_, Function, FunctionBody, FunctionCall_, LValue,
+    LValue_, Loc, ModuleDefinition, Script, Statement, StructName, Var_,
+};
+use std::{collections::BTreeMap, fmt};
+
+/// Where a value may come from.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Taint {
+    /// The value may be derived from a transaction argument.
+    pub argument: bool,
+    /// The value may be derived from a value read from global storage.
+    pub storage: bool,
+}
+
+impl Taint {
+    fn union(self, other: Taint) -> Taint {
+        Taint {
+            argument: self.argument || other.argument,
+            storage: self.storage || other.storage,
+        }
+    }
+
+    fn is_clean(self) -> bool {
+        !self.argument && !self.storage
+    }
+}
+
+/// The taint of the locals of a function. Untainted locals are left out.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Taints(pub BTreeMap<Var_, Taint>);
+
+impl Taints {
+    pub fn get(&self, var: &Var_) -> Taint {
+        self.0.get(var).cloned().unwrap_or_default()
+    }
+
+    fn set(&mut self, var: &Var_, taint: Taint) {
+        if taint.is_clean() {
+            self.0.remove(var);
+        } else {
+            self.0.insert(var.clone(), taint);
+        }
+    }
+
+    fn add(&mut self, var: &Var_, taint: Taint) {
+        let taint = self.get(var).union(taint);
+        self.set(var, taint);
+    }
+
+    /// Returns the taint of the value of an expression.
+    pub fn exp(&self, e: &Exp) -> Taint {
+        match &e.value {
+            Exp_::Value(_) | Exp_::Placeholder(_) => Taint::default(),
+            Exp_::Move(var) | Exp_::Copy(var) | Exp_::Var(var) | Exp_::BorrowLocal(_, var) => {
+                self.get(&var.value)
+            }
+            Exp_::Dereference(e) | Exp_::UnaryExp(_, e) | Exp_::Borrow { exp: e, .. } => {
+                self.exp(e)
+            }
+            Exp_::BinopExp(lhs, _, rhs) => self.exp(lhs).union(self.exp(rhs)),
+            Exp_::Pack(_, _, fields) => fields
+                .iter()
+                .fold(Taint::default(), |taint, (_, e)| taint.union(self.exp(e))),
+            Exp_::ExprList(exps) => exps
+                .iter()
+                .fold(Taint::default(), |taint, e| taint.union(self.exp(e))),
+            Exp_::FunctionCall(call, args) => {
+                let taint = self.exp(args);
+                match &call.value {
+                    FunctionCall_::Builtin(Builtin::BorrowGlobal(_, _, _))
+                    | FunctionCall_::Builtin(Builtin::MoveFrom(_, _)) => Taint {
+                        storage: true,
+                        ..taint
+                    },
+                    _ => taint,
+                }
+            }
+        }
+    }
+
+    /// Assigns values of the given taint to the lvalues.
+    fn assign(&mut self, lvalues: &[LValue], taints: &[Taint]) {
+        for (lvalue, taint) in lvalues.iter().zip(taints) {
+            match &lvalue.value {
+                LValue_::Var(var) => self.set(&var.value, *taint),
+                LValue_::Mutate(e) => {
+                    if let Some(var) = root(e) {
+                        self.add(var, *taint);
+                    }
+                }
+                LValue_::Pop => (),
+            }
+        }
+    }
+
+    /// Returns the taint of each of the values of an expression assigned to `arity` lvalues.
+    fn values(&self, e: &Exp, arity: usize) -> Vec<Taint> {
+        match &e.value {
+            Exp_::ExprList(exps) if exps.len() == arity => {
+                exps.iter().map(|e| self.exp(e)).collect()
+            }
+            _ => vec![self.exp(e); arity],
+        }
+    }
+}
+
+impl Lattice for Taints {
+    fn join(&mut self, other: &Self) {
+        for (var, taint) in &other.0 {
+            self.add(var, *taint);
+        }
+    }
+}
+
+/// Returns the local a reference is rooted in, if any.
+fn root(e: &Exp) -> Option<&Var_> {
+    match &e.value {
+        Exp_::Move(var) | Exp_::Copy(var) | Exp_::Var(var) | Exp_::BorrowLocal(_, var) => {
+            Some(&var.value)
+        }
+        Exp_::Borrow { exp, .. } => root(exp),
+        _ => None,
+    }
+}
+
+/// The transfer function of the taint analysis.
+pub struct TaintTransfer;
+
+impl Transfer for TaintTransfer {
+    type State = Taints;
+
+    fn cmd(&self, cmd: &Cmd, taints: &mut Taints) {
+        match &cmd.value {
+            Cmd_::Assign(lvalues, e) => {
+                let values = taints.values(e, lvalues.len());
+                taints.assign(lvalues, &values);
+            }
+            Cmd_::Unpack(_, _, fields, e) => {
+                let taint = taints.exp(e);
+                for (_, var) in fields {
+                    taints.set(&var.value, taint);
+                }
+            }
+            Cmd_::Return(_)
+            | Cmd_::Abort(_)
+            | Cmd_::Break(_)
+            | Cmd_::Continue
+            | Cmd_::Emit(_, _)
+            | Cmd_::Exp(_) => (),
+        }
+    }
+
+    fn break_value(&self, results: &[LValue], value: &Exp, taints: &mut Taints) {
+        let values = taints.values(value, results.len());
+        taints.assign(results, &values);
+    }
+}
+
+/// A flow of a transaction argument into a sensitive operation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaintedFlow {
+    /// The location of the operation.
+    pub loc: Loc,
+    pub sink: Sink,
+}
+
+/// The sensitive operations.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Sink {
+    /// `move_to_sender<T>(e)`, with `e` derived from a transaction argument.
+    MoveToSender(StructName),
+    /// Arithmetic combining a transaction argument with a stored value.
+    Arithmetic(BinOp),
+}
+
+impl fmt::Display for TaintedFlow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.sink {
+            Sink::MoveToSender(name) => write!(
+                f,
+                "transaction argument moved to the sender's storage by `move_to_sender<{}>`",
+                name
+            ),
+            Sink::Arithmetic(op) => write!(
+                f,
+                "transaction argument combined with a value from global storage by `{}`",
+                op
+            ),
+        }
+    }
+}
+
+/// Returns the tainted flows of every function of the module, in source order.
+pub fn module_taint(module: &ModuleDefinition) -> Vec<TaintedFlow> {
+    module
+        .functions
+        .iter()
+        .flat_map(|(_, function)| function_taint(function))
+        .collect()
+}
+
+/// Returns the tainted flows of the script's `main` function, in source order.
+pub fn script_taint(script: &Script) -> Vec<TaintedFlow> {
+    function_taint(&script.main)
+}
+
+/// Returns the tainted flows of a function, in source order.
+pub fn function_taint(function: &Function) -> Vec<TaintedFlow> {
+    let code = match &function.value.body {
+        FunctionBody::Move { code, .. } => code,
+        FunctionBody::Native => return vec![],
+    };
+    let mut entry = Taints::default();
+    for (var, _) in &function.value.signature.formals {
+        entry.set(
+            &var.value,
+            Taint {
+                argument: true,
+                storage: false,
+            },
+        );
+    }
+    let dataflow = forward(&TaintTransfer, code, entry);
+    let mut sinks = Sinks {
+        dataflow: &dataflow,
+        flows: vec![],
+    };
+    sinks.block(code);
+    let mut flows = sinks.flows;
+    flows.sort_by_key(|flow| flow.loc.start());
+    flows
+}
+
+/// Finds the sinks reached by tainted values, given the taint before each command.
+struct Sinks<'a> {
+    dataflow: &'a Dataflow<Taints>,
+    flows: Vec<TaintedFlow>,
+}
+
+impl<'a> Sinks<'a> {
+    fn block(&mut self, block: &Block_) {
+        for statement in &block.stmts {
+            match statement {
+                Statement::CommandStatement(cmd) => {
+                    if let Some(taints) = self.dataflow.state_before(cmd.span) {
+                        for e in cmd_exps(&cmd.value) {
+                            self.exp(e, taints);
+                        }
+                    }
+                }
+                Statement::IfElseStatement(if_else) => {
+                    self.cond(&if_else.cond);
+                    self.block(&if_else.if_block.value);
+                    if let Some(else_block) = &if_else.else_block {
+                        self.block(&else_block.value);
+                    }
+                }
+                Statement::WhileStatement(while_) => {
+                    self.cond(&while_.cond);
+                    self.block(&while_.block.value);
+                }
+                Statement::LoopStatement(loop_) => self.block(&loop_.block.value),
+                Statement::EmptyStatement => (),
+            }
+        }
+    }
+
+    fn cond(&mut self, cond: &Exp) {
+        if let Some(taints) = self.dataflow.state_before(cond.span) {
+            self.exp(cond, taints);
+        }
+    }
+
+    fn exp(&mut self, e: &Exp, taints: &Taints) {
+        match &e.value {
+            Exp_::FunctionCall(call, args) => {
+                if let FunctionCall_::Builtin(Builtin::MoveToSender(name, _)) = &call.value {
+                    if taints.exp(args).argument {
+                        self.flows.push(TaintedFlow {
+                            loc: e.span,
+                            sink: Sink::MoveToSender(name.clone()),
+                        });
+                    }
+                }
+                self.exp(args, taints);
+            }
+            Exp_::BinopExp(lhs, op, rhs) => {
+                if let BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod = op {
+                    let (lhs_taint, rhs_taint) = (taints.exp(lhs), taints.exp(rhs));
+                    if (lhs_taint.argument && rhs_taint.storage)
+                        || (lhs_taint.storage && rhs_taint.argument)
+                    {
+                        self.flows.push(TaintedFlow {
+                            loc: e.span,
+                            sink: Sink::Arithmetic(op.clone()),
+                        });
+                    }
+                }
+                self.exp(lhs, taints);
+                self.exp(rhs, taints);
+            }
+            Exp_::Dereference(e) | Exp_::UnaryExp(_, e) | Exp_::Borrow { exp: e, .. } => {
+                self.exp(e, taints)
+            }
+            Exp_::Pack(_, _, fields) => {
+                for (_, e) in fields {
+                    self.exp(e, taints);
+                }
+            }
+            Exp_::ExprList(exps) => {
+                for e in exps {
+                    self.exp(e, taints);
+                }
+            }
+            Exp_::Value(_)
+            | Exp_::Move(_)
+            | Exp_::Copy(_)
+            | Exp_::Var(_)
+            | Exp_::BorrowLocal(_, _)
+            | Exp_::Placeholder(_) => (),
+        }
+    }
+}
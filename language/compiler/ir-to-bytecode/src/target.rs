@@ -0,0 +1,287 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Versions of the bytecode a module can be compiled for.
+//!
+//! The instruction set of the VM grew after its first release. Version 1 of the bytecode only
+//! knows `u64` integers; version 2 adds the `u8` and `u128` types with the instructions loading
+//! them, the casts between integer types and the shifts. Both are serialized with the same
+//! header, so a VM that was not upgraded only rejects a module using a newer construct when it
+//! loads it.
+//!
+//! Compiling for an older version instead checks the compiled code against it and reports every
+//! construct that needs a newer one where it is in the IR source: the instructions, and the types
+//! of fields, locals and signatures, including the signatures of the imported functions called and
+//! the type arguments of instructions.
+//!
+//! The code generated for an older version does without the newer instructions where older ones
+//! compute the same values: a shift of a `u64` by a constant is compiled to a division, or to a
+//! remainder and a multiplication, and a cast of a `u64` to `u64` to nothing, including in the
+//! integer builtins. The code generated for the latest version, the default, uses the newer
+//! instructions.
+
+use crate::errors::TargetError;
+use anyhow::{bail, Result};
+use bytecode_source_map::source_map::ModuleSourceMap;
+use move_ir_types::ast::Loc;
+use vm::{
+    access::ModuleAccess,
+    file_format::{
+        Bytecode, CodeOffset, CompiledModule, CompiledScript, FieldDefinitionIndex,
+        FunctionDefinition, FunctionDefinitionIndex, LocalsSignatureIndex, SignatureToken,
+        StructDefinitionIndex, StructFieldInformation, TableIndex,
+    },
+};
+
+/// The oldest version of the bytecode the compiler can target.
+pub const MIN_BYTECODE_VERSION: u64 = 1;
+
+/// The version of the bytecode the compiler targets by default, the newest one.
+pub const LATEST_BYTECODE_VERSION: u64 = 2;
+
+/// Fails if the compiler cannot target `version`.
+pub fn check_version(version: u64) -> Result<()> {
+    if version < MIN_BYTECODE_VERSION || version > LATEST_BYTECODE_VERSION {
+        bail!(
+            "Unsupported bytecode version {}, the supported versions are {} to {}",
+            version,
+            MIN_BYTECODE_VERSION,
+            LATEST_BYTECODE_VERSION
+        )
+    }
+    Ok(())
+}
+
+/// The first version of the bytecode with `instruction`.
+pub fn instruction_version(instruction: &Bytecode) -> u64 {
+    match instruction {
+        Bytecode::LdU8(_)
+        | Bytecode::LdU128(_)
+        | Bytecode::CastU8
+        | Bytecode::CastU64
+        | Bytecode::CastU128
+        | Bytecode::Shl
+        | Bytecode::Shr => 2,
+        _ => 1,
+    }
+}
+
+/// The first version of the bytecode with every type `token` is made of.
+pub fn type_version(token: &SignatureToken) -> u64 {
+    newest_type(token).map_or(MIN_BYTECODE_VERSION, |(_, version)| version)
+}
+
+/// Checks the code of `module`, compiled with `source_map`, against version `target` of the
+/// bytecode.
+pub fn check_module_target(
+    module: &CompiledModule,
+    source_map: &ModuleSourceMap<Loc>,
+    target: u64,
+) -> Vec<TargetError> {
+    let mut checker = Checker {
+        module,
+        source_map,
+        target,
+        errors: vec![],
+    };
+    for (index, struct_def) in module.struct_defs().iter().enumerate() {
+        checker.struct_def(
+            StructDefinitionIndex(index as TableIndex),
+            &struct_def.field_information,
+        );
+    }
+    for (index, function_def) in module.function_defs().iter().enumerate() {
+        checker.function_def(FunctionDefinitionIndex(index as TableIndex), function_def);
+    }
+    checker.errors
+}
+
+/// Checks the code of `script`, compiled with `source_map`, against version `target` of the
+/// bytecode.
+pub fn check_script_target(
+    script: &CompiledScript,
+    source_map: &ModuleSourceMap<Loc>,
+    target: u64,
+) -> Vec<TargetError> {
+    check_module_target(&script.clone().into_module(), source_map, target)
+}
+
+// The primitive type `token` contains that needs the newest version of the bytecode, if it needs
+// more than the first one, with that version.
+fn newest_type(token: &SignatureToken) -> Option<(&'static str, u64)> {
+    match token {
+        SignatureToken::U8 => Some(("u8", 2)),
+        SignatureToken::U128 => Some(("u128", 2)),
+        SignatureToken::Reference(inner) | SignatureToken::MutableReference(inner) => {
+            newest_type(inner)
+        }
+        SignatureToken::Struct(_, type_actuals) => type_actuals
+            .iter()
+            .filter_map(newest_type)
+            .max_by_key(|(_, version)| *version),
+        SignatureToken::Bool
+        | SignatureToken::U64
+        | SignatureToken::ByteArray
+        | SignatureToken::Address
+        | SignatureToken::TypeParameter(_) => None,
+    }
+}
+
+// The name of an instruction, without its operands.
+fn instruction_name(instruction: &Bytecode) -> String {
+    let text = format!("{:?}", instruction);
+    match text.find('(') {
+        Some(end) => text[..end].to_string(),
+        None => text,
+    }
+}
+
+// The type arguments of an instruction, if it has any.
+fn type_actuals(instruction: &Bytecode) -> Option<LocalsSignatureIndex> {
+    match instruction {
+        Bytecode::Call(_, type_actuals)
+        | Bytecode::Pack(_, type_actuals)
+        | Bytecode::Unpack(_, type_actuals)
+        | Bytecode::MutBorrowGlobal(_, type_actuals)
+        | Bytecode::ImmBorrowGlobal(_, type_actuals)
+        | Bytecode::Exists(_, type_actuals)
+        | Bytecode::MoveFrom(_, type_actuals)
+        | Bytecode::MoveToSender(_, type_actuals) => Some(*type_actuals),
+        _ => None,
+    }
+}
+
+struct Checker<'a> {
+    module: &'a CompiledModule,
+    source_map: &'a ModuleSourceMap<Loc>,
+    target: u64,
+    errors: Vec<TargetError>,
+}
+
+impl<'a> Checker<'a> {
+    fn struct_def(&mut self, index: StructDefinitionIndex, fields: &StructFieldInformation) {
+        let (field_count, first) = match fields {
+            StructFieldInformation::Native => return,
+            StructFieldInformation::Declared {
+                field_count,
+                fields,
+            } => (*field_count, fields.0),
+        };
+        let decl_loc = self
+            .source_map
+            .get_struct_source_map(index)
+            .map(|map| map.decl_location)
+            .unwrap_or_default();
+        let module = self.module;
+        for nth in 0..field_count {
+            let field = module.field_def_at(FieldDefinitionIndex(first + nth));
+            let loc = self
+                .source_map
+                .get_struct_field_name(index, FieldDefinitionIndex(nth))
+                .unwrap_or(decl_loc);
+            let name = module.identifier_at(field.name);
+            let token = &module.type_signature_at(field.signature).0;
+            self.type_(token, loc, || format!("of field `{}`", name));
+        }
+    }
+
+    fn function_def(&mut self, index: FunctionDefinitionIndex, function_def: &FunctionDefinition) {
+        let module = self.module;
+        let handle = module.function_handle_at(function_def.function);
+        let name = module.identifier_at(handle.name);
+        let signature = module.function_signature_at(handle.signature);
+        let function_map = self.source_map.get_function_source_map(index).ok();
+        let decl_loc = function_map.map_or_else(Loc::default, |map| map.decl_location);
+        self.signature(
+            &signature.arg_types,
+            &signature.return_types,
+            decl_loc,
+            || format!("in the signature of `{}`", name),
+        );
+        if function_def.is_native() {
+            return;
+        }
+        let locals = &module.locals_signature_at(function_def.code.locals).0;
+        for (nth, token) in locals.iter().enumerate().skip(signature.arg_types.len()) {
+            let (local, loc) = match function_map.and_then(|map| map.get_local_name(nth as u64)) {
+                Some((local, loc)) => (local.to_string(), loc),
+                None => (format!("#{}", nth), decl_loc),
+            };
+            self.type_(token, loc, || format!("of local `{}` of `{}`", local, name));
+        }
+        for (offset, instruction) in function_def.code.code.iter().enumerate() {
+            let loc = function_map
+                .and_then(|map| map.get_code_location(offset as CodeOffset))
+                .unwrap_or(decl_loc);
+            let needed = instruction_version(instruction);
+            if needed > self.target {
+                self.errors.push(TargetError {
+                    loc,
+                    construct: format!("instruction `{}`", instruction_name(instruction)),
+                    needed,
+                    target: self.target,
+                });
+            }
+            if let Some(type_actuals) = type_actuals(instruction) {
+                for token in &module.locals_signature_at(type_actuals).0 {
+                    self.type_(token, loc, || {
+                        format!(
+                            "in the type arguments of `{}`",
+                            instruction_name(instruction)
+                        )
+                    });
+                }
+            }
+            if let Bytecode::Call(callee, _) = instruction {
+                let callee = module.function_handle_at(*callee);
+                if callee.module.0 != CompiledModule::IMPLEMENTED_MODULE_INDEX {
+                    let callee_module = module.module_handle_at(callee.module);
+                    let callee_name = format!(
+                        "{}.{}",
+                        module.identifier_at(callee_module.name),
+                        module.identifier_at(callee.name)
+                    );
+                    let signature = module.function_signature_at(callee.signature);
+                    self.signature(&signature.arg_types, &signature.return_types, loc, || {
+                        format!("in the signature of `{}`", callee_name)
+                    });
+                }
+            }
+        }
+    }
+
+    // Reports the newest type of a signature, once.
+    fn signature(
+        &mut self,
+        arg_types: &[SignatureToken],
+        return_types: &[SignatureToken],
+        loc: Loc,
+        what: impl FnOnce() -> String,
+    ) {
+        let newest = arg_types
+            .iter()
+            .chain(return_types)
+            .filter_map(newest_type)
+            .max_by_key(|(_, version)| *version);
+        if let Some((type_name, needed)) = newest {
+            self.report(type_name, needed, loc, what);
+        }
+    }
+
+    fn type_(&mut self, token: &SignatureToken, loc: Loc, what: impl FnOnce() -> String) {
+        if let Some((type_name, needed)) = newest_type(token) {
+            self.report(type_name, needed, loc, what);
+        }
+    }
+
+    fn report(&mut self, type_name: &str, needed: u64, loc: Loc, what: impl FnOnce() -> String) {
+        if needed > self.target {
+            self.errors.push(TargetError {
+                loc,
+                construct: format!("type `{}` {}", type_name, what()),
+                needed,
+                target: self.target,
+            });
+        }
+    }
+}
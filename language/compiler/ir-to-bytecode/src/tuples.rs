@@ -0,0 +1,391 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Tuple types.
+//!
+//! The bytecode has no tuples, but its functions take and return several values, which tuples
+//! name together: a local, parameter or return value of type `(u64, bool)` is lowered to one
+//! local, parameter or return value per element. Using a tuple local then gives all of its
+//! elements, e.g. `a, b = move(t);` destructures `t`, and `t = (1, true);` assigns it.
+//!
+//! Tuples cannot be borrowed, nor be the type of a field or a type actual. The number of values
+//! of an assignment or a `return` involving a tuple is checked while lowering, unless it depends
+//! on a call.
+
+use crate::errors::TupleError;
+use libra_types::identifier::Identifier;
+use move_ir_types::ast::{
+    visit_exp_mut, Block_, Cmd_, Exp, Exp_, Function, FunctionBody, LValue, LValue_, Loc,
+    ModuleDefinition, Program, Script, Spanned, Statement, StructDefinitionFields, Type, Var, Var_,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Lowers the tuples of every module and then of the script of a program.
+pub fn lower_program(program: &mut Program) -> Vec<TupleError> {
+    let mut errors = vec![];
+    for module in &mut program.modules {
+        errors.extend(lower_module(module));
+    }
+    errors.extend(lower_script(&mut program.script));
+    errors
+}
+
+/// Lowers the tuples of the functions of a module, and rejects the fields of its structs that
+/// have a tuple type.
+pub fn lower_module(module: &mut ModuleDefinition) -> Vec<TupleError> {
+    let mut errors = vec![];
+    for def in &module.structs {
+        if let StructDefinitionFields::Move { fields } = &def.value.fields {
+            for (field, ty) in fields {
+                if contains_tuple(ty) {
+                    errors.push(TupleError {
+                        loc: field.span,
+                        message: format!("field {} cannot have the tuple type {}", field, ty),
+                    });
+                }
+            }
+        }
+    }
+    for (_, function) in &mut module.functions {
+        lower_function(function, &mut errors);
+    }
+    errors
+}
+
+/// Lowers the tuples of the `main` function of a script.
+pub fn lower_script(script: &mut Script) -> Vec<TupleError> {
+    let mut errors = vec![];
+    lower_function(&mut script.main, &mut errors);
+    errors
+}
+
+fn lower_function(function: &mut Function, errors: &mut Vec<TupleError>) {
+    let mut names: BTreeSet<Var_> = function
+        .value
+        .signature
+        .formals
+        .iter()
+        .map(|(var, _)| var.value.clone())
+        .collect();
+    if let FunctionBody::Move { locals, .. } = &function.value.body {
+        names.extend(locals.iter().map(|(var, _)| var.value.clone()));
+    }
+    let mut lowering = Lowering {
+        names,
+        tuples: BTreeMap::new(),
+        returns: 0,
+        returns_tuple: function.value.signature.return_type.iter().any(is_tuple),
+        errors,
+    };
+
+    let header = function.value.header;
+    let signature = &mut function.value.signature;
+    signature.formals = lowering.declarations(&signature.formals);
+    let mut return_type = vec![];
+    for ty in &signature.return_type {
+        match flatten(ty) {
+            Some(tys) => return_type.extend(tys),
+            None => lowering.errors.push(TupleError {
+                loc: header,
+                message: format!("return type {} contains a tuple that is not at its top", ty),
+            }),
+        }
+    }
+    signature.return_type = return_type;
+    lowering.returns = signature.return_type.len();
+
+    if let FunctionBody::Move { locals, code } = &mut function.value.body {
+        *locals = lowering.declarations(locals);
+        lowering.block(code);
+    }
+}
+
+struct Lowering<'a> {
+    // The names of the locals and parameters of the function, including the lowered ones.
+    names: BTreeSet<Var_>,
+    // The locals of a tuple type, with the locals their elements are lowered to.
+    tuples: BTreeMap<Var_, Vec<Var_>>,
+    // The number of values the function returns once lowered, and whether its return type has a
+    // tuple.
+    returns: usize,
+    returns_tuple: bool,
+    errors: &'a mut Vec<TupleError>,
+}
+
+impl<'a> Lowering<'a> {
+    // Replaces the locals or parameters of a tuple type with one per element, named after them.
+    fn declarations(&mut self, declarations: &[(Var, Type)]) -> Vec<(Var, Type)> {
+        let mut lowered = vec![];
+        for (var, ty) in declarations {
+            match flatten(ty) {
+                Some(_) if !is_tuple(ty) => lowered.push((var.clone(), ty.clone())),
+                Some(tys) => {
+                    let mut elements = vec![];
+                    for (i, ty) in tys.into_iter().enumerate() {
+                        let element = self.fresh(&var.value, i);
+                        elements.push(element.clone());
+                        let span = var.span;
+                        lowered.push((
+                            Spanned {
+                                span,
+                                value: element,
+                            },
+                            ty,
+                        ));
+                    }
+                    self.tuples.insert(var.value.clone(), elements);
+                }
+                None => self.errors.push(TupleError {
+                    loc: var.span,
+                    message: format!(
+                        "{} cannot have the type {}, which contains a tuple that is not at its top",
+                        var, ty
+                    ),
+                }),
+            }
+        }
+        lowered
+    }
+
+    // Returns an unused name for the element `i` of the tuple `var`, as in `t_0`.
+    fn fresh(&mut self, var: &Var_, i: usize) -> Var_ {
+        let mut separator = "_".to_string();
+        loop {
+            let name = format!("{}{}{}", var, separator, i);
+            let element = Var_::new(Identifier::new(name).unwrap());
+            if self.names.insert(element.clone()) {
+                return element;
+            }
+            separator.push('_');
+        }
+    }
+
+    fn block(&mut self, block: &mut Block_) {
+        for statement in &mut block.stmts {
+            match statement {
+                Statement::CommandStatement(cmd) => self.cmd(&mut cmd.value, cmd.span),
+                Statement::IfElseStatement(if_else) => {
+                    self.exp(&mut if_else.cond);
+                    self.block(&mut if_else.if_block.value);
+                    if let Some(else_block) = &mut if_else.else_block {
+                        self.block(&mut else_block.value);
+                    }
+                }
+                Statement::WhileStatement(while_) => {
+                    self.exp(&mut while_.cond);
+                    self.block(&mut while_.block.value);
+                }
+                Statement::LoopStatement(loop_) => {
+                    self.lvalues(&mut loop_.results);
+                    self.block(&mut loop_.block.value);
+                }
+                Statement::EmptyStatement => (),
+            }
+        }
+    }
+
+    fn cmd(&mut self, cmd: &mut Cmd_, loc: Loc) {
+        match cmd {
+            Cmd_::Assign(lvalues, e) => {
+                let has_tuple = self.has_tuple_lvalue(lvalues) || self.is_tuple_use(e);
+                self.lvalues(lvalues);
+                self.exp(e);
+                if let (true, Some(values)) = (has_tuple, arity(e)) {
+                    if values != lvalues.len() {
+                        self.errors.push(TupleError {
+                            loc,
+                            message: format!(
+                                "cannot assign {} to {} target{}",
+                                count_values(values),
+                                lvalues.len(),
+                                if lvalues.len() == 1 { "" } else { "s" }
+                            ),
+                        });
+                    }
+                }
+            }
+            Cmd_::Unpack(_, _, bindings, e) => {
+                for (_, var) in bindings.iter() {
+                    if self.tuples.contains_key(&var.value) {
+                        self.errors.push(TupleError {
+                            loc: var.span,
+                            message: format!("the tuple {} cannot be bound to a field", var),
+                        });
+                    }
+                }
+                self.exp(e);
+            }
+            Cmd_::Return(e) => {
+                let has_tuple = self.returns_tuple || self.is_tuple_use(e);
+                self.exp(e);
+                if let (true, Some(values)) = (has_tuple, arity(e)) {
+                    if values != self.returns {
+                        self.errors.push(TupleError {
+                            loc,
+                            message: format!(
+                                "cannot return {} from a function returning {}",
+                                count_values(values),
+                                self.returns
+                            ),
+                        });
+                    }
+                }
+            }
+            Cmd_::Exp(e) | Cmd_::Abort(Some(e)) | Cmd_::Break(Some(e)) => self.exp(e),
+            Cmd_::Emit(handle, e) => {
+                self.exp(handle);
+                self.exp(e);
+            }
+            Cmd_::Abort(None) | Cmd_::Break(None) | Cmd_::Continue => (),
+        }
+    }
+
+    fn has_tuple_lvalue(&self, lvalues: &[LValue]) -> bool {
+        lvalues.iter().any(|lvalue| match &lvalue.value {
+            LValue_::Var(var) => self.tuples.contains_key(&var.value),
+            LValue_::Mutate(_) | LValue_::Pop => false,
+        })
+    }
+
+    // Whether the list of values `e` uses a tuple as one of them.
+    fn is_tuple_use(&self, e: &Exp) -> bool {
+        let mut exps = vec![e];
+        while let Some(e) = exps.pop() {
+            match &e.value {
+                Exp_::ExprList(elements) => exps.extend(elements),
+                Exp_::Move(var) | Exp_::Copy(var) | Exp_::Var(var)
+                    if self.tuples.contains_key(&var.value) =>
+                {
+                    return true
+                }
+                _ => (),
+            }
+        }
+        false
+    }
+
+    fn lvalues(&mut self, lvalues: &mut Vec<LValue>) {
+        let mut lowered = vec![];
+        for mut lvalue in lvalues.drain(..) {
+            match &mut lvalue.value {
+                LValue_::Var(var) => {
+                    if let Some(elements) = self.tuples.get(&var.value) {
+                        for element in elements {
+                            let var = Spanned {
+                                span: var.span,
+                                value: element.clone(),
+                            };
+                            lowered.push(Spanned {
+                                span: lvalue.span,
+                                value: LValue_::Var(var),
+                            });
+                        }
+                        continue;
+                    }
+                }
+                LValue_::Mutate(e) => self.exp(e),
+                LValue_::Pop => (),
+            }
+            lowered.push(lvalue);
+        }
+        *lvalues = lowered;
+    }
+
+    fn exp(&mut self, e: &mut Exp) {
+        visit_exp_mut(e, |e| {
+            let span = e.span;
+            let lowered = match &e.value {
+                Exp_::Move(var) => self.elements(var, span, Exp_::Move),
+                Exp_::Copy(var) => self.elements(var, span, Exp_::Copy),
+                Exp_::Var(var) => self.elements(var, span, Exp_::Var),
+                Exp_::BorrowLocal(_, var) => {
+                    if self.tuples.contains_key(&var.value) {
+                        self.errors.push(TupleError {
+                            loc: span,
+                            message: format!("the tuple {} cannot be borrowed", var),
+                        });
+                    }
+                    None
+                }
+                _ => None,
+            };
+            if let Some(lowered) = lowered {
+                e.value = lowered;
+            }
+        });
+    }
+
+    fn elements(&self, var: &Var, span: Loc, use_: fn(Var) -> Exp_) -> Option<Exp_> {
+        let elements = self.tuples.get(&var.value)?;
+        let exps = elements
+            .iter()
+            .map(|element| Spanned {
+                span,
+                value: use_(Spanned {
+                    span: var.span,
+                    value: element.clone(),
+                }),
+            })
+            .collect();
+        Some(Exp_::ExprList(exps))
+    }
+}
+
+// Returns the types a type of a local, parameter or return value is lowered to, or `None` if it
+// contains a tuple elsewhere than at its top.
+fn flatten(ty: &Type) -> Option<Vec<Type>> {
+    match ty {
+        Type::Tuple(tys) => {
+            let mut flattened = vec![];
+            for ty in tys {
+                flattened.extend(flatten(ty)?);
+            }
+            Some(flattened)
+        }
+        _ if contains_tuple(ty) => None,
+        _ => Some(vec![ty.clone()]),
+    }
+}
+
+fn is_tuple(ty: &Type) -> bool {
+    match ty {
+        Type::Tuple(_) => true,
+        _ => false,
+    }
+}
+
+fn contains_tuple(ty: &Type) -> bool {
+    match ty {
+        Type::Tuple(_) => true,
+        Type::Struct(_, tys) => tys.iter().any(contains_tuple),
+        Type::Reference(_, ty) => contains_tuple(ty),
+        Type::Address
+        | Type::U8
+        | Type::U64
+        | Type::U128
+        | Type::Bool
+        | Type::ByteArray
+        | Type::TypeParameter(_) => false,
+    }
+}
+
+// Returns the number of values of an expression, unless it calls a function.
+fn arity(e: &Exp) -> Option<usize> {
+    let (mut exps, mut n) = (vec![e], 0);
+    while let Some(e) = exps.pop() {
+        match &e.value {
+            Exp_::ExprList(elements) => exps.extend(elements),
+            Exp_::FunctionCall(_, _) | Exp_::Placeholder(_) => return None,
+            _ => n += 1,
+        }
+    }
+    Some(n)
+}
+
+fn count_values(n: usize) -> String {
+    if n == 1 {
+        "1 value".to_string()
+    } else {
+        format!("{} values", n)
+    }
+}
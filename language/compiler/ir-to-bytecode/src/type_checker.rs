@@ -0,0 +1,1158 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A coarse type checker over the parsed AST.
+//!
+//! The compiler deliberately leaves semantic checks to the bytecode verifier, whose errors point
+//! at bytecode offsets and are hard to read. This pass catches the common mistakes earlier and
+//! reports them against the IR source: unbound locals, structs, fields, functions and modules,
+//! operands and arguments of the wrong type, wrong numbers of values, and writes through or
+//! mutable borrows of immutable references.
+//!
+//! Only the current compilation unit is known to the checker. Calls to functions of other modules
+//! produce values of an unknown type, which are never reported as errors; the verifier remains the
+//! authority on those.
+
+use crate::errors::TypeError;
+use move_ir_types::ast::{
+    BinOp, Block_, Builtin, Cmd_, CopyableVal_, Exp, Exp_, Field, Field_, Function, FunctionBody,
+    FunctionCall_, FunctionName, ImportDefinition, Kind, LValue, LValue_, Loc, ModuleDefinition,
+    ModuleName, Program, QualifiedStructIdent, Script, Statement, StructDefinition,
+    StructDefinitionFields, StructName, Type, TypeVar, UnaryOp, Var, Var_,
+};
+use std::collections::BTreeMap;
+
+/// Type checks every module and then the script of a program.
+pub fn check_program(program: &Program) -> Vec<TypeError> {
+    let mut errors = vec![];
+    for module in &program.modules {
+        errors.extend(check_module(module));
+    }
+    errors.extend(check_script(&program.script));
+    errors
+}
+
+/// Type checks the struct declarations and the functions of a module.
+pub fn check_module(module: &ModuleDefinition) -> Vec<TypeError> {
+    let mut checker = Checker::new(&module.imports, &module.structs, &module.functions);
+    for struct_def in &module.structs {
+        if let StructDefinitionFields::Move { fields } = &struct_def.value.fields {
+            for (_, ty) in fields {
+                checker.check_type(struct_def.span, ty);
+            }
+        }
+    }
+    for (_, function) in &module.functions {
+        checker.function(function);
+    }
+    checker.errors
+}
+
+/// Type checks the `main` function of a script.
+pub fn check_script(script: &Script) -> Vec<TypeError> {
+    let mut checker = Checker::new(&script.imports, &[], &[]);
+    checker.function(&script.main);
+    checker.errors
+}
+
+/// Returns the expressions of the functions of a module producing a `&mut T` where a `&T` is
+/// expected, with their type `T`.
+pub(crate) fn module_freezes(module: &ModuleDefinition) -> Vec<(Loc, Type)> {
+    let mut checker = Checker::new(&module.imports, &module.structs, &module.functions);
+    checker.freezes = Some(vec![]);
+    for (_, function) in &module.functions {
+        checker.function(function);
+    }
+    checker.freezes.unwrap_or_default()
+}
+
+/// Returns the expressions of the `main` function of a script producing a `&mut T` where a `&T`
+/// is expected, with their type `T`.
+pub(crate) fn script_freezes(script: &Script) -> Vec<(Loc, Type)> {
+    let mut checker = Checker::new(&script.imports, &[], &[]);
+    checker.freezes = Some(vec![]);
+    checker.function(&script.main);
+    checker.freezes.unwrap_or_default()
+}
+
+fn is_integer(ty: &Type) -> bool {
+    match ty {
+        Type::U8 | Type::U64 | Type::U128 => true,
+        _ => false,
+    }
+}
+
+/// Whether `handle` is a mutable reference to an event handle for events of type `event`. The module
+/// of the handle is left to the compiler, which knows where the aliases lead.
+fn is_event_handle(handle: &Type, event: &Type) -> bool {
+    match handle {
+        Type::Reference(true, inner) => match &**inner {
+            Type::Struct(ident, tys) => {
+                ident.name.as_inner().as_str() == "EventHandle"
+                    && tys.len() == 1
+                    && tys[0] == *event
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn self_struct(name: &StructName, type_actuals: &[Type]) -> Type {
+    Type::Struct(
+        QualifiedStructIdent::new(ModuleName::module_self(), name.clone()),
+        type_actuals.to_vec(),
+    )
+}
+
+/// Replaces the type parameters in `ty` with the matching type actuals.
+pub(crate) fn subst(ty: &Type, type_formals: &[(TypeVar, Kind)], type_actuals: &[Type]) -> Type {
+    match ty {
+        Type::TypeParameter(tv) => type_formals
+            .iter()
+            .zip(type_actuals)
+            .find(|((formal, _), _)| formal.value == *tv)
+            .map_or_else(|| ty.clone(), |(_, actual)| actual.clone()),
+        Type::Reference(is_mutable, inner) => {
+            Type::reference(*is_mutable, subst(inner, type_formals, type_actuals))
+        }
+        Type::Struct(ident, tys) => Type::Struct(
+            ident.clone(),
+            tys.iter()
+                .map(|ty| subst(ty, type_formals, type_actuals))
+                .collect(),
+        ),
+        _ => ty.clone(),
+    }
+}
+
+fn format_types(tys: &[Type]) -> String {
+    let tys: Vec<String> = tys.iter().map(|ty| format!("`{}`", ty)).collect();
+    format!("({})", tys.join(", "))
+}
+
+/// How an expression uses the values of one of its subexpressions.
+#[derive(Clone, Copy)]
+enum Use {
+    /// As a single operand, which must produce exactly one value.
+    Single,
+    /// As arguments or elements of a list, which may produce any number of values.
+    Values,
+}
+
+/// An expression left to check, before or after its subexpressions.
+enum Visit<'e> {
+    Enter(&'e Exp, Use),
+    Exit(&'e Exp, Use),
+}
+
+/// Returns the type of a single value, if it is known.
+fn operand(values: Option<Vec<(Type, Option<Loc>)>>) -> Option<Type> {
+    values?.pop().map(|(ty, _)| ty)
+}
+
+struct Checker<'a> {
+    imports: &'a [ImportDefinition],
+    structs: &'a [StructDefinition],
+    functions: &'a [(FunctionName, Function)],
+    // The state of the function being checked
+    locals: BTreeMap<Var_, Type>,
+    return_type: &'a [Type],
+    // The values assigned by the enclosing loops, empty for a loop without a value
+    loops: Vec<Vec<LValue>>,
+    errors: Vec<TypeError>,
+    // The mutable references given where immutable ones are expected, when they are to be frozen
+    freezes: Option<Vec<(Loc, Type)>>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(
+        imports: &'a [ImportDefinition],
+        structs: &'a [StructDefinition],
+        functions: &'a [(FunctionName, Function)],
+    ) -> Self {
+        Self {
+            imports,
+            structs,
+            functions,
+            locals: BTreeMap::new(),
+            return_type: &[],
+            loops: vec![],
+            errors: vec![],
+            freezes: None,
+        }
+    }
+
+    fn error(&mut self, loc: Loc, message: String) {
+        self.errors.push(TypeError { loc, message });
+    }
+
+    fn expect_type(&mut self, loc: Loc, expected: &Type, actual: &Type) {
+        if expected != actual {
+            self.error(
+                loc,
+                format!(
+                    "expected a value of type `{}`, found `{}`",
+                    expected, actual
+                ),
+            );
+        }
+    }
+
+    /// Returns whether a value of type `actual` can be given where a value of type `expected` is
+    /// expected. When references are frozen, a `&mut T` is accepted for a `&T` if it is produced
+    /// by the expression at `loc` alone.
+    fn accepts(&mut self, expected: &Type, actual: &Type, loc: Option<Loc>) -> bool {
+        if expected == actual {
+            return true;
+        }
+        match (&mut self.freezes, expected, actual, loc) {
+            (
+                Some(freezes),
+                Type::Reference(false, expected),
+                Type::Reference(true, actual),
+                Some(loc),
+            ) if expected == actual => {
+                freezes.push((loc, (**actual).clone()));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    //**********************************************************************************************
+    // Declarations
+    //**********************************************************************************************
+
+    fn is_module_alias(&self, module: &ModuleName) -> bool {
+        module.as_inner() == ModuleName::self_name()
+            || self.imports.iter().any(|import| import.alias == *module)
+    }
+
+    fn struct_def(&mut self, loc: Loc, name: &StructName) -> Option<&'a StructDefinition> {
+        let structs = self.structs;
+        let struct_def = structs.iter().find(|s| s.value.name == *name);
+        if struct_def.is_none() {
+            self.error(loc, format!("unbound struct `{}`", name));
+        }
+        struct_def
+    }
+
+    fn resource_def(&mut self, loc: Loc, name: &StructName) -> Option<&'a StructDefinition> {
+        let struct_def = self.struct_def(loc, name)?;
+        if !struct_def.value.is_nominal_resource {
+            self.error(
+                loc,
+                format!(
+                    "`{}` is not a resource and cannot be held in global storage",
+                    name
+                ),
+            );
+        }
+        Some(struct_def)
+    }
+
+    /// The declared fields of a struct, with the type actuals substituted in.
+    fn struct_fields(
+        &mut self,
+        loc: Loc,
+        struct_def: &'a StructDefinition,
+        type_actuals: &[Type],
+    ) -> Option<Vec<(Field_, Type)>> {
+        let struct_def = &struct_def.value;
+        if struct_def.type_formals.len() != type_actuals.len() {
+            self.error(
+                loc,
+                format!(
+                    "struct `{}` expects {} type arguments, found {}",
+                    struct_def.name,
+                    struct_def.type_formals.len(),
+                    type_actuals.len()
+                ),
+            );
+            return None;
+        }
+        match &struct_def.fields {
+            StructDefinitionFields::Move { fields } => Some(
+                fields
+                    .iter()
+                    .map(|(field, ty)| {
+                        let ty = subst(ty, &struct_def.type_formals, type_actuals);
+                        (field.value.clone(), ty)
+                    })
+                    .collect(),
+            ),
+            StructDefinitionFields::Native => {
+                self.error(
+                    loc,
+                    format!(
+                        "the fields of native struct `{}` are not accessible",
+                        struct_def.name
+                    ),
+                );
+                None
+            }
+        }
+    }
+
+    fn check_type(&mut self, loc: Loc, ty: &Type) {
+        match ty {
+            Type::Struct(ident, tys) => {
+                if !self.is_module_alias(&ident.module) {
+                    self.error(loc, format!("unbound module `{}`", ident.module));
+                } else if ident.module.as_inner() == ModuleName::self_name() {
+                    if let Some(struct_def) = self.struct_def(loc, &ident.name) {
+                        let arity = struct_def.value.type_formals.len();
+                        if arity != tys.len() {
+                            self.error(
+                                loc,
+                                format!(
+                                    "struct `{}` expects {} type arguments, found {}",
+                                    ident.name,
+                                    arity,
+                                    tys.len()
+                                ),
+                            );
+                        }
+                    }
+                }
+                for ty in tys {
+                    self.check_type(loc, ty);
+                }
+            }
+            Type::Reference(_, inner) => self.check_type(loc, inner),
+            _ => (),
+        }
+    }
+
+    fn function(&mut self, function: &'a Function) {
+        let signature = &function.value.signature;
+        self.locals.clear();
+        self.return_type = &signature.return_type;
+        for (var, ty) in &signature.formals {
+            self.check_type(var.span, ty);
+            self.locals.insert(var.value.clone(), ty.clone());
+        }
+        for ty in &signature.return_type {
+            self.check_type(function.span, ty);
+        }
+        if let FunctionBody::Move { locals, code } = &function.value.body {
+            for (var, ty) in locals {
+                self.check_type(var.span, ty);
+                self.locals.insert(var.value.clone(), ty.clone());
+            }
+            self.block(code);
+        }
+    }
+
+    fn local(&mut self, var: &Var) -> Option<Type> {
+        let ty = self.locals.get(&var.value).cloned();
+        if ty.is_none() {
+            self.error(var.span, format!("unbound local `{}`", var.value));
+        }
+        ty
+    }
+
+    //**********************************************************************************************
+    // Statements
+    //**********************************************************************************************
+
+    fn block(&mut self, block: &Block_) {
+        for statement in &block.stmts {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::CommandStatement(cmd) => self.cmd(cmd.span, &cmd.value),
+            Statement::IfElseStatement(if_else) => {
+                self.condition(&if_else.cond);
+                self.block(&if_else.if_block.value);
+                if let Some(else_block) = &if_else.else_block {
+                    self.block(&else_block.value);
+                }
+            }
+            Statement::WhileStatement(while_) => {
+                self.condition(&while_.cond);
+                self.loops.push(vec![]);
+                self.block(&while_.block.value);
+                self.loops.pop();
+            }
+            Statement::LoopStatement(loop_) => {
+                self.loops.push(loop_.results.clone());
+                self.block(&loop_.block.value);
+                self.loops.pop();
+            }
+            Statement::EmptyStatement => (),
+        }
+    }
+
+    fn condition(&mut self, cond: &Exp) {
+        if let Some(ty) = self.single(cond) {
+            self.expect_type(cond.span, &Type::Bool, &ty);
+        }
+    }
+
+    fn cmd(&mut self, loc: Loc, cmd: &Cmd_) {
+        match cmd {
+            Cmd_::Assign(lvalues, e) => self.assign(loc, lvalues, e),
+            Cmd_::Unpack(name, type_actuals, bindings, e) => {
+                let ty = self.single(e);
+                let struct_def = match self.struct_def(loc, name) {
+                    Some(struct_def) => struct_def,
+                    None => return,
+                };
+                if let Some(ty) = ty {
+                    self.expect_type(e.span, &self_struct(name, type_actuals), &ty);
+                }
+                let fields = match self.struct_fields(loc, struct_def, type_actuals) {
+                    Some(fields) => fields,
+                    None => return,
+                };
+                self.check_field_names(loc, name, &fields, bindings);
+                for (field, var) in bindings {
+                    let field_ty = fields.iter().find(|(f, _)| *f == field.value);
+                    if let (Some((_, field_ty)), Some(local_ty)) =
+                        (field_ty, self.local(var).as_ref())
+                    {
+                        if field_ty != local_ty {
+                            self.error(
+                                var.span,
+                                format!(
+                                    "cannot bind field `{}` of type `{}` to local `{}` of type `{}`",
+                                    field.value, field_ty, var.value, local_ty
+                                ),
+                            );
+                        }
+                    }
+                }
+            }
+            Cmd_::Abort(e_opt) => {
+                if let Some(e) = e_opt {
+                    if let Some(ty) = self.single(e) {
+                        self.expect_type(e.span, &Type::U64, &ty);
+                    }
+                }
+            }
+            Cmd_::Return(e) => {
+                let returned = match &e.value {
+                    Exp_::ExprList(exps) => exps.iter().collect(),
+                    _ => vec![&**e],
+                };
+                for e in returned {
+                    if let Exp_::BorrowLocal(_, var) = &e.value {
+                        self.error(
+                            e.span,
+                            format!("cannot return a reference to local `{}`", var.value),
+                        );
+                    }
+                }
+                if let Some(values) = self.values(e) {
+                    let return_type = self.return_type;
+                    let mut accepted = values.len() == return_type.len();
+                    for (expected, (ty, loc)) in return_type.iter().zip(&values) {
+                        accepted &= self.accepts(expected, ty, *loc);
+                    }
+                    if !accepted {
+                        let tys: Vec<Type> = values.into_iter().map(|(ty, _)| ty).collect();
+                        self.error(
+                            loc,
+                            format!(
+                                "expected to return {}, found {}",
+                                format_types(return_type),
+                                format_types(&tys)
+                            ),
+                        );
+                    }
+                }
+            }
+            Cmd_::Break(e) => {
+                let results = match self.loops.last() {
+                    Some(results) => results.clone(),
+                    None => return,
+                };
+                match e {
+                    Some(e) if results.is_empty() => {
+                        self.exp(e);
+                        self.error(
+                            loc,
+                            "cannot break with a value out of a loop without a value".to_string(),
+                        );
+                    }
+                    Some(e) => self.assign(loc, &results, e),
+                    None if !results.is_empty() => self.error(
+                        loc,
+                        "expected a value to break out of a loop with a value".to_string(),
+                    ),
+                    None => (),
+                }
+            }
+            Cmd_::Continue => (),
+            Cmd_::Emit(handle, e) => {
+                let handle_ty = self.single(handle);
+                let event_ty = self.single(e);
+                if let (Some(handle_ty), Some(event_ty)) = (handle_ty, event_ty) {
+                    if !is_event_handle(&handle_ty, &event_ty) {
+                        self.error(
+                            handle.span,
+                            format!(
+                                "`emit` expects an event handle of type \
+                                 `&mut LibraAccount.EventHandle<{}>`, found `{}`",
+                                event_ty, handle_ty
+                            ),
+                        );
+                    }
+                }
+            }
+            Cmd_::Exp(e) => {
+                if let Some(tys) = self.exp(e) {
+                    if !tys.is_empty() {
+                        self.error(
+                            e.span,
+                            format!(
+                                "the values {} of this expression are never used",
+                                format_types(&tys)
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn assign(&mut self, loc: Loc, lvalues: &[LValue], e: &Exp) {
+        let values = self.values(e);
+        if let Some(values) = &values {
+            if values.len() != lvalues.len() {
+                self.error(
+                    loc,
+                    format!(
+                        "expected {} values to assign, found {}",
+                        lvalues.len(),
+                        values.len()
+                    ),
+                );
+                return;
+            }
+        }
+        for (idx, lvalue) in lvalues.iter().enumerate() {
+            let value = values.as_ref().map(|values| &values[idx]);
+            self.lvalue(&lvalue.value, lvalue.span, value);
+        }
+    }
+
+    fn lvalue(&mut self, lvalue: &LValue_, loc: Loc, value: Option<&(Type, Option<Loc>)>) {
+        let ty = value.map(|(ty, _)| ty);
+        match lvalue {
+            LValue_::Var(var) => {
+                if let (Some(local_ty), Some((ty, value_loc))) = (self.local(var).as_ref(), value) {
+                    if !self.accepts(local_ty, ty, *value_loc) {
+                        self.error(
+                            loc,
+                            format!(
+                                "cannot assign a value of type `{}` to local `{}` of type `{}`",
+                                ty, var.value, local_ty
+                            ),
+                        );
+                    }
+                }
+            }
+            LValue_::Mutate(e) => match self.single(e) {
+                Some(Type::Reference(true, inner)) => {
+                    if let Some(ty) = ty {
+                        self.expect_type(loc, &inner, ty);
+                    }
+                }
+                Some(Type::Reference(false, _)) => self.error(
+                    loc,
+                    "cannot assign through an immutable reference".to_string(),
+                ),
+                Some(other) => self.error(
+                    e.span,
+                    format!("expected a mutable reference, found `{}`", other),
+                ),
+                None => (),
+            },
+            LValue_::Pop => (),
+        }
+    }
+
+    fn check_field_names<T>(
+        &mut self,
+        loc: Loc,
+        name: &StructName,
+        declared: &[(Field_, Type)],
+        given: &[(Field, T)],
+    ) {
+        let mut names_match = true;
+        for (field, _) in given {
+            if !declared.iter().any(|(f, _)| *f == field.value) {
+                names_match = false;
+                self.error(
+                    field.span,
+                    format!("struct `{}` has no field `{}`", name, field.value),
+                );
+            }
+        }
+        for (field, _) in declared {
+            if !given.iter().any(|(f, _)| f.value == *field) {
+                names_match = false;
+                self.error(
+                    loc,
+                    format!("missing field `{}` of struct `{}`", field, name),
+                );
+            }
+        }
+        // Fields are packed and unpacked by position, so they must follow the declaration.
+        let in_order = declared
+            .iter()
+            .zip(given)
+            .all(|((declared, _), (given, _))| *declared == given.value);
+        if names_match && !in_order {
+            self.error(
+                loc,
+                format!(
+                    "the fields of struct `{}` must be listed in declaration order",
+                    name
+                ),
+            );
+        }
+    }
+
+    //**********************************************************************************************
+    // Expressions
+    //**********************************************************************************************
+
+    /// Checks an expression that must produce exactly one value, returning its type if known.
+    fn single(&mut self, e: &Exp) -> Option<Type> {
+        operand(self.check_exp(e, Use::Single))
+    }
+
+    /// Checks an expression, returning the types of the values it produces, or `None` if they are
+    /// not known.
+    fn exp(&mut self, e: &Exp) -> Option<Vec<Type>> {
+        let values = self.values(e)?;
+        Some(values.into_iter().map(|(ty, _)| ty).collect())
+    }
+
+    /// Checks an expression like `exp`, pairing the type of each value it produces with the
+    /// location of the element of the list producing that value alone, if there is one.
+    fn values(&mut self, e: &Exp) -> Option<Vec<(Type, Option<Loc>)>> {
+        self.check_exp(e, Use::Values)
+    }
+
+    // Expressions are nested without bound in the source, so their subexpressions are checked
+    // with a stack of their own rather than by recursion. The values of each subexpression are
+    // kept on a stack of results until the expression containing it is checked.
+    fn check_exp(&mut self, e: &Exp, use_: Use) -> Option<Vec<(Type, Option<Loc>)>> {
+        let mut visits = vec![Visit::Enter(e, use_)];
+        let mut results: Vec<Option<Vec<(Type, Option<Loc>)>>> = vec![];
+        while let Some(visit) = visits.pop() {
+            match visit {
+                Visit::Enter(e, use_) => {
+                    visits.push(Visit::Exit(e, use_));
+                    let operands = match &e.value {
+                        Exp_::FunctionCall(_, _) | Exp_::ExprList(_) => Use::Values,
+                        _ => Use::Single,
+                    };
+                    let subexps = e.value.subexps().into_iter().rev();
+                    visits.extend(subexps.map(|e| Visit::Enter(e, operands)));
+                }
+                Visit::Exit(e, use_) => {
+                    let operands = results.split_off(results.len() - e.value.subexps().len());
+                    let values: Option<Vec<_>> = match &e.value {
+                        Exp_::ExprList(_) => operands
+                            .into_iter()
+                            .collect::<Option<Vec<_>>>()
+                            .map(|values| values.into_iter().flatten().collect()),
+                        _ => self.exp_types(e, operands).map(|tys| {
+                            let loc = if tys.len() == 1 { Some(e.span) } else { None };
+                            tys.into_iter().map(|ty| (ty, loc)).collect()
+                        }),
+                    };
+                    results.push(match (use_, values) {
+                        (Use::Single, Some(values)) if values.len() != 1 => {
+                            let tys: Vec<Type> = values.into_iter().map(|(ty, _)| ty).collect();
+                            self.error(
+                                e.span,
+                                format!("expected a single value, found {}", format_types(&tys)),
+                            );
+                            None
+                        }
+                        (_, values) => values,
+                    });
+                }
+            }
+        }
+        results
+            .pop()
+            .expect("the expression checked leaves its values")
+    }
+
+    /// Returns the types of the values an expression other than a list produces, given the values
+    /// of its subexpressions, or `None` if they are not known.
+    fn exp_types(
+        &mut self,
+        e: &Exp,
+        operands: Vec<Option<Vec<(Type, Option<Loc>)>>>,
+    ) -> Option<Vec<Type>> {
+        let loc = e.span;
+        let mut operands = operands.into_iter();
+        let mut next = || operands.next().and_then(|values| values);
+        let ty = match &e.value {
+            Exp_::Dereference(_) => match operand(next())? {
+                Type::Reference(_, ty) => *ty,
+                other => {
+                    self.error(
+                        loc,
+                        format!("cannot dereference a value of type `{}`", other),
+                    );
+                    return None;
+                }
+            },
+            Exp_::UnaryExp(UnaryOp::Not, inner) => {
+                if let Some(ty) = operand(next()) {
+                    self.expect_type(inner.span, &Type::Bool, &ty);
+                }
+                Type::Bool
+            }
+            Exp_::BinopExp(lhs, op, rhs) => {
+                let lhs_ty = operand(next());
+                let rhs_ty = operand(next());
+                return self
+                    .binop(loc, op, (lhs, lhs_ty), (rhs, rhs_ty))
+                    .map(|ty| vec![ty]);
+            }
+            Exp_::Value(value) => match &value.value {
+                CopyableVal_::Address(_) => Type::Address,
+                CopyableVal_::U8(_) => Type::U8,
+                CopyableVal_::U64(_) => Type::U64,
+                CopyableVal_::U128(_) => Type::U128,
+                CopyableVal_::Bool(_) => Type::Bool,
+                CopyableVal_::ByteArray(_) => Type::ByteArray,
+            },
+            Exp_::Pack(name, type_actuals, field_exps) => {
+                let exp_tys: Vec<_> = field_exps
+                    .iter()
+                    .map(|(field, e)| (field, e.span, operand(next())))
+                    .collect();
+                let struct_def = self.struct_def(loc, name)?;
+                let fields = self.struct_fields(loc, struct_def, type_actuals)?;
+                self.check_field_names(loc, name, &fields, field_exps);
+                for (field, span, ty) in exp_tys {
+                    let field_ty = fields.iter().find(|(f, _)| *f == field.value);
+                    if let (Some((_, field_ty)), Some(ty)) = (field_ty, ty.as_ref()) {
+                        self.expect_type(span, field_ty, ty);
+                    }
+                }
+                self_struct(name, type_actuals)
+            }
+            Exp_::Borrow {
+                is_mutable,
+                exp: inner,
+                field,
+            } => {
+                let (is_mutable_ref, ident, type_actuals) = match operand(next())? {
+                    Type::Reference(is_mutable_ref, ty) => match *ty {
+                        Type::Struct(ident, type_actuals) => (is_mutable_ref, ident, type_actuals),
+                        other => {
+                            self.error(
+                                inner.span,
+                                format!("expected a reference to a struct, found `&{}`", other),
+                            );
+                            return None;
+                        }
+                    },
+                    other => {
+                        self.error(
+                            inner.span,
+                            format!("expected a reference to a struct, found `{}`", other),
+                        );
+                        return None;
+                    }
+                };
+                if *is_mutable && !is_mutable_ref {
+                    self.error(
+                        loc,
+                        format!(
+                            "cannot mutably borrow field `{}` through an immutable reference",
+                            field
+                        ),
+                    );
+                }
+                // Fields of structs declared in other modules are not visible here.
+                if ident.module.as_inner() != ModuleName::self_name() {
+                    return None;
+                }
+                let struct_def = self.struct_def(loc, &ident.name)?;
+                let fields = self.struct_fields(loc, struct_def, &type_actuals)?;
+                match fields.into_iter().find(|(f, _)| f == field) {
+                    Some((_, ty)) => Type::reference(*is_mutable, ty),
+                    None => {
+                        self.error(
+                            loc,
+                            format!("struct `{}` has no field `{}`", ident.name, field),
+                        );
+                        return None;
+                    }
+                }
+            }
+            Exp_::Move(var) | Exp_::Copy(var) | Exp_::Var(var) => self.local(var)?,
+            Exp_::BorrowLocal(is_mutable, var) => Type::reference(*is_mutable, self.local(var)?),
+            Exp_::FunctionCall(call, _) => {
+                let args = next();
+                return match &call.value {
+                    FunctionCall_::Builtin(builtin) => self.builtin(loc, builtin, args),
+                    FunctionCall_::ModuleFunctionCall {
+                        module,
+                        name,
+                        type_actuals,
+                    } => self.call(loc, module, name, type_actuals, args),
+                };
+            }
+            Exp_::ExprList(_) => unreachable!("the values of lists are those of their elements"),
+            // The type of a placeholder is the type of the value it is given.
+            Exp_::Placeholder(_) => return None,
+        };
+        Some(vec![ty])
+    }
+
+    fn binop(
+        &mut self,
+        loc: Loc,
+        op: &BinOp,
+        (lhs, lhs_ty): (&Exp, Option<Type>),
+        (rhs, rhs_ty): (&Exp, Option<Type>),
+    ) -> Option<Type> {
+        match op {
+            BinOp::Add
+            | BinOp::Sub
+            | BinOp::Mul
+            | BinOp::Mod
+            | BinOp::Div
+            | BinOp::BitOr
+            | BinOp::BitAnd
+            | BinOp::Xor => {
+                self.integer_operands(loc, op, lhs, &lhs_ty, rhs, &rhs_ty);
+                lhs_ty.or(rhs_ty)
+            }
+            BinOp::Shl | BinOp::Shr => {
+                if let Some(ty) = &lhs_ty {
+                    if !is_integer(ty) {
+                        self.error(lhs.span, format!("expected an integer, found `{}`", ty));
+                    }
+                }
+                if let Some(ty) = &rhs_ty {
+                    self.expect_type(rhs.span, &Type::U8, ty);
+                }
+                lhs_ty
+            }
+            BinOp::And | BinOp::Or => {
+                for (e, ty) in &[(lhs, &lhs_ty), (rhs, &rhs_ty)] {
+                    if let Some(ty) = ty {
+                        self.expect_type(e.span, &Type::Bool, ty);
+                    }
+                }
+                Some(Type::Bool)
+            }
+            BinOp::Eq | BinOp::Neq => {
+                if let (Some(lhs_ty), Some(rhs_ty)) = (&lhs_ty, &rhs_ty) {
+                    if lhs_ty != rhs_ty {
+                        self.error(
+                            loc,
+                            format!(
+                                "cannot compare `{}` with `{}` using `{}`",
+                                lhs_ty, rhs_ty, op
+                            ),
+                        );
+                    }
+                }
+                Some(Type::Bool)
+            }
+            BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => {
+                self.integer_operands(loc, op, lhs, &lhs_ty, rhs, &rhs_ty);
+                Some(Type::Bool)
+            }
+        }
+    }
+
+    fn integer_operands(
+        &mut self,
+        loc: Loc,
+        op: &BinOp,
+        lhs: &Exp,
+        lhs_ty: &Option<Type>,
+        rhs: &Exp,
+        rhs_ty: &Option<Type>,
+    ) {
+        let mut all_integers = true;
+        for (e, ty) in &[(lhs, lhs_ty), (rhs, rhs_ty)] {
+            if let Some(ty) = ty {
+                if !is_integer(ty) {
+                    all_integers = false;
+                    self.error(e.span, format!("expected an integer, found `{}`", ty));
+                }
+            }
+        }
+        if let (true, Some(lhs_ty), Some(rhs_ty)) = (all_integers, lhs_ty, rhs_ty) {
+            if lhs_ty != rhs_ty {
+                self.error(
+                    loc,
+                    format!(
+                        "mismatched operand types `{}` and `{}` for `{}`",
+                        lhs_ty, rhs_ty, op
+                    ),
+                );
+            }
+        }
+    }
+
+    fn arguments(
+        &mut self,
+        loc: Loc,
+        callee: &str,
+        expected: &[Type],
+        actual: Option<Vec<(Type, Option<Loc>)>>,
+    ) {
+        let actual = match actual {
+            Some(actual) => actual,
+            None => return,
+        };
+        if expected.len() != actual.len() {
+            self.error(
+                loc,
+                format!(
+                    "`{}` expects {} arguments, found {}",
+                    callee,
+                    expected.len(),
+                    actual.len()
+                ),
+            );
+            return;
+        }
+        for (idx, (expected, (actual, actual_loc))) in expected.iter().zip(&actual).enumerate() {
+            if !self.accepts(expected, actual, *actual_loc) {
+                self.error(
+                    loc,
+                    format!(
+                        "argument {} of `{}` expects a value of type `{}`, found `{}`",
+                        idx + 1,
+                        callee,
+                        expected,
+                        actual
+                    ),
+                );
+            }
+        }
+    }
+
+    fn builtin(
+        &mut self,
+        loc: Loc,
+        builtin: &Builtin,
+        args: Option<Vec<(Type, Option<Loc>)>>,
+    ) -> Option<Vec<Type>> {
+        let callee = builtin.to_string();
+        let arg_tys: Option<Vec<Type>> = args
+            .as_ref()
+            .map(|args| args.iter().map(|(ty, _)| ty.clone()).collect());
+        let ty = match builtin {
+            Builtin::Exists(name, _) => {
+                self.arguments(loc, &callee, &[Type::Address], args);
+                self.resource_def(loc, name)?;
+                Type::Bool
+            }
+            Builtin::BorrowGlobal(is_mutable, name, type_actuals) => {
+                self.arguments(loc, &callee, &[Type::Address], args);
+                self.resource_def(loc, name)?;
+                Type::reference(*is_mutable, self_struct(name, type_actuals))
+            }
+            Builtin::GetTxnSender => {
+                self.arguments(loc, &callee, &[], args);
+                Type::Address
+            }
+            Builtin::MoveFrom(name, type_actuals) => {
+                self.arguments(loc, &callee, &[Type::Address], args);
+                self.resource_def(loc, name)?;
+                self_struct(name, type_actuals)
+            }
+            Builtin::MoveToSender(name, type_actuals) => {
+                let ty = self_struct(name, type_actuals);
+                self.arguments(loc, &callee, &[ty], args);
+                self.resource_def(loc, name)?;
+                return Some(vec![]);
+            }
+            Builtin::Freeze => match arg_tys.as_deref() {
+                Some([Type::Reference(true, inner)]) => Type::reference(false, (**inner).clone()),
+                Some([other]) => {
+                    self.error(
+                        loc,
+                        format!("`freeze` expects a mutable reference, found `{}`", other),
+                    );
+                    return None;
+                }
+                Some(tys) => {
+                    self.error(
+                        loc,
+                        format!("`freeze` expects 1 argument, found {}", tys.len()),
+                    );
+                    return None;
+                }
+                None => return None,
+            },
+            Builtin::ToU8 | Builtin::ToU64 | Builtin::ToU128 => {
+                match arg_tys.as_deref() {
+                    Some([ty]) if !is_integer(ty) => self.error(
+                        loc,
+                        format!("`{}` expects an integer, found `{}`", callee, ty),
+                    ),
+                    Some([_]) | None => (),
+                    Some(tys) => self.error(
+                        loc,
+                        format!("`{}` expects 1 argument, found {}", callee, tys.len()),
+                    ),
+                }
+                match builtin {
+                    Builtin::ToU8 => Type::U8,
+                    Builtin::ToU64 => Type::U64,
+                    _ => Type::U128,
+                }
+            }
+            Builtin::Rotl | Builtin::Rotr => match arg_tys.as_deref() {
+                Some([ty, Type::U8]) if is_integer(ty) => ty.clone(),
+                Some([ty, amount]) => {
+                    if !is_integer(ty) {
+                        self.error(
+                            loc,
+                            format!("`{}` expects an integer, found `{}`", callee, ty),
+                        );
+                    }
+                    if *amount != Type::U8 {
+                        self.error(
+                            loc,
+                            format!("`{}` expects a `u8` amount, found `{}`", callee, amount),
+                        );
+                    }
+                    return None;
+                }
+                Some(tys) => {
+                    self.error(
+                        loc,
+                        format!("`{}` expects 2 arguments, found {}", callee, tys.len()),
+                    );
+                    return None;
+                }
+                None => return None,
+            },
+            Builtin::CountOnes => {
+                match arg_tys.as_deref() {
+                    Some([ty]) if !is_integer(ty) => self.error(
+                        loc,
+                        format!("`{}` expects an integer, found `{}`", callee, ty),
+                    ),
+                    Some([_]) | None => (),
+                    Some(tys) => self.error(
+                        loc,
+                        format!("`{}` expects 1 argument, found {}", callee, tys.len()),
+                    ),
+                }
+                Type::U64
+            }
+            Builtin::CheckedAdd
+            | Builtin::CheckedSub
+            | Builtin::SaturatingAdd
+            | Builtin::SaturatingSub => match arg_tys.as_deref() {
+                Some([lhs, rhs]) if is_integer(lhs) && lhs == rhs => match builtin {
+                    Builtin::CheckedAdd | Builtin::CheckedSub => {
+                        return Some(vec![lhs.clone(), Type::Bool])
+                    }
+                    _ => lhs.clone(),
+                },
+                Some([lhs, rhs]) => {
+                    self.error(
+                        loc,
+                        format!(
+                            "`{}` expects two integers of the same type, found `{}` and `{}`",
+                            callee, lhs, rhs
+                        ),
+                    );
+                    return None;
+                }
+                Some(tys) => {
+                    self.error(
+                        loc,
+                        format!("`{}` expects 2 arguments, found {}", callee, tys.len()),
+                    );
+                    return None;
+                }
+                None => return None,
+            },
+            Builtin::Sha2_256 | Builtin::Sha3_256 => {
+                self.arguments(loc, &callee, &[Type::ByteArray], args);
+                Type::ByteArray
+            }
+            Builtin::ToBytes(ty) => {
+                self.check_type(loc, ty);
+                if let Type::Reference(..) = ty {
+                    // The native serializes the value its argument refers to, never a reference
+                    self.error(loc, format!("`{}` cannot serialize a reference", callee));
+                }
+                self.arguments(loc, &callee, &[Type::reference(false, ty.clone())], args);
+                Type::ByteArray
+            }
+        };
+        Some(vec![ty])
+    }
+
+    fn call(
+        &mut self,
+        loc: Loc,
+        module: &ModuleName,
+        name: &FunctionName,
+        type_actuals: &[Type],
+        args: Option<Vec<(Type, Option<Loc>)>>,
+    ) -> Option<Vec<Type>> {
+        if !self.is_module_alias(module) {
+            self.error(loc, format!("unbound module `{}`", module));
+            return None;
+        }
+        if module.as_inner() != ModuleName::self_name() {
+            return None;
+        }
+        let functions = self.functions;
+        let function = match functions.iter().find(|(f, _)| f == name) {
+            Some((_, function)) => function,
+            None => {
+                self.error(loc, format!("unbound function `{}.{}`", module, name));
+                return None;
+            }
+        };
+        let signature = &function.value.signature;
+        if signature.type_formals.len() != type_actuals.len() {
+            self.error(
+                loc,
+                format!(
+                    "`{}` expects {} type arguments, found {}",
+                    name,
+                    signature.type_formals.len(),
+                    type_actuals.len()
+                ),
+            );
+            return None;
+        }
+        let formals: Vec<Type> = signature
+            .formals
+            .iter()
+            .map(|(_, ty)| subst(ty, &signature.type_formals, type_actuals))
+            .collect();
+        self.arguments(loc, &format!("{}.{}", module, name), &formals, args);
+        Some(
+            signature
+                .return_type
+                .iter()
+                .map(|ty| subst(ty, &signature.type_formals, type_actuals))
+                .collect(),
+        )
+    }
+}
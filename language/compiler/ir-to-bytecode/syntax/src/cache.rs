@@ -0,0 +1,81 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Parse result cache.
+//!
+//! When built with the `cache` feature, [`parse_module_string`](../syntax/fn.parse_module_string.html)
+//! memoizes the modules it parses, keyed by a hash of their source, and returns a copy of the
+//! cached module when it is given the same source again. Test runners compiling the same sources,
+//! such as the standard library, over and over then parse them once per process.
+//!
+//! The cache is shared by all threads. Only successful parses are cached, and the source is kept
+//! alongside the module, so that a hash collision cannot return the wrong module.
+
+use move_ir_types::ast::ModuleDefinition;
+use once_cell::sync::Lazy;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::Mutex,
+};
+
+type Entries = HashMap<u64, Vec<(String, ModuleDefinition)>>;
+
+static CACHE: Lazy<Mutex<Entries>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn key(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn entries() -> std::sync::MutexGuard<'static, Entries> {
+    // A panic while holding the lock cannot leave an entry half written.
+    CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Returns a copy of the module parsed from `source`, if it is cached.
+pub(crate) fn get(source: &str) -> Option<ModuleDefinition> {
+    entries()
+        .get(&key(source))?
+        .iter()
+        .find(|(cached, _)| cached == source)
+        .map(|(_, module)| module.clone())
+}
+
+/// Caches the module parsed from `source`.
+pub(crate) fn insert(source: &str, module: &ModuleDefinition) {
+    let mut entries = entries();
+    let bucket = entries.entry(key(source)).or_default();
+    if bucket.iter().all(|(cached, _)| cached != source) {
+        bucket.push((source.to_string(), module.clone()));
+    }
+}
+
+/// Returns the number of cached modules.
+pub fn len() -> usize {
+    entries().values().map(Vec::len).sum()
+}
+
+/// Empties the cache.
+pub fn clear() {
+    entries().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::parse_module_string;
+
+    #[test]
+    fn parses_are_cached() {
+        let source = "module M { public f(): u64 { return 1; } }";
+        let parsed = parse_module_string(source).unwrap();
+        assert!(super::get(source).is_some());
+        assert_eq!(parse_module_string(source).unwrap(), parsed);
+        assert!(super::get("module M { }").is_none());
+        assert!(parse_module_string("module M { f( }").is_err());
+        assert!(super::get("module M { f( }").is_none());
+    }
+}
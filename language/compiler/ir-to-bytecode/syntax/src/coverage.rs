@@ -0,0 +1,300 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Grammar production coverage.
+//!
+//! When built with the `coverage` feature, the parser records every grammar production it goes
+//! through. Parsing a test corpus and then asking for a [`report`](fn.report.html) tells which
+//! productions the corpus never exercises, so that every syntax form, including the
+//! specification language, can be given a test.
+//!
+//! Productions are recorded per thread: the report only covers what was parsed on the calling
+//! thread since the last [`reset`](fn.reset.html).
+
+use move_ir_types::ast::BinOp;
+use std::{cell::RefCell, collections::BTreeMap, fmt};
+
+/// A grammar production, as the non-terminal and the name of one of its alternatives.
+pub type Production = (&'static str, &'static str);
+
+/// Every production the parser records.
+pub const PRODUCTIONS: &[Production] = &[
+    ("ScriptOrModule", "Script"),
+    ("ScriptOrModule", "Module"),
+    ("LanguagePragma", "Language"),
+    ("Script", "Main"),
+    ("Script", "Generic"),
+    ("Script", "Returns"),
+    ("Module", "Module"),
+    ("CfgAttribute", "Cfg"),
+    ("CfgPredicate", "Flag"),
+    ("CfgPredicate", "Feature"),
+    ("CfgPredicate", "Not"),
+    ("CfgPredicate", "All"),
+    ("CfgPredicate", "Any"),
+    ("InlineAttribute", "Inline"),
+    ("InlineAttribute", "NeverInline"),
+    ("AnnotateAttribute", "Annotate"),
+    ("ImportDecl", "Qualified"),
+    ("ImportDecl", "Transaction"),
+    ("ImportDecl", "Alias"),
+    ("StructDecl", "Struct"),
+    ("StructDecl", "Resource"),
+    ("StructDecl", "Native"),
+    ("StructDecl", "Invariants"),
+    ("FunctionDecl", "Move"),
+    ("FunctionDecl", "Native"),
+    ("FunctionDecl", "Public"),
+    ("FunctionDecl", "Internal"),
+    ("FunctionDecl", "Acquires"),
+    ("FunctionDecl", "Specifications"),
+    ("ReturnType", "Single"),
+    ("ReturnType", "Multiple"),
+    ("Declaration", "Let"),
+    ("NameAndTypeFormals", "Name"),
+    ("NameAndTypeFormals", "Generic"),
+    ("NameAndTypeActuals", "Name"),
+    ("NameAndTypeActuals", "Generic"),
+    ("TypeFormal", "Unconstrained"),
+    ("TypeFormal", "Kind"),
+    ("Kind", "Resource"),
+    ("Kind", "Unrestricted"),
+    ("TypeActuals", "Empty"),
+    ("TypeActuals", "Explicit"),
+    ("Type", "Address"),
+    ("Type", "U8"),
+    ("Type", "U64"),
+    ("Type", "U128"),
+    ("Type", "Bool"),
+    ("Type", "ByteArray"),
+    ("Type", "Struct"),
+    ("Type", "Reference"),
+    ("Type", "MutableReference"),
+    ("Type", "TypeParameter"),
+    ("Type", "Tuple"),
+    ("Statement", "Cmd"),
+    ("Statement", "Assert"),
+    ("Statement", "Empty"),
+    ("IfStatement", "If"),
+    ("IfStatement", "IfElse"),
+    ("WhileStatement", "While"),
+    ("LoopStatement", "Loop"),
+    ("LoopStatement", "Assign"),
+    ("Cmd", "Assign"),
+    ("Cmd", "Unpack"),
+    ("Cmd", "Abort"),
+    ("Cmd", "AbortWithoutCode"),
+    ("Cmd", "Return"),
+    ("Cmd", "Continue"),
+    ("Cmd", "Break"),
+    ("Cmd", "BreakWithValue"),
+    ("Cmd", "Emit"),
+    ("Cmd", "Call"),
+    ("Cmd", "ExprList"),
+    ("LValue", "Var"),
+    ("LValue", "Mutate"),
+    ("LValue", "Pop"),
+    ("FieldBindings", "Binding"),
+    ("FieldBindings", "Shorthand"),
+    ("Exp", "Or"),
+    ("Exp", "And"),
+    ("Exp", "Eq"),
+    ("Exp", "Neq"),
+    ("Exp", "Lt"),
+    ("Exp", "Gt"),
+    ("Exp", "Le"),
+    ("Exp", "Ge"),
+    ("Exp", "BitOr"),
+    ("Exp", "Xor"),
+    ("Exp", "BitAnd"),
+    ("Exp", "Shl"),
+    ("Exp", "Shr"),
+    ("Exp", "Add"),
+    ("Exp", "Sub"),
+    ("Exp", "Mul"),
+    ("Exp", "Div"),
+    ("Exp", "Mod"),
+    ("UnaryExp", "Not"),
+    ("UnaryExp", "Dereference"),
+    ("UnaryExp", "BorrowField"),
+    ("UnaryExp", "BorrowFieldMut"),
+    ("CallOrTerm", "Call"),
+    ("QualifiedFunctionName", "Builtin"),
+    ("QualifiedFunctionName", "ModuleFunction"),
+    ("Builtin", "Exists"),
+    ("Builtin", "BorrowGlobal"),
+    ("Builtin", "BorrowGlobalMut"),
+    ("Builtin", "GetTxnSender"),
+    ("Builtin", "MoveFrom"),
+    ("Builtin", "MoveToSender"),
+    ("Builtin", "Freeze"),
+    ("Builtin", "ToU8"),
+    ("Builtin", "ToU64"),
+    ("Builtin", "ToU128"),
+    ("Builtin", "Rotl"),
+    ("Builtin", "Rotr"),
+    ("Builtin", "CountOnes"),
+    ("Builtin", "CheckedAdd"),
+    ("Builtin", "CheckedSub"),
+    ("Builtin", "SaturatingAdd"),
+    ("Builtin", "SaturatingSub"),
+    ("Builtin", "Sha2_256"),
+    ("Builtin", "Sha3_256"),
+    ("Builtin", "ToBytes"),
+    ("Term", "Move"),
+    ("Term", "Copy"),
+    ("Term", "MoveKeyword"),
+    ("Term", "CopyKeyword"),
+    ("Term", "Var"),
+    ("Term", "BorrowLocal"),
+    ("Term", "BorrowLocalMut"),
+    ("Term", "Value"),
+    ("Term", "Pack"),
+    ("Term", "ExprList"),
+    ("Term", "Placeholder"),
+    ("CopyableVal", "Address"),
+    ("CopyableVal", "True"),
+    ("CopyableVal", "False"),
+    ("CopyableVal", "U8"),
+    ("CopyableVal", "U64"),
+    ("CopyableVal", "U128"),
+    ("CopyableVal", "ByteArray"),
+    ("Synthetic", "Synthetic"),
+    ("Invariant", "Invariant"),
+    ("Invariant", "Modifier"),
+    ("Condition", "AbortsIf"),
+    ("Condition", "Ensures"),
+    ("Condition", "Requires"),
+    ("Condition", "SucceedsIf"),
+    ("SpecExp", "Implies"),
+    ("SpecExp", "Or"),
+    ("SpecExp", "And"),
+    ("SpecExp", "Eq"),
+    ("SpecExp", "Neq"),
+    ("SpecExp", "Lt"),
+    ("SpecExp", "Gt"),
+    ("SpecExp", "Le"),
+    ("SpecExp", "Ge"),
+    ("SpecExp", "BitOr"),
+    ("SpecExp", "Xor"),
+    ("SpecExp", "BitAnd"),
+    ("SpecExp", "Add"),
+    ("SpecExp", "Sub"),
+    ("SpecExp", "Mul"),
+    ("SpecExp", "Div"),
+    ("SpecExp", "Mod"),
+    ("UnarySpecExp", "Constant"),
+    ("UnarySpecExp", "GlobalExists"),
+    ("UnarySpecExp", "Dereference"),
+    ("UnarySpecExp", "Reference"),
+    ("UnarySpecExp", "Not"),
+    ("UnarySpecExp", "IfElse"),
+    ("UnarySpecExp", "Old"),
+    ("UnarySpecExp", "Call"),
+    ("UnarySpecExp", "StorageLocation"),
+    ("StorageLocation", "Ret"),
+    ("StorageLocation", "RetIndex"),
+    ("StorageLocation", "TxnSender"),
+    ("StorageLocation", "Address"),
+    ("StorageLocation", "Global"),
+    ("StorageLocation", "Formal"),
+    ("StorageLocation", "AccessPath"),
+];
+
+thread_local! {
+    static HITS: RefCell<BTreeMap<Production, u64>> = RefCell::new(BTreeMap::new());
+}
+
+/// Records that the parser went through `production`.
+pub(crate) fn record(production: Production) {
+    debug_assert!(
+        PRODUCTIONS.contains(&production),
+        "unknown production {}::{}",
+        production.0,
+        production.1
+    );
+    HITS.with(|hits| *hits.borrow_mut().entry(production).or_insert(0) += 1);
+}
+
+/// The name of the alternative of a binary expression with this operator.
+pub(crate) fn binop_name(op: &BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "Add",
+        BinOp::Sub => "Sub",
+        BinOp::Mul => "Mul",
+        BinOp::Mod => "Mod",
+        BinOp::Div => "Div",
+        BinOp::BitOr => "BitOr",
+        BinOp::BitAnd => "BitAnd",
+        BinOp::Xor => "Xor",
+        BinOp::Shl => "Shl",
+        BinOp::Shr => "Shr",
+        BinOp::And => "And",
+        BinOp::Or => "Or",
+        BinOp::Eq => "Eq",
+        BinOp::Neq => "Neq",
+        BinOp::Lt => "Lt",
+        BinOp::Gt => "Gt",
+        BinOp::Le => "Le",
+        BinOp::Ge => "Ge",
+    }
+}
+
+/// Forgets the productions recorded so far on this thread.
+pub fn reset() {
+    HITS.with(|hits| hits.borrow_mut().clear());
+}
+
+/// Returns how many times each production was recorded on this thread since the last reset.
+pub fn report() -> CoverageReport {
+    HITS.with(|hits| {
+        let hits = hits.borrow();
+        CoverageReport {
+            hits: PRODUCTIONS
+                .iter()
+                .map(|production| (*production, hits.get(production).cloned().unwrap_or(0)))
+                .collect(),
+        }
+    })
+}
+
+/// The number of times each production was exercised, in the order of `PRODUCTIONS`.
+#[derive(Clone, Debug)]
+pub struct CoverageReport {
+    hits: Vec<(Production, u64)>,
+}
+
+impl CoverageReport {
+    /// Returns how many times `production` was exercised.
+    pub fn hits(&self, production: Production) -> u64 {
+        self.hits
+            .iter()
+            .find(|(p, _)| *p == production)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    /// Returns the productions that were never exercised.
+    pub fn uncovered(&self) -> Vec<Production> {
+        self.hits
+            .iter()
+            .filter(|(_, count)| *count == 0)
+            .map(|(production, _)| *production)
+            .collect()
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let uncovered = self.uncovered();
+        writeln!(
+            f,
+            "{} of {} productions covered",
+            self.hits.len() - uncovered.len(),
+            self.hits.len()
+        )?;
+        for (nonterminal, alternative) in uncovered {
+            writeln!(f, "uncovered: {}::{}", nonterminal, alternative)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1227 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A description of the grammar the parser accepts.
+//!
+//! The productions below follow the `parse_*` functions of the parser, and the tests check that
+//! every terminal is spelled the way the lexer reads it and that every token is used. The grammar
+//! can be exported in the W3C EBNF notation with [`to_ebnf`](fn.to_ebnf.html), which is also the
+//! input of railroad diagram generators.
+
+use std::fmt::Write;
+
+/// A symbol on the right hand side of a production.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symbol {
+    /// A token with a fixed spelling, e.g. `while` or `assert(`.
+    Terminal(&'static str),
+    /// A name read as a keyword only where the grammar expects one, e.g. `rotl` before its
+    /// arguments. Elsewhere it is lexed and parsed as a `Name`.
+    Contextual(&'static str),
+    /// A token of one of the `LEXEMES`, e.g. a name or a number.
+    Lexeme(&'static str),
+    /// One of the `PRODUCTIONS`.
+    NonTerminal(&'static str),
+    /// The symbols, or nothing.
+    Optional(&'static [Symbol]),
+    /// The symbols, repeated zero or more times.
+    Repeated(&'static [Symbol]),
+    /// Zero or more occurrences of the symbols separated by commas, with an optional trailing
+    /// comma.
+    CommaList(&'static [Symbol]),
+}
+
+use self::Symbol::*;
+
+/// A production, as the sequences of symbols of its alternatives.
+#[derive(Clone, Copy, Debug)]
+pub struct Production {
+    pub name: &'static str,
+    pub alternatives: &'static [&'static [Symbol]],
+}
+
+/// A class of tokens whose spelling varies.
+#[derive(Clone, Copy, Debug)]
+pub struct LexemeDefinition {
+    pub name: &'static str,
+    /// The spelling of the tokens, in EBNF. Lexemes do not contain whitespace.
+    pub definition: &'static str,
+    /// A token of the class.
+    pub example: &'static str,
+}
+
+/// The productions of the grammar. `ScriptOrModule` is the start symbol of a transaction, and
+/// `Program` the one of a program with inline modules.
+pub const PRODUCTIONS: &[Production] = &[
+    Production {
+        name: "ScriptOrModule",
+        alternatives: &[
+            &[
+                Optional(&[NonTerminal("LanguagePragma")]),
+                NonTerminal("Script"),
+            ],
+            &[
+                Optional(&[NonTerminal("LanguagePragma")]),
+                NonTerminal("Module"),
+            ],
+        ],
+    },
+    Production {
+        name: "Program",
+        alternatives: &[
+            &[
+                Optional(&[NonTerminal("LanguagePragma")]),
+                NonTerminal("Module"),
+            ],
+            &[
+                Optional(&[NonTerminal("LanguagePragma")]),
+                Optional(&[NonTerminal("Modules")]),
+                NonTerminal("Script"),
+            ],
+        ],
+    },
+    Production {
+        name: "LanguagePragma",
+        alternatives: &[&[
+            Contextual("pragma"),
+            Lexeme("Name"),
+            Lexeme("U64"),
+            Terminal(";"),
+        ]],
+    },
+    Production {
+        name: "Modules",
+        alternatives: &[&[
+            Terminal("modules:"),
+            Repeated(&[NonTerminal("Module")]),
+            Terminal("script:"),
+        ]],
+    },
+    Production {
+        name: "Script",
+        alternatives: &[&[
+            Repeated(&[
+                Optional(&[NonTerminal("CfgAttribute")]),
+                NonTerminal("ImportDecl"),
+            ]),
+            Terminal("main"),
+            Optional(&[
+                Terminal("<"),
+                CommaList(&[NonTerminal("TypeFormal")]),
+                Terminal(">"),
+            ]),
+            Terminal("("),
+            CommaList(&[NonTerminal("ArgDecl")]),
+            Terminal(")"),
+            Optional(&[NonTerminal("ReturnType")]),
+            NonTerminal("FunctionBlock"),
+        ]],
+    },
+    Production {
+        name: "Module",
+        alternatives: &[&[
+            Terminal("module"),
+            Lexeme("Name"),
+            Terminal("{"),
+            Repeated(&[
+                Optional(&[NonTerminal("CfgAttribute")]),
+                NonTerminal("ImportDecl"),
+            ]),
+            Repeated(&[NonTerminal("Synthetic")]),
+            Repeated(&[
+                Optional(&[NonTerminal("CfgAttribute")]),
+                NonTerminal("StructDecl"),
+            ]),
+            Repeated(&[
+                Optional(&[NonTerminal("CfgAttribute")]),
+                Optional(&[NonTerminal("InlineAttribute")]),
+                Optional(&[NonTerminal("AnnotateAttribute")]),
+                NonTerminal("FunctionDecl"),
+            ]),
+            Terminal("}"),
+        ]],
+    },
+    Production {
+        name: "CfgAttribute",
+        alternatives: &[&[
+            Terminal("#"),
+            Terminal("["),
+            Lexeme("Name"),
+            Terminal("("),
+            NonTerminal("CfgPredicate"),
+            Terminal(")"),
+            Terminal("]"),
+        ]],
+    },
+    Production {
+        name: "CfgPredicate",
+        alternatives: &[
+            &[Lexeme("Name")],
+            &[Lexeme("Name"), Terminal("="), Lexeme("String")],
+            &[
+                Lexeme("Name"),
+                Terminal("("),
+                CommaList(&[NonTerminal("CfgPredicate")]),
+                Terminal(")"),
+            ],
+        ],
+    },
+    Production {
+        name: "InlineAttribute",
+        alternatives: &[&[Terminal("#"), Terminal("["), Lexeme("Name"), Terminal("]")]],
+    },
+    Production {
+        name: "AnnotateAttribute",
+        alternatives: &[&[
+            Terminal("#"),
+            Terminal("["),
+            Lexeme("Name"),
+            Terminal("("),
+            CommaList(&[Lexeme("Name"), Terminal("="), Lexeme("String")]),
+            Terminal(")"),
+            Terminal("]"),
+        ]],
+    },
+    Production {
+        name: "ImportDecl",
+        alternatives: &[&[
+            Terminal("import"),
+            NonTerminal("ModuleIdent"),
+            Optional(&[Terminal("as"), Lexeme("Name")]),
+            Terminal(";"),
+        ]],
+    },
+    Production {
+        name: "ModuleIdent",
+        alternatives: &[
+            &[Lexeme("AccountAddress"), Terminal("."), Lexeme("Name")],
+            &[Lexeme("DotName")],
+        ],
+    },
+    Production {
+        name: "Synthetic",
+        alternatives: &[&[
+            Terminal("synthetic"),
+            Lexeme("Name"),
+            Terminal(":"),
+            NonTerminal("Type"),
+            Terminal(";"),
+        ]],
+    },
+    Production {
+        name: "StructDecl",
+        alternatives: &[
+            &[
+                NonTerminal("StructKind"),
+                NonTerminal("NameAndTypeFormals"),
+                Terminal("{"),
+                CommaList(&[NonTerminal("FieldDecl")]),
+                CommaList(&[NonTerminal("Invariant")]),
+                Terminal("}"),
+            ],
+            &[
+                Terminal("native"),
+                NonTerminal("StructKind"),
+                NonTerminal("NameAndTypeFormals"),
+                Terminal(";"),
+            ],
+        ],
+    },
+    Production {
+        name: "StructKind",
+        alternatives: &[&[Terminal("struct")], &[Terminal("resource")]],
+    },
+    Production {
+        name: "FieldDecl",
+        alternatives: &[&[Lexeme("Name"), Terminal(":"), NonTerminal("Type")]],
+    },
+    Production {
+        name: "FunctionDecl",
+        alternatives: &[
+            &[
+                NonTerminal("FunctionSignature"),
+                NonTerminal("FunctionBlock"),
+            ],
+            &[
+                Terminal("native"),
+                NonTerminal("FunctionSignature"),
+                Terminal(";"),
+            ],
+        ],
+    },
+    Production {
+        name: "FunctionSignature",
+        alternatives: &[&[
+            Optional(&[Terminal("public")]),
+            NonTerminal("NameAndTypeFormals"),
+            Terminal("("),
+            CommaList(&[NonTerminal("ArgDecl")]),
+            Terminal(")"),
+            Optional(&[NonTerminal("ReturnType")]),
+            Optional(&[NonTerminal("AcquireList")]),
+            Repeated(&[NonTerminal("Condition")]),
+        ]],
+    },
+    Production {
+        name: "ArgDecl",
+        alternatives: &[&[Lexeme("Name"), Terminal(":"), NonTerminal("Type")]],
+    },
+    Production {
+        name: "ReturnType",
+        alternatives: &[&[
+            Terminal(":"),
+            NonTerminal("Type"),
+            Repeated(&[Terminal("*"), NonTerminal("Type")]),
+        ]],
+    },
+    Production {
+        name: "AcquireList",
+        alternatives: &[&[
+            Terminal("acquires"),
+            Lexeme("Name"),
+            Repeated(&[Terminal(","), Lexeme("Name")]),
+        ]],
+    },
+    Production {
+        name: "FunctionBlock",
+        alternatives: &[&[
+            Terminal("{"),
+            Repeated(&[NonTerminal("Declaration")]),
+            Repeated(&[NonTerminal("Statement")]),
+            Terminal("}"),
+        ]],
+    },
+    Production {
+        name: "Declaration",
+        alternatives: &[&[
+            Terminal("let"),
+            Lexeme("Name"),
+            Terminal(":"),
+            NonTerminal("Type"),
+            Terminal(";"),
+        ]],
+    },
+    Production {
+        name: "NameAndTypeFormals",
+        alternatives: &[
+            &[
+                Lexeme("NameBeginTy"),
+                CommaList(&[NonTerminal("TypeFormal")]),
+                Terminal(">"),
+            ],
+            &[Lexeme("Name")],
+        ],
+    },
+    Production {
+        name: "TypeFormal",
+        alternatives: &[&[
+            Lexeme("Name"),
+            Optional(&[Terminal(":"), NonTerminal("Kind")]),
+        ]],
+    },
+    Production {
+        name: "Kind",
+        alternatives: &[&[Terminal("resource")], &[Terminal("unrestricted")]],
+    },
+    Production {
+        name: "NameAndTypeActuals",
+        alternatives: &[
+            &[
+                Lexeme("NameBeginTy"),
+                CommaList(&[NonTerminal("Type")]),
+                Terminal(">"),
+            ],
+            &[Lexeme("Name")],
+        ],
+    },
+    Production {
+        name: "TypeActuals",
+        alternatives: &[&[
+            Terminal("<"),
+            CommaList(&[NonTerminal("Type")]),
+            Terminal(">"),
+        ]],
+    },
+    Production {
+        name: "Type",
+        alternatives: &[
+            &[Terminal("address")],
+            &[Terminal("u8")],
+            &[Terminal("u64")],
+            &[Terminal("u128")],
+            &[Terminal("bool")],
+            &[Terminal("bytearray")],
+            &[Lexeme("DotName"), Optional(&[NonTerminal("TypeActuals")])],
+            &[Terminal("&"), NonTerminal("Type")],
+            &[Terminal("&mut "), NonTerminal("Type")],
+            &[Lexeme("Name")],
+            &[
+                Terminal("("),
+                NonTerminal("Type"),
+                Terminal(","),
+                NonTerminal("Type"),
+                Repeated(&[Terminal(","), NonTerminal("Type")]),
+                Terminal(")"),
+            ],
+        ],
+    },
+    Production {
+        name: "Block",
+        alternatives: &[&[
+            Terminal("{"),
+            Repeated(&[NonTerminal("Statement")]),
+            Terminal("}"),
+        ]],
+    },
+    Production {
+        name: "Statement",
+        alternatives: &[
+            &[NonTerminal("Cmd"), Terminal(";")],
+            &[
+                Terminal("assert("),
+                NonTerminal("Exp"),
+                Terminal(","),
+                NonTerminal("Exp"),
+                Terminal(")"),
+            ],
+            &[NonTerminal("IfStatement")],
+            &[NonTerminal("WhileStatement")],
+            &[NonTerminal("LoopStatement")],
+            &[Terminal(";")],
+        ],
+    },
+    Production {
+        name: "IfStatement",
+        alternatives: &[&[
+            Terminal("if"),
+            Terminal("("),
+            NonTerminal("Exp"),
+            Terminal(")"),
+            NonTerminal("Block"),
+            Optional(&[Terminal("else"), NonTerminal("Block")]),
+        ]],
+    },
+    Production {
+        name: "WhileStatement",
+        alternatives: &[&[
+            Terminal("while"),
+            Terminal("("),
+            NonTerminal("Exp"),
+            Terminal(")"),
+            NonTerminal("Block"),
+        ]],
+    },
+    Production {
+        name: "LoopStatement",
+        alternatives: &[
+            &[Terminal("loop"), NonTerminal("Block")],
+            &[
+                NonTerminal("LValue"),
+                Repeated(&[Terminal(","), NonTerminal("LValue")]),
+                Terminal("="),
+                Terminal("loop"),
+                NonTerminal("Block"),
+                Terminal(";"),
+            ],
+        ],
+    },
+    Production {
+        name: "Cmd",
+        alternatives: &[
+            &[
+                NonTerminal("LValue"),
+                Repeated(&[Terminal(","), NonTerminal("LValue")]),
+                Terminal("="),
+                NonTerminal("Exp"),
+            ],
+            &[
+                NonTerminal("NameAndTypeActuals"),
+                Terminal("{"),
+                CommaList(&[NonTerminal("FieldBinding")]),
+                Terminal("}"),
+                Terminal("="),
+                NonTerminal("Exp"),
+            ],
+            &[Terminal("abort"), Optional(&[NonTerminal("Exp")])],
+            &[Terminal("return"), CommaList(&[NonTerminal("Exp")])],
+            &[Terminal("continue")],
+            &[Terminal("break"), Optional(&[NonTerminal("Exp")])],
+            &[
+                Contextual("emit"),
+                NonTerminal("Exp"),
+                Terminal(","),
+                NonTerminal("Exp"),
+            ],
+            &[NonTerminal("Call")],
+            &[
+                Terminal("("),
+                CommaList(&[NonTerminal("Exp")]),
+                Terminal(")"),
+            ],
+        ],
+    },
+    Production {
+        name: "LValue",
+        alternatives: &[
+            &[Lexeme("Name")],
+            &[Terminal("*"), NonTerminal("Exp")],
+            &[Terminal("_")],
+        ],
+    },
+    Production {
+        name: "FieldBinding",
+        alternatives: &[&[Lexeme("Name"), Optional(&[Terminal(":"), Lexeme("Name")])]],
+    },
+    Production {
+        name: "Exp",
+        alternatives: &[&[
+            NonTerminal("UnaryExp"),
+            Repeated(&[NonTerminal("BinOp"), NonTerminal("UnaryExp")]),
+        ]],
+    },
+    Production {
+        name: "BinOp",
+        alternatives: &[
+            &[Terminal("||")],
+            &[Terminal("&&")],
+            &[Terminal("==")],
+            &[Terminal("!=")],
+            &[Terminal("<")],
+            &[Terminal(">")],
+            &[Terminal("<=")],
+            &[Terminal(">=")],
+            &[Terminal("|")],
+            &[Terminal("^")],
+            &[Terminal("&")],
+            &[Terminal("<<")],
+            &[Terminal(">>")],
+            &[Terminal("+")],
+            &[Terminal("-")],
+            &[Terminal("*")],
+            &[Terminal("/")],
+            &[Terminal("%")],
+        ],
+    },
+    Production {
+        name: "UnaryExp",
+        alternatives: &[
+            &[Terminal("!"), NonTerminal("UnaryExp")],
+            &[Terminal("*"), NonTerminal("UnaryExp")],
+            &[
+                Terminal("&mut "),
+                NonTerminal("UnaryExp"),
+                Terminal("."),
+                Lexeme("Name"),
+            ],
+            &[
+                Terminal("&"),
+                NonTerminal("UnaryExp"),
+                Terminal("."),
+                Lexeme("Name"),
+            ],
+            &[NonTerminal("CallOrTerm")],
+        ],
+    },
+    Production {
+        name: "Call",
+        alternatives: &[&[
+            NonTerminal("QualifiedFunctionName"),
+            NonTerminal("CallOrTerm"),
+        ]],
+    },
+    Production {
+        name: "CallOrTerm",
+        alternatives: &[&[NonTerminal("Call")], &[NonTerminal("Term")]],
+    },
+    Production {
+        name: "QualifiedFunctionName",
+        alternatives: &[
+            &[NonTerminal("Builtin")],
+            &[Lexeme("DotName"), Optional(&[NonTerminal("TypeActuals")])],
+        ],
+    },
+    Production {
+        name: "Builtin",
+        alternatives: &[
+            &[
+                Terminal("exists<"),
+                NonTerminal("NameAndTypeActuals"),
+                Terminal(">"),
+            ],
+            &[
+                Terminal("borrow_global<"),
+                NonTerminal("NameAndTypeActuals"),
+                Terminal(">"),
+            ],
+            &[
+                Terminal("borrow_global_mut<"),
+                NonTerminal("NameAndTypeActuals"),
+                Terminal(">"),
+            ],
+            &[Terminal("get_txn_sender")],
+            &[
+                Terminal("move_from<"),
+                NonTerminal("NameAndTypeActuals"),
+                Terminal(">"),
+            ],
+            &[
+                Terminal("move_to_sender<"),
+                NonTerminal("NameAndTypeActuals"),
+                Terminal(">"),
+            ],
+            &[Terminal("freeze")],
+            &[Terminal("to_u8")],
+            &[Terminal("to_u64")],
+            &[Terminal("to_u128")],
+            &[Contextual("rotl")],
+            &[Contextual("rotr")],
+            &[Contextual("count_ones")],
+            &[Contextual("checked_add")],
+            &[Contextual("checked_sub")],
+            &[Contextual("saturating_add")],
+            &[Contextual("saturating_sub")],
+            &[Contextual("sha2_256")],
+            &[Contextual("sha3_256")],
+            &[Contextual("to_bytes<"), NonTerminal("Type"), Terminal(">")],
+        ],
+    },
+    Production {
+        name: "Term",
+        alternatives: &[
+            &[
+                Contextual("move"),
+                Terminal("("),
+                Lexeme("Name"),
+                Terminal(")"),
+            ],
+            &[
+                Contextual("copy"),
+                Terminal("("),
+                Lexeme("Name"),
+                Terminal(")"),
+            ],
+            &[Contextual("move"), Lexeme("Name")],
+            &[Contextual("copy"), Lexeme("Name")],
+            &[Lexeme("Name")],
+            &[Terminal("&mut "), Lexeme("Name")],
+            &[Terminal("&"), Lexeme("Name")],
+            &[NonTerminal("CopyableVal")],
+            &[
+                NonTerminal("NameAndTypeActuals"),
+                Terminal("{"),
+                CommaList(&[NonTerminal("FieldExp")]),
+                Terminal("}"),
+            ],
+            &[
+                Terminal("("),
+                CommaList(&[NonTerminal("Exp")]),
+                Terminal(")"),
+            ],
+            &[
+                Terminal("{"),
+                Terminal("{"),
+                Lexeme("Name"),
+                Terminal("}"),
+                Terminal("}"),
+            ],
+        ],
+    },
+    Production {
+        name: "FieldExp",
+        alternatives: &[&[Lexeme("Name"), Terminal(":"), NonTerminal("Exp")]],
+    },
+    Production {
+        name: "CopyableVal",
+        alternatives: &[
+            &[Lexeme("AccountAddress")],
+            &[Terminal("true")],
+            &[Terminal("false")],
+            &[Lexeme("U8")],
+            &[Lexeme("U64")],
+            &[Lexeme("U128")],
+            &[Lexeme("ByteArray")],
+        ],
+    },
+    Production {
+        name: "Invariant",
+        alternatives: &[&[
+            Terminal("invariant"),
+            Optional(&[Terminal("{"), Lexeme("Name"), Terminal("}")]),
+            NonTerminal("SpecExp"),
+        ]],
+    },
+    Production {
+        name: "Condition",
+        alternatives: &[
+            &[Terminal("aborts_if"), NonTerminal("SpecExp")],
+            &[Terminal("ensures"), NonTerminal("SpecExp")],
+            &[Terminal("requires"), NonTerminal("SpecExp")],
+            &[Terminal("succeeds_if"), NonTerminal("SpecExp")],
+        ],
+    },
+    Production {
+        name: "SpecExp",
+        alternatives: &[&[
+            NonTerminal("UnarySpecExp"),
+            Repeated(&[NonTerminal("SpecBinOp"), NonTerminal("UnarySpecExp")]),
+        ]],
+    },
+    Production {
+        name: "SpecBinOp",
+        alternatives: &[
+            &[Terminal("==>")],
+            &[Terminal("||")],
+            &[Terminal("&&")],
+            &[Terminal("==")],
+            &[Terminal("!=")],
+            &[Terminal("<")],
+            &[Terminal(">")],
+            &[Terminal("<=")],
+            &[Terminal(">=")],
+            &[Terminal("|")],
+            &[Terminal("^")],
+            &[Terminal("&")],
+            &[Terminal("+")],
+            &[Terminal("-")],
+            &[Terminal("*")],
+            &[Terminal("/")],
+            &[Terminal("%")],
+        ],
+    },
+    Production {
+        name: "UnarySpecExp",
+        alternatives: &[
+            &[NonTerminal("CopyableVal")],
+            &[
+                Terminal("global_exists"),
+                NonTerminal("SpecStructIdent"),
+                Terminal("("),
+                NonTerminal("StorageLocation"),
+                Terminal(")"),
+            ],
+            &[Terminal("*"), NonTerminal("StorageLocation")],
+            &[Terminal("&"), NonTerminal("StorageLocation")],
+            &[Terminal("!"), NonTerminal("UnarySpecExp")],
+            &[
+                Terminal("if"),
+                Terminal("("),
+                NonTerminal("SpecExp"),
+                Terminal(")"),
+                NonTerminal("SpecExp"),
+                Terminal("else"),
+                NonTerminal("SpecExp"),
+            ],
+            &[
+                Terminal("old"),
+                Terminal("("),
+                NonTerminal("SpecExp"),
+                Terminal(")"),
+            ],
+            &[
+                Lexeme("Name"),
+                Terminal("("),
+                CommaList(&[NonTerminal("SpecExp")]),
+                Terminal(")"),
+            ],
+            &[NonTerminal("StorageLocation")],
+        ],
+    },
+    Production {
+        name: "SpecStructIdent",
+        alternatives: &[&[
+            Terminal("<"),
+            Lexeme("Name"),
+            Terminal("."),
+            Lexeme("Name"),
+            Optional(&[NonTerminal("TypeActuals")]),
+            Terminal(">"),
+        ]],
+    },
+    Production {
+        name: "StorageLocation",
+        alternatives: &[&[
+            NonTerminal("StorageLocationBase"),
+            Repeated(&[Terminal("."), Lexeme("Name")]),
+        ]],
+    },
+    Production {
+        name: "StorageLocationBase",
+        alternatives: &[
+            &[
+                Terminal("RET"),
+                Optional(&[Terminal("("), Lexeme("U64"), Terminal(")")]),
+            ],
+            &[Terminal("txn_sender")],
+            &[Lexeme("AccountAddress")],
+            &[
+                Terminal("global"),
+                NonTerminal("SpecStructIdent"),
+                Terminal("("),
+                NonTerminal("StorageLocation"),
+                Terminal(")"),
+            ],
+            &[Lexeme("Name")],
+        ],
+    },
+];
+
+/// The classes of tokens whose spelling varies.
+pub const LEXEMES: &[LexemeDefinition] = &[
+    LexemeDefinition {
+        name: "Name",
+        definition: "[a-zA-Z$_] [a-zA-Z0-9$_]*",
+        example: "x",
+    },
+    LexemeDefinition {
+        name: "NameBeginTy",
+        definition: "Name '<'",
+        example: "T<",
+    },
+    LexemeDefinition {
+        name: "DotName",
+        definition: "Name '.' Name",
+        example: "M.f",
+    },
+    LexemeDefinition {
+        name: "AccountAddress",
+        definition: "'0' [xX] [0-9a-fA-F]+",
+        example: "0x1",
+    },
+    LexemeDefinition {
+        name: "U8",
+        definition: "[0-9]+ 'u8'",
+        example: "1u8",
+    },
+    LexemeDefinition {
+        name: "U64",
+        definition: "[0-9]+ 'u64'?",
+        example: "1",
+    },
+    LexemeDefinition {
+        name: "U128",
+        definition: "[0-9]+ 'u128'",
+        example: "1u128",
+    },
+    LexemeDefinition {
+        name: "ByteArray",
+        definition: "'h\"' [0-9a-fA-F]* '\"'",
+        example: "h\"00\"",
+    },
+    LexemeDefinition {
+        name: "String",
+        definition: "'\"' [^\"#xA]* '\"'",
+        example: "\"test\"",
+    },
+];
+
+/// Returns the grammar in the W3C EBNF notation.
+pub fn to_ebnf() -> String {
+    let mut out = String::new();
+    for production in PRODUCTIONS {
+        write!(out, "{} ::=", production.name).unwrap();
+        for (i, alternative) in production.alternatives.iter().enumerate() {
+            if i > 0 {
+                out.push_str("\n    |");
+            }
+            out.push(' ');
+            out.push_str(&sequence_to_ebnf(alternative));
+        }
+        out.push('\n');
+    }
+    out.push_str("\n/* Lexemes, which do not contain whitespace. */\n");
+    for lexeme in LEXEMES {
+        writeln!(out, "{} ::= {}", lexeme.name, lexeme.definition).unwrap();
+    }
+    out
+}
+
+fn sequence_to_ebnf(symbols: &[Symbol]) -> String {
+    symbols
+        .iter()
+        .map(symbol_to_ebnf)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn symbol_to_ebnf(symbol: &Symbol) -> String {
+    match symbol {
+        Terminal(spelling) | Contextual(spelling) => format!("'{}'", spelling),
+        Lexeme(name) | NonTerminal(name) => (*name).to_string(),
+        Optional(symbols) => format!("{}?", group(symbols)),
+        Repeated(symbols) => format!("{}*", group(symbols)),
+        CommaList(symbols) => {
+            let item = sequence_to_ebnf(symbols);
+            format!("( {} ( ',' {} )* ','? )?", item, item)
+        }
+    }
+}
+
+/// Prints the symbols as a single EBNF item, in parentheses unless there is only one.
+fn group(symbols: &[Symbol]) -> String {
+    if symbols.len() == 1 {
+        if let Terminal(_) | Contextual(_) | Lexeme(_) | NonTerminal(_) = symbols[0] {
+            return symbol_to_ebnf(&symbols[0]);
+        }
+    }
+    format!("( {} )", sequence_to_ebnf(symbols))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{get_contextual_token, Lexer, Tok};
+    use std::collections::BTreeSet;
+
+    /// Every token of the lexer. `lexeme_of` fails to compile when a token is added, as a
+    /// reminder to add it here too.
+    const ALL_TOKENS: &[Tok] = &[
+        Tok::EOF,
+        Tok::AccountAddressValue,
+        Tok::U8Value,
+        Tok::U64Value,
+        Tok::U128Value,
+        Tok::NameValue,
+        Tok::NameBeginTyValue,
+        Tok::DotNameValue,
+        Tok::ByteArrayValue,
+        Tok::StringValue,
+        Tok::Exclaim,
+        Tok::ExclaimEqual,
+        Tok::NumSign,
+        Tok::Percent,
+        Tok::Amp,
+        Tok::AmpAmp,
+        Tok::AmpMut,
+        Tok::LParen,
+        Tok::RParen,
+        Tok::Star,
+        Tok::Plus,
+        Tok::Comma,
+        Tok::Minus,
+        Tok::Period,
+        Tok::Slash,
+        Tok::Colon,
+        Tok::Semicolon,
+        Tok::Less,
+        Tok::LessEqual,
+        Tok::LessLess,
+        Tok::Equal,
+        Tok::EqualEqual,
+        Tok::EqualEqualGreater,
+        Tok::Greater,
+        Tok::GreaterEqual,
+        Tok::GreaterGreater,
+        Tok::LBracket,
+        Tok::RBracket,
+        Tok::Caret,
+        Tok::Underscore,
+        Tok::Abort,
+        Tok::AbortsIf,
+        Tok::Acquires,
+        Tok::Address,
+        Tok::As,
+        Tok::Assert,
+        Tok::Bool,
+        Tok::BorrowGlobal,
+        Tok::BorrowGlobalMut,
+        Tok::Break,
+        Tok::Bytearray,
+        Tok::Continue,
+        Tok::Copy,
+        Tok::Else,
+        Tok::Emit,
+        Tok::Ensures,
+        Tok::Exists,
+        Tok::False,
+        Tok::Freeze,
+        Tok::GetTxnSender,
+        Tok::Global,
+        Tok::GlobalExists,
+        Tok::ToU8,
+        Tok::ToU64,
+        Tok::ToU128,
+        Tok::Rotl,
+        Tok::Rotr,
+        Tok::CountOnes,
+        Tok::CheckedAdd,
+        Tok::CheckedSub,
+        Tok::SaturatingAdd,
+        Tok::SaturatingSub,
+        Tok::Sha2_256,
+        Tok::Sha3_256,
+        Tok::ToBytes,
+        Tok::If,
+        Tok::Import,
+        Tok::Invariant,
+        Tok::Let,
+        Tok::Loop,
+        Tok::Main,
+        Tok::Module,
+        Tok::Modules,
+        Tok::Move,
+        Tok::MoveFrom,
+        Tok::MoveToSender,
+        Tok::Native,
+        Tok::Old,
+        Tok::Pragma,
+        Tok::Public,
+        Tok::Requires,
+        Tok::Resource,
+        Tok::SpecReturn,
+        Tok::Return,
+        Tok::Script,
+        Tok::Struct,
+        Tok::SucceedsIf,
+        Tok::Synthetic,
+        Tok::True,
+        Tok::TxnSender,
+        Tok::U8,
+        Tok::U64,
+        Tok::U128,
+        Tok::Unrestricted,
+        Tok::While,
+        Tok::LBrace,
+        Tok::Pipe,
+        Tok::PipePipe,
+        Tok::RBrace,
+    ];
+
+    /// The lexeme of the tokens whose spelling varies.
+    fn lexeme_of(tok: Tok) -> Option<&'static str> {
+        match tok {
+            Tok::AccountAddressValue => Some("AccountAddress"),
+            Tok::U8Value => Some("U8"),
+            Tok::U64Value => Some("U64"),
+            Tok::U128Value => Some("U128"),
+            Tok::NameValue => Some("Name"),
+            Tok::NameBeginTyValue => Some("NameBeginTy"),
+            Tok::DotNameValue => Some("DotName"),
+            Tok::ByteArrayValue => Some("ByteArray"),
+            Tok::StringValue => Some("String"),
+            Tok::EOF
+            | Tok::Exclaim
+            | Tok::ExclaimEqual
+            | Tok::NumSign
+            | Tok::Percent
+            | Tok::Amp
+            | Tok::AmpAmp
+            | Tok::AmpMut
+            | Tok::LParen
+            | Tok::RParen
+            | Tok::Star
+            | Tok::Plus
+            | Tok::Comma
+            | Tok::Minus
+            | Tok::Period
+            | Tok::Slash
+            | Tok::Colon
+            | Tok::Semicolon
+            | Tok::Less
+            | Tok::LessEqual
+            | Tok::LessLess
+            | Tok::Equal
+            | Tok::EqualEqual
+            | Tok::EqualEqualGreater
+            | Tok::Greater
+            | Tok::GreaterEqual
+            | Tok::GreaterGreater
+            | Tok::LBracket
+            | Tok::RBracket
+            | Tok::Caret
+            | Tok::Underscore
+            | Tok::Abort
+            | Tok::AbortsIf
+            | Tok::Acquires
+            | Tok::Address
+            | Tok::As
+            | Tok::Assert
+            | Tok::Bool
+            | Tok::BorrowGlobal
+            | Tok::BorrowGlobalMut
+            | Tok::Break
+            | Tok::Bytearray
+            | Tok::Continue
+            | Tok::Copy
+            | Tok::Else
+            | Tok::Emit
+            | Tok::Ensures
+            | Tok::Exists
+            | Tok::False
+            | Tok::Freeze
+            | Tok::GetTxnSender
+            | Tok::Global
+            | Tok::GlobalExists
+            | Tok::ToU8
+            | Tok::ToU64
+            | Tok::ToU128
+            | Tok::Rotl
+            | Tok::Rotr
+            | Tok::CountOnes
+            | Tok::CheckedAdd
+            | Tok::CheckedSub
+            | Tok::SaturatingAdd
+            | Tok::SaturatingSub
+            | Tok::Sha2_256
+            | Tok::Sha3_256
+            | Tok::ToBytes
+            | Tok::If
+            | Tok::Import
+            | Tok::Invariant
+            | Tok::Let
+            | Tok::Loop
+            | Tok::Main
+            | Tok::Module
+            | Tok::Modules
+            | Tok::Move
+            | Tok::MoveFrom
+            | Tok::MoveToSender
+            | Tok::Native
+            | Tok::Old
+            | Tok::Pragma
+            | Tok::Public
+            | Tok::Requires
+            | Tok::Resource
+            | Tok::SpecReturn
+            | Tok::Return
+            | Tok::Script
+            | Tok::Struct
+            | Tok::SucceedsIf
+            | Tok::Synthetic
+            | Tok::True
+            | Tok::TxnSender
+            | Tok::U8
+            | Tok::U64
+            | Tok::U128
+            | Tok::Unrestricted
+            | Tok::While
+            | Tok::LBrace
+            | Tok::Pipe
+            | Tok::PipePipe
+            | Tok::RBrace => None,
+        }
+    }
+
+    /// Lexes `text`, which must be exactly one token.
+    fn lex_one(text: &str) -> Tok {
+        let mut lexer = Lexer::new(text);
+        lexer.advance().unwrap();
+        let tok = lexer.peek();
+        assert_eq!(lexer.content(), text, "{:?} is not a single token", text);
+        lexer.advance().unwrap();
+        assert_eq!(lexer.peek(), Tok::EOF, "{:?} is not a single token", text);
+        tok
+    }
+
+    fn visit(symbols: &[Symbol], f: &mut impl FnMut(&Symbol)) {
+        for symbol in symbols {
+            f(symbol);
+            match symbol {
+                Optional(inner) | Repeated(inner) | CommaList(inner) => visit(inner, f),
+                Terminal(_) | Contextual(_) | Lexeme(_) | NonTerminal(_) => (),
+            }
+        }
+    }
+
+    fn all_symbols() -> Vec<Symbol> {
+        let mut symbols = vec![];
+        for production in PRODUCTIONS {
+            for alternative in production.alternatives {
+                visit(alternative, &mut |symbol| symbols.push(*symbol));
+            }
+        }
+        symbols
+    }
+
+    #[test]
+    fn terminals_are_tokens() {
+        for symbol in all_symbols() {
+            if let Terminal(spelling) = symbol {
+                let tok = lex_one(spelling);
+                assert_eq!(lexeme_of(tok), None, "{:?} is not a fixed token", spelling);
+            }
+        }
+    }
+
+    /// Returns the keyword of a contextual name, which is lexed as a name.
+    fn contextual_token(spelling: &str) -> Tok {
+        let tok = lex_one(spelling);
+        assert!(
+            tok == Tok::NameValue || tok == Tok::NameBeginTyValue,
+            "{:?} is not lexed as a name",
+            spelling
+        );
+        let name = spelling.trim_end_matches('<');
+        get_contextual_token(name).unwrap_or_else(|| panic!("{:?} is not contextual", spelling))
+    }
+
+    #[test]
+    fn contextual_names_are_names() {
+        for symbol in all_symbols() {
+            if let Contextual(spelling) = symbol {
+                contextual_token(spelling);
+            }
+        }
+    }
+
+    #[test]
+    fn lexeme_examples_are_tokens() {
+        for lexeme in LEXEMES {
+            assert_eq!(lexeme_of(lex_one(lexeme.example)), Some(lexeme.name));
+        }
+    }
+
+    #[test]
+    fn every_token_is_used() {
+        let mut used = BTreeSet::new();
+        used.insert(Tok::EOF);
+        for symbol in all_symbols() {
+            match symbol {
+                Terminal(spelling) => {
+                    used.insert(lex_one(spelling));
+                }
+                Contextual(spelling) => {
+                    used.insert(contextual_token(spelling));
+                }
+                Lexeme(name) => {
+                    let lexeme = LEXEMES.iter().find(|lexeme| lexeme.name == name);
+                    let lexeme = lexeme.unwrap_or_else(|| panic!("undefined lexeme {}", name));
+                    used.insert(lex_one(lexeme.example));
+                }
+                _ => (),
+            }
+        }
+        for tok in ALL_TOKENS {
+            assert!(used.contains(tok), "{:?} is not used by the grammar", tok);
+        }
+    }
+
+    #[test]
+    fn every_non_terminal_is_defined_once() {
+        let mut names = BTreeSet::new();
+        for production in PRODUCTIONS {
+            assert!(
+                names.insert(production.name),
+                "{} is defined twice",
+                production.name
+            );
+        }
+        for symbol in all_symbols() {
+            if let NonTerminal(name) = symbol {
+                assert!(names.contains(name), "{} is not defined", name);
+            }
+        }
+    }
+
+    #[test]
+    fn ebnf_export() {
+        let ebnf = to_ebnf();
+        assert!(ebnf.contains("WhileStatement ::= 'while' '(' Exp ')' Block\n"));
+        assert!(ebnf.contains(
+            "Script ::= ( CfgAttribute? ImportDecl )* 'main' \
+             ( '<' ( TypeFormal ( ',' TypeFormal )* ','? )? '>' )? \
+             '(' ( ArgDecl ( ',' ArgDecl )* ','? )? ')' ReturnType? FunctionBlock\n"
+        ));
+        assert!(ebnf.contains("StorageLocation ::= StorageLocationBase ( '.' Name )*\n"));
+        assert!(ebnf.contains("U64 ::= [0-9]+ 'u64'?\n"));
+    }
+}
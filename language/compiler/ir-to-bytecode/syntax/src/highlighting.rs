@@ -0,0 +1,421 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Editor syntax highlighting.
+//!
+//! [`to_textmate`](fn.to_textmate.html) generates a TextMate grammar, the format of VS Code and
+//! Sublime Text highlighting, and of most Vim and Emacs highlighting plugins. The keywords and
+//! operators are the terminals of the [`grammar`](../grammar/index.html), classified by the token
+//! the lexer reads them as, so the highlighting cannot drift from the real lexer: a new token does
+//! not compile until it is given a class here. Contextual names, such as `rotl`, are classified by
+//! the keyword the parser reads them as.
+
+use crate::{
+    grammar::{Symbol, PRODUCTIONS},
+    lexer::{get_contextual_token, Lexer, Tok},
+};
+use std::{collections::BTreeSet, fmt::Write};
+
+/// The highlighting classes of tokens.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TokenClass {
+    /// Control flow, e.g. `while` or `return`.
+    Control,
+    /// Declarations and modifiers, e.g. `module` or `public`.
+    Declaration,
+    /// Keywords of the specification language, e.g. `ensures`.
+    Specification,
+    /// Builtin functions, e.g. `borrow_global` or `move`.
+    Builtin,
+    /// Primitive types, e.g. `u64`.
+    Type,
+    /// `true` and `false`.
+    Boolean,
+    /// Account addresses, e.g. `0x1`.
+    Address,
+    /// Integer literals, e.g. `1u8`.
+    Number,
+    /// Byte array literals, e.g. `h"00"`.
+    ByteArray,
+    /// String literals, e.g. `"test"` in `#[cfg(feature = "test")]`.
+    String,
+    /// Operators, e.g. `+` or `&mut`.
+    Operator,
+    /// `//` comments.
+    Comment,
+}
+
+impl TokenClass {
+    /// The TextMate scope of the class, which themes choose colors for.
+    pub fn scope(self) -> &'static str {
+        match self {
+            TokenClass::Control => "keyword.control.mvir",
+            TokenClass::Declaration => "storage.modifier.mvir",
+            TokenClass::Specification => "keyword.other.specification.mvir",
+            TokenClass::Builtin => "support.function.builtin.mvir",
+            TokenClass::Type => "storage.type.mvir",
+            TokenClass::Boolean => "constant.language.boolean.mvir",
+            TokenClass::Address => "constant.numeric.address.mvir",
+            TokenClass::Number => "constant.numeric.integer.mvir",
+            TokenClass::ByteArray => "string.quoted.double.bytearray.mvir",
+            TokenClass::String => "string.quoted.double.mvir",
+            TokenClass::Operator => "keyword.operator.mvir",
+            TokenClass::Comment => "comment.line.double-slash.mvir",
+        }
+    }
+}
+
+/// The class of a token with a fixed spelling, or `None` if it is not highlighted.
+fn class_of(tok: Tok) -> Option<TokenClass> {
+    match tok {
+        Tok::Abort
+        | Tok::Break
+        | Tok::Continue
+        | Tok::Else
+        | Tok::If
+        | Tok::Loop
+        | Tok::Return
+        | Tok::While => Some(TokenClass::Control),
+        Tok::Acquires
+        | Tok::As
+        | Tok::Import
+        | Tok::Invariant
+        | Tok::Let
+        | Tok::Main
+        | Tok::Module
+        | Tok::Modules
+        | Tok::Native
+        | Tok::Pragma
+        | Tok::Public
+        | Tok::Resource
+        | Tok::Script
+        | Tok::Struct
+        | Tok::Synthetic
+        | Tok::Unrestricted => Some(TokenClass::Declaration),
+        Tok::AbortsIf
+        | Tok::Ensures
+        | Tok::Global
+        | Tok::GlobalExists
+        | Tok::Old
+        | Tok::Requires
+        | Tok::SpecReturn
+        | Tok::SucceedsIf
+        | Tok::TxnSender => Some(TokenClass::Specification),
+        Tok::Assert
+        | Tok::BorrowGlobal
+        | Tok::BorrowGlobalMut
+        | Tok::Copy
+        | Tok::Emit
+        | Tok::Exists
+        | Tok::Freeze
+        | Tok::GetTxnSender
+        | Tok::Move
+        | Tok::MoveFrom
+        | Tok::MoveToSender
+        | Tok::ToU8
+        | Tok::ToU64
+        | Tok::ToU128
+        | Tok::Rotl
+        | Tok::Rotr
+        | Tok::CountOnes
+        | Tok::CheckedAdd
+        | Tok::CheckedSub
+        | Tok::SaturatingAdd
+        | Tok::SaturatingSub
+        | Tok::Sha2_256
+        | Tok::Sha3_256
+        | Tok::ToBytes => Some(TokenClass::Builtin),
+        Tok::Address | Tok::Bool | Tok::Bytearray | Tok::U8 | Tok::U64 | Tok::U128 => {
+            Some(TokenClass::Type)
+        }
+        Tok::True | Tok::False => Some(TokenClass::Boolean),
+        Tok::Exclaim
+        | Tok::ExclaimEqual
+        | Tok::Percent
+        | Tok::Amp
+        | Tok::AmpAmp
+        | Tok::AmpMut
+        | Tok::Star
+        | Tok::Plus
+        | Tok::Minus
+        | Tok::Slash
+        | Tok::Less
+        | Tok::LessEqual
+        | Tok::LessLess
+        | Tok::Equal
+        | Tok::EqualEqual
+        | Tok::EqualEqualGreater
+        | Tok::Greater
+        | Tok::GreaterEqual
+        | Tok::GreaterGreater
+        | Tok::Caret
+        | Tok::Pipe
+        | Tok::PipePipe => Some(TokenClass::Operator),
+        // Literals are highlighted by `LITERALS`, and names and punctuation are not highlighted.
+        Tok::EOF
+        | Tok::AccountAddressValue
+        | Tok::U8Value
+        | Tok::U64Value
+        | Tok::U128Value
+        | Tok::NameValue
+        | Tok::NameBeginTyValue
+        | Tok::DotNameValue
+        | Tok::ByteArrayValue
+        | Tok::StringValue
+        | Tok::NumSign
+        | Tok::LBracket
+        | Tok::RBracket
+        | Tok::LParen
+        | Tok::RParen
+        | Tok::Comma
+        | Tok::Period
+        | Tok::Colon
+        | Tok::Semicolon
+        | Tok::Underscore
+        | Tok::LBrace
+        | Tok::RBrace => None,
+    }
+}
+
+/// A pattern highlighting the tokens of one of the grammar `LEXEMES`.
+pub struct LiteralPattern {
+    /// The name of the lexeme.
+    pub lexeme: &'static str,
+    pub class: TokenClass,
+    /// A regular expression matching exactly the tokens of the lexeme.
+    pub pattern: &'static str,
+}
+
+/// The patterns of the literals, in the order they must be tried in.
+pub const LITERALS: &[LiteralPattern] = &[
+    LiteralPattern {
+        lexeme: "ByteArray",
+        class: TokenClass::ByteArray,
+        pattern: r#"\bh"[0-9a-fA-F]*""#,
+    },
+    LiteralPattern {
+        lexeme: "String",
+        class: TokenClass::String,
+        pattern: r#""[^"\n]*""#,
+    },
+    LiteralPattern {
+        lexeme: "AccountAddress",
+        class: TokenClass::Address,
+        pattern: r"\b0[xX][0-9a-fA-F]+\b",
+    },
+    LiteralPattern {
+        lexeme: "U8",
+        class: TokenClass::Number,
+        pattern: r"\b[0-9]+u8\b",
+    },
+    LiteralPattern {
+        lexeme: "U128",
+        class: TokenClass::Number,
+        pattern: r"\b[0-9]+u128\b",
+    },
+    LiteralPattern {
+        lexeme: "U64",
+        class: TokenClass::Number,
+        pattern: r"\b[0-9]+(?:u64)?\b",
+    },
+];
+
+const COMMENT_PATTERN: &str = "//.*$";
+
+/// Returns the terminals of the grammar that are highlighted, with their class.
+pub fn highlighted_terminals() -> Vec<(TokenClass, &'static str)> {
+    let mut terminals = BTreeSet::new();
+    for production in PRODUCTIONS {
+        for alternative in production.alternatives {
+            collect_terminals(alternative, &mut terminals);
+        }
+    }
+    terminals
+        .into_iter()
+        .filter_map(|spelling| {
+            let mut lexer = Lexer::new(spelling);
+            lexer.advance().ok()?;
+            let tok = match lexer.peek() {
+                Tok::NameValue | Tok::NameBeginTyValue => {
+                    get_contextual_token(spelling.trim_end_matches('<'))?
+                }
+                tok => tok,
+            };
+            class_of(tok).map(|class| (class, spelling))
+        })
+        .collect()
+}
+
+fn collect_terminals(symbols: &[Symbol], terminals: &mut BTreeSet<&'static str>) {
+    for symbol in symbols {
+        match symbol {
+            Symbol::Terminal(spelling) | Symbol::Contextual(spelling) => {
+                terminals.insert(*spelling);
+            }
+            Symbol::Optional(inner) | Symbol::Repeated(inner) | Symbol::CommaList(inner) => {
+                collect_terminals(inner, terminals)
+            }
+            Symbol::Lexeme(_) | Symbol::NonTerminal(_) => (),
+        }
+    }
+}
+
+/// Returns the regular expression matching the terminal where the lexer reads it. Some keywords
+/// are only read as such when followed by a character, e.g. `move(`, which is matched by
+/// lookahead so that the character is left to the next pattern.
+fn terminal_pattern(spelling: &str) -> String {
+    let spelling = spelling.trim_end();
+    let (word, lookahead) = match spelling.chars().last() {
+        Some(c @ '<') | Some(c @ '(') | Some(c @ ':')
+            if spelling.starts_with(|c: char| c.is_ascii_alphabetic()) =>
+        {
+            (&spelling[..spelling.len() - 1], Some(c))
+        }
+        _ => (spelling, None),
+    };
+    let mut pattern = String::new();
+    if word.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        pattern.push_str(r"\b");
+    }
+    pattern.push_str(&escape_regex(word));
+    if word.ends_with(|c: char| c.is_ascii_alphanumeric() || c == '_') {
+        pattern.push_str(r"\b");
+    }
+    if let Some(c) = lookahead {
+        write!(pattern, "(?={})", escape_regex(&c.to_string())).unwrap();
+    }
+    pattern
+}
+
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Returns the TextMate grammar of Move IR, in JSON, for the `.mvir` files.
+pub fn to_textmate() -> String {
+    let mut patterns = vec![(TokenClass::Comment, COMMENT_PATTERN.to_string())];
+    for literal in LITERALS {
+        patterns.push((literal.class, literal.pattern.to_string()));
+    }
+
+    // Longer terminals first, so that e.g. `==>` is not highlighted as `==` and `>`.
+    let mut terminals = highlighted_terminals();
+    terminals.sort_by_key(|(class, spelling)| (*class, std::cmp::Reverse(spelling.len())));
+    let mut i = 0;
+    while i < terminals.len() {
+        let class = terminals[i].0;
+        let alternatives: Vec<_> = terminals[i..]
+            .iter()
+            .take_while(|(c, _)| *c == class)
+            .map(|(_, spelling)| terminal_pattern(spelling))
+            .collect();
+        i += alternatives.len();
+        patterns.push((class, format!("(?:{})", alternatives.join("|"))));
+    }
+
+    let mut out = String::new();
+    out.push_str("{\n");
+    out.push_str("  \"name\": \"Move IR\",\n");
+    out.push_str("  \"scopeName\": \"source.mvir\",\n");
+    out.push_str("  \"fileTypes\": [\"mvir\"],\n");
+    out.push_str("  \"patterns\": [\n");
+    for (i, (class, pattern)) in patterns.iter().enumerate() {
+        write!(
+            out,
+            "    {{ \"name\": {}, \"match\": {} }}",
+            json_string(class.scope()),
+            json_string(pattern)
+        )
+        .unwrap();
+        out.push_str(if i + 1 < patterns.len() { ",\n" } else { "\n" });
+    }
+    out.push_str("  ]\n}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::LEXEMES;
+    use regex::bytes::{Regex, RegexBuilder};
+
+    /// Compiles `pattern` with the ASCII word boundaries of TextMate grammars.
+    fn regex(pattern: &str) -> Regex {
+        RegexBuilder::new(pattern).unicode(false).build().unwrap()
+    }
+
+    #[test]
+    fn literal_patterns_match_lexeme_examples() {
+        for literal in LITERALS {
+            let lexeme = LEXEMES
+                .iter()
+                .find(|lexeme| lexeme.name == literal.lexeme)
+                .unwrap_or_else(|| panic!("undefined lexeme {}", literal.lexeme));
+            let regex = regex(&format!("^(?:{})$", literal.pattern));
+            assert!(
+                regex.is_match(lexeme.example.as_bytes()),
+                "{} does not match {:?}",
+                literal.pattern,
+                lexeme.example
+            );
+        }
+    }
+
+    #[test]
+    fn terminals_are_classified() {
+        let terminals = highlighted_terminals();
+        assert!(terminals.contains(&(TokenClass::Control, "while")));
+        assert!(terminals.contains(&(TokenClass::Builtin, "borrow_global<")));
+        assert!(terminals.contains(&(TokenClass::Builtin, "rotl")));
+        assert!(terminals.contains(&(TokenClass::Declaration, "pragma")));
+        assert!(terminals.contains(&(TokenClass::Specification, "aborts_if")));
+        assert!(terminals.contains(&(TokenClass::Operator, "==>")));
+        assert!(!terminals.iter().any(|(_, spelling)| *spelling == "{"));
+    }
+
+    #[test]
+    fn terminal_patterns() {
+        assert_eq!(terminal_pattern("while"), r"\bwhile\b");
+        assert_eq!(terminal_pattern("move("), r"\bmove\b(?=\()");
+        assert_eq!(terminal_pattern("modules:"), r"\bmodules\b(?=:)");
+        assert_eq!(terminal_pattern("&mut "), r"&mut\b");
+        assert_eq!(terminal_pattern("<<"), "<<");
+        assert_eq!(terminal_pattern("||"), r"\|\|");
+    }
+
+    #[test]
+    fn textmate_export() {
+        let grammar: serde_json::Value = serde_json::from_str(&to_textmate()).unwrap();
+        assert_eq!(grammar["scopeName"], "source.mvir");
+        let patterns = grammar["patterns"].as_array().unwrap();
+        assert_eq!(patterns[0]["name"], TokenClass::Comment.scope());
+        for pattern in patterns {
+            let pattern = pattern["match"].as_str().unwrap();
+            // The Rust regex engine lacks lookahead, which only follows keywords.
+            if !pattern.contains("(?=") {
+                regex(pattern);
+            }
+        }
+    }
+}
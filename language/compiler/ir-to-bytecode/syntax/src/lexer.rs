@@ -1,7 +1,9 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::syntax::ParseError;
+use crate::syntax::{ParseError, ParserOptions};
+use codespan::{ByteIndex, Span};
+use libra_types::identifier::Identifier;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Tok {
@@ -14,8 +16,10 @@ pub enum Tok {
     NameBeginTyValue,
     DotNameValue,
     ByteArrayValue,
+    StringValue,
     Exclaim,
     ExclaimEqual,
+    NumSign,
     Percent,
     Amp,
     AmpAmp,
@@ -39,6 +43,8 @@ pub enum Tok {
     Greater,
     GreaterEqual,
     GreaterGreater,
+    LBracket,
+    RBracket,
     Caret,
     Underscore,
     /// Abort statement in the Move language
@@ -57,6 +63,7 @@ pub enum Tok {
     Continue,
     Copy,
     Else,
+    Emit,
     Ensures,
     Exists,
     False,
@@ -70,6 +77,16 @@ pub enum Tok {
     ToU8,
     ToU64,
     ToU128,
+    Rotl,
+    Rotr,
+    CountOnes,
+    CheckedAdd,
+    CheckedSub,
+    SaturatingAdd,
+    SaturatingSub,
+    Sha2_256,
+    Sha3_256,
+    ToBytes,
     If,
     Import,
     /// For spec language
@@ -84,6 +101,7 @@ pub enum Tok {
     MoveToSender,
     Native,
     Old,
+    Pragma,
     Public,
     Requires,
     Resource,
@@ -122,6 +140,9 @@ impl Tok {
 
 pub struct Lexer<'input> {
     pub spec_mode: bool,
+    pub options: ParserOptions,
+    /// The version declared by the language pragma of the source, once parsed.
+    pub language_version: Option<u64>,
     text: &'input str,
     prev_end: usize,
     cur_start: usize,
@@ -133,6 +154,8 @@ impl<'input> Lexer<'input> {
     pub fn new(s: &'input str) -> Lexer {
         Lexer {
             spec_mode: false, // read tokens without trailing punctuation during specs.
+            options: ParserOptions::default(),
+            language_version: None,
             text: s,
             prev_end: 0,
             cur_start: 0,
@@ -141,11 +164,27 @@ impl<'input> Lexer<'input> {
         }
     }
 
+    /// Creates a lexer reading `s` from `offset`, which must be at the start of a token. Locations
+    /// are still offsets in `s`.
+    #[cfg(feature = "parallel")]
+    pub fn starting_at(s: &'input str, offset: usize) -> Lexer<'input> {
+        let mut lexer = Lexer::new(s);
+        lexer.prev_end = offset;
+        lexer.cur_start = offset;
+        lexer.cur_end = offset;
+        lexer
+    }
+
     pub fn peek(&self) -> Tok {
         self.token
     }
 
-    pub fn content(&self) -> &str {
+    /// The whole text being lexed, which locations are offsets in.
+    pub fn source(&self) -> &'input str {
+        self.text
+    }
+
+    pub fn content(&self) -> &'input str {
         &self.text[self.cur_start..self.cur_end]
     }
 
@@ -158,20 +197,45 @@ impl<'input> Lexer<'input> {
     }
 
     pub fn lookahead(&self) -> Result<Tok, ParseError<usize, anyhow::Error>> {
-        let text = self.text[self.cur_end..].trim_start();
-        let offset = self.text.len() - text.len();
-        let (tok, _) = find_token(text, offset, self.spec_mode)?;
+        let offset = skip_whitespace(self.text, self.cur_end);
+        let (tok, _) = find_token(&self.text[offset..], offset, self.spec_mode)?;
         Ok(tok)
     }
 
+    /// Whether the next token directly follows the current one, with no whitespace in between.
+    pub fn next_is_adjacent(&self) -> bool {
+        skip_whitespace(self.text, self.cur_end) == self.cur_end
+    }
+
     pub fn advance(&mut self) -> Result<(), ParseError<usize, anyhow::Error>> {
         self.prev_end = self.cur_end;
-        let text = self.text[self.cur_end..].trim_start();
-        self.cur_start = self.text.len() - text.len();
+        self.cur_start = skip_whitespace(self.text, self.cur_end);
+        let text = &self.text[self.cur_start..];
         let (token, len) = find_token(text, self.cur_start, self.spec_mode)?;
         self.cur_end = self.cur_start + len;
         self.token = token;
-        Ok(())
+        self.check_reserved()
+    }
+
+    // Fail if the current token is a name reserved by the options.
+    fn check_reserved(&self) -> Result<(), ParseError<usize, anyhow::Error>> {
+        let reserved = self.options.reserved_names;
+        if reserved.is_empty() {
+            return Ok(());
+        }
+        let content = self.content();
+        match self.token {
+            Tok::NameValue => check_not_reserved(reserved, content, self.cur_start),
+            Tok::NameBeginTyValue => {
+                check_not_reserved(reserved, &content[..content.len() - 1], self.cur_start)
+            }
+            Tok::DotNameValue => {
+                let dot = content.find('.').unwrap();
+                check_not_reserved(reserved, &content[..dot], self.cur_start)?;
+                check_not_reserved(reserved, &content[dot + 1..], self.cur_start + dot + 1)
+            }
+            _ => Ok(()),
+        }
     }
 
     pub fn replace_token(
@@ -185,20 +249,112 @@ impl<'input> Lexer<'input> {
     }
 }
 
+// Classes of ASCII bytes, as bit sets, so that the scanning loops below look up a table instead of
+// matching characters. Non-ASCII bytes belong to no class.
+const WS: u8 = 0x1; // whitespace, as for char::is_whitespace
+const ID: u8 = 0x2; // [a-zA-Z$_]
+const DG: u8 = 0x4; // [0-9]
+const HX: u8 = 0x8; // [0-9a-fA-F]
+
+#[rustfmt::skip]
+static BYTE_CLASSES: [u8; 128] = [
+    // 0x00
+    0, 0, 0, 0, 0, 0, 0, 0, 0, WS, WS, WS, WS, WS, 0, 0,
+    // 0x10
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    // 0x20
+    WS, 0, 0, 0, ID, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    // 0x30
+    DG | HX, DG | HX, DG | HX, DG | HX, DG | HX, DG | HX, DG | HX, DG | HX, DG | HX, DG | HX, 0, 0, 0, 0, 0, 0,
+    // 0x40
+    0, ID | HX, ID | HX, ID | HX, ID | HX, ID | HX, ID | HX, ID, ID, ID, ID, ID, ID, ID, ID, ID,
+    // 0x50
+    ID, ID, ID, ID, ID, ID, ID, ID, ID, ID, ID, 0, 0, 0, 0, ID,
+    // 0x60
+    0, ID | HX, ID | HX, ID | HX, ID | HX, ID | HX, ID | HX, ID, ID, ID, ID, ID, ID, ID, ID, ID,
+    // 0x70
+    ID, ID, ID, ID, ID, ID, ID, ID, ID, ID, ID, 0, 0, 0, 0, 0,
+];
+
+fn byte_class(byte: u8) -> u8 {
+    BYTE_CLASSES.get(byte as usize).copied().unwrap_or(0)
+}
+
+// Return the length of the longest prefix of `text` made of bytes in `class`.
+fn class_len(text: &str, class: u8) -> usize {
+    text.bytes()
+        .position(|byte| byte_class(byte) & class == 0)
+        .unwrap_or_else(|| text.len())
+}
+
+// Return the offset of the first non-whitespace character of `text` at or after `offset`, as
+// `trim_start` would. Runs of ASCII whitespace are skipped through the byte table, and only
+// non-ASCII characters go through `trim_start`.
+fn skip_whitespace(text: &str, mut offset: usize) -> usize {
+    loop {
+        offset += class_len(&text[offset..], WS);
+        match text.as_bytes().get(offset) {
+            Some(byte) if !byte.is_ascii() => {
+                let trimmed_offset = text.len() - text[offset..].trim_start().len();
+                if trimmed_offset == offset {
+                    return offset;
+                }
+                offset = trimmed_offset;
+            }
+            _ => return offset,
+        }
+    }
+}
+
+/// Returns the offsets of the `module` keywords of `text` after `offset` that are outside of
+/// braces, which start the modules of a program. This only looks at bytes, to be much faster than
+/// lexing, so it can be fooled by malformed input: the parser checks that the modules end where
+/// the next ones start.
+pub fn top_level_module_starts(text: &str, offset: usize) -> Vec<usize> {
+    const MODULE: &[u8] = b"module";
+    let bytes = text.as_bytes();
+    let is_name_byte = |i: usize| {
+        bytes
+            .get(i)
+            .map_or(false, |b| byte_class(*b) & (ID | DG) != 0)
+    };
+    let mut starts = vec![];
+    let mut depth = 0usize;
+    let mut i = offset;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => depth += 1,
+            b'}' => depth = depth.saturating_sub(1),
+            b'm' if depth == 0
+                && bytes[i..].starts_with(MODULE)
+                && !(i > 0 && is_name_byte(i - 1))
+                && !is_name_byte(i + MODULE.len()) =>
+            {
+                starts.push(i);
+                i += MODULE.len();
+                continue;
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    starts
+}
+
 // Find the next token and its length without changing the state of the lexer.
 fn find_token(
     text: &str,
     start_offset: usize,
     spec_mode: bool,
 ) -> Result<(Tok, usize), ParseError<usize, anyhow::Error>> {
-    let c: char = match text.chars().next() {
-        Some(next_char) => next_char,
+    let c = match text.as_bytes().first() {
+        Some(next_byte) => *next_byte,
         None => {
             return Ok((Tok::EOF, 0));
         }
     };
     let (tok, len) = match c {
-        '0'..='9' => {
+        b'0'..=b'9' => {
             if (text.starts_with("0x") || text.starts_with("0X")) && text.len() > 2 {
                 let hex_len = get_hex_digits_len(&text[2..]);
                 if hex_len == 0 {
@@ -211,12 +367,12 @@ fn find_token(
                 get_decimal_number(&text)
             }
         }
-        'a'..='z' | 'A'..='Z' | '$' | '_' => {
+        b'a'..=b'z' | b'A'..=b'Z' | b'$' | b'_' => {
             let len = get_name_len(&text);
             let name = &text[..len];
-            if !spec_mode {
-                match &text[len..].chars().next() {
-                    Some('"') => {
+            let (tok, tok_len) = if !spec_mode {
+                match text.as_bytes().get(len) {
+                    Some(b'"') => {
                         // Special case for ByteArrayValue: h\"[0-9A-Fa-f]*\"
                         let mut bvlen = 0;
                         if name == "h" && {
@@ -228,7 +384,7 @@ fn find_token(
                             (get_name_token(name), len)
                         }
                     }
-                    Some('.') => {
+                    Some(b'.') => {
                         let len2 = get_name_len(&text[(len + 1)..]);
                         if len2 > 0 {
                             (Tok::DotNameValue, len + 1 + len2)
@@ -236,21 +392,22 @@ fn find_token(
                             (get_name_token(name), len)
                         }
                     }
-                    Some('<') => match name {
+                    Some(b'<') => match name {
                         "borrow_global" => (Tok::BorrowGlobal, len + 1),
                         "borrow_global_mut" => (Tok::BorrowGlobalMut, len + 1),
                         "exists" => (Tok::Exists, len + 1),
                         "move_from" => (Tok::MoveFrom, len + 1),
                         "move_to_sender" => (Tok::MoveToSender, len + 1),
+                        // The type formals of `main` are given like those of a function, but
+                        // `main` is a keyword rather than a name.
+                        "main" => (Tok::Main, len),
                         _ => (Tok::NameBeginTyValue, len + 1),
                     },
-                    Some('(') => match name {
+                    Some(b'(') => match name {
                         "assert" => (Tok::Assert, len + 1),
-                        "copy" => (Tok::Copy, len + 1),
-                        "move" => (Tok::Move, len + 1),
                         _ => (get_name_token(name), len),
                     },
-                    Some(':') => match name {
+                    Some(b':') => match name {
                         "modules" => (Tok::Modules, len + 1),
                         "script" => (Tok::Script, len + 1),
                         _ => (get_name_token(name), len),
@@ -259,9 +416,20 @@ fn find_token(
                 }
             } else {
                 (get_name_token(name), len) // just return the name in spec_mode
+            };
+            if !spec_mode {
+                match tok {
+                    Tok::NameValue | Tok::NameBeginTyValue => check_name(name, start_offset)?,
+                    Tok::DotNameValue => {
+                        check_name(name, start_offset)?;
+                        check_name(&text[len + 1..tok_len], start_offset + len + 1)?;
+                    }
+                    _ => (),
+                }
             }
+            (tok, tok_len)
         }
-        '&' => {
+        b'&' => {
             if text.starts_with("&mut ") {
                 (Tok::AmpMut, 5)
             } else if text.starts_with("&&") {
@@ -270,14 +438,14 @@ fn find_token(
                 (Tok::Amp, 1)
             }
         }
-        '|' => {
+        b'|' => {
             if text.starts_with("||") {
                 (Tok::PipePipe, 2)
             } else {
                 (Tok::Pipe, 1)
             }
         }
-        '=' => {
+        b'=' => {
             if text.starts_with("==>") {
                 (Tok::EqualEqualGreater, 3)
             } else if text.starts_with("==") {
@@ -286,14 +454,14 @@ fn find_token(
                 (Tok::Equal, 1)
             }
         }
-        '!' => {
+        b'!' => {
             if text.starts_with("!=") {
                 (Tok::ExclaimEqual, 2)
             } else {
                 (Tok::Exclaim, 1)
             }
         }
-        '<' => {
+        b'<' => {
             if text.starts_with("<=") {
                 (Tok::LessEqual, 2)
             } else if text.starts_with("<<") {
@@ -302,7 +470,7 @@ fn find_token(
                 (Tok::Less, 1)
             }
         }
-        '>' => {
+        b'>' => {
             if text.starts_with(">=") {
                 (Tok::GreaterEqual, 2)
             } else if text.starts_with(">>") {
@@ -311,20 +479,31 @@ fn find_token(
                 (Tok::Greater, 1)
             }
         }
-        '%' => (Tok::Percent, 1),
-        '(' => (Tok::LParen, 1),
-        ')' => (Tok::RParen, 1),
-        '*' => (Tok::Star, 1),
-        '+' => (Tok::Plus, 1),
-        ',' => (Tok::Comma, 1),
-        '-' => (Tok::Minus, 1),
-        '.' => (Tok::Period, 1),
-        '/' => (Tok::Slash, 1),
-        ':' => (Tok::Colon, 1),
-        ';' => (Tok::Semicolon, 1),
-        '^' => (Tok::Caret, 1),
-        '{' => (Tok::LBrace, 1),
-        '}' => (Tok::RBrace, 1),
+        b'"' => match text[1..].find(|c| c == '"' || c == '\n') {
+            Some(len) if text[1 + len..].starts_with('"') => (Tok::StringValue, len + 2),
+            _ => {
+                return Err(ParseError::InvalidToken {
+                    location: start_offset,
+                });
+            }
+        },
+        b'#' => (Tok::NumSign, 1),
+        b'%' => (Tok::Percent, 1),
+        b'(' => (Tok::LParen, 1),
+        b')' => (Tok::RParen, 1),
+        b'*' => (Tok::Star, 1),
+        b'+' => (Tok::Plus, 1),
+        b',' => (Tok::Comma, 1),
+        b'-' => (Tok::Minus, 1),
+        b'.' => (Tok::Period, 1),
+        b'/' => (Tok::Slash, 1),
+        b':' => (Tok::Colon, 1),
+        b';' => (Tok::Semicolon, 1),
+        b'[' => (Tok::LBracket, 1),
+        b']' => (Tok::RBracket, 1),
+        b'^' => (Tok::Caret, 1),
+        b'{' => (Tok::LBrace, 1),
+        b'}' => (Tok::RBrace, 1),
         _ => {
             return Err(ParseError::InvalidToken {
                 location: start_offset,
@@ -335,29 +514,52 @@ fn find_token(
     Ok((tok, len))
 }
 
+// Fail if a name of the source, starting at `offset`, is not a valid identifier. The lexer scans
+// names more broadly than identifiers, e.g. with `$`, so that the whole name is reported. Only the
+// names outside of specifications are checked: all of them become identifiers, while the formals,
+// functions and modifiers named in specifications are kept as they are written.
+fn check_name(name: &str, offset: usize) -> Result<(), ParseError<usize, anyhow::Error>> {
+    if Identifier::is_valid(name) {
+        return Ok(());
+    }
+    Err(ParseError::InvalidName {
+        name: name.to_string(),
+        loc: Span::new(
+            ByteIndex(offset as u32),
+            ByteIndex((offset + name.len()) as u32),
+        ),
+    })
+}
+
+// Fail if `name`, starting at `offset`, is one of the reserved names.
+fn check_not_reserved(
+    reserved: &[&str],
+    name: &str,
+    offset: usize,
+) -> Result<(), ParseError<usize, anyhow::Error>> {
+    if !reserved.contains(&name) {
+        return Ok(());
+    }
+    Err(ParseError::ReservedName {
+        name: name.to_string(),
+        loc: Span::new(
+            ByteIndex(offset as u32),
+            ByteIndex((offset + name.len()) as u32),
+        ),
+    })
+}
+
 // Return the length of the substring matching [a-zA-Z$_][a-zA-Z0-9$_]
 fn get_name_len(text: &str) -> usize {
     // If the first character is 0..=9 or EOF, then return a length of 0.
-    let first_char = text.chars().next().unwrap_or('0');
-    if ('0'..='9').contains(&first_char) {
-        return 0;
+    match text.as_bytes().first() {
+        Some(byte) if byte_class(*byte) & ID != 0 => class_len(text, ID | DG),
+        _ => 0,
     }
-    text.chars()
-        .position(|c| match c {
-            'a'..='z' | 'A'..='Z' | '$' | '_' | '0'..='9' => false,
-            _ => true,
-        })
-        .unwrap_or_else(|| text.len())
 }
 
 fn get_decimal_number(text: &str) -> (Tok, usize) {
-    let len = text
-        .chars()
-        .position(|c| match c {
-            '0'..='9' => false,
-            _ => true,
-        })
-        .unwrap_or_else(|| text.len());
+    let len = class_len(text, DG);
     let rest = &text[len..];
     if rest.starts_with("u8") {
         (Tok::U8Value, len + 2)
@@ -372,12 +574,7 @@ fn get_decimal_number(text: &str) -> (Tok, usize) {
 
 // Return the length of the substring containing characters in [0-9a-fA-F].
 fn get_hex_digits_len(text: &str) -> usize {
-    text.chars()
-        .position(|c| match c {
-            'a'..='f' | 'A'..='F' | '0'..='9' => false,
-            _ => true,
-        })
-        .unwrap_or_else(|| text.len())
+    class_len(text, HX)
 }
 
 // Check for an optional sequence of hex digits following by a double quote, and return
@@ -385,8 +582,8 @@ fn get_hex_digits_len(text: &str) -> usize {
 // seeing the 'h"' prefix.
 fn get_byte_array_value_len(text: &str) -> usize {
     let hex_len = get_hex_digits_len(text);
-    match &text[hex_len..].chars().next() {
-        Some('"') => hex_len + 1,
+    match text.as_bytes().get(hex_len) {
+        Some(b'"') => hex_len + 1,
         _ => 0,
     }
 }
@@ -440,3 +637,27 @@ fn get_name_token(name: &str) -> Tok {
         _ => Tok::NameValue,
     }
 }
+
+/// Return the keyword for a name which is lexed as a `NameValue` (or a `NameBeginTyValue` for
+/// `to_bytes`) and read as a keyword only where the parser expects one: a builtin before its
+/// arguments, `move` and `copy` before a local, `emit` at the start of a statement and `pragma` at
+/// the start of a source. Elsewhere these names remain available for locals, functions and fields.
+pub fn get_contextual_token(name: &str) -> Option<Tok> {
+    Some(match name {
+        "checked_add" => Tok::CheckedAdd,
+        "checked_sub" => Tok::CheckedSub,
+        "copy" => Tok::Copy,
+        "count_ones" => Tok::CountOnes,
+        "emit" => Tok::Emit,
+        "move" => Tok::Move,
+        "pragma" => Tok::Pragma,
+        "rotl" => Tok::Rotl,
+        "rotr" => Tok::Rotr,
+        "saturating_add" => Tok::SaturatingAdd,
+        "saturating_sub" => Tok::SaturatingSub,
+        "sha2_256" => Tok::Sha2_256,
+        "sha3_256" => Tok::Sha3_256,
+        "to_bytes" => Tok::ToBytes,
+        _ => return None,
+    })
+}
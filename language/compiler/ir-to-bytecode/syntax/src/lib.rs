@@ -4,6 +4,10 @@
 #![forbid(unsafe_code)]
 
 //! # Grammar
+//!
+//! An overview of the language. The exact grammar the parser accepts is in [`grammar`], and can
+//! be exported as EBNF.
+//!
 //! ## Identifiers
 //! ```text
 //! f ∈ FieldName     // [a-zA-Z$_][a-zA-Z0-9$_]*
@@ -40,6 +44,8 @@
 //!   | t      // base type
 //!   | &t     // immutable reference to a base type
 //!   | &mut t // mutable reference to a base type
+//!   | (𝛕_1, ..., 𝛕_j) // 'j' >= 2. tuple, only for locals, parameters and return values, which
+//!                     // are given one local, parameter or return value per element
 //!
 //! 𝛕-list ∈ [Type] ::=
 //!   | unit            // empty type list.
@@ -63,8 +69,13 @@
 //! ## Expressions
 //! ```text
 //! o ∈ VarOp ::=
-//!   | copy(x) // returns value bound to 'x'
-//!   | move(x) // moves the value out of 'x', i.e. returns the value and makes 'x' unusable
+//!   | copy x  // returns value bound to 'x'
+//!   | move x  // moves the value out of 'x', i.e. returns the value and makes 'x' unusable
+//!   | copy(x) // same as 'copy x'
+//!   | move(x) // same as 'move x'
+//!   | x       // either of the above, as chosen by the compiler: 'x' is moved at its last use or
+//!             // when its type is a resource, and copied otherwise. Write 'x < e' and '&x.f < e'
+//!             // with a space, as 'x<' starts type actuals
 //!
 //! r ∈ ReferenceOp ::=
 //!   | &x        // type: 't -> &mut t'
@@ -86,7 +97,8 @@
 //!   | !e_1
 //!   | e_1 || e_2
 //!   | e_1 && e_2
-//!   // u64 operators
+//!   // u64 operators. The comparisons cannot be chained: 'a < b < c' is an error, write
+//!   // 'a < b && b < c' instead
 //!   | e_1 >= e_2
 //!   | e_1 <= e_2
 //!   | e_1 > e_2
@@ -117,6 +129,7 @@
 //!                          // borrows a mutable reference to the resource struct 'n' at the specified address
 //!                          // fails if there is no resource
 //!                          // fails if it is already borrowed in this transaction's execution
+//!                          // a field can be borrowed from it directly, as in '&borrow_global<n>(e).f'
 //!   | exists<n>(e)         // type: 'address -> bool', s.t. 'n' is a resource struct
 //!                          // returns 'true' if the resource struct 'n' at the specified address exists
 //!                          // returns 'false' otherwise
@@ -129,6 +142,30 @@
 //!   | freeze(x)                 // type: '&mut t -> &t'
 //!                               // coerce a mutable reference to an immutable reference
 //!   | get_txn_sender()          // type: 'unit -> address'
+//!   | rotl(e_1, e_2)            // type: 'uN * u8 -> uN'
+//!                               // rotates the bits of 'e_1' left by 'e_2' modulo N
+//!   | rotr(e_1, e_2)            // type: 'uN * u8 -> uN'
+//!                               // rotates the bits of 'e_1' right by 'e_2' modulo N
+//!   | count_ones(e)             // type: 'uN -> u64'
+//!                               // counts the bits of 'e' that are set
+//!   | checked_add(e_1, e_2)     // type: 'uN * uN -> uN * bool'
+//!                               // adds, returning the sum and true, or 0 and false on overflow
+//!   | checked_sub(e_1, e_2)     // type: 'uN * uN -> uN * bool'
+//!                               // subtracts, returning the difference and true, or 0 and false
+//!                               // on underflow
+//!   | saturating_add(e_1, e_2)  // type: 'uN * uN -> uN'
+//!                               // adds, clamping the sum to the largest uN
+//!   | saturating_sub(e_1, e_2)  // type: 'uN * uN -> uN'
+//!                               // subtracts, clamping the difference to 0
+//!   | sha2_256(e)               // type: 'bytearray -> bytearray'
+//!                               // hashes 'e' with SHA-256, with the Hash module of the
+//!                               // standard library
+//!   | sha3_256(e)               // type: 'bytearray -> bytearray'
+//!                               // hashes 'e' with SHA3-256, with the Hash module of the
+//!                               // standard library
+//!   | to_bytes<t>(e)            // type: '&t -> bytearray'
+//!                               // serializes the value 'e' refers to canonically, with the
+//!                               // LCS module of the standard library
 //!
 //! call ∈ Call ::=
 //!   | mop
@@ -138,12 +175,15 @@
 //! c ∈ Cmd ::=
 //!   | x = e                               // assign the result of evaluating 'e' to 'x'
 //!   | x_1, ..., x_j = call                // Invokes 'call', assigns result to 'x_1' to 'x_j'
+//!   | x_1, ..., x_j = e                   // destructures the tuple 'e' into 'x_1' to 'x_j'
 //!   | call                                // Invokes 'call' that has a return type of 'unit'
 //!   | *x = e                              // mutation, s.t. 'x: &mut t' and 'e: t' and 't' is not of resource kind
 //!   | assert(e_1, e_2)                    // type: 'bool * u64 -> unit'
 //!                                         // halts execution with error code 'e_2' if 'e_1' evaluates to 'false'
 //!   | break                               // exit a loop
+//!   | break e                             // exit a loop, with 'e' as the value of the loop
 //!   | continue                            // return to the top of a loop
+//!   | emit e_1, e_2                       // emits the event 'e_2' to the event handle 'e_1: &mut LibraAccount.EventHandle<t>'
 //!   | return e_1, ..., e_n                // return values from procedure
 //!   | n { f_1: x_1, ... , f_j: x_j } = e  // "de-constructor" for 'n'
 //!                                         // "unpacks" a struct value 'e: _#Self.n'
@@ -157,6 +197,7 @@
 //!   | if (e) { s }                // conditional without else branch
 //!   | while (e) { s }             // while loop
 //!   | loop { s }                  // loops forever
+//!   | x_1, ..., x_j = loop { s }; // loops until a 'break e' assigns 'e' to 'x_1' to 'x_j'
 //!   | c;                          // command
 //!   | s_1 s_2                     // sequencing
 //! ```
@@ -188,13 +229,70 @@
 //!   | module m { idecl_1 ... idecl_i sdecl_1 ... sdecl_j pdecl_1 ... pdecl_k }
 //! ```
 //!
+//! ## Attributes
+//! ```text
+//! cfg ∈ CfgPredicate ::=
+//!   | x                      // holds if the flag 'x' is set, e.g. 'test'
+//!   | feature = "x"          // holds if the feature 'x' is enabled
+//!   | not(cfg)               // holds if 'cfg' does not
+//!   | all(cfg_1, ..., cfg_j) // holds if every 'cfg_i' holds
+//!   | any(cfg_1, ..., cfg_j) // holds if some 'cfg_i' holds
+//!
+//! attr ∈ Attribute ::=
+//!   | #[cfg(cfg)]       // before an idecl, sdecl or pdecl, which is only compiled if 'cfg' holds
+//!   | #[inline]         // before a pdecl, whose calls are inlined whatever its size
+//!   | #[never_inline]   // before a pdecl, whose calls are never inlined
+//!   | #[annotate(x_1 = "v_1", ..., x_j = "v_j")]
+//!                       // before a pdecl, whose selected entries are embedded in the metadata
+//!                       // section of the compiled module
+//! ```
+//!
 //! ## Transaction Scripts
 //! ```text
 //! TransactionScript ::=
 //!   // declaration of the transaction scripts procedure
 //!   // the 'main' procedure must be 'public' and any parameters must have a ground type
 //!   | idecl_1 ... idecl_i public main(x_1: g_1, ..., x_j: g_j) { s }
+//!   // a generic script, instantiated with the type arguments of the transaction running it
+//!   // or specialized by the compiler with given types
+//!   | idecl_1 ... idecl_i public main<T_1, ..., T_n>(x_1: g_1, ..., x_j: g_j) { s }
+//!   // a script returning values, which the verifier rejects as the script of a transaction, and
+//!   // which runs as a query instead
+//!   | idecl_1 ... idecl_i public main(x_1: g_1, ..., x_j: g_j): 𝛕-list { s }
 //! ```
+//!
+//! ## Language version
+//! ```text
+//! LanguagePragma ::=
+//!   // at the start of a source, before its modules or script
+//!   | pragma language u; // the version of the language the source is written in
+//! ```
+
+/// Records that the parser went through a grammar production, when built with the `coverage`
+/// feature.
+macro_rules! cover {
+    ($nonterminal:expr, $alternative:expr) => {
+        #[cfg(feature = "coverage")]
+        crate::coverage::record(($nonterminal, $alternative));
+    };
+}
+
+/// Records the time spent in the rest of the enclosing block as a grammar production, when built
+/// with the `profiling` feature.
+macro_rules! profile {
+    ($production:expr) => {
+        #[cfg(feature = "profiling")]
+        let _profile = crate::profile::enter($production);
+    };
+}
 
+#[cfg(feature = "cache")]
+pub mod cache;
+#[cfg(feature = "coverage")]
+pub mod coverage;
+pub mod grammar;
+pub mod highlighting;
 mod lexer;
+#[cfg(feature = "profiling")]
+pub mod profile;
 pub mod syntax;
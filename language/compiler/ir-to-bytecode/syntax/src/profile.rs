@@ -0,0 +1,180 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Parser profiling.
+//!
+//! When built with the `profiling` feature, the parser records, for the main grammar productions,
+//! how many nodes they parsed and how long it took. Parsing a real workload and then printing the
+//! [`report`](fn.report.html) shows where the parser spends its time.
+//!
+//! Each call of a production parses one node: one module, one function declaration, one
+//! expression... Expressions count once per call of the expression parser, not once per
+//! subexpression, since subexpressions are parsed by the same loop. The total time of a
+//! production includes the productions it goes through, counting a recursive production once, and
+//! its self time excludes them.
+//!
+//! As for coverage, productions are recorded per thread: the report only covers what was parsed
+//! on the calling thread since the last [`reset`](fn.reset.html), so modules parsed in parallel
+//! are not included.
+
+use std::{
+    cell::RefCell,
+    cmp::Reverse,
+    collections::BTreeMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// What was recorded for a production.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ProductionProfile {
+    /// The number of nodes the production parsed, including the attempts that failed.
+    pub nodes: u64,
+    /// The time spent in the production, including the productions it goes through.
+    pub total_time: Duration,
+    /// The time spent in the production itself.
+    pub self_time: Duration,
+}
+
+struct Frame {
+    production: &'static str,
+    start: Instant,
+    children_time: Duration,
+}
+
+#[derive(Default)]
+struct Profiler {
+    profiles: BTreeMap<&'static str, ProductionProfile>,
+    stack: Vec<Frame>,
+}
+
+thread_local! {
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::default());
+}
+
+/// Records the time spent in a production until it is dropped.
+pub(crate) struct Guard;
+
+/// Enters `production`, which is left when the returned guard is dropped.
+pub(crate) fn enter(production: &'static str) -> Guard {
+    PROFILER.with(|profiler| {
+        profiler.borrow_mut().stack.push(Frame {
+            production,
+            start: Instant::now(),
+            children_time: Duration::default(),
+        })
+    });
+    Guard
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let end = Instant::now();
+        PROFILER.with(|profiler| {
+            let profiler = &mut *profiler.borrow_mut();
+            let frame = match profiler.stack.pop() {
+                Some(frame) => frame,
+                // The profile was reset while parsing.
+                None => return,
+            };
+            let elapsed = end.duration_since(frame.start);
+            let recursive = profiler
+                .stack
+                .iter()
+                .any(|outer| outer.production == frame.production);
+            if let Some(parent) = profiler.stack.last_mut() {
+                parent.children_time += elapsed;
+            }
+            let profile = profiler.profiles.entry(frame.production).or_default();
+            profile.nodes += 1;
+            if !recursive {
+                profile.total_time += elapsed;
+            }
+            profile.self_time += elapsed.checked_sub(frame.children_time).unwrap_or_default();
+        })
+    }
+}
+
+/// Forgets the productions recorded so far on this thread.
+pub fn reset() {
+    PROFILER.with(|profiler| *profiler.borrow_mut() = Profiler::default());
+}
+
+/// Returns what was recorded on this thread since the last reset.
+pub fn report() -> ProfileReport {
+    PROFILER.with(|profiler| ProfileReport {
+        profiles: profiler.borrow().profiles.clone(),
+    })
+}
+
+/// The profiles of the productions the parser went through.
+#[derive(Clone, Debug)]
+pub struct ProfileReport {
+    profiles: BTreeMap<&'static str, ProductionProfile>,
+}
+
+impl ProfileReport {
+    /// Returns the profile of `production`, which is empty if the parser did not go through it.
+    pub fn profile(&self, production: &str) -> ProductionProfile {
+        self.profiles.get(production).cloned().unwrap_or_default()
+    }
+
+    /// Returns the productions the parser went through and their profiles, by decreasing self
+    /// time.
+    pub fn hotspots(&self) -> Vec<(&'static str, ProductionProfile)> {
+        let mut profiles: Vec<_> = self
+            .profiles
+            .iter()
+            .map(|(production, profile)| (*production, *profile))
+            .collect();
+        profiles.sort_by_key(|(_, profile)| Reverse(profile.self_time));
+        profiles
+    }
+}
+
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<16} {:>10} {:>12} {:>12} {:>15}",
+            "production", "nodes", "total (ms)", "self (ms)", "self/node (us)"
+        )?;
+        for (production, profile) in self.hotspots() {
+            let self_us = profile.self_time.as_secs_f64() * 1e6;
+            writeln!(
+                f,
+                "{:<16} {:>10} {:>12.3} {:>12.3} {:>15.3}",
+                production,
+                profile.nodes,
+                profile.total_time.as_secs_f64() * 1e3,
+                self_us / 1e3,
+                self_us / profile.nodes as f64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::parse_module_string;
+
+    #[test]
+    fn profiles_productions() {
+        reset();
+        parse_module_string(
+            "module M { f(x: u64): u64 { return (copy(x) + 1) * 2; } g(): u64 { return 3; } }",
+        )
+        .unwrap();
+        let report = report();
+        assert_eq!(report.profile("Module").nodes, 1);
+        assert_eq!(report.profile("FunctionDecl").nodes, 2);
+        assert_eq!(report.profile("Exp").nodes, 2);
+        assert_eq!(report.profile("Invariant").nodes, 0);
+        let module = report.profile("Module");
+        assert!(module.total_time >= report.profile("FunctionDecl").total_time);
+        assert!(module.self_time <= module.total_time);
+        assert_eq!(report.hotspots().len(), report.profiles.len());
+    }
+}
@@ -3,22 +3,87 @@
 
 use anyhow::{Context, Error};
 use codespan::{ByteIndex, Span};
+use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
+use std::sync::Arc;
 
+pub use crate::lexer::top_level_module_starts;
 use crate::lexer::*;
 use hex;
 use libra_types::identifier::Identifier;
 use libra_types::{account_address::AccountAddress, byte_array::ByteArray};
 use move_ir_types::{ast::*, spec_language_ast::*};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 // FIXME: The following simplified version of ParseError copied from
 // lalrpop-util should be replaced.
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ParseError<L, E> {
-    InvalidToken { location: L },
-    User { error: E },
+    InvalidToken {
+        location: L,
+    },
+    User {
+        error: E,
+    },
+    /// A name that is not a valid identifier, e.g. one with a `$`.
+    InvalidName {
+        name: String,
+        loc: Loc,
+    },
+    /// A name given twice where names must be unique, e.g. two fields of a struct.
+    Duplicate {
+        /// What the name is for, e.g. "field".
+        kind: &'static str,
+        name: String,
+        first: Loc,
+        second: Loc,
+    },
+    /// A name reserved by the parser options.
+    ReservedName {
+        name: String,
+        loc: Loc,
+    },
+    /// A construct slated for removal, rejected in strict mode.
+    Deprecated {
+        /// The construct, e.g. "`assert`".
+        construct: &'static str,
+        /// What to write instead.
+        advice: &'static str,
+        loc: Loc,
+    },
+    /// A language pragma declaring a version the parser does not know.
+    UnsupportedLanguageVersion {
+        version: String,
+        loc: Loc,
+    },
+    /// A bytearray literal with an odd number of hex digits, or longer than allowed.
+    InvalidByteArray {
+        message: String,
+        loc: Loc,
+    },
+    /// An attribute the parser does not know, misplaced or repeated, or a `cfg` predicate the
+    /// parser does not know.
+    InvalidAttribute {
+        message: String,
+        loc: Loc,
+    },
+    /// A comparison of the result of another comparison, as in `a < b < c`.
+    ChainedComparison {
+        /// The location of the comparisons.
+        loc: Loc,
+        /// The conjunction of comparisons replacing them, as in `a < b && b < c`.
+        replacement: String,
+    },
+    /// An expression nested deeper than the `max_exp_depth` of the parser options.
+    TooDeeplyNested {
+        /// The location of the innermost expression exceeding the depth.
+        loc: Loc,
+        /// The maximal depth of an expression.
+        max_depth: usize,
+    },
 }
 
 impl<L> From<Error> for ParseError<L, Error> {
@@ -37,17 +102,155 @@ where
         match *self {
             User { ref error } => write!(f, "{}", error),
             InvalidToken { ref location } => write!(f, "Invalid token at {}", location),
+            InvalidName { ref name, loc } => {
+                write!(f, "Invalid name {} at {}", name, loc.start().0)
+            }
+            Duplicate {
+                kind,
+                ref name,
+                second,
+                ..
+            } => write!(f, "Duplicate {} {} at {}", kind, name, second.start().0),
+            ReservedName { ref name, loc } => {
+                write!(f, "Reserved name {} at {}", name, loc.start().0)
+            }
+            Deprecated {
+                construct,
+                advice,
+                loc,
+            } => write!(
+                f,
+                "Deprecated {} at {}: {}",
+                construct,
+                loc.start().0,
+                advice
+            ),
+            UnsupportedLanguageVersion { ref version, loc } => write!(
+                f,
+                "Unsupported language version {} at {}",
+                version,
+                loc.start().0
+            ),
+            InvalidByteArray { ref message, loc } | InvalidAttribute { ref message, loc } => {
+                write!(f, "{} at {}", message, loc.start().0)
+            }
+            ChainedComparison {
+                loc,
+                ref replacement,
+            } => write!(
+                f,
+                "Chained comparison at {}, did you mean `{}`?",
+                loc.start().0,
+                replacement
+            ),
+            TooDeeplyNested { loc, max_depth } => write!(
+                f,
+                "Expression nested deeper than {} at {}",
+                max_depth,
+                loc.start().0
+            ),
+        }
+    }
+}
+
+/// The latest version of the language, which a source can declare with `pragma language N;` at its
+/// start. Version 1 is the language of sources without a pragma. Version 2 rejects the constructs
+/// slated for removal, as in strict mode.
+pub const LATEST_LANGUAGE_VERSION: u64 = 2;
+
+/// The default maximal depth of an expression, counting each unary operator, call, parenthesized
+/// list and pack around its terms. Binary operators do not count, so that the chains of left
+/// associative operators, as in `a + b + c`, are not deep however long. Code generation and the
+/// type checker do not recurse over expressions, but some optional passes and lints do: the depth
+/// is bounded so that an adversarial input cannot overflow their stack.
+pub const DEFAULT_MAX_EXP_DEPTH: usize = 1024;
+
+/// Names expected to become keywords in a future version of the language.
+pub const FUTURE_KEYWORDS: &[&str] = &["vector", "signer", "const", "friend"];
+
+/// Options of the parser.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// Skip specifications instead of parsing them: the conditions of functions, the invariants of
+    /// structs and the synthetic variables of modules are lexed and discarded, and are missing
+    /// from the AST. Only the parentheses of their expressions are checked to be balanced. Code
+    /// generation does not use specifications, so this speeds up building modules to publish.
+    pub skip_specs: bool,
+    /// Fail at the first specification, for sources that must not have any.
+    pub reject_specs: bool,
+    /// Parse the modules of a program on worker threads. The result is the same as with a
+    /// sequential parse, errors included. Ignored when the crate is built without its `parallel`
+    /// feature, as for WebAssembly, which has no threads.
+    pub parallel: bool,
+    /// The maximum number of bytes of a bytearray literal, checked before decoding it so that a
+    /// huge literal fails fast. `None` means no limit.
+    pub max_byte_array_len: Option<usize>,
+    /// Names to reject wherever an identifier is expected, to stage the introduction of new
+    /// keywords such as [`FUTURE_KEYWORDS`](constant.FUTURE_KEYWORDS.html). Code using them can be
+    /// found beforehand with the `reserved_names` lint, which only warns.
+    pub reserved_names: &'static [&'static str],
+    /// Reject the constructs slated for removal from the language, so that code can be kept
+    /// compatible with future versions: imports of `Transaction` modules, and `assert`.
+    pub strict: bool,
+    /// The maximal depth of an expression, which defaults to
+    /// [`DEFAULT_MAX_EXP_DEPTH`](constant.DEFAULT_MAX_EXP_DEPTH.html). `None` means no limit.
+    pub max_exp_depth: Option<usize>,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            skip_specs: false,
+            reject_specs: false,
+            parallel: false,
+            max_byte_array_len: None,
+            reserved_names: &[],
+            strict: false,
+            max_exp_depth: Some(DEFAULT_MAX_EXP_DEPTH),
         }
     }
 }
 
+fn make_loc(start: usize, end: usize) -> Loc {
+    Span::new(ByteIndex(start as u32), ByteIndex(end as u32))
+}
+
 fn spanned<T>(start: usize, end: usize, value: T) -> Spanned<T> {
     Spanned {
         value,
-        span: Span::new(ByteIndex(start as u32), ByteIndex(end as u32)),
+        span: make_loc(start, end),
     }
 }
 
+// Fails if two of the declarations, given by name and location, have the same name.
+fn check_unique_names<'a, N: ?Sized + Eq + std::hash::Hash + fmt::Display + 'a>(
+    kind: &'static str,
+    declarations: impl IntoIterator<Item = (&'a N, Loc)>,
+) -> Result<(), ParseError<usize, anyhow::Error>> {
+    let mut seen = HashMap::new();
+    for (name, loc) in declarations {
+        if let Some(first) = seen.insert(name, loc) {
+            return Err(ParseError::Duplicate {
+                kind,
+                name: name.to_string(),
+                first,
+                second: loc,
+            });
+        }
+    }
+    Ok(())
+}
+
+// Fails if two of `fields` have the same name.
+fn check_unique_fields<T>(fields: &[(Field, T)]) -> Result<(), ParseError<usize, anyhow::Error>> {
+    check_unique_names(
+        "field",
+        fields
+            .iter()
+            .map(|(field, _)| (field.value.name(), field.span)),
+    )
+}
+
 fn consume_token<'input>(
     tokens: &mut Lexer<'input>,
     tok: Tok,
@@ -99,22 +302,24 @@ where
     Ok(v)
 }
 
+// Names are returned as slices of the source, so that they are only copied once, when they are
+// validated into the identifier they name.
 fn parse_name<'input>(
     tokens: &mut Lexer<'input>,
-) -> Result<String, ParseError<usize, anyhow::Error>> {
+) -> Result<&'input str, ParseError<usize, anyhow::Error>> {
     if tokens.peek() != Tok::NameValue {
         return Err(ParseError::InvalidToken {
             location: tokens.start_loc(),
         });
     }
-    let name = tokens.content().to_string();
+    let name = tokens.content();
     tokens.advance()?;
     Ok(name)
 }
 
 fn parse_name_begin_ty<'input>(
     tokens: &mut Lexer<'input>,
-) -> Result<String, ParseError<usize, anyhow::Error>> {
+) -> Result<&'input str, ParseError<usize, anyhow::Error>> {
     if tokens.peek() != Tok::NameBeginTyValue {
         return Err(ParseError::InvalidToken {
             location: tokens.start_loc(),
@@ -122,22 +327,25 @@ fn parse_name_begin_ty<'input>(
     }
     let s = tokens.content();
     // The token includes a "<" at the end, so chop that off to get the name.
-    let name = s[..s.len() - 1].to_string();
+    let name = &s[..s.len() - 1];
     tokens.advance()?;
     Ok(name)
 }
 
+// Returns the names on both sides of the dot.
 fn parse_dot_name<'input>(
     tokens: &mut Lexer<'input>,
-) -> Result<String, ParseError<usize, anyhow::Error>> {
+) -> Result<(&'input str, &'input str), ParseError<usize, anyhow::Error>> {
     if tokens.peek() != Tok::DotNameValue {
         return Err(ParseError::InvalidToken {
             location: tokens.start_loc(),
         });
     }
-    let name = tokens.content().to_string();
+    let s = tokens.content();
+    // The lexer only makes a DotNameValue of two names separated by a dot.
+    let dot = s.find('.').unwrap();
     tokens.advance()?;
-    Ok(name)
+    Ok((&s[..dot], &s[dot + 1..]))
 }
 
 // AccountAddress: AccountAddress = {
@@ -208,18 +416,22 @@ fn parse_copyable_val<'input>(
     let start_loc = tokens.start_loc();
     let val = match tokens.peek() {
         Tok::AccountAddressValue => {
+            cover!("CopyableVal", "Address");
             let addr = parse_account_address(tokens)?;
             CopyableVal_::Address(addr)
         }
         Tok::True => {
+            cover!("CopyableVal", "True");
             tokens.advance()?;
             CopyableVal_::Bool(true)
         }
         Tok::False => {
+            cover!("CopyableVal", "False");
             tokens.advance()?;
             CopyableVal_::Bool(false)
         }
         Tok::U8Value => {
+            cover!("CopyableVal", "U8");
             let mut s = tokens.content();
             if s.ends_with("u8") {
                 s = &s[..s.len() - 2]
@@ -229,6 +441,7 @@ fn parse_copyable_val<'input>(
             CopyableVal_::U8(i)
         }
         Tok::U64Value => {
+            cover!("CopyableVal", "U64");
             let mut s = tokens.content();
             if s.ends_with("u64") {
                 s = &s[..s.len() - 3]
@@ -238,6 +451,7 @@ fn parse_copyable_val<'input>(
             CopyableVal_::U64(i)
         }
         Tok::U128Value => {
+            cover!("CopyableVal", "U128");
             let mut s = tokens.content();
             if s.ends_with("u128") {
                 s = &s[..s.len() - 4]
@@ -247,13 +461,37 @@ fn parse_copyable_val<'input>(
             CopyableVal_::U128(i)
         }
         Tok::ByteArrayValue => {
+            cover!("CopyableVal", "ByteArray");
             let s = tokens.content();
-            let buf = ByteArray::new(hex::decode(&s[2..s.len() - 1]).unwrap_or_else(|_| {
-                // The lexer guarantees this, but tracking this knowledge all the way to here is tedious
+            let digits = &s[2..s.len() - 1];
+            let loc = make_loc(tokens.start_loc(), tokens.start_loc() + s.len());
+            if digits.len() % 2 != 0 {
+                return Err(ParseError::InvalidByteArray {
+                    message: format!(
+                        "Bytearray literal has an odd number of hex digits ({})",
+                        digits.len()
+                    ),
+                    loc,
+                });
+            }
+            if let Some(max_len) = tokens.options.max_byte_array_len {
+                if digits.len() / 2 > max_len {
+                    return Err(ParseError::InvalidByteArray {
+                        message: format!(
+                            "Bytearray literal of {} bytes exceeds the limit of {} bytes",
+                            digits.len() / 2,
+                            max_len
+                        ),
+                        loc,
+                    });
+                }
+            }
+            let buf = ByteArray::new(hex::decode(digits).unwrap_or_else(|_| {
+                // The lexer only accepts hex digits and the count is even
                 unreachable!("The string {:?} is not a valid hex-encoded byte array", s)
             }));
             tokens.advance()?;
-            CopyableVal_::ByteArray(buf)
+            CopyableVal_::ByteArray(Arc::new(buf))
         }
         _ => {
             return Err(ParseError::InvalidToken {
@@ -269,7 +507,7 @@ fn parse_copyable_val<'input>(
 // is 1, and larger values have higher precedence. For tokens that are not
 // binary operators, this returns a value of zero so that they will be
 // below the minimum value and will mark the end of the binary expression
-// for the code in parse_rhs_of_binary_exp.
+// for the code in parse_exp_iteratively.
 fn get_precedence(token: &Tok) -> u32 {
     match token {
         // Reserved minimum precedence value is 1 (specified in parse_exp_)
@@ -297,66 +535,31 @@ fn get_precedence(token: &Tok) -> u32 {
 }
 
 fn parse_exp<'input>(tokens: &mut Lexer<'input>) -> Result<Exp, ParseError<usize, anyhow::Error>> {
-    let lhs = parse_unary_exp(tokens)?;
-    parse_rhs_of_binary_exp(tokens, lhs, /* min_prec */ 1)
+    parse_exp_iteratively(tokens, /* call_or_term */ false)
 }
 
-fn parse_rhs_of_binary_exp<'input>(
-    tokens: &mut Lexer<'input>,
-    lhs: Exp,
-    min_prec: u32,
-) -> Result<Exp, ParseError<usize, anyhow::Error>> {
-    let mut result = lhs;
-    let mut next_tok_prec = get_precedence(&tokens.peek());
-
-    // Continue parsing binary expressions as long as they have they
-    // specified minimum precedence.
-    while next_tok_prec >= min_prec {
-        let op_token = tokens.peek();
-        tokens.advance()?;
-
-        let mut rhs = parse_unary_exp(tokens)?;
-
-        // If the next token is another binary operator with a higher
-        // precedence, then recursively parse that expression as the RHS.
-        let this_prec = next_tok_prec;
-        next_tok_prec = get_precedence(&tokens.peek());
-        if this_prec < next_tok_prec {
-            rhs = parse_rhs_of_binary_exp(tokens, rhs, this_prec + 1)?;
-            next_tok_prec = get_precedence(&tokens.peek());
-        }
-
-        let op = match op_token {
-            Tok::EqualEqual => BinOp::Eq,
-            Tok::ExclaimEqual => BinOp::Neq,
-            Tok::Less => BinOp::Lt,
-            Tok::Greater => BinOp::Gt,
-            Tok::LessEqual => BinOp::Le,
-            Tok::GreaterEqual => BinOp::Ge,
-            Tok::PipePipe => BinOp::Or,
-            Tok::AmpAmp => BinOp::And,
-            Tok::Caret => BinOp::Xor,
-            Tok::LessLess => BinOp::Shl,
-            Tok::GreaterGreater => BinOp::Shr,
-            Tok::Pipe => BinOp::BitOr,
-            Tok::Amp => BinOp::BitAnd,
-            Tok::Plus => BinOp::Add,
-            Tok::Minus => BinOp::Sub,
-            Tok::Star => BinOp::Mul,
-            Tok::Slash => BinOp::Div,
-            Tok::Percent => BinOp::Mod,
-            _ => panic!("Unexpected token that is not a binary operator"),
-        };
-        let start_loc = result.span.start();
-        let end_loc = tokens.previous_end_loc();
-        let e = Exp_::BinopExp(Box::new(result), op, Box::new(rhs));
-        result = Spanned {
-            span: Span::new(start_loc, ByteIndex(end_loc as u32)),
-            value: e,
-        };
+fn parse_binop(token: &Tok) -> BinOp {
+    match token {
+        Tok::EqualEqual => BinOp::Eq,
+        Tok::ExclaimEqual => BinOp::Neq,
+        Tok::Less => BinOp::Lt,
+        Tok::Greater => BinOp::Gt,
+        Tok::LessEqual => BinOp::Le,
+        Tok::GreaterEqual => BinOp::Ge,
+        Tok::PipePipe => BinOp::Or,
+        Tok::AmpAmp => BinOp::And,
+        Tok::Caret => BinOp::Xor,
+        Tok::LessLess => BinOp::Shl,
+        Tok::GreaterGreater => BinOp::Shr,
+        Tok::Pipe => BinOp::BitOr,
+        Tok::Amp => BinOp::BitAnd,
+        Tok::Plus => BinOp::Add,
+        Tok::Minus => BinOp::Sub,
+        Tok::Star => BinOp::Mul,
+        Tok::Slash => BinOp::Div,
+        Tok::Percent => BinOp::Mod,
+        _ => panic!("Unexpected token that is not a binary operator"),
     }
-
-    Ok(result)
 }
 
 // QualifiedFunctionName : FunctionCall = {
@@ -378,18 +581,28 @@ fn parse_qualified_function_name<'input>(
         | Tok::Freeze
         | Tok::ToU8
         | Tok::ToU64
-        | Tok::ToU128 => {
+        | Tok::ToU128
+        | Tok::Rotl
+        | Tok::Rotr
+        | Tok::CountOnes
+        | Tok::CheckedAdd
+        | Tok::CheckedSub
+        | Tok::SaturatingAdd
+        | Tok::SaturatingSub
+        | Tok::Sha2_256
+        | Tok::Sha3_256
+        | Tok::ToBytes => {
+            cover!("QualifiedFunctionName", "Builtin");
             let f = parse_builtin(tokens)?;
             FunctionCall_::Builtin(f)
         }
         Tok::DotNameValue => {
-            let module_dot_name = parse_dot_name(tokens)?;
+            cover!("QualifiedFunctionName", "ModuleFunction");
+            let (module, name) = parse_dot_name(tokens)?;
             let type_actuals = parse_type_actuals(tokens)?;
-            let v: Vec<&str> = module_dot_name.split('.').collect();
-            assert!(v.len() == 2);
             FunctionCall_::ModuleFunctionCall {
-                module: ModuleName::parse(v[0])?,
-                name: FunctionName::parse(v[1])?,
+                module: ModuleName::parse(module)?,
+                name: FunctionName::parse(name)?,
                 type_actuals,
             }
         }
@@ -411,75 +624,6 @@ fn parse_qualified_function_name<'input>(
 //     CallOrTerm,
 // }
 
-fn parse_borrow_field_<'input>(
-    tokens: &mut Lexer<'input>,
-    mutable: bool,
-) -> Result<Exp_, ParseError<usize, anyhow::Error>> {
-    // This could be either a field borrow (from UnaryExp) or
-    // a borrow of a local variable (from Term). In the latter case,
-    // only a simple name token is allowed, and it must not be
-    // the start of a pack expression.
-    let e = if tokens.peek() == Tok::NameValue {
-        if tokens.lookahead()? != Tok::LBrace {
-            let var = parse_var(tokens)?;
-            return Ok(Exp_::BorrowLocal(mutable, var));
-        }
-        let start_loc = tokens.start_loc();
-        let name = parse_name(tokens)?;
-        let end_loc = tokens.previous_end_loc();
-        let type_actuals: Vec<Type> = vec![];
-        spanned(
-            start_loc,
-            end_loc,
-            parse_pack_(tokens, &name, type_actuals)?,
-        )
-    } else {
-        parse_unary_exp(tokens)?
-    };
-    consume_token(tokens, Tok::Period)?;
-    let f = parse_field_(parse_name(tokens)?)?;
-    Ok(Exp_::Borrow {
-        is_mutable: mutable,
-        exp: Box::new(e),
-        field: f,
-    })
-}
-
-fn parse_unary_exp_<'input>(
-    tokens: &mut Lexer<'input>,
-) -> Result<Exp_, ParseError<usize, anyhow::Error>> {
-    match tokens.peek() {
-        Tok::Exclaim => {
-            tokens.advance()?;
-            let e = parse_unary_exp(tokens)?;
-            Ok(Exp_::UnaryExp(UnaryOp::Not, Box::new(e)))
-        }
-        Tok::Star => {
-            tokens.advance()?;
-            let e = parse_unary_exp(tokens)?;
-            Ok(Exp_::Dereference(Box::new(e)))
-        }
-        Tok::AmpMut => {
-            tokens.advance()?;
-            parse_borrow_field_(tokens, true)
-        }
-        Tok::Amp => {
-            tokens.advance()?;
-            parse_borrow_field_(tokens, false)
-        }
-        _ => parse_call_or_term_(tokens),
-    }
-}
-
-fn parse_unary_exp<'input>(
-    tokens: &mut Lexer<'input>,
-) -> Result<Exp, ParseError<usize, anyhow::Error>> {
-    let start_loc = tokens.start_loc();
-    let e = parse_unary_exp_(tokens)?;
-    let end_loc = tokens.previous_end_loc();
-    Ok(spanned(start_loc, end_loc, e))
-}
-
 // Call: Exp = {
 //     <f: Sp<QualifiedFunctionName>> <exp: Sp<CallOrTerm>> => Exp::FunctionCall(f, Box::new(exp)),
 // }
@@ -501,98 +645,554 @@ fn parse_call<'input>(tokens: &mut Lexer<'input>) -> Result<Exp, ParseError<usiz
 //     Term,
 // }
 
-fn parse_call_or_term_<'input>(
-    tokens: &mut Lexer<'input>,
-) -> Result<Exp_, ParseError<usize, anyhow::Error>> {
-    match tokens.peek() {
-        Tok::Exists
-        | Tok::BorrowGlobal
-        | Tok::BorrowGlobalMut
-        | Tok::GetTxnSender
-        | Tok::MoveFrom
-        | Tok::MoveToSender
-        | Tok::Freeze
-        | Tok::DotNameValue
-        | Tok::ToU8
-        | Tok::ToU64
-        | Tok::ToU128 => {
-            let f = parse_qualified_function_name(tokens)?;
-            let exp = parse_call_or_term(tokens)?;
-            Ok(Exp_::FunctionCall(f, Box::new(exp)))
-        }
-        _ => parse_term_(tokens),
-    }
-}
-
 fn parse_call_or_term<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<Exp, ParseError<usize, anyhow::Error>> {
-    let start_loc = tokens.start_loc();
-    let v = parse_call_or_term_(tokens)?;
-    let end_loc = tokens.previous_end_loc();
-    Ok(spanned(start_loc, end_loc, v))
+    parse_exp_iteratively(tokens, /* call_or_term */ true)
 }
 
 // FieldExp: (Field_, Exp_) = {
 //     <f: Sp<Field>> ":" <e: Sp<Exp>> => (f, e)
 // }
 
-fn parse_field_exp<'input>(
-    tokens: &mut Lexer<'input>,
-) -> Result<(Field, Exp), ParseError<usize, anyhow::Error>> {
-    let f = parse_field(tokens)?;
-    consume_token(tokens, Tok::Colon)?;
-    let e = parse_exp(tokens)?;
-    Ok((f, e))
-}
-
 // Term: Exp = {
-//     "move(" <v: Sp<Var>> ")" => Exp::Move(v),
-//     "copy(" <v: Sp<Var>> ")" => Exp::Copy(v),
+//     "move" "(" <v: Sp<Var>> ")" => Exp::Move(v),
+//     "copy" "(" <v: Sp<Var>> ")" => Exp::Copy(v),
+//     "move" <v: Sp<Var>> => Exp::Move(v),
+//     "copy" <v: Sp<Var>> => Exp::Copy(v),
+//     <v: Sp<Var>> => Exp::Var(v),
 //     "&mut " <v: Sp<Var>> => Exp::BorrowLocal(true, v),
 //     "&" <v: Sp<Var>> => Exp::BorrowLocal(false, v),
 //     Sp<CopyableVal> => Exp::Value(<>),
 //     <name_and_type_actuals: NameAndTypeActuals> "{" <fs:Comma<FieldExp>> "}" =>? { ... },
 //     "(" <exps: Comma<Sp<Exp>>> ")" => Exp::ExprList(exps),
+//     "{" "{" <n: Name> "}" "}" =>? Exp::Placeholder(n),
 // }
 
-fn parse_pack_<'input>(
-    tokens: &mut Lexer<'input>,
-    name: &str,
+// The rules above are not parsed by recursive descent: nesting expressions would then nest calls
+// of the parser, and an adversarial input with deeply nested expressions could overflow the
+// stack. Instead, expressions that have been started but still miss a subexpression are kept on
+// an explicit stack of frames, so that the stack usage of the parser does not depend on the
+// nesting depth. The depth of each expression is still tracked, and bounded by the `max_exp_depth`
+// option for the code recursing over the AST.
+
+// An operator that applies to the expression after it: a unary expression, or a call or term
+// for a function call.
+enum PrefixOp {
+    Not,
+    Dereference,
+    // A borrow of a field, which is given after the expression.
+    BorrowField(bool),
+    Call(FunctionCall),
+}
+
+// An expression waiting for one of its subexpressions.
+enum ExpFrame<'input> {
+    Prefix {
+        start_loc: usize,
+        op: PrefixOp,
+    },
+    // A binary operator and its left operand, waiting for the right operand.
+    Binop {
+        lhs: Exp,
+        lhs_depth: usize,
+        op: BinOp,
+        prec: u32,
+    },
+    // A parenthesized list of expressions, waiting for its next element. `depth` is the maximal
+    // depth of its elements so far.
+    List {
+        start_loc: usize,
+        exps: Vec<Exp>,
+        depth: usize,
+    },
+    // A pack, waiting for the value of `field`. `depth` is the maximal depth of its field values
+    // so far.
+    Pack {
+        start: PackStart<'input>,
+        fields: Vec<(Field, Exp)>,
+        field: Field,
+        depth: usize,
+    },
+}
+
+// What is known of a pack once its name is parsed.
+struct PackStart<'input> {
+    start_loc: usize,
+    // The span of a pack borrowed with `&` or `&mut` ends with its name.
+    name_end_loc: Option<usize>,
+    name: &'input str,
     type_actuals: Vec<Type>,
-) -> Result<Exp_, ParseError<usize, anyhow::Error>> {
+}
+
+// Parses an expression, or only a call or term if `call_or_term` is set.
+fn parse_exp_iteratively<'input>(
+    tokens: &mut Lexer<'input>,
+    call_or_term: bool,
+) -> Result<Exp, ParseError<usize, anyhow::Error>> {
+    profile!("Exp");
+    let mut stack = vec![];
+    'operand: loop {
+        // Push the expressions starting before the next term, and parse that term.
+        let mut e = loop {
+            let start_loc = tokens.start_loc();
+            let in_call_or_term = match stack.last() {
+                Some(ExpFrame::Prefix {
+                    op: PrefixOp::Call(_),
+                    ..
+                }) => true,
+                Some(_) => false,
+                None => call_or_term,
+            };
+            recognize_move_or_copy(tokens)?;
+            recognize_builtin(tokens)?;
+            let op = match tokens.peek() {
+                Tok::Exclaim if !in_call_or_term => {
+                    cover!("UnaryExp", "Not");
+                    tokens.advance()?;
+                    PrefixOp::Not
+                }
+                Tok::Star if !in_call_or_term => {
+                    cover!("UnaryExp", "Dereference");
+                    tokens.advance()?;
+                    PrefixOp::Dereference
+                }
+                Tok::AmpMut | Tok::Amp if !in_call_or_term => {
+                    let mutable = tokens.peek() == Tok::AmpMut;
+                    tokens.advance()?;
+                    recognize_move_or_copy(tokens)?;
+                    // This could be either a field borrow (from UnaryExp) or
+                    // a borrow of a local variable (from Term). In the latter case,
+                    // only a simple name token is allowed, and it must not be
+                    // the start of a pack expression.
+                    if tokens.peek() == Tok::NameValue {
+                        if tokens.lookahead()? != Tok::LBrace {
+                            if mutable {
+                                cover!("Term", "BorrowLocalMut");
+                            } else {
+                                cover!("Term", "BorrowLocal");
+                            }
+                            let var = parse_var(tokens)?;
+                            let end_loc = tokens.previous_end_loc();
+                            break spanned(start_loc, end_loc, Exp_::BorrowLocal(mutable, var));
+                        }
+                        stack.push(ExpFrame::Prefix {
+                            start_loc,
+                            op: PrefixOp::BorrowField(mutable),
+                        });
+                        let name_start_loc = tokens.start_loc();
+                        let name = parse_name(tokens)?;
+                        let start = PackStart {
+                            start_loc: name_start_loc,
+                            name_end_loc: Some(tokens.previous_end_loc()),
+                            name,
+                            type_actuals: vec![],
+                        };
+                        match parse_pack_start(tokens, &mut stack, start)? {
+                            Some(e) => break e,
+                            None => continue,
+                        }
+                    }
+                    PrefixOp::BorrowField(mutable)
+                }
+                // A local followed by the field an enclosing `&` borrows, as in `&x.f`, is lexed
+                // as a single dotted name, which names a function when followed by its type
+                // actuals or its arguments.
+                Tok::DotNameValue if !starts_call_arguments(tokens)? => {
+                    cover!("Term", "Var");
+                    let dot = tokens.content().find('.').unwrap();
+                    tokens.replace_token(Tok::NameValue, dot)?;
+                    let var = parse_var(tokens)?;
+                    let end_loc = tokens.previous_end_loc();
+                    break spanned(start_loc, end_loc, Exp_::Var(var));
+                }
+                Tok::Exists
+                | Tok::BorrowGlobal
+                | Tok::BorrowGlobalMut
+                | Tok::GetTxnSender
+                | Tok::MoveFrom
+                | Tok::MoveToSender
+                | Tok::Freeze
+                | Tok::DotNameValue
+                | Tok::ToU8
+                | Tok::ToU64
+                | Tok::ToU128
+                | Tok::Rotl
+                | Tok::Rotr
+                | Tok::CountOnes
+                | Tok::CheckedAdd
+                | Tok::CheckedSub
+                | Tok::SaturatingAdd
+                | Tok::SaturatingSub
+                | Tok::Sha2_256
+                | Tok::Sha3_256
+                | Tok::ToBytes => {
+                    cover!("CallOrTerm", "Call");
+                    PrefixOp::Call(parse_qualified_function_name(tokens)?)
+                }
+                // A name is a pack if it is followed by fields, and a local otherwise.
+                Tok::NameValue if tokens.lookahead()? != Tok::LBrace => {
+                    cover!("Term", "Var");
+                    let var = parse_var(tokens)?;
+                    let end_loc = tokens.previous_end_loc();
+                    break spanned(start_loc, end_loc, Exp_::Var(var));
+                }
+                Tok::NameValue | Tok::NameBeginTyValue => {
+                    let (name, type_actuals) = parse_name_and_type_actuals(tokens)?;
+                    let start = PackStart {
+                        start_loc,
+                        name_end_loc: None,
+                        name,
+                        type_actuals,
+                    };
+                    match parse_pack_start(tokens, &mut stack, start)? {
+                        Some(e) => break e,
+                        None => continue,
+                    }
+                }
+                Tok::LParen => {
+                    tokens.advance()?;
+                    if tokens.peek() == Tok::RParen {
+                        tokens.advance()?;
+                        cover!("Term", "ExprList");
+                        let end_loc = tokens.previous_end_loc();
+                        break spanned(start_loc, end_loc, Exp_::ExprList(vec![]));
+                    }
+                    stack.push(ExpFrame::List {
+                        start_loc,
+                        exps: vec![],
+                        depth: 0,
+                    });
+                    continue;
+                }
+                _ => {
+                    let e = parse_term_(tokens)?;
+                    let end_loc = tokens.previous_end_loc();
+                    break spanned(start_loc, end_loc, e);
+                }
+            };
+            stack.push(ExpFrame::Prefix { start_loc, op });
+        };
+        let mut depth = 1;
+
+        // Pop the expressions completed by the term, until one needs another subexpression.
+        loop {
+            while let Some(frame) = stack.pop() {
+                match frame {
+                    ExpFrame::Prefix { start_loc, op } => {
+                        e = apply_prefix(tokens, start_loc, op, e)?;
+                        depth = check_depth(tokens, &e, depth + 1)?;
+                    }
+                    frame => {
+                        stack.push(frame);
+                        break;
+                    }
+                }
+            }
+            if stack.is_empty() && call_or_term {
+                return Ok(e);
+            }
+
+            // Binary operators are left associative: the ones on the stack with a precedence
+            // at least that of the next operator take `e` as their right operand.
+            let next_prec = get_precedence(&tokens.peek());
+            while let Some(frame) = stack.pop() {
+                match frame {
+                    ExpFrame::Binop {
+                        lhs,
+                        lhs_depth,
+                        op,
+                        prec,
+                    } if prec >= next_prec => {
+                        check_not_chained(tokens, &lhs, &op, &e)?;
+                        cover!("Exp", crate::coverage::binop_name(&op));
+                        let start_loc = lhs.span.start();
+                        let end_loc = tokens.previous_end_loc();
+                        e = Spanned {
+                            span: Span::new(start_loc, ByteIndex(end_loc as u32)),
+                            value: Exp_::BinopExp(Box::new(lhs), op, Box::new(e)),
+                        };
+                        depth = std::cmp::max(lhs_depth, depth);
+                    }
+                    frame => {
+                        stack.push(frame);
+                        break;
+                    }
+                }
+            }
+            if next_prec > 0 {
+                let op = parse_binop(&tokens.peek());
+                tokens.advance()?;
+                stack.push(ExpFrame::Binop {
+                    lhs: e,
+                    lhs_depth: depth,
+                    op,
+                    prec: next_prec,
+                });
+                continue 'operand;
+            }
+
+            match stack.pop() {
+                None => return Ok(e),
+                Some(ExpFrame::List {
+                    start_loc,
+                    mut exps,
+                    depth: list_depth,
+                }) => {
+                    exps.push(e);
+                    let list_depth = std::cmp::max(list_depth, depth);
+                    if tokens.peek() != Tok::RParen {
+                        consume_token(tokens, Tok::Comma)?;
+                    }
+                    if tokens.peek() != Tok::RParen {
+                        stack.push(ExpFrame::List {
+                            start_loc,
+                            exps,
+                            depth: list_depth,
+                        });
+                        continue 'operand;
+                    }
+                    tokens.advance()?;
+                    cover!("Term", "ExprList");
+                    let end_loc = tokens.previous_end_loc();
+                    e = spanned(start_loc, end_loc, Exp_::ExprList(exps));
+                    depth = check_depth(tokens, &e, list_depth + 1)?;
+                }
+                Some(ExpFrame::Pack {
+                    start,
+                    mut fields,
+                    field,
+                    depth: pack_depth,
+                }) => {
+                    fields.push((field, e));
+                    let pack_depth = std::cmp::max(pack_depth, depth);
+                    if tokens.peek() != Tok::RBrace {
+                        consume_token(tokens, Tok::Comma)?;
+                    }
+                    if tokens.peek() != Tok::RBrace {
+                        let field = parse_field(tokens)?;
+                        consume_token(tokens, Tok::Colon)?;
+                        stack.push(ExpFrame::Pack {
+                            start,
+                            fields,
+                            field,
+                            depth: pack_depth,
+                        });
+                        continue 'operand;
+                    }
+                    tokens.advance()?;
+                    e = finish_pack(tokens, start, fields)?;
+                    depth = check_depth(tokens, &e, pack_depth + 1)?;
+                }
+                Some(ExpFrame::Prefix { .. }) | Some(ExpFrame::Binop { .. }) => {
+                    unreachable!("prefixes and binary operators are applied first")
+                }
+            }
+        }
+    }
+}
+
+// Whether the dotted name of the current token is followed by the arguments of a call, or by its
+// type actuals, which are written right after it as in `M.f<T>`.
+// Rejects the comparison of the result of a comparison with `op`, as in `a < b < c`, which reads
+// as a range check but would compare a `bool`. Parenthesized comparisons are lists, and are not
+// rejected.
+fn check_not_chained<'input>(
+    tokens: &Lexer<'input>,
+    lhs: &Exp,
+    op: &BinOp,
+    rhs: &Exp,
+) -> Result<(), ParseError<usize, anyhow::Error>> {
+    let middle = match &lhs.value {
+        Exp_::BinopExp(_, lhs_op, middle) if is_ordering(lhs_op) && is_ordering(op) => middle,
+        _ => return Ok(()),
+    };
+    let text = tokens.source();
+    let slice = |loc: Loc| &text[loc.start().0 as usize..loc.end().0 as usize];
+    let (lhs_end, end) = (lhs.span.end().0 as usize, rhs.span.end().0 as usize);
+    Err(ParseError::ChainedComparison {
+        loc: make_loc(lhs.span.start().0 as usize, end),
+        replacement: format!(
+            "{} && {}{}",
+            slice(lhs.span),
+            slice(middle.span),
+            &text[lhs_end..end]
+        ),
+    })
+}
+
+// Returns `depth`, the depth of `e`, failing if it exceeds the maximal depth of an expression.
+fn check_depth<'input>(
+    tokens: &Lexer<'input>,
+    e: &Exp,
+    depth: usize,
+) -> Result<usize, ParseError<usize, anyhow::Error>> {
+    match tokens.options.max_exp_depth {
+        Some(max_depth) if depth > max_depth => Err(ParseError::TooDeeplyNested {
+            loc: e.span,
+            max_depth,
+        }),
+        _ => Ok(depth),
+    }
+}
+
+fn is_ordering(op: &BinOp) -> bool {
+    match op {
+        BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => true,
+        _ => false,
+    }
+}
+
+fn starts_call_arguments(tokens: &Lexer) -> Result<bool, ParseError<usize, anyhow::Error>> {
+    Ok(match tokens.lookahead()? {
+        Tok::LParen => true,
+        Tok::Less => tokens.next_is_adjacent(),
+        _ => false,
+    })
+}
+
+// Reads a name as the builtin it spells where a call may start: when the name is followed by the
+// arguments of the builtin, or by the type actual of `to_bytes`. Elsewhere the name remains
+// available for locals, functions and fields.
+fn recognize_builtin(tokens: &mut Lexer) -> Result<(), ParseError<usize, anyhow::Error>> {
+    let content = tokens.content();
+    let builtin = match tokens.peek() {
+        Tok::NameValue if tokens.lookahead()? == Tok::LParen => {
+            match get_contextual_token(content) {
+                Some(Tok::Emit) | Some(Tok::Pragma) | Some(Tok::ToBytes) => None,
+                tok => tok,
+            }
+        }
+        Tok::NameBeginTyValue => match get_contextual_token(&content[..content.len() - 1]) {
+            Some(Tok::ToBytes) => Some(Tok::ToBytes),
+            _ => None,
+        },
+        _ => None,
+    };
+    match builtin {
+        Some(tok) => tokens.replace_token(tok, content.len()),
+        None => Ok(()),
+    }
+}
+
+// Reads `move` or `copy` as the keyword when followed by a local, written after it or in
+// parentheses. Elsewhere, as in `let move: u64;` or `move = 1`, the name remains a local.
+fn recognize_move_or_copy(tokens: &mut Lexer) -> Result<(), ParseError<usize, anyhow::Error>> {
+    if tokens.peek() != Tok::NameValue {
+        return Ok(());
+    }
+    let content = tokens.content();
+    match get_contextual_token(content) {
+        Some(tok @ Tok::Move) | Some(tok @ Tok::Copy) => match tokens.lookahead()? {
+            Tok::LParen | Tok::NameValue | Tok::DotNameValue => {
+                tokens.replace_token(tok, content.len())
+            }
+            _ => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+fn apply_prefix<'input>(
+    tokens: &mut Lexer<'input>,
+    start_loc: usize,
+    op: PrefixOp,
+    e: Exp,
+) -> Result<Exp, ParseError<usize, anyhow::Error>> {
+    let e = match op {
+        PrefixOp::Not => Exp_::UnaryExp(UnaryOp::Not, Box::new(e)),
+        PrefixOp::Dereference => Exp_::Dereference(Box::new(e)),
+        PrefixOp::BorrowField(mutable) => {
+            consume_token(tokens, Tok::Period)?;
+            let f = parse_field_(parse_name(tokens)?)?;
+            if mutable {
+                cover!("UnaryExp", "BorrowFieldMut");
+            } else {
+                cover!("UnaryExp", "BorrowField");
+            }
+            Exp_::Borrow {
+                is_mutable: mutable,
+                exp: Box::new(e),
+                field: f,
+            }
+        }
+        PrefixOp::Call(f) => Exp_::FunctionCall(f, Box::new(e)),
+    };
+    let end_loc = tokens.previous_end_loc();
+    Ok(spanned(start_loc, end_loc, e))
+}
+
+// Parses the pack after its name up to its first field value, which is left to the caller by
+// pushing a frame for it. Returns the pack instead if it has no fields.
+fn parse_pack_start<'input>(
+    tokens: &mut Lexer<'input>,
+    stack: &mut Vec<ExpFrame<'input>>,
+    start: PackStart<'input>,
+) -> Result<Option<Exp>, ParseError<usize, anyhow::Error>> {
+    cover!("Term", "Pack");
     consume_token(tokens, Tok::LBrace)?;
-    let fs = parse_comma_list(tokens, &[Tok::RBrace], parse_field_exp, true)?;
-    consume_token(tokens, Tok::RBrace)?;
-    Ok(Exp_::Pack(
-        StructName::parse(name)?,
-        type_actuals,
-        fs.into_iter().collect::<Vec<_>>(),
+    if tokens.peek() == Tok::RBrace {
+        tokens.advance()?;
+        return Ok(Some(finish_pack(tokens, start, vec![])?));
+    }
+    let field = parse_field(tokens)?;
+    consume_token(tokens, Tok::Colon)?;
+    stack.push(ExpFrame::Pack {
+        start,
+        fields: vec![],
+        field,
+        depth: 0,
+    });
+    Ok(None)
+}
+
+fn finish_pack<'input>(
+    tokens: &mut Lexer<'input>,
+    start: PackStart<'input>,
+    fields: Vec<(Field, Exp)>,
+) -> Result<Exp, ParseError<usize, anyhow::Error>> {
+    check_unique_fields(&fields)?;
+    let end_loc = start
+        .name_end_loc
+        .unwrap_or_else(|| tokens.previous_end_loc());
+    Ok(spanned(
+        start.start_loc,
+        end_loc,
+        Exp_::Pack(StructName::parse(start.name)?, start.type_actuals, fields),
     ))
 }
 
+// Parses the terms that contain no expression. The others are handled by parse_exp_iteratively.
 fn parse_term_<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<Exp_, ParseError<usize, anyhow::Error>> {
     match tokens.peek() {
         Tok::Move => {
             tokens.advance()?;
-            let v = parse_var(tokens)?;
-            consume_token(tokens, Tok::RParen)?;
-            Ok(Exp_::Move(v))
+            if tokens.peek() == Tok::LParen {
+                cover!("Term", "Move");
+            } else {
+                cover!("Term", "MoveKeyword");
+            }
+            Ok(Exp_::Move(parse_moved_or_copied_var(tokens)?))
         }
         Tok::Copy => {
             tokens.advance()?;
-            let v = parse_var(tokens)?;
-            consume_token(tokens, Tok::RParen)?;
-            Ok(Exp_::Copy(v))
+            if tokens.peek() == Tok::LParen {
+                cover!("Term", "Copy");
+            } else {
+                cover!("Term", "CopyKeyword");
+            }
+            Ok(Exp_::Copy(parse_moved_or_copied_var(tokens)?))
         }
         Tok::AmpMut => {
+            cover!("Term", "BorrowLocalMut");
             tokens.advance()?;
             let v = parse_var(tokens)?;
             Ok(Exp_::BorrowLocal(true, v))
         }
         Tok::Amp => {
+            cover!("Term", "BorrowLocal");
             tokens.advance()?;
             let v = parse_var(tokens)?;
             Ok(Exp_::BorrowLocal(false, v))
@@ -603,16 +1203,18 @@ fn parse_term_<'input>(
         | Tok::U8Value
         | Tok::U64Value
         | Tok::U128Value
-        | Tok::ByteArrayValue => Ok(Exp_::Value(parse_copyable_val(tokens)?)),
-        Tok::NameValue | Tok::NameBeginTyValue => {
-            let (name, type_actuals) = parse_name_and_type_actuals(tokens)?;
-            parse_pack_(tokens, &name, type_actuals)
+        | Tok::ByteArrayValue => {
+            cover!("Term", "Value");
+            Ok(Exp_::Value(parse_copyable_val(tokens)?))
         }
-        Tok::LParen => {
+        Tok::LBrace => {
+            cover!("Term", "Placeholder");
             tokens.advance()?;
-            let exps = parse_comma_list(tokens, &[Tok::RParen], parse_exp, true)?;
-            consume_token(tokens, Tok::RParen)?;
-            Ok(Exp_::ExprList(exps))
+            consume_token(tokens, Tok::LBrace)?;
+            let name = parse_name(tokens)?;
+            consume_token(tokens, Tok::RBrace)?;
+            consume_token(tokens, Tok::RBrace)?;
+            Ok(Exp_::Placeholder(Identifier::new(name)?))
         }
         _ => Err(ParseError::InvalidToken {
             location: tokens.start_loc(),
@@ -620,6 +1222,27 @@ fn parse_term_<'input>(
     }
 }
 
+// Parses the local following `move` or `copy`, which is either written after them or, as in
+// the older pseudo-call form, in parentheses.
+fn parse_moved_or_copied_var<'input>(
+    tokens: &mut Lexer<'input>,
+) -> Result<Var, ParseError<usize, anyhow::Error>> {
+    match tokens.peek() {
+        Tok::LParen => (),
+        // As in `&move x.f`, the local and the field borrowed from it are lexed as a dotted name.
+        Tok::DotNameValue => {
+            let dot = tokens.content().find('.').unwrap();
+            tokens.replace_token(Tok::NameValue, dot)?;
+            return parse_var(tokens);
+        }
+        _ => return parse_var(tokens),
+    }
+    tokens.advance()?;
+    let v = parse_var(tokens)?;
+    consume_token(tokens, Tok::RParen)?;
+    Ok(v)
+}
+
 // StructName: StructName = {
 //     <n: Name> =>? StructName::parse(n),
 // }
@@ -637,11 +1260,9 @@ fn parse_struct_name<'input>(
 fn parse_qualified_struct_ident<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<QualifiedStructIdent, ParseError<usize, anyhow::Error>> {
-    let module_dot_struct = parse_dot_name(tokens)?;
-    let v: Vec<&str> = module_dot_struct.split('.').collect();
-    assert!(v.len() == 2);
-    let m: ModuleName = ModuleName::parse(v[0])?;
-    let n: StructName = StructName::parse(v[1])?;
+    let (module, name) = parse_dot_name(tokens)?;
+    let m: ModuleName = ModuleName::parse(module)?;
+    let n: StructName = StructName::parse(name)?;
     Ok(QualifiedStructIdent::new(m, n))
 }
 
@@ -679,6 +1300,19 @@ fn consume_end_of_generics<'input>(
 //     "move_from<" <name_and_type_actuals: NameAndTypeActuals> ">" =>? { ... },
 //     "move_to_sender<" <name_and_type_actuals: NameAndTypeActuals> ">" =>? { ...},
 //     "freeze" => Builtin::Freeze,
+//     "to_u8" => Builtin::ToU8,
+//     "to_u64" => Builtin::ToU64,
+//     "to_u128" => Builtin::ToU128,
+//     "rotl" => Builtin::Rotl,
+//     "rotr" => Builtin::Rotr,
+//     "count_ones" => Builtin::CountOnes,
+//     "checked_add" => Builtin::CheckedAdd,
+//     "checked_sub" => Builtin::CheckedSub,
+//     "saturating_add" => Builtin::SaturatingAdd,
+//     "saturating_sub" => Builtin::SaturatingSub,
+//     "sha2_256" => Builtin::Sha2_256,
+//     "sha3_256" => Builtin::Sha3_256,
+//     "to_bytes<" <t: Type> ">" => Builtin::ToBytes(t),
 // }
 
 fn parse_builtin<'input>(
@@ -686,12 +1320,14 @@ fn parse_builtin<'input>(
 ) -> Result<Builtin, ParseError<usize, anyhow::Error>> {
     match tokens.peek() {
         Tok::Exists => {
+            cover!("Builtin", "Exists");
             tokens.advance()?;
             let (name, type_actuals) = parse_name_and_type_actuals(tokens)?;
             consume_end_of_generics(tokens)?;
             Ok(Builtin::Exists(StructName::parse(name)?, type_actuals))
         }
         Tok::BorrowGlobal => {
+            cover!("Builtin", "BorrowGlobal");
             tokens.advance()?;
             let (name, type_actuals) = parse_name_and_type_actuals(tokens)?;
             consume_end_of_generics(tokens)?;
@@ -702,6 +1338,7 @@ fn parse_builtin<'input>(
             ))
         }
         Tok::BorrowGlobalMut => {
+            cover!("Builtin", "BorrowGlobalMut");
             tokens.advance()?;
             let (name, type_actuals) = parse_name_and_type_actuals(tokens)?;
             consume_end_of_generics(tokens)?;
@@ -712,16 +1349,19 @@ fn parse_builtin<'input>(
             ))
         }
         Tok::GetTxnSender => {
+            cover!("Builtin", "GetTxnSender");
             tokens.advance()?;
             Ok(Builtin::GetTxnSender)
         }
         Tok::MoveFrom => {
+            cover!("Builtin", "MoveFrom");
             tokens.advance()?;
             let (name, type_actuals) = parse_name_and_type_actuals(tokens)?;
             consume_end_of_generics(tokens)?;
             Ok(Builtin::MoveFrom(StructName::parse(name)?, type_actuals))
         }
         Tok::MoveToSender => {
+            cover!("Builtin", "MoveToSender");
             tokens.advance()?;
             let (name, type_actuals) = parse_name_and_type_actuals(tokens)?;
             consume_end_of_generics(tokens)?;
@@ -731,21 +1371,77 @@ fn parse_builtin<'input>(
             ))
         }
         Tok::Freeze => {
+            cover!("Builtin", "Freeze");
             tokens.advance()?;
             Ok(Builtin::Freeze)
         }
         Tok::ToU8 => {
+            cover!("Builtin", "ToU8");
             tokens.advance()?;
             Ok(Builtin::ToU8)
         }
         Tok::ToU64 => {
+            cover!("Builtin", "ToU64");
             tokens.advance()?;
             Ok(Builtin::ToU64)
         }
         Tok::ToU128 => {
+            cover!("Builtin", "ToU128");
             tokens.advance()?;
             Ok(Builtin::ToU128)
         }
+        Tok::Rotl => {
+            cover!("Builtin", "Rotl");
+            tokens.advance()?;
+            Ok(Builtin::Rotl)
+        }
+        Tok::Rotr => {
+            cover!("Builtin", "Rotr");
+            tokens.advance()?;
+            Ok(Builtin::Rotr)
+        }
+        Tok::CountOnes => {
+            cover!("Builtin", "CountOnes");
+            tokens.advance()?;
+            Ok(Builtin::CountOnes)
+        }
+        Tok::CheckedAdd => {
+            cover!("Builtin", "CheckedAdd");
+            tokens.advance()?;
+            Ok(Builtin::CheckedAdd)
+        }
+        Tok::CheckedSub => {
+            cover!("Builtin", "CheckedSub");
+            tokens.advance()?;
+            Ok(Builtin::CheckedSub)
+        }
+        Tok::SaturatingAdd => {
+            cover!("Builtin", "SaturatingAdd");
+            tokens.advance()?;
+            Ok(Builtin::SaturatingAdd)
+        }
+        Tok::SaturatingSub => {
+            cover!("Builtin", "SaturatingSub");
+            tokens.advance()?;
+            Ok(Builtin::SaturatingSub)
+        }
+        Tok::Sha2_256 => {
+            cover!("Builtin", "Sha2_256");
+            tokens.advance()?;
+            Ok(Builtin::Sha2_256)
+        }
+        Tok::Sha3_256 => {
+            cover!("Builtin", "Sha3_256");
+            tokens.advance()?;
+            Ok(Builtin::Sha3_256)
+        }
+        Tok::ToBytes => {
+            cover!("Builtin", "ToBytes");
+            tokens.advance()?;
+            let ty = parse_type(tokens)?;
+            consume_end_of_generics(tokens)?;
+            Ok(Builtin::ToBytes(ty))
+        }
         _ => Err(ParseError::InvalidToken {
             location: tokens.start_loc(),
         }),
@@ -763,15 +1459,18 @@ fn parse_lvalue_<'input>(
 ) -> Result<LValue_, ParseError<usize, anyhow::Error>> {
     match tokens.peek() {
         Tok::NameValue => {
+            cover!("LValue", "Var");
             let l = parse_var(tokens)?;
             Ok(LValue_::Var(l))
         }
         Tok::Star => {
+            cover!("LValue", "Mutate");
             tokens.advance()?;
             let e = parse_exp(tokens)?;
             Ok(LValue_::Mutate(e))
         }
         Tok::Underscore => {
+            cover!("LValue", "Pop");
             tokens.advance()?;
             Ok(LValue_::Pop)
         }
@@ -800,10 +1499,12 @@ fn parse_field_bindings<'input>(
 ) -> Result<(Field, Var), ParseError<usize, anyhow::Error>> {
     let f = parse_field(tokens)?;
     if tokens.peek() == Tok::Colon {
+        cover!("FieldBindings", "Binding");
         tokens.advance()?; // consume the colon
         let v = parse_var(tokens)?;
         Ok((f, v))
     } else {
+        cover!("FieldBindings", "Shorthand");
         Ok((
             f.clone(),
             Spanned {
@@ -820,14 +1521,21 @@ fn parse_field_bindings<'input>(
 //     "abort" <err: Sp<Exp>?> => { ... },
 //     "return" <v: Comma<Sp<Exp>>> => Cmd::Return(Box::new(Spanned::no_loc(Exp::ExprList(v)))),
 //     "continue" => Cmd::Continue,
-//     "break" => Cmd::Break,
+//     "break" <e: Sp<Exp>?> => Cmd::Break(e.map(Box::new)),
+//     "emit" <handle: Sp<Exp>> "," <event: Sp<Exp>> => Cmd::Emit(Box::new(handle), Box::new(event)),
 //     <Sp<Call>> => Cmd::Exp(Box::new(<>)),
 //     "(" <Comma<Sp<Exp>>> ")" => Cmd::Exp(Box::new(Spanned::no_loc(Exp::ExprList(<>)))),
 // }
 
+// A command, or the assignment of the value of a loop, which is a statement.
+enum CmdOrLoop {
+    Cmd(Cmd_),
+    Loop(Loop),
+}
+
 fn parse_assign_<'input>(
     tokens: &mut Lexer<'input>,
-) -> Result<Cmd_, ParseError<usize, anyhow::Error>> {
+) -> Result<CmdOrLoop, ParseError<usize, anyhow::Error>> {
     let lvalues = parse_comma_list(tokens, &[Tok::Equal], parse_lvalue, false)?;
     if lvalues.is_empty() {
         return Err(ParseError::InvalidToken {
@@ -835,8 +1543,18 @@ fn parse_assign_<'input>(
         });
     }
     consume_token(tokens, Tok::Equal)?;
+    if tokens.peek() == Tok::Loop {
+        cover!("LoopStatement", "Assign");
+        tokens.advance()?;
+        let block = parse_block(tokens)?;
+        return Ok(CmdOrLoop::Loop(Loop {
+            block,
+            results: lvalues,
+        }));
+    }
     let e = parse_exp(tokens)?;
-    Ok(Cmd_::Assign(lvalues, e))
+    cover!("Cmd", "Assign");
+    Ok(CmdOrLoop::Cmd(Cmd_::Assign(lvalues, e)))
 }
 
 fn parse_unpack_<'input>(
@@ -849,6 +1567,7 @@ fn parse_unpack_<'input>(
     consume_token(tokens, Tok::RBrace)?;
     consume_token(tokens, Tok::Equal)?;
     let e = parse_exp(tokens)?;
+    cover!("Cmd", "Unpack");
     Ok(Cmd_::Unpack(
         StructName::parse(name)?,
         type_actuals,
@@ -860,43 +1579,84 @@ fn parse_unpack_<'input>(
 fn parse_cmd_<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<Cmd_, ParseError<usize, anyhow::Error>> {
-    match tokens.peek() {
+    let start_loc = tokens.start_loc();
+    match parse_cmd_or_loop(tokens)? {
+        CmdOrLoop::Cmd(cmd) => Ok(cmd),
+        // The value of a loop can only be assigned by a statement.
+        CmdOrLoop::Loop(_) => Err(ParseError::InvalidToken {
+            location: start_loc,
+        }),
+    }
+}
+
+fn parse_cmd_or_loop<'input>(
+    tokens: &mut Lexer<'input>,
+) -> Result<CmdOrLoop, ParseError<usize, anyhow::Error>> {
+    profile!("Cmd");
+    recognize_builtin(tokens)?;
+    // `emit` starts a statement unless it is a local being assigned or a struct being unpacked.
+    if tokens.peek() == Tok::NameValue && tokens.content() == "emit" {
+        match tokens.lookahead()? {
+            Tok::Equal | Tok::Comma | Tok::LBrace => (),
+            _ => tokens.replace_token(Tok::Emit, "emit".len())?,
+        }
+    }
+    let cmd = match tokens.peek() {
         Tok::NameValue => {
             // This could be either an LValue for an assignment or
             // NameAndTypeActuals (with no type_actuals) for an unpack.
             if tokens.lookahead()? == Tok::LBrace {
                 let name = parse_name(tokens)?;
-                parse_unpack_(tokens, &name, vec![])
+                parse_unpack_(tokens, name, vec![])
             } else {
-                parse_assign_(tokens)
+                return parse_assign_(tokens);
             }
         }
-        Tok::Star | Tok::Underscore => parse_assign_(tokens),
+        Tok::Star | Tok::Underscore => return parse_assign_(tokens),
         Tok::NameBeginTyValue => {
             let (name, tys) = parse_name_and_type_actuals(tokens)?;
-            parse_unpack_(tokens, &name, tys)
+            parse_unpack_(tokens, name, tys)
         }
         Tok::Abort => {
             tokens.advance()?;
             let val = if tokens.peek() == Tok::Semicolon {
+                cover!("Cmd", "AbortWithoutCode");
                 None
             } else {
+                cover!("Cmd", "Abort");
                 Some(Box::new(parse_exp(tokens)?))
             };
             Ok(Cmd_::Abort(val))
         }
         Tok::Return => {
+            cover!("Cmd", "Return");
             tokens.advance()?;
             let v = parse_comma_list(tokens, &[Tok::Semicolon], parse_exp, true)?;
             Ok(Cmd_::Return(Box::new(Spanned::no_loc(Exp_::ExprList(v)))))
         }
         Tok::Continue => {
+            cover!("Cmd", "Continue");
             tokens.advance()?;
             Ok(Cmd_::Continue)
         }
         Tok::Break => {
             tokens.advance()?;
-            Ok(Cmd_::Break)
+            let val = if tokens.peek() == Tok::Semicolon {
+                cover!("Cmd", "Break");
+                None
+            } else {
+                cover!("Cmd", "BreakWithValue");
+                Some(Box::new(parse_exp(tokens)?))
+            };
+            Ok(Cmd_::Break(val))
+        }
+        Tok::Emit => {
+            cover!("Cmd", "Emit");
+            tokens.advance()?;
+            let handle = parse_exp(tokens)?;
+            consume_token(tokens, Tok::Comma)?;
+            let event = parse_exp(tokens)?;
+            Ok(Cmd_::Emit(Box::new(handle), Box::new(event)))
         }
         Tok::Exists
         | Tok::BorrowGlobal
@@ -908,8 +1668,22 @@ fn parse_cmd_<'input>(
         | Tok::DotNameValue
         | Tok::ToU8
         | Tok::ToU64
-        | Tok::ToU128 => Ok(Cmd_::Exp(Box::new(parse_call(tokens)?))),
+        | Tok::ToU128
+        | Tok::Rotl
+        | Tok::Rotr
+        | Tok::CountOnes
+        | Tok::CheckedAdd
+        | Tok::CheckedSub
+        | Tok::SaturatingAdd
+        | Tok::SaturatingSub
+        | Tok::Sha2_256
+        | Tok::Sha3_256
+        | Tok::ToBytes => {
+            cover!("Cmd", "Call");
+            Ok(Cmd_::Exp(Box::new(parse_call(tokens)?)))
+        }
         Tok::LParen => {
+            cover!("Cmd", "ExprList");
             tokens.advance()?;
             let v = parse_comma_list(tokens, &[Tok::RParen], parse_exp, true)?;
             consume_token(tokens, Tok::RParen)?;
@@ -918,7 +1692,8 @@ fn parse_cmd_<'input>(
         _ => Err(ParseError::InvalidToken {
             location: tokens.start_loc(),
         }),
-    }
+    }?;
+    Ok(CmdOrLoop::Cmd(cmd))
 }
 
 // Statement : Statement = {
@@ -933,8 +1708,18 @@ fn parse_cmd_<'input>(
 fn parse_statement<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<Statement, ParseError<usize, anyhow::Error>> {
+    profile!("Statement");
     match tokens.peek() {
         Tok::Assert => {
+            cover!("Statement", "Assert");
+            if tokens.options.strict {
+                let start = tokens.start_loc();
+                return Err(ParseError::Deprecated {
+                    construct: "`assert`",
+                    advice: "use `if (!cond) abort code` instead",
+                    loc: make_loc(start, start + "assert".len()),
+                });
+            }
             tokens.advance()?;
             let e = parse_exp(tokens)?;
             consume_token(tokens, Tok::Comma)?;
@@ -966,13 +1751,21 @@ fn parse_statement<'input>(
         Tok::While => parse_while_statement(tokens),
         Tok::Loop => parse_loop_statement(tokens),
         Tok::Semicolon => {
+            cover!("Statement", "Empty");
             tokens.advance()?;
             Ok(Statement::EmptyStatement)
         }
         _ => {
             // Anything else should be parsed as a Cmd...
+            cover!("Statement", "Cmd");
             let start_loc = tokens.start_loc();
-            let c = parse_cmd_(tokens)?;
+            let c = match parse_cmd_or_loop(tokens)? {
+                CmdOrLoop::Cmd(c) => c,
+                CmdOrLoop::Loop(loop_) => {
+                    consume_token(tokens, Tok::Semicolon)?;
+                    return Ok(Statement::LoopStatement(loop_));
+                }
+            };
             let end_loc = tokens.previous_end_loc();
             let cmd = spanned(start_loc, end_loc, c);
             consume_token(tokens, Tok::Semicolon)?;
@@ -995,12 +1788,14 @@ fn parse_if_statement<'input>(
     consume_token(tokens, Tok::RParen)?;
     let if_block = parse_block(tokens)?;
     if tokens.peek() == Tok::Else {
+        cover!("IfStatement", "IfElse");
         tokens.advance()?;
         let else_block = parse_block(tokens)?;
         Ok(Statement::IfElseStatement(IfElse::if_else(
             cond, if_block, else_block,
         )))
     } else {
+        cover!("IfStatement", "If");
         Ok(Statement::IfElseStatement(IfElse::if_block(cond, if_block)))
     }
 }
@@ -1012,6 +1807,7 @@ fn parse_if_statement<'input>(
 fn parse_while_statement<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<Statement, ParseError<usize, anyhow::Error>> {
+    cover!("WhileStatement", "While");
     consume_token(tokens, Tok::While)?;
     consume_token(tokens, Tok::LParen)?;
     let cond = parse_exp(tokens)?;
@@ -1022,14 +1818,19 @@ fn parse_while_statement<'input>(
 
 // LoopStatement : Statement = {
 //     "loop" <block: Sp<Block>> => { ... }
+//     <lvalues: Comma<Sp<LValue>>> "=" "loop" <block: Sp<Block>> ";" => { ... }
 // }
 
 fn parse_loop_statement<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<Statement, ParseError<usize, anyhow::Error>> {
+    cover!("LoopStatement", "Loop");
     consume_token(tokens, Tok::Loop)?;
     let block = parse_block(tokens)?;
-    Ok(Statement::LoopStatement(Loop { block }))
+    Ok(Statement::LoopStatement(Loop {
+        block,
+        results: vec![],
+    }))
 }
 
 // Statements : Vec<Statement> = {
@@ -1070,6 +1871,7 @@ fn parse_block<'input>(
 fn parse_declaration<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<(Var, Type), ParseError<usize, anyhow::Error>> {
+    cover!("Declaration", "Let");
     consume_token(tokens, Tok::Let)?;
     let v = parse_var(tokens)?;
     consume_token(tokens, Tok::Colon)?;
@@ -1101,6 +1903,7 @@ fn parse_declarations<'input>(
 fn parse_function_block_<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<(Vec<(Var, Type)>, Block_), ParseError<usize, anyhow::Error>> {
+    profile!("FunctionBlock");
     consume_token(tokens, Tok::LBrace)?;
     let locals = parse_declarations(tokens)?;
     let stmts = parse_statements(tokens)?;
@@ -1117,8 +1920,14 @@ fn parse_kind<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<Kind, ParseError<usize, anyhow::Error>> {
     let k = match tokens.peek() {
-        Tok::Resource => Kind::Resource,
-        Tok::Unrestricted => Kind::Unrestricted,
+        Tok::Resource => {
+            cover!("Kind", "Resource");
+            Kind::Resource
+        }
+        Tok::Unrestricted => {
+            cover!("Kind", "Unrestricted");
+            Kind::Unrestricted
+        }
         _ => {
             return Err(ParseError::InvalidToken {
                 location: tokens.start_loc(),
@@ -1138,50 +1947,78 @@ fn parse_kind<'input>(
 //     "&" <t: Type> => Type::Reference(false, Box::new(t)),
 //     "&mut " <t: Type> => Type::Reference(true, Box::new(t)),
 //     <n: Name> =>? Ok(Type::TypeParameter(TypeVar::parse(n)?)),
+//     "(" <t: Type> <v: ("," <Type>)+> ")" => Type::Tuple(...),
 // }
 
 fn parse_type<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<Type, ParseError<usize, anyhow::Error>> {
+    profile!("Type");
     let t = match tokens.peek() {
         Tok::Address => {
+            cover!("Type", "Address");
             tokens.advance()?;
             Type::Address
         }
         Tok::U8 => {
+            cover!("Type", "U8");
             tokens.advance()?;
             Type::U8
         }
         Tok::U64 => {
+            cover!("Type", "U64");
             tokens.advance()?;
             Type::U64
         }
         Tok::U128 => {
+            cover!("Type", "U128");
             tokens.advance()?;
             Type::U128
         }
         Tok::Bool => {
+            cover!("Type", "Bool");
             tokens.advance()?;
             Type::Bool
         }
         Tok::Bytearray => {
+            cover!("Type", "ByteArray");
             tokens.advance()?;
             Type::ByteArray
         }
         Tok::DotNameValue => {
+            cover!("Type", "Struct");
             let s = parse_qualified_struct_ident(tokens)?;
             let tys = parse_type_actuals(tokens)?;
             Type::Struct(s, tys)
         }
         Tok::Amp => {
+            cover!("Type", "Reference");
             tokens.advance()?;
             Type::Reference(false, Box::new(parse_type(tokens)?))
         }
         Tok::AmpMut => {
+            cover!("Type", "MutableReference");
             tokens.advance()?;
             Type::Reference(true, Box::new(parse_type(tokens)?))
         }
-        Tok::NameValue => Type::TypeParameter(TypeVar_::parse(parse_name(tokens)?)?),
+        Tok::NameValue => {
+            cover!("Type", "TypeParameter");
+            Type::TypeParameter(TypeVar_::parse(parse_name(tokens)?)?)
+        }
+        Tok::LParen => {
+            cover!("Type", "Tuple");
+            tokens.advance()?;
+            let mut tys = vec![parse_type(tokens)?];
+            loop {
+                consume_token(tokens, Tok::Comma)?;
+                tys.push(parse_type(tokens)?);
+                if tokens.peek() != Tok::Comma {
+                    break;
+                }
+            }
+            consume_token(tokens, Tok::RParen)?;
+            Type::Tuple(tys)
+        }
         _ => {
             return Err(ParseError::InvalidToken {
                 location: tokens.start_loc(),
@@ -1214,10 +2051,12 @@ fn parse_type_formal<'input>(
 ) -> Result<(TypeVar, Kind), ParseError<usize, anyhow::Error>> {
     let type_var = parse_type_var(tokens)?;
     if tokens.peek() == Tok::Colon {
+        cover!("TypeFormal", "Kind");
         tokens.advance()?; // consume the ":"
         let k = parse_kind(tokens)?;
         Ok((type_var, k))
     } else {
+        cover!("TypeFormal", "Unconstrained");
         Ok((type_var, Kind::All))
     }
 }
@@ -1230,11 +2069,13 @@ fn parse_type_actuals<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<Vec<Type>, ParseError<usize, anyhow::Error>> {
     let tys = if tokens.peek() == Tok::Less {
+        cover!("TypeActuals", "Explicit");
         tokens.advance()?; // consume the "<"
         let list = parse_comma_list(tokens, &[Tok::Greater], parse_type, true)?;
         consume_token(tokens, Tok::Greater)?;
         list
     } else {
+        cover!("TypeActuals", "Empty");
         vec![]
     };
     Ok(tys)
@@ -1247,12 +2088,14 @@ fn parse_type_actuals<'input>(
 
 fn parse_name_and_type_formals<'input>(
     tokens: &mut Lexer<'input>,
-) -> Result<(String, Vec<(TypeVar, Kind)>), ParseError<usize, anyhow::Error>> {
+) -> Result<(&'input str, Vec<(TypeVar, Kind)>), ParseError<usize, anyhow::Error>> {
     let mut has_types = false;
     let n = if tokens.peek() == Tok::NameBeginTyValue {
+        cover!("NameAndTypeFormals", "Generic");
         has_types = true;
         parse_name_begin_ty(tokens)?
     } else {
+        cover!("NameAndTypeFormals", "Name");
         parse_name(tokens)?
     };
     let k = if has_types {
@@ -1272,12 +2115,14 @@ fn parse_name_and_type_formals<'input>(
 
 fn parse_name_and_type_actuals<'input>(
     tokens: &mut Lexer<'input>,
-) -> Result<(String, Vec<Type>), ParseError<usize, anyhow::Error>> {
+) -> Result<(&'input str, Vec<Type>), ParseError<usize, anyhow::Error>> {
     let mut has_types = false;
     let n = if tokens.peek() == Tok::NameBeginTyValue {
+        cover!("NameAndTypeActuals", "Generic");
         has_types = true;
         parse_name_begin_ty(tokens)?
     } else {
+        cover!("NameAndTypeActuals", "Name");
         parse_name(tokens)?
     };
     let tys = if has_types {
@@ -1317,6 +2162,11 @@ fn parse_return_type<'input>(
         tokens.advance()?;
         v.push(parse_type(tokens)?);
     }
+    if v.len() == 1 {
+        cover!("ReturnType", "Single");
+    } else {
+        cover!("ReturnType", "Multiple");
+    }
     Ok(v)
 }
 
@@ -1342,7 +2192,7 @@ fn parse_acquire_list<'input>(
 // parses Name '.' Name and returns pair of strings.
 fn spec_parse_dot_name<'input>(
     tokens: &mut Lexer<'input>,
-) -> Result<(String, String), ParseError<usize, anyhow::Error>> {
+) -> Result<(&'input str, &'input str), ParseError<usize, anyhow::Error>> {
     let name1 = parse_name(tokens)?;
     consume_token(tokens, Tok::Period)?;
     let name2 = parse_name(tokens)?;
@@ -1367,6 +2217,7 @@ fn parse_storage_location<'input>(
             tokens.advance()?;
             let i = {
                 if tokens.peek() == Tok::LParen {
+                    cover!("StorageLocation", "RetIndex");
                     consume_token(tokens, Tok::LParen)?;
                     let i = u8::from_str(tokens.content()).unwrap();
                     consume_token(tokens, Tok::U64Value)?;
@@ -1374,6 +2225,7 @@ fn parse_storage_location<'input>(
                     i
                 } else {
                     // RET without brackets; use RET(0)
+                    cover!("StorageLocation", "Ret");
                     0
                 }
             };
@@ -1381,11 +2233,16 @@ fn parse_storage_location<'input>(
             StorageLocation::Ret(i)
         }
         Tok::TxnSender => {
+            cover!("StorageLocation", "TxnSender");
             tokens.advance()?;
             StorageLocation::TxnSenderAddress
         }
-        Tok::AccountAddressValue => StorageLocation::Address(parse_account_address(tokens)?),
+        Tok::AccountAddressValue => {
+            cover!("StorageLocation", "Address");
+            StorageLocation::Address(parse_account_address(tokens)?)
+        }
         Tok::Global => {
+            cover!("StorageLocation", "Global");
             consume_token(tokens, Tok::Global)?;
             consume_token(tokens, Tok::Less)?;
             let type_ = spec_parse_qualified_struct_ident(tokens)?;
@@ -1400,7 +2257,10 @@ fn parse_storage_location<'input>(
                 address,
             }
         }
-        _ => StorageLocation::Formal(parse_name(tokens)?),
+        _ => {
+            cover!("StorageLocation", "Formal");
+            StorageLocation::Formal(parse_name(tokens)?.to_string())
+        }
     };
 
     // parsed the storage location base. now parse its fields (if any)
@@ -1412,6 +2272,7 @@ fn parse_storage_location<'input>(
     if fields.is_empty() {
         Ok(base)
     } else {
+        cover!("StorageLocation", "AccessPath");
         Ok(StorageLocation::AccessPath {
             base: Box::new(base),
             fields,
@@ -1429,8 +2290,12 @@ fn parse_unary_spec_exp<'input>(
         | Tok::U8Value
         | Tok::U64Value
         | Tok::U128Value
-        | Tok::ByteArrayValue => SpecExp::Constant(parse_copyable_val(tokens)?.value),
+        | Tok::ByteArrayValue => {
+            cover!("UnarySpecExp", "Constant");
+            SpecExp::Constant(parse_copyable_val(tokens)?.value)
+        }
         Tok::GlobalExists => {
+            cover!("UnarySpecExp", "GlobalExists");
             consume_token(tokens, Tok::GlobalExists)?;
             consume_token(tokens, Tok::Less)?;
             let type_ = spec_parse_qualified_struct_ident(tokens)?;
@@ -1446,21 +2311,37 @@ fn parse_unary_spec_exp<'input>(
             }
         }
         Tok::Star => {
+            cover!("UnarySpecExp", "Dereference");
             tokens.advance()?;
             let stloc = parse_storage_location(tokens)?;
             SpecExp::Dereference(stloc)
         }
         Tok::Amp => {
+            cover!("UnarySpecExp", "Reference");
             tokens.advance()?;
             let stloc = parse_storage_location(tokens)?;
             SpecExp::Reference(stloc)
         }
         Tok::Exclaim => {
+            cover!("UnarySpecExp", "Not");
             tokens.advance()?;
             let exp = parse_unary_spec_exp(tokens)?;
             SpecExp::Not(Box::new(exp))
         }
+        Tok::If => {
+            // if (c) e1 else e2, where e2 extends as far as possible
+            cover!("UnarySpecExp", "IfElse");
+            tokens.advance()?;
+            consume_token(tokens, Tok::LParen)?;
+            let cond = parse_spec_exp(tokens)?;
+            consume_token(tokens, Tok::RParen)?;
+            let if_exp = parse_spec_exp(tokens)?;
+            consume_token(tokens, Tok::Else)?;
+            let else_exp = parse_spec_exp(tokens)?;
+            SpecExp::IfElse(Box::new(cond), Box::new(if_exp), Box::new(else_exp))
+        }
         Tok::Old => {
+            cover!("UnarySpecExp", "Old");
             tokens.advance()?;
             consume_token(tokens, Tok::LParen)?;
             let exp = parse_spec_exp(tokens)?;
@@ -1470,8 +2351,10 @@ fn parse_unary_spec_exp<'input>(
         Tok::NameValue => {
             let next = tokens.lookahead();
             if next.is_err() || next.unwrap() != Tok::LParen {
+                cover!("UnarySpecExp", "StorageLocation");
                 SpecExp::StorageLocation(parse_storage_location(tokens)?)
             } else {
+                cover!("UnarySpecExp", "Call");
                 let name = parse_name(tokens)?;
                 let mut args = vec![];
                 consume_token(tokens, Tok::LParen)?;
@@ -1484,10 +2367,13 @@ fn parse_unary_spec_exp<'input>(
                     consume_token(tokens, Tok::Comma)?;
                 }
                 consume_token(tokens, Tok::RParen)?;
-                SpecExp::Call(name, args)
+                SpecExp::Call(name.to_string(), args)
             }
         }
-        _ => SpecExp::StorageLocation(parse_storage_location(tokens)?),
+        _ => {
+            cover!("UnarySpecExp", "StorageLocation");
+            SpecExp::StorageLocation(parse_storage_location(tokens)?)
+        }
     })
 }
 
@@ -1517,6 +2403,7 @@ fn parse_rhs_of_spec_exp<'input>(
         }
         if op_token == Tok::EqualEqualGreater {
             // Syntactic sugar: p ==> c ~~~> !p || c
+            cover!("SpecExp", "Implies");
             result = SpecExp::Binop(
                 Box::new(SpecExp::Not(Box::new(result))),
                 BinOp::Or,
@@ -1542,6 +2429,7 @@ fn parse_rhs_of_spec_exp<'input>(
                 Tok::Percent => BinOp::Mod,
                 _ => panic!("Unexpected token that is not a binary operator"),
             };
+            cover!("SpecExp", crate::coverage::binop_name(&op));
             result = SpecExp::Binop(Box::new(result), op, Box::new(rhs))
         }
     }
@@ -1551,6 +2439,7 @@ fn parse_rhs_of_spec_exp<'input>(
 fn parse_spec_exp<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<SpecExp, ParseError<usize, anyhow::Error>> {
+    profile!("SpecExp");
     let lhs = parse_unary_spec_exp(tokens)?;
     parse_rhs_of_spec_exp(tokens, lhs, /* min_prec */ 1)
 }
@@ -1562,22 +2451,27 @@ fn parse_spec_exp<'input>(
 fn parse_spec_condition<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<Condition_, ParseError<usize, anyhow::Error>> {
+    profile!("SpecCondition");
     // Set lexer to read names without trailing punctuation
     tokens.spec_mode = true;
     let retval = Ok(match tokens.peek() {
         Tok::AbortsIf => {
+            cover!("Condition", "AbortsIf");
             tokens.advance()?;
             Condition_::AbortsIf(parse_spec_exp(tokens)?)
         }
         Tok::Ensures => {
+            cover!("Condition", "Ensures");
             tokens.advance()?;
             Condition_::Ensures(parse_spec_exp(tokens)?)
         }
         Tok::Requires => {
+            cover!("Condition", "Requires");
             tokens.advance()?;
             Condition_::Requires(parse_spec_exp(tokens)?)
         }
         Tok::SucceedsIf => {
+            cover!("Condition", "SucceedsIf");
             tokens.advance()?;
             Condition_::SucceedsIf(parse_spec_exp(tokens)?)
         }
@@ -1595,6 +2489,7 @@ fn parse_spec_condition<'input>(
 fn parse_invariant<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<Invariant, ParseError<usize, anyhow::Error>> {
+    profile!("Invariant");
     // Set lexer to read names without trailing punctuation
     tokens.spec_mode = true;
     let start = tokens.start_loc();
@@ -1607,11 +2502,13 @@ fn parse_invariant_<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<Invariant_, ParseError<usize, anyhow::Error>> {
     consume_token(tokens, Tok::Invariant)?;
+    cover!("Invariant", "Invariant");
     let modifier = if tokens.peek() == Tok::LBrace {
+        cover!("Invariant", "Modifier");
         tokens.advance()?;
         let s = parse_name(tokens)?;
         consume_token(tokens, Tok::RBrace)?;
-        s
+        s.to_string()
     } else {
         String::new()
     };
@@ -1625,6 +2522,7 @@ fn parse_invariant_<'input>(
 fn parse_synthetic<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<SyntheticDefinition, ParseError<usize, anyhow::Error>> {
+    profile!("Synthetic");
     // Set lexer to read names without trailing punctuation
     tokens.spec_mode = true;
     let start = tokens.start_loc();
@@ -1636,6 +2534,7 @@ fn parse_synthetic<'input>(
 fn parse_synthetic_<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<SyntheticDefinition_, ParseError<usize, anyhow::Error>> {
+    cover!("Synthetic", "Synthetic");
     consume_token(tokens, Tok::Synthetic)?;
     let name = Identifier::from(parse_field(tokens)?.value.name());
     consume_token(tokens, Tok::Colon)?;
@@ -1644,6 +2543,113 @@ fn parse_synthetic_<'input>(
     Ok(SyntheticDefinition_ { name, type_ })
 }
 
+// Fails at the current token, which starts a specification, if specifications are rejected.
+fn check_specs_allowed<'input>(
+    tokens: &mut Lexer<'input>,
+) -> Result<(), ParseError<usize, anyhow::Error>> {
+    if tokens.options.reject_specs {
+        return Err(ParseError::InvalidToken {
+            location: tokens.start_loc(),
+        });
+    }
+    Ok(())
+}
+
+// Skips a non-empty specification expression, up to one of the tokens in `end_tokens` outside of
+// parentheses, without parsing it. The lexer must be in spec mode.
+fn skip_spec_exp<'input>(
+    tokens: &mut Lexer<'input>,
+    end_tokens: &[Tok],
+) -> Result<(), ParseError<usize, anyhow::Error>> {
+    profile!("SkippedSpec");
+    let mut depth = 0;
+    let mut empty = true;
+    loop {
+        let tok = tokens.peek();
+        if depth == 0 && end_tokens.contains(&tok) {
+            if empty {
+                return Err(ParseError::InvalidToken {
+                    location: tokens.start_loc(),
+                });
+            }
+            return Ok(());
+        }
+        match tok {
+            Tok::LParen => depth += 1,
+            Tok::RParen if depth > 0 => depth -= 1,
+            Tok::EOF | Tok::RParen | Tok::LBrace | Tok::RBrace | Tok::Semicolon => {
+                return Err(ParseError::InvalidToken {
+                    location: tokens.start_loc(),
+                })
+            }
+            _ => (),
+        }
+        empty = false;
+        tokens.advance()?;
+    }
+}
+
+fn skip_spec_condition<'input>(
+    tokens: &mut Lexer<'input>,
+) -> Result<(), ParseError<usize, anyhow::Error>> {
+    tokens.spec_mode = true;
+    let result = tokens.advance().and_then(|()| {
+        skip_spec_exp(
+            tokens,
+            &[
+                Tok::AbortsIf,
+                Tok::Ensures,
+                Tok::Requires,
+                Tok::SucceedsIf,
+                Tok::LBrace,
+                Tok::Semicolon,
+            ],
+        )
+    });
+    tokens.spec_mode = false;
+    result
+}
+
+fn skip_invariants<'input>(
+    tokens: &mut Lexer<'input>,
+) -> Result<(), ParseError<usize, anyhow::Error>> {
+    tokens.spec_mode = true;
+    let result = skip_invariants_(tokens);
+    tokens.spec_mode = false;
+    result
+}
+
+fn skip_invariants_<'input>(
+    tokens: &mut Lexer<'input>,
+) -> Result<(), ParseError<usize, anyhow::Error>> {
+    loop {
+        consume_token(tokens, Tok::Invariant)?;
+        if tokens.peek() == Tok::LBrace {
+            tokens.advance()?;
+            parse_name(tokens)?;
+            consume_token(tokens, Tok::RBrace)?;
+        }
+        skip_spec_exp(tokens, &[Tok::Comma, Tok::RBrace])?;
+        if tokens.peek() == Tok::Comma {
+            tokens.advance()?;
+        }
+        if tokens.peek() == Tok::RBrace {
+            return Ok(());
+        }
+    }
+}
+
+fn skip_synthetic<'input>(
+    tokens: &mut Lexer<'input>,
+) -> Result<(), ParseError<usize, anyhow::Error>> {
+    tokens.spec_mode = true;
+    let result = consume_token(tokens, Tok::Synthetic)
+        .and_then(|()| skip_spec_exp(tokens, &[Tok::Semicolon]))
+        .and_then(|()| consume_token(tokens, Tok::Semicolon));
+    tokens.spec_mode = false;
+    result
+}
+
 // FunctionDecl : (FunctionName, Function_) = {
 //   <f: Sp<MoveFunctionDecl>> => (f.value.0, Spanned { span: f.span, value: f.value.1 }),
 //   <f: Sp<NativeFunctionDecl>> => (f.value.0, Spanned { span: f.span, value: f.value.1 }),
@@ -1666,19 +2672,24 @@ fn parse_synthetic_<'input>(
 fn parse_function_decl<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<(FunctionName, Function), ParseError<usize, anyhow::Error>> {
+    profile!("FunctionDecl");
     let start_loc = tokens.start_loc();
 
     let is_native = if tokens.peek() == Tok::Native {
+        cover!("FunctionDecl", "Native");
         tokens.advance()?;
         true
     } else {
+        cover!("FunctionDecl", "Move");
         false
     };
 
     let is_public = if tokens.peek() == Tok::Public {
+        cover!("FunctionDecl", "Public");
         tokens.advance()?;
         true
     } else {
+        cover!("FunctionDecl", "Internal");
         false
     };
 
@@ -1694,14 +2705,22 @@ fn parse_function_decl<'input>(
     };
 
     let acquires = if tokens.peek() == Tok::Acquires {
+        cover!("FunctionDecl", "Acquires");
         Some(parse_acquire_list(tokens)?)
     } else {
         None
     };
+    let header = make_loc(start_loc, tokens.previous_end_loc());
 
     // parse each specification directive--there may be zero or more
     let mut specifications = Vec::new();
     while tokens.peek().is_spec_directive() {
+        cover!("FunctionDecl", "Specifications");
+        check_specs_allowed(tokens)?;
+        if tokens.options.skip_specs {
+            skip_spec_condition(tokens)?;
+            continue;
+        }
         let start_loc = tokens.start_loc();
         let cond = parse_spec_condition(tokens)?;
         let end_loc = tokens.previous_end_loc();
@@ -1709,7 +2728,7 @@ fn parse_function_decl<'input>(
     }
 
     let func_name = FunctionName::parse(name)?;
-    let func = Function_::new(
+    let mut func = Function_::new(
         if is_public {
             FunctionVisibility::Public
         } else {
@@ -1728,6 +2747,7 @@ fn parse_function_decl<'input>(
             FunctionBody::Move { locals, code: body }
         },
     );
+    func.header = header;
 
     let end_loc = tokens.previous_end_loc();
     Ok((func_name, spanned(start_loc, end_loc, func)))
@@ -1750,16 +2770,19 @@ fn parse_field_decl<'input>(
 //     "modules:" <c: Module*> "script:" => c,
 // }
 
-fn parse_modules<'input>(
+fn parse_modules<'input, F>(
     tokens: &mut Lexer<'input>,
-) -> Result<Vec<ModuleDefinition>, ParseError<usize, anyhow::Error>> {
+    on_module: &mut F,
+) -> Result<(), ParseError<usize, anyhow::Error>>
+where
+    F: FnMut(ModuleDefinition) -> Result<(), anyhow::Error>,
+{
     consume_token(tokens, Tok::Modules)?;
-    let mut c: Vec<ModuleDefinition> = vec![];
     while tokens.peek() == Tok::Module {
-        c.push(parse_module(tokens)?);
+        on_module(parse_module(tokens)?)?;
     }
     consume_token(tokens, Tok::Script)?;
-    Ok(c)
+    Ok(())
 }
 
 // pub Program : Program = {
@@ -1770,8 +2793,25 @@ fn parse_modules<'input>(
 fn parse_program<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<Program, ParseError<usize, anyhow::Error>> {
+    let mut modules = vec![];
+    let script = parse_program_streaming(tokens, &mut |m| {
+        modules.push(m);
+        Ok(())
+    })?;
+    Ok(Program::new(modules, script))
+}
+
+// Parses a program, passing each of its modules to `on_module` as soon as it is parsed, and
+// returns its script. An error returned by `on_module` stops the parsing.
+fn parse_program_streaming<'input, F>(
+    tokens: &mut Lexer<'input>,
+    on_module: &mut F,
+) -> Result<Script, ParseError<usize, anyhow::Error>>
+where
+    F: FnMut(ModuleDefinition) -> Result<(), anyhow::Error>,
+{
     if tokens.peek() == Tok::Module {
-        let m = parse_module(tokens)?;
+        on_module(parse_module(tokens)?)?;
         let ret = Spanned {
             span: Span::default(),
             value: Cmd_::Return(Box::new(Spanned::no_loc(Exp_::ExprList(vec![])))),
@@ -1790,51 +2830,134 @@ fn parse_program<'input>(
             vec![],
             body,
         );
-        Ok(Program::new(
-            vec![m],
-            Script::new(vec![], Spanned::no_loc(main)),
-        ))
+        let mut script = Script::new(vec![], Spanned::no_loc(main));
+        script.language_version = tokens.language_version;
+        Ok(script)
     } else {
-        let modules = if tokens.peek() == Tok::Modules {
-            parse_modules(tokens)?
-        } else {
-            vec![]
-        };
-        let s = parse_script(tokens)?;
-        Ok(Program::new(modules, s))
+        if tokens.peek() == Tok::Modules {
+            parse_modules(tokens, on_module)?;
+        }
+        parse_script(tokens)
+    }
+}
+
+// Parses a program with its modules on worker threads, splitting it where `modules:` is followed by
+// `module` keywords outside of braces. Returns `None` if the program has less than two modules, if
+// it does not parse or if it was not split at the boundaries of its modules, for the caller to
+// parse it sequentially, so that errors are reported as usual.
+#[cfg(feature = "parallel")]
+fn parse_program_in_parallel(input: &str, options: ParserOptions) -> Option<Program> {
+    let mut tokens = Lexer::new(input);
+    tokens.options = options;
+    tokens.advance().ok()?;
+    parse_language_pragma(&mut tokens).ok()?;
+    // The pragma may change the options, which apply to the whole program.
+    let (options, language_version) = (tokens.options, tokens.language_version);
+    if tokens.peek() != Tok::Modules {
+        return None;
     }
+    let starts = top_level_module_starts(input, tokens.start_loc() + tokens.content().len());
+    tokens.advance().ok()?;
+    if starts.len() < 2 || starts[0] != tokens.start_loc() {
+        return None;
+    }
+
+    // Each module is parsed by a lexer of its own, which must stop at the start of the next one.
+    let modules = starts
+        .par_iter()
+        .enumerate()
+        .map(|(i, start)| {
+            let mut tokens = Lexer::starting_at(input, *start);
+            tokens.options = options;
+            tokens.language_version = language_version;
+            tokens.advance().ok()?;
+            let module = parse_module(&mut tokens).ok()?;
+            let at_boundary = match starts.get(i + 1) {
+                Some(next) => tokens.start_loc() == *next,
+                None => tokens.peek() != Tok::Module,
+            };
+            if at_boundary {
+                Some((module, tokens.start_loc()))
+            } else {
+                None
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let script_start = modules.last()?.1;
+    let mut tokens = Lexer::starting_at(input, script_start);
+    tokens.options = options;
+    tokens.language_version = language_version;
+    tokens.advance().ok()?;
+    consume_token(&mut tokens, Tok::Script).ok()?;
+    let script = parse_script(&mut tokens).ok()?;
+    let modules = modules.into_iter().map(|(module, _)| module).collect();
+    Some(Program::new(modules, script))
 }
 
 // pub Script : Script = {
-//     <imports: (ImportDecl)*>
-//     "main" "(" <args: Comma<ArgDecl>> ")" <locals_body: FunctionBlock> => { ... }
+//     <imports: (CfgAttribute? ImportDecl)*>
+//     "main" <type_formals: ("<" <Comma<TypeFormal>> ">")?> "(" <args: Comma<ArgDecl>> ")"
+//     <ret: ReturnType?> <locals_body: FunctionBlock> => { ... }
 // }
 
 fn parse_script<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<Script, ParseError<usize, anyhow::Error>> {
+    profile!("Script");
     let start_loc = tokens.start_loc();
     let mut imports: Vec<ImportDefinition> = vec![];
+    let mut attributes = parse_attributes(tokens)?;
     while tokens.peek() == Tok::Import {
-        imports.push(parse_import_decl(tokens)?);
+        let mut import = parse_import_decl(tokens)?;
+        import.cfg = attributes.into_cfg()?;
+        imports.push(import);
+        attributes = parse_attributes(tokens)?;
+    }
+    if !attributes.is_empty() {
+        // Only the imports of a script can be compiled conditionally, not `main`.
+        return Err(ParseError::InvalidToken {
+            location: tokens.start_loc(),
+        });
     }
+    cover!("Script", "Main");
+    let header_start = tokens.start_loc();
     consume_token(tokens, Tok::Main)?;
+    let type_formals = if tokens.peek() == Tok::Less {
+        cover!("Script", "Generic");
+        tokens.advance()?;
+        let list = parse_comma_list(tokens, &[Tok::Greater], parse_type_formal, true)?;
+        consume_token(tokens, Tok::Greater)?;
+        list
+    } else {
+        vec![]
+    };
     consume_token(tokens, Tok::LParen)?;
     let args = parse_comma_list(tokens, &[Tok::RParen], parse_arg_decl, true)?;
     consume_token(tokens, Tok::RParen)?;
+    let return_type = if tokens.peek() == Tok::Colon {
+        cover!("Script", "Returns");
+        parse_return_type(tokens)?
+    } else {
+        vec![]
+    };
+    let header = make_loc(header_start, tokens.previous_end_loc());
     let (locals, body) = parse_function_block_(tokens)?;
     let end_loc = tokens.previous_end_loc();
-    let main = Function_::new(
+    let mut main = Function_::new(
         FunctionVisibility::Public,
         args,
-        vec![],
-        vec![],
+        return_type,
+        type_formals,
         vec![],
         vec![],
         FunctionBody::Move { locals, code: body },
     );
+    main.header = header;
     let main = spanned(start_loc, end_loc, main);
-    Ok(Script::new(imports, main))
+    let mut script = Script::new(imports, main);
+    script.language_version = tokens.language_version;
+    Ok(script)
 }
 
 // StructKind: bool = {
@@ -1851,6 +2974,7 @@ fn parse_script<'input>(
 fn parse_struct_decl<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<StructDefinition, ParseError<usize, anyhow::Error>> {
+    profile!("StructDecl");
     let start_loc = tokens.start_loc();
 
     let is_native = if tokens.peek() == Tok::Native {
@@ -1861,8 +2985,14 @@ fn parse_struct_decl<'input>(
     };
 
     let is_nominal_resource = match tokens.peek() {
-        Tok::Struct => false,
-        Tok::Resource => true,
+        Tok::Struct => {
+            cover!("StructDecl", "Struct");
+            false
+        }
+        Tok::Resource => {
+            cover!("StructDecl", "Resource");
+            true
+        }
         _ => {
             return Err(ParseError::InvalidToken {
                 location: tokens.start_loc(),
@@ -1874,6 +3004,7 @@ fn parse_struct_decl<'input>(
     let (name, type_formals) = parse_name_and_type_formals(tokens)?;
 
     if is_native {
+        cover!("StructDecl", "Native");
         consume_token(tokens, Tok::Semicolon)?;
         let end_loc = tokens.previous_end_loc();
         return Ok(spanned(
@@ -1890,8 +3021,16 @@ fn parse_struct_decl<'input>(
         parse_field_decl,
         true,
     )?;
+    check_unique_fields(&fields)?;
     let invariants = if tokens.peek() == Tok::Invariant {
-        parse_comma_list(tokens, &[Tok::RBrace], parse_invariant, true)?
+        cover!("StructDecl", "Invariants");
+        check_specs_allowed(tokens)?;
+        if tokens.options.skip_specs {
+            skip_invariants(tokens)?;
+            vec![]
+        } else {
+            parse_comma_list(tokens, &[Tok::RBrace], parse_invariant, true)?
+        }
     } else {
         vec![]
     };
@@ -1932,18 +3071,25 @@ fn parse_module_ident<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<ModuleIdent, ParseError<usize, anyhow::Error>> {
     if tokens.peek() == Tok::AccountAddressValue {
+        cover!("ImportDecl", "Qualified");
         return Ok(ModuleIdent::Qualified(parse_qualified_module_ident(
             tokens,
         )?));
     }
-    let transaction_dot_module = parse_dot_name(tokens)?;
-    let v: Vec<&str> = transaction_dot_module.split('.').collect();
-    assert!(v.len() == 2);
-    let ident: String = v[0].to_string();
+    let start = tokens.start_loc();
+    let (ident, module) = parse_dot_name(tokens)?;
     if ident != "Transaction" {
         panic!("Ident = {} which is not Transaction", ident);
     }
-    let m: ModuleName = ModuleName::parse(v[1])?;
+    if tokens.options.strict {
+        return Err(ParseError::Deprecated {
+            construct: "`Transaction` import",
+            advice: "import the module by its address instead",
+            loc: make_loc(start, tokens.previous_end_loc()),
+        });
+    }
+    let m: ModuleName = ModuleName::parse(module)?;
+    cover!("ImportDecl", "Transaction");
     Ok(ModuleIdent::Transaction(m))
 }
 
@@ -1972,9 +3118,11 @@ fn parse_import_alias<'input>(
 fn parse_import_decl<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<ImportDefinition, ParseError<usize, anyhow::Error>> {
+    profile!("ImportDecl");
     consume_token(tokens, Tok::Import)?;
     let ident = parse_module_ident(tokens)?;
     let alias = if tokens.peek() == Tok::As {
+        cover!("ImportDecl", "Alias");
         Some(parse_import_alias(tokens)?)
     } else {
         None
@@ -1983,11 +3131,234 @@ fn parse_import_decl<'input>(
     Ok(ImportDefinition::new(ident, alias))
 }
 
+// CfgAttribute: CfgPredicate = {
+//     "#" "[" "cfg" "(" <predicate: CfgPredicate> ")" "]" => predicate,
+// }
+//
+// InlineAttribute: InlineHint = {
+//     "#" "[" "inline" "]" => InlineHint::Inline,
+//     "#" "[" "never_inline" "]" => InlineHint::NeverInline,
+// }
+//
+// AnnotateAttribute: Vec<(String, String)> = {
+//     "#" "[" "annotate" "(" <entries: Comma<(<Name> "=" <String>)>> ")" "]" => entries,
+// }
+
+/// The attributes of the next declaration, which are parsed before knowing what it declares.
+#[derive(Default)]
+struct Attributes {
+    cfg: Option<CfgPredicate>,
+    /// The inlining attribute, with its location.
+    inline: Option<(InlineHint, Loc)>,
+    /// The entries of the `annotate` attribute, with the location of the attribute name.
+    annotations: Option<(Vec<(String, String)>, Loc)>,
+}
+
+impl Attributes {
+    fn is_empty(&self) -> bool {
+        self.cfg.is_none() && self.inline.is_none() && self.annotations.is_none()
+    }
+
+    // Returns the `cfg` predicate of a declaration other than a procedure, which cannot have the
+    // other attributes.
+    fn into_cfg(self) -> Result<Option<CfgPredicate>, ParseError<usize, anyhow::Error>> {
+        let (name, loc) = match (self.inline, self.annotations) {
+            (Some((hint, loc)), _) => (hint.to_string(), loc),
+            (None, Some((_, loc))) => ("annotate".to_string(), loc),
+            (None, None) => return Ok(self.cfg),
+        };
+        Err(ParseError::InvalidAttribute {
+            message: format!("Only procedures can have the `{}` attribute", name),
+            loc,
+        })
+    }
+}
+
+// Parses the attributes of the next declaration, in any order.
+fn parse_attributes<'input>(
+    tokens: &mut Lexer<'input>,
+) -> Result<Attributes, ParseError<usize, anyhow::Error>> {
+    let mut attributes = Attributes::default();
+    while tokens.peek() == Tok::NumSign {
+        let start = tokens.start_loc();
+        tokens.advance()?;
+        consume_token(tokens, Tok::LBracket)?;
+        let name_start = tokens.start_loc();
+        let name = match tokens.peek() {
+            Tok::NameValue => tokens.content().to_string(),
+            _ => String::new(),
+        };
+        let name_loc = make_loc(name_start, name_start + tokens.content().len());
+        let duplicate = match name.as_str() {
+            "cfg" => {
+                cover!("CfgAttribute", "Cfg");
+                tokens.advance()?;
+                consume_token(tokens, Tok::LParen)?;
+                let predicate = parse_cfg_predicate(tokens)?;
+                consume_token(tokens, Tok::RParen)?;
+                attributes.cfg.replace(predicate).is_some()
+            }
+            "inline" | "never_inline" => {
+                let hint = if name == "inline" {
+                    cover!("InlineAttribute", "Inline");
+                    InlineHint::Inline
+                } else {
+                    cover!("InlineAttribute", "NeverInline");
+                    InlineHint::NeverInline
+                };
+                tokens.advance()?;
+                attributes.inline.replace((hint, name_loc)).is_some()
+            }
+            "annotate" => {
+                cover!("AnnotateAttribute", "Annotate");
+                tokens.advance()?;
+                consume_token(tokens, Tok::LParen)?;
+                let entries =
+                    parse_comma_list(tokens, &[Tok::RParen], parse_annotation_entry, true)?;
+                consume_token(tokens, Tok::RParen)?;
+                check_unique_names(
+                    "annotation key",
+                    entries.iter().map(|(key, _, loc)| (key.as_str(), *loc)),
+                )?;
+                let entries = entries
+                    .into_iter()
+                    .map(|(key, value, _)| (key, value))
+                    .collect();
+                attributes
+                    .annotations
+                    .replace((entries, name_loc))
+                    .is_some()
+            }
+            _ => {
+                return Err(ParseError::InvalidAttribute {
+                    message: "Unknown attribute, expected `cfg`, `inline`, `never_inline` or \
+                              `annotate`"
+                        .to_string(),
+                    loc: name_loc,
+                });
+            }
+        };
+        consume_token(tokens, Tok::RBracket)?;
+        if duplicate {
+            let message = match name.as_str() {
+                "cfg" => "A declaration can only have one `cfg` attribute",
+                "annotate" => "A procedure can only have one `annotate` attribute",
+                _ => "A procedure can only have one of the `inline` and `never_inline` attributes",
+            };
+            return Err(ParseError::InvalidAttribute {
+                message: message.to_string(),
+                loc: make_loc(start, tokens.previous_end_loc()),
+            });
+        }
+    }
+    Ok(attributes)
+}
+
+// Parses a `key = "value"` entry of an `annotate` attribute, returning the location of the key.
+fn parse_annotation_entry<'input>(
+    tokens: &mut Lexer<'input>,
+) -> Result<(String, String, Loc), ParseError<usize, anyhow::Error>> {
+    let start = tokens.start_loc();
+    if tokens.peek() != Tok::NameValue {
+        return Err(ParseError::InvalidToken { location: start });
+    }
+    let key = tokens.content().to_string();
+    let loc = make_loc(start, start + key.len());
+    tokens.advance()?;
+    consume_token(tokens, Tok::Equal)?;
+    if tokens.peek() != Tok::StringValue {
+        return Err(ParseError::InvalidToken {
+            location: tokens.start_loc(),
+        });
+    }
+    let content = tokens.content();
+    let value = content[1..content.len() - 1].to_string();
+    tokens.advance()?;
+    Ok((key, value, loc))
+}
+
+// CfgPredicate: CfgPredicate = {
+//     <flag: Name> => CfgPredicate::Flag(flag),
+//     "feature" "=" <feature: String> => CfgPredicate::Feature(feature),
+//     "not" "(" <p: CfgPredicate> ")" => CfgPredicate::Not(Box::new(p)),
+//     "all" "(" <ps: Comma<CfgPredicate>> ")" => CfgPredicate::All(ps),
+//     "any" "(" <ps: Comma<CfgPredicate>> ")" => CfgPredicate::Any(ps),
+// }
+
+fn parse_cfg_predicate<'input>(
+    tokens: &mut Lexer<'input>,
+) -> Result<CfgPredicate, ParseError<usize, anyhow::Error>> {
+    let start = tokens.start_loc();
+    if tokens.peek() != Tok::NameValue {
+        return Err(ParseError::InvalidToken { location: start });
+    }
+    let name = tokens.content().to_string();
+    let name_loc = make_loc(start, start + name.len());
+    tokens.advance()?;
+    match tokens.peek() {
+        Tok::Equal => {
+            if name != "feature" {
+                return Err(ParseError::InvalidAttribute {
+                    message: format!("Unknown cfg option {}, expected `feature`", name),
+                    loc: name_loc,
+                });
+            }
+            cover!("CfgPredicate", "Feature");
+            tokens.advance()?;
+            if tokens.peek() != Tok::StringValue {
+                return Err(ParseError::InvalidToken {
+                    location: tokens.start_loc(),
+                });
+            }
+            let content = tokens.content();
+            let feature = content[1..content.len() - 1].to_string();
+            tokens.advance()?;
+            Ok(CfgPredicate::Feature(feature))
+        }
+        Tok::LParen => {
+            tokens.advance()?;
+            let mut predicates =
+                parse_comma_list(tokens, &[Tok::RParen], parse_cfg_predicate, true)?;
+            consume_token(tokens, Tok::RParen)?;
+            match name.as_str() {
+                "all" => {
+                    cover!("CfgPredicate", "All");
+                    Ok(CfgPredicate::All(predicates))
+                }
+                "any" => {
+                    cover!("CfgPredicate", "Any");
+                    Ok(CfgPredicate::Any(predicates))
+                }
+                "not" if predicates.len() == 1 => {
+                    cover!("CfgPredicate", "Not");
+                    Ok(CfgPredicate::Not(Box::new(predicates.pop().unwrap())))
+                }
+                "not" => Err(ParseError::InvalidAttribute {
+                    message: "`not` takes exactly one predicate".to_string(),
+                    loc: make_loc(start, tokens.previous_end_loc()),
+                }),
+                _ => Err(ParseError::InvalidAttribute {
+                    message: format!(
+                        "Unknown cfg predicate {}, expected `all`, `any` or `not`",
+                        name
+                    ),
+                    loc: name_loc,
+                }),
+            }
+        }
+        _ => {
+            cover!("CfgPredicate", "Flag");
+            Ok(CfgPredicate::Flag(name))
+        }
+    }
+}
+
 // pub Module : ModuleDefinition = {
 //     "module" <n: Name> "{"
-//         <imports: (ImportDecl)*>
-//         <structs: (StructDecl)*>
-//         <functions: (FunctionDecl)*>
+//         <imports: (CfgAttribute? ImportDecl)*>
+//         <synthetics: (Synthetic)*>
+//         <structs: (CfgAttribute? StructDecl)*>
+//         <functions: (CfgAttribute? InlineAttribute? AnnotateAttribute? FunctionDecl)*>
 //     "}" =>? ModuleDefinition::new(n, imports, structs, functions),
 // }
 
@@ -2004,34 +3375,127 @@ fn is_struct_decl<'input>(
 fn parse_module<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<ModuleDefinition, ParseError<usize, anyhow::Error>> {
+    profile!("Module");
+    cover!("Module", "Module");
     consume_token(tokens, Tok::Module)?;
     let name = parse_name(tokens)?;
     consume_token(tokens, Tok::LBrace)?;
 
+    let mut attributes = parse_attributes(tokens)?;
+
     let mut imports: Vec<ImportDefinition> = vec![];
+    let mut import_locs = vec![];
     while tokens.peek() == Tok::Import {
-        imports.push(parse_import_decl(tokens)?);
+        let start_loc = tokens.start_loc();
+        let mut import = parse_import_decl(tokens)?;
+        import.cfg = attributes.into_cfg()?;
+        imports.push(import);
+        import_locs.push(make_loc(start_loc, tokens.previous_end_loc()));
+        attributes = parse_attributes(tokens)?;
     }
 
     let mut synthetics = vec![];
-    while tokens.peek() == Tok::Synthetic {
-        synthetics.push(parse_synthetic(tokens)?);
+    while attributes.is_empty() && tokens.peek() == Tok::Synthetic {
+        check_specs_allowed(tokens)?;
+        if tokens.options.skip_specs {
+            skip_synthetic(tokens)?;
+        } else {
+            synthetics.push(parse_synthetic(tokens)?);
+        }
+        attributes = parse_attributes(tokens)?;
     }
 
     let mut structs: Vec<StructDefinition> = vec![];
     while is_struct_decl(tokens)? {
-        structs.push(parse_struct_decl(tokens)?);
+        let mut struct_def = parse_struct_decl(tokens)?;
+        struct_def.value.cfg = attributes.into_cfg()?;
+        structs.push(struct_def);
+        attributes = parse_attributes(tokens)?;
     }
 
     let mut functions: Vec<(FunctionName, Function)> = vec![];
-    while tokens.peek() != Tok::RBrace {
-        functions.push(parse_function_decl(tokens)?);
+    while !attributes.is_empty() || tokens.peek() != Tok::RBrace {
+        let (name, mut function) = parse_function_decl(tokens)?;
+        function.value.cfg = attributes.cfg;
+        function.value.inline = attributes.inline.map(|(hint, _)| hint);
+        function.value.annotations = attributes
+            .annotations
+            .map_or_else(Vec::new, |(entries, _)| entries);
+        functions.push((name, function));
+        attributes = parse_attributes(tokens)?;
     }
     tokens.advance()?; // consume the RBrace
 
-    Ok(ModuleDefinition::new(
-        name, imports, structs, functions, synthetics,
-    )?)
+    // Declarations compiled under different conditions may have the same name, e.g. a function
+    // with a variant for tests. Whether the ones compiled together are distinct is only known
+    // once the conditions are resolved.
+    check_unique_names(
+        "import alias",
+        imports
+            .iter()
+            .zip(import_locs)
+            .filter(|(import, _)| import.cfg.is_none())
+            .map(|(import, loc)| (import.alias.as_inner(), loc)),
+    )?;
+    check_unique_names(
+        "struct",
+        structs
+            .iter()
+            .filter(|s| s.value.cfg.is_none())
+            .map(|s| (s.value.name.as_inner(), s.span)),
+    )?;
+    check_unique_names(
+        "function",
+        functions
+            .iter()
+            .filter(|(_, f)| f.value.cfg.is_none())
+            .map(|(name, f)| (name.as_inner(), f.span)),
+    )?;
+    let mut module = ModuleDefinition::new(name, imports, structs, functions, synthetics)?;
+    module.language_version = tokens.language_version;
+    Ok(module)
+}
+
+// LanguagePragma: () = {
+//     "pragma" "language" <version: U64> ";" => { ... },
+// }
+
+// Parses the language pragma at the start of a source, if it has one, and enables the grammar of
+// the declared version for the rest of the source.
+fn parse_language_pragma<'input>(
+    tokens: &mut Lexer<'input>,
+) -> Result<(), ParseError<usize, anyhow::Error>> {
+    if tokens.peek() != Tok::NameValue || tokens.content() != "pragma" {
+        return Ok(());
+    }
+    cover!("LanguagePragma", "Language");
+    tokens.advance()?;
+    if tokens.peek() != Tok::NameValue || tokens.content() != "language" {
+        return Err(ParseError::InvalidToken {
+            location: tokens.start_loc(),
+        });
+    }
+    tokens.advance()?;
+    if tokens.peek() != Tok::U64Value {
+        return Err(ParseError::InvalidToken {
+            location: tokens.start_loc(),
+        });
+    }
+    let content = tokens.content();
+    let version = u64::from_str(content.trim_end_matches("u64"))
+        .ok()
+        .filter(|version| *version >= 1 && *version <= LATEST_LANGUAGE_VERSION)
+        .ok_or_else(|| ParseError::UnsupportedLanguageVersion {
+            version: content.to_string(),
+            loc: make_loc(tokens.start_loc(), tokens.start_loc() + content.len()),
+        })?;
+    tokens.advance()?;
+    consume_token(tokens, Tok::Semicolon)?;
+    tokens.language_version = Some(version);
+    if version >= 2 {
+        tokens.options.strict = true;
+    }
+    Ok(())
 }
 
 // pub ScriptOrModule: ScriptOrModule = {
@@ -2043,8 +3507,10 @@ fn parse_script_or_module<'input>(
     tokens: &mut Lexer<'input>,
 ) -> Result<ScriptOrModule, ParseError<usize, anyhow::Error>> {
     if tokens.peek() == Tok::Module {
+        cover!("ScriptOrModule", "Module");
         Ok(ScriptOrModule::Module(parse_module(tokens)?))
     } else {
+        cover!("ScriptOrModule", "Script");
         Ok(ScriptOrModule::Script(parse_script(tokens)?))
     }
 }
@@ -2059,25 +3525,79 @@ pub fn parse_cmd_string<'input>(
 
 pub fn parse_module_string<'input>(
     input: &'input str,
+) -> Result<ModuleDefinition, ParseError<usize, anyhow::Error>> {
+    #[cfg(feature = "cache")]
+    {
+        if let Some(module) = crate::cache::get(input) {
+            return Ok(module);
+        }
+    }
+    let module = parse_module_string_with_options(input, ParserOptions::default())?;
+    #[cfg(feature = "cache")]
+    crate::cache::insert(input, &module);
+    Ok(module)
+}
+
+/// Parses a module with the given options. Modules parsed with options are not cached.
+pub fn parse_module_string_with_options<'input>(
+    input: &'input str,
+    options: ParserOptions,
 ) -> Result<ModuleDefinition, ParseError<usize, anyhow::Error>> {
     let mut tokens = Lexer::new(input);
+    tokens.options = options;
     tokens.advance()?;
+    parse_language_pragma(&mut tokens)?;
     parse_module(&mut tokens)
 }
 
 pub fn parse_program_string<'input>(
     input: &'input str,
 ) -> Result<Program, ParseError<usize, anyhow::Error>> {
+    parse_program_string_with_options(input, ParserOptions::default())
+}
+
+/// Parses a program with the given options.
+pub fn parse_program_string_with_options<'input>(
+    input: &'input str,
+    options: ParserOptions,
+) -> Result<Program, ParseError<usize, anyhow::Error>> {
+    #[cfg(feature = "parallel")]
+    {
+        if options.parallel {
+            if let Some(program) = parse_program_in_parallel(input, options) {
+                return Ok(program);
+            }
+        }
+    }
     let mut tokens = Lexer::new(input);
+    tokens.options = options;
     tokens.advance()?;
+    parse_language_pragma(&mut tokens)?;
     parse_program(&mut tokens)
 }
 
+/// Parses a program, passing each of its modules to `on_module` as soon as it is parsed instead of
+/// collecting them, and returns its script. Parsing stops at the first error, including one
+/// returned by `on_module`.
+pub fn parse_program_string_streaming<'input, F>(
+    input: &'input str,
+    mut on_module: F,
+) -> Result<Script, ParseError<usize, anyhow::Error>>
+where
+    F: FnMut(ModuleDefinition) -> Result<(), anyhow::Error>,
+{
+    let mut tokens = Lexer::new(input);
+    tokens.advance()?;
+    parse_language_pragma(&mut tokens)?;
+    parse_program_streaming(&mut tokens, &mut on_module)
+}
+
 pub fn parse_script_string<'input>(
     input: &'input str,
 ) -> Result<Script, ParseError<usize, anyhow::Error>> {
     let mut tokens = Lexer::new(input);
     tokens.advance()?;
+    parse_language_pragma(&mut tokens)?;
     parse_script(&mut tokens)
 }
 
@@ -2086,5 +3606,79 @@ pub fn parse_script_or_module_string<'input>(
 ) -> Result<ScriptOrModule, ParseError<usize, anyhow::Error>> {
     let mut tokens = Lexer::new(input);
     tokens.advance()?;
+    parse_language_pragma(&mut tokens)?;
     parse_script_or_module(&mut tokens)
 }
+
+/// The start of a source, before its modules or its script: its language pragma and, for a program
+/// listing its modules, the `modules:` keyword.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SourceHeader {
+    /// The options to parse the rest of the source with, as changed by the pragma.
+    pub options: ParserOptions,
+    /// The version declared by the pragma.
+    pub language_version: Option<u64>,
+    /// Whether the source lists modules after `modules:`, followed by `script:` and its script.
+    pub lists_modules: bool,
+    /// The offset of the first token after the header.
+    pub end: usize,
+}
+
+/// Parses the header of a source, for parsing the rest of it in parts with `parse_source_part`.
+pub fn parse_source_header(
+    input: &str,
+    options: ParserOptions,
+) -> Result<SourceHeader, ParseError<usize, anyhow::Error>> {
+    let mut tokens = Lexer::new(input);
+    tokens.options = options;
+    tokens.advance()?;
+    parse_language_pragma(&mut tokens)?;
+    let lists_modules = tokens.peek() == Tok::Modules;
+    if lists_modules {
+        tokens.advance()?;
+    }
+    Ok(SourceHeader {
+        options: tokens.options,
+        language_version: tokens.language_version,
+        lists_modules,
+        end: tokens.start_loc(),
+    })
+}
+
+/// Parses a part of a source following `header`. The rest of a source listing its modules is split
+/// before the `module` keywords found by `top_level_module_starts`, so that each part but the last
+/// holds a single module and the last one ends with `script:` and the script. Any other source is a
+/// single part, holding a module or a script. Locations are offsets in `input`, the text of the
+/// part. Returns the modules and the script of the part.
+pub fn parse_source_part(
+    input: &str,
+    header: &SourceHeader,
+    last: bool,
+) -> Result<(Vec<ModuleDefinition>, Option<Script>), ParseError<usize, anyhow::Error>> {
+    let mut tokens = Lexer::new(input);
+    tokens.options = header.options;
+    tokens.language_version = header.language_version;
+    tokens.advance()?;
+    if !header.lists_modules {
+        return Ok(match parse_script_or_module(&mut tokens)? {
+            ScriptOrModule::Module(module) => (vec![module], None),
+            ScriptOrModule::Script(script) => (vec![], Some(script)),
+        });
+    }
+    let mut modules = vec![];
+    if !last {
+        modules.push(parse_module(&mut tokens)?);
+        // The next module should start where the part ends.
+        if tokens.peek() != Tok::EOF {
+            return Err(ParseError::InvalidToken {
+                location: tokens.start_loc(),
+            });
+        }
+        return Ok((modules, None));
+    }
+    while tokens.peek() == Tok::Module {
+        modules.push(parse_module(&mut tokens)?);
+    }
+    consume_token(&mut tokens, Tok::Script)?;
+    Ok((modules, Some(parse_script(&mut tokens)?)))
+}
@@ -0,0 +1,75 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+use compiler::Compiler;
+use ir_to_bytecode::{abi::extract_abi, builders::generate_rust_builders, parser::parse_script};
+use std::{fs, path::PathBuf, process};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "Move IR ABI",
+    about = "Extract the ABI of Move IR transaction scripts as JSON."
+)]
+struct Args {
+    /// Directory the ABIs are written to, one `<script name>.json` file per script, where the
+    /// name of a script is the name of its file
+    #[structopt(short = "o", long = "output-dir", parse(from_os_str))]
+    pub output_dir: PathBuf,
+    /// Also compile the scripts against the standard library and write Rust functions building
+    /// transactions that call them to this file
+    #[structopt(long = "rust", parse(from_os_str))]
+    pub rust_path: Option<PathBuf>,
+    /// Paths to the Move IR transaction scripts
+    #[structopt(parse(from_os_str), required = true)]
+    pub source_paths: Vec<PathBuf>,
+}
+
+fn main() {
+    let args = Args::from_args();
+    fs::create_dir_all(&args.output_dir).expect("Unable to create output directory");
+
+    let mut failed = false;
+    let mut compiled = vec![];
+    for path in &args.source_paths {
+        let source = fs::read_to_string(path).expect("Unable to read file");
+        let script = match parse_script(&source) {
+            Ok(script) => script,
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                failed = true;
+                continue;
+            }
+        };
+        let name = path
+            .file_stem()
+            .expect("Script paths name files")
+            .to_string_lossy();
+        let abi = extract_abi(&name, &source, &script);
+        let output_path = args.output_dir.join(name.as_ref()).with_extension("json");
+        fs::write(output_path, abi.to_json()).expect("Unable to write file");
+        if args.rust_path.is_some() {
+            match Compiler::default().into_script_blob(&source) {
+                Ok(code) => compiled.push((abi, code)),
+                Err(err) => {
+                    eprintln!("{}: {}", path.display(), err);
+                    failed = true;
+                }
+            }
+        }
+    }
+    if let (Some(rust_path), false) = (&args.rust_path, failed) {
+        match generate_rust_builders(&compiled) {
+            Ok(builders) => fs::write(rust_path, builders).expect("Unable to write file"),
+            Err(err) => {
+                eprintln!("{}", err);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,57 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+use ir_to_bytecode::{call_graph::CallGraph, parser::parse_module};
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    process,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "Move IR Call Graph",
+    about = "Print the call graph of Move IR modules in the DOT language."
+)]
+struct Args {
+    /// Print the call graph as JSON instead
+    #[structopt(long = "json")]
+    pub json: bool,
+    /// Paths to the Move IR modules, whose calls to each other are linked
+    #[structopt(parse(from_os_str), required = true)]
+    pub source_paths: Vec<PathBuf>,
+}
+
+fn main() {
+    let args = Args::from_args();
+
+    let mut modules = vec![];
+    let mut failed = false;
+    for path in &args.source_paths {
+        let source = fs::read_to_string(path).expect("Unable to read file");
+        match parse_module(&source) {
+            Ok(module) => modules.push(module),
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+
+    let graph = CallGraph::new(&modules);
+    let output = if args.json {
+        graph.to_json().expect("Unable to serialize call graph")
+    } else {
+        graph.to_dot().into_bytes()
+    };
+    io::stdout()
+        .write_all(&output)
+        .expect("Unable to write call graph");
+}
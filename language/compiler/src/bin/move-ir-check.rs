@@ -0,0 +1,181 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+use ir_to_bytecode::{errors::SyntaxError, parser::try_parse_script_or_module};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::{
+    fs, panic,
+    path::{Path, PathBuf},
+    process,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "Move IR Check",
+    about = "Check the syntax of Move IR scripts and modules."
+)]
+struct Args {
+    /// Print the report as JSON instead of one line per diagnostic
+    #[structopt(long = "json")]
+    pub json: bool,
+    /// Files, directories searched for `.mvir` files, or glob patterns of the sources to check
+    #[structopt(required = true)]
+    pub sources: Vec<String>,
+}
+
+/// A problem found in a file. The line and column are 1-based, and absent when the parser could
+/// not tell where the problem is.
+#[derive(Debug, Serialize)]
+struct Diagnostic {
+    line: Option<usize>,
+    column: Option<usize>,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+struct FileReport {
+    path: PathBuf,
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    files: Vec<FileReport>,
+    checked: usize,
+    failed: usize,
+}
+
+fn collect_mvir_files(dir: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_mvir_files(&path, paths)?;
+        } else if path.extension().map_or(false, |ext| ext == "mvir") {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Expands the command line sources into the sorted list of files to check.
+fn expand_sources(sources: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut paths = vec![];
+    for source in sources {
+        if source.contains(|c| c == '*' || c == '?' || c == '[') {
+            let entries = glob::glob(source).map_err(|err| format!("{}: {}", source, err))?;
+            for entry in entries {
+                let path = entry.map_err(|err| err.to_string())?;
+                if path.is_file() {
+                    paths.push(path);
+                }
+            }
+        } else {
+            let path = PathBuf::from(source);
+            if path.is_dir() {
+                collect_mvir_files(&path, &mut paths)
+                    .map_err(|err| format!("{}: {}", path.display(), err))?;
+            } else {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// Returns the 1-based line and column of the byte at `offset` in `source`.
+fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+fn syntax_diagnostic(source: &str, err: SyntaxError) -> Diagnostic {
+    let position = err
+        .loc
+        .map(|loc| line_and_column(source, loc.start().to_usize()));
+    Diagnostic {
+        line: position.map(|(line, _)| line),
+        column: position.map(|(_, column)| column),
+        message: err.message,
+    }
+}
+
+/// Parses the file at `path`, which holds a single script or module. The parser stops at the
+/// first error, so a file gets at most one diagnostic.
+fn check_file(path: PathBuf) -> FileReport {
+    let diagnostic = match fs::read_to_string(&path) {
+        Err(err) => Some(Diagnostic {
+            line: None,
+            column: None,
+            message: format!("unable to read file: {}", err),
+        }),
+        Ok(source) => match panic::catch_unwind(|| try_parse_script_or_module(&source)) {
+            Ok(Ok(_)) => None,
+            Ok(Err(err)) => Some(syntax_diagnostic(&source, err)),
+            Err(_) => Some(Diagnostic {
+                line: None,
+                column: None,
+                message: "the parser crashed on this file".to_string(),
+            }),
+        },
+    };
+    FileReport {
+        path,
+        diagnostics: diagnostic.into_iter().collect(),
+    }
+}
+
+fn main() {
+    let args = Args::from_args();
+    let paths = match expand_sources(&args.sources) {
+        Ok(paths) => paths,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    };
+
+    // Parser crashes are reported as diagnostics of the file that caused them.
+    panic::set_hook(Box::new(|_| {}));
+    let files: Vec<_> = paths.into_par_iter().map(check_file).collect();
+    let _ = panic::take_hook();
+
+    let report = Report {
+        checked: files.len(),
+        failed: files.iter().filter(|f| !f.diagnostics.is_empty()).count(),
+        files,
+    };
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("Unable to serialize report")
+        );
+    } else {
+        for file in &report.files {
+            for diagnostic in &file.diagnostics {
+                match (diagnostic.line, diagnostic.column) {
+                    (Some(line), Some(column)) => println!(
+                        "{}:{}:{}: {}",
+                        file.path.display(),
+                        line,
+                        column,
+                        diagnostic.message
+                    ),
+                    _ => println!("{}: {}", file.path.display(), diagnostic.message),
+                }
+            }
+        }
+    }
+    if report.failed > 0 {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,25 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+use compiler::daemon::Daemon;
+use std::io;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "Move IR Daemon",
+    about = "Answer JSON-RPC requests to parse, check, compile and format Move IR, one per line \
+             on standard input, keeping the standard library compiled between requests."
+)]
+struct Args {}
+
+fn main() {
+    Args::from_args();
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    Daemon::new()
+        .serve(stdin.lock(), stdout.lock())
+        .expect("Unable to read requests or write responses");
+}
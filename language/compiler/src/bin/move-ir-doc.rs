@@ -0,0 +1,48 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+use ir_to_bytecode::{docgen::render_module, parser::parse_module};
+use std::{fs, path::PathBuf, process};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "Move IR Doc",
+    about = "Generate Markdown reference documentation for Move IR modules."
+)]
+struct Args {
+    /// Directory the documentation is written to, one `<module name>.md` file per module
+    #[structopt(short = "o", long = "output-dir", parse(from_os_str))]
+    pub output_dir: PathBuf,
+    /// Paths to the Move IR modules to document
+    #[structopt(parse(from_os_str), required = true)]
+    pub source_paths: Vec<PathBuf>,
+}
+
+fn main() {
+    let args = Args::from_args();
+    fs::create_dir_all(&args.output_dir).expect("Unable to create output directory");
+
+    let mut failed = false;
+    for path in &args.source_paths {
+        let source = fs::read_to_string(path).expect("Unable to read file");
+        let module = match parse_module(&source) {
+            Ok(module) => module,
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                failed = true;
+                continue;
+            }
+        };
+        let output_path = args
+            .output_dir
+            .join(module.name.as_inner().as_str())
+            .with_extension("md");
+        fs::write(output_path, render_module(&source, &module)).expect("Unable to write file");
+    }
+    if failed {
+        process::exit(1);
+    }
+}
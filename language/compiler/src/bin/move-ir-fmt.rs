@@ -0,0 +1,58 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+use ir_to_bytecode::formatter::{format_source, FormatOptions};
+use std::{fs, path::PathBuf, process};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "Move IR Format",
+    about = "Format Move IR scripts and modules in place."
+)]
+struct Args {
+    /// Report the files that are not formatted instead of rewriting them
+    #[structopt(long = "check")]
+    pub check: bool,
+    /// The width lines are wrapped at
+    #[structopt(long = "max-width", default_value = "100")]
+    pub max_width: usize,
+    /// Paths to the Move IR sources to format
+    #[structopt(parse(from_os_str), required = true)]
+    pub source_paths: Vec<PathBuf>,
+}
+
+fn main() {
+    let args = Args::from_args();
+    let options = FormatOptions {
+        max_width: args.max_width,
+        ..FormatOptions::default()
+    };
+
+    let mut failed = false;
+    for path in &args.source_paths {
+        let source = fs::read_to_string(path).expect("Unable to read file");
+        let formatted = match format_source(&source, &options) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if args.check {
+            println!("{}", path.display());
+            failed = true;
+        } else {
+            fs::write(path, formatted).expect("Unable to write file");
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,31 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+use ir_to_bytecode_syntax::{grammar::to_ebnf, highlighting::to_textmate};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "Move IR Grammar",
+    about = "Print the grammar of Move IR, as EBNF or as an editor highlighting grammar."
+)]
+struct Args {
+    /// The format to print: `ebnf`, or `textmate` for the TextMate grammar of VS Code and most
+    /// editors
+    #[structopt(
+        long = "format",
+        default_value = "ebnf",
+        possible_values = &["ebnf", "textmate"]
+    )]
+    pub format: String,
+}
+
+fn main() {
+    let args = Args::from_args();
+    match args.format.as_str() {
+        "textmate" => print!("{}", to_textmate()),
+        _ => print!("{}", to_ebnf()),
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+use bytecode_source_map::utils::line_and_column;
+use ir_to_bytecode::mutation::mutants;
+use std::{
+    fs,
+    path::PathBuf,
+    process::{self, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "Move IR Mutate",
+    about = "Evaluate a test suite by running it on mutants of a Move IR source, reporting the \
+             mutants it does not kill."
+)]
+struct Args {
+    /// Seconds after which a run of the test command is stopped, killing the mutant
+    #[structopt(long = "timeout", default_value = "60")]
+    pub timeout: u64,
+    /// Path to the Move IR source to mutate, which is rewritten with each mutant in turn
+    #[structopt(parse(from_os_str))]
+    pub source_path: PathBuf,
+    /// Test command, which passes if it exits successfully
+    #[structopt(required = true, last = true)]
+    pub command: Vec<String>,
+}
+
+/// Puts the original source back when dropped, even if the run is interrupted by a panic.
+struct Restore<'a> {
+    path: &'a PathBuf,
+    source: &'a str,
+}
+
+impl Drop for Restore<'_> {
+    fn drop(&mut self) {
+        fs::write(self.path, self.source).expect("Unable to restore file");
+    }
+}
+
+/// Runs the test command, returning whether it passed in time.
+fn run_tests(args: &Args) -> bool {
+    let mut child = Command::new(&args.command[0])
+        .args(&args.command[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Unable to run test command");
+    let deadline = Instant::now() + Duration::from_secs(args.timeout);
+    loop {
+        if let Some(status) = child.try_wait().expect("Unable to wait for test command") {
+            return status.success();
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return false;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn main() {
+    let args = Args::from_args();
+    let source = fs::read_to_string(&args.source_path).expect("Unable to read file");
+    let mutants = mutants(&source).unwrap_or_else(|err| {
+        eprintln!("{}: {}", args.source_path.display(), err);
+        process::exit(2);
+    });
+    if !run_tests(&args) {
+        eprintln!("The test command fails on the original source");
+        process::exit(2);
+    }
+
+    let mut survived = 0;
+    {
+        let _restore = Restore {
+            path: &args.source_path,
+            source: &source,
+        };
+        for mutant in &mutants {
+            fs::write(&args.source_path, &mutant.source).expect("Unable to write file");
+            if run_tests(&args) {
+                let (line, column) = line_and_column(&source, mutant.loc.start());
+                println!(
+                    "{}:{}:{}: mutant survived: {}",
+                    args.source_path.display(),
+                    line,
+                    column,
+                    mutant.description
+                );
+                survived += 1;
+            }
+        }
+    }
+    println!(
+        "{} mutants, {} killed, {} survived",
+        mutants.len(),
+        mutants.len() - survived,
+        survived
+    );
+    if survived > 0 {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,287 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A long-running compiler answering JSON-RPC 2.0 requests, so that build systems and editors do
+//! not pay for starting a process and compiling the standard library on every request.
+//!
+//! Requests and responses are JSON objects, one per line. The methods take a `source` holding a
+//! single script or module, as a `.mvir` file does:
+//!
+//! - `parse` returns `{"kind": "script" | "module"}`.
+//! - `check` returns `{"diagnostics": [...]}`, empty if the source compiles.
+//! - `compile` returns `{"kind": ..., "bytecode": <hex>}`, and takes an optional `address`, the
+//!   sender of a script or the address a module is published at, a `0x` literal or 64 hex digits.
+//!   `check` takes it too.
+//! - `abi` returns the ABI of a script, named after the optional `name`, `main` by default.
+//! - `format` returns `{"source": ...}`, formatted.
+//! - `shutdown` returns `null` and stops the daemon.
+//!
+//! Diagnostics are the objects described in `ir_to_bytecode::diagnostics`. A source that does not
+//! compile fails the request with the `COMPILATION_FAILED` error code and its diagnostics as the
+//! error data. Scripts and modules are compiled against the standard library, which the daemon
+//! compiles once, and responses are cached by request, so asking twice is cheap.
+
+use crate::Compiler;
+use ir_to_bytecode::{
+    abi::extract_abi,
+    diagnostics::{self, Diagnostic},
+    formatter::{format_source, FormatOptions},
+    parser::try_parse_script_or_module,
+    type_checker::{check_module, check_script},
+};
+use libra_types::account_address::AccountAddress;
+use move_ir_types::ast::ScriptOrModule;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+use stdlib::stdlib_modules;
+
+/// The request is not JSON.
+pub const PARSE_ERROR: i64 = -32700;
+/// The request is not a JSON-RPC request.
+pub const INVALID_REQUEST: i64 = -32600;
+/// The method does not exist.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// The parameters are missing or have the wrong type.
+pub const INVALID_PARAMS: i64 = -32602;
+/// The source does not compile.
+pub const COMPILATION_FAILED: i64 = -32000;
+
+/// The number of responses kept in the cache. The cache is cleared when it is full.
+const CACHE_CAPACITY: usize = 1024;
+
+/// The error of a failed request.
+#[derive(Clone, Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    fn compilation_failed(diagnostics: Vec<Diagnostic>) -> Self {
+        RpcError {
+            code: COMPILATION_FAILED,
+            message: "Compilation failed".to_string(),
+            data: Some(json!(diagnostics)),
+        }
+    }
+}
+
+/// A compiler answering JSON-RPC requests. See the [module documentation](index.html).
+#[derive(Debug, Default)]
+pub struct Daemon {
+    cache: HashMap<(String, String), Result<Value, RpcError>>,
+    stopped: bool,
+}
+
+impl Daemon {
+    /// Creates a daemon, compiling the standard library so that the first request does not wait
+    /// for it.
+    pub fn new() -> Self {
+        stdlib_modules();
+        Self::default()
+    }
+
+    /// Whether a `shutdown` request was handled.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Answers requests read from `input`, one per line, writing the responses to `output`, until
+    /// the input ends or a `shutdown` request is handled.
+    pub fn serve(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line) {
+                writeln!(output, "{}", response)?;
+                output.flush()?;
+            }
+            if self.stopped {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answers a request, returning the response, or `None` if the request is a notification,
+    /// which has no `id`.
+    pub fn handle(&mut self, request: &str) -> Option<String> {
+        let request: Value = match serde_json::from_str(request) {
+            Ok(request) => request,
+            Err(err) => {
+                return Some(response(
+                    &Value::Null,
+                    Err(RpcError::new(PARSE_ERROR, err.to_string())),
+                ))
+            }
+        };
+        let id = request.get("id").cloned();
+        let result = match request.get("method").and_then(Value::as_str) {
+            Some(method) if request.get("jsonrpc") == Some(&json!("2.0")) => {
+                let params = request.get("params").cloned().unwrap_or(Value::Null);
+                self.call(method, params)
+            }
+            _ => Err(RpcError::new(INVALID_REQUEST, "Invalid request")),
+        };
+        id.map(|id| response(&id, result))
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        if method == "shutdown" {
+            self.stopped = true;
+            return Ok(Value::Null);
+        }
+        let key = (method.to_string(), params.to_string());
+        if let Some(result) = self.cache.get(&key) {
+            return result.clone();
+        }
+        let result = match method {
+            "parse" => parse(&params),
+            "check" => check(&params),
+            "compile" => compile(&params),
+            "abi" => abi(&params),
+            "format" => format(&params),
+            _ => {
+                return Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!("Unknown method {}", method),
+                ))
+            }
+        };
+        if self.cache.len() >= CACHE_CAPACITY {
+            self.cache.clear();
+        }
+        self.cache.insert(key, result.clone());
+        result
+    }
+}
+
+fn response(id: &Value, result: Result<Value, RpcError>) -> String {
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => {
+            let mut error = json!({ "code": err.code, "message": err.message });
+            if let Some(data) = err.data {
+                error["data"] = data;
+            }
+            json!({ "jsonrpc": "2.0", "id": id, "error": error })
+        }
+    };
+    response.to_string()
+}
+
+fn string_param<'a>(params: &'a Value, name: &str) -> Result<Option<&'a str>, RpcError> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(RpcError::new(
+            INVALID_PARAMS,
+            format!("Parameter {} must be a string", name),
+        )),
+    }
+}
+
+// Parses the `source` parameter, failing with its syntax error.
+fn parse_source(params: &Value) -> Result<(&str, ScriptOrModule), RpcError> {
+    let source = string_param(params, "source")?
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "Missing parameter source"))?;
+    match try_parse_script_or_module(source) {
+        Ok(parsed) => Ok((source, parsed)),
+        Err(err) => Err(RpcError::compilation_failed(vec![
+            Diagnostic::from_syntax_error(source, err),
+        ])),
+    }
+}
+
+fn kind(parsed: &ScriptOrModule) -> &'static str {
+    match parsed {
+        ScriptOrModule::Script(_) => "script",
+        ScriptOrModule::Module(_) => "module",
+    }
+}
+
+fn parse(params: &Value) -> Result<Value, RpcError> {
+    let (_, parsed) = parse_source(params)?;
+    Ok(json!({ "kind": kind(&parsed) }))
+}
+
+fn check(params: &Value) -> Result<Value, RpcError> {
+    match compile(params) {
+        Ok(_) => Ok(json!({ "diagnostics": [] })),
+        Err(RpcError {
+            code: COMPILATION_FAILED,
+            data: Some(diagnostics),
+            ..
+        }) => Ok(json!({ "diagnostics": diagnostics })),
+        Err(err) => Err(err),
+    }
+}
+
+fn compile(params: &Value) -> Result<Value, RpcError> {
+    let address = match string_param(params, "address")? {
+        Some(address) => diagnostics::parse_address(address)
+            .map_err(|err| RpcError::new(INVALID_PARAMS, err.message))?,
+        None => AccountAddress::default(),
+    };
+    let (source, parsed) = parse_source(params)?;
+    // Type errors are located here, as the compiler only reports them as text.
+    let type_errors = match &parsed {
+        ScriptOrModule::Script(script) => check_script(script),
+        ScriptOrModule::Module(module) => check_module(module),
+    };
+    if !type_errors.is_empty() {
+        return Err(RpcError::compilation_failed(
+            type_errors
+                .into_iter()
+                .map(|err| Diagnostic::located(source, err.loc, err.message))
+                .collect(),
+        ));
+    }
+    let compiler = Compiler {
+        address,
+        check_types: true,
+        verify: true,
+        ..Compiler::default()
+    };
+    let compiled = match parsed {
+        ScriptOrModule::Script(_) => compiler.into_script_blob(source),
+        ScriptOrModule::Module(_) => compiler.into_module_blob(source),
+    };
+    match compiled {
+        Ok(bytecode) => Ok(json!({ "kind": kind(&parsed), "bytecode": hex::encode(bytecode) })),
+        Err(err) => Err(RpcError::compilation_failed(vec![Diagnostic::unlocated(
+            format!("{:#}", err),
+        )])),
+    }
+}
+
+fn abi(params: &Value) -> Result<Value, RpcError> {
+    let name = string_param(params, "name")?.unwrap_or("main");
+    match parse_source(params)? {
+        (source, ScriptOrModule::Script(script)) => Ok(json!(extract_abi(name, source, &script))),
+        (_, ScriptOrModule::Module(_)) => {
+            Err(RpcError::new(INVALID_PARAMS, "Only scripts have an ABI"))
+        }
+    }
+}
+
+fn format(params: &Value) -> Result<Value, RpcError> {
+    let (source, _) = parse_source(params)?;
+    let formatted = format_source(source, &FormatOptions::default())
+        .expect("a source that parses can be formatted");
+    Ok(json!({ "source": formatted }))
+}
@@ -0,0 +1,330 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Static gas cost estimation for compiled functions and scripts.
+//!
+//! The estimate walks the control flow of the emitted bytecode and, for every class of
+//! instructions, computes the cheapest and the most expensive path from the entry point to a
+//! `Ret` or `Abort`. The lower bound charges every instruction at the smallest abstract memory
+//! size, while the upper bound charges size-dependent instructions at a caller supplied maximum.
+//!
+//! A few things are deliberately not accounted for:
+//! - the cost of a callee is not included in the cost of a `Call`; only the call itself is charged.
+//! - native functions are not charged, since their cost is reported by the native at runtime.
+//! - any backward branch is treated as a loop, which makes the upper bound unbounded.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap},
+    fmt,
+};
+use vm::{
+    access::{ModuleAccess, ScriptAccess},
+    file_format::{Bytecode, CodeOffset, CompiledModule, CompiledScript},
+    gas_schedule::{instruction_key, AbstractMemorySize, CostTable, GasAlgebra, GasCarrier},
+};
+
+/// Coarse grouping of bytecode instructions used to break down a gas estimate.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum InstructionClass {
+    /// Constants and `Pop`
+    Stack,
+    /// `CopyLoc`, `MoveLoc` and `StLoc`
+    Local,
+    /// Arithmetic, bitwise and cast instructions
+    Arithmetic,
+    /// Comparisons and boolean operators
+    Logic,
+    /// Branches, `Ret` and `Abort`
+    Control,
+    /// Function calls
+    Call,
+    /// Borrowing, reading and writing through references
+    Reference,
+    /// `Pack` and `Unpack`
+    Struct,
+    /// Instructions touching global storage
+    GlobalStorage,
+    /// Instructions reading the transaction metadata
+    Transaction,
+}
+
+impl InstructionClass {
+    /// All of the instruction classes, in the order they are reported.
+    pub const ALL: [InstructionClass; 10] = [
+        InstructionClass::Stack,
+        InstructionClass::Local,
+        InstructionClass::Arithmetic,
+        InstructionClass::Logic,
+        InstructionClass::Control,
+        InstructionClass::Call,
+        InstructionClass::Reference,
+        InstructionClass::Struct,
+        InstructionClass::GlobalStorage,
+        InstructionClass::Transaction,
+    ];
+
+    /// Returns the class the instruction belongs to.
+    pub fn of(instr: &Bytecode) -> Self {
+        use Bytecode::*;
+        match instr {
+            Pop | LdU8(_) | LdU64(_) | LdU128(_) | LdByteArray(_) | LdAddr(_) | LdTrue
+            | LdFalse => InstructionClass::Stack,
+            CopyLoc(_) | MoveLoc(_) | StLoc(_) => InstructionClass::Local,
+            CastU8 | CastU64 | CastU128 | Add | Sub | Mul | Mod | Div | BitOr | BitAnd | Xor
+            | Shl | Shr => InstructionClass::Arithmetic,
+            Or | And | Not | Eq | Neq | Lt | Gt | Le | Ge => InstructionClass::Logic,
+            Ret | BrTrue(_) | BrFalse(_) | Branch(_) | Abort => InstructionClass::Control,
+            Call(_, _) => InstructionClass::Call,
+            ReadRef | WriteRef | FreezeRef | MutBorrowLoc(_) | ImmBorrowLoc(_)
+            | MutBorrowField(_) | ImmBorrowField(_) => InstructionClass::Reference,
+            Pack(_, _) | Unpack(_, _) => InstructionClass::Struct,
+            MutBorrowGlobal(_, _)
+            | ImmBorrowGlobal(_, _)
+            | Exists(_, _)
+            | MoveFrom(_, _)
+            | MoveToSender(_, _) => InstructionClass::GlobalStorage,
+            GetTxnGasUnitPrice | GetTxnMaxGasUnits | GetGasRemaining | GetTxnSenderAddress
+            | GetTxnSequenceNumber | GetTxnPublicKey => InstructionClass::Transaction,
+        }
+    }
+}
+
+impl fmt::Display for InstructionClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            InstructionClass::Stack => "stack",
+            InstructionClass::Local => "local",
+            InstructionClass::Arithmetic => "arithmetic",
+            InstructionClass::Logic => "logic",
+            InstructionClass::Control => "control",
+            InstructionClass::Call => "call",
+            InstructionClass::Reference => "reference",
+            InstructionClass::Struct => "struct",
+            InstructionClass::GlobalStorage => "global storage",
+            InstructionClass::Transaction => "transaction",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Lower and upper bound of the gas consumed by a piece of code.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct GasBounds {
+    /// Gas consumed by the cheapest path through the code.
+    pub lower: GasCarrier,
+    /// Gas consumed by the most expensive path through the code, `None` if the code may loop.
+    pub upper: Option<GasCarrier>,
+}
+
+impl fmt::Display for GasBounds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.upper {
+            Some(upper) => write!(f, "[{}, {}]", self.lower, upper),
+            None => write!(f, "[{}, unbounded)", self.lower),
+        }
+    }
+}
+
+/// Gas estimate for a single function body.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GasEstimate {
+    /// Bounds for the function as a whole.
+    pub total: GasBounds,
+    /// Bounds for each instruction class that appears in the function.
+    pub per_class: BTreeMap<InstructionClass, GasBounds>,
+}
+
+impl fmt::Display for GasEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "total: {}", self.total)?;
+        for (class, bounds) in &self.per_class {
+            writeln!(f, "    {}: {}", class, bounds)?;
+        }
+        Ok(())
+    }
+}
+
+/// Computes `GasEstimate`s for compiled code against a given cost table.
+pub struct GasEstimator<'a> {
+    cost_table: &'a CostTable,
+    max_memory_size: AbstractMemorySize<GasCarrier>,
+}
+
+impl<'a> GasEstimator<'a> {
+    /// Creates an estimator. `max_memory_size` is the abstract memory size used to charge
+    /// size-dependent instructions when computing upper bounds.
+    pub fn new(cost_table: &'a CostTable, max_memory_size: AbstractMemorySize<GasCarrier>) -> Self {
+        Self {
+            cost_table,
+            max_memory_size,
+        }
+    }
+
+    /// Estimates the `main` function of a script.
+    pub fn estimate_script(&self, script: &CompiledScript) -> GasEstimate {
+        self.estimate_code(&script.main().code.code)
+    }
+
+    /// Estimates every function defined in a module, in definition order. Native functions
+    /// are reported with an empty estimate.
+    pub fn estimate_module(&self, module: &CompiledModule) -> Vec<(String, GasEstimate)> {
+        module
+            .function_defs()
+            .iter()
+            .map(|function_def| {
+                let handle = module.function_handle_at(function_def.function);
+                let name = module.identifier_at(handle.name).to_string();
+                (name, self.estimate_code(&function_def.code.code))
+            })
+            .collect()
+    }
+
+    /// Estimates a single function body.
+    pub fn estimate_code(&self, code: &[Bytecode]) -> GasEstimate {
+        let total = self.bounds(code, |_| true);
+        let mut per_class = BTreeMap::new();
+        for class in InstructionClass::ALL.iter() {
+            if code
+                .iter()
+                .any(|instr| InstructionClass::of(instr) == *class)
+            {
+                let bounds = self.bounds(code, |instr| InstructionClass::of(instr) == *class);
+                per_class.insert(*class, bounds);
+            }
+        }
+        GasEstimate { total, per_class }
+    }
+
+    fn bounds<F: Fn(&Bytecode) -> bool>(&self, code: &[Bytecode], counted: F) -> GasBounds {
+        let min_size = AbstractMemorySize::new(1);
+        let lower = cheapest_path(code, |instr| {
+            if counted(instr) {
+                self.instruction_cost(instr, min_size)
+            } else {
+                0
+            }
+        });
+        let upper = most_expensive_path(code, |instr| {
+            if counted(instr) {
+                self.instruction_cost(instr, self.max_memory_size)
+            } else {
+                0
+            }
+        });
+        GasBounds { lower, upper }
+    }
+
+    fn instruction_cost(
+        &self,
+        instr: &Bytecode,
+        size: AbstractMemorySize<GasCarrier>,
+    ) -> GasCarrier {
+        let unit_cost = self
+            .cost_table
+            .instruction_cost(instruction_key(instr))
+            .total()
+            .get();
+        if is_size_dependent(instr) {
+            unit_cost.saturating_mul(size.get())
+        } else {
+            unit_cost
+        }
+    }
+}
+
+// Mirrors the instructions the interpreter charges by abstract memory size rather than as a
+// constant.
+fn is_size_dependent(instr: &Bytecode) -> bool {
+    use Bytecode::*;
+    match instr {
+        CopyLoc(_)
+        | MoveLoc(_)
+        | StLoc(_)
+        | Call(_, _)
+        | Pack(_, _)
+        | Unpack(_, _)
+        | ReadRef
+        | WriteRef
+        | Eq
+        | Neq
+        | LdByteArray(_)
+        | Exists(_, _)
+        | MoveFrom(_, _)
+        | MoveToSender(_, _)
+        | MutBorrowGlobal(_, _)
+        | ImmBorrowGlobal(_, _) => true,
+        _ => false,
+    }
+}
+
+/// Cost of the cheapest path from the entry point to a `Ret` or `Abort`. Code that can never
+/// finish gets a trivial lower bound of zero.
+fn cheapest_path<F: Fn(&Bytecode) -> GasCarrier>(code: &[Bytecode], cost: F) -> GasCarrier {
+    if code.is_empty() {
+        return 0;
+    }
+    let mut best: Vec<Option<GasCarrier>> = vec![None; code.len()];
+    let mut queue = BinaryHeap::new();
+    best[0] = Some(cost(&code[0]));
+    queue.push(Reverse((cost(&code[0]), 0 as CodeOffset)));
+    while let Some(Reverse((dist, pc))) = queue.pop() {
+        if best[pc as usize].map_or(false, |known| known < dist) {
+            continue;
+        }
+        let successors = Bytecode::get_successors(pc, code);
+        if successors.is_empty() {
+            // Dijkstra pops the cheapest node first, so the first exit found is the cheapest.
+            return dist;
+        }
+        for succ in successors {
+            let next = dist.saturating_add(cost(&code[succ as usize]));
+            if best[succ as usize].map_or(true, |known| next < known) {
+                best[succ as usize] = Some(next);
+                queue.push(Reverse((next, succ)));
+            }
+        }
+    }
+    0
+}
+
+/// Cost of the most expensive path from the entry point to a `Ret` or `Abort`, or `None` if a
+/// reachable backward branch makes the path length unbounded.
+fn most_expensive_path<F: Fn(&Bytecode) -> GasCarrier>(
+    code: &[Bytecode],
+    cost: F,
+) -> Option<GasCarrier> {
+    if code.is_empty() {
+        return Some(0);
+    }
+    let mut reachable = vec![false; code.len()];
+    let mut stack = vec![0 as CodeOffset];
+    reachable[0] = true;
+    while let Some(pc) = stack.pop() {
+        for succ in Bytecode::get_successors(pc, code) {
+            if succ <= pc {
+                return None;
+            }
+            if !reachable[succ as usize] {
+                reachable[succ as usize] = true;
+                stack.push(succ);
+            }
+        }
+    }
+
+    // Every reachable edge points forward, so walking the code backwards visits each successor
+    // before its predecessors.
+    let mut worst: Vec<GasCarrier> = vec![0; code.len()];
+    for pc in (0..code.len()).rev() {
+        if !reachable[pc] {
+            continue;
+        }
+        let tail = Bytecode::get_successors(pc as CodeOffset, code)
+            .into_iter()
+            .map(|succ| worst[succ as usize])
+            .max()
+            .unwrap_or(0);
+        worst[pc] = tail.saturating_add(cost(&code[pc]));
+    }
+    Some(worst[0])
+}
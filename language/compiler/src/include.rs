@@ -0,0 +1,201 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Expansion of `include` directives.
+//!
+//! A line consisting of `include "<path>";` is replaced with the content of the file at `<path>`,
+//! relative to the directory of the including file, so that test suites and generated scripts can
+//! share preambles. Included files can include other files, but not themselves, directly or not.
+//!
+//! The expansion keeps track of where each part of the text comes from, so that locations in the
+//! expanded text can be reported in the file they were written in.
+
+use anyhow::{bail, Result};
+use bytecode_source_map::utils::line_and_column;
+use codespan::ByteIndex;
+use ir_to_bytecode::resolver::{FileSystem, SourceResolver};
+use std::path::{Path, PathBuf};
+
+/// The text of a source file after expanding its `include` directives.
+#[derive(Clone, Debug)]
+pub struct ExpandedSource {
+    /// The expanded text, to be parsed.
+    pub text: String,
+    /// The paths and contents of the source file, first, and of every included file.
+    files: Vec<(PathBuf, String)>,
+    /// The parts of the expanded text copied from a file, in order.
+    segments: Vec<Segment>,
+}
+
+/// A part of the expanded text copied verbatim from one of the files.
+#[derive(Clone, Copy, Debug)]
+struct Segment {
+    start: usize,
+    len: usize,
+    file: usize,
+    file_start: usize,
+}
+
+impl ExpandedSource {
+    /// Reads the file at `path` and expands its `include` directives. Fails if a file cannot be
+    /// read, if a directive is malformed or if files include each other in a cycle.
+    pub fn read(path: &Path) -> Result<Self> {
+        Self::read_with(&FileSystem, path)
+    }
+
+    /// Reads the file at `path` and the files it includes from `resolver`, as
+    /// [`read`](#method.read) does from the file system.
+    pub fn read_with(resolver: &dyn SourceResolver, path: &Path) -> Result<Self> {
+        let mut source = ExpandedSource {
+            text: String::new(),
+            files: vec![],
+            segments: vec![],
+        };
+        source.expand(resolver, path, &mut vec![])?;
+        Ok(source)
+    }
+
+    /// Returns the path of the source file.
+    pub fn path(&self) -> &Path {
+        &self.files[0].0
+    }
+
+    /// Returns the file the byte at `index` of the expanded text comes from, with its 1-based line
+    /// and column in that file.
+    pub fn locate(&self, index: ByteIndex) -> (&Path, usize, usize) {
+        let offset = index.to_usize();
+        let segment = self
+            .segments
+            .iter()
+            .rev()
+            .find(|segment| segment.start <= offset && segment.len > 0);
+        let (path, text) = &self.files[segment.map_or(0, |segment| segment.file)];
+        let file_offset = segment.map_or(0, |segment| {
+            segment.file_start + (offset - segment.start).min(segment.len)
+        });
+        let (line, column) = line_and_column(text, ByteIndex(file_offset as u32));
+        (path, line, column)
+    }
+
+    /// Rewrites a line of a report produced against the expanded text, of the form
+    /// `<line>:<column>: <message>` after an optional indentation, so that its position is the one
+    /// in the file the code comes from. Returns that file with the rewritten line, or `None` for
+    /// lines without a position.
+    pub fn relocate_report_line(&self, line: &str) -> Option<(&Path, String)> {
+        let message = line.trim_start();
+        let indent = &line[..line.len() - message.len()];
+        let mut parts = message.splitn(3, ':');
+        let position = match (parts.next(), parts.next(), parts.next()) {
+            (Some(line), Some(column), Some(rest)) => line
+                .parse::<usize>()
+                .ok()
+                .and_then(|line| column.parse::<usize>().ok().map(|column| (line, column)))
+                .map(|position| (position, rest)),
+            _ => None,
+        };
+        position.map(|((line, column), rest)| {
+            let (path, line, column) = self.locate(self.index_of(line, column));
+            (path, format!("{}{}:{}:{}", indent, line, column, rest))
+        })
+    }
+
+    /// Returns the index in the expanded text of a 1-based line and column.
+    fn index_of(&self, line: usize, column: usize) -> ByteIndex {
+        let line_start = if line <= 1 {
+            0
+        } else {
+            self.text
+                .match_indices('\n')
+                .nth(line - 2)
+                .map_or(self.text.len(), |(i, _)| i + 1)
+        };
+        let offset = self.text[line_start..]
+            .char_indices()
+            .nth(column.saturating_sub(1))
+            .map_or(self.text.len(), |(i, _)| line_start + i);
+        ByteIndex(offset as u32)
+    }
+
+    fn expand(
+        &mut self,
+        resolver: &dyn SourceResolver,
+        path: &Path,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        let canonical = resolver.canonicalize(path)?;
+        if let Some(pos) = stack.iter().position(|p| *p == canonical) {
+            let cycle: Vec<_> = stack[pos..]
+                .iter()
+                .chain(std::iter::once(&canonical))
+                .map(|p| p.display().to_string())
+                .collect();
+            bail!("include cycle: {}", cycle.join(" -> "));
+        }
+        let text = resolver.read_file(path)?;
+        let file = self.files.len();
+        self.files.push((path.to_path_buf(), text.clone()));
+        stack.push(canonical);
+
+        let mut copied = 0;
+        let mut line_start = 0;
+        for (line_number, line) in text.split('\n').enumerate() {
+            let directive = parse_directive(line).or_else(|()| {
+                bail!(
+                    "{}:{}: malformed include directive, expected `include \"<path>\";`",
+                    path.display(),
+                    line_number + 1
+                )
+            })?;
+            if let Some(included) = directive {
+                self.copy(file, &text[copied..line_start], copied);
+                let included_path = path
+                    .parent()
+                    .unwrap_or_else(|| Path::new(""))
+                    .join(included);
+                self.expand(resolver, &included_path, stack)?;
+                copied = line_start + line.len();
+            }
+            line_start += line.len() + 1;
+        }
+        self.copy(file, &text[copied..], copied);
+
+        stack.pop();
+        Ok(())
+    }
+
+    fn copy(&mut self, file: usize, text: &str, file_start: usize) {
+        self.segments.push(Segment {
+            start: self.text.len(),
+            len: text.len(),
+            file,
+            file_start,
+        });
+        self.text.push_str(text);
+    }
+}
+
+/// Returns the path of the file included by `line`, or `None` if the line is not an `include`
+/// directive. Fails if it looks like one but is malformed.
+fn parse_directive(line: &str) -> std::result::Result<Option<&str>, ()> {
+    let line = line.trim();
+    if !line.starts_with("include") {
+        return Ok(None);
+    }
+    let rest = &line["include".len()..];
+    if !rest.starts_with(|c: char| c == ' ' || c == '\t') || !rest.trim_start().starts_with('"') {
+        // Not a directive, e.g. an assignment to a variable named `include`.
+        return Ok(None);
+    }
+    let rest = &rest.trim_start()[1..];
+    let end = rest.find('"').ok_or(())?;
+    let after = rest[end + 1..].trim_start();
+    if !after.starts_with(';') {
+        return Err(());
+    }
+    let after = after[1..].trim_start();
+    if after.is_empty() || after.starts_with("//") {
+        Ok(Some(&rest[..end]))
+    } else {
+        Err(())
+    }
+}
@@ -3,24 +3,63 @@
 
 #![forbid(unsafe_code)]
 
+pub mod daemon;
+pub mod gas_estimate;
+pub mod include;
+pub mod snapshot;
 pub mod util;
 
 #[cfg(test)]
 mod unit_tests;
 
-use anyhow::Result;
-use bytecode_source_map::source_map::{ModuleSourceMap, SourceMap};
-use bytecode_verifier::VerifiedModule;
+use anyhow::{bail, format_err, Result};
+use bytecode_source_map::{
+    source_map::{ModuleSourceMap, SourceMap},
+    utils::line_and_column,
+};
+use bytecode_verifier::{
+    verifier::{verify_module_dependencies, verify_script_dependencies},
+    VerifiedModule, VerifiedScript,
+};
 use ir_to_bytecode::{
-    compiler::{compile_module, compile_program},
-    parser::parse_program,
+    acquires::{unused_acquires, UnusedAcquires},
+    cfg::{configure_module, configure_program, CfgOptions},
+    coalesce::{coalesce_module, coalesce_program, CoalescedFunction},
+    compiler::{compile_module_for_target, compile_module_partially, compile_program_for_target},
+    constants::{fold_module, fold_program, FoldedBranch},
+    errors::{
+        ArityError, CfgError, FunctionError, LimitError, NativeError, PlaceholderError,
+        TargetError, TupleError, TypeError,
+    },
+    freeze::{freeze_module, freeze_program, Freeze},
+    inline::{inline_module, inline_program, Inlining},
+    limits::{check_module_limits, check_program_limits},
+    lint::Lint,
+    metadata::{embed_metadata, module_metadata, BuildInfo, Metadata},
+    moves::{resolve_module, resolve_program, InferredMove},
+    natives::check_module_natives,
+    parser::{parse_program_with_options, ParserOptions},
+    partial::stub_failed_functions,
+    placeholders::substitute_placeholders,
+    plugins::Plugins,
+    relocate::{relocate_module, relocate_program, Relocation},
+    rename::{rename_module, RenameMap, RenameScheme},
+    specialize::specialize_script,
+    ssa::{transform_module, transform_program},
+    target::{check_module_target, check_script_target, check_version, LATEST_BYTECODE_VERSION},
+    tuples::{lower_module, lower_program},
+    type_checker::{check_module, check_program},
 };
 use libra_types::{
     account_address::AccountAddress,
+    identifier::Identifier,
     transaction::{Script, TransactionArgument},
 };
-use move_ir_types::ast::Loc;
-use std::mem;
+use move_ir_types::ast::{CopyableVal_, Loc, ModuleDefinition, Program, Type};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, mem,
+};
 use stdlib::stdlib_modules;
 use vm::file_format::{CompiledModule, CompiledProgram, CompiledScript};
 
@@ -35,6 +74,74 @@ pub struct Compiler {
     pub stdlib_address: AccountAddress,
     /// Extra dependencies to compile with.
     pub extra_deps: Vec<VerifiedModule>,
+    /// Run the bytecode verifier on the compiled output, failing with errors located in the IR
+    /// source if verification does not pass. The native functions the modules declare are
+    /// checked against the ones the VM implements before compiling them.
+    pub verify: bool,
+    /// Replace the `acquires` list of every module function with the one inferred from its uses
+    /// of global storage.
+    pub infer_acquires: bool,
+    /// Fail compilation, reporting the difference, if a declared `acquires` list does not match
+    /// the inferred one.
+    pub check_acquires: bool,
+    /// Type check the parsed IR before generating bytecode, failing with errors located in the
+    /// IR source.
+    pub check_types: bool,
+    /// Insert a `freeze` around every `&mut T` the type checker finds given where a `&T` is
+    /// expected, instead of failing to verify. `ir_to_bytecode::freeze` reports where they are.
+    pub auto_freeze: bool,
+    /// Make every bare use of a local, as in `x + 1`, a move or a copy depending on whether the
+    /// local is used afterwards and on its type, instead of failing to compile it.
+    /// `ir_to_bytecode::moves` reports the moves it chose.
+    pub implicit_moves: bool,
+    /// Inline the calls of small private functions into their callers, as `ir_to_bytecode::inline`
+    /// does.
+    pub inline_functions: bool,
+    /// Fold the operators over literals and propagate the locals assigned a constant once, as
+    /// `ir_to_bytecode::constants` does, removing the branches that are never taken.
+    pub fold_constants: bool,
+    /// Convert every function to SSA form and back before generating bytecode, as
+    /// `ir_to_bytecode::ssa` does.
+    pub ssa: bool,
+    /// Let locals whose lifetimes do not overlap share a slot of the frame, as
+    /// `ir_to_bytecode::coalesce` does, to stay under the limit on the number of locals.
+    pub coalesce_locals: bool,
+    /// The values of the `{{name}}` placeholders of the script. Compilation fails if the script
+    /// has a placeholder without a value, or if a value is given for a placeholder it does not
+    /// have.
+    pub placeholder_values: BTreeMap<Identifier, CopyableVal_>,
+    /// The types to specialize the type parameters of a generic script with, in order. When
+    /// empty, a generic script compiles to a generic script, which the transactions running it
+    /// instantiate with their type arguments.
+    pub type_arguments: Vec<Type>,
+    /// The options to parse the IR with.
+    pub parser_options: ParserOptions,
+    /// The flags set and the features enabled for the `cfg` attributes of declarations. The
+    /// declarations whose attribute does not hold are not compiled.
+    pub cfg: CfgOptions,
+    /// The addresses to rewrite in the source, mapped to the addresses to rewrite them to, so
+    /// that a module developed against placeholder addresses such as `0x0` can be retargeted.
+    /// `ir_to_bytecode::relocate` reports the rewritten addresses.
+    pub relocations: BTreeMap<AccountAddress, AccountAddress>,
+    /// Rename the locals, internal functions and fields of every module following the scheme, as
+    /// `ir_to_bytecode::rename` does, once its acquires lists are processed.
+    pub rename: Option<RenameScheme>,
+    /// The keys of the `annotate` attribute entries to embed in the metadata table of compiled
+    /// modules, as `ir_to_bytecode::metadata` does. Entries with other keys are not compiled.
+    pub metadata_keys: BTreeSet<String>,
+    /// Record the language version of the source, the version of the compiler and the options
+    /// enabled in the metadata table of compiled modules, as returned by `build_info`.
+    pub record_build_info: bool,
+    /// The version of the bytecode to compile for, as `ir_to_bytecode::target` describes, the
+    /// latest one if `None`. The code generated for an older version computes what the newer
+    /// instructions would with older ones where it can, and compilation fails, with the
+    /// constructs that need a newer version located in the IR source, if the code does not fit
+    /// in it.
+    pub target_version: Option<u64>,
+    /// The passes supplied by the user, run once the source is parsed and just before bytecode
+    /// is generated, as `ir_to_bytecode::plugins` describes. Compilation fails if one of them
+    /// reports an error; keep a clone to read the warnings they report.
+    pub plugins: Plugins,
 
     // The typical way this should be used is with functional record update syntax:
     //
@@ -48,25 +155,76 @@ pub struct Compiler {
     pub _non_exhaustive: (),
 }
 
+/// What the passes of the compiler report besides errors, for tools to show to the user. The
+/// report of a pass that did not run is empty.
+#[derive(Clone, Debug, Default)]
+pub struct PassReports {
+    /// The addresses rewritten by `relocations`.
+    pub relocations: Vec<Relocation>,
+    /// The bare uses of locals made moves by `implicit_moves`.
+    pub moves: Vec<InferredMove>,
+    /// The `freeze`s inserted by `auto_freeze`.
+    pub freezes: Vec<Freeze>,
+    /// The acquires of the functions that acquire a resource they do not need, unless
+    /// `infer_acquires` replaced the lists.
+    pub unused_acquires: Vec<UnusedAcquires>,
+    /// The names given by `rename`, one map per module, in order.
+    pub renames: Vec<RenameMap>,
+    /// The calls inlined by `inline_functions`.
+    pub inlining: Inlining,
+    /// The branches `fold_constants` found are never taken.
+    pub folded_branches: Vec<FoldedBranch>,
+    /// The functions whose frame `coalesce_locals` shrank.
+    pub coalesced: Vec<CoalescedFunction>,
+    /// The functions compiled as stubs, with their errors, when compiling partially.
+    pub failures: Vec<FunctionError>,
+}
+
+/// A program compiled by `Compiler::compile_parsed_program`.
+#[derive(Clone, Debug)]
+pub struct ProgramOutput {
+    pub program: CompiledProgram,
+    /// The source maps of the modules, in order, then the one of the script.
+    pub source_maps: SourceMap<Loc>,
+    /// The modules the program was compiled and verified against.
+    pub deps: Vec<VerifiedModule>,
+    /// The program once checked and renamed, before the passes rewriting its code: what
+    /// interfaces and bundles describe.
+    pub checked: Program,
+}
+
+/// A module compiled by `Compiler::compile_parsed_module`.
+#[derive(Clone, Debug)]
+pub struct ModuleOutput {
+    pub module: CompiledModule,
+    pub source_map: ModuleSourceMap<Loc>,
+    /// The modules the module was compiled and verified against.
+    pub deps: Vec<VerifiedModule>,
+    /// The module once checked and renamed, before the passes rewriting its code: what
+    /// interfaces and bundles describe.
+    pub checked: ModuleDefinition,
+}
+
 impl Compiler {
     /// Compiles into a `CompiledProgram` where the bytecode hasn't been serialized.
     pub fn into_compiled_program(mut self, code: &str) -> Result<CompiledProgram> {
-        Ok(self.compile_impl(code)?.0)
+        Ok(self.compile_impl(code)?.program)
     }
 
     pub fn into_compiled_program_and_source_maps(
         mut self,
         code: &str,
     ) -> Result<(CompiledProgram, SourceMap<Loc>)> {
-        let (compiled_program, source_maps, _) = self.compile_impl(code)?;
-        Ok((compiled_program, source_maps))
+        let output = self.compile_impl(code)?;
+        Ok((output.program, output.source_maps))
     }
 
     pub fn into_compiled_program_and_source_maps_deps(
         mut self,
         code: &str,
     ) -> Result<(CompiledProgram, SourceMap<Loc>, Vec<VerifiedModule>)> {
-        Ok(self.compile_impl(code)?)
+        let output = self.compile_impl(code)?;
+        Ok((output.program, output.source_maps, output.deps))
     }
 
     /// Compiles into a `CompiledProgram` and also returns the dependencies.
@@ -74,19 +232,19 @@ impl Compiler {
         mut self,
         code: &str,
     ) -> Result<(CompiledProgram, Vec<VerifiedModule>)> {
-        let (compiled_program, _, deps) = self.compile_impl(code)?;
-        Ok((compiled_program, deps))
+        let output = self.compile_impl(code)?;
+        Ok((output.program, output.deps))
     }
 
     /// Compiles into a `CompiledScript`.
     pub fn into_script(mut self, code: &str) -> Result<CompiledScript> {
-        let compiled_program = self.compile_impl(code)?.0;
+        let compiled_program = self.compile_impl(code)?.program;
         Ok(compiled_program.script)
     }
 
     /// Compiles the script into a serialized form.
     pub fn into_script_blob(mut self, code: &str) -> Result<Vec<u8>> {
-        let compiled_program = self.compile_impl(code)?.0;
+        let compiled_program = self.compile_impl(code)?.program;
 
         let mut serialized_script = Vec::<u8>::new();
         compiled_program.script.serialize(&mut serialized_script)?;
@@ -98,6 +256,20 @@ impl Compiler {
         Ok(self.compile_mod(code)?.0)
     }
 
+    /// Compiles the module, replacing the functions that fail to compile with stubs aborting with
+    /// `ir_to_bytecode::partial::FAILED_FUNCTION_ABORT_CODE`, as `ir_to_bytecode::partial`
+    /// describes, so that the errors of every function are reported at once. Returns the module
+    /// and the errors of the failed functions, which `util::function_error_report` formats.
+    /// Fails as `into_compiled_module` does on errors outside of any function.
+    pub fn into_partially_compiled_module(
+        mut self,
+        code: &str,
+    ) -> Result<(CompiledModule, Vec<FunctionError>)> {
+        let mut reports = PassReports::default();
+        let compiled_module = self.compile_mod_impl(code, true, &mut reports)?.module;
+        Ok((compiled_module, reports.failures))
+    }
+
     /// Compiles the module into a serialized form.
     pub fn into_module_blob(mut self, code: &str) -> Result<Vec<u8>> {
         let compiled_module = self.compile_mod(code)?.0;
@@ -112,27 +284,342 @@ impl Compiler {
         Ok(Script::new(self.into_script_blob(code)?, args))
     }
 
-    fn compile_impl(
+    /// Parses the program, resolves its `cfg` attributes, rewrites its addresses and runs the
+    /// post-parse hooks of the plugins: the first steps of compiling it, after which tools can
+    /// analyze the program as it is compiled before passing it to `compile_parsed_program`.
+    pub fn parse_program(&self, code: &str, reports: &mut PassReports) -> Result<Program> {
+        let mut program = parse_program_with_options(code, self.parser_options)?;
+        report_cfg_errors(code, configure_program(&mut program, &self.cfg))?;
+        reports
+            .relocations
+            .extend(relocate_program(&mut program, &self.relocations));
+        report_plugin_errors(code, self.plugins.after_parse_program(&mut program))?;
+        Ok(program)
+    }
+
+    /// Parses the module as `parse_program` parses a program, for `compile_parsed_module`.
+    pub fn parse_module(&self, code: &str, reports: &mut PassReports) -> Result<ModuleDefinition> {
+        let parsed_program = parse_program_with_options(code, self.parser_options)?;
+        let mut modules = parsed_program.modules;
+        assert_eq!(modules.len(), 1, "Must have single module");
+        let mut module = modules.pop().expect("Module must exist");
+        report_cfg_errors(code, configure_module(&mut module, &self.cfg))?;
+        reports
+            .relocations
+            .extend(relocate_module(&mut module, &self.relocations));
+        report_plugin_errors(code, self.plugins.after_parse_module(&mut module))?;
+        Ok(module)
+    }
+
+    /// Runs the rest of the passes over a program returned by `parse_program` for `code`, and
+    /// generates its bytecode. The passes add their reports to `reports`, even if a later one
+    /// fails.
+    pub fn compile_parsed_program(
+        mut self,
+        code: &str,
+        program: Program,
+        reports: &mut PassReports,
+    ) -> Result<ProgramOutput> {
+        self.compile_program_impl(code, program, reports)
+    }
+
+    /// Runs the rest of the passes over a module returned by `parse_module` for `code`, and
+    /// generates its bytecode, as `compile_parsed_program` does.
+    pub fn compile_parsed_module(
+        mut self,
+        code: &str,
+        module: ModuleDefinition,
+        reports: &mut PassReports,
+    ) -> Result<ModuleOutput> {
+        self.compile_module_impl(code, module, false, reports)
+    }
+
+    /// Compiles the module as `compile_parsed_module` does, replacing the functions that fail to
+    /// compile with stubs as `into_partially_compiled_module` does. Their errors are reported in
+    /// `PassReports::failures`.
+    pub fn partially_compile_parsed_module(
+        mut self,
+        code: &str,
+        module: ModuleDefinition,
+        reports: &mut PassReports,
+    ) -> Result<ModuleOutput> {
+        self.compile_module_impl(code, module, true, reports)
+    }
+
+    /// How this compiler builds a source declaring `language_version`: the options recorded are
+    /// the enabled flags, named after the fields, and the `cfg` flags and features.
+    pub fn build_info(&self, language_version: Option<u64>) -> BuildInfo {
+        let flags = [
+            ("verify", self.verify),
+            ("infer_acquires", self.infer_acquires),
+            ("check_acquires", self.check_acquires),
+            ("check_types", self.check_types),
+            ("auto_freeze", self.auto_freeze),
+            ("implicit_moves", self.implicit_moves),
+            ("inline_functions", self.inline_functions),
+            ("fold_constants", self.fold_constants),
+            ("ssa", self.ssa),
+            ("coalesce_locals", self.coalesce_locals),
+            ("strict", self.parser_options.strict),
+        ];
+        let options = flags
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| (*name).to_string())
+            .chain(self.cfg.flags.iter().map(|flag| format!("cfg={}", flag)))
+            .chain(
+                self.cfg
+                    .features
+                    .iter()
+                    .map(|feature| format!("feature={}", feature)),
+            )
+            .chain(
+                self.target_version
+                    .map(|version| format!("target_version={}", version)),
+            );
+        BuildInfo::new(language_version, options)
+    }
+
+    // The metadata to embed in the module compiled from `module`.
+    fn metadata(&self, module: &ModuleDefinition) -> Metadata {
+        let mut metadata = module_metadata(module, &self.metadata_keys);
+        if self.record_build_info {
+            metadata.build = Some(self.build_info(module.language_version));
+        }
+        metadata
+    }
+
+    // The version of the bytecode to generate code for, failing if it is not supported.
+    fn checked_target_version(&self) -> Result<u64> {
+        let version = self.target_version.unwrap_or(LATEST_BYTECODE_VERSION);
+        check_version(version)?;
+        Ok(version)
+    }
+
+    fn compile_impl(&mut self, code: &str) -> Result<ProgramOutput> {
+        let mut reports = PassReports::default();
+        let program = self.parse_program(code, &mut reports)?;
+        self.compile_program_impl(code, program, &mut reports)
+    }
+
+    fn compile_program_impl(
         &mut self,
         code: &str,
-    ) -> Result<(CompiledProgram, SourceMap<Loc>, Vec<VerifiedModule>)> {
-        let parsed_program = parse_program(code)?;
+        mut parsed_program: Program,
+        reports: &mut PassReports,
+    ) -> Result<ProgramOutput> {
+        substitute_placeholders(&mut parsed_program.script, &self.placeholder_values)
+            .map_err(|err| located_placeholder_error(code, err))?;
+        if !self.type_arguments.is_empty() {
+            specialize_script(&mut parsed_program.script, &self.type_arguments)?;
+        }
+        report_tuple_errors(code, lower_program(&mut parsed_program))?;
+        if self.implicit_moves {
+            reports.moves.extend(resolve_program(&mut parsed_program));
+        }
+        if self.auto_freeze {
+            reports.freezes.extend(freeze_program(&mut parsed_program));
+        }
+        if self.check_types {
+            report_type_errors(code, check_program(&parsed_program))?;
+        }
+        for module in &mut parsed_program.modules {
+            self.process_acquires(code, module, reports)?;
+            self.rename(module, reports);
+            if self.verify {
+                report_native_errors(code, check_module_natives(module, self.address))?;
+            }
+        }
+        let checked = parsed_program.clone();
+        if self.inline_functions {
+            reports.inlining.extend(inline_program(&mut parsed_program));
+        }
+        if self.fold_constants {
+            reports
+                .folded_branches
+                .extend(fold_program(&mut parsed_program));
+        }
+        if self.ssa {
+            transform_program(&mut parsed_program, |_| ());
+        }
+        if self.coalesce_locals {
+            reports
+                .coalesced
+                .extend(coalesce_program(&mut parsed_program));
+        }
+        report_plugin_errors(code, self.plugins.before_emit_program(&mut parsed_program))?;
+        report_limit_errors(code, check_program_limits(&parsed_program))?;
+        let metadata: Vec<_> = parsed_program
+            .modules
+            .iter()
+            .map(|module| self.metadata(module))
+            .collect();
         let deps = self.deps();
-        let (compiled_program, source_maps) = compile_program(self.address, parsed_program, &deps)?;
-        Ok((compiled_program, source_maps, deps))
+        let target_version = self.checked_target_version()?;
+        let (mut compiled_program, source_maps) =
+            compile_program_for_target(self.address, parsed_program, &deps, target_version)
+                .map_err(|err| located_compile_error(code, err))?;
+        if let Some(target) = self.target_version {
+            let mut errors = vec![];
+            for (module, source_map) in compiled_program.modules.iter().zip(&source_maps) {
+                errors.extend(check_module_target(module, source_map, target));
+            }
+            let source_map = &source_maps[compiled_program.modules.len()];
+            errors.extend(check_script_target(
+                &compiled_program.script,
+                source_map,
+                target,
+            ));
+            report_target_errors(code, errors)?;
+        }
+        compiled_program.modules = compiled_program
+            .modules
+            .into_iter()
+            .zip(&metadata)
+            .map(|(module, metadata)| embed_metadata(module, metadata))
+            .collect::<Result<_>>()?;
+        if self.verify {
+            verify_program(code, &compiled_program, &source_maps, &deps)?;
+        }
+        Ok(ProgramOutput {
+            program: compiled_program,
+            source_maps,
+            deps,
+            checked,
+        })
     }
 
     fn compile_mod(
         &mut self,
         code: &str,
     ) -> Result<(CompiledModule, ModuleSourceMap<Loc>, Vec<VerifiedModule>)> {
-        let parsed_program = parse_program(code)?;
+        let output = self.compile_mod_impl(code, false, &mut PassReports::default())?;
+        Ok((output.module, output.source_map, output.deps))
+    }
+
+    fn compile_mod_impl(
+        &mut self,
+        code: &str,
+        partial: bool,
+        reports: &mut PassReports,
+    ) -> Result<ModuleOutput> {
+        let module = self.parse_module(code, reports)?;
+        self.compile_module_impl(code, module, partial, reports)
+    }
+
+    // Compiles the module, stubbing the functions that fail to compile and reporting their errors
+    // if `partial`.
+    fn compile_module_impl(
+        &mut self,
+        code: &str,
+        mut module: ModuleDefinition,
+        partial: bool,
+        reports: &mut PassReports,
+    ) -> Result<ModuleOutput> {
         let deps = self.deps();
-        let mut modules = parsed_program.modules;
-        assert_eq!(modules.len(), 1, "Must have single module");
-        let module = modules.pop().expect("Module must exist");
-        let (compiled_module, source_map) = compile_module(self.address, module, &deps)?;
-        Ok((compiled_module, source_map, deps))
+        let errors = lower_module(&mut module);
+        report_tuple_errors(
+            code,
+            tolerate(partial, &mut reports.failures, &mut module, errors, |e| {
+                Some(e.loc)
+            }),
+        )?;
+        if self.implicit_moves {
+            reports.moves.extend(resolve_module(&mut module));
+        }
+        if self.auto_freeze {
+            reports.freezes.extend(freeze_module(&mut module));
+        }
+        if self.check_types {
+            let errors = check_module(&module);
+            report_type_errors(
+                code,
+                tolerate(partial, &mut reports.failures, &mut module, errors, |e| {
+                    Some(e.loc)
+                }),
+            )?;
+        }
+        self.process_acquires(code, &mut module, reports)?;
+        self.rename(&mut module, reports);
+        if self.verify {
+            let errors = check_module_natives(&module, self.address);
+            report_native_errors(
+                code,
+                tolerate(partial, &mut reports.failures, &mut module, errors, |e| {
+                    Some(e.loc)
+                }),
+            )?;
+        }
+        let checked = module.clone();
+        if self.inline_functions {
+            reports.inlining.extend(inline_module(&mut module));
+        }
+        if self.fold_constants {
+            reports.folded_branches.extend(fold_module(&mut module));
+        }
+        if self.ssa {
+            transform_module(&mut module, |_| ());
+        }
+        if self.coalesce_locals {
+            reports.coalesced.extend(coalesce_module(&mut module));
+        }
+        report_plugin_errors(code, self.plugins.before_emit_module(&mut module))?;
+        let errors = check_module_limits(&module);
+        report_limit_errors(
+            code,
+            tolerate(partial, &mut reports.failures, &mut module, errors, |e| {
+                e.loc
+            }),
+        )?;
+        let metadata = self.metadata(&module);
+        let target_version = self.checked_target_version()?;
+        let (compiled_module, source_map) = if partial {
+            let (compiled_module, source_map, failed) =
+                compile_module_partially(self.address, module, &deps, target_version)
+                    .map_err(|err| located_compile_error(code, err))?;
+            reports.failures.extend(failed);
+            (compiled_module, source_map)
+        } else {
+            compile_module_for_target(self.address, module, &deps, target_version)
+                .map_err(|err| located_compile_error(code, err))?
+        };
+        if let Some(target) = self.target_version {
+            report_target_errors(
+                code,
+                check_module_target(&compiled_module, &source_map, target),
+            )?;
+        }
+        let compiled_module = embed_metadata(compiled_module, &metadata)?;
+        if self.verify {
+            verify_module(code, compiled_module.clone(), &source_map, &deps)?;
+        }
+        Ok(ModuleOutput {
+            module: compiled_module,
+            source_map,
+            deps,
+            checked,
+        })
+    }
+
+    // Replaces or checks the acquires lists of the module, and reports the resources it acquires
+    // without needing them unless they were replaced.
+    fn process_acquires(
+        &self,
+        code: &str,
+        module: &mut ModuleDefinition,
+        reports: &mut PassReports,
+    ) -> Result<()> {
+        util::process_acquires(code, module, self.infer_acquires, self.check_acquires)?;
+        if !self.infer_acquires {
+            reports.unused_acquires.extend(unused_acquires(module));
+        }
+        Ok(())
+    }
+
+    fn rename(&self, module: &mut ModuleDefinition, reports: &mut PassReports) {
+        if let Some(scheme) = &self.rename {
+            reports.renames.push(rename_module(module, scheme));
+        }
     }
 
     fn deps(&mut self) -> Vec<VerifiedModule> {
@@ -146,3 +633,160 @@ impl Compiler {
         }
     }
 }
+
+// Stubs the functions of `module` the errors are located in when compiling partially, keeping
+// their errors in `failures`, and returns the errors that cannot be tolerated.
+fn tolerate<E: fmt::Display>(
+    partial: bool,
+    failures: &mut Vec<FunctionError>,
+    module: &mut ModuleDefinition,
+    errors: Vec<E>,
+    loc: impl Fn(&E) -> Option<Loc>,
+) -> Vec<E> {
+    if !partial {
+        return errors;
+    }
+    let (failed, rest) = stub_failed_functions(module, errors, loc);
+    failures.extend(failed);
+    rest
+}
+
+fn report_type_errors(code: &str, errors: Vec<TypeError>) -> Result<()> {
+    if !errors.is_empty() {
+        bail!(
+            "Type checking failed:\n{}",
+            util::type_error_report(code, &errors)
+        )
+    }
+    Ok(())
+}
+
+fn report_cfg_errors(code: &str, errors: Vec<CfgError>) -> Result<()> {
+    if !errors.is_empty() {
+        bail!(
+            "Conditional compilation failed:\n{}",
+            util::cfg_error_report(code, &errors)
+        )
+    }
+    Ok(())
+}
+
+fn report_tuple_errors(code: &str, errors: Vec<TupleError>) -> Result<()> {
+    if !errors.is_empty() {
+        bail!(
+            "Tuple lowering failed:\n{}",
+            util::tuple_error_report(code, &errors)
+        )
+    }
+    Ok(())
+}
+
+fn report_native_errors(code: &str, errors: Vec<NativeError>) -> Result<()> {
+    if !errors.is_empty() {
+        bail!(
+            "Native function checking failed:\n{}",
+            util::native_error_report(code, &errors)
+        )
+    }
+    Ok(())
+}
+
+fn report_limit_errors(code: &str, errors: Vec<LimitError>) -> Result<()> {
+    if !errors.is_empty() {
+        bail!(
+            "Binary format limits exceeded:\n{}",
+            util::limit_error_report(code, &errors)
+        )
+    }
+    Ok(())
+}
+
+fn report_target_errors(code: &str, errors: Vec<TargetError>) -> Result<()> {
+    if !errors.is_empty() {
+        bail!(
+            "Code needs a newer bytecode version than the target one:\n{}",
+            util::target_error_report(code, &errors)
+        )
+    }
+    Ok(())
+}
+
+fn report_plugin_errors(code: &str, errors: Vec<Lint>) -> Result<()> {
+    if !errors.is_empty() {
+        bail!(
+            "Plugin checks failed:\n{}",
+            util::lint_report(code, &errors)
+        )
+    }
+    Ok(())
+}
+
+fn located_placeholder_error(code: &str, err: PlaceholderError) -> anyhow::Error {
+    match &err {
+        PlaceholderError::Missing { loc, .. } => {
+            let (line, column) = line_and_column(code, loc.start());
+            format_err!("{}:{}: {}", line, column, err)
+        }
+        PlaceholderError::Unused { .. } => err.into(),
+    }
+}
+
+// Locates the errors of the compiler that carry a location in the IR source.
+fn located_compile_error(code: &str, err: anyhow::Error) -> anyhow::Error {
+    if let Some(error) = err.downcast_ref::<ArityError>() {
+        return format_err!("{}", util::arity_error_report(code, error));
+    }
+    match err.downcast_ref::<LimitError>() {
+        Some(error) => format_err!("{}", util::limit_error_report(code, &[error.clone()])),
+        None => err,
+    }
+}
+
+fn verify_module(
+    code: &str,
+    module: CompiledModule,
+    source_map: &ModuleSourceMap<Loc>,
+    deps: &[VerifiedModule],
+) -> Result<VerifiedModule> {
+    let errors = match VerifiedModule::new(module) {
+        Ok(verified_module) => {
+            let errors = verify_module_dependencies(&verified_module, deps);
+            if errors.is_empty() {
+                return Ok(verified_module);
+            }
+            errors
+        }
+        Err((_, errors)) => errors,
+    };
+    bail!(
+        "Verification failed:\n{}",
+        util::verification_error_report(code, source_map, &errors)
+    )
+}
+
+fn verify_program(
+    code: &str,
+    program: &CompiledProgram,
+    source_maps: &[ModuleSourceMap<Loc>],
+    deps: &[VerifiedModule],
+) -> Result<()> {
+    // Verify each unit separately, so that errors can be mapped through the matching source map.
+    // The script's source map comes after the modules' ones.
+    let mut deps = deps.to_vec();
+    for (module, source_map) in program.modules.iter().zip(source_maps) {
+        let verified_module = verify_module(code, module.clone(), source_map, &deps)?;
+        deps.push(verified_module);
+    }
+    let source_map = &source_maps[program.modules.len()];
+    let errors = match VerifiedScript::new(program.script.clone()) {
+        Ok(verified_script) => verify_script_dependencies(&verified_script, &deps),
+        Err((_, errors)) => errors,
+    };
+    if !errors.is_empty() {
+        bail!(
+            "Verification failed:\n{}",
+            util::verification_error_report(code, source_map, &errors)
+        )
+    }
+    Ok(())
+}
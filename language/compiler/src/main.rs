@@ -4,28 +4,46 @@
 #![forbid(unsafe_code)]
 
 use anyhow::Context;
-use bytecode_verifier::{
-    verifier::{verify_module_dependencies, VerifiedProgram},
-    VerifiedModule,
+use bytecode_verifier::VerifiedModule;
+use compiler::{
+    gas_estimate::GasEstimator, include::ExpandedSource, util, Compiler, ModuleOutput, PassReports,
+    ProgramOutput,
+};
+use ir_to_bytecode::{
+    borrows::{module_borrows, script_borrows},
+    bundle::{Bundle, BUNDLE_EXT},
+    cfg::CfgOptions,
+    control_flow::{ControlFlowGraphs, GraphFormat},
+    dead_stores::{module_dead_stores, script_dead_stores, DeadStore},
+    dependencies::DependencyManifest,
+    formatter::{format_module, FormatOptions},
+    interface::module_interface,
+    lint::{Lint, Linter, MixedLogicalOperators, Rule, Severity, ShadowedLocals},
+    metrics::Metrics,
+    parser::{ParserOptions, FUTURE_KEYWORDS},
+    relocate::parse_relocation,
+    rename::RenameScheme,
+    taint::{module_taint, script_taint},
+    target::check_version,
 };
-use compiler::{util, Compiler};
-use ir_to_bytecode::parser::{parse_module, parse_script};
 use libra_types::{
     access_path::AccessPath,
     account_address::AccountAddress,
     transaction::{Module, Script},
-    vm_error::VMStatus,
 };
 use serde_json;
 use std::{
+    collections::BTreeMap,
     convert::TryFrom,
     fs,
     io::Write,
     path::{Path, PathBuf},
 };
-use stdlib::stdlib_modules;
 use structopt::StructOpt;
-use vm::file_format::CompiledModule;
+use vm::{
+    file_format::CompiledModule,
+    gas_schedule::{AbstractMemorySize, CostTable, GasAlgebra},
+};
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "IR Compiler", about = "Move IR to bytecode compiler.")]
@@ -54,24 +72,219 @@ struct Args {
 
     #[structopt(long = "src-map")]
     pub output_source_maps: bool,
+    /// Print static gas bounds for every compiled function, using the JSON cost table at this path
+    #[structopt(long = "estimate-gas", parse(from_os_str))]
+    pub gas_schedule_path: Option<PathBuf>,
+    /// Abstract memory size used for size-dependent instructions in gas upper bounds
+    #[structopt(long = "max-memory-size", default_value = "32")]
+    pub max_memory_size: u64,
+    /// Replace the acquires list of every function with the one inferred from its body
+    #[structopt(long = "infer-acquires")]
+    pub infer_acquires: bool,
+    /// Fail if a declared acquires list differs from the inferred one, reporting the difference
+    #[structopt(long = "check-acquires")]
+    pub check_acquires: bool,
+    /// Type check the source before compiling it, reporting errors against the IR
+    #[structopt(long = "type-check")]
+    pub type_check: bool,
+    /// Freeze mutable references given where immutable ones are expected, noting where
+    #[structopt(long = "auto-freeze")]
+    pub auto_freeze: bool,
+    /// Accept bare uses of locals, moving them at their last use and copying them otherwise,
+    /// noting the moves
+    #[structopt(long = "implicit-moves")]
+    pub implicit_moves: bool,
+    /// Inline the calls of small private functions into their callers, noting the inlined calls
+    /// and warning about the functions marked `#[inline]` that cannot be
+    #[structopt(long = "inline")]
+    pub inline: bool,
+    /// Fold operators over literals and locals assigned a constant once, noting the branches that
+    /// are never taken
+    #[structopt(long = "fold-constants")]
+    pub fold_constants: bool,
+    /// Convert every function to static single assignment form and back before generating
+    /// bytecode
+    #[structopt(long = "ssa")]
+    pub ssa: bool,
+    /// Let locals whose lifetimes do not overlap share a slot, noting the functions whose frame
+    /// shrinks
+    #[structopt(long = "coalesce-locals")]
+    pub coalesce_locals: bool,
+    /// Report, for every function of the source, the locals and globals it borrows mutably and
+    /// immutably, the references it passes to the functions it calls, and the references it
+    /// freezes
+    #[structopt(long = "borrows")]
+    pub borrows: bool,
+    /// Warn about transaction arguments moved to the sender's storage, or combined by arithmetic
+    /// with values read from global storage
+    #[structopt(long = "taint")]
+    pub taint: bool,
+    /// Warn about assignments to locals whose value is never read
+    #[structopt(long = "warn-dead-stores")]
+    pub warn_dead_stores: bool,
+    /// Run the lint rules over the source, failing if a denied rule is violated. Shadowed locals
+    /// and mixed logical operators are reported without it
+    #[structopt(long = "lint")]
+    pub lint: bool,
+    /// Do not run the given lint rule
+    #[structopt(long = "allow", number_of_values = 1)]
+    pub allow: Vec<String>,
+    /// Report violations of the given lint rule as warnings
+    #[structopt(long = "warn", number_of_values = 1)]
+    pub warn: Vec<String>,
+    /// Report violations of the given lint rule as errors
+    #[structopt(long = "deny", number_of_values = 1)]
+    pub deny: Vec<String>,
+    /// Also write the interface of the module, declaring its structs and public functions without
+    /// their implementation, to a `.interface.mvir` file to compile with `--no-verify`
+    #[structopt(long = "interface", requires = "module-input")]
+    pub emit_interface: bool,
+    /// Skip the specifications of the source instead of parsing them, as they are not compiled
+    #[structopt(long = "skip-specs")]
+    pub skip_specs: bool,
+    /// Reject bytearray literals longer than this many bytes
+    #[structopt(long = "max-bytearray-len")]
+    pub max_byte_array_len: Option<usize>,
+    /// Reject names reserved for future keywords, such as `vector` or `signer`
+    #[structopt(long = "reserve-keywords")]
+    pub reserve_keywords: bool,
+    /// Reject the constructs slated for removal from the language
+    #[structopt(long = "strict")]
+    pub strict: bool,
+    /// Also write a `.mvb` bundle holding the bytecode, the source map, the ABI of a script and
+    /// the specifications of the source
+    #[structopt(long = "bundle")]
+    pub bundle: bool,
+    /// Compile the declarations whose `cfg` attribute requires the given flag, e.g. `test`
+    #[structopt(long = "cfg", number_of_values = 1)]
+    pub cfg: Vec<String>,
+    /// Compile the declarations whose `cfg` attribute requires the given feature
+    #[structopt(long = "feature", number_of_values = 1)]
+    pub features: Vec<String>,
+    /// Rewrite the given address of the source to another, written `0xold=0xnew`, noting where
+    #[structopt(long = "relocate", number_of_values = 1)]
+    pub relocate: Vec<String>,
+    /// Embed the entries of the `annotate` attributes with the given key in the metadata table
+    /// of the compiled module
+    #[structopt(long = "metadata", number_of_values = 1, requires = "module-input")]
+    pub metadata: Vec<String>,
+    /// Record the language version of the source, the version of the compiler and the options
+    /// given to it in the metadata table of the compiled module. Bundles always record them
+    #[structopt(long = "build-info", requires = "module-input")]
+    pub build_info: bool,
+    /// Also write a `.metrics.json` report of the complexity of every function of the source,
+    /// e.g. its cyclomatic complexity and its number of accesses to global storage
+    #[structopt(long = "metrics")]
+    pub metrics: bool,
+    /// Also write the control-flow graph of every function of the source, as `json` to a
+    /// `.cfg.json` file or as `dot` to a `.cfg.dot` file
+    #[structopt(long = "emit-cfg")]
+    pub emit_cfg: Option<GraphFormat>,
+    /// Rename the locals, internal functions and fields of the module following the scheme,
+    /// `minify`, `obfuscate` or `prefix:<prefix>`, and also write the renamed module to a
+    /// `.renamed.mvir` file, without its specifications, and the names to a `.renames.json` file
+    #[structopt(long = "rename", requires = "module-input")]
+    pub rename: Option<RenameScheme>,
+    /// Also write a `.deps.json` manifest of the modules, structs and functions the compiled
+    /// script or module depends on, and of the language version its source declares
+    #[structopt(long = "dependency-manifest")]
+    pub dependency_manifest: bool,
+    /// Compile the module even if some of its functions fail to compile, replacing them with
+    /// stubs that abort, and report the errors of all of them at once before failing
+    #[structopt(long = "partial", requires = "module-input")]
+    pub partial: bool,
+    /// Compile for an older version of the bytecode, for a network whose VM was not upgraded,
+    /// lowering newer instructions to older ones where it can and failing with the constructs of
+    /// the source that need a newer one. Defaults to the latest
+    #[structopt(long = "target-version")]
+    pub target_version: Option<u64>,
+}
+
+fn read_source(source_path: &Path) -> ExpandedSource {
+    ExpandedSource::read(source_path).unwrap_or_else(|err| {
+        println!("{}", err);
+        std::process::exit(1);
+    })
+}
+
+/// Prints a report produced against the expanded source, each line prefixed with the file its
+/// position is in.
+fn print_report(source: &ExpandedSource, report: &str) {
+    for line in report.lines() {
+        match source.relocate_report_line(line) {
+            Some((path, line)) => println!("{}:{}", path.display(), line),
+            None => println!("{}:{}", source.path().display(), line),
+        }
+    }
 }
 
-fn print_errors_and_exit(verification_errors: &[VMStatus]) -> ! {
-    println!("Verification failed. Errors below:");
-    for e in verification_errors {
-        println!("{:?}", e);
+// Prints an error of the compiler, each line located in the file its position is in, and exits.
+fn exit_with_error(source: &ExpandedSource, err: anyhow::Error) -> ! {
+    for line in err.to_string().lines() {
+        match source.relocate_report_line(line) {
+            Some((path, line)) => println!("{}:{}", path.display(), line),
+            None => println!("{}", line),
+        }
     }
     std::process::exit(1);
 }
 
-fn do_verify_module(module: CompiledModule, dependencies: &[VerifiedModule]) -> VerifiedModule {
-    let verified_module =
-        VerifiedModule::new(module).unwrap_or_else(|(_, errors)| print_errors_and_exit(&errors));
-    let errors = verify_module_dependencies(&verified_module, dependencies);
-    if !errors.is_empty() {
-        print_errors_and_exit(&errors);
+// Prints what the passes of the compiler report, in the order they run.
+fn print_pass_reports(source: &ExpandedSource, reports: &PassReports) {
+    let text = &source.text;
+    print_report(source, &util::moves_report(text, &reports.moves));
+    print_report(source, &util::freeze_report(text, &reports.freezes));
+    print_report(
+        source,
+        &util::unused_acquires_report(text, &reports.unused_acquires),
+    );
+    print_report(source, &util::inlining_report(text, &reports.inlining));
+    print_report(
+        source,
+        &util::folding_report(text, &reports.folded_branches),
+    );
+    print_report(source, &util::coalescing_report(text, &reports.coalesced));
+}
+
+fn print_dead_stores(source: &ExpandedSource, stores: &[DeadStore]) {
+    print_report(source, &util::dead_store_report(&source.text, stores));
+}
+
+fn linter(args: &Args) -> Linter {
+    let mut linter = Linter::default();
+    let levels = [
+        (&args.allow, Severity::Allow),
+        (&args.warn, Severity::Warn),
+        (&args.deny, Severity::Deny),
+    ];
+    for (rules, severity) in levels.iter() {
+        for rule in rules.iter() {
+            linter.set_severity(rule, *severity).unwrap_or_else(|err| {
+                println!("{}", err);
+                std::process::exit(1);
+            });
+        }
+    }
+    linter
+}
+
+// Without `--lint`, only shadowed locals and mixed logical operators are reported: the former
+// usually are a copy/paste mistake, and the compiler rejects them without pointing at either
+// declaration, while the latter compile to something other than what the author may have meant.
+fn enabled_lints(lint: bool, mut lints: Vec<Lint>) -> Vec<Lint> {
+    if !lint {
+        lints.retain(|lint| {
+            lint.rule == ShadowedLocals.name() || lint.rule == MixedLogicalOperators.name()
+        });
+    }
+    lints
+}
+
+fn print_lints_and_check(source: &ExpandedSource, lints: &[Lint]) {
+    print_report(source, &util::lint_report(&source.text, lints));
+    if lints.iter().any(|lint| lint.severity == Severity::Deny) {
+        std::process::exit(1);
     }
-    verified_module
 }
 
 fn write_output(path: &PathBuf, buf: &[u8]) {
@@ -83,17 +296,115 @@ fn write_output(path: &PathBuf, buf: &[u8]) {
         .unwrap();
 }
 
+fn write_metrics(path: &PathBuf, metrics: &Metrics) {
+    write_output(
+        path,
+        &metrics.to_bytes().expect("Unable to serialize metrics"),
+    );
+}
+
+fn write_control_flow_graphs(path: &Path, format: GraphFormat, graphs: &ControlFlowGraphs) {
+    let output = match format {
+        GraphFormat::Json => graphs
+            .to_json()
+            .expect("Unable to serialize control-flow graphs"),
+        GraphFormat::Dot => graphs.to_dot().into_bytes(),
+    };
+    write_output(&path.with_extension(format.extension()), &output);
+}
+
+fn write_bundle(path: &Path, bundle: anyhow::Result<Bundle>) {
+    bundle
+        .and_then(|bundle| bundle.write_to_file(&path.with_extension(BUNDLE_EXT)))
+        .unwrap_or_else(|err| {
+            println!("{:#}", err);
+            std::process::exit(1);
+        });
+}
+
+fn read_cost_table(path: &Path) -> CostTable {
+    let cost_table = fs::read_to_string(path).expect("Unable to read gas schedule file");
+    serde_json::from_str(&cost_table).expect("Unable to parse gas schedule file")
+}
+
+fn print_module_gas_estimate(estimator: &GasEstimator, module: &CompiledModule) {
+    for (name, estimate) in estimator.estimate_module(module) {
+        print!("{}::{} {}", module.self_id().name(), name, estimate);
+    }
+}
+
 fn main() {
     let args = Args::from_args();
+    let linter = linter(&args);
+    let parser_options = ParserOptions {
+        skip_specs: args.skip_specs,
+        max_byte_array_len: args.max_byte_array_len,
+        reserved_names: if args.reserve_keywords {
+            FUTURE_KEYWORDS
+        } else {
+            &[]
+        },
+        strict: args.strict,
+        ..ParserOptions::default()
+    };
+    if let Some(version) = args.target_version {
+        check_version(version).unwrap_or_else(|err| {
+            println!("{}", err);
+            std::process::exit(1);
+        });
+    }
+
+    let relocations = args
+        .relocate
+        .iter()
+        .map(|relocation| parse_relocation(relocation))
+        .collect::<anyhow::Result<BTreeMap<_, _>>>()
+        .unwrap_or_else(|err| {
+            println!("{}", err);
+            std::process::exit(1);
+        });
 
     let address = args
         .address
         .map(|a| AccountAddress::try_from(a).unwrap())
         .unwrap_or_else(AccountAddress::default);
+
+    // The options of the compiler, which runs every pass of the pipeline and records how modules
+    // are built.
+    let options = Compiler {
+        address,
+        verify: !args.no_verify,
+        infer_acquires: args.infer_acquires,
+        check_acquires: args.check_acquires,
+        check_types: args.type_check,
+        auto_freeze: args.auto_freeze,
+        implicit_moves: args.implicit_moves,
+        inline_functions: args.inline,
+        fold_constants: args.fold_constants,
+        ssa: args.ssa,
+        coalesce_locals: args.coalesce_locals,
+        parser_options,
+        cfg: CfgOptions {
+            flags: args.cfg.iter().cloned().collect(),
+            features: args.features.iter().cloned().collect(),
+        },
+        relocations,
+        rename: args.rename.clone(),
+        metadata_keys: args.metadata.iter().cloned().collect(),
+        record_build_info: args.build_info,
+        target_version: args.target_version,
+        ..Compiler::default()
+    };
+
     let source_path = Path::new(&args.source_path);
     let mvir_extension = "mvir";
     let mv_extension = "mv";
     let source_map_extension = "mvsm";
+    let interface_extension = "interface.mvir";
+    let dependency_manifest_extension = "deps.json";
+    let renamed_extension = "renamed.mvir";
+    let metrics_extension = "metrics.json";
+    let rename_map_extension = "renames.json";
     let extension = source_path
         .extension()
         .expect("Missing file extension for input source file");
@@ -105,14 +416,20 @@ fn main() {
         std::process::exit(1);
     }
 
+    let source = read_source(source_path);
+    let mut reports = PassReports::default();
     if args.list_dependencies {
-        let source = fs::read_to_string(args.source_path.clone()).expect("Unable to read file");
         let dependency_list: Vec<AccessPath> = if args.module_input {
-            let module = parse_module(&source).expect("Unable to parse module");
-            module.get_external_deps()
+            options
+                .parse_module(&source.text, &mut reports)
+                .unwrap_or_else(|err| exit_with_error(&source, err))
+                .get_external_deps()
         } else {
-            let script = parse_script(&source).expect("Unable to parse module");
-            script.get_external_deps()
+            options
+                .parse_program(&source.text, &mut reports)
+                .unwrap_or_else(|err| exit_with_error(&source, err))
+                .script
+                .get_external_deps()
         }
         .into_iter()
         .map(|m| AccessPath::code_access_path(&m))
@@ -124,8 +441,9 @@ fn main() {
         return;
     }
 
-    let deps = {
-        if let Some(path) = args.deps_path {
+    // The dependencies listed in a file replace the standard library.
+    let deps: Vec<VerifiedModule> = match &args.deps_path {
+        Some(path) => {
             let deps = fs::read_to_string(path).expect("Unable to read dependency file");
             let deps_list: Vec<Vec<u8>> =
                 serde_json::from_str(deps.as_str()).expect("Unable to parse dependency file");
@@ -139,32 +457,88 @@ fn main() {
                     .expect("Downloaded module blob failed verifier")
                 })
                 .collect()
-        } else if args.no_stdlib {
-            vec![]
-        } else {
-            stdlib_modules().to_vec()
         }
+        None => vec![],
+    };
+    let compiler = Compiler {
+        skip_stdlib_deps: args.no_stdlib || args.deps_path.is_some(),
+        extra_deps: deps,
+        ..options
     };
 
     if !args.module_input {
-        let source = fs::read_to_string(args.source_path.clone()).expect("Unable to read file");
-        let compiler = Compiler {
-            address,
-            skip_stdlib_deps: args.no_stdlib,
-            extra_deps: deps,
-            ..Compiler::default()
-        };
-        let (compiled_program, source_map, dependencies) = compiler
-            .into_compiled_program_and_source_maps_deps(&source)
-            .expect("Failed to compile program");
-
-        let compiled_program = if !args.no_verify {
-            let verified_program = VerifiedProgram::new(compiled_program, &dependencies)
-                .expect("Failed to verify program");
-            verified_program.into_inner()
-        } else {
-            compiled_program
-        };
+        let program = compiler
+            .parse_program(&source.text, &mut reports)
+            .unwrap_or_else(|err| exit_with_error(&source, err));
+        print_report(
+            &source,
+            &util::relocation_report(&source.text, &reports.relocations),
+        );
+        if args.warn_dead_stores {
+            let mut stores = vec![];
+            for module in &program.modules {
+                stores.extend(module_dead_stores(module));
+            }
+            stores.extend(script_dead_stores(&program.script));
+            print_dead_stores(&source, &stores);
+        }
+        let lints = enabled_lints(args.lint, linter.lint_program(&program));
+        print_lints_and_check(&source, &lints);
+        if args.metrics {
+            let mut metrics = Metrics::default();
+            for module in &program.modules {
+                metrics.extend(Metrics::for_module(module));
+            }
+            metrics.extend(Metrics::for_script(&program.script));
+            write_metrics(&source_path.with_extension(metrics_extension), &metrics);
+        }
+        if let Some(format) = args.emit_cfg {
+            let mut graphs = ControlFlowGraphs::default();
+            for module in &program.modules {
+                graphs.extend(ControlFlowGraphs::for_module(module));
+            }
+            graphs.extend(ControlFlowGraphs::for_script(&program.script));
+            write_control_flow_graphs(source_path, format, &graphs);
+        }
+        if args.borrows {
+            let mut functions = vec![];
+            for module in &program.modules {
+                functions.extend(module_borrows(module));
+            }
+            functions.extend(script_borrows(&program.script));
+            print_report(&source, &util::borrow_report(&source.text, &functions));
+        }
+        if args.taint {
+            let mut flows = vec![];
+            for module in &program.modules {
+                flows.extend(module_taint(module));
+            }
+            flows.extend(script_taint(&program.script));
+            print_report(&source, &util::taint_report(&source.text, &flows));
+        }
+        let build = compiler.build_info(program.script.language_version);
+        let compiled = compiler.compile_parsed_program(&source.text, program, &mut reports);
+        print_pass_reports(&source, &reports);
+        let ProgramOutput {
+            program: compiled_program,
+            source_maps: mut source_map,
+            checked,
+            ..
+        } = compiled.unwrap_or_else(|err| exit_with_error(&source, err));
+        let script_ast = checked.script;
+
+        if let Some(path) = &args.gas_schedule_path {
+            let cost_table = read_cost_table(path);
+            let estimator =
+                GasEstimator::new(&cost_table, AbstractMemorySize::new(args.max_memory_size));
+            for module in &compiled_program.modules {
+                print_module_gas_estimate(&estimator, module);
+            }
+            print!(
+                "main {}",
+                estimator.estimate_script(&compiled_program.script)
+            );
+        }
 
         if args.output_source_maps {
             let source_map_bytes = serde_json::to_vec(&source_map)
@@ -183,15 +557,98 @@ fn main() {
         let payload = Script::new(script, vec![]);
         let payload_bytes = serde_json::to_vec(&payload).expect("Unable to serialize program");
         write_output(&source_path.with_extension(mv_extension), &payload_bytes);
+
+        if args.dependency_manifest {
+            let manifest = DependencyManifest::for_script(
+                &compiled_program.script,
+                script_ast.language_version,
+            );
+            write_output(
+                &source_path.with_extension(dependency_manifest_extension),
+                &manifest
+                    .to_bytes()
+                    .expect("Unable to serialize dependency manifest"),
+            );
+        }
+
+        if args.bundle {
+            let name = source_path
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+            // The source map of the script comes after the ones of the modules of the program.
+            let script_source_map = source_map.pop().expect("a program has a script");
+            write_bundle(
+                source_path,
+                Bundle::for_script(
+                    &name,
+                    &source.text,
+                    &script_ast,
+                    &compiled_program.script,
+                    script_source_map,
+                )
+                .map(|bundle| bundle.with_build_info(build)),
+            );
+        }
     } else {
-        let (compiled_module, source_map) =
-            util::do_compile_module(&args.source_path, address, &deps);
-        let compiled_module = if !args.no_verify {
-            let verified_module = do_verify_module(compiled_module, &deps);
-            verified_module.into_inner()
+        let module = compiler
+            .parse_module(&source.text, &mut reports)
+            .unwrap_or_else(|err| exit_with_error(&source, err));
+        print_report(
+            &source,
+            &util::relocation_report(&source.text, &reports.relocations),
+        );
+        if args.warn_dead_stores {
+            print_dead_stores(&source, &module_dead_stores(&module));
+        }
+        let lints = enabled_lints(args.lint, linter.lint_module(&module));
+        print_lints_and_check(&source, &lints);
+        if args.metrics {
+            write_metrics(
+                &source_path.with_extension(metrics_extension),
+                &Metrics::for_module(&module),
+            );
+        }
+        if let Some(format) = args.emit_cfg {
+            write_control_flow_graphs(source_path, format, &ControlFlowGraphs::for_module(&module));
+        }
+        if args.borrows {
+            let functions = module_borrows(&module);
+            print_report(&source, &util::borrow_report(&source.text, &functions));
+        }
+        if args.taint {
+            print_report(
+                &source,
+                &util::taint_report(&source.text, &module_taint(&module)),
+            );
+        }
+        let build = compiler.build_info(module.language_version);
+        let compiled = if args.partial {
+            compiler.partially_compile_parsed_module(&source.text, module, &mut reports)
         } else {
-            compiled_module
+            compiler.compile_parsed_module(&source.text, module, &mut reports)
         };
+        print_pass_reports(&source, &reports);
+        let ModuleOutput {
+            module: compiled_module,
+            source_map,
+            checked,
+            ..
+        } = compiled.unwrap_or_else(|err| exit_with_error(&source, err));
+        let language_version = checked.language_version;
+
+        if args.rename.is_some() {
+            write_output(
+                &source_path.with_extension(renamed_extension),
+                format_module(&checked, &FormatOptions::default()).as_bytes(),
+            );
+        }
+
+        if let Some(path) = &args.gas_schedule_path {
+            let cost_table = read_cost_table(path);
+            let estimator =
+                GasEstimator::new(&cost_table, AbstractMemorySize::new(args.max_memory_size));
+            print_module_gas_estimate(&estimator, &compiled_module);
+        }
 
         if args.output_source_maps {
             let source_map_bytes = serde_json::to_vec(&source_map)
@@ -209,5 +666,46 @@ fn main() {
         let payload = Module::new(module);
         let payload_bytes = serde_json::to_vec(&payload).expect("Unable to serialize program");
         write_output(&source_path.with_extension(mv_extension), &payload_bytes);
+
+        if args.dependency_manifest {
+            let manifest = DependencyManifest::for_module(&compiled_module, language_version);
+            write_output(
+                &source_path.with_extension(dependency_manifest_extension),
+                &manifest
+                    .to_bytes()
+                    .expect("Unable to serialize dependency manifest"),
+            );
+        }
+
+        if args.bundle {
+            write_bundle(
+                source_path,
+                Bundle::for_module(&source.text, &checked, &compiled_module, source_map)
+                    .map(|bundle| bundle.with_build_info(build)),
+            );
+        }
+
+        if let Some(renames) = reports.renames.pop() {
+            write_output(
+                &source_path.with_extension(rename_map_extension),
+                &renames.to_bytes().expect("Unable to serialize renames"),
+            );
+        }
+
+        if args.emit_interface {
+            write_output(
+                &source_path.with_extension(interface_extension),
+                format_module(&module_interface(&checked), &FormatOptions::default()).as_bytes(),
+            );
+        }
+
+        if !reports.failures.is_empty() {
+            println!("Compilation failed in some functions, compiled as stubs. Errors below:");
+            print_report(
+                &source,
+                &util::function_error_report(&source.text, &reports.failures),
+            );
+            std::process::exit(1);
+        }
     }
 }
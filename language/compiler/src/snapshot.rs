@@ -0,0 +1,95 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Snapshot tests of the compiler output.
+//!
+//! A snapshot test compiles an IR snippet and compares what the compiler made of it with a `.exp`
+//! file checked in with the test: the canonical disassembly of `disassembler::canonical`, or any
+//! artifact rendered as JSON, such as a dependency manifest. A change of code generation then
+//! shows up in review as a diff of the snapshots it changes. As for the golden tests of
+//! `ir_to_bytecode::golden`, setting the `UPDATE_BASELINE` environment variable writes the
+//! snapshots with the current output instead of comparing them.
+//!
+//! A snapshot is named by a path without extension, such as `snapshots/pack`, whose `.exp` file
+//! holds the expected output.
+
+use crate::Compiler;
+use anyhow::{Context, Result};
+use disassembler::canonical::{canonical_module, canonical_script};
+use ir_to_bytecode::{
+    golden::{update_baseline_requested, Mismatch, EXP_EXT, UPDATE_BASELINE},
+    parser::try_parse_script_or_module,
+};
+use move_ir_types::ast::ScriptOrModule;
+use serde::Serialize;
+use std::{fs, path::Path};
+
+/// Compiles `source`, a module or a program, and returns the canonical disassembly of the
+/// modules it declares followed by the one of its script, separated by blank lines.
+pub fn disassemble(compiler: Compiler, source: &str) -> Result<String> {
+    if let Ok(ScriptOrModule::Module(_)) = try_parse_script_or_module(source) {
+        return Ok(canonical_module(&compiler.into_compiled_module(source)?));
+    }
+    let program = compiler.into_compiled_program(source)?;
+    let mut dumps: Vec<_> = program.modules.iter().map(canonical_module).collect();
+    dumps.push(canonical_script(&program.script));
+    Ok(dumps.join("\n"))
+}
+
+/// Renders `value` as pretty-printed JSON ending with a newline, so that its snapshot diffs line
+/// by line.
+pub fn to_json(value: &impl Serialize) -> Result<String> {
+    let mut json = serde_json::to_string_pretty(value)?;
+    json.push('\n');
+    Ok(json)
+}
+
+/// Compares `actual` with the snapshot `path`, returning the mismatch if they differ. With
+/// `update`, the snapshot is written with `actual` instead, and there is no mismatch.
+pub fn check_snapshot(path: &Path, actual: &str, update: bool) -> Result<Option<Mismatch>> {
+    let exp_path = path.with_extension(EXP_EXT);
+    if update {
+        if let Some(dir) = exp_path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Unable to create {}", dir.display()))?;
+        }
+        fs::write(&exp_path, actual)
+            .with_context(|| format!("Unable to write {}", exp_path.display()))?;
+        return Ok(None);
+    }
+    let expected = fs::read_to_string(&exp_path).ok();
+    if expected.as_deref() == Some(actual) {
+        return Ok(None);
+    }
+    Ok(Some(Mismatch {
+        path: path.to_path_buf(),
+        expected,
+        actual: actual.to_string(),
+    }))
+}
+
+/// Panics if `actual` differs from the snapshot `path`, unless `UPDATE_BASELINE` is set, in
+/// which case the snapshot is written with it.
+pub fn assert_snapshot(path: &Path, actual: &str) {
+    match check_snapshot(path, actual, update_baseline_requested()) {
+        Ok(None) => (),
+        Ok(Some(mismatch)) => panic!(
+            "{}\nRun with {}=1 to update the snapshot",
+            mismatch, UPDATE_BASELINE
+        ),
+        Err(err) => panic!("{:#}", err),
+    }
+}
+
+/// Compiles `source` with `compiler` and checks its canonical disassembly against the snapshot
+/// `path`, as `assert_snapshot` does.
+pub fn assert_disassembly_snapshot(path: &Path, compiler: Compiler, source: &str) {
+    let actual = disassemble(compiler, source).unwrap_or_else(|err| panic!("{:#}", err));
+    assert_snapshot(path, &actual);
+}
+
+/// Checks `value`, rendered as JSON, against the snapshot `path`, as `assert_snapshot` does.
+pub fn assert_json_snapshot(path: &Path, value: &impl Serialize) {
+    let actual = to_json(value).unwrap_or_else(|err| panic!("{:#}", err));
+    assert_snapshot(path, &actual);
+}
@@ -0,0 +1,75 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::abi::{script_abi, ArgumentAbi, ScriptAbi, TypeAbi};
+use stdlib::transaction_scripts;
+
+#[test]
+fn extracts_arguments_and_docs() {
+    let source = "import 0x0.LibraAccount;
+import 0x0.LibraCoin as Coin;
+
+// Pays a coin.
+// The sender must have enough funds.
+main(
+    payee: address, // Who gets paid.
+    // The coin to pay.
+    coin: Coin.T,
+    metadata: bytearray
+) {
+    LibraAccount.deposit(move(payee), move(coin));
+    return;
+}
+";
+    let abi = script_abi("pay", source).unwrap();
+    let address = "0x0000000000000000000000000000000000000000000000000000000000000000";
+    assert_eq!(
+        abi,
+        ScriptAbi {
+            name: "pay".to_string(),
+            doc: "Pays a coin.\nThe sender must have enough funds.".to_string(),
+            type_arguments: vec![],
+            arguments: vec![
+                ArgumentAbi {
+                    name: "payee".to_string(),
+                    type_: TypeAbi::Address,
+                    doc: "Who gets paid.".to_string(),
+                },
+                ArgumentAbi {
+                    name: "coin".to_string(),
+                    type_: TypeAbi::Struct {
+                        address: Some(address.to_string()),
+                        module: "LibraCoin".to_string(),
+                        name: "T".to_string(),
+                        type_arguments: vec![],
+                    },
+                    doc: "The coin to pay.".to_string(),
+                },
+                ArgumentAbi {
+                    name: "metadata".to_string(),
+                    type_: TypeAbi::ByteArray,
+                    doc: String::new(),
+                },
+            ],
+        }
+    );
+}
+
+#[test]
+fn serializes_to_json() {
+    let abi = script_abi("peer_to_peer", transaction_scripts::peer_to_peer()).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&abi.to_json()).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "name": "peer_to_peer",
+            "doc": "",
+            "type_arguments": [],
+            "arguments": [
+                { "name": "payee", "type": "address", "doc": "" },
+                { "name": "amount", "type": "u64", "doc": "" },
+            ],
+        })
+    );
+    assert_eq!(ScriptAbi::from_json(&abi.to_json()).unwrap(), abi);
+}
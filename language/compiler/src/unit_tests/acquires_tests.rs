@@ -0,0 +1,382 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{util::unused_acquires_report, Compiler};
+use ir_to_bytecode::{
+    acquires::{
+        acquires_diff, acquisition_chain, fill_acquires, infer_acquires, infer_acquisitions,
+        resolve_acquires, unused_acquires, AcquiredBy,
+    },
+    compiler::compile_module,
+    parser::parse_module,
+};
+use libra_types::account_address::AccountAddress;
+use move_ir_types::ast::{FunctionName, StructName};
+use vm::file_format::CompiledModule;
+
+fn struct_names(names: &[&str]) -> Vec<StructName> {
+    names
+        .iter()
+        .map(|name| StructName::parse(*name).unwrap())
+        .collect()
+}
+
+fn acquires_of(code: &str, function: &str) -> Vec<StructName> {
+    let module = parse_module(code).unwrap();
+    infer_acquires(&module)[&FunctionName::parse(function).unwrap()]
+        .iter()
+        .cloned()
+        .collect()
+}
+
+const MODULE: &str = "
+module M {
+    resource A { x: u64 }
+    resource B { y: u64 }
+
+    read_a(addr: address): u64 {
+        return *&(borrow_global<A>(move(addr))).x;
+    }
+
+    take_b(addr: address): u64 {
+        let y: u64;
+        B { y } = move_from<B>(move(addr));
+        return move(y);
+    }
+
+    both(addr: address): u64 {
+        return Self.read_a(copy(addr)) + Self.take_b(move(addr));
+    }
+
+    countdown(n: u64, addr: address): u64 {
+        if (copy(n) == 0) {
+            return Self.read_a(move(addr));
+        }
+        return Self.countdown(move(n) - 1, move(addr));
+    }
+
+    pure(): u64 {
+        return 0;
+    }
+}
+";
+
+#[test]
+fn infer_direct_uses() {
+    assert_eq!(acquires_of(MODULE, "read_a"), struct_names(&["A"]));
+    assert_eq!(acquires_of(MODULE, "take_b"), struct_names(&["B"]));
+    assert!(acquires_of(MODULE, "pure").is_empty());
+}
+
+#[test]
+fn infer_through_calls() {
+    assert_eq!(acquires_of(MODULE, "both"), struct_names(&["A", "B"]));
+    assert_eq!(acquires_of(MODULE, "countdown"), struct_names(&["A"]));
+}
+
+#[test]
+fn diff_against_declared() {
+    let code = "
+module M {
+    resource A { x: u64 }
+    resource B { y: u64 }
+
+    f(addr: address): u64 acquires B {
+        return *&(borrow_global<A>(move(addr))).x;
+    }
+
+    g(addr: address): u64 acquires A {
+        return Self.f(move(addr));
+    }
+}
+";
+    let module = parse_module(code).unwrap();
+    let diffs = acquires_diff(&module);
+    assert_eq!(diffs.len(), 1);
+    assert_eq!(diffs[0].function, FunctionName::parse("f").unwrap());
+    assert_eq!(diffs[0].missing, struct_names(&["A"]));
+    assert_eq!(diffs[0].extraneous, struct_names(&["B"]));
+    assert_eq!(
+        diffs[0].to_string(),
+        "function f: missing acquires A; extraneous acquires B"
+    );
+}
+
+#[test]
+fn filled_module_verifies() {
+    let mut module = parse_module(MODULE).unwrap();
+    fill_acquires(&mut module);
+    assert!(acquires_diff(&module).is_empty());
+
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        verify: true,
+        infer_acquires: true,
+        ..Compiler::default()
+    };
+    compiler.into_compiled_module(MODULE).unwrap();
+}
+
+#[test]
+fn check_acquires_fails_compilation() {
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        check_acquires: true,
+        ..Compiler::default()
+    };
+    let err = compiler
+        .into_compiled_module(MODULE)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("function read_a: missing acquires A"),
+        "{}",
+        err
+    );
+    assert!(
+        err.contains("function both: missing acquires A, B"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn stdlib_acquires_match_inferred() {
+    for module in stdlib::stdlib::module_defs() {
+        assert_eq!(acquires_diff(module), vec![], "module {}", module.name);
+    }
+}
+
+#[test]
+fn check_acquires_reports_justifying_uses() {
+    let code = "module M {
+    resource A { x: u64 }
+    resource B { y: u64 }
+    f(addr: address): u64 acquires B {
+        return *&(borrow_global<A>(move(addr))).x;
+    }
+    g(addr: address): u64 {
+        return Self.f(move(addr));
+    }
+    h() acquires A {
+        return;
+    }
+}";
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        check_acquires: true,
+        ..Compiler::default()
+    };
+    let err = compiler.into_compiled_module(code).unwrap_err().to_string();
+    let expected = "4:5: function f: missing acquires A; extraneous acquires B
+    5:19: `borrow_global<A>` acquires A
+    help: declare `acquires A`
+7:5: function g: missing acquires A
+    8:16: the call to `f` acquires A
+    5:19: in `f`, `borrow_global<A>` acquires A
+    help: declare `acquires A`
+10:5: function h: extraneous acquires A
+    help: remove the acquires list";
+    assert!(err.ends_with(expected), "{}", err);
+}
+
+#[test]
+fn acquisition_chains_end_at_global_storage() {
+    let module = parse_module(MODULE).unwrap();
+    let acquisitions = infer_acquisitions(&module);
+    let chain = |function: &str, name: &str| {
+        acquisition_chain(
+            &acquisitions,
+            &FunctionName::parse(function).unwrap(),
+            &StructName::parse(name).unwrap(),
+        )
+        .into_iter()
+        .map(|(function, acquisition)| (function.to_string(), acquisition.by))
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        chain("countdown", "A"),
+        vec![
+            (
+                "countdown".to_string(),
+                AcquiredBy::Call(FunctionName::parse("read_a").unwrap())
+            ),
+            ("read_a".to_string(), AcquiredBy::BorrowGlobal),
+        ]
+    );
+    assert_eq!(
+        chain("take_b", "B"),
+        vec![("take_b".to_string(), AcquiredBy::MoveFrom)]
+    );
+    assert!(chain("pure", "A").is_empty());
+}
+
+#[test]
+fn check_acquires_reports_call_chains() {
+    let code = "module M {
+    resource A { x: u64 }
+    f(addr: address): u64 acquires A {
+        return *&(borrow_global<A>(move(addr))).x;
+    }
+    g(addr: address): u64 acquires A {
+        return Self.f(move(addr));
+    }
+    public h(addr: address): u64 {
+        return Self.g(move(addr));
+    }
+}";
+    let module = parse_module(code).unwrap();
+    let diffs = acquires_diff(&module);
+    assert_eq!(diffs.len(), 1);
+    // The header ends with the (empty) acquires list, before the body.
+    assert_eq!(
+        &code[diffs[0].loc.start().to_usize()..diffs[0].loc.end().to_usize()],
+        "public h(addr: address): u64"
+    );
+
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        check_acquires: true,
+        ..Compiler::default()
+    };
+    let err = compiler.into_compiled_module(code).unwrap_err().to_string();
+    let expected = "9:5: function h: missing acquires A
+    10:16: the call to `g` acquires A
+    7:16: in `g`, the call to `f` acquires A
+    4:19: in `f`, `borrow_global<A>` acquires A
+    help: declare `acquires A`";
+    assert!(err.ends_with(expected), "{}", err);
+}
+
+#[test]
+fn resolve_acquires_entries() {
+    let code = "
+module M {
+    struct S { x: u64 }
+    resource Coin { value: u64 }
+    resource Balance { coin: Self.Coin }
+
+    f() acquires Coin, S {
+        return;
+    }
+
+    g() acquires Coins, Balance, Unrelated {
+        return;
+    }
+}
+";
+    let module = parse_module(code).unwrap();
+    let errors = resolve_acquires(&module);
+    assert_eq!(
+        errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+        vec![
+            "function f acquires S, which is a struct and not a resource",
+            "function g acquires Coins, which is not declared in the module",
+            "function g acquires Unrelated, which is not declared in the module",
+        ]
+    );
+    assert_eq!(errors[0].suggestion(), None);
+    assert_eq!(errors[1].suggestion(), Some(&struct_names(&["Coin"])[0]));
+    assert_eq!(errors[2].suggestion(), None);
+
+    // Direct compilation fails on the first invalid entry.
+    let err = compile_module(AccountAddress::default(), module, &[] as &[CompiledModule])
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        err,
+        "function f acquires S, which is a struct and not a resource"
+    );
+}
+
+#[test]
+fn invalid_acquires_fail_compilation_with_suggestion() {
+    let code = "module M {
+    resource Coin { value: u64 }
+    f(addr: address): u64 acquires Con {
+        return *&(borrow_global<Coin>(move(addr))).value;
+    }
+}";
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    let err = compiler
+        .clone()
+        .into_compiled_module(code)
+        .unwrap_err()
+        .to_string();
+    let expected = "3:5: function f acquires Con, which is not declared in the module
+    help: did you mean `Coin`?";
+    assert!(err.ends_with(expected), "{}", err);
+
+    // Inferred lists replace the declared ones.
+    let compiler = Compiler {
+        infer_acquires: true,
+        ..compiler
+    };
+    compiler.into_compiled_module(code).unwrap();
+}
+
+const UNUSED: &str = "module M {
+    resource A { v: u64 }
+    resource B { v: u64 }
+
+    f(addr: address): u64 acquires A, B {
+        return *&(borrow_global<A>(move(addr))).v;
+    }
+
+    g(addr: address): u64 acquires B, A {
+        let x: u64;
+        if (false) {
+            x = *&(borrow_global<B>(copy(addr))).v;
+        }
+        return Self.f(move(addr));
+    }
+
+    h(addr: address): u64 acquires A {
+        abort 1;
+        return *&(borrow_global<A>(move(addr))).v;
+    }
+}";
+
+#[test]
+fn unused_acquires_entries() {
+    let module = parse_module(UNUSED).unwrap();
+    let unused = unused_acquires(&module);
+    let entries: Vec<_> = unused
+        .iter()
+        .map(|function| {
+            (
+                function.function.to_string(),
+                function.unused.clone(),
+                function.trimmed.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            ("f".to_string(), struct_names(&["B"]), struct_names(&["A"])),
+            ("g".to_string(), struct_names(&["B"]), struct_names(&["A"])),
+            ("h".to_string(), struct_names(&["A"]), vec![]),
+        ]
+    );
+    // The syntactic inference still counts the accesses no path reaches.
+    assert_eq!(acquires_of(UNUSED, "g"), struct_names(&["A", "B"]));
+}
+
+#[test]
+fn unused_acquires_report_unreachable_accesses() {
+    let module = parse_module(UNUSED).unwrap();
+    let report = unused_acquires_report(UNUSED, &unused_acquires(&module));
+    let expected = "5:5: warning: function f acquires B, which no path of the function accesses
+    help: declare `acquires A`
+9:5: warning: function g acquires B, which no path of the function accesses
+    12:20: `borrow_global<B>` acquires B
+    help: remove the code no path reaches and declare `acquires A`
+17:5: warning: function h acquires A, which no path of the function accesses
+    19:19: `borrow_global<A>` acquires A
+    help: remove the code no path reaches and the acquires list";
+    assert_eq!(report, expected);
+}
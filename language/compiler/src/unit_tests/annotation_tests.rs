@@ -0,0 +1,61 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::{
+    annotations::{parse_annotated_script_or_module, parse_annotations, Annotation},
+    errors::AnnotationError,
+};
+use libra_types::vm_error::StatusCode;
+use move_ir_types::ast::ScriptOrModule;
+
+#[test]
+fn parses_header_annotations() {
+    let source = "// Double move.
+//! abort 7
+//! verifier : COPYLOC_EXISTS_BORROW_ERROR
+//!runtime: ARITHMETIC_ERROR
+//! compiler: Unbound variable x
+//! sender: alice
+
+main() {
+    //! abort 8
+    abort 7;
+}
+";
+    let (script, annotations) = parse_annotated_script_or_module(source).unwrap();
+    assert!(match script {
+        ScriptOrModule::Script(_) => true,
+        ScriptOrModule::Module(_) => false,
+    });
+    assert_eq!(
+        annotations,
+        vec![
+            Annotation::Abort(7),
+            Annotation::Verifier(StatusCode::COPYLOC_EXISTS_BORROW_ERROR),
+            Annotation::Runtime(StatusCode::ARITHMETIC_ERROR),
+            Annotation::Compiler("Unbound variable x".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn rejects_malformed_annotations() {
+    let error = |line, message: &str| {
+        Err(AnnotationError {
+            line,
+            message: message.to_string(),
+        })
+    };
+    assert_eq!(
+        parse_annotations("//! abort seven\nmain() { return; }"),
+        error(1, "expected an abort code, found \"seven\"")
+    );
+    assert_eq!(
+        parse_annotations("\n//! verifier: BORROW_EROR"),
+        error(2, "unknown status \"BORROW_EROR\"")
+    );
+    assert_eq!(
+        parse_annotations("//! compiler:"),
+        error(1, "expected an error message")
+    );
+}
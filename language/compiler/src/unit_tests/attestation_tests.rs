@@ -0,0 +1,87 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::{
+    attestation::{module_digest, script_digest, Manifest},
+    compiler::{compile_module, compile_script},
+    parser::{parse_module, parse_script},
+};
+use libra_types::account_address::AccountAddress;
+use stdlib::stdlib_modules;
+
+const MODULE: &str = "module M {
+    public f(x: u64): u64 {
+        return move(x) + 1;
+    }
+}
+";
+
+const SCRIPT: &str = "main(amount: u64) {
+    assert(move(amount) > 0, 42);
+    return;
+}
+";
+
+#[test]
+fn source_digests_ignore_layout_and_comments() {
+    let relaid = "// Adds one.
+module M { public f(x: u64): u64 { return move(x)+1; } }";
+    assert_eq!(
+        module_digest(&parse_module(MODULE).unwrap()),
+        module_digest(&parse_module(relaid).unwrap())
+    );
+
+    let changed = MODULE.replace("+ 1", "+ 2");
+    assert_ne!(
+        module_digest(&parse_module(MODULE).unwrap()),
+        module_digest(&parse_module(&changed).unwrap())
+    );
+    assert_ne!(
+        script_digest(&parse_script(SCRIPT).unwrap()),
+        script_digest(&parse_script(&SCRIPT.replace("42", "43")).unwrap())
+    );
+}
+
+#[test]
+fn manifests_tie_sources_to_bytecode() {
+    let module = parse_module(MODULE).unwrap();
+    let (compiled_module, _) =
+        compile_module(AccountAddress::default(), module.clone(), stdlib_modules()).unwrap();
+    let script = parse_script(SCRIPT).unwrap();
+    let (compiled_script, _) =
+        compile_script(AccountAddress::default(), script.clone(), stdlib_modules()).unwrap();
+
+    let mut manifest = Manifest::default();
+    manifest.add_module(&module, &compiled_module).unwrap();
+    manifest
+        .add_script("check", &script, &compiled_script)
+        .unwrap();
+    assert!(manifest.add_module(&module, &compiled_module).is_err());
+
+    let manifest = Manifest::from_bytes(&manifest.to_bytes().unwrap()).unwrap();
+    manifest.check_module(&module, &compiled_module).unwrap();
+    manifest
+        .check_script("check", &script, &compiled_script)
+        .unwrap();
+    assert!(manifest
+        .check_script("other", &script, &compiled_script)
+        .is_err());
+
+    let changed = parse_module(&MODULE.replace("+ 1", "+ 2")).unwrap();
+    let (recompiled, _) =
+        compile_module(AccountAddress::default(), changed.clone(), stdlib_modules()).unwrap();
+    let err = manifest.check_module(&changed, &recompiled).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("The source of M does not match the manifest"));
+    let err = manifest.check_module(&module, &recompiled).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("The bytecode of M does not match the manifest"));
+}
+
+#[test]
+fn manifests_of_other_formats_are_rejected() {
+    assert!(Manifest::from_bytes(b"{\"format\": \"move-ir-bundle\", \"version\": 1}").is_err());
+    assert!(Manifest::from_bytes(b"not json").is_err());
+}
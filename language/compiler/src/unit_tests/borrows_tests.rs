@@ -0,0 +1,125 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::util::borrow_report;
+use ir_to_bytecode::{
+    borrows::{module_borrows, script_borrows, FunctionBorrows, Place},
+    parser::{parse_module, parse_script},
+};
+use move_ir_types::ast::{StructName, Var_};
+use std::collections::BTreeSet;
+
+const MODULE: &str = "module Coin {
+    resource T { value: u64 }
+
+    native public hash(data: bytearray): bytearray;
+
+    public value(coin: &Self.T): u64 {
+        return *&move(coin).value;
+    }
+
+    public deposit(amount: u64) acquires T {
+        let t: &mut Self.T;
+        let v: u64;
+        t = borrow_global_mut<T>(get_txn_sender());
+        v = Self.value(freeze(copy(t)));
+        Self.add(&mut move(t).value, move(amount));
+        Self.bump(&mut v);
+        return;
+    }
+
+    add(value: &mut u64, amount: u64) {
+        *move(value) = *copy(value) + move(amount);
+        return;
+    }
+
+    bump(v: &mut u64) {
+        *move(v) = 1;
+        return;
+    }
+}
+";
+
+fn local(name: &str) -> Place {
+    Place::Local(Var_::parse(name).unwrap())
+}
+
+fn reference(name: &str) -> Place {
+    Place::Reference(Var_::parse(name).unwrap())
+}
+
+fn places(places: Vec<Place>) -> BTreeSet<Place> {
+    places.into_iter().collect()
+}
+
+fn borrows() -> Vec<FunctionBorrows> {
+    module_borrows(&parse_module(MODULE).unwrap())
+}
+
+#[test]
+fn borrowed_places() {
+    let borrows = borrows();
+    let names: Vec<&str> = borrows.iter().map(|f| f.function.name.as_str()).collect();
+    assert_eq!(names, vec!["value", "deposit", "add", "bump"]);
+
+    assert!(borrows[0].mutable.is_empty());
+    assert_eq!(borrows[0].immutable, places(vec![reference("coin")]));
+
+    let deposit = &borrows[1];
+    assert_eq!(
+        deposit.mutable,
+        places(vec![
+            local("v"),
+            reference("t"),
+            Place::Global(StructName::parse("T").unwrap()),
+        ])
+    );
+    assert!(deposit.immutable.is_empty());
+}
+
+#[test]
+fn borrow_notes() {
+    let report = borrow_report(MODULE, &borrows());
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "6:5: note: `Coin.value` borrows `*coin` immutably",
+            "7:17: note: field `value` of `*coin` borrowed immutably",
+            "10:5: note: `Coin.deposit` borrows `v`, `*t`, global `T` mutably",
+            "13:13: note: global `T` borrowed mutably",
+            "14:24: note: reference to `*t` passed to `Self.value`",
+            "14:24: note: reference to `*t` frozen",
+            "15:18: note: mutable reference to `*t` passed to `Self.add`",
+            "15:18: note: field `value` of `*t` borrowed mutably",
+            "16:19: note: mutable reference to `v` passed to `Self.bump`",
+            "16:19: note: `v` borrowed mutably",
+            "20:5: note: `Coin.add` borrows nothing",
+            "25:5: note: `Coin.bump` borrows nothing",
+        ]
+    );
+}
+
+#[test]
+fn script_borrows_are_reported() {
+    let script = parse_script(
+        "import 0x0.LibraAccount;
+main() {
+    let x: u64;
+    x = 0;
+    LibraAccount.check(&x, 1);
+    return;
+}
+",
+    )
+    .unwrap();
+    let borrows = script_borrows(&script);
+    assert_eq!(borrows.len(), 1);
+    assert_eq!(borrows[0].function.module, None);
+    assert_eq!(borrows[0].immutable, places(vec![local("x")]));
+    assert_eq!(borrows[0].to_string(), "`main` borrows `x` immutably");
+    assert_eq!(
+        borrows[0].uses[0].to_string(),
+        "reference to `x` passed to `LibraAccount.check`"
+    );
+}
@@ -397,3 +397,44 @@ fn compile_loop_return() {
     assert!(instr_count!(compiled_script, Branch(_)) == 2);
     assert!(instr_count!(compiled_script, Ret) == 3);
 }
+
+#[test]
+fn compile_loop_break_value() {
+    let code = String::from(
+        "
+        main() {
+            let i: u64;
+            let x: u64;
+            i = 0;
+            x = loop {
+                if (copy(i) >= 10) {
+                    break copy(i) * 2;
+                }
+                i = move(i) + 1;
+            };
+            assert(move(x) == 20, 42);
+            return;
+        }
+        ",
+    );
+    let compiled_script_res = compile_script_string(&code);
+    let compiled_script = compiled_script_res.unwrap();
+    assert!(instr_count!(compiled_script, Branch(_)) == 2);
+    assert!(instr_count!(compiled_script, StLoc(1)) == 1);
+}
+
+#[test]
+fn compile_break_value_out_of_loop_without_value() {
+    let code = String::from(
+        "
+        main() {
+            loop {
+                break 0;
+            }
+            return;
+        }
+        ",
+    );
+    let compiled_script_res = compile_script_string(&code);
+    assert!(compiled_script_res.is_err());
+}
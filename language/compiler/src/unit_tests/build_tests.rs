@@ -0,0 +1,134 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::unit_tests::testutils::temp_dir;
+use ir_to_bytecode::build::{compile_directory, compile_modules, Build, BuildOptions};
+use stdlib::{stdlib::module_defs, stdlib_modules};
+use vm::access::ModuleAccess;
+
+const BASE: &str = "module Base {
+    public f(): u64 {
+        return 42;
+    }
+}
+";
+
+const TOP: &str = "module Top {
+    import 0x0.Base;
+    public g(): u64 {
+        return Base.f();
+    }
+}
+";
+
+fn module_names(build: &Build) -> Vec<String> {
+    build
+        .modules
+        .iter()
+        .map(|built| built.module.self_id().name().to_string())
+        .collect()
+}
+
+fn error_messages(build: &Build) -> Vec<String> {
+    build.errors.iter().map(|err| err.to_string()).collect()
+}
+
+#[test]
+fn modules_are_built_after_their_imports() {
+    let dir = temp_dir(&[("a/top.mvir", TOP), ("b/base.mvir", BASE)]);
+    let build = compile_directory(dir.path(), &BuildOptions::default()).unwrap();
+    assert!(build.is_success(), "{:?}", build.errors);
+    assert_eq!(module_names(&build), vec!["Base", "Top"]);
+    assert!(build.modules[0]
+        .path
+        .as_ref()
+        .unwrap()
+        .ends_with("b/base.mvir"));
+}
+
+#[test]
+fn errors_of_all_modules_are_collected() {
+    let broken = "module Broken {\n    import 0x0.Base;\n    h() {\n        Base.nope();\n        return;\n    }\n}\n";
+    let dependent = "module Dependent {\n    import 0x0.Broken;\n}\n";
+    let dir = temp_dir(&[
+        ("base.mvir", BASE),
+        ("broken.mvir", broken),
+        ("dependent.mvir", dependent),
+        ("syntax.mvir", "module Syntax {"),
+        ("top.mvir", TOP),
+    ]);
+    let build = compile_directory(dir.path(), &BuildOptions::default()).unwrap();
+    assert_eq!(module_names(&build), vec!["Base", "Top"]);
+    assert_eq!(build.errors.len(), 3, "{:?}", build.errors);
+    assert!(build.errors[0]
+        .path
+        .as_ref()
+        .unwrap()
+        .ends_with("syntax.mvir"));
+    assert!(build.errors[0].loc.is_some());
+    assert_eq!(
+        build.errors[1].module.as_ref().unwrap().to_string(),
+        "Broken"
+    );
+    assert_eq!(
+        error_messages(&build)[2],
+        "Module Dependent imports module Broken, which does not compile"
+    );
+}
+
+#[test]
+fn import_cycles_are_reported() {
+    let a = "module A {\n    import 0x0.B;\n}\n";
+    let b = "module B {\n    import 0x0.A;\n}\n";
+    let c = "module C {\n    import 0x0.A;\n}\n";
+    let dir = temp_dir(&[
+        ("a.mvir", a),
+        ("b.mvir", b),
+        ("base.mvir", BASE),
+        ("c.mvir", c),
+    ]);
+    let build = compile_directory(dir.path(), &BuildOptions::default()).unwrap();
+    assert_eq!(module_names(&build), vec!["Base"]);
+    assert_eq!(
+        error_messages(&build),
+        vec![
+            "Module A imports itself through a cycle of imports",
+            "Module B imports itself through a cycle of imports",
+            "Module C imports modules in a cycle of imports",
+        ]
+    );
+}
+
+#[test]
+fn duplicate_modules_are_reported() {
+    let dir = temp_dir(&[("a.mvir", BASE), ("b.mvir", BASE)]);
+    let build = compile_directory(dir.path(), &BuildOptions::default()).unwrap();
+    assert_eq!(module_names(&build), vec!["Base"]);
+    assert_eq!(error_messages(&build), vec!["Duplicate module Base"]);
+    assert!(build.errors[0].path.as_ref().unwrap().ends_with("b.mvir"));
+}
+
+#[test]
+fn stdlib_builds_in_any_order() {
+    let module_defs = module_defs()
+        .iter()
+        .rev()
+        .map(|module_def| (*module_def).clone())
+        .collect();
+    let build = compile_modules(module_defs, &BuildOptions::default());
+    assert!(build.is_success(), "{:?}", build.errors);
+    let (modules, _) = build.into_modules();
+    assert_eq!(modules.len(), stdlib_modules().len());
+}
+
+#[test]
+fn modules_build_against_dependencies() {
+    let options = BuildOptions {
+        deps: stdlib_modules().to_vec(),
+        ..BuildOptions::default()
+    };
+    let uses_stdlib = "module UsesStdlib {\n    import 0x0.LibraAccount;\n    public f(): u64 {\n        return LibraAccount.balance(get_txn_sender());\n    }\n}\n";
+    let dir = temp_dir(&[("m.mvir", uses_stdlib)]);
+    let build = compile_directory(dir.path(), &options).unwrap();
+    assert!(build.is_success(), "{:?}", build.errors);
+}
@@ -0,0 +1,93 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::{abi::script_abi, builders::generate_rust_builders, errors::BuilderError};
+use stdlib::transaction_scripts;
+
+#[test]
+fn generates_builders() {
+    let transfer = script_abi("peer_to_peer", transaction_scripts::peer_to_peer()).unwrap();
+    let source = "// Records something.
+main(
+    type: bytearray, // What kind of record.
+    flag: bool
+) {
+    return;
+}
+";
+    let record = script_abi("record", source).unwrap();
+    let code: Vec<u8> = (0..20).collect();
+    let builders = generate_rust_builders(&[(transfer, vec![161, 28]), (record, code)]).unwrap();
+    let expected =
+        "// Transaction builders generated from the ABIs of transaction scripts. Do not edit.
+
+use libra_types::{
+    account_address::AccountAddress,
+    byte_array::ByteArray,
+    transaction::{Script, TransactionArgument},
+};
+
+/// Encodes a call to the `peer_to_peer` script.
+pub fn encode_peer_to_peer_script(payee: AccountAddress, amount: u64) -> Script {
+    Script::new(
+        PEER_TO_PEER_CODE.to_vec(),
+        vec![
+            TransactionArgument::Address(payee),
+            TransactionArgument::U64(amount),
+        ],
+    )
+}
+
+/// Encodes a call to the `record` script.
+///
+/// Records something.
+///
+/// * `type`: What kind of record.
+pub fn encode_record_script(r#type: Vec<u8>, flag: bool) -> Script {
+    Script::new(
+        RECORD_CODE.to_vec(),
+        vec![
+            TransactionArgument::ByteArray(ByteArray::new(r#type)),
+            TransactionArgument::Bool(flag),
+        ],
+    )
+}
+
+const PEER_TO_PEER_CODE: &[u8] = &[
+    161, 28,
+];
+
+const RECORD_CODE: &[u8] = &[
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+    16, 17, 18, 19,
+];
+";
+    assert_eq!(builders, expected);
+}
+
+#[test]
+fn rejects_arguments_transactions_cannot_pass() {
+    let source = "import 0x0.LibraCoin;
+main(coin: LibraCoin.T) {
+    abort 0;
+}
+";
+    let abi = script_abi("pay", source).unwrap();
+    assert_eq!(
+        generate_rust_builders(&[(abi, vec![])]),
+        Err(BuilderError::UnsupportedArgument {
+            script: "pay".to_string(),
+            argument: "coin".to_string(),
+            type_: "LibraCoin.T".to_string(),
+        })
+    );
+
+    let abi = script_abi("two-words", transaction_scripts::peer_to_peer()).unwrap();
+    assert_eq!(
+        generate_rust_builders(&[(abi, vec![])]),
+        Err(BuilderError::InvalidName {
+            script: "two-words".to_string(),
+            name: "two-words".to_string(),
+        })
+    );
+}
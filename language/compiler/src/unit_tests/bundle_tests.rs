@@ -0,0 +1,108 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::{
+    bundle::{Bundle, UnitKind, BUNDLE_VERSION},
+    compiler::{compile_module, compile_script},
+    metadata::{embed_metadata, BuildInfo, Metadata},
+    parser::{parse_module, parse_script},
+};
+use libra_temppath::TempPath;
+use libra_types::account_address::AccountAddress;
+use stdlib::stdlib_modules;
+
+const MODULE: &str = "module M {
+    resource R { x: u64 }
+    public f(x: u64): u64
+        requires x > 0
+        ensures RET == x
+    {
+        return move(x);
+    }
+}
+";
+
+const SCRIPT: &str = "// Does nothing with an amount.
+main(amount: u64) {
+    return;
+}
+";
+
+#[test]
+fn module_bundles_round_trip() {
+    let module = parse_module(MODULE).unwrap();
+    let (compiled, source_map) =
+        compile_module(AccountAddress::default(), module.clone(), stdlib_modules()).unwrap();
+    let bundle = Bundle::for_module(MODULE, &module, &compiled, source_map).unwrap();
+    assert_eq!(bundle.kind, UnitKind::Module);
+    assert!(bundle.abi.is_none());
+    assert_eq!(
+        bundle.specs.functions["f"],
+        vec!["requires x > 0", "ensures RET == x"]
+    );
+
+    let path = TempPath::new();
+    bundle.write_to_file(path.path()).unwrap();
+    let read = Bundle::read_from_file(path.path()).unwrap();
+    assert_eq!(read.module().unwrap().self_id(), compiled.self_id());
+    assert_eq!(read.specs, bundle.specs);
+    assert!(read.script().is_err());
+}
+
+#[test]
+fn script_bundles_hold_the_abi() {
+    let script = parse_script(SCRIPT).unwrap();
+    let (compiled, source_map) =
+        compile_script(AccountAddress::default(), script.clone(), stdlib_modules()).unwrap();
+    let bundle = Bundle::for_script("noop", SCRIPT, &script, &compiled, source_map).unwrap();
+    let read = Bundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+    assert_eq!(read.kind, UnitKind::Script);
+    let abi = read.abi.clone().unwrap();
+    assert_eq!(abi.name, "noop");
+    assert_eq!(abi.doc, "Does nothing with an amount.");
+    assert_eq!(read.script().unwrap(), compiled);
+}
+
+#[test]
+fn other_formats_and_versions_are_rejected() {
+    assert!(Bundle::from_bytes(b"{\"format\": \"other\", \"version\": 1}").is_err());
+    let script = parse_script(SCRIPT).unwrap();
+    let (compiled, source_map) =
+        compile_script(AccountAddress::default(), script.clone(), stdlib_modules()).unwrap();
+    let mut bundle = Bundle::for_script("noop", SCRIPT, &script, &compiled, source_map).unwrap();
+    bundle.version = BUNDLE_VERSION + 1;
+    let err = Bundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap_err();
+    assert!(
+        err.to_string().starts_with("Unsupported bundle version"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn bundles_record_the_build_info() {
+    let module = parse_module(MODULE).unwrap();
+    let (compiled, source_map) =
+        compile_module(AccountAddress::default(), module.clone(), stdlib_modules()).unwrap();
+    let bundle = Bundle::for_module(MODULE, &module, &compiled, source_map.clone()).unwrap();
+    assert_eq!(bundle.build, None);
+
+    let build = BuildInfo::new(None, vec!["ssa".to_string()]);
+    let metadata = Metadata {
+        build: Some(build.clone()),
+        ..Metadata::default()
+    };
+    let compiled = embed_metadata(compiled, &metadata).unwrap();
+    let bundle = Bundle::for_module(MODULE, &module, &compiled, source_map).unwrap();
+    let read = Bundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+    assert_eq!(read.build, Some(build.clone()));
+
+    let script = parse_script(SCRIPT).unwrap();
+    let (compiled, source_map) =
+        compile_script(AccountAddress::default(), script.clone(), stdlib_modules()).unwrap();
+    let bundle = Bundle::for_script("noop", SCRIPT, &script, &compiled, source_map)
+        .unwrap()
+        .with_build_info(build.clone());
+    let read = Bundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+    assert_eq!(read.build, Some(build));
+}
@@ -0,0 +1,53 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::parser::{
+    parse_module, parse_module_with_options, try_parse_script_or_module, ParserOptions,
+};
+
+fn module_with_literal(digits: &str) -> String {
+    format!(
+        "module M {{ f(): bytearray {{ return h\"{}\"; }} }}",
+        digits
+    )
+}
+
+fn limited(max_byte_array_len: usize) -> ParserOptions {
+    ParserOptions {
+        max_byte_array_len: Some(max_byte_array_len),
+        ..ParserOptions::default()
+    }
+}
+
+#[test]
+fn odd_length_bytearray_is_located() {
+    let source = module_with_literal("abc");
+    let error = try_parse_script_or_module(&source).unwrap_err();
+    assert_eq!(
+        error.message,
+        "Bytearray literal has an odd number of hex digits (3)"
+    );
+    let loc = error.loc.unwrap();
+    assert_eq!(
+        &source[loc.start().0 as usize..loc.end().0 as usize],
+        "h\"abc\""
+    );
+}
+
+#[test]
+fn bytearray_within_limit_is_accepted() {
+    assert!(parse_module(&module_with_literal("")).is_ok());
+    assert!(parse_module_with_options(&module_with_literal("00ff"), limited(2)).is_ok());
+}
+
+#[test]
+fn bytearray_over_limit_is_rejected() {
+    let error = parse_module_with_options(&module_with_literal("00ff01"), limited(2)).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("Bytearray literal of 3 bytes exceeds the limit of 2 bytes"),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,154 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::{
+    call_graph::{Call, CallGraph, Callee, FunctionId},
+    parser::parse_module,
+};
+use std::collections::BTreeSet;
+
+const COIN: &str = "module Coin {
+    import 0x0.LibraAccount;
+
+    resource T { value: u64 }
+
+    public balance(addr: address): u64 acquires T {
+        if (exists<T>(copy(addr))) {
+            return *&borrow_global<T>(move(addr)).value;
+        }
+        return 0;
+    }
+
+    public even(n: u64): bool {
+        if (copy(n) == 0) {
+            return true;
+        }
+        return Wallet.odd(move(n) - 1);
+    }
+
+    public sender_balance(): u64 acquires T {
+        return Self.balance(get_txn_sender()) + LibraAccount.balance(get_txn_sender());
+    }
+}
+";
+
+const WALLET: &str = "module Wallet {
+    import 0x0.Coin as C;
+
+    public odd(n: u64): bool {
+        if (copy(n) == 0) {
+            return false;
+        }
+        return C.even(move(n) - 1);
+    }
+
+    public countdown(n: u64) {
+        if (copy(n) > 0) {
+            Self.countdown(move(n) - 1);
+        }
+        return;
+    }
+
+    public total(): u64 {
+        return C.sender_balance();
+    }
+}
+";
+
+fn function(module: &str, name: &str) -> FunctionId {
+    FunctionId::new(module, name)
+}
+
+fn graph() -> CallGraph {
+    CallGraph::new(&[parse_module(COIN).unwrap(), parse_module(WALLET).unwrap()])
+}
+
+#[test]
+fn calls_are_resolved_across_modules() {
+    let graph = graph();
+    assert_eq!(graph.functions.len(), 6);
+    let sender_balance = function("Coin", "sender_balance");
+    let callees: Vec<&FunctionId> = graph.callees(&sender_balance).collect();
+    assert_eq!(
+        callees,
+        vec![
+            &function("Coin", "balance"),
+            &function("LibraAccount", "balance"),
+        ]
+    );
+    assert!(graph.is_external(&function("LibraAccount", "balance")));
+    assert!(graph.calls.contains(&Call {
+        caller: function("Wallet", "odd"),
+        callee: Callee::Function(function("Coin", "even")),
+    }));
+}
+
+#[test]
+fn builtins_are_callees() {
+    let graph = graph();
+    let builtins: BTreeSet<String> = graph
+        .calls
+        .iter()
+        .filter_map(|call| match &call.callee {
+            Callee::Builtin(name) => Some(name.clone()),
+            Callee::Function(_) => None,
+        })
+        .collect();
+    let expected: BTreeSet<String> =
+        vec!["borrow_global<Coin.T>", "exists<Coin.T>", "get_txn_sender"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+    assert_eq!(builtins, expected);
+}
+
+#[test]
+fn reachable_functions() {
+    let graph = graph();
+    let expected: BTreeSet<FunctionId> = vec![
+        function("Coin", "balance"),
+        function("Coin", "sender_balance"),
+        function("LibraAccount", "balance"),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(graph.reachable_from(&function("Wallet", "total")), expected);
+    assert!(graph
+        .reachable_from(&function("Coin", "balance"))
+        .is_empty());
+}
+
+#[test]
+fn recursion_across_modules() {
+    let graph = graph();
+    let groups: Vec<Vec<FunctionId>> = graph
+        .recursive_groups()
+        .into_iter()
+        .map(|group| group.into_iter().collect())
+        .collect();
+    assert_eq!(
+        groups,
+        vec![
+            vec![function("Coin", "even"), function("Wallet", "odd")],
+            vec![function("Wallet", "countdown")],
+        ]
+    );
+}
+
+#[test]
+fn dot_output() {
+    let dot = graph().to_dot();
+    assert!(dot.starts_with("digraph calls {\n"));
+    assert!(dot.contains("    \"Coin.balance\";\n"));
+    assert!(dot.contains("    \"LibraAccount.balance\" [style=dashed];\n"));
+    assert!(dot.contains("    \"exists<Coin.T>\" [shape=box];\n"));
+    assert!(dot.contains("    \"Wallet.total\" -> \"Coin.sender_balance\";\n"));
+    assert!(dot.ends_with("}\n"));
+}
+
+#[test]
+fn call_graph_round_trips_through_json() {
+    let graph = graph();
+    let parsed: CallGraph = serde_json::from_slice(&graph.to_json().unwrap()).unwrap();
+    assert_eq!(parsed, graph);
+}
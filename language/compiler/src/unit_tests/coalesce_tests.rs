@@ -0,0 +1,210 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{unit_tests::testutils::assert_contains, util::coalescing_report, Compiler};
+use ir_to_bytecode::{
+    coalesce::{coalesce_module, coalesce_script},
+    formatter::{format_module, FormatOptions},
+    parser::{parse_module, parse_script},
+};
+use move_ir_types::ast::Var_;
+
+const MODULE: &str = "module M {
+    resource T { v: u64 }
+
+    sum(a: u64, b: u64): u64 {
+        let x: u64;
+        let y: u64;
+        let z: u64;
+        x = move(a) + 1;
+        y = move(b) + 2;
+        z = move(x) + move(y);
+        return move(z);
+    }
+
+    overlap(a: u64): u64 {
+        let x: u64;
+        let y: u64;
+        x = copy(a);
+        y = copy(a);
+        return move(x) + move(y) + move(a);
+    }
+
+    types(a: u64): bool {
+        let f: bool;
+        f = move(a) > 0;
+        return move(f);
+    }
+
+    read(a: u64): u64 {
+        let r: &u64;
+        let y: u64;
+        let z: u64;
+        y = move(a);
+        r = &y;
+        z = *move(r);
+        return move(z);
+    }
+
+    count(n: u64): u64 {
+        let i: u64;
+        let j: u64;
+        i = 0;
+        while (copy(i) < copy(n)) {
+            j = move(i) + 1;
+            i = move(j);
+        }
+        return move(i);
+    }
+
+    unwrap(t: Self.T): u64 {
+        let v: u64;
+        let w: u64;
+        T { v } = move(t);
+        w = move(v);
+        return move(w);
+    }
+}
+";
+
+fn var(name: &str) -> Var_ {
+    Var_::parse(name).unwrap()
+}
+
+fn coalesced() -> String {
+    let mut module = parse_module(MODULE).unwrap();
+    coalesce_module(&mut module);
+    format_module(&module, &FormatOptions::default())
+}
+
+#[test]
+fn dead_parameters_and_locals_are_reused() {
+    let mut module = parse_module(MODULE).unwrap();
+    let functions = coalesce_module(&mut module);
+    let sum = functions
+        .iter()
+        .find(|coalesced| coalesced.function.name == "sum")
+        .unwrap();
+    assert_eq!(sum.slots_before, 5);
+    assert_eq!(sum.slots_after, 2);
+    assert_eq!(
+        sum.merged,
+        vec![
+            (var("x"), var("a")),
+            (var("y"), var("b")),
+            (var("z"), var("a")),
+        ]
+    );
+    assert_contains(
+        &format_module(&module, &FormatOptions::default()),
+        &[
+            "a = move(a) + 1;",
+            "b = move(b) + 2;",
+            "a = move(a) + move(b);",
+            "return move(a);",
+        ],
+    );
+}
+
+#[test]
+fn overlapping_lifetimes_keep_their_slots() {
+    let mut module = parse_module(MODULE).unwrap();
+    let functions = coalesce_module(&mut module);
+    assert!(functions
+        .iter()
+        .all(|coalesced| coalesced.function.name != "overlap"));
+    assert_contains(
+        &coalesced(),
+        &["x = copy(a);", "y = copy(a);", "let x: u64;", "let y: u64;"],
+    );
+}
+
+#[test]
+fn slots_are_only_shared_by_locals_of_the_same_type() {
+    assert_contains(&coalesced(), &["let f: bool;", "f = move(a) > 0;"]);
+}
+
+#[test]
+fn borrowed_locals_keep_their_slot() {
+    // `z` takes the slot of the parameter, not of the borrowed `y`.
+    assert_contains(
+        &coalesced(),
+        &[
+            "y = move(a);",
+            "r = &y;",
+            "a = *move(r);",
+            "return move(a);",
+        ],
+    );
+}
+
+#[test]
+fn assignments_between_locals_sharing_a_slot_are_removed() {
+    let formatted = coalesced();
+    assert_contains(&formatted, &["i = move(i) + 1;", "return move(v);"]);
+    assert!(!formatted.contains("i = move(i);"), "{}", formatted);
+    assert!(!formatted.contains("let j: u64;"), "{}", formatted);
+    assert!(!formatted.contains("let w: u64;"), "{}", formatted);
+}
+
+#[test]
+fn scripts_are_coalesced() {
+    let mut script = parse_script(
+        "main(a: u64) {
+            let t: (u64, u64);
+            let x: u64;
+            let y: u64;
+            t = (copy(a), move(a));
+            x, y = move(t);
+            assert(move(x) == move(y), 42);
+            return;
+        }",
+    )
+    .unwrap();
+    let functions = coalesce_script(&mut script);
+    assert_eq!(functions.len(), 1);
+    assert_eq!(functions[0].merged, vec![(var("x"), var("a"))]);
+}
+
+#[test]
+fn report_lists_the_shared_slots() {
+    let mut module = parse_module(MODULE).unwrap();
+    let report = coalescing_report(MODULE, &coalesce_module(&mut module));
+    assert_contains(
+        &report,
+        &[
+            "4:5: note: `M.sum` uses 2 local slots instead of 5: `x` reuses the slot of `a`, \
+             `y` reuses the slot of `b`, `z` reuses the slot of `a`",
+        ],
+    );
+}
+
+#[test]
+fn functions_verify_after_coalescing() {
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        verify: true,
+        coalesce_locals: true,
+        ..Compiler::default()
+    };
+    compiler.into_compiled_module(MODULE).unwrap();
+}
+
+#[test]
+fn stdlib_compiles_through_ssa_and_coalescing() {
+    let modules: &[&str] = &[
+        include_str!("../../../stdlib/modules/libra_coin.mvir"),
+        include_str!("../../../stdlib/modules/libra_account.mvir"),
+        include_str!("../../../stdlib/modules/libra_system.mvir"),
+        include_str!("../../../stdlib/modules/validator_config.mvir"),
+    ];
+    for code in modules {
+        let compiler = Compiler {
+            verify: true,
+            ssa: true,
+            coalesce_locals: true,
+            ..Compiler::default()
+        };
+        compiler.into_compiled_module(code).unwrap();
+    }
+}
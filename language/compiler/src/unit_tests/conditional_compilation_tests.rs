@@ -0,0 +1,186 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::Compiler;
+use ir_to_bytecode::{
+    cfg::{configure_module, CfgOptions},
+    compiler::compile_module,
+    formatter::{format_source, FormatOptions},
+    parser::parse_module,
+};
+use libra_types::account_address::AccountAddress;
+use stdlib::stdlib_modules;
+use vm::{access::ModuleAccess, file_format::Bytecode};
+
+const MODULE: &str = "
+module M {
+    #[cfg(test)]
+    import 0x0.LibraAccount;
+
+    #[cfg(test)]
+    resource Fixture { i: u64 }
+
+    #[cfg(feature = \"testnet\")]
+    public limit(): u64 {
+        return 1;
+    }
+
+    #[cfg(not(feature = \"testnet\"))]
+    public limit(): u64 {
+        return 100;
+    }
+
+    #[cfg(all(test, any(feature = \"a\", feature = \"b\")))]
+    public helper() {
+        return;
+    }
+
+    public f(): u64 {
+        return Self.limit();
+    }
+}
+";
+
+fn options(flags: &[&str], features: &[&str]) -> CfgOptions {
+    CfgOptions {
+        flags: flags.iter().map(|&flag| flag.to_string()).collect(),
+        features: features.iter().map(|&feature| feature.to_string()).collect(),
+    }
+}
+
+fn configured(options: &CfgOptions) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let mut module = parse_module(MODULE).unwrap();
+    assert!(configure_module(&mut module, options).is_empty());
+    assert!(module.imports.iter().all(|import| import.cfg.is_none()));
+    assert!(module.structs.iter().all(|s| s.value.cfg.is_none()));
+    assert!(module.functions.iter().all(|(_, f)| f.value.cfg.is_none()));
+    (
+        module
+            .imports
+            .iter()
+            .map(|import| import.alias.to_string())
+            .collect(),
+        module
+            .structs
+            .iter()
+            .map(|s| s.value.name.to_string())
+            .collect(),
+        module
+            .functions
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect(),
+    )
+}
+
+#[test]
+fn predicates_select_declarations() {
+    let (imports, structs, functions) = configured(&CfgOptions::default());
+    assert!(imports.is_empty());
+    assert!(structs.is_empty());
+    assert_eq!(functions, vec!["limit", "f"]);
+
+    let (imports, structs, functions) = configured(&options(&["test"], &[]));
+    assert_eq!(imports, vec!["LibraAccount"]);
+    assert_eq!(structs, vec!["Fixture"]);
+    assert_eq!(functions, vec!["limit", "f"]);
+
+    let (_, _, functions) = configured(&options(&["test"], &["b"]));
+    assert_eq!(functions, vec!["limit", "helper", "f"]);
+
+    let (_, _, functions) = configured(&options(&[], &["a", "testnet"]));
+    assert_eq!(functions, vec!["limit", "f"]);
+}
+
+#[test]
+fn features_select_variants() {
+    for (features, limit) in &[(vec![], 100), (vec!["testnet"], 1)] {
+        let compiler = Compiler {
+            cfg: options(&[], features),
+            ..Compiler::default()
+        };
+        let module = compiler.into_compiled_module(MODULE).unwrap();
+        let limit_def = module
+            .function_defs()
+            .iter()
+            .find(|def| {
+                module
+                    .identifier_at(module.function_handle_at(def.function).name)
+                    .as_str()
+                    == "limit"
+            })
+            .unwrap();
+        assert_eq!(limit_def.code.code[0], Bytecode::LdU64(*limit));
+    }
+}
+
+#[test]
+fn conditional_code_is_excluded_by_default() {
+    let module = parse_module(MODULE).unwrap();
+    assert_eq!(module.functions.len(), 4);
+    let (compiled, _) =
+        compile_module(AccountAddress::default(), module, stdlib_modules()).unwrap();
+    assert_eq!(compiled.function_defs().len(), 2);
+    assert!(compiled.struct_defs().is_empty());
+}
+
+#[test]
+fn duplicates_are_reported_once_resolved() {
+    let source = "
+module M {
+    #[cfg(test)]
+    f() { return; }
+    #[cfg(any(test, feature = \"a\"))]
+    f() { return; }
+}
+";
+    let mut module = parse_module(source).unwrap();
+    assert!(configure_module(&mut module, &options(&[], &["a"])).is_empty());
+
+    let mut module = parse_module(source).unwrap();
+    let errors = configure_module(&mut module, &options(&["test"], &[]));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].to_string(),
+        "duplicate function f for the enabled cfg flags and features"
+    );
+    assert!(errors[0].loc.is_some());
+
+    let compiler = Compiler {
+        cfg: options(&["test"], &[]),
+        ..Compiler::default()
+    };
+    let err = compiler.into_compiled_module(source).unwrap_err();
+    assert!(err.to_string().contains("duplicate function f"), "{}", err);
+
+    assert!(parse_module("module M { f() { return; } f() { return; } }").is_err());
+}
+
+#[test]
+fn invalid_attributes_are_rejected() {
+    for source in &[
+        "module M { #[test] f() { return; } }",
+        "module M { #[inline(always)] f() { return; } }",
+        "module M { #[cfg(version = \"1\")] f() { return; } }",
+        "module M { #[cfg(one_of(test))] f() { return; } }",
+        "module M { #[cfg(not(test, debug))] f() { return; } }",
+        "module M { #[cfg(feature = \"a)] f() { return; } }",
+        "module M { f() { return; } #[cfg(test)] }",
+        "module M { #[cfg(test)] synthetic x: u64; }",
+    ] {
+        assert!(parse_module(source).is_err(), "{}", source);
+    }
+}
+
+#[test]
+fn attributes_are_formatted() {
+    let source = "module M {\n    #[cfg(test)]\n    import 0x0.LibraAccount;\n\n    #[cfg( all(test,not(feature=\"a\")) )]\n    resource T { i: u64 }\n\n    #[cfg(any(test))]\n    f() {\n        return;\n    }\n}\n";
+    let formatted = format_source(source, &FormatOptions::default()).unwrap();
+    assert!(formatted.contains("    #[cfg(test)]\n    import 0x0.LibraAccount;\n"));
+    assert!(formatted.contains("    #[cfg(all(test, not(feature = \"a\")))]\n    resource T"));
+    assert!(formatted.contains("    #[cfg(any(test))]\n    f() {"));
+    assert_eq!(
+        format_source(&formatted, &FormatOptions::default()).unwrap(),
+        formatted
+    );
+}
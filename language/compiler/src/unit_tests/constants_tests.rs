@@ -0,0 +1,188 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{unit_tests::testutils::assert_contains, util::folding_report, Compiler};
+use ir_to_bytecode::{
+    constants::{fold_module, fold_script, BranchKind},
+    formatter::{format_module, FormatOptions},
+    parser::{parse_module, parse_script},
+};
+use move_ir_types::ast::FunctionBody;
+
+const MODULE: &str = "module M {
+    fee(amount: u64): u64 {
+        let rate: u64;
+        let base: u64;
+        let debug: bool;
+        rate = 2 * 5;
+        base = copy(rate) + 100;
+        debug = false;
+        if (copy(debug)) {
+            abort 1;
+        } else {
+            amount = move(amount) + copy(base);
+        }
+        return move(amount) * move(rate);
+    }
+
+    reassigned(n: u64): u64 {
+        let i: u64;
+        let step: u64;
+        i = 0;
+        step = 1;
+        while (copy(i) < copy(n)) {
+            i = move(i) + copy(step);
+        }
+        return move(i);
+    }
+
+    overflow(): u8 {
+        let x: u8;
+        x = 255u8;
+        return move(x) + 1u8;
+    }
+
+    branches(c: bool): u64 {
+        let x: u64;
+        if (move(c)) {
+            x = 1;
+        } else {
+            x = 2;
+        }
+        while (1 > 2) {
+            x = 3;
+        }
+        return move(x);
+    }
+
+    borrowed(): u64 {
+        let x: u64;
+        let r: &mut u64;
+        x = 1;
+        r = &mut x;
+        *move(r) = 2;
+        return move(x);
+    }
+}
+";
+
+fn folded() -> String {
+    let mut module = parse_module(MODULE).unwrap();
+    fold_module(&mut module);
+    format_module(&module, &FormatOptions::default())
+}
+
+#[test]
+fn constants_are_propagated_and_removed() {
+    let formatted = folded();
+    assert_contains(
+        &formatted,
+        &["amount = move(amount) + 110;", "return move(amount) * 10;"],
+    );
+    for removed in &["let rate", "let base", "let debug", "abort 1;"] {
+        assert!(!formatted.contains(removed), "{}", formatted);
+    }
+}
+
+#[test]
+fn reassigned_locals_are_not_propagated() {
+    // `step` is assigned once and propagated, while `i` is assigned twice.
+    assert_contains(
+        &folded(),
+        &["i = 0;", "while (copy(i) < copy(n)) {", "i = move(i) + 1;"],
+    );
+}
+
+#[test]
+fn aborting_operators_are_not_folded() {
+    assert_contains(&folded(), &["return 255u8 + 1u8;"]);
+}
+
+#[test]
+fn branches_on_constants_are_folded() {
+    let mut module = parse_module(MODULE).unwrap();
+    let branches = fold_module(&mut module);
+    let kinds: Vec<(BranchKind, bool)> = branches
+        .iter()
+        .map(|branch| (branch.kind, branch.value))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![(BranchKind::IfElse, false), (BranchKind::While, false)]
+    );
+    let formatted = format_module(&module, &FormatOptions::default());
+    // The `if` on a parameter is kept.
+    assert_contains(&formatted, &["if (move(c)) {", "x = 2;"]);
+    assert!(!formatted.contains("x = 3;"), "{}", formatted);
+}
+
+#[test]
+fn borrowed_locals_are_not_propagated() {
+    assert_contains(&folded(), &["x = 1;", "r = &mut x;", "return move(x);"]);
+}
+
+#[test]
+fn asserts_on_constants_are_removed() {
+    let mut script = parse_script(
+        "main() {
+            let limit: u64;
+            limit = 10;
+            assert(copy(limit) > 5, 42);
+            return;
+        }",
+    )
+    .unwrap();
+    let branches = fold_script(&mut script);
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].kind, BranchKind::If);
+    assert!(!branches[0].value);
+    match &script.main.value.body {
+        FunctionBody::Move { locals, code } => {
+            assert!(locals.is_empty());
+            assert_eq!(code.stmts.len(), 1);
+        }
+        FunctionBody::Native => panic!("main has a body"),
+    }
+}
+
+#[test]
+fn report_notes_the_folded_branches() {
+    let mut module = parse_module(MODULE).unwrap();
+    let report = folding_report(MODULE, &fold_module(&mut module));
+    assert_contains(
+        &report,
+        &[
+            "9:13: note: condition is always false, the `if` is replaced by its `else` branch",
+            "41:16: note: condition is always false, the `while` is removed",
+        ],
+    );
+}
+
+#[test]
+fn functions_verify_after_folding() {
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        verify: true,
+        fold_constants: true,
+        ..Compiler::default()
+    };
+    compiler.into_compiled_module(MODULE).unwrap();
+}
+
+#[test]
+fn stdlib_compiles_with_folded_constants() {
+    let modules: &[&str] = &[
+        include_str!("../../../stdlib/modules/libra_coin.mvir"),
+        include_str!("../../../stdlib/modules/libra_account.mvir"),
+        include_str!("../../../stdlib/modules/libra_system.mvir"),
+        include_str!("../../../stdlib/modules/validator_config.mvir"),
+    ];
+    for code in modules {
+        let compiler = Compiler {
+            verify: true,
+            fold_constants: true,
+            ..Compiler::default()
+        };
+        compiler.into_compiled_module(code).unwrap();
+    }
+}
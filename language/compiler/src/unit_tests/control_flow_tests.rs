@@ -0,0 +1,236 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::{
+    control_flow::{ControlFlowGraph, ControlFlowGraphs, GraphFormat, Terminator},
+    formatter::format_cmd,
+    parser::{parse_module, parse_script},
+};
+use move_ir_types::ast::FunctionBody;
+
+fn graph(source: &str) -> ControlFlowGraph {
+    let script = parse_script(source).unwrap();
+    match &script.main.value.body {
+        FunctionBody::Move { code, .. } => ControlFlowGraph::new(code),
+        FunctionBody::Native => panic!("main is not native"),
+    }
+}
+
+fn cmds(graph: &ControlFlowGraph) -> Vec<Vec<String>> {
+    graph
+        .blocks
+        .iter()
+        .map(|block| {
+            block
+                .cmds
+                .iter()
+                .map(|cmd| format_cmd(&cmd.value))
+                .collect()
+        })
+        .collect()
+}
+
+fn successors(graph: &ControlFlowGraph) -> Vec<Vec<usize>> {
+    graph
+        .blocks
+        .iter()
+        .map(|block| block.successors())
+        .collect()
+}
+
+#[test]
+fn if_else_blocks() {
+    let graph = graph(
+        "main(b: bool) {
+    let x: u64;
+    x = 0;
+    if (copy(b)) {
+        x = 1;
+    } else {
+        abort 2;
+    }
+    if (move(b)) {
+        x = 3;
+    }
+    return;
+}
+",
+    );
+    assert_eq!(
+        cmds(&graph),
+        vec![
+            vec!["x = 0;"],
+            vec!["x = 1;"],
+            vec!["abort 2;"],
+            vec!["x = 3;"],
+            vec![],
+            vec!["return;"],
+        ]
+    );
+    assert_eq!(
+        successors(&graph),
+        vec![vec![1, 2], vec![3, 4], vec![], vec![5], vec![5], vec![]]
+    );
+    assert_eq!(graph.blocks[2].terminator, Terminator::Abort);
+    assert_eq!(graph.blocks[5].terminator, Terminator::Return);
+}
+
+const LOOPS: &str = "main(n: u64) {
+    let i: u64;
+    let j: u64;
+    i = 0;
+    while (copy(i) < copy(n)) {
+        i = move(i) + 1;
+        if (copy(i) == 5) {
+            continue;
+        }
+        j = 0;
+    }
+    j = loop {
+        if (copy(i) > 10) {
+            break copy(i);
+        }
+        i = move(i) * 2;
+    };
+    return;
+    i = 1;
+}
+";
+
+#[test]
+fn loop_blocks() {
+    let graph = graph(LOOPS);
+    assert_eq!(
+        cmds(&graph),
+        vec![
+            vec!["i = 0;"],
+            vec![],
+            vec!["i = move(i) + 1;"],
+            vec!["continue;"],
+            vec!["j = 0;"],
+            vec![],
+            vec![],
+            vec!["break copy(i);"],
+            vec!["i = move(i) * 2;"],
+            vec!["return;"],
+        ]
+    );
+    assert_eq!(
+        successors(&graph),
+        vec![
+            vec![1],
+            vec![2, 5],
+            vec![3, 4],
+            vec![1],
+            vec![1],
+            vec![6],
+            vec![7, 8],
+            vec![9],
+            vec![6],
+            vec![],
+        ]
+    );
+}
+
+#[test]
+fn predecessors_and_order() {
+    let graph = graph(LOOPS);
+    assert_eq!(graph.predecessors(1), vec![0, 3, 4]);
+    assert_eq!(
+        graph.predecessors(ControlFlowGraph::ENTRY),
+        Vec::<usize>::new()
+    );
+    let preds = graph.all_predecessors();
+    assert_eq!(preds.len(), graph.blocks.len());
+    for (id, preds) in preds.iter().enumerate() {
+        assert_eq!(preds, &graph.predecessors(id));
+    }
+    assert_eq!(
+        graph.reverse_postorder(),
+        vec![0, 1, 5, 6, 8, 7, 9, 2, 4, 3]
+    );
+}
+
+#[test]
+fn loops_are_found() {
+    let graph = graph(LOOPS);
+    assert_eq!(graph.back_edges(), vec![(3, 1), (4, 1), (8, 6)]);
+    assert_eq!(graph.loop_headers(), vec![1, 6].into_iter().collect());
+    assert_eq!(graph.loop_blocks(1), vec![1, 2, 3, 4].into_iter().collect());
+    assert_eq!(graph.loop_blocks(6), vec![6, 8].into_iter().collect());
+
+    let break_ = &graph.blocks[7].cmds[0];
+    assert_eq!(graph.block_of(break_.span), Some(7));
+    match &graph.blocks[1].terminator {
+        Terminator::Branch { cond, .. } => assert_eq!(graph.block_of(cond.span), Some(1)),
+        terminator => panic!("unexpected terminator {:?}", terminator),
+    }
+}
+
+#[test]
+fn nested_loops() {
+    let graph = graph(
+        "main(n: u64) {
+    let i: u64;
+    i = 0;
+    while (copy(i) < copy(n)) {
+        loop {
+            i = move(i) + 1;
+            if (copy(i) > 3) {
+                break;
+            }
+        }
+    }
+    return;
+}
+",
+    );
+    let headers: Vec<usize> = graph.loop_headers().into_iter().collect();
+    assert_eq!(headers.len(), 2);
+    let (outer, inner) = (headers[0], headers[1]);
+    assert!(graph
+        .loop_blocks(outer)
+        .is_superset(&graph.loop_blocks(inner)));
+    assert!(!graph.loop_blocks(inner).contains(&outer));
+}
+
+#[test]
+fn graphs_are_exported() {
+    let module = parse_module(
+        "module M {
+    native public hash(data: bytearray): bytearray;
+
+    public f(b: bool): u64 {
+        if (move(b)) {
+            return 1;
+        }
+        return 2;
+    }
+}
+",
+    )
+    .unwrap();
+    let graphs = ControlFlowGraphs::for_module(&module);
+    assert_eq!(graphs.functions.len(), 1);
+
+    let json: serde_json::Value = serde_json::from_slice(&graphs.to_json().unwrap()).unwrap();
+    let f = &json["functions"][0];
+    assert_eq!(f["module"], "M");
+    assert_eq!(f["name"], "f");
+    assert_eq!(f["blocks"][0]["terminator"]["kind"], "branch");
+    assert_eq!(f["blocks"][0]["terminator"]["cond"], "move(b)");
+    assert_eq!(f["blocks"][0]["terminator"]["if_true"], 1);
+    assert_eq!(f["blocks"][1]["cmds"][0]["text"], "return 1;");
+
+    let dot = graphs.to_dot();
+    assert!(dot.starts_with("digraph cfg {\n    subgraph cluster_0 {\n        label=\"M.f\";\n"));
+    assert!(dot.contains("        \"M.f:0\" [shape=box, label=\"0:\\lif move(b)\\l\"];\n"));
+    assert!(dot.contains("        \"M.f:0\" -> \"M.f:2\" [label=\"false\"];\n"));
+}
+
+#[test]
+fn graph_formats_are_parsed() {
+    assert_eq!("json".parse::<GraphFormat>().unwrap(), GraphFormat::Json);
+    assert_eq!("dot".parse::<GraphFormat>().unwrap(), GraphFormat::Dot);
+    assert!("svg".parse::<GraphFormat>().is_err());
+}
@@ -0,0 +1,126 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::daemon::{Daemon, COMPILATION_FAILED, INVALID_PARAMS, METHOD_NOT_FOUND, PARSE_ERROR};
+use serde_json::{json, Value};
+
+const MODULE: &str = "module M {
+    public f(): u64 {
+        return 42;
+    }
+}
+";
+
+fn call(daemon: &mut Daemon, method: &str, params: Value) -> Value {
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let response = daemon.handle(&request.to_string()).unwrap();
+    serde_json::from_str(&response).unwrap()
+}
+
+#[test]
+fn modules_are_parsed_checked_and_compiled() {
+    let mut daemon = Daemon::new();
+    let params = json!({ "source": MODULE, "address": "0x1" });
+    assert_eq!(
+        call(&mut daemon, "parse", params.clone())["result"]["kind"],
+        "module"
+    );
+    assert_eq!(
+        call(&mut daemon, "check", params.clone())["result"]["diagnostics"],
+        json!([])
+    );
+    let response = call(&mut daemon, "compile", params.clone());
+    assert!(response["result"]["bytecode"]
+        .as_str()
+        .unwrap()
+        .starts_with("a11ceb0b"));
+    // A second request is answered from the cache.
+    assert_eq!(call(&mut daemon, "compile", params), response);
+}
+
+#[test]
+fn errors_are_located_diagnostics() {
+    let mut daemon = Daemon::new();
+    let source = "main() {\n    let x: u64;\n    x = true;\n    return;\n}\n";
+    let response = call(&mut daemon, "check", json!({ "source": source }));
+    assert_eq!(response["result"]["diagnostics"][0]["line"], 3);
+
+    let response = call(&mut daemon, "compile", json!({ "source": "module M {" }));
+    assert_eq!(response["error"]["code"], COMPILATION_FAILED);
+    assert_eq!(response["error"]["data"][0]["line"], 1);
+    assert_eq!(response["error"]["data"][0]["fix"], Value::Null);
+
+    let source = "main(a: u64) {\n    assert(0 < copy(a) < 10, 42);\n    return;\n}\n";
+    let response = call(&mut daemon, "check", json!({ "source": source }));
+    assert_eq!(
+        response["result"]["diagnostics"][0]["fix"],
+        json!({
+            "line": 2,
+            "column": 12,
+            "end_line": 2,
+            "end_column": 28,
+            "replacement": "0 < copy(a) && copy(a) < 10",
+        })
+    );
+}
+
+#[test]
+fn scripts_have_an_abi_and_sources_can_be_formatted() {
+    let mut daemon = Daemon::new();
+    let source = "main(  amount: u64) {\n  return;\n}\n";
+    let response = call(
+        &mut daemon,
+        "abi",
+        json!({ "source": source, "name": "pay" }),
+    );
+    assert_eq!(response["result"]["name"], "pay");
+    assert_eq!(response["result"]["arguments"][0]["name"], "amount");
+    let response = call(&mut daemon, "format", json!({ "source": source }));
+    assert_eq!(
+        response["result"]["source"],
+        "main(amount: u64) {\n    return;\n}\n"
+    );
+}
+
+#[test]
+fn invalid_requests_are_rejected() {
+    let mut daemon = Daemon::new();
+    let response: Value = serde_json::from_str(&daemon.handle("{").unwrap()).unwrap();
+    assert_eq!(response["error"]["code"], PARSE_ERROR);
+    assert_eq!(
+        call(&mut daemon, "link", json!({}))["error"]["code"],
+        METHOD_NOT_FOUND
+    );
+    assert_eq!(
+        call(&mut daemon, "parse", json!({ "source": 42 }))["error"]["code"],
+        INVALID_PARAMS
+    );
+    // Notifications have no response.
+    assert!(daemon
+        .handle(r#"{"jsonrpc": "2.0", "method": "parse", "params": {"source": ""}}"#)
+        .is_none());
+}
+
+#[test]
+fn serving_stops_at_shutdown() {
+    let mut daemon = Daemon::new();
+    let input = format!(
+        "{}\n\n{}\n{}\n",
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "parse", "params": { "source": MODULE } }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "parse", "params": { "source": MODULE } }),
+    );
+    let mut output = vec![];
+    daemon.serve(input.as_bytes(), &mut output).unwrap();
+    assert!(daemon.is_stopped());
+    let responses: Vec<Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(responses.len(), 2);
+    assert_eq!(
+        responses[1],
+        json!({ "jsonrpc": "2.0", "id": 2, "result": null })
+    );
+}
@@ -0,0 +1,138 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::{
+    dataflow::{forward, Dataflow, Lattice, Transfer},
+    parser::parse_script,
+};
+use move_ir_types::ast::{Cmd, Cmd_, Exp, FunctionBody, LValue, LValue_};
+use std::collections::BTreeSet;
+
+/// The locals that may have been assigned, and the number of conditions evaluated, capped to
+/// keep the lattice finite.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Assigned {
+    vars: BTreeSet<String>,
+    conditions: usize,
+}
+
+impl Lattice for Assigned {
+    fn join(&mut self, other: &Self) {
+        self.vars.extend(other.vars.iter().cloned());
+        self.conditions = std::cmp::max(self.conditions, other.conditions);
+    }
+}
+
+struct AssignedTransfer;
+
+fn assign(lvalues: &[LValue], state: &mut Assigned) {
+    for lvalue in lvalues {
+        if let LValue_::Var(var) = &lvalue.value {
+            state.vars.insert(var.value.to_string());
+        }
+    }
+}
+
+impl Transfer for AssignedTransfer {
+    type State = Assigned;
+
+    fn cmd(&self, cmd: &Cmd, state: &mut Assigned) {
+        if let Cmd_::Assign(lvalues, _) = &cmd.value {
+            assign(lvalues, state);
+        }
+    }
+
+    fn cond(&self, _cond: &Exp, state: &mut Assigned) {
+        state.conditions = std::cmp::min(state.conditions + 1, 3);
+    }
+
+    fn break_value(&self, results: &[LValue], _value: &Exp, state: &mut Assigned) {
+        assign(results, state);
+    }
+}
+
+fn analyze(source: &str) -> Dataflow<Assigned> {
+    let script = parse_script(source).unwrap();
+    match &script.main.value.body {
+        FunctionBody::Move { code, .. } => forward(&AssignedTransfer, code, Assigned::default()),
+        FunctionBody::Native => panic!("main is not native"),
+    }
+}
+
+fn vars(state: &Assigned) -> Vec<&str> {
+    state.vars.iter().map(String::as_str).collect()
+}
+
+#[test]
+fn branches_are_joined() {
+    let dataflow = analyze(
+        "main(b: bool) {
+    let x: u64;
+    let y: u64;
+    if (copy(b)) {
+        x = 1;
+    } else {
+        y = 2;
+        return;
+    }
+    return;
+}
+",
+    );
+    let returns = dataflow.returns.unwrap();
+    assert_eq!(vars(&returns), vec!["x", "y"]);
+    assert_eq!(returns.conditions, 1);
+    assert_eq!(dataflow.exit, None);
+}
+
+#[test]
+fn loops_reach_a_fixed_point() {
+    let dataflow = analyze(
+        "main(n: u64) {
+    let i: u64;
+    let j: u64;
+    let k: u64;
+    i = 0;
+    while (copy(i) < copy(n)) {
+        if (copy(i) == 5) {
+            j = 1;
+            continue;
+        }
+        i = move(i) + 1;
+    }
+    k = loop {
+        break 3;
+    };
+    return;
+}
+",
+    );
+    let returns = dataflow.returns.unwrap();
+    assert_eq!(vars(&returns), vec!["i", "j", "k"]);
+    // The conditions of the `while` and of the `if` are counted on every iteration.
+    assert_eq!(returns.conditions, 3);
+}
+
+#[test]
+fn unreachable_code_has_no_state() {
+    let source = "main() {
+    let x: u64;
+    loop {
+        break;
+        x = 1;
+    }
+    abort 1;
+    x = 2;
+}
+";
+    let dataflow = analyze(source);
+    let assignments: Vec<usize> = dataflow
+        .before
+        .keys()
+        .map(|loc| loc.start().to_usize())
+        .filter(|start| source[*start..].starts_with("x = "))
+        .collect();
+    assert!(assignments.is_empty());
+    assert_eq!(dataflow.returns, None);
+    assert_eq!(dataflow.exit, None);
+}
@@ -0,0 +1,150 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::util::dead_store_report;
+use ir_to_bytecode::{
+    dead_stores::{module_dead_stores, script_dead_stores},
+    parser::parse_script,
+};
+
+fn report(code: &str) -> String {
+    let script = parse_script(code).unwrap();
+    dead_store_report(code, &script_dead_stores(&script))
+}
+
+#[test]
+fn overwritten_store() {
+    let code = "main() {
+    let x: u64;
+    x = 1;
+    x = 2;
+    assert(move(x) == 2, 42);
+    return;
+}";
+    assert_eq!(
+        report(code),
+        "3:5: warning: value assigned to `x` is overwritten before being read
+4:5: note: overwritten here"
+    );
+}
+
+#[test]
+fn never_read_store() {
+    let code = "main() {
+    let x: u64;
+    let y: u64;
+    x = 1;
+    y = copy(x) + 1;
+    x = move(y);
+    return;
+}";
+    assert_eq!(
+        report(code),
+        "6:5: warning: value assigned to `x` is never read"
+    );
+}
+
+#[test]
+fn store_read_on_one_branch_is_live() {
+    let code = "main() {
+    let x: u64;
+    let b: bool;
+    b = true;
+    x = 1;
+    if (move(b)) {
+        x = 2;
+    }
+    assert(move(x) > 0, 42);
+    return;
+}";
+    assert_eq!(report(code), "");
+}
+
+#[test]
+fn store_overwritten_on_every_branch() {
+    let code = "main() {
+    let x: u64;
+    let b: bool;
+    b = true;
+    x = 1;
+    if (move(b)) {
+        x = 2;
+    } else {
+        x = 3;
+    }
+    assert(move(x) > 0, 42);
+    return;
+}";
+    // The branches overwrite the value at different places, so there is no single note.
+    assert_eq!(
+        report(code),
+        "5:5: warning: value assigned to `x` is never read"
+    );
+}
+
+#[test]
+fn store_read_by_next_loop_iteration_is_live() {
+    let code = "main() {
+    let i: u64;
+    let sum: u64;
+    i = 0;
+    sum = 0;
+    while (copy(i) < 10) {
+        sum = copy(sum) + copy(i);
+        i = move(i) + 1;
+    }
+    assert(move(sum) > 0, 42);
+    return;
+}";
+    assert_eq!(report(code), "");
+}
+
+#[test]
+fn store_in_loop_never_read() {
+    let code = "main() {
+    let i: u64;
+    let last: u64;
+    i = 0;
+    loop {
+        last = copy(i);
+        i = move(i) + 1;
+        if (copy(i) > 10) {
+            break;
+        }
+    }
+    return;
+}";
+    assert_eq!(
+        report(code),
+        "6:9: warning: value assigned to `last` is never read"
+    );
+}
+
+#[test]
+fn borrowed_and_unpacked_locals_are_not_reported() {
+    let code = "main() {
+    let x: u64;
+    let r: &u64;
+    x = 1;
+    r = &x;
+    x = 2;
+    _ = move(r);
+    return;
+}";
+    assert_eq!(report(code), "");
+}
+
+#[test]
+fn stdlib_dead_stores() {
+    let stores: Vec<String> = stdlib::stdlib::module_defs()
+        .iter()
+        .flat_map(|module| module_dead_stores(module))
+        .map(|store| store.to_string())
+        .collect();
+    // The maximum transaction time is computed but not used yet, see the TODO in
+    // libra_transaction_timeout.mvir.
+    assert_eq!(
+        stores,
+        vec!["value assigned to `max_txn_time` is never read"]
+    );
+}
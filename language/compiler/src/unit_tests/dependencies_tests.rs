@@ -0,0 +1,76 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::{
+    compiler::{compile_module, compile_script},
+    dependencies::{DependencyManifest, ModuleDependency},
+    parser::{parse_module, parse_script},
+};
+use libra_types::account_address::AccountAddress;
+use stdlib::stdlib_modules;
+
+#[test]
+fn script_dependencies_are_read_off_its_handles() {
+    let code = "pragma language 2;
+import 0x0.LibraAccount;
+import 0x0.LibraCoin;
+import 0x0.Vector;
+main(payee: address, amount: u64) {
+    let coin: LibraCoin.T;
+    coin = LibraAccount.withdraw_from_sender(move(amount));
+    LibraAccount.deposit(move(payee), move(coin));
+    return;
+}";
+    let script = parse_script(code).unwrap();
+    let language_version = script.language_version;
+    let (compiled, _) =
+        compile_script(AccountAddress::default(), script, stdlib_modules()).unwrap();
+    let manifest = DependencyManifest::for_script(&compiled, language_version);
+    let address = format!("0x{:x}", AccountAddress::default());
+    assert_eq!(manifest.language_version, Some(2));
+    assert_eq!(manifest.addresses, vec![address.clone()]);
+    assert_eq!(
+        manifest.modules,
+        vec![
+            ModuleDependency {
+                id: format!("{}.LibraAccount", address),
+                address: address.clone(),
+                name: "LibraAccount".to_string(),
+                structs: vec![],
+                functions: vec!["withdraw_from_sender".to_string(), "deposit".to_string()],
+            },
+            ModuleDependency {
+                id: format!("{}.LibraCoin", address),
+                address: address.clone(),
+                name: "LibraCoin".to_string(),
+                structs: vec!["T".to_string()],
+                functions: vec![],
+            },
+            // Unused imports remain in the compiled script.
+            ModuleDependency {
+                id: format!("{}.Vector", address),
+                address: address.clone(),
+                name: "Vector".to_string(),
+                structs: vec![],
+                functions: vec![],
+            },
+        ]
+    );
+}
+
+#[test]
+fn module_dependencies_leave_out_the_module_itself() {
+    let code = "module M {
+    resource R { x: u64 }
+    public f(): Self.R {
+        return R { x: 0 };
+    }
+}";
+    let module = parse_module(code).unwrap();
+    let (compiled, _) =
+        compile_module(AccountAddress::default(), module, stdlib_modules()).unwrap();
+    let manifest = DependencyManifest::for_module(&compiled, None);
+    assert_eq!(manifest.language_version, None);
+    assert!(manifest.addresses.is_empty());
+    assert!(manifest.modules.is_empty());
+}
@@ -0,0 +1,64 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::Compiler;
+use libra_types::account_config;
+use stdlib::{build_stdlib, transaction_scripts};
+
+// Every build below creates fresh hash maps with their own random seeds, so any dependency on
+// hash map iteration order shows up as a difference between two builds.
+
+fn stdlib_bytes() -> (Vec<Vec<u8>>, Vec<String>) {
+    let (modules, source_maps) = build_stdlib(account_config::core_code_address());
+    let modules = modules
+        .into_iter()
+        .map(|module| {
+            let mut bytes = vec![];
+            module.as_inner().serialize(&mut bytes).unwrap();
+            bytes
+        })
+        .collect();
+    let source_maps = source_maps
+        .iter()
+        .map(|source_map| serde_json::to_string(source_map).unwrap())
+        .collect();
+    (modules, source_maps)
+}
+
+#[test]
+fn stdlib_compilation_is_deterministic() {
+    let (modules, source_maps) = stdlib_bytes();
+    for _ in 0..2 {
+        let (other_modules, other_source_maps) = stdlib_bytes();
+        assert!(
+            modules == other_modules,
+            "stdlib modules differ between builds"
+        );
+        assert!(
+            source_maps == other_source_maps,
+            "stdlib source maps differ between builds"
+        );
+    }
+}
+
+#[test]
+fn script_compilation_is_deterministic() {
+    for code in &[
+        transaction_scripts::add_validator(),
+        transaction_scripts::peer_to_peer(),
+        transaction_scripts::peer_to_peer_with_metadata(),
+        transaction_scripts::create_account(),
+        transaction_scripts::register_validator(),
+        transaction_scripts::remove_validator(),
+        transaction_scripts::rotate_consensus_pubkey(),
+        transaction_scripts::rotate_key(),
+        transaction_scripts::mint(),
+        transaction_scripts::block_prologue(),
+    ] {
+        let blob = Compiler::default().into_script_blob(code).unwrap();
+        for _ in 0..2 {
+            let other = Compiler::default().into_script_blob(code).unwrap();
+            assert!(blob == other, "script compiled differently:\n{}", code);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::{
+    diagnostics::{parse_address, Diagnostic, DiagnosticFix},
+    parser::try_parse_script_or_module,
+};
+use libra_types::account_address::AccountAddress;
+
+#[test]
+fn syntax_errors_are_located_with_their_fix() {
+    let source = "main(a: u64) {\n    assert(0 < copy(a) < 10, 42);\n    return;\n}\n";
+    let err = try_parse_script_or_module(source).unwrap_err();
+    let diagnostic = Diagnostic::from_syntax_error(source, err);
+    assert_eq!(diagnostic.line, Some(2));
+    assert_eq!(
+        diagnostic.fix,
+        Some(DiagnosticFix {
+            line: 2,
+            column: 12,
+            end_line: 2,
+            end_column: 28,
+            replacement: "0 < copy(a) && copy(a) < 10".to_string(),
+        })
+    );
+    let json = serde_json::to_value(Diagnostic::unlocated("oops".to_string())).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({ "line": null, "column": null, "message": "oops", "fix": null })
+    );
+}
+
+#[test]
+fn addresses_are_literals_or_hex_digits() {
+    let address = AccountAddress::from_hex_literal("0x1").unwrap();
+    assert_eq!(parse_address("0x1").unwrap(), address);
+    assert_eq!(parse_address(&format!("{:x}", address)).unwrap(), address);
+    let err = parse_address("xyz").unwrap_err();
+    assert!(err.message.starts_with("Invalid address xyz"));
+    assert_eq!(err.line, None);
+}
@@ -0,0 +1,100 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::{
+    annotations::Annotation,
+    directives::{
+        is_new_transaction, parse_test_directives, Argument, Stage, TestDirective, TestDirectives,
+    },
+};
+use libra_types::transaction::TransactionArgument;
+
+#[test]
+fn directives_ignore_whitespace() {
+    for s in &[
+        "//! sender: alice",
+        "//! sender :alice",
+        "//! sender\t:\tAlice",
+        "//!\nsender\n:\nalice",
+    ] {
+        assert_eq!(
+            s.parse::<TestDirective>().unwrap(),
+            TestDirective::Sender("alice".to_string())
+        );
+    }
+    assert_eq!(
+        "//! args: 1, 0x12, {{bob}},".parse::<TestDirective>().unwrap(),
+        TestDirective::Arguments(vec![
+            Argument::SelfContained(TransactionArgument::U64(1)),
+            "0x12".parse().unwrap(),
+            Argument::AddressOf("bob".to_string()),
+        ])
+    );
+    assert_eq!(
+        "//! no-run: compiler, runtime".parse::<TestDirective>().unwrap(),
+        TestDirective::DisableStages(vec![Stage::Compiler, Stage::Runtime])
+    );
+}
+
+#[test]
+fn malformed_directives_are_rejected() {
+    for s in &[
+        "//!",
+        "//! garbage",
+        "//! sender:",
+        "//! args: bob",
+        "//! no-run: linker",
+        "//! max-gas: 123, 45",
+        "//! sequence-number: abc",
+    ] {
+        s.parse::<TestDirective>().unwrap_err();
+    }
+    assert!(TestDirective::try_parse("// sender: alice").unwrap().is_none());
+}
+
+#[test]
+fn new_transactions_are_recognized() {
+    assert!(is_new_transaction("//! new-transaction"));
+    assert!(is_new_transaction("//!new-transaction "));
+    assert!(!is_new_transaction("//! new transaction"));
+}
+
+#[test]
+fn transaction_headers_are_typed() {
+    let directives = parse_test_directives(
+        "//! sender: bob
+//! max-gas: 1000
+//! sequence-number: 3
+//! expiration-time: 60
+//! no-run: verifier
+//! abort 7
+main() {
+    abort 7;
+}
+",
+    )
+    .unwrap();
+    assert_eq!(
+        directives,
+        TestDirectives {
+            disabled_stages: vec![Stage::Verifier].into_iter().collect(),
+            sender: Some("bob".to_string()),
+            arguments: None,
+            max_gas: Some(1000),
+            sequence_number: Some(3),
+            expiration_time: Some(60),
+            expected: vec![Annotation::Abort(7)],
+        }
+    );
+    assert!(directives.is_stage_disabled(Stage::Verifier));
+}
+
+#[test]
+fn repeated_directives_are_rejected_with_their_line() {
+    let err = parse_test_directives("//! max-gas: 1\n\n//! max-gas: 2\n").unwrap_err();
+    assert_eq!(err.line, 3);
+    assert_eq!(err.message, "max gas amount already set");
+
+    let err = parse_test_directives("//! no-run: runtime, runtime\n").unwrap_err();
+    assert_eq!(err.line, 1);
+}
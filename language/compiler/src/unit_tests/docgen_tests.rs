@@ -0,0 +1,94 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::unit_tests::testutils::STDLIB_SOURCES;
+use ir_to_bytecode::{docgen::module_documentation, parser::parse_module};
+
+#[test]
+fn documents_structs_and_functions() {
+    let source = "// Coins with a balance.
+module Coin {
+    import 0x0.Vector;
+
+    // A coin.
+    resource T {
+        // How much the coin is worth.
+        value: u64,
+        owner: address, // Who minted it.
+        invariant value > 0,
+    }
+
+    native struct Handle<K: resource>;
+
+    // Splits a coin in two.
+    // Fails if `amount` is larger than the value of the coin.
+    public split(coin: Self.T, amount: u64): Self.T * Self.T
+        requires amount <= coin.value
+        ensures true
+    {
+        abort 0;
+    }
+
+    native value(coin: &Self.T): u64 acquires T;
+}
+";
+    let expected = "# Module `Coin`
+
+Coins with a balance.
+
+## Structs
+
+### resource `T`
+
+A coin.
+
+| Field | Type | Description |
+|-------|------|-------------|
+| `value` | `u64` | How much the coin is worth. |
+| `owner` | `address` | Who minted it. |
+
+Invariants:
+
+- `invariant value > 0`
+
+### struct `Handle<K: resource>`
+
+Native struct.
+
+## Functions
+
+### Function `split`
+
+```
+public split(coin: Self.T, amount: u64): Self.T * Self.T
+```
+
+Splits a coin in two.
+Fails if `amount` is larger than the value of the coin.
+
+Specification:
+
+- `requires amount <= coin.value`
+- `ensures true`
+
+### Function `value`
+
+```
+native value(coin: &Self.T): u64
+```
+
+Acquires: `T`
+";
+    assert_eq!(module_documentation(source).unwrap(), expected);
+}
+
+#[test]
+fn documents_every_stdlib_function() {
+    for source in STDLIB_SOURCES {
+        let module = parse_module(source).unwrap();
+        let doc = module_documentation(source).unwrap();
+        for (name, _) in &module.functions {
+            assert!(doc.contains(&format!("### Function `{}`", name)));
+        }
+    }
+}
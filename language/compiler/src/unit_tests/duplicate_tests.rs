@@ -0,0 +1,60 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::parser::{parse_module, try_parse_script_or_module};
+
+// Returns the message of the syntax error of `source` and the text it points at.
+fn syntax_error(source: &str) -> (String, &str) {
+    let error = try_parse_script_or_module(source).unwrap_err();
+    let loc = error.loc.unwrap();
+    (
+        error.message,
+        &source[loc.start().0 as usize..loc.end().0 as usize],
+    )
+}
+
+#[test]
+fn duplicate_struct_fields() {
+    let source = "module M { struct S { f: u64, g: u64, f: bool } }";
+    let (message, text) = syntax_error(source);
+    assert_eq!(message, "Duplicate field f");
+    assert_eq!(text, "f");
+    assert!(parse_module(source).is_err());
+    assert!(parse_module("module M { struct S { f: u64, g: bool } }").is_ok());
+}
+
+#[test]
+fn duplicate_pack_fields() {
+    let (message, _) =
+        syntax_error("module M { struct S { f: u64 } new(): Self.S { return S { f: 1, f: 2 }; } }");
+    assert_eq!(message, "Duplicate field f");
+}
+
+#[test]
+fn duplicate_structs() {
+    let source = "module M { struct T { b: bool } resource T { x: u64 } }";
+    let (message, text) = syntax_error(source);
+    assert_eq!(message, "Duplicate struct T");
+    assert_eq!(text, "resource T { x: u64 }");
+}
+
+#[test]
+fn duplicate_functions() {
+    let source = "module M { f() { return; } g() { return; } public f() { return; } }";
+    let (message, text) = syntax_error(source);
+    assert_eq!(message, "Duplicate function f");
+    assert_eq!(text, "public f() { return; }");
+}
+
+#[test]
+fn conflicting_import_aliases() {
+    let source = "module M { import 0x0.LibraCoin as C; import 0x0.LibraAccount as C; }";
+    let (message, text) = syntax_error(source);
+    assert_eq!(message, "Duplicate import alias C");
+    assert_eq!(text, "import 0x0.LibraAccount as C;");
+
+    let (message, _) =
+        syntax_error("module M { import 0x0.LibraCoin; import Transaction.LibraCoin; }");
+    assert_eq!(message, "Duplicate import alias LibraCoin");
+    assert!(parse_module("module M { import 0x0.LibraCoin; import 0x0.LibraCoin as C; }").is_ok());
+}
@@ -2,12 +2,20 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::unit_tests::testutils::{
-    compile_module_string, compile_script_string, compile_script_string_and_assert_error,
-    count_locals,
+    compile_module_string, compile_module_string_with_stdlib, compile_script_string,
+    compile_script_string_and_assert_error, compile_script_string_with_stdlib, count_locals,
 };
+use codespan::{ByteIndex, Span};
+use ir_to_bytecode::{
+    errors::Fix,
+    parser::{parse_module, try_parse_script_or_module},
+};
+use libra_types::byte_array::ByteArray;
+use move_ir_types::ast::{Cmd_, CopyableVal_, Exp_, Function, FunctionBody, Statement};
+use std::sync::Arc;
 use vm::{
     access::{ModuleAccess, ScriptAccess},
-    file_format::Bytecode::*,
+    file_format::{Bytecode::*, SignatureToken},
 };
 
 #[test]
@@ -239,3 +247,368 @@ fn compile_borrow_field() {
     let compiled_module_res = compile_module_string(&code);
     let _compiled_module = compiled_module_res.unwrap();
 }
+
+#[test]
+fn compile_bit_builtins() {
+    let code = String::from(
+        "
+        main() {
+            let x: u64;
+            x = rotl(1u64, 3u8);
+            x = rotr(move(x), 3u8);
+            x = count_ones(move(x)) + count_ones(rotl(1u8, 9u8));
+            _ = count_ones(move(x));
+            _ = rotr(1u128, 200u8);
+            return;
+        }
+        ",
+    );
+    let compiled_script = compile_script_string(&code).unwrap();
+    // The builtins share temporaries by type: a u64, two u8s and a u128.
+    assert_eq!(count_locals(&compiled_script), 5);
+    assert_eq!(instr_count!(compiled_script, CastU64), 3);
+}
+
+#[test]
+fn compile_bit_builtins_of_non_integers() {
+    let code = String::from(
+        "
+        main() {
+            _ = count_ones(true);
+            return;
+        }
+        ",
+    );
+    assert!(compile_script_string(&code).is_err());
+}
+
+#[test]
+fn compile_overflow_builtins() {
+    let code = String::from(
+        "
+        main() {
+            let x: u64;
+            let ok: bool;
+            x, ok = checked_add(1, 2);
+            x, ok = checked_sub(move(x), 3);
+            x = saturating_add(move(x), 4) + saturating_sub(5, 6);
+            _ = saturating_add(1u8, 2u8);
+            return;
+        }
+        ",
+    );
+    let compiled_script = compile_script_string(&code).unwrap();
+    // Two locals, and the builtins share four temporaries of each type.
+    assert_eq!(count_locals(&compiled_script), 10);
+    assert_eq!(instr_count!(compiled_script, Mul), 5);
+}
+
+#[test]
+fn compile_to_bytes() {
+    let code = String::from(
+        "
+        import 0x0.LCS as L;
+        main() {
+            let x: u64;
+            x = 1;
+            _ = to_bytes<u64>(&x);
+            _ = L.serialize<u64>(&x);
+            return;
+        }
+        ",
+    );
+    let compiled_script = compile_script_string_with_stdlib(&code).unwrap();
+    // The builtin calls the function of the imported module, under its alias.
+    assert_eq!(compiled_script.function_handles().len(), 2);
+    assert_eq!(instr_count!(compiled_script, Call(_, _)), 2);
+}
+
+#[test]
+fn compile_to_bytes_without_lcs() {
+    let code = String::from(
+        "
+        main() {
+            let x: u64;
+            x = 1;
+            _ = to_bytes<u64>(&x);
+            return;
+        }
+        ",
+    );
+    assert!(compile_script_string(&code).is_err());
+    assert!(compile_script_string_with_stdlib(&code).is_ok());
+}
+
+#[test]
+fn compile_to_bytes_of_structs() {
+    let code = String::from(
+        "
+        module M {
+            import 0x0.Vector;
+            struct Pair { a: u8, b: bool }
+            public bytes(): bytearray * bytearray {
+                let p: Self.Pair;
+                let v: Vector.T<Self.Pair>;
+                p = Pair { a: 7u8, b: true };
+                v = Vector.empty<Self.Pair>();
+                Vector.push_back<Self.Pair>(&mut v, Pair { a: 9u8, b: false });
+                return to_bytes<Self.Pair>(&p), to_bytes<Vector.T<Self.Pair>>(&v);
+            }
+        }
+        ",
+    );
+    let compiled_module = compile_module_string_with_stdlib(&code).unwrap();
+    let serialize = compiled_module
+        .function_handles()
+        .iter()
+        .position(|handle| compiled_module.identifier_at(handle.name).as_str() == "serialize")
+        .unwrap();
+    let type_actuals: Vec<_> = compiled_module
+        .function_defs()
+        .iter()
+        .flat_map(|def| def.code.code.iter())
+        .filter_map(|instr| match instr {
+            Call(idx, type_actuals) if idx.0 as usize == serialize => {
+                Some(&compiled_module.locals_signature_at(*type_actuals).0)
+            }
+            _ => None,
+        })
+        .collect();
+    // The native is called once per value, with the serialized type as its type actual.
+    assert_eq!(type_actuals.len(), 2);
+    let pair = match type_actuals[0].as_slice() {
+        [SignatureToken::Struct(pair, tys)] if tys.is_empty() => *pair,
+        other => panic!("unexpected type actuals {:?}", other),
+    };
+    match type_actuals[1].as_slice() {
+        [SignatureToken::Struct(vector, tys)] => {
+            assert_ne!(*vector, pair);
+            assert_eq!(tys, &vec![SignatureToken::Struct(pair, vec![])]);
+        }
+        other => panic!("unexpected type actuals {:?}", other),
+    }
+}
+
+#[test]
+fn compile_hash_builtins() {
+    let code = String::from(
+        "
+        import 0x0.Hash as H;
+        main() {
+            _ = sha3_256(h\"616263\");
+            _ = sha2_256(h\"616263\");
+            _ = H.sha3_256(h\"616263\");
+            return;
+        }
+        ",
+    );
+    let compiled_script = compile_script_string_with_stdlib(&code).unwrap();
+    // The builtins call the natives of the imported module, under its alias.
+    assert_eq!(compiled_script.function_handles().len(), 3);
+    assert_eq!(instr_count!(compiled_script, Call(_, _)), 3);
+    assert!(compile_script_string(&code.replace("import 0x0.Hash as H;", "")).is_err());
+}
+
+#[test]
+fn compile_emit() {
+    let code = String::from(
+        "
+        import 0x0.LibraAccount;
+        main() {
+            let handle: LibraAccount.EventHandle<u64>;
+            let x: u64;
+            handle = LibraAccount.new_event_handle<u64>();
+            x = 1;
+            emit &mut handle, move(x);
+            emit &mut handle, 2;
+            LibraAccount.destroy_handle<u64>(move(handle));
+            return;
+        }
+        ",
+    );
+    let compiled_script = compile_script_string_with_stdlib(&code).unwrap();
+    // Both events are emitted by calls to `LibraAccount.emit_event<u64>`.
+    assert_eq!(compiled_script.function_handles().len(), 4);
+    assert_eq!(instr_count!(compiled_script, Call(_, _)), 4);
+}
+
+#[test]
+fn compile_emit_of_generic_events() {
+    let code = "
+        module M {
+            import 0x0.LibraAccount;
+            struct E<T> { b: bool }
+            make<T>(): Self.E<T> {
+                return E<T> { b: true };
+            }
+            f<T: unrestricted>(x: T) {
+                let handle: LibraAccount.EventHandle<Self.E<T>>;
+                let params: LibraAccount.EventHandle<T>;
+                handle = LibraAccount.new_event_handle<Self.E<T>>();
+                emit &mut handle, E<T> { b: false };
+                LibraAccount.destroy_handle<Self.E<T>>(move(handle));
+                params = LibraAccount.new_event_handle<T>();
+                emit &mut params, move(x);
+                LibraAccount.destroy_handle<T>(move(params));
+                return;
+            }
+            g() {
+                let handle: LibraAccount.EventHandle<Self.E<u64>>;
+                handle = LibraAccount.new_event_handle<Self.E<u64>>();
+                emit &mut handle, Self.make<u64>();
+                LibraAccount.destroy_handle<Self.E<u64>>(move(handle));
+                return;
+            }
+        }
+        ";
+    let err = compile_module_string_with_stdlib(code).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Cannot infer the type of the event"));
+    // Without `g`, whose event is a generic struct returned by a call, the types are known.
+    let end = code.find("            g() {").unwrap();
+    assert!(compile_module_string_with_stdlib(&format!("{}}}", &code[..end])).is_ok());
+}
+
+#[test]
+fn compile_borrow_global_field() {
+    let code = "
+        module M {
+            resource T { f: u64 }
+            get(addr: address): u64 acquires T {
+                return *&borrow_global<T>(move(addr)).f;
+            }
+            set(addr: address, x: u64) acquires T {
+                *&mut borrow_global_mut<T>(move(addr)).f = move(x);
+                return;
+            }
+        }
+        ";
+    let compiled_module = compile_module_string(code).unwrap();
+    let code_of = |i: usize| compiled_module.function_defs()[i].code.code.as_slice();
+    // The field is borrowed straight from the global reference, without going through a local.
+    match code_of(0) {
+        [MoveLoc(0), ImmBorrowGlobal(_, _), ImmBorrowField(_), ReadRef, Ret] => (),
+        code => panic!("{:?}", code),
+    }
+    match code_of(1) {
+        [MoveLoc(1), MoveLoc(0), MutBorrowGlobal(_, _), MutBorrowField(_), WriteRef, Ret] => (),
+        code => panic!("{:?}", code),
+    }
+}
+
+/// Returns the bytearray literal a function returns, as in `return h"..";`.
+fn returned_literal(function: &Function) -> Arc<ByteArray> {
+    let code = match &function.value.body {
+        FunctionBody::Move { code, .. } => code,
+        FunctionBody::Native => panic!("native function"),
+    };
+    let e = match code.stmts.front() {
+        Some(Statement::CommandStatement(cmd)) => match &cmd.value {
+            Cmd_::Return(e) => e,
+            cmd => panic!("unexpected command {}", cmd),
+        },
+        _ => panic!("unexpected statement"),
+    };
+    let e = match &e.value {
+        Exp_::ExprList(exps) if exps.len() == 1 => &exps[0],
+        _ => e,
+    };
+    match &e.value {
+        Exp_::Value(value) => match &value.value {
+            CopyableVal_::ByteArray(bytes) => bytes.clone(),
+            value => panic!("unexpected value {}", value),
+        },
+        e => panic!("unexpected expression {}", e),
+    }
+}
+
+#[test]
+fn compile_module_bytearray_literals() {
+    let code = "
+        module M {
+            f(): bytearray {
+                return h\"deadbeef\";
+            }
+            g(): bytearray {
+                return h\"deadbeef\";
+            }
+        }
+    ";
+    // Copies of the AST share their literals.
+    let module = parse_module(code).unwrap();
+    let copy = module.clone();
+    assert_eq!(module.functions.len(), 2);
+    for ((_, function), (_, copied)) in module.functions.iter().zip(&copy.functions) {
+        assert!(Arc::ptr_eq(
+            &returned_literal(function),
+            &returned_literal(copied)
+        ));
+    }
+
+    let compiled_module = compile_module_string(code).unwrap();
+    assert_eq!(
+        compiled_module.byte_array_pool(),
+        &[ByteArray::new(vec![0xde, 0xad, 0xbe, 0xef])][..]
+    );
+}
+
+#[test]
+fn move_and_copy_keywords_compile_like_pseudo_calls() {
+    let module = |borrow: &str, read: &str| {
+        format!(
+            "
+            module M {{
+                resource T {{ value: u64 }}
+
+                public f(arg: &mut Self.T, x: u64): u64 {{
+                    let field_ref: &mut u64;
+                    field_ref = {};
+                    *move(field_ref) = {};
+                    return move(x);
+                }}
+            }}
+            ",
+            borrow, read
+        )
+    };
+    let keywords = compile_module_string(&module("&mut move arg.value", "copy x + 1")).unwrap();
+    let calls = compile_module_string(&module("&mut move(arg).value", "copy(x) + 1")).unwrap();
+    assert_eq!(keywords, calls);
+}
+
+#[test]
+fn chained_comparisons_suggest_conjunctions() {
+    let source = "main(a: u64, b: u64, c: u64) {\n    assert(copy(a) < copy(b) <= move(c), 42);\n    return;\n}";
+    let error = try_parse_script_or_module(source).unwrap_err();
+    let chain = "copy(a) < copy(b) <= move(c)";
+    let start = source.find(chain).unwrap();
+    let loc = Span::new(
+        ByteIndex(start as u32),
+        ByteIndex((start + chain.len()) as u32),
+    );
+    assert_eq!(error.loc, Some(loc));
+    assert_eq!(
+        error.message,
+        "Chained comparison, did you mean `copy(a) < copy(b) && copy(b) <= move(c)`?"
+    );
+    assert_eq!(
+        error.fix,
+        Some(Fix {
+            loc,
+            replacement: "copy(a) < copy(b) && copy(b) <= move(c)".to_string(),
+        })
+    );
+
+    // Equalities and parenthesized comparisons compare booleans on purpose.
+    for exp in &[
+        "copy(a) < copy(b) == (copy(b) < move(c))",
+        "(copy(a) < copy(b)) != true",
+    ] {
+        let source = format!(
+            "main(a: u64, b: u64, c: u64) {{ assert({}, 42); return; }}",
+            exp
+        );
+        assert!(try_parse_script_or_module(&source).is_ok(), "{}", exp);
+    }
+}
@@ -0,0 +1,55 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::parser::{parse_module, parse_program};
+use move_ir_types::{
+    ast::Loc,
+    footprint::{memory_footprint, MemoryFootprint},
+};
+use std::mem::size_of;
+
+fn module_footprint(source: &str) -> MemoryFootprint {
+    memory_footprint(&parse_module(source).unwrap())
+}
+
+#[test]
+fn strings_count_names_and_bytearrays() {
+    let short = module_footprint("module M { f(x: u64) { return; } }");
+    let long = module_footprint("module M { f(xyz: u64) { return; } }");
+    assert_eq!(long.strings, short.strings + 2);
+    assert_eq!(long.nodes, short.nodes);
+    assert_eq!(long.spans, short.spans);
+
+    let empty = module_footprint("module M { f(): bytearray { return h\"\"; } }");
+    let bytes = module_footprint("module M { f(): bytearray { return h\"deadbeef\"; } }");
+    assert_eq!(bytes.strings, empty.strings + 4);
+}
+
+#[test]
+fn spans_are_counted_separately() {
+    let footprint = module_footprint(
+        "module M { resource T { i: u64 } f(t: &Self.T): u64 { return *(&move(t).i) + 1; } }",
+    );
+    assert_eq!(footprint.spans % size_of::<Loc>(), 0);
+    assert!(footprint.spans > 0);
+    assert_eq!(
+        footprint.total(),
+        footprint.nodes + footprint.strings + footprint.spans
+    );
+}
+
+#[test]
+fn larger_programs_take_more_memory() {
+    let one = memory_footprint(
+        &parse_program("modules: module M { f() { return; } } script: main() { return; }").unwrap(),
+    );
+    let two = memory_footprint(
+        &parse_program(
+            "modules: module M { f() { return; } } module N { g() { return; } } script: main() { return; }",
+        )
+        .unwrap(),
+    );
+    assert!(two.nodes > one.nodes);
+    assert!(two.strings > one.strings);
+    assert!(two.spans > one.spans);
+}
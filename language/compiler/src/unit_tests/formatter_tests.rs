@@ -0,0 +1,143 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::unit_tests::testutils::STDLIB_SOURCES;
+use ir_to_bytecode::{
+    compiler::{compile_module, compile_script},
+    formatter::{format_source, FormatOptions},
+    parser::{parse_module, parse_script},
+};
+use libra_types::account_address::AccountAddress;
+use stdlib::{stdlib_modules, transaction_scripts};
+
+fn format(source: &str) -> String {
+    format_source(source, &FormatOptions::default()).unwrap()
+}
+
+fn comments(source: &str) -> Vec<&str> {
+    source
+        .lines()
+        .filter_map(|line| line.find("//").map(|idx| line[idx..].trim_end()))
+        .collect()
+}
+
+fn module_bytes(source: &str, deps: &[bytecode_verifier::VerifiedModule]) -> Vec<u8> {
+    let module = parse_module(source).unwrap();
+    let (compiled, _) = compile_module(AccountAddress::default(), module, deps).unwrap();
+    let mut bytes = vec![];
+    compiled.serialize(&mut bytes).unwrap();
+    bytes
+}
+
+fn script_bytes(source: &str) -> Vec<u8> {
+    let script = parse_script(source).unwrap();
+    let (compiled, _) =
+        compile_script(AccountAddress::default(), script, stdlib_modules()).unwrap();
+    let mut bytes = vec![];
+    compiled.serialize(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn stdlib_modules_format_to_the_same_bytecode() {
+    let stdlib = stdlib_modules();
+    assert_eq!(STDLIB_SOURCES.len(), stdlib.len());
+    for (i, source) in STDLIB_SOURCES.iter().enumerate() {
+        let formatted = format(source);
+        let deps = &stdlib[..i];
+        assert!(
+            module_bytes(source, deps) == module_bytes(&formatted, deps),
+            "{}",
+            formatted
+        );
+        assert_eq!(comments(source), comments(&formatted));
+        assert_eq!(
+            format(&formatted),
+            formatted,
+            "formatting is not idempotent"
+        );
+    }
+}
+
+#[test]
+fn transaction_scripts_format_to_the_same_bytecode() {
+    let scripts = [
+        transaction_scripts::add_validator(),
+        transaction_scripts::peer_to_peer(),
+        transaction_scripts::peer_to_peer_with_metadata(),
+        transaction_scripts::create_account(),
+        transaction_scripts::register_validator(),
+        transaction_scripts::remove_validator(),
+        transaction_scripts::rotate_consensus_pubkey(),
+        transaction_scripts::rotate_key(),
+        transaction_scripts::mint(),
+        transaction_scripts::block_prologue(),
+    ];
+    for source in scripts.iter() {
+        let formatted = format(source);
+        assert!(
+            script_bytes(source) == script_bytes(&formatted),
+            "{}",
+            formatted
+        );
+        assert_eq!(comments(source), comments(&formatted));
+        assert_eq!(
+            format(&formatted),
+            formatted,
+            "formatting is not idempotent"
+        );
+    }
+}
+
+#[test]
+fn normalizes_layout() {
+    let source = "
+import 0x0.LibraAccount;
+main(payee: address,amount: u64) {
+  let x:u64;
+    // keep me
+  x=copy(amount)*(2+3);
+  if(copy(x)>10){assert(copy(x) < 100,  42);}
+  LibraAccount.pay_from_sender_with_metadata(move(payee), move(amount), h\"00\"); // and me
+  return;
+}
+";
+    let expected = "\
+import 0x0.LibraAccount;
+main(payee: address, amount: u64) {
+    let x: u64;
+    // keep me
+    x = copy(amount) * (2 + 3);
+    if (copy(x) > 10) {
+        assert(copy(x) < 100, 42);
+    }
+    LibraAccount.pay_from_sender_with_metadata(
+        move(payee),
+        move(amount),
+        h\"00\",
+    ); // and me
+    return;
+}
+";
+    let options = FormatOptions {
+        max_width: 60,
+        ..FormatOptions::default()
+    };
+    assert_eq!(format_source(source, &options).unwrap(), expected);
+}
+
+#[test]
+fn formats_script_signatures() {
+    let source = "main <T:resource,U> ( ) { return; }";
+    assert_eq!(format(source), "main<T: resource, U>() {\n    return;\n}\n");
+    let source = "main(x:u64):u64*bool { return copy(x), true; }";
+    assert_eq!(
+        format(source),
+        "main(x: u64): u64 * bool {\n    return copy(x), true;\n}\n"
+    );
+}
+
+#[test]
+fn reports_parse_errors() {
+    assert!(format_source("main() { return }", &FormatOptions::default()).is_err());
+}
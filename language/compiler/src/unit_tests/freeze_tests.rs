@@ -0,0 +1,90 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::Compiler;
+use bytecode_source_map::utils::line_and_column;
+use ir_to_bytecode::{
+    formatter::{format_module, FormatOptions},
+    freeze::{freeze_module, freeze_script},
+    parser::{parse_module, parse_script},
+    type_checker::check_module,
+};
+
+const MODULE: &str = "module M {
+    resource T { v: u64 }
+    value(t: &Self.T): u64 {
+        return *&copy(t).v;
+    }
+    first(t: &mut Self.T): &Self.T {
+        return move(t);
+    }
+    f(t: &mut Self.T): u64 {
+        let r: &Self.T;
+        r = copy(t);
+        return Self.value(move(t)) + *&move(r).v;
+    }
+}
+";
+
+#[test]
+fn mutable_references_are_frozen() {
+    let mut module = parse_module(MODULE).unwrap();
+    let notes: Vec<String> = freeze_module(&mut module)
+        .iter()
+        .map(|freeze| {
+            let (line, column) = line_and_column(MODULE, freeze.loc.start());
+            format!("{}:{}: {}", line, column, freeze)
+        })
+        .collect();
+    assert_eq!(
+        notes,
+        vec![
+            "7:16: `&mut Self.T` frozen to `&Self.T`",
+            "11:13: `&mut Self.T` frozen to `&Self.T`",
+            "12:27: `&mut Self.T` frozen to `&Self.T`",
+        ]
+    );
+    let formatted = format_module(&module, &FormatOptions::default());
+    assert!(formatted.contains("return freeze(move(t));"), "{}", formatted);
+    assert!(formatted.contains("r = freeze(copy(t));"), "{}", formatted);
+    assert!(
+        formatted.contains("Self.value(freeze(move(t)))"),
+        "{}",
+        formatted
+    );
+    assert!(check_module(&module).is_empty());
+}
+
+#[test]
+fn frozen_modules_verify() {
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        verify: true,
+        ..Compiler::default()
+    };
+    assert!(compiler.clone().into_compiled_module(MODULE).is_err());
+    let compiler = Compiler {
+        auto_freeze: true,
+        check_types: true,
+        ..compiler
+    };
+    compiler.into_compiled_module(MODULE).unwrap();
+}
+
+#[test]
+fn only_single_values_are_frozen() {
+    let source = "
+        main() {
+            let x: u64;
+            let r: &u64;
+            let s: &u64;
+            x = 0;
+            r, s = (&mut x, &x);
+            return;
+        }
+        ";
+    let mut script = parse_script(source).unwrap();
+    let freezes = freeze_script(&mut script);
+    assert_eq!(freezes.len(), 1);
+    assert_eq!(&source[freezes[0].loc.start().to_usize()..][..6], "&mut x");
+}
@@ -1,7 +1,17 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::unit_tests::testutils::compile_module_string;
+use crate::{
+    unit_tests::testutils::{
+        compile_module_string, compile_script_string_and_assert_error,
+        compile_script_string_with_stdlib,
+    },
+    Compiler,
+};
+use vm::{
+    access::ScriptAccess,
+    file_format::{Kind, SignatureToken},
+};
 
 #[test]
 fn compile_script_with_functions() {
@@ -102,3 +112,65 @@ fn compile_script_with_invalid_large_frame() {
     let compiled_module_res = compile_module_string(&code);
     assert!(compiled_module_res.is_err());
 }
+
+#[test]
+fn compile_generic_script() {
+    let code = "
+        import 0x0.Vector;
+        main<Element: unrestricted, Other>(n: u64) {
+            let v: Vector.T<Element>;
+            v = Vector.empty<Element>();
+            assert(Vector.length<Element>(&v) == move(n), 42);
+            Vector.destroy_empty<Element>(move(v));
+            return;
+        }
+        ";
+    let compiled_script = compile_script_string_with_stdlib(code).unwrap();
+    let main = compiled_script.function_handle_at(compiled_script.main().function);
+    let signature = compiled_script.function_signature_at(main.signature);
+    assert_eq!(signature.type_formals, vec![Kind::Unrestricted, Kind::All]);
+}
+
+#[test]
+fn compile_script_returning_values() {
+    let code = "
+        main(x: u64): u64 * bool {
+            return copy(x) + 1, move(x) > 1;
+        }
+        ";
+    // The verifier rejects scripts returning values as the scripts of transactions.
+    let compiled_script = compile_script_string_and_assert_error(code, vec![]).unwrap();
+    let main = compiled_script.function_handle_at(compiled_script.main().function);
+    let signature = compiled_script.function_signature_at(main.signature);
+    assert_eq!(
+        signature.return_types,
+        vec![SignatureToken::U64, SignatureToken::Bool]
+    );
+}
+
+#[test]
+fn multi_value_assignments_match_the_arity_of_the_callee() {
+    let code = "module M {
+    pair(): u64 * bool {
+        return 1, true;
+    }
+    f() {
+        let a: u64;
+        let b: bool;
+        let c: u64;
+        a, b, c = Self.pair();
+        return;
+    }
+}";
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    let err = compiler.into_compiled_module(code).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "9:9: cannot assign 2 values returned by `Self.pair` to 3 targets
+9:9: note: 3 targets assigned here
+9:19: note: `Self.pair` returns 2 values"
+    );
+}
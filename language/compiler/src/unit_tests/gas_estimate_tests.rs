@@ -0,0 +1,134 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    gas_estimate::{GasEstimator, InstructionClass},
+    unit_tests::testutils::{compile_module_string, compile_script_string},
+};
+use vm::gas_schedule::{AbstractMemorySize, CostTable, GasAlgebra, GasCost};
+
+// Every instruction costs one unit of gas, so bounds count executed instructions.
+fn unit_cost_table() -> CostTable {
+    let mut cost_table = CostTable::zero();
+    for cost in cost_table.instruction_table.iter_mut() {
+        *cost = GasCost::new(1, 0);
+    }
+    cost_table
+}
+
+#[test]
+fn estimate_straight_line_script() {
+    let code = String::from(
+        "
+        main() {
+            let x: u64;
+            x = 1 + 2;
+            return;
+        }
+        ",
+    );
+    let compiled_script = compile_script_string(&code).unwrap();
+    let cost_table = unit_cost_table();
+    let estimator = GasEstimator::new(&cost_table, AbstractMemorySize::new(1));
+    let estimate = estimator.estimate_script(&compiled_script);
+    // LdU64, LdU64, Add, StLoc, Ret
+    assert_eq!(estimate.total.lower, 5);
+    assert_eq!(estimate.total.upper, Some(5));
+    let arithmetic = estimate.per_class[&InstructionClass::Arithmetic];
+    assert_eq!(arithmetic.lower, 1);
+    assert_eq!(arithmetic.upper, Some(1));
+    assert!(!estimate.per_class.contains_key(&InstructionClass::Call));
+}
+
+#[test]
+fn estimate_branching_script() {
+    let code = String::from(
+        "
+        main() {
+            let x: u64;
+            x = 0;
+            if (42 > 0) {
+                x = 1 + 2 + 3;
+            }
+            return;
+        }
+        ",
+    );
+    let compiled_script = compile_script_string(&code).unwrap();
+    let cost_table = unit_cost_table();
+    let estimator = GasEstimator::new(&cost_table, AbstractMemorySize::new(1));
+    let estimate = estimator.estimate_script(&compiled_script);
+    assert!(estimate.total.lower < estimate.total.upper.unwrap());
+    let arithmetic = estimate.per_class[&InstructionClass::Arithmetic];
+    assert_eq!(arithmetic.lower, 0);
+    assert_eq!(arithmetic.upper, Some(2));
+}
+
+#[test]
+fn estimate_size_dependent_instructions() {
+    let code = String::from(
+        "
+        main() {
+            let x: u64;
+            x = 1;
+            return;
+        }
+        ",
+    );
+    let compiled_script = compile_script_string(&code).unwrap();
+    let cost_table = unit_cost_table();
+    let estimator = GasEstimator::new(&cost_table, AbstractMemorySize::new(10));
+    let estimate = estimator.estimate_script(&compiled_script);
+    // StLoc is charged by size, LdU64 and Ret are not
+    assert_eq!(estimate.total.lower, 3);
+    assert_eq!(estimate.total.upper, Some(12));
+}
+
+#[test]
+fn estimate_loop_is_unbounded() {
+    let code = String::from(
+        "
+        main() {
+            let x: u64;
+            x = 0;
+            while (copy(x) < 10) {
+                x = copy(x) + 1;
+            }
+            return;
+        }
+        ",
+    );
+    let compiled_script = compile_script_string(&code).unwrap();
+    let cost_table = unit_cost_table();
+    let estimator = GasEstimator::new(&cost_table, AbstractMemorySize::new(1));
+    let estimate = estimator.estimate_script(&compiled_script);
+    assert!(estimate.total.lower > 0);
+    assert_eq!(estimate.total.upper, None);
+}
+
+#[test]
+fn estimate_module_functions() {
+    let code = String::from(
+        "
+        module M {
+            public f(): u64 {
+                return 1;
+            }
+
+            g(): u64 {
+                return Self.f() + Self.f();
+            }
+        }
+        ",
+    );
+    let compiled_module = compile_module_string(&code).unwrap();
+    let cost_table = unit_cost_table();
+    let estimator = GasEstimator::new(&cost_table, AbstractMemorySize::new(1));
+    let estimates = estimator.estimate_module(&compiled_module);
+    assert_eq!(estimates.len(), 2);
+    assert_eq!(estimates[0].0, "f");
+    assert_eq!(estimates[0].1.total.upper, Some(2));
+    assert_eq!(estimates[1].0, "g");
+    let calls = estimates[1].1.per_class[&InstructionClass::Call];
+    assert_eq!(calls.lower, 2);
+}
@@ -0,0 +1,53 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::unit_tests::testutils::temp_dir;
+use ir_to_bytecode::golden::{parser_output, run_parser_golden_tests};
+use std::fs;
+
+const MODULE: &str = "module M {
+    public f(): u64 {
+        return 42;
+    }
+}
+";
+
+#[test]
+fn syntax_errors_are_located_by_line_and_column() {
+    let output = parser_output("module M {\n    f() {\n        return 42\n    }\n}\n");
+    assert!(output.starts_with("error: 4:5: "), "{}", output);
+}
+
+#[test]
+fn baselines_are_written_then_matched() {
+    let dir = temp_dir(&[
+        ("m.mvir", MODULE),
+        ("errors/bad.mvir", "module M {"),
+        ("notes.txt", "not a test"),
+    ]);
+    let mismatches = run_parser_golden_tests(dir.path(), false).unwrap();
+    assert_eq!(mismatches.len(), 2);
+    assert!(mismatches.iter().all(|m| m.expected.is_none()));
+    assert!(mismatches[0].path.ends_with("errors/bad.mvir"));
+
+    assert!(run_parser_golden_tests(dir.path(), true)
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        fs::read_to_string(dir.path().join("m.exp")).unwrap(),
+        parser_output(MODULE)
+    );
+    assert!(!dir.path().join("notes.exp").exists());
+    assert!(run_parser_golden_tests(dir.path(), false)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn changed_output_is_reported() {
+    let dir = temp_dir(&[("m.mvir", MODULE), ("m.exp", "module M {}\n")]);
+    let mismatches = run_parser_golden_tests(dir.path(), false).unwrap();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].expected.as_deref(), Some("module M {}\n"));
+    assert_eq!(mismatches[0].actual, parser_output(MODULE));
+}
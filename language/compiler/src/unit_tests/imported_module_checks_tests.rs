@@ -0,0 +1,126 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_source_map::utils::line_and_column;
+use bytecode_verifier::VerifiedModule;
+use ir_to_bytecode::{
+    build::{compile_directory_with, BuildOptions},
+    compiler::compile_module,
+    imports::{check_module_imports, check_script_imports},
+    parser::{parse_module, parse_script},
+    resolver::{InMemory, WithModuleStore},
+};
+use libra_types::account_address::{AccountAddress, ADDRESS_LENGTH};
+use std::{collections::BTreeMap, path::Path};
+use stdlib::stdlib_modules;
+
+const BASE: &str = "module Base {
+    struct S<T> { x: u64 }
+    public f(x: u64): u64 {
+        return move(x);
+    }
+    g() {
+        return;
+    }
+}
+";
+
+fn error_lines(source: &str, errors: &[ir_to_bytecode::errors::ImportError]) -> Vec<String> {
+    errors
+        .iter()
+        .map(|err| {
+            let (line, _) = line_and_column(source, err.loc.start());
+            format!("{}: {}", line, err)
+        })
+        .collect()
+}
+
+#[test]
+fn uses_of_compiled_modules_are_checked() {
+    let source = "import 0x0.LibraAccount;
+import 0x0.LibraCoin;
+main() {
+    let coin: LibraCoin.T;
+    let other: LibraCoin.Nope;
+    LibraAccount.deposit(get_txn_sender());
+    LibraAccount.withdraw_from_account();
+    LibraAccount.nope();
+    LibraAccount.deposit<u64>(get_txn_sender(), move(coin));
+    return;
+}
+";
+    let script = parse_script(source).unwrap();
+    let errors = check_script_imports(&script, AccountAddress::default(), stdlib_modules());
+    let lines = error_lines(source, &errors);
+    assert_eq!(lines.len(), 5, "{:?}", lines);
+    assert!(lines[0].ends_with("unbound struct `LibraCoin.Nope`"));
+    assert_eq!(
+        lines[1],
+        "6: `LibraAccount.deposit` expects 2 arguments, found 1"
+    );
+    assert_eq!(
+        lines[2],
+        "7: function `LibraAccount.withdraw_from_account` is not public"
+    );
+    assert_eq!(lines[3], "8: unbound function `LibraAccount.nope`");
+    assert_eq!(
+        lines[4],
+        "9: `LibraAccount.deposit` expects 0 type arguments, found 1"
+    );
+}
+
+#[test]
+fn calls_returning_several_values_are_not_counted() {
+    let source = "module M {
+    import 0x0.LibraAccount;
+    f(): u64 {
+        return LibraAccount.balance(get_txn_sender());
+    }
+}
+";
+    let module = parse_module(source).unwrap();
+    assert!(check_module_imports(&module, AccountAddress::default(), stdlib_modules()).is_empty());
+}
+
+#[test]
+fn imports_are_fetched_from_a_module_store() {
+    let base_address = AccountAddress::new([1; ADDRESS_LENGTH]);
+    let (base, _) = compile_module(
+        base_address,
+        parse_module(BASE).unwrap(),
+        &Vec::<VerifiedModule>::new(),
+    )
+    .unwrap();
+    let mut store = BTreeMap::new();
+    store.insert(base.self_id(), base);
+
+    let import = format!("import 0x{}.Base;", base_address);
+    let mut sources = InMemory::new();
+    sources.add_file(
+        "src/top.mvir",
+        format!(
+            "module Top {{\n    {}\n    public h(): u64 {{\n        return Base.f(1);\n    }}\n}}\n",
+            import
+        ),
+    );
+    sources.add_file(
+        "src/wrong.mvir",
+        format!(
+            "module Wrong {{\n    {}\n    h(s: Base.S) {{\n        Base.g();\n        return;\n    }}\n}}\n",
+            import
+        ),
+    );
+    let resolver = WithModuleStore { sources, store };
+    let build =
+        compile_directory_with(&resolver, Path::new("src"), &BuildOptions::default()).unwrap();
+    assert_eq!(build.modules.len(), 1);
+    let messages: Vec<_> = build.errors.iter().map(|err| err.to_string()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "`Base.S` expects 1 type arguments, found 0",
+            "function `Base.g` is not public",
+        ]
+    );
+    assert!(build.errors.iter().all(|err| err.loc.is_some()));
+}
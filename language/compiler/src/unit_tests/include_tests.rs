@@ -0,0 +1,86 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{include::ExpandedSource, unit_tests::testutils::temp_dir, Compiler};
+use codespan::ByteIndex;
+use std::path::Path;
+
+const MODULE: &str = "module M {
+    public f(): u64 {
+        return 42;
+    }
+}
+";
+
+#[test]
+fn includes_are_expanded_relative_to_the_including_file() {
+    let dir = temp_dir(&[
+        ("main.mvir", "include \"lib/m.mvir\";\nscript:\nimport Transaction.M;\nmain() {\n    assert(M.f() == 42, 1);\n    return;\n}\n"),
+        ("lib/m.mvir", "modules:\ninclude \"module.mvir\";\n"),
+        ("lib/module.mvir", MODULE),
+    ]);
+    let source = ExpandedSource::read(&dir.path().join("main.mvir")).unwrap();
+    assert!(source.text.starts_with("modules:\nmodule M {"));
+    assert!(source.text.contains("}\n\n\nscript:\nimport"));
+    Compiler::default()
+        .into_compiled_program(&source.text)
+        .unwrap();
+}
+
+#[test]
+fn locations_are_mapped_to_the_included_file() {
+    let dir = temp_dir(&[
+        ("main.mvir", "// header\ninclude \"m.mvir\";\nmain() {}\n"),
+        ("m.mvir", MODULE),
+    ]);
+    let source = ExpandedSource::read(&dir.path().join("main.mvir")).unwrap();
+
+    let index = source.text.find("42").unwrap();
+    let (path, line, column) = source.locate(ByteIndex(index as u32));
+    assert_eq!(path, dir.path().join("m.mvir").as_path());
+    assert_eq!((line, column), (3, 16));
+
+    let index = source.text.find("main").unwrap();
+    let (path, line, column) = source.locate(ByteIndex(index as u32));
+    assert_eq!(path, dir.path().join("main.mvir").as_path());
+    assert_eq!((line, column), (3, 1));
+
+    let (path, line) = source
+        .relocate_report_line("    4:16: error: here")
+        .unwrap();
+    assert_eq!(path, dir.path().join("m.mvir").as_path());
+    assert_eq!(line, "    3:16: error: here");
+    assert!(source.relocate_report_line("help: elsewhere").is_none());
+}
+
+#[test]
+fn include_cycles_are_rejected() {
+    let dir = temp_dir(&[
+        ("a.mvir", "include \"b.mvir\";\n"),
+        ("b.mvir", "include \"a.mvir\";\n"),
+    ]);
+    let err = ExpandedSource::read(&dir.path().join("a.mvir")).unwrap_err();
+    assert!(err.to_string().starts_with("include cycle: "), "{}", err);
+    assert!(err.to_string().ends_with("a.mvir"), "{}", err);
+}
+
+#[test]
+fn malformed_directives_are_rejected() {
+    let dir = temp_dir(&[("main.mvir", "main() {}\ninclude \"m.mvir\"\n")]);
+    let err = ExpandedSource::read(&dir.path().join("main.mvir")).unwrap_err();
+    assert!(
+        err.to_string()
+            .ends_with("main.mvir:2: malformed include directive, expected `include \"<path>\";`"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn other_uses_of_include_are_not_directives() {
+    let code = "main() {\n    let include: u64;\n    include = 1;\n    // include \"x.mvir\";\n    return;\n}\n";
+    let dir = temp_dir(&[("main.mvir", code)]);
+    let source = ExpandedSource::read(Path::new(&dir.path().join("main.mvir"))).unwrap();
+    assert_eq!(source.text, code);
+    assert_eq!(source.path(), dir.path().join("main.mvir").as_path());
+}
@@ -0,0 +1,122 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::{
+    incremental::{DiagnosticsUpdate, Document, TextEdit},
+    parser::ParserOptions,
+};
+
+const PROGRAM: &str = "modules:
+module A {
+    public one(): u64 {
+        return 1;
+    }
+}
+module B {
+    import Transaction.A;
+    // Two is one plus one.
+    public two(): u64 {
+        return A.one() + A.one();
+    }
+}
+script:
+import Transaction.B;
+main() {
+    return;
+}
+";
+
+fn edit(document: &mut Document, old: &str, new: &str) -> Vec<DiagnosticsUpdate> {
+    let start = document.text().find(old).unwrap();
+    let mut updates = vec![];
+    let edit = TextEdit {
+        start,
+        end: start + old.len(),
+        text: new.to_string(),
+    };
+    document.edit(&edit, |update| updates.push(update)).unwrap();
+    updates
+}
+
+#[test]
+fn only_edited_parts_are_parsed_again() {
+    let mut document = Document::new(PROGRAM, ParserOptions::default());
+    assert!(document.syntax_errors().is_empty());
+    assert!(document.type_errors().is_empty());
+
+    let updates = edit(&mut document, "return 1;", "return 1 + 0;");
+    let a_start = document.text().find("module A").unwrap();
+    let b_start = document.text().find("module B").unwrap();
+    assert_eq!(
+        updates,
+        vec![
+            DiagnosticsUpdate::Region {
+                start: a_start,
+                end: b_start,
+                errors: vec![],
+            },
+            DiagnosticsUpdate::Complete {
+                syntax_errors: vec![],
+                type_errors: vec![],
+            },
+        ]
+    );
+
+    // Module B was kept and moved by the edit of module A. Editing a comment of module B parses it
+    // again, with the script, keeping module A.
+    let updates = edit(&mut document, "// Two is", "// Two\n// is");
+    assert_eq!(updates.len(), 2);
+}
+
+#[test]
+fn syntax_errors_are_streamed_before_type_errors() {
+    let mut document = Document::new(PROGRAM, ParserOptions::default());
+    let updates = edit(&mut document, "return 1;", "return 1");
+    assert_eq!(updates.len(), 2);
+    let errors = match &updates[0] {
+        DiagnosticsUpdate::Region { errors, .. } => errors.clone(),
+        update => panic!("unexpected update {:?}", update),
+    };
+    assert_eq!(errors.len(), 1);
+    let loc = errors[0].loc.unwrap();
+    assert_eq!(&document.text()[loc.start().to_usize()..][..1], "}");
+    assert_eq!(document.syntax_errors(), errors);
+
+    edit(&mut document, "return 1", "return true;");
+    assert!(document.syntax_errors().is_empty());
+    let type_errors = document.type_errors();
+    assert_eq!(type_errors.len(), 1);
+    let loc = type_errors[0].loc;
+    assert!(document.text()[..loc.start().to_usize()].contains("module A"));
+    assert!(!document.text()[..loc.start().to_usize()].contains("module B"));
+}
+
+#[test]
+fn edits_of_the_header_parse_every_part_again() {
+    let mut document = Document::new(PROGRAM, ParserOptions::default());
+    let updates = edit(&mut document, "modules:", "pragma language 2;\nmodules:");
+    // Both parts are parsed again, and the import of a `Transaction` module is rejected by the
+    // version 2 of the language.
+    assert_eq!(updates.len(), 3);
+    let errors = document.syntax_errors();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message.starts_with("Deprecated"), "{}", errors[0]);
+
+    // Modules and scripts on their own are a single part.
+    let mut document = Document::new("main() {\n    return;\n}\n", ParserOptions::default());
+    let updates = edit(&mut document, "return;", "return");
+    assert_eq!(updates.len(), 2);
+    assert_eq!(document.syntax_errors().len(), 1);
+}
+
+#[test]
+fn invalid_edits_are_rejected() {
+    let mut document = Document::new(PROGRAM, ParserOptions::default());
+    let edit = TextEdit {
+        start: PROGRAM.len(),
+        end: PROGRAM.len() + 1,
+        text: String::new(),
+    };
+    assert!(document.edit(&edit, |_| ()).is_err());
+    assert_eq!(document.text(), PROGRAM);
+}
@@ -0,0 +1,226 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{unit_tests::testutils::assert_contains, util::inlining_report, Compiler};
+use ir_to_bytecode::{
+    formatter::{format_module, format_source, FormatOptions},
+    inline::{inline_module, NotInlinedReason},
+    parser::parse_module,
+};
+
+const MODULE: &str = "module M {
+    resource T { v: u64 }
+
+    double(x: u64): u64 {
+        return move(x) * 2;
+    }
+
+    public quadruple(x: u64): u64 {
+        let y: u64;
+        y = Self.double(move(x));
+        return Self.double(move(y));
+    }
+
+    value(t: &Self.T): u64 {
+        return *&move(t).v;
+    }
+
+    public total(t: &Self.T, extra: u64): u64 {
+        let v: u64;
+        v = Self.value(move(t));
+        return move(v) + move(extra);
+    }
+
+    check(x: u64) {
+        assert(move(x) > 0, 42);
+        return;
+    }
+
+    public positive(x: u64): u64 {
+        Self.check(copy(x));
+        return move(x);
+    }
+
+    peek(t: &Self.T): u64 {
+        return *&copy(t).v;
+    }
+
+    big(x: u64): u64 {
+        return move(x) + 1 + 2 + 3 + 4 + 5 + 6 + 7 + 8 + 9 + 10 + 11 + 12;
+    }
+
+    #[never_inline]
+    one(): u64 {
+        return 1;
+    }
+
+    #[inline]
+    sum(n: u64): u64 {
+        if (copy(n) == 0) {
+            return 0;
+        }
+        return copy(n) + Self.sum(move(n) - 1);
+    }
+
+    #[inline]
+    public add(a: u64, b: u64): u64 {
+        return move(a) + move(b);
+    }
+
+    public kept(t: &Self.T): u64 {
+        let a: u64;
+        let b: u64;
+        let c: u64;
+        let d: u64;
+        a = Self.one();
+        b = Self.sum(3);
+        c = Self.peek(move(t));
+        d = Self.big(move(a));
+        return Self.add(move(b) + move(c), move(d));
+    }
+}
+";
+
+fn inlined() -> String {
+    let mut module = parse_module(MODULE).unwrap();
+    inline_module(&mut module);
+    format_module(&module, &FormatOptions::default())
+}
+
+#[test]
+fn calls_are_replaced_by_bodies() {
+    assert_contains(
+        &inlined(),
+        &[
+            "let x_1: u64;",
+            "let x_2: u64;",
+            "x_1 = move(x);",
+            "y = move(x_1) * 2;",
+            "x_2 = move(y);",
+            "return move(x_2) * 2;",
+        ],
+    );
+}
+
+#[test]
+fn references_moved_once_are_inlined() {
+    assert_contains(
+        &inlined(),
+        &["let t_1: &Self.T;", "t_1 = move(t);", "v = *&move(t_1).v;"],
+    );
+}
+
+#[test]
+fn calls_returning_nothing_are_inlined() {
+    let formatted = inlined();
+    assert_contains(&formatted, &["x_1 = copy(x);"]);
+    assert!(!formatted.contains("Self.check("), "{}", formatted);
+}
+
+#[test]
+fn other_calls_are_kept() {
+    assert_contains(
+        &inlined(),
+        &[
+            "a = Self.one();",
+            "b = Self.sum(3);",
+            "c = Self.peek(move(t));",
+            "d = Self.big(move(a));",
+            "return Self.add(move(b) + move(c), move(d));",
+        ],
+    );
+}
+
+#[test]
+fn inline_attributes_override_the_size() {
+    let source = MODULE.replace("    big(x: u64)", "    #[inline]\n    big(x: u64)");
+    let mut module = parse_module(&source).unwrap();
+    let inlining = inline_module(&mut module);
+    assert!(inlining
+        .calls
+        .iter()
+        .any(|call| call.callee.as_inner().as_str() == "big"));
+}
+
+#[test]
+fn functions_that_cannot_be_inlined_are_reported() {
+    let mut module = parse_module(MODULE).unwrap();
+    let inlining = inline_module(&mut module);
+    let not_inlined: Vec<(String, NotInlinedReason)> = inlining
+        .not_inlined
+        .iter()
+        .map(|function| (function.name.to_string(), function.reason))
+        .collect();
+    assert_eq!(
+        not_inlined,
+        vec![
+            ("sum".to_string(), NotInlinedReason::Recursive),
+            ("add".to_string(), NotInlinedReason::Public),
+        ]
+    );
+    let callees: Vec<String> = inlining
+        .calls
+        .iter()
+        .map(|call| call.callee.to_string())
+        .collect();
+    assert_eq!(callees, vec!["double", "double", "value", "check"]);
+}
+
+#[test]
+fn report_notes_calls_and_warns_about_attributes() {
+    let mut module = parse_module(MODULE).unwrap();
+    let report = inlining_report(MODULE, &inline_module(&mut module));
+    assert_contains(
+        &report,
+        &[
+            "10:9: note: the call of `double` is inlined into `quadruple`",
+            "48:5: warning: `sum` has an `#[inline]` attribute but is recursive",
+            "56:5: warning: `add` has an `#[inline]` attribute but is public",
+        ],
+    );
+}
+
+#[test]
+fn inline_attributes_are_parsed_and_formatted() {
+    let source = "module M {\n    #[cfg(test)] #[inline]\n    f() {\n        return;\n    }\n\n    #[never_inline]\n    g() {\n        return;\n    }\n}\n";
+    let formatted = format_source(source, &FormatOptions::default()).unwrap();
+    assert!(formatted.contains("    #[cfg(test)]\n    #[inline]\n    f() {"));
+    assert!(formatted.contains("    #[never_inline]\n    g() {"));
+    for source in &[
+        "module M { #[inline] resource T { v: u64 } }",
+        "module M { #[never_inline] import 0x0.LibraAccount; f() { return; } }",
+        "module M { #[inline] #[never_inline] f() { return; } }",
+        "module M { #[cfg(test)] #[cfg(debug)] f() { return; } }",
+    ] {
+        assert!(parse_module(source).is_err(), "{}", source);
+    }
+}
+
+#[test]
+fn functions_verify_after_inlining() {
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        verify: true,
+        inline_functions: true,
+        ..Compiler::default()
+    };
+    compiler.into_compiled_module(MODULE).unwrap();
+}
+
+#[test]
+fn stdlib_compiles_with_inlined_functions() {
+    let modules: &[&str] = &[
+        include_str!("../../../stdlib/modules/libra_coin.mvir"),
+        include_str!("../../../stdlib/modules/libra_account.mvir"),
+        include_str!("../../../stdlib/modules/libra_system.mvir"),
+        include_str!("../../../stdlib/modules/validator_config.mvir"),
+    ];
+    for code in modules {
+        let compiler = Compiler {
+            verify: true,
+            inline_functions: true,
+            ..Compiler::default()
+        };
+        compiler.into_compiled_module(code).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::unit_tests::testutils::{
+    compile_module_string, compile_module_string_with_deps, compile_script_string_with_deps,
+};
+use ir_to_bytecode::{
+    formatter::{format_module, FormatOptions},
+    interface::module_interface,
+    parser::parse_module,
+};
+
+const COUNTER: &str = "
+module Counter {
+    import 0x0.LibraAccount;
+    import 0x0.Vector;
+
+    resource T {
+        value: u64,
+        invariant value < 100,
+    }
+
+    struct Handle<K> {
+        owner: address,
+    }
+
+    public publish() {
+        move_to_sender<T>(T { value: 0 });
+        return;
+    }
+
+    public bump(addr: address): u64 acquires T {
+        let t: &mut Self.T;
+        t = borrow_global_mut<T>(move(addr));
+        *(&mut copy(t).value) = *(&copy(t).value) + Self.one();
+        return *(&move(t).value);
+    }
+
+    one(): u64 {
+        return 1;
+    }
+}
+";
+
+#[test]
+fn interface_keeps_public_declarations() {
+    let interface = module_interface(&parse_module(COUNTER).unwrap());
+    let expected = "module Counter {
+    native resource T;
+    native struct Handle<K>;
+    native public publish();
+    native public bump(addr: address): u64 acquires T;
+}
+";
+    assert_eq!(
+        format_module(&interface, &FormatOptions::default()),
+        expected
+    );
+}
+
+#[test]
+fn dependents_compile_against_interface() {
+    let interface = module_interface(&parse_module(COUNTER).unwrap());
+    let interface = format_module(&interface, &FormatOptions::default());
+    let compiled = compile_module_string(&interface).unwrap();
+
+    let script = "
+import 0x0.Counter;
+main() {
+    let value: u64;
+    Counter.publish();
+    value = Counter.bump(get_txn_sender());
+    return;
+}
+";
+    compile_script_string_with_deps(script, vec![compiled.clone()]).unwrap();
+
+    let module = "
+module User {
+    import 0x0.Counter;
+    public bump_twice(addr: address): u64 {
+        let value: u64;
+        value = Counter.bump(copy(addr));
+        return Counter.bump(move(addr)) + move(value);
+    }
+}
+";
+    compile_module_string_with_deps(module, vec![compiled]).unwrap();
+}
@@ -0,0 +1,98 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::{
+    compiler::{compile_module, compile_program},
+    formatter::{format_source, FormatOptions},
+    parser::{parse_module, parse_program, parse_script, try_parse_script_or_module},
+};
+use libra_types::account_address::AccountAddress;
+use vm::file_format::CompiledModule;
+
+#[test]
+fn pragma_is_parsed_into_the_ast() {
+    let module = parse_module("pragma language 1; module M { }").unwrap();
+    assert_eq!(module.language_version, Some(1));
+    let module = parse_module("module M { }").unwrap();
+    assert_eq!(module.language_version, None);
+    let script = parse_script("pragma language 2u64;\nmain() { return; }").unwrap();
+    assert_eq!(script.language_version, Some(2));
+}
+
+#[test]
+fn pragma_applies_to_every_module_of_a_program() {
+    let program = parse_program(
+        "pragma language 1;
+modules:
+module M { }
+module N { }
+script:
+main() { return; }",
+    )
+    .unwrap();
+    assert!(program
+        .modules
+        .iter()
+        .all(|module| module.language_version == Some(1)));
+    assert_eq!(program.script.language_version, Some(1));
+}
+
+#[test]
+fn version_2_rejects_deprecated_syntax() {
+    let source = "main() { assert(true, 42); return; }";
+    assert!(try_parse_script_or_module(source).is_ok());
+    assert!(try_parse_script_or_module(&format!("pragma language 1; {}", source)).is_ok());
+    let error = try_parse_script_or_module(&format!("pragma language 2; {}", source)).unwrap_err();
+    assert_eq!(
+        error.message,
+        "Deprecated `assert`: use `if (!cond) abort code` instead"
+    );
+}
+
+#[test]
+fn unsupported_versions_are_located() {
+    for version in &["0", "3", "99999999999999999999"] {
+        let source = format!("pragma language {}; module M {{ }}", version);
+        let error = try_parse_script_or_module(&source).unwrap_err();
+        assert_eq!(
+            error.message,
+            format!("Unsupported language version {}", version)
+        );
+        let loc = error.loc.unwrap();
+        assert_eq!(
+            &source[loc.start().0 as usize..loc.end().0 as usize],
+            *version
+        );
+    }
+    assert!(try_parse_script_or_module("pragma version 1; module M { }").is_err());
+}
+
+#[test]
+fn version_is_embedded_in_the_source_map() {
+    let module = parse_module("pragma language 2; module M { }").unwrap();
+    let (_, source_map) =
+        compile_module(AccountAddress::default(), module, &[] as &[CompiledModule]).unwrap();
+    assert_eq!(source_map.language_version, Some(2));
+
+    let program = parse_program("main() { return; }").unwrap();
+    let (_, source_maps) =
+        compile_program(AccountAddress::default(), program, &[] as &[CompiledModule]).unwrap();
+    assert_eq!(source_maps[0].language_version, None);
+}
+
+#[test]
+fn formatter_keeps_the_pragma() {
+    let source = "pragma   language 1 ;  // old code
+
+module M {
+}
+";
+    assert_eq!(
+        format_source(source, &FormatOptions::default()).unwrap(),
+        "pragma language 1; // old code
+
+module M {
+}
+"
+    );
+}
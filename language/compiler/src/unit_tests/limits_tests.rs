@@ -0,0 +1,103 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{unit_tests::testutils::compiler, util::limit_error_report};
+use ir_to_bytecode::{
+    compiler::compile_module,
+    errors::LimitError,
+    limits::{check_module_limits, check_script_limits, Limit},
+    parser::{parse_module, parse_script},
+};
+use libra_types::account_address::AccountAddress;
+use move_ir_types::ast::{FunctionBody, StructDefinitionFields};
+use vm::file_format::CompiledModule;
+
+fn module_with_locals(count: usize) -> String {
+    let locals: String = (0..count)
+        .map(|i| format!("        let x{}: u64;\n", i))
+        .collect();
+    format!(
+        "module M {{\n    f() {{\n{}        return;\n    }}\n}}\n",
+        locals
+    )
+}
+
+#[test]
+fn too_many_locals_are_reported_with_the_function() {
+    let source = module_with_locals(256);
+    let errors = check_module_limits(&parse_module(&source).unwrap());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].limit, Limit::Locals);
+    assert_eq!(errors[0].count, 256);
+    let err = compiler().into_compiled_module(&source).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Binary format limits exceeded:\n\
+         2:5: function `f` has 256 parameters and locals, more than the 255 the binary format allows"
+    );
+}
+
+#[test]
+fn declarations_at_the_limit_compile() {
+    compiler()
+        .into_compiled_module(&module_with_locals(255))
+        .unwrap();
+}
+
+#[test]
+fn too_many_fields_and_type_parameters_are_reported() {
+    let source = "module M {\n    struct S<T> { f: u64 }\n}\n";
+    let mut module = parse_module(source).unwrap();
+    let s = &mut module.structs[0].value;
+    if let StructDefinitionFields::Move { fields } = &mut s.fields {
+        let field = fields[0].clone();
+        fields.resize(65_536, field);
+    }
+    let type_formal = s.type_formals[0].clone();
+    s.type_formals.resize(65_537, type_formal);
+    assert_eq!(
+        limit_error_report(source, &check_module_limits(&module)),
+        "2:5: struct `S` has 65537 type parameters, more than the 65535 the binary format allows\n\
+         2:5: struct `S` has 65536 fields, more than the 65535 the binary format allows"
+    );
+}
+
+#[test]
+fn too_many_module_handles_are_reported_without_location() {
+    let source = "import 0x0.LibraAccount;\nmain() {\n    return;\n}\n";
+    let mut script = parse_script(source).unwrap();
+    let import = script.imports[0].clone();
+    script.imports.resize(65_535, import);
+    let errors = check_script_limits(&script);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].loc, None);
+    assert_eq!(
+        limit_error_report(source, &errors),
+        "script has 65536 module handles, more than the 65535 the binary format allows"
+    );
+}
+
+#[test]
+fn too_many_instructions_are_reported_by_the_compiler() {
+    let source =
+        "module M {\n    f() {\n        let x: u64;\n        x = 1;\n        return;\n    }\n}\n";
+    let mut module = parse_module(source).unwrap();
+    if let FunctionBody::Move { code, .. } = &mut module.functions[0].1.value.body {
+        // Each assignment loads the constant and stores it.
+        let assignment = code.stmts[0].clone();
+        for _ in 0..33_000 {
+            code.stmts.push_front(assignment.clone());
+        }
+    }
+    assert!(check_module_limits(&module).is_empty());
+    let err = compile_module(AccountAddress::default(), module, &[] as &[CompiledModule])
+        .unwrap_err()
+        .downcast::<LimitError>()
+        .unwrap();
+    assert_eq!(err.limit, Limit::Instructions);
+    assert_eq!(err.count, 66_003);
+    assert_eq!(
+        limit_error_report(source, &[err]),
+        "2:5: function `f` has 66003 instructions, more than the 65535 the binary format allows"
+    );
+}
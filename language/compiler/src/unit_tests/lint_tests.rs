@@ -0,0 +1,179 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::util::lint_report;
+use ir_to_bytecode::{
+    lint::{Linter, Report, Rule, Severity},
+    parser::{parse_module, parse_script},
+};
+use move_ir_types::ast::{Builtin, Exp, Exp_, FunctionCall_};
+
+fn script_report(linter: &Linter, code: &str) -> String {
+    let script = parse_script(code).unwrap();
+    lint_report(code, &linter.lint_script(&script))
+}
+
+fn module_report(linter: &Linter, code: &str) -> String {
+    let module = parse_module(code).unwrap();
+    lint_report(code, &linter.lint_module(&module))
+}
+
+#[test]
+fn unused_acquires() {
+    let code = "module M {
+    resource R { x: u64 }
+    resource S { x: u64 }
+    public f(): u64 acquires R, S {
+        return *&borrow_global<R>(get_txn_sender()).x;
+    }
+}";
+    assert_eq!(
+        module_report(&Linter::default(), code),
+        "4:5: warning: function `f` declares `acquires S` but never acquires it [unused_acquires]"
+    );
+}
+
+#[test]
+fn magic_abort_codes_are_allowed_by_default() {
+    let code = "main() {
+    assert(true, 42);
+    abort 7;
+}";
+    assert_eq!(script_report(&Linter::default(), code), "");
+
+    let mut linter = Linter::default();
+    linter
+        .set_severity("magic_abort_codes", Severity::Deny)
+        .unwrap();
+    assert_eq!(
+        script_report(&linter, code),
+        "2:18: error: abort code `42` is a magic number [magic_abort_codes]
+3:11: error: abort code `7` is a magic number [magic_abort_codes]"
+    );
+}
+
+#[test]
+fn computed_abort_codes_are_not_magic() {
+    let code = "import 0x0.Codes;
+main() {
+    abort Codes.not_found();
+}";
+    let mut linter = Linter::default();
+    linter
+        .set_severity("magic_abort_codes", Severity::Warn)
+        .unwrap();
+    assert_eq!(script_report(&linter, code), "");
+}
+
+#[test]
+fn shadowed_locals() {
+    let code = "import 0x0.LibraCoin;
+main(x: u64) {
+    let LibraCoin: u64;
+    let x: bool;
+    return;
+}";
+    assert_eq!(
+        script_report(&Linter::default(), code),
+        "3:9: warning: `LibraCoin` shadows the module with the same name [shadowed_locals]
+4:9: warning: `x` shadows a local of `main` with the same name [shadowed_locals]
+2:6: note: previously declared here"
+    );
+}
+
+#[test]
+fn reserved_names() {
+    let code = "module M {
+    struct vector<signer> { const: u64 }
+    friend(x: u64) {
+        let signer: bool;
+        return;
+    }
+}";
+    assert_eq!(
+        module_report(&Linter::default(), code),
+        "2:5: warning: `vector` is a reserved name [reserved_names]
+2:19: warning: `signer` is a reserved name [reserved_names]
+2:29: warning: `const` is a reserved name [reserved_names]
+3:5: warning: `friend` is a reserved name [reserved_names]
+4:13: warning: `signer` is a reserved name [reserved_names]"
+    );
+}
+
+#[test]
+fn mixed_logical_operators() {
+    let code = "main(a: bool, b: bool, c: bool, d: bool) {
+    assert(copy(a) || copy(b) && copy(c), 1);
+    assert(copy(a) && copy(b) || copy(c) || !copy(d) && copy(a), 2);
+    assert(copy(a) || (copy(b) && copy(c)), 3);
+    assert((copy(a) || copy(b)) && copy(c), 4);
+    return;
+}";
+    assert_eq!(
+        script_report(&Linter::default(), code),
+        "2:23: warning: `&&` binds more tightly than `||`, this is parsed as \
+`copy(a) || (copy(b) && copy(c))` [mixed_logical_operators]
+3:12: warning: `&&` binds more tightly than `||`, this is parsed as \
+`(copy(a) && copy(b)) || copy(c)` [mixed_logical_operators]
+3:45: warning: `&&` binds more tightly than `||`, this is parsed as \
+`copy(a) && copy(b) || copy(c) || (!copy(d) && copy(a))` [mixed_logical_operators]"
+    );
+}
+
+#[test]
+fn allowed_rules_do_not_run() {
+    let code = "main(x: u64) {
+    let x: u64;
+    return;
+}";
+    let mut linter = Linter::default();
+    linter
+        .set_severity("shadowed_locals", Severity::Allow)
+        .unwrap();
+    assert_eq!(script_report(&linter, code), "");
+}
+
+#[test]
+fn unknown_rules_are_rejected() {
+    let mut linter = Linter::default();
+    assert!(linter.set_severity("no_such_rule", Severity::Deny).is_err());
+    assert!("forbid".parse::<Severity>().is_err());
+}
+
+struct NoMoveFrom;
+
+impl Rule for NoMoveFrom {
+    fn name(&self) -> &'static str {
+        "no_move_from"
+    }
+
+    fn description(&self) -> &'static str {
+        "uses of `move_from`"
+    }
+
+    fn check_exp(&self, exp: &Exp, reports: &mut Vec<Report>) {
+        if let Exp_::FunctionCall(f, _) = &exp.value {
+            if let FunctionCall_::Builtin(Builtin::MoveFrom(_, _)) = &f.value {
+                reports.push(Report::new(exp.span, "`move_from` is not allowed"));
+            }
+        }
+    }
+}
+
+#[test]
+fn custom_rules() {
+    let code = "module M {
+    resource R { x: u64 }
+    public f(): u64 acquires R {
+        let x: u64;
+        R { x } = move_from<R>(get_txn_sender());
+        return move(x);
+    }
+}";
+    let mut linter = Linter::empty();
+    linter.add_rule(Box::new(NoMoveFrom));
+    assert_eq!(
+        module_report(&linter, code),
+        "5:19: warning: `move_from` is not allowed [no_move_from]"
+    );
+}
@@ -0,0 +1,205 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::Compiler;
+use ir_to_bytecode::{
+    cfg::CfgOptions,
+    formatter::{format_source, FormatOptions},
+    metadata::{
+        embed_metadata, read_build_info, read_metadata, BuildInfo, Metadata, COMPILER_VERSION,
+    },
+    parser::parse_module,
+};
+use std::collections::BTreeMap;
+use vm::{access::ModuleAccess, file_format::CompiledModule};
+
+const MODULE: &str = "module M {
+    #[annotate(audit = \"2020-03 Acme\", since = \"1.2\", reviewer = \"bob\")]
+    public pay(amount: u64): u64 {
+        return move(amount);
+    }
+
+    #[inline]
+    #[annotate(since = \"1.0\")]
+    double(x: u64): u64 {
+        return move(x) * 2;
+    }
+
+    public plain() {
+        return;
+    }
+}
+";
+
+fn compile(keys: &[&str]) -> CompiledModule {
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        verify: true,
+        metadata_keys: keys.iter().map(|&key| key.to_string()).collect(),
+        ..Compiler::default()
+    };
+    compiler.into_compiled_module(MODULE).unwrap()
+}
+
+fn annotations(entries: &[(&str, &[(&str, &str)])]) -> BTreeMap<String, BTreeMap<String, String>> {
+    entries
+        .iter()
+        .map(|&(function, pairs)| {
+            let pairs = pairs
+                .iter()
+                .map(|&(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            (function.to_string(), pairs)
+        })
+        .collect()
+}
+
+#[test]
+fn annotations_are_parsed_in_order() {
+    let module = parse_module(MODULE).unwrap();
+    let (_, pay) = &module.functions[0];
+    assert_eq!(
+        pay.value.annotations,
+        vec![
+            ("audit".to_string(), "2020-03 Acme".to_string()),
+            ("since".to_string(), "1.2".to_string()),
+            ("reviewer".to_string(), "bob".to_string()),
+        ]
+    );
+    assert!(module.functions[2].1.value.annotations.is_empty());
+}
+
+#[test]
+fn annotations_are_formatted() {
+    let formatted = format_source(MODULE, &FormatOptions::default()).unwrap();
+    assert!(formatted.contains(
+        "    #[annotate(audit = \"2020-03 Acme\", since = \"1.2\", reviewer = \"bob\")]\n    public pay("
+    ));
+    assert!(formatted.contains("    #[inline]\n    #[annotate(since = \"1.0\")]\n    double("));
+}
+
+#[test]
+fn invalid_annotations_are_rejected() {
+    for source in &[
+        "module M { #[annotate(audit = \"x\")] resource T { v: u64 } }",
+        "module M { #[annotate(audit = \"x\")] import 0x0.LibraAccount; f() { return; } }",
+        "module M { #[annotate(audit = \"x\", audit = \"y\")] f() { return; } }",
+        "module M { #[annotate(audit = \"x\")] #[annotate(since = \"y\")] f() { return; } }",
+        "module M { #[annotate(audit = 1)] f() { return; } }",
+        "module M { #[annotate(audit)] f() { return; } }",
+    ] {
+        assert!(parse_module(source).is_err(), "{}", source);
+    }
+}
+
+#[test]
+fn selected_annotations_are_embedded() {
+    let module = compile(&["audit", "since"]);
+    let metadata = read_metadata(&module).unwrap();
+    assert_eq!(
+        metadata.annotations,
+        annotations(&[
+            ("double", &[("since", "1.0")]),
+            ("pay", &[("audit", "2020-03 Acme"), ("since", "1.2")]),
+        ])
+    );
+}
+
+#[test]
+fn metadata_survives_serialization() {
+    let module = compile(&["audit"]);
+    let mut bytes = vec![];
+    module.serialize(&mut bytes).unwrap();
+    let module = CompiledModule::deserialize(&bytes).unwrap();
+    let metadata = read_metadata(&module).unwrap();
+    assert_eq!(
+        metadata.annotations,
+        annotations(&[("pay", &[("audit", "2020-03 Acme")])])
+    );
+}
+
+#[test]
+fn modules_without_selected_annotations_have_no_metadata() {
+    let module = compile(&["unknown"]);
+    assert!(module.metadata().is_empty());
+    assert!(read_metadata(&module).unwrap().is_empty());
+    assert_eq!(module, compile(&[]));
+}
+
+#[test]
+fn metadata_is_kept_in_its_table() {
+    let module = compile(&["audit", "since"]);
+    assert_eq!(module.metadata().len(), 1);
+    let mut inner = module.into_inner();
+    inner.metadata.clear();
+    assert_eq!(inner, compile(&[]).into_inner());
+}
+
+#[test]
+fn program_modules_embed_their_metadata() {
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        verify: true,
+        metadata_keys: vec!["audit".to_string()].into_iter().collect(),
+        ..Compiler::default()
+    };
+    let program = compiler
+        .into_compiled_program(&format!(
+            "modules:\n{}\nscript:\nmain() {{ return; }}",
+            MODULE
+        ))
+        .unwrap();
+    assert_eq!(
+        read_metadata(&program.modules[0]).unwrap().annotations,
+        annotations(&[("pay", &[("audit", "2020-03 Acme")])])
+    );
+}
+
+#[test]
+fn a_module_has_one_entry_per_key() {
+    let module = compile(&["audit"]);
+    let metadata = Metadata {
+        annotations: annotations(&[("plain", &[("audit", "none")])]),
+        ..Metadata::default()
+    };
+    assert!(embed_metadata(module, &metadata).is_err());
+}
+
+#[test]
+fn build_info_is_recorded_on_request() {
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        verify: true,
+        fold_constants: true,
+        cfg: CfgOptions {
+            flags: vec!["test".to_string()].into_iter().collect(),
+            ..CfgOptions::default()
+        },
+        record_build_info: true,
+        ..Compiler::default()
+    };
+    let expected = BuildInfo {
+        language_version: Some(1),
+        compiler_version: COMPILER_VERSION.to_string(),
+        options: vec![
+            "cfg=test".to_string(),
+            "fold_constants".to_string(),
+            "verify".to_string(),
+        ],
+    };
+    assert_eq!(compiler.build_info(Some(1)), expected);
+
+    let module = compiler
+        .into_compiled_module(&format!("pragma language 1;\n{}", MODULE))
+        .unwrap();
+    let mut bytes = vec![];
+    module.serialize(&mut bytes).unwrap();
+    let module = CompiledModule::deserialize(&bytes).unwrap();
+    assert_eq!(read_build_info(&module).unwrap(), Some(expected));
+    assert!(read_metadata(&module).unwrap().annotations.is_empty());
+}
+
+#[test]
+fn build_info_is_not_recorded_by_default() {
+    assert_eq!(read_build_info(&compile(&["audit"])).unwrap(), None);
+}
@@ -0,0 +1,90 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::{
+    metrics::{FunctionMetrics, Metrics, ReportedFunction},
+    parser::{parse_module, parse_script},
+};
+
+const MODULE: &str = "module M {
+    resource T<X> { x: X }
+
+    native public hash(data: bytearray): bytearray;
+
+    public id<X>(x: X): X {
+        return move(x);
+    }
+
+    public f(b: bool, n: u64): u64 acquires T {
+        let i: u64;
+        i = 0;
+        if (copy(b) && (copy(n) > 0 || exists<T<u64>>(get_txn_sender()))) {
+            while (copy(i) < copy(n)) {
+                if (copy(i) == 3) {
+                    break;
+                }
+                i = Self.id<u64>(move(i) + 1);
+            }
+        } else {
+            i = *&borrow_global<T<u64>>(get_txn_sender()).x;
+        }
+        return move(i);
+    }
+}
+";
+
+#[test]
+fn module_metrics() {
+    let module = parse_module(MODULE).unwrap();
+    let metrics = Metrics::for_module(&module);
+    assert_eq!(
+        metrics.functions,
+        vec![
+            FunctionMetrics {
+                function: ReportedFunction::in_module("M", "id"),
+                statements: 1,
+                cyclomatic_complexity: 1,
+                max_nesting_depth: 0,
+                global_accesses: 0,
+                generic_instantiations: 0,
+            },
+            FunctionMetrics {
+                function: ReportedFunction::in_module("M", "f"),
+                statements: 8,
+                cyclomatic_complexity: 6,
+                max_nesting_depth: 3,
+                global_accesses: 2,
+                generic_instantiations: 3,
+            },
+        ]
+    );
+}
+
+#[test]
+fn script_metrics() {
+    let script =
+        parse_script("main() {\n    loop {\n        break;\n    }\n    return;\n}\n").unwrap();
+    let metrics = Metrics::for_script(&script);
+    assert_eq!(metrics.functions.len(), 1);
+    let main = &metrics.functions[0];
+    assert_eq!(main.function, ReportedFunction::main());
+    assert_eq!(
+        (
+            main.statements,
+            main.cyclomatic_complexity,
+            main.max_nesting_depth
+        ),
+        (3, 2, 1)
+    );
+}
+
+#[test]
+fn metrics_round_trip_through_json() {
+    let module = parse_module(MODULE).unwrap();
+    let metrics = Metrics::for_module(&module);
+    let json: serde_json::Value = serde_json::from_slice(&metrics.to_bytes().unwrap()).unwrap();
+    assert_eq!(json["functions"][1]["name"], "f");
+    assert_eq!(json["functions"][1]["cyclomatic_complexity"], 6);
+    let parsed: Metrics = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed, metrics);
+}
@@ -5,10 +5,74 @@
 #[macro_use]
 pub(crate) mod testutils;
 
+mod abi_tests;
+mod acquires_tests;
+mod annotation_tests;
+mod attestation_tests;
+mod borrows_tests;
 mod branch_tests;
+mod build_tests;
+mod builders_tests;
+mod bundle_tests;
+mod bytearray_tests;
+mod call_graph_tests;
 mod cfg_tests;
+mod coalesce_tests;
+mod conditional_compilation_tests;
+mod constants_tests;
+mod control_flow_tests;
+mod daemon_tests;
+mod dataflow_tests;
+mod dead_stores_tests;
+mod dependencies_tests;
+mod determinism_tests;
+mod diagnostics_tests;
+mod directives_tests;
+mod docgen_tests;
+mod duplicate_tests;
 mod expression_tests;
+mod footprint_tests;
+mod formatter_tests;
+mod freeze_tests;
 mod function_tests;
+mod gas_estimate_tests;
+mod golden_tests;
 mod import_tests;
+mod imported_module_checks_tests;
+mod include_tests;
+mod incremental_tests;
+mod inline_tests;
+mod interface_tests;
+mod language_version_tests;
+mod limits_tests;
+mod lint_tests;
+mod metadata_tests;
+mod metrics_tests;
+mod moves_tests;
+mod mutation_tests;
+mod name_tests;
+mod natives_tests;
+mod nesting_tests;
+mod parallel_parse_tests;
+mod partial_tests;
+mod pipeline_tests;
+mod placeholder_tests;
+mod plugins_tests;
+mod relocate_tests;
+mod rename_tests;
+mod resolver_tests;
+mod roundtrip_tests;
 mod serializer_tests;
+mod skip_specs_tests;
+mod snapshot_tests;
+mod spec_tests;
+mod specialize_tests;
+mod ssa_tests;
 mod stdlib_scripts;
+mod streaming_tests;
+mod strict_tests;
+mod taint_tests;
+mod target_tests;
+mod tuples_tests;
+mod type_checker_tests;
+mod verify_tests;
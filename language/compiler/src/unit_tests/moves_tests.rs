@@ -0,0 +1,147 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::Compiler;
+use bytecode_source_map::utils::line_and_column;
+use ir_to_bytecode::{
+    formatter::{format_module, format_script, FormatOptions},
+    moves::{resolve_module, resolve_script, MoveReason},
+    parser::{parse_module, parse_script},
+};
+
+const MODULE: &str = "module M {
+    resource T { v: u64 }
+    twice(x: u64): u64 {
+        return x + x;
+    }
+    sum(n: u64): u64 {
+        let i: u64;
+        let s: u64;
+        i = 0;
+        s = 0;
+        while (i < n) {
+            s = s + i;
+            i = i + 1;
+        }
+        return s;
+    }
+    unwrap(t: Self.T): u64 {
+        let v: u64;
+        T { v } = t;
+        return v;
+    }
+    read(x: u64): u64 {
+        let r: &u64;
+        r = &x;
+        return x + *move(r);
+    }
+    value(t: &mut Self.T): u64 {
+        return *&t.v + *&mut t.v;
+    }
+}
+";
+
+#[test]
+fn bare_locals_are_moved_at_their_last_use() {
+    let mut module = parse_module(MODULE).unwrap();
+    let notes: Vec<String> = resolve_module(&mut module)
+        .iter()
+        .map(|inferred| {
+            let (line, column) = line_and_column(MODULE, inferred.loc.start());
+            format!("{}:{}: {}", line, column, inferred)
+        })
+        .collect();
+    assert_eq!(
+        notes,
+        vec![
+            "4:20: `x` moved, as it is not used afterwards",
+            "12:17: `s` moved, as it is not used afterwards",
+            "13:17: `i` moved, as it is not used afterwards",
+            "15:16: `s` moved, as it is not used afterwards",
+            "19:19: `t` moved, as its type cannot be copied",
+            "20:16: `v` moved, as it is not used afterwards",
+            "28:30: `t` moved, as it is not used afterwards",
+        ]
+    );
+    let formatted = format_module(&module, &FormatOptions::default());
+    for resolved in &[
+        "return copy(x) + move(x);",
+        "while (copy(i) < copy(n)) {",
+        // The values of `s` and `i` are replaced, so they are not used afterwards.
+        "s = move(s) + copy(i);",
+        "i = move(i) + 1;",
+        "return move(s);",
+        "T { v } = move(t);",
+        // `x` is borrowed, so it is copied even at its last use.
+        "return copy(x) + *move(r);",
+        "return *&copy(t).v + *&mut move(t).v;",
+    ] {
+        assert!(formatted.contains(resolved), "{}", formatted);
+    }
+}
+
+#[test]
+fn implicit_moves_verify() {
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        verify: true,
+        ..Compiler::default()
+    };
+    let err = compiler
+        .clone()
+        .into_compiled_module(MODULE)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("local x is used without `move` or `copy`"),
+        "{}",
+        err
+    );
+    let compiler = Compiler {
+        implicit_moves: true,
+        check_types: true,
+        ..compiler
+    };
+    compiler.into_compiled_module(MODULE).unwrap();
+}
+
+#[test]
+fn bare_names_are_locals_unless_followed_by_fields() {
+    let mut module = parse_module(
+        "module M {
+    struct S { x: u64 }
+    f(x: u64): Self.S {
+        return S { x: x };
+    }
+}",
+    )
+    .unwrap();
+    assert_eq!(resolve_module(&mut module).len(), 1);
+    let formatted = format_module(&module, &FormatOptions::default());
+    assert!(
+        formatted.contains("return S { x: move(x) };"),
+        "{}",
+        formatted
+    );
+}
+
+#[test]
+fn scripts_resolve_bare_locals() {
+    let mut script = parse_script(
+        "main(x: u64) {
+    let y: u64;
+    y = x * x;
+    return;
+}",
+    )
+    .unwrap();
+    let moves = resolve_script(&mut script);
+    assert_eq!(moves.len(), 1);
+    assert_eq!(moves[0].reason, MoveReason::LastUse);
+    let formatted = format_script(&script, &FormatOptions::default());
+    assert!(
+        formatted.contains("y = copy(x) * move(x);"),
+        "{}",
+        formatted
+    );
+}
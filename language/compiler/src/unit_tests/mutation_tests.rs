@@ -0,0 +1,51 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::unit_tests::testutils::compile_module_string;
+use ir_to_bytecode::mutation::{mutants, MutationKind};
+
+const MODULE: &str = "
+module M {
+    // Returns the number of steps to count up to `n`.
+    public count(n: u64): u64 {
+        let i: u64;
+        assert(copy(n) < 100, 7);
+        i = 0;
+        while (copy(i) < copy(n)) {
+            i = move(i) + 1;
+        }
+        return move(i);
+    }
+}
+";
+
+#[test]
+fn mutates_operators_conditions_and_literals() {
+    let mutants = mutants(MODULE).unwrap();
+    let descriptions: Vec<_> = mutants
+        .iter()
+        .map(|mutant| (mutant.kind, mutant.description.as_str()))
+        .collect();
+    assert_eq!(
+        descriptions,
+        vec![
+            (MutationKind::NegateCondition, "negate condition"),
+            (MutationKind::SwapOperator, "replace `<` with `<=`"),
+            (MutationKind::ShiftLiteral, "replace 100 with 101"),
+            (MutationKind::ShiftLiteral, "replace 100 with 99"),
+            (MutationKind::ShiftLiteral, "replace 7 with 8"),
+            (MutationKind::ShiftLiteral, "replace 7 with 6"),
+            (MutationKind::ShiftLiteral, "replace 0 with 1"),
+            (MutationKind::NegateCondition, "negate condition"),
+            (MutationKind::SwapOperator, "replace `<` with `<=`"),
+            (MutationKind::SwapOperator, "replace `+` with `-`"),
+            (MutationKind::ShiftLiteral, "replace 1 with 2"),
+            (MutationKind::ShiftLiteral, "replace 1 with 0"),
+        ]
+    );
+    assert!(mutants[0].source.contains("assert(!(copy(n) < 100), 7);"));
+    assert!(mutants[9].source.contains("i = move(i) - 1;"));
+    for mutant in &mutants {
+        compile_module_string(&mutant.source).unwrap();
+    }
+}
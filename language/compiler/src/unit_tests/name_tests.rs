@@ -0,0 +1,114 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::parser::{
+    parse_module, parse_module_with_options, try_parse_script_or_module, ParserOptions,
+    FUTURE_KEYWORDS,
+};
+
+// Returns the message of the syntax error of `source` and the text it points at.
+fn syntax_error(source: &str) -> (String, &str) {
+    let error = try_parse_script_or_module(source).unwrap_err();
+    let loc = error.loc.unwrap();
+    (
+        error.message,
+        &source[loc.start().0 as usize..loc.end().0 as usize],
+    )
+}
+
+#[test]
+fn invalid_names_are_located() {
+    for (source, name) in &[
+        ("module M$ { }", "M$"),
+        ("module M { struct S$ { f: u64 } }", "S$"),
+        ("module M { struct S { $f: u64 } }", "$f"),
+        ("module M { f$() { return; } }", "f$"),
+        ("module M { f() { let x$: u64; return; } }", "x$"),
+        (
+            "module M { f(t: &Self.T) { _ = &move(t).$f; return; } }",
+            "$f",
+        ),
+        ("module M { f(t: &Self.T) { _ = &_.f; return; } }", "_"),
+        ("module M { f<$T>() { return; } }", "$T"),
+        ("main() { return Self$.f(); }", "Self$"),
+    ] {
+        let (message, text) = syntax_error(source);
+        assert_eq!(message, format!("Invalid name {}", name), "{}", source);
+        assert_eq!(text, *name, "{}", source);
+    }
+}
+
+#[test]
+fn valid_names_are_accepted() {
+    assert!(parse_module(
+        "module M_1 { struct _S { _f: u64 } _g(_x: u64): u64 { _ = 1; return move(_x); } }"
+    )
+    .is_ok());
+}
+
+#[test]
+fn builtin_names_are_contextual() {
+    // The builtins, `emit` and `pragma` are only keywords where the grammar expects them.
+    for source in &[
+        "module M { struct S { rotl: u64, emit: u64, pragma: bool } }",
+        "module M { native public sha3_256(data: bytearray): bytearray; }",
+        "module M { to_bytes<T>(rotl: T): T { return move(rotl); } }",
+        "module M { f(emit: u64) { let rotl: u64; rotl = rotl(move(emit), 1u8); return; } }",
+        "module M { f(emit: u64) { emit = count_ones(copy(emit)); return; } }",
+        "module M { f(emit: u64) { _ = Self.checked_add(move(emit)); return; } }",
+        "module M { f(s: Self.S) { emit, pragma = (1, 2); S { emit: pragma } = move(s); return; } }",
+    ] {
+        assert!(parse_module(source).is_ok(), "{}", source);
+    }
+}
+
+#[test]
+fn move_and_copy_are_contextual() {
+    // `move` and `copy` are only keywords before a local, in parentheses or not.
+    for source in &[
+        "module M { struct S { move: u64, copy: bool } }",
+        "module M { f() { let move: u64; move = 1; move = move + 1; return; } }",
+        "module M { f(copy: u64): u64 { let move: u64; move = copy(copy); return move move; } }",
+        "module M { f(s: &Self.S): u64 { return *&move s.move; } }",
+        "module M { f(move: u64): u64 ensures RET == move { return move(move); } }",
+    ] {
+        assert!(parse_module(source).is_ok(), "{}", source);
+    }
+}
+
+#[test]
+fn reserved_names_are_rejected_on_request() {
+    let options = ParserOptions {
+        reserved_names: FUTURE_KEYWORDS,
+        ..ParserOptions::default()
+    };
+    for (source, name) in &[
+        ("module vector { }", "vector"),
+        ("module M { struct S { signer: u64 } }", "signer"),
+        ("module M { f<const>() { return; } }", "const"),
+        ("module M { f() { return friend.g(); } }", "friend"),
+        ("module M { f() { return Self.friend(); } }", "friend"),
+    ] {
+        assert!(parse_module(source).is_ok(), "{}", source);
+        let error = parse_module_with_options(source, options).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with(&format!("ParserError: Reserved name {} at ", name)),
+            "{}: {}",
+            source,
+            error
+        );
+    }
+}
+
+#[test]
+fn names_of_specifications_are_not_checked() {
+    assert!(parse_module(
+        "module M {
+    resource T { i: u64, invariant {$pack} $i > 0 }
+    f(x: u64): u64 ensures RET == $x { return move(x); }
+}"
+    )
+    .is_ok());
+}
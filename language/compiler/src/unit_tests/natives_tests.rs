@@ -0,0 +1,120 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::Compiler;
+use bytecode_source_map::utils::line_and_column;
+use ir_to_bytecode::{errors::NativeError, natives::check_module_natives, parser::parse_module};
+use libra_types::{account_address::AccountAddress, account_config};
+use stdlib::stdlib::module_defs;
+
+fn native_errors(source: &str, address: AccountAddress) -> Vec<String> {
+    let module = parse_module(source).unwrap();
+    error_lines(source, &check_module_natives(&module, address))
+}
+
+fn error_lines(source: &str, errors: &[NativeError]) -> Vec<String> {
+    errors
+        .iter()
+        .map(|err| {
+            let (line, _) = line_and_column(source, err.loc.start());
+            format!("{}: {}", line, err)
+        })
+        .collect()
+}
+
+#[test]
+fn stdlib_natives_match_the_vm() {
+    for module in module_defs() {
+        let errors = check_module_natives(module, account_config::core_code_address());
+        assert!(errors.is_empty(), "{}: {:?}", module.name, errors);
+    }
+}
+
+#[test]
+fn mismatched_natives_are_reported() {
+    let source = "module Hash {
+    native public sha2_256(data: bytearray, extra: u64): bytearray;
+    native public sha3_256(data: bytearray);
+    native public sha1(data: bytearray): bytearray;
+}
+";
+    assert_eq!(
+        native_errors(source, account_config::core_code_address()),
+        vec![
+            "2: native function `sha2_256` takes 1 arguments, but is declared with 2",
+            "3: native function `sha3_256` returns 1 values, but is declared to return 0",
+            "4: the VM implements no native function `Hash.sha1` for modules published at \
+             0000000000000000000000000000000000000000000000000000000000000000",
+        ]
+    );
+}
+
+#[test]
+fn native_type_parameters_are_checked() {
+    let source = "module LCS {
+    native public serialize<T: resource>(v: &T): bytearray;
+}
+";
+    assert_eq!(
+        native_errors(source, account_config::core_code_address()),
+        vec![
+            "2: type parameter `T` of native function `serialize` has kind `all`, but is \
+             declared with kind `resource`"
+        ]
+    );
+    let source = "module LCS {
+    native public serialize(v: &u64): bytearray;
+}
+";
+    assert_eq!(
+        native_errors(source, account_config::core_code_address()),
+        vec!["2: native function `serialize` has 1 type parameters, but is declared with 0"]
+    );
+}
+
+#[test]
+fn natives_of_other_addresses_are_reported() {
+    let source = "module Hash {
+    native public sha3_256(data: bytearray): bytearray;
+}
+";
+    let address = AccountAddress::new([1; 32]);
+    let errors = native_errors(source, address);
+    assert_eq!(errors.len(), 1);
+    assert!(
+        errors[0].ends_with(&format!(
+            "for modules published at {}; it implements the one published at {}",
+            address,
+            account_config::core_code_address()
+        )),
+        "{}",
+        errors[0]
+    );
+}
+
+#[test]
+fn verifying_compiler_checks_natives() {
+    let source = "module Signature {
+    native public ed25519_verify(signature: bytearray, public_key: bytearray): bool;
+}
+";
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        verify: true,
+        ..Compiler::default()
+    };
+    let err = compiler
+        .into_compiled_module(source)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.starts_with("Native function checking failed:\n2:5: "),
+        "{}",
+        err
+    );
+    assert!(
+        err.contains("`ed25519_verify` takes 3 arguments"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,134 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    unit_tests::testutils::{compile_script_string, verifying_compiler},
+    Compiler,
+};
+use ir_to_bytecode::parser::{
+    parse_program, parse_program_with_options, ParserOptions, DEFAULT_MAX_EXP_DEPTH,
+};
+
+const DEPTH: usize = 100_000;
+
+// A length of chains whose code fits in a function.
+const CHAIN: usize = 30_000;
+
+fn script(exp: &str) -> String {
+    format!("main() {{ let x: u64; x = {}; return; }}", exp)
+}
+
+fn parse_nested(exp: &str) -> anyhow::Result<()> {
+    parse_program(&script(exp)).map(|_| ())
+}
+
+// Parses `exp` without a limit on its depth.
+fn parse_unbounded(exp: &str) -> anyhow::Result<()> {
+    parse_program_with_options(&script(exp), unbounded()).map(|_| ())
+}
+
+// Options without a limit on the depth of expressions.
+fn unbounded() -> ParserOptions {
+    ParserOptions {
+        max_exp_depth: None,
+        ..ParserOptions::default()
+    }
+}
+
+fn assert_too_deep(exp: &str) {
+    let error = parse_nested(exp).unwrap_err().to_string();
+    assert!(
+        error.contains(&format!(
+            "Expression nested deeper than {}",
+            DEFAULT_MAX_EXP_DEPTH
+        )),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn deeply_nested_not() {
+    assert_too_deep(&format!("{}true", "!".repeat(DEFAULT_MAX_EXP_DEPTH)));
+    parse_nested(&format!("{}true", "!".repeat(DEFAULT_MAX_EXP_DEPTH - 1))).unwrap();
+    parse_unbounded(&format!("{}true", "!".repeat(DEPTH))).unwrap();
+}
+
+#[test]
+fn deeply_nested_parens() {
+    let nested = |depth| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+    assert_too_deep(&nested(DEFAULT_MAX_EXP_DEPTH));
+    parse_nested(&nested(DEFAULT_MAX_EXP_DEPTH - 1)).unwrap();
+    parse_unbounded(&nested(DEPTH)).unwrap();
+}
+
+#[test]
+fn long_binop_chains_are_not_deep() {
+    parse_nested(&vec!["1"; DEPTH].join(" + ")).unwrap();
+    parse_nested(&vec!["true"; DEPTH].join(" && ")).unwrap();
+}
+
+#[test]
+fn long_binop_chains_compile() {
+    compile_script_string(&script(&vec!["1"; CHAIN].join(" + "))).unwrap();
+}
+
+#[test]
+fn deeply_nested_expressions_compile() {
+    let compiler = || Compiler {
+        parser_options: unbounded(),
+        ..verifying_compiler()
+    };
+    let parens = format!("{}1{}", "(".repeat(DEPTH), ")".repeat(DEPTH));
+    compiler().into_script(&script(&parens)).unwrap();
+    let not = format!("{}(1 == 1)", "!".repeat(CHAIN));
+    let code = format!("main() {{ let x: bool; x = {}; return; }}", not);
+    compiler().into_script(&code).unwrap();
+}
+
+#[test]
+fn long_binop_chains_are_cloned_and_dropped() {
+    // Cloning and dropping the AST do not recurse over the chain, which is as deep as it is long.
+    let program = parse_program(&script(&vec!["1"; DEPTH].join(" + "))).unwrap();
+    let copy = program.clone();
+    drop(program);
+    drop(copy);
+    let parens = format!("{}1{}", "(".repeat(DEPTH), ")".repeat(DEPTH));
+    let program = parse_program_with_options(&script(&parens), unbounded()).unwrap();
+    drop(program.clone());
+}
+
+#[test]
+fn parenthesized_right_operands_are_deep() {
+    let nested = |depth| format!("{}1{}", "1 + (".repeat(depth), ")".repeat(depth));
+    assert_too_deep(&nested(DEFAULT_MAX_EXP_DEPTH));
+    parse_nested(&nested(DEFAULT_MAX_EXP_DEPTH - 1)).unwrap();
+}
+
+#[test]
+fn max_exp_depth_is_configurable() {
+    let options = ParserOptions {
+        max_exp_depth: Some(8),
+        ..ParserOptions::default()
+    };
+    let parse = |exp: &str| parse_program_with_options(&script(exp), options).map(|_| ());
+    let error = parse("((((((((1))))))))").unwrap_err().to_string();
+    assert!(
+        error.contains("Expression nested deeper than 8"),
+        "{}",
+        error
+    );
+    parse("(((((((1)))))))").unwrap();
+    parse(&vec!["1"; DEPTH].join(" + ")).unwrap();
+}
+
+#[test]
+fn wide_expressions_are_not_deep() {
+    // The elements of a list are siblings, and only the deepest counts.
+    let element = format!(
+        "{}1{}",
+        "(".repeat(DEFAULT_MAX_EXP_DEPTH - 2),
+        ")".repeat(DEFAULT_MAX_EXP_DEPTH - 2)
+    );
+    parse_nested(&format!("({})", vec![element.as_str(); 100].join(", "))).unwrap();
+}
@@ -0,0 +1,72 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::parser::{parse_program_with_options, ParserOptions, DEFAULT_MAX_EXP_DEPTH};
+
+const PARALLEL: ParserOptions = ParserOptions {
+    skip_specs: false,
+    reject_specs: false,
+    parallel: true,
+    max_byte_array_len: None,
+    reserved_names: &[],
+    strict: false,
+    max_exp_depth: Some(DEFAULT_MAX_EXP_DEPTH),
+};
+
+fn program(module_count: usize) -> String {
+    let mut program = "modules:\n".to_string();
+    for i in 0..module_count {
+        program.push_str(&format!(
+            "module M{} {{
+    resource T {{ i: u64, invariant i > {} }}
+    public f{}(x: u64): u64
+    ensures RET == x
+    {{
+        if (copy(x) > {}) {{ return move(x) * 2; }}
+        return move(x);
+    }}
+}}
+",
+            i, i, i, i
+        ));
+    }
+    program
+        .push_str("script:\nimport Transaction.M0;\nmain() {\n    _ = M0.f0(1);\n    return;\n}\n");
+    program
+}
+
+#[test]
+fn parallel_parse_matches_sequential_parse() {
+    for module_count in &[0, 1, 2, 7] {
+        let source = program(*module_count);
+        let sequential = parse_program_with_options(&source, ParserOptions::default()).unwrap();
+        let parallel = parse_program_with_options(&source, PARALLEL).unwrap();
+        assert_eq!(parallel.modules, sequential.modules);
+        assert_eq!(parallel.script, sequential.script);
+    }
+}
+
+#[test]
+fn parallel_parse_reports_sequential_errors() {
+    let source = program(4);
+    let sources = vec![
+        // An error in a module.
+        source.replacen("public f2", "public f2 f2", 1),
+        // Unbalanced braces, which split the program at the wrong places.
+        source.replacen("return move(x) * 2; }", "return move(x) * 2;", 1),
+        source.replacen("{ i: u64", "{ i: u64 }}", 1),
+        // A module after the script.
+        source.clone() + "module N {}\n",
+        // A missing script.
+        source.replacen("script:", "", 1),
+    ];
+    for source in sources {
+        let sequential = parse_program_with_options(&source, ParserOptions::default())
+            .map(|_| ())
+            .map_err(|err| err.to_string());
+        let parallel = parse_program_with_options(&source, PARALLEL)
+            .map(|_| ())
+            .map_err(|err| err.to_string());
+        assert_eq!(parallel, sequential);
+    }
+}
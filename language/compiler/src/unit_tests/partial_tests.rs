@@ -0,0 +1,155 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{unit_tests::testutils::verifying_compiler, util::function_error_report, Compiler};
+use ir_to_bytecode::{
+    errors::TupleError,
+    parser::parse_module,
+    partial::{stub_failed_functions, FAILED_FUNCTION_ABORT_CODE},
+};
+use vm::{
+    access::ModuleAccess,
+    file_format::{Bytecode, CompiledModule},
+};
+
+const MODULE: &str = "module M {
+    public ok(x: u64): u64 {
+        return move(x) + 1;
+    }
+
+    public typo(x: u64): u64 {
+        return move(y);
+    }
+
+    public calls_typo(): u64 {
+        return Self.typo(1);
+    }
+
+    public stray_break() {
+        break;
+    }
+}
+";
+
+fn code(module: &CompiledModule, name: &str) -> Vec<Bytecode> {
+    let def = module
+        .function_defs()
+        .iter()
+        .find(|def| {
+            module
+                .identifier_at(module.function_handle_at(def.function).name)
+                .as_str()
+                == name
+        })
+        .unwrap();
+    def.code.code.clone()
+}
+
+fn stub() -> Vec<Bytecode> {
+    vec![Bytecode::LdU64(FAILED_FUNCTION_ABORT_CODE), Bytecode::Abort]
+}
+
+#[test]
+fn failed_functions_are_stubbed_and_reported_together() {
+    let (module, errors) = verifying_compiler()
+        .into_partially_compiled_module(MODULE)
+        .unwrap();
+    let failed: Vec<_> = errors
+        .iter()
+        .map(|error| (error.function.to_string(), error.message.as_str()))
+        .collect();
+    assert_eq!(
+        failed,
+        vec![
+            ("typo".to_string(), "variable y undefined"),
+            ("stray_break".to_string(), "break outside loop"),
+        ]
+    );
+    assert_eq!(code(&module, "typo"), stub());
+    assert_eq!(code(&module, "stray_break"), stub());
+    assert_ne!(code(&module, "ok"), stub());
+    assert_ne!(code(&module, "calls_typo"), stub());
+
+    let report = function_error_report(MODULE, &errors);
+    let lines: Vec<_> = report.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(
+        lines[0].ends_with(": in `typo`: variable y undefined"),
+        "{}",
+        report
+    );
+    assert!(
+        lines[1].ends_with(": in `stray_break`: break outside loop"),
+        "{}",
+        report
+    );
+}
+
+#[test]
+fn errors_of_the_passes_are_located_in_their_function() {
+    let source = "module M {
+    public bad(): u64 {
+        return true + 1;
+    }
+
+    public good(): u64 {
+        return 1;
+    }
+}
+";
+    let checked = Compiler {
+        check_types: true,
+        ..verifying_compiler()
+    };
+    let (module, errors) = checked.into_partially_compiled_module(source).unwrap();
+    assert!(!errors.is_empty());
+    assert!(errors
+        .iter()
+        .all(|error| error.function.to_string() == "bad"));
+    let report = function_error_report(source, &errors);
+    assert!(report.starts_with("3:"), "{}", report);
+    assert_eq!(code(&module, "bad"), stub());
+    assert_eq!(
+        code(&module, "good"),
+        vec![Bytecode::LdU64(1), Bytecode::Ret]
+    );
+}
+
+#[test]
+fn errors_outside_of_functions_are_not_tolerated() {
+    let mut module = parse_module(
+        "module M {
+    struct S { f: u64 }
+
+    f() {
+        return;
+    }
+}",
+    )
+    .unwrap();
+    let struct_loc = module.structs[0].span;
+    let function_loc = module.functions[0].1.span;
+    let errors = vec![
+        TupleError {
+            loc: struct_loc,
+            message: "in the struct".to_string(),
+        },
+        TupleError {
+            loc: function_loc,
+            message: "in the function".to_string(),
+        },
+    ];
+    let (failed, rest) = stub_failed_functions(&mut module, errors, |error| Some(error.loc));
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].message, "in the function");
+    assert_eq!(rest.len(), 1);
+    assert_eq!(rest[0].message, "in the struct");
+}
+
+#[test]
+fn full_compilation_still_fails_on_the_first_error() {
+    let err = verifying_compiler()
+        .into_compiled_module(MODULE)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "variable y undefined");
+}
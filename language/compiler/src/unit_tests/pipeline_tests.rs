@@ -0,0 +1,87 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{Compiler, PassReports};
+use ir_to_bytecode::rename::RenameScheme;
+
+const MODULE: &str = "module M {
+    double(x: u64): u64 {
+        let debug: bool;
+        debug = false;
+        if (move(debug)) {
+            abort 1;
+        }
+        return x * 2;
+    }
+    public f(n: u64): u64 {
+        return Self.double(move(n));
+    }
+}";
+
+fn compiler() -> Compiler {
+    Compiler {
+        skip_stdlib_deps: true,
+        verify: true,
+        implicit_moves: true,
+        check_types: true,
+        fold_constants: true,
+        rename: Some(RenameScheme::Prefix("r_".to_string())),
+        ..Compiler::default()
+    }
+}
+
+#[test]
+fn module_reports_its_passes() {
+    let compiler = compiler();
+    let mut reports = PassReports::default();
+    let module = compiler.parse_module(MODULE, &mut reports).unwrap();
+    let output = compiler
+        .clone()
+        .compile_parsed_module(MODULE, module, &mut reports)
+        .unwrap();
+    assert!(!reports.moves.is_empty());
+    assert!(!reports.folded_branches.is_empty());
+    assert_eq!(reports.renames.len(), 1);
+    assert_eq!(reports.renames[0].functions["double"], "r_double");
+    assert!(output
+        .checked
+        .functions
+        .iter()
+        .any(|(name, _)| name.as_inner().as_str() == "r_double"));
+    assert_eq!(
+        output.module,
+        compiler.into_compiled_module(MODULE).unwrap()
+    );
+}
+
+#[test]
+fn program_reports_the_passes_of_its_modules() {
+    let code = format!("modules:\n{}\nscript:\nmain() {{ return; }}", MODULE);
+    let compiler = compiler();
+    let mut reports = PassReports::default();
+    let program = compiler.parse_program(&code, &mut reports).unwrap();
+    let output = compiler
+        .compile_parsed_program(&code, program, &mut reports)
+        .unwrap();
+    assert!(!reports.moves.is_empty());
+    assert!(!reports.folded_branches.is_empty());
+    assert_eq!(reports.renames.len(), 1);
+    assert_eq!(output.program.modules.len(), 1);
+    assert_eq!(output.checked.modules.len(), 1);
+}
+
+#[test]
+fn reports_survive_a_failing_pass() {
+    let code = "module M {
+    f(x: u64): bool {
+        return x;
+    }
+}";
+    let mut reports = PassReports::default();
+    let module = compiler().parse_module(code, &mut reports).unwrap();
+    compiler()
+        .compile_parsed_module(code, module, &mut reports)
+        .unwrap_err();
+    assert_eq!(reports.moves.len(), 1);
+    assert!(reports.renames.is_empty());
+}
@@ -0,0 +1,116 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::Compiler;
+use ir_to_bytecode::{
+    errors::PlaceholderError,
+    parser::parse_script,
+    placeholders::{placeholders, substitute_placeholders},
+};
+use libra_types::{account_address::AccountAddress, identifier::Identifier};
+use move_ir_types::ast::CopyableVal_;
+use std::collections::BTreeMap;
+
+const PAY: &str = "
+import 0x0.LibraAccount;
+main() {
+    if ({{amount}} > 0) {
+        LibraAccount.pay_from_sender({{recipient}}, {{amount}});
+    }
+    return;
+}
+";
+
+fn ident(name: &str) -> Identifier {
+    Identifier::new(name).unwrap()
+}
+
+fn pay_values(amount: CopyableVal_) -> BTreeMap<Identifier, CopyableVal_> {
+    let mut values = BTreeMap::new();
+    values.insert(ident("amount"), amount);
+    values.insert(
+        ident("recipient"),
+        CopyableVal_::Address(AccountAddress::new([1; 32])),
+    );
+    values
+}
+
+#[test]
+fn placeholders_are_listed() {
+    let script = parse_script(PAY).unwrap();
+    let names: Vec<_> = placeholders(&script).into_iter().collect();
+    assert_eq!(names, vec![ident("amount"), ident("recipient")]);
+}
+
+#[test]
+fn placeholders_are_substituted() {
+    let mut script = parse_script(PAY).unwrap();
+    substitute_placeholders(&mut script, &pay_values(CopyableVal_::U64(10))).unwrap();
+    assert!(placeholders(&script).is_empty());
+
+    let compiler = Compiler {
+        placeholder_values: pay_values(CopyableVal_::U64(10)),
+        ..Compiler::default()
+    };
+    compiler.into_script(PAY).unwrap();
+}
+
+#[test]
+fn missing_values_are_located() {
+    let mut values = pay_values(CopyableVal_::U64(10));
+    values.remove(&ident("recipient"));
+
+    let mut script = parse_script(PAY).unwrap();
+    match substitute_placeholders(&mut script, &values) {
+        Err(PlaceholderError::Missing { name, .. }) => assert_eq!(name, ident("recipient")),
+        result => panic!("unexpected result {:?}", result),
+    }
+
+    let compiler = Compiler {
+        placeholder_values: values,
+        ..Compiler::default()
+    };
+    let err = compiler.into_script(PAY).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "5:38: no value for placeholder {{recipient}}"
+    );
+}
+
+#[test]
+fn unused_values_are_rejected() {
+    let mut values = pay_values(CopyableVal_::U64(10));
+    values.insert(ident("memo"), CopyableVal_::Bool(true));
+    let mut script = parse_script(PAY).unwrap();
+    assert_eq!(
+        substitute_placeholders(&mut script, &values),
+        Err(PlaceholderError::Unused {
+            name: ident("memo")
+        })
+    );
+}
+
+#[test]
+fn unsubstituted_placeholders_do_not_compile() {
+    let err = Compiler::default().into_script(PAY).unwrap_err();
+    assert!(
+        err.to_string().contains("no value for placeholder"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn values_of_the_wrong_type_are_rejected() {
+    let compiler = Compiler {
+        placeholder_values: pay_values(CopyableVal_::Bool(true)),
+        check_types: true,
+        ..Compiler::default()
+    };
+    let err = compiler.into_script(PAY).unwrap_err();
+    assert!(
+        err.to_string().starts_with("Type checking failed"),
+        "{}",
+        err
+    );
+}
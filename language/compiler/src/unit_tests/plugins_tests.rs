@@ -0,0 +1,172 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{unit_tests::testutils::verifying_compiler, Compiler};
+use ir_to_bytecode::{
+    formatter::{format_module, FormatOptions},
+    lint::{Lint, Report, Severity},
+    metadata::read_metadata,
+    plugins::{Plugin, Plugins},
+};
+use move_ir_types::ast::{FunctionVisibility, ModuleDefinition};
+use std::sync::{Arc, Mutex};
+
+const MODULE: &str = "module M {
+    public f(): u64
+        ensures RET == 3
+    {
+        return 1 + 2;
+    }
+
+    public g() {
+        return;
+    }
+}
+";
+
+// Requires specifications on public functions.
+struct RequireSpecs;
+
+impl Plugin for RequireSpecs {
+    fn name(&self) -> &'static str {
+        "require_specs"
+    }
+
+    fn after_parse_module(&self, module: &mut ModuleDefinition, reports: &mut Vec<Report>) {
+        for (name, function) in &module.functions {
+            if function.value.visibility == FunctionVisibility::Public
+                && function.value.specifications.is_empty()
+            {
+                reports.push(Report::new(
+                    function.span,
+                    format!("public function `{}` has no specifications", name),
+                ));
+            }
+        }
+    }
+}
+
+// Downgrades the reports of `require_specs` to warnings.
+struct Lenient;
+
+impl Plugin for Lenient {
+    fn name(&self) -> &'static str {
+        "lenient"
+    }
+
+    fn filter_diagnostic(&self, diagnostic: &mut Lint) -> bool {
+        if diagnostic.rule == "require_specs" {
+            diagnostic.severity = Severity::Warn;
+        }
+        true
+    }
+}
+
+// Drops every report.
+struct Silent;
+
+impl Plugin for Silent {
+    fn name(&self) -> &'static str {
+        "silent"
+    }
+
+    fn filter_diagnostic(&self, _diagnostic: &mut Lint) -> bool {
+        false
+    }
+}
+
+// Records the module at both hooks, and annotates its functions once parsed.
+#[derive(Default)]
+struct Recorder {
+    seen: Arc<Mutex<Vec<String>>>,
+}
+
+impl Plugin for Recorder {
+    fn name(&self) -> &'static str {
+        "recorder"
+    }
+
+    fn after_parse_module(&self, module: &mut ModuleDefinition, _reports: &mut Vec<Report>) {
+        for (_, function) in &mut module.functions {
+            function
+                .value
+                .annotations
+                .push(("checked".to_string(), "yes".to_string()));
+        }
+        let formatted = format_module(module, &FormatOptions::default());
+        self.seen.lock().unwrap().push(formatted);
+    }
+
+    fn before_emit_module(&self, module: &mut ModuleDefinition, _reports: &mut Vec<Report>) {
+        let formatted = format_module(module, &FormatOptions::default());
+        self.seen.lock().unwrap().push(formatted);
+    }
+}
+
+#[test]
+fn denied_reports_fail_compilation() {
+    let mut compiler = verifying_compiler();
+    compiler.plugins.add_plugin(Box::new(RequireSpecs));
+    let err = compiler.into_compiled_module(MODULE).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Plugin checks failed:\n\
+         8:5: error: public function `g` has no specifications [require_specs]"
+    );
+}
+
+#[test]
+fn filters_can_downgrade_or_drop_reports() {
+    let mut plugins = Plugins::default();
+    plugins.add_plugin(Box::new(RequireSpecs));
+    plugins.add_plugin(Box::new(Lenient));
+    let lenient = Compiler {
+        plugins: plugins.clone(),
+        ..verifying_compiler()
+    };
+    lenient.into_compiled_module(MODULE).unwrap();
+    let warnings = plugins.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].severity, Severity::Warn);
+    assert!(plugins.take_warnings().is_empty());
+
+    let mut plugins = Plugins::default();
+    plugins.add_plugin(Box::new(RequireSpecs));
+    plugins.add_plugin(Box::new(Silent));
+    let silent = Compiler {
+        plugins: plugins.clone(),
+        ..verifying_compiler()
+    };
+    silent.into_compiled_module(MODULE).unwrap();
+    assert!(plugins.take_warnings().is_empty());
+}
+
+#[test]
+fn hooks_run_before_and_after_the_passes() {
+    let recorder = Recorder::default();
+    let seen = recorder.seen.clone();
+    let mut compiler = Compiler {
+        fold_constants: true,
+        metadata_keys: vec!["checked".to_string()].into_iter().collect(),
+        ..verifying_compiler()
+    };
+    compiler.plugins.add_plugin(Box::new(recorder));
+    let module = compiler.into_compiled_module(MODULE).unwrap();
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 2);
+    assert!(seen[0].contains("return 1 + 2;"), "{}", seen[0]);
+    assert!(seen[1].contains("return 3;"), "{}", seen[1]);
+    // The annotations added once parsed are compiled.
+    let metadata = read_metadata(&module).unwrap();
+    assert_eq!(metadata.annotations["f"]["checked"], "yes");
+    assert_eq!(metadata.annotations["g"]["checked"], "yes");
+}
+
+#[test]
+fn plugins_are_listed_by_name() {
+    let mut plugins = Plugins::default();
+    assert!(plugins.is_empty());
+    plugins.add_plugin(Box::new(RequireSpecs));
+    plugins.add_plugin(Box::new(Silent));
+    assert_eq!(format!("{:?}", plugins), "[\"require_specs\", \"silent\"]");
+}
@@ -0,0 +1,155 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{util::relocation_report, Compiler};
+use ir_to_bytecode::{
+    parser::{parse_module, parse_script},
+    relocate::{parse_relocation, relocate_module, relocate_script, RelocationSite},
+};
+use libra_types::account_address::AccountAddress;
+use move_ir_types::ast::{ModuleDefinition, ModuleIdent};
+use std::collections::BTreeMap;
+use vm::access::ModuleAccess;
+
+const MODULE: &str = "module M {
+    import 0x0.LibraAccount;
+    import 0x0.Other;
+    import 0x7.Kept;
+
+    resource T {
+        i: u64,
+        invariant global_exists<Self.T>(0x0),
+    }
+
+    f(): address
+    ensures RET == 0x0
+    {
+        let a: address;
+        a = 0x0;
+        if (copy(a) == 0x7) {
+            return 0x0;
+        }
+        return move(a);
+    }
+}
+";
+
+fn address(literal: &str) -> AccountAddress {
+    AccountAddress::from_hex_literal(literal).unwrap()
+}
+
+fn relocations(pairs: &[(&str, &str)]) -> BTreeMap<AccountAddress, AccountAddress> {
+    pairs
+        .iter()
+        .map(|(from, to)| (address(from), address(to)))
+        .collect()
+}
+
+fn import_addresses(module: &ModuleDefinition) -> Vec<AccountAddress> {
+    module
+        .imports
+        .iter()
+        .filter_map(|import| match &import.ident {
+            ModuleIdent::Qualified(ident) => Some(ident.address),
+            ModuleIdent::Transaction(_) => None,
+        })
+        .collect()
+}
+
+#[test]
+fn module_addresses_are_relocated() {
+    let mut module = parse_module(MODULE).unwrap();
+    let relocated = relocate_module(&mut module, &relocations(&[("0x0", "0x42")]));
+
+    assert_eq!(
+        import_addresses(&module),
+        vec![address("0x42"), address("0x42"), address("0x7")]
+    );
+    assert_eq!(relocated.len(), 6);
+    assert!(relocated
+        .iter()
+        .all(|relocation| relocation.from == address("0x0") && relocation.to == address("0x42")));
+    match &relocated[0].site {
+        RelocationSite::Import(name) => assert_eq!(name.to_string(), "LibraAccount"),
+        site => panic!("unexpected site {:?}", site),
+    }
+    let literals: Vec<&str> = relocated[2..]
+        .iter()
+        .map(|relocation| {
+            let loc = relocation.loc().unwrap();
+            &MODULE[loc.start().to_usize()..loc.end().to_usize()]
+        })
+        .collect();
+    assert_eq!(
+        literals,
+        vec![
+            "invariant global_exists<Self.T>(0x0)",
+            "ensures RET == 0x0",
+            "0x0",
+            "0x0",
+        ]
+    );
+
+    // Relocating again with the same map has nothing left to rewrite.
+    assert!(relocate_module(&mut module, &relocations(&[("0x0", "0x42")])).is_empty());
+}
+
+#[test]
+fn unmapped_addresses_are_kept() {
+    let mut module = parse_module(MODULE).unwrap();
+    let original = module.clone();
+    assert!(relocate_module(&mut module, &relocations(&[("0x1", "0x2")])).is_empty());
+    assert_eq!(module, original);
+}
+
+#[test]
+fn relocations_are_not_chained() {
+    let mut module = parse_module(MODULE).unwrap();
+    let relocated = relocate_module(&mut module, &relocations(&[("0x0", "0x7"), ("0x7", "0x8")]));
+    assert_eq!(
+        import_addresses(&module),
+        vec![address("0x7"), address("0x7"), address("0x8")]
+    );
+    assert_eq!(relocated.len(), 8);
+}
+
+#[test]
+fn script_addresses_are_relocated() {
+    let source = "import 0x0.LibraAccount;\nmain() {\n    LibraAccount.pay_from_sender(0x0, 1);\n    return;\n}\n";
+    let mut script = parse_script(source).unwrap();
+    let relocated = relocate_script(&mut script, &relocations(&[("0x0", "0x1")]));
+    assert_eq!(
+        relocation_report(source, &relocated),
+        format!(
+            "note: import of LibraAccount relocated from {} to {}\n\
+             3:34: note: address {} relocated to {}",
+            address("0x0"),
+            address("0x1"),
+            address("0x0"),
+            address("0x1"),
+        )
+    );
+}
+
+#[test]
+fn compiled_module_uses_relocated_addresses() {
+    let source = "module M {\n    public f(): address {\n        return 0x2;\n    }\n}\n";
+    let compiler = Compiler {
+        relocations: relocations(&[("0x2", "0x3")]),
+        ..Compiler::default()
+    };
+    let module = compiler.into_compiled_module(source).unwrap();
+    assert!(module.address_pool().contains(&address("0x3")));
+    assert!(!module.address_pool().contains(&address("0x2")));
+}
+
+#[test]
+fn relocations_are_parsed() {
+    assert_eq!(
+        parse_relocation("0x0=0x1234").unwrap(),
+        (address("0x0"), address("0x1234"))
+    );
+    for invalid in &["0x0", "0x0=", "=0x1", "0=1", "0x0=0xzz", "0x0=0x1=0x2"] {
+        assert!(parse_relocation(invalid).is_err(), "{}", invalid);
+    }
+}
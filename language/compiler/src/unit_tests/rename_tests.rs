@@ -0,0 +1,214 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::{
+    compiler::{compile_module, compile_script},
+    parser::{parse_module, parse_script},
+    rename::{rename_module, rename_script, RenameMap, RenameScheme},
+};
+use libra_types::account_address::AccountAddress;
+use move_ir_types::{
+    ast::{ModuleDefinition, StructDefinitionFields},
+    spec_language_ast::{Condition_, SpecExp, StorageLocation},
+};
+use std::collections::BTreeMap;
+use stdlib::stdlib_modules;
+use vm::access::ModuleAccess;
+
+const MODULE: &str = "module M {
+    resource Counter {
+        count: u64,
+        limit: u64,
+        invariant count <= limit,
+    }
+
+    struct Pair {
+        count: u64,
+        other: bool,
+    }
+
+    native public hash(data: bytearray): bytearray;
+
+    public new(start: u64): Self.Counter {
+        return Counter { count: Self.checked(copy(start)), limit: 100 };
+    }
+
+    public get(counter: &Self.Counter): u64 {
+        return *(&move(counter).count);
+    }
+
+    public pair(count: u64): bool {
+        let p: Self.Pair;
+        let c: u64;
+        let o: bool;
+        p = Pair { count: move(count), other: true };
+        Pair { count: c, other: o } = move(p);
+        return move(o);
+    }
+
+    checked(value: u64): u64
+    requires value < 100
+    {
+        assert(copy(value) < 100, 1);
+        return move(value);
+    }
+}
+";
+
+fn names(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+    pairs
+        .iter()
+        .map(|&(from, to)| (from.to_string(), to.to_string()))
+        .collect()
+}
+
+fn function_names(module: &ModuleDefinition) -> Vec<String> {
+    module
+        .functions
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+fn renamed(scheme: RenameScheme) -> (ModuleDefinition, RenameMap) {
+    let mut module = parse_module(MODULE).unwrap();
+    let map = rename_module(&mut module, &scheme);
+    (module, map)
+}
+
+#[test]
+fn minify_renames_internal_names() {
+    let (module, map) = renamed(RenameScheme::Minify);
+    assert_eq!(map.functions, names(&[("checked", "a")]));
+    assert_eq!(
+        map.fields,
+        names(&[("count", "a"), ("limit", "b"), ("other", "c")])
+    );
+    assert_eq!(map.locals["new"], names(&[("start", "a")]));
+    assert_eq!(
+        map.locals["pair"],
+        names(&[("c", "c"), ("count", "a"), ("o", "d"), ("p", "b")])
+    );
+    assert_eq!(map.locals["hash"], names(&[("data", "a")]));
+    assert_eq!(
+        function_names(&module),
+        vec!["hash", "new", "get", "pair", "a"]
+    );
+
+    let (compiled, _) =
+        compile_module(AccountAddress::default(), module, stdlib_modules()).unwrap();
+    let identifiers: Vec<&str> = compiled
+        .identifiers()
+        .iter()
+        .map(|ident| ident.as_str())
+        .collect();
+    for kept in &["Counter", "Pair", "hash", "new", "get", "pair"] {
+        assert!(identifiers.contains(kept), "{}", kept);
+    }
+    for original in &["checked", "count", "limit", "other"] {
+        assert!(!identifiers.contains(original), "{}", original);
+    }
+}
+
+#[test]
+fn specifications_are_renamed() {
+    let (module, _) = renamed(RenameScheme::Prefix("x_".to_string()));
+    let invariant = &module.structs[0].value.invariants[0].value.condition;
+    match invariant {
+        SpecExp::Binop(lhs, _, rhs) => {
+            assert_eq!(
+                **lhs,
+                SpecExp::StorageLocation(StorageLocation::Formal("x_count".to_string()))
+            );
+            assert_eq!(
+                **rhs,
+                SpecExp::StorageLocation(StorageLocation::Formal("x_limit".to_string()))
+            );
+        }
+        e => panic!("unexpected invariant {:?}", e),
+    }
+    let (name, checked) = &module.functions[4];
+    assert_eq!(name.to_string(), "x_checked");
+    match &checked.value.specifications[0].value {
+        Condition_::Requires(SpecExp::Binop(lhs, _, _)) => assert_eq!(
+            **lhs,
+            SpecExp::StorageLocation(StorageLocation::Formal("x_value".to_string()))
+        ),
+        condition => panic!("unexpected condition {:?}", condition),
+    }
+    match &module.structs[1].value.fields {
+        StructDefinitionFields::Move { fields } => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(field, _)| field.value.to_string())
+                .collect();
+            assert_eq!(fields, vec!["x_count", "x_other"]);
+        }
+        StructDefinitionFields::Native => panic!("Pair is not native"),
+    }
+}
+
+#[test]
+fn new_names_are_distinct_from_kept_names() {
+    let source = "module M {
+    public fun0() { return; }
+    public _f() { return; }
+    f() { Self.g(); return; }
+    g() { return; }
+}
+";
+    let mut module = parse_module(source).unwrap();
+    let map = rename_module(&mut module, &RenameScheme::Obfuscate);
+    assert_eq!(map.functions, names(&[("f", "fun1"), ("g", "fun2")]));
+    compile_module(AccountAddress::default(), module, stdlib_modules()).unwrap();
+
+    let mut module = parse_module(source).unwrap();
+    let map = rename_module(&mut module, &RenameScheme::Prefix("_".to_string()));
+    assert_eq!(map.functions, names(&[("f", "_f1"), ("g", "_g")]));
+}
+
+#[test]
+fn minified_names_skip_keywords() {
+    let locals: Vec<String> = (0..60).map(|idx| format!("v{}", idx)).collect();
+    let source = format!(
+        "main() {{\n{}\n{}\n    return;\n}}\n",
+        locals
+            .iter()
+            .map(|local| format!("    let {}: u64;", local))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        locals
+            .iter()
+            .map(|local| format!("    {} = 0;", local))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    );
+    let mut script = parse_script(&source).unwrap();
+    let map = rename_script(&mut script, &RenameScheme::Minify);
+    let new_names: Vec<&str> = map.locals["main"].values().map(String::as_str).collect();
+    assert_eq!(new_names.len(), 60);
+    assert!(new_names.contains(&"z"));
+    assert!(new_names.contains(&"aa"));
+    assert!(!new_names.contains(&"as"));
+    assert!(!new_names.contains(&"if"));
+    compile_script(AccountAddress::default(), script, stdlib_modules()).unwrap();
+}
+
+#[test]
+fn rename_schemes_are_parsed() {
+    assert_eq!(
+        "minify".parse::<RenameScheme>().unwrap(),
+        RenameScheme::Minify
+    );
+    assert_eq!(
+        "obfuscate".parse::<RenameScheme>().unwrap(),
+        RenameScheme::Obfuscate
+    );
+    assert_eq!(
+        "prefix:_".parse::<RenameScheme>().unwrap(),
+        RenameScheme::Prefix("_".to_string())
+    );
+    for invalid in &["", "shorten", "prefix:", "prefix:1", "prefix:a-"] {
+        assert!(invalid.parse::<RenameScheme>().is_err(), "{}", invalid);
+    }
+}
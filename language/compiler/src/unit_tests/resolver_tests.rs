@@ -0,0 +1,76 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::include::ExpandedSource;
+use ir_to_bytecode::{
+    build::{compile_directory_with, BuildOptions},
+    resolver::{Entry, InMemory, SourceResolver},
+};
+use libra_types::account_address::AccountAddress;
+use move_ir_types::ast::{ModuleName, QualifiedModuleIdent};
+use std::path::{Path, PathBuf};
+use vm::access::ModuleAccess;
+
+#[test]
+fn in_memory_directories_list_files_and_subdirectories() {
+    let mut resolver = InMemory::new();
+    resolver.add_file("src/a.mvir", "a");
+    resolver.add_file("src/nested/b.mvir", "b");
+    resolver.add_file("other/c.mvir", "c");
+    assert_eq!(
+        resolver.list_directory(Path::new("src")).unwrap(),
+        vec![
+            Entry::File(PathBuf::from("src/a.mvir")),
+            Entry::Directory(PathBuf::from("src/nested")),
+        ]
+    );
+    assert_eq!(
+        resolver
+            .read_file(Path::new("src/nested/../a.mvir"))
+            .unwrap(),
+        "a"
+    );
+    assert!(resolver.read_file(Path::new("src/missing.mvir")).is_err());
+}
+
+#[test]
+fn directories_build_from_memory_with_fetched_imports() {
+    let mut resolver = InMemory::new();
+    resolver.add_file(
+        "src/top.mvir",
+        "module Top {\n    import 0x0.Base;\n    public g(): u64 {\n        return Base.f();\n    }\n}\n",
+    );
+    resolver.add_module(
+        QualifiedModuleIdent {
+            name: ModuleName::parse("Base").unwrap(),
+            address: AccountAddress::default(),
+        },
+        "module Base {\n    public f(): u64 {\n        return 42;\n    }\n}\n",
+    );
+    let build =
+        compile_directory_with(&resolver, Path::new("src"), &BuildOptions::default()).unwrap();
+    assert!(build.is_success(), "{:?}", build.errors);
+    let names: Vec<_> = build
+        .modules
+        .iter()
+        .map(|built| built.module.self_id().name().to_string())
+        .collect();
+    assert_eq!(names, vec!["Base", "Top"]);
+    assert_eq!(build.modules[0].path, None);
+}
+
+#[test]
+fn includes_are_read_from_the_resolver() {
+    let mut resolver = InMemory::new();
+    resolver.add_file(
+        "dir/main.mvir",
+        "include \"lib/pre.mvir\";\nmain() {\n    return;\n}\n",
+    );
+    resolver.add_file("dir/lib/pre.mvir", "// preamble\n");
+    let source = ExpandedSource::read_with(&resolver, Path::new("dir/main.mvir")).unwrap();
+    assert!(source.text.starts_with("// preamble\n"), "{}", source.text);
+
+    resolver.add_file("dir/lib/pre.mvir", "include \"../main.mvir\";\n");
+    let err = ExpandedSource::read_with(&resolver, Path::new("dir/main.mvir")).unwrap_err();
+    assert!(err.to_string().starts_with("include cycle: "), "{}", err);
+}
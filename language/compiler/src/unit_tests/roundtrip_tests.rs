@@ -0,0 +1,139 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::unit_tests::testutils::STDLIB_SOURCES;
+use ir_to_bytecode::{
+    formatter::{format_module, format_script, format_source, FormatOptions},
+    parser::parse_script_or_module,
+};
+use move_ir_types::{
+    ast::{ModuleDefinition, Script, ScriptOrModule, Type},
+    location::EraseLocations,
+};
+use proptest::prelude::*;
+use stdlib::transaction_scripts;
+
+fn parse(source: &str) -> ScriptOrModule {
+    let mut parsed = parse_script_or_module(source)
+        .unwrap_or_else(|err| panic!("{}\nin source:\n{}", err, source));
+    parsed.erase_locations();
+    parsed
+}
+
+/// Asserts that two ASTs have the same structure, including the formals of a script, which the
+/// equality of scripts ignores.
+fn assert_same_structure(left: &ScriptOrModule, right: &ScriptOrModule, source: &str) {
+    match (left, right) {
+        (ScriptOrModule::Module(left), ScriptOrModule::Module(right)) => {
+            assert!(left == right, "the AST changed when reparsing:\n{}", source)
+        }
+        (ScriptOrModule::Script(left), ScriptOrModule::Script(right)) => assert!(
+            left.imports == right.imports && left.main == right.main,
+            "the AST changed when reparsing:\n{}",
+            source
+        ),
+        _ => panic!("a script and a module:\n{}", source),
+    }
+}
+
+/// Drops the specification clauses, invariants and synthetics, which the AST printer leaves out.
+fn without_specifications(script_or_module: &ScriptOrModule) -> ScriptOrModule {
+    let mut result = script_or_module.clone();
+    match &mut result {
+        ScriptOrModule::Script(script) => script.main.value.specifications.clear(),
+        ScriptOrModule::Module(module) => {
+            module.synthetics.clear();
+            for s in &mut module.structs {
+                s.value.invariants.clear();
+            }
+            for (_, f) in &mut module.functions {
+                f.value.specifications.clear();
+            }
+        }
+    }
+    result
+}
+
+/// Checks that `parse(print(parse(source)))` has the same structure as `parse(source)`, for both
+/// the source formatter and the AST printer.
+fn assert_round_trips(source: &str, options: &FormatOptions) {
+    let parsed = parse(source);
+
+    let formatted = format_source(source, options).unwrap();
+    assert_same_structure(&parsed, &parse(&formatted), &formatted);
+
+    let printed = match &parsed {
+        ScriptOrModule::Script(script) => format_script(script, options),
+        ScriptOrModule::Module(module) => format_module(module, options),
+    };
+    assert_same_structure(&without_specifications(&parsed), &parse(&printed), &printed);
+}
+
+#[test]
+fn stdlib_modules_round_trip() {
+    for source in STDLIB_SOURCES {
+        assert_round_trips(source, &FormatOptions::default());
+    }
+}
+
+#[test]
+fn transaction_scripts_round_trip() {
+    assert_round_trips(
+        transaction_scripts::peer_to_peer(),
+        &FormatOptions::default(),
+    );
+    assert_round_trips(transaction_scripts::rotate_key(), &FormatOptions::default());
+}
+
+#[test]
+fn narrow_layouts_round_trip() {
+    let options = FormatOptions {
+        indent_width: 2,
+        max_width: 30,
+    };
+    for source in STDLIB_SOURCES {
+        assert_round_trips(source, &options);
+    }
+}
+
+#[test]
+fn printed_lists_are_separated() {
+    // The items of a list are separated, with no separator before the first one.
+    let tuple = Type::Tuple(vec![Type::U64, Type::Bool, Type::Address]);
+    assert_eq!(tuple.to_string(), "(u64, bool, address)");
+
+    let printed = parse(
+        "
+        module M {
+            struct Pair<T1, T2> { a: T1, b: T2 }
+            f(p: Self.Pair<u64, bool>): u64 * bool {
+                let a: u64;
+                let b: bool;
+                Pair<u64, bool> { a: a, b: b } = move(p);
+                a, b = (move(a), move(b));
+                return move(a), move(b);
+            }
+        }
+        ",
+    )
+    .to_string();
+    assert!(printed.contains("Self.Pair<u64, bool>"), "{}", printed);
+    assert!(printed.contains("a, b = ("), "{}", printed);
+    assert!(
+        !printed.contains("<, ") && !printed.contains("(, "),
+        "{}",
+        printed
+    );
+}
+
+proptest! {
+    #[test]
+    fn generated_modules_round_trip(module in any::<ModuleDefinition>()) {
+        assert_round_trips(&format_module(&module, &FormatOptions::default()), &FormatOptions::default());
+    }
+
+    #[test]
+    fn generated_scripts_round_trip(script in any::<Script>()) {
+        assert_round_trips(&format_script(&script, &FormatOptions::default()), &FormatOptions::default());
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::parser::{parse_module, parse_module_with_options, ParserOptions};
+
+const MODULE: &str = "
+module M {
+    synthetic total: u64;
+
+    resource T {
+        i: u64,
+        invariant i > 0,
+        invariant {update} old(i) < i && total == old(total) + i - old(i),
+    }
+
+    public f(t: &Self.T, x: u64): u64 acquires T
+    requires x > 0
+    ensures RET == old(t.i) + x
+    aborts_if !global_exists<Self.T>(txn_sender)
+    succeeds_if global_exists<Self.T>(0x0)
+    {
+        return *(&move(t).i) + move(x);
+    }
+
+    native public g(x: u64): u64
+    ensures RET(0) == x;
+}
+";
+
+fn options(skip_specs: bool, reject_specs: bool) -> ParserOptions {
+    ParserOptions {
+        skip_specs,
+        reject_specs,
+        ..ParserOptions::default()
+    }
+}
+
+#[test]
+fn skip_specs_drops_only_specs() {
+    let mut module = parse_module(MODULE).unwrap();
+    assert_eq!(module.synthetics.len(), 1);
+    module.synthetics.clear();
+    for struct_def in &mut module.structs {
+        assert_eq!(struct_def.value.invariants.len(), 2);
+        struct_def.value.invariants.clear();
+    }
+    for (_, function) in &mut module.functions {
+        assert!(!function.value.specifications.is_empty());
+        function.value.specifications.clear();
+    }
+
+    let skipped = parse_module_with_options(MODULE, options(true, false)).unwrap();
+    assert_eq!(skipped, module);
+}
+
+#[test]
+fn skip_specs_checks_parentheses() {
+    for source in &[
+        "module M { f() requires (true { return; } }",
+        "module M { f() requires true) { return; } }",
+        "module M { f() requires { return; } }",
+        "module M { resource T { i: u64, invariant } }",
+        "module M { synthetic x: u64 }",
+    ] {
+        assert!(parse_module_with_options(source, options(true, false)).is_err());
+    }
+}
+
+#[test]
+fn reject_specs() {
+    for source in &[
+        "module M { f() requires true { return; } }",
+        "module M { resource T { i: u64, invariant i > 0 } }",
+        "module M { synthetic x: u64; }",
+    ] {
+        assert!(parse_module(source).is_ok());
+        assert!(parse_module_with_options(source, options(false, true)).is_err());
+        assert!(parse_module_with_options(source, options(true, true)).is_err());
+    }
+    let source = "module M { f() { return; } }";
+    assert!(parse_module_with_options(source, options(false, true)).is_ok());
+}
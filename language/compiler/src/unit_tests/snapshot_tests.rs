@@ -0,0 +1,108 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    snapshot::{assert_disassembly_snapshot, check_snapshot, disassemble, to_json},
+    unit_tests::testutils::compiler,
+    Compiler,
+};
+use ir_to_bytecode::metadata::BuildInfo;
+use libra_temppath::TempPath;
+use std::{fs, path::Path};
+
+const MODULE: &str = "module M {
+    struct Pair { a: u64, b: u64 }
+
+    public sum(p: Self.Pair): u64 {
+        let a: u64;
+        let b: u64;
+        Pair { a, b } = move(p);
+        return Self.add(move(a), move(b));
+    }
+
+    add(x: u64, y: u64): u64 {
+        return move(x) + move(y);
+    }
+}
+";
+
+#[test]
+fn snapshots_are_written_then_matched() {
+    let dir = TempPath::new();
+    dir.create_as_dir().unwrap();
+    let path = dir.path().join("nested").join("m");
+    let dump = disassemble(compiler(), MODULE).unwrap();
+
+    let mismatch = check_snapshot(&path, &dump, false).unwrap().unwrap();
+    assert_eq!(mismatch.path, path);
+    assert_eq!(mismatch.expected, None);
+    assert!(
+        mismatch.to_string().contains("no .exp file"),
+        "{}",
+        mismatch
+    );
+
+    assert_eq!(check_snapshot(&path, &dump, true).unwrap(), None);
+    assert_eq!(
+        fs::read_to_string(path.with_extension("exp")).unwrap(),
+        dump
+    );
+    assert_eq!(check_snapshot(&path, &dump, false).unwrap(), None);
+}
+
+#[test]
+fn changes_of_the_output_are_mismatches() {
+    let dir = TempPath::new();
+    dir.create_as_dir().unwrap();
+    let path = dir.path().join("m");
+    let dump = disassemble(compiler(), MODULE).unwrap();
+    check_snapshot(&path, &dump, true).unwrap();
+
+    let inlined = Compiler {
+        inline_functions: true,
+        ..compiler()
+    };
+    let changed = disassemble(inlined, MODULE).unwrap();
+    assert_ne!(changed, dump);
+    let mismatch = check_snapshot(&path, &changed, false).unwrap().unwrap();
+    assert_eq!(mismatch.expected.as_deref(), Some(dump.as_str()));
+    assert_eq!(mismatch.actual, changed);
+}
+
+#[test]
+fn programs_are_dumped_module_by_module_then_script() {
+    let source = "modules:
+module N {
+    public one(): u64 {
+        return 1;
+    }
+}
+script:
+import Transaction.N;
+main() {
+    let x: u64;
+    x = N.one();
+    return;
+}
+";
+    let dump = disassemble(compiler(), source).unwrap();
+    let module = dump.find("module ").unwrap();
+    let script = dump.find("\nscript\n").unwrap();
+    assert!(module < script, "{}", dump);
+    assert!(dump.contains("    Call(N.one)\n"), "{}", dump);
+}
+
+#[test]
+fn json_snapshots_end_with_a_newline() {
+    let build = BuildInfo::new(Some(1), vec!["verify".to_string()]);
+    let json = to_json(&build).unwrap();
+    assert!(json.starts_with("{\n"), "{}", json);
+    assert!(json.ends_with("}\n"), "{}", json);
+    assert_eq!(serde_json::from_str::<BuildInfo>(&json).unwrap(), build);
+}
+
+#[test]
+fn disassembly_matches_the_checked_in_snapshot() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/unit_tests/snapshots/pair");
+    assert_disassembly_snapshot(&path, compiler(), MODULE);
+}
@@ -0,0 +1,63 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::parser::parse_module;
+use move_ir_types::{
+    ast::BinOp,
+    spec_language_ast::{Condition_, SpecExp, StorageLocation},
+};
+
+fn ensures(spec: &str) -> SpecExp {
+    let source = format!(
+        "module M {{ f(x: u64): u64 ensures {} {{ return move(x); }} }}",
+        spec
+    );
+    let module = parse_module(&source).unwrap();
+    let (_, function) = &module.functions[0];
+    match &function.value.specifications[0].value {
+        Condition_::Ensures(e) => e.clone(),
+        condition => panic!("unexpected condition {:?}", condition),
+    }
+}
+
+fn formal(name: &str) -> Box<SpecExp> {
+    Box::new(SpecExp::StorageLocation(StorageLocation::Formal(
+        name.to_string(),
+    )))
+}
+
+#[test]
+fn if_else_spec_expressions() {
+    let e = ensures("if (x > 0) RET == x else RET == 0 && old(x) == 0");
+    let (cond, if_exp, else_exp) = match e {
+        SpecExp::IfElse(cond, if_exp, else_exp) => (cond, if_exp, else_exp),
+        e => panic!("expected a conditional, found {:?}", e),
+    };
+    match *cond {
+        SpecExp::Binop(lhs, BinOp::Gt, _) => assert_eq!(lhs, formal("x")),
+        e => panic!("unexpected condition {:?}", e),
+    }
+    match *if_exp {
+        SpecExp::Binop(_, BinOp::Eq, rhs) => assert_eq!(rhs, formal("x")),
+        e => panic!("unexpected branch {:?}", e),
+    }
+    // The else branch extends as far as possible.
+    match *else_exp {
+        SpecExp::Binop(_, BinOp::And, _) => (),
+        e => panic!("unexpected branch {:?}", e),
+    }
+
+    // Conditionals nest.
+    match ensures("if (x > 0) if (x > 1) true else false else false") {
+        SpecExp::IfElse(_, if_exp, _) => match *if_exp {
+            SpecExp::IfElse(..) => (),
+            e => panic!("expected a conditional, found {:?}", e),
+        },
+        e => panic!("expected a conditional, found {:?}", e),
+    }
+}
+
+#[test]
+fn if_spec_expressions_need_else() {
+    assert!(parse_module("module M { f(x: u64) ensures if (x > 0) true { return; } }").is_err());
+}
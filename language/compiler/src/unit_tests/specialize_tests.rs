@@ -0,0 +1,99 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::Compiler;
+use ir_to_bytecode::{
+    errors::SpecializeError,
+    formatter::{format_script, FormatOptions},
+    parser::parse_script,
+    specialize::specialize_script,
+};
+use libra_types::identifier::Identifier;
+use move_ir_types::ast::{Kind, ModuleName, QualifiedStructIdent, StructName, Type, TypeVar_};
+use vm::access::ScriptAccess;
+
+const SCRIPT: &str = "
+import 0x0.Vector;
+main<Element: unrestricted>(n: u64) {
+    let v: Vector.T<Element>;
+    v = Vector.empty<Element>();
+    assert(Vector.length<Element>(&v) == move(n), 42);
+    Vector.destroy_empty<Element>(move(v));
+    return;
+}
+";
+
+fn specialize(source: &str, type_args: &[Type]) -> Result<String, SpecializeError> {
+    let mut script = parse_script(source).unwrap();
+    specialize_script(&mut script, type_args)?;
+    Ok(format_script(&script, &FormatOptions::default()))
+}
+
+fn type_var(name: &str) -> TypeVar_ {
+    TypeVar_::new(Identifier::new(name).unwrap())
+}
+
+#[test]
+fn type_parameters_are_substituted() {
+    let specialized = specialize(SCRIPT, &[Type::U64]).unwrap();
+    assert!(specialized.contains("main(n: u64) {"), "{}", specialized);
+    assert!(
+        specialized.contains("let v: Vector.T<u64>;"),
+        "{}",
+        specialized
+    );
+    assert!(
+        specialized.contains("v = Vector.empty<u64>();"),
+        "{}",
+        specialized
+    );
+    assert!(!specialized.contains("Element"), "{}", specialized);
+}
+
+#[test]
+fn type_arguments_are_checked() {
+    assert_eq!(
+        specialize(SCRIPT, &[]),
+        Err(SpecializeError::Arity {
+            expected: 1,
+            given: 0
+        })
+    );
+    assert_eq!(
+        specialize(SCRIPT, &[Type::reference(false, Type::U64)]),
+        Err(SpecializeError::NotConcrete {
+            name: type_var("Element"),
+            type_: "&u64".to_string(),
+        })
+    );
+    let source = "main<Currency: resource>() { return; }";
+    assert_eq!(
+        specialize(source, &[Type::Bool]),
+        Err(SpecializeError::KindMismatch {
+            name: type_var("Currency"),
+            kind: Kind::Resource,
+            type_: "bool".to_string(),
+        })
+    );
+    let coin = Type::Struct(
+        QualifiedStructIdent::new(
+            ModuleName::new(Identifier::new("LibraCoin").unwrap()),
+            StructName::new(Identifier::new("T").unwrap()),
+        ),
+        vec![],
+    );
+    assert!(specialize(source, &[coin]).is_ok());
+}
+
+#[test]
+fn compiler_specializes_scripts() {
+    let compiler = Compiler {
+        verify: true,
+        type_arguments: vec![Type::Address],
+        ..Compiler::default()
+    };
+    let compiled_script = compiler.into_script(SCRIPT).unwrap();
+    let main = compiled_script.function_handle_at(compiled_script.main().function);
+    let signature = compiled_script.function_signature_at(main.signature);
+    assert!(signature.type_formals.is_empty());
+}
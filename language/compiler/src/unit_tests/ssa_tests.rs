@@ -0,0 +1,268 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{unit_tests::testutils::assert_contains, Compiler};
+use ir_to_bytecode::{
+    formatter::{format_module, FormatOptions},
+    parser::parse_module,
+    ssa::{to_ssa, transform_module, Edge, Join, Phi, SsaFunction},
+};
+use move_ir_types::ast::{FunctionBody, ModuleDefinition, Var_};
+
+const MODULE: &str = "module M {
+    resource T { v: u64 }
+
+    choose(b: bool): u64 {
+        let x: u64;
+        x = 1;
+        if (move(b)) {
+            x = move(x) + 1;
+        } else {
+            x = 3;
+        }
+        return move(x);
+    }
+
+    sum(n: u64): u64 {
+        let i: u64;
+        let s: u64;
+        i = 0;
+        s = 0;
+        while (copy(i) < copy(n)) {
+            i = move(i) + 1;
+            if (copy(i) == 5) {
+                continue;
+            }
+            s = move(s) + copy(i);
+        }
+        return move(s);
+    }
+
+    root(n: u64): u64 {
+        let i: u64;
+        let r: u64;
+        i = 0;
+        r = loop {
+            if (copy(i) >= copy(n)) {
+                break 0;
+            }
+            i = move(i) + 1;
+            if (copy(i) * copy(i) == copy(n)) {
+                break copy(i);
+            }
+        };
+        return move(r) + move(i);
+    }
+
+    bound(n: u64): u64 {
+        let i: u64;
+        i = 0;
+        while (copy(i) < copy(n)) {
+            if (copy(i) * copy(i) > copy(n)) {
+                i = 100;
+                break;
+            }
+            i = move(i) + 1;
+        }
+        return move(i);
+    }
+
+    read(x: u64): u64 {
+        let r: &u64;
+        let y: u64;
+        y = move(x);
+        r = &y;
+        y = *move(r) + 1;
+        return move(y);
+    }
+
+    unwrap(t: Self.T): u64 {
+        let v: u64;
+        T { v } = move(t);
+        if (copy(v) > 0) {
+            return move(v);
+        }
+        abort 1;
+        v = 2;
+        return move(v);
+    }
+}
+";
+
+fn var(name: &str) -> Var_ {
+    Var_::parse(name).unwrap()
+}
+
+fn ssa(name: &str) -> SsaFunction {
+    let module = parse_module(MODULE).unwrap();
+    let (_, function) = module
+        .functions
+        .iter()
+        .find(|(function, _)| function.as_inner().as_str() == name)
+        .unwrap();
+    to_ssa(&function.value).unwrap()
+}
+
+/// Prints the module with the body of a function in SSA form.
+fn format_ssa(name: &str) -> String {
+    let mut module = parse_module(MODULE).unwrap();
+    let ssa = ssa(name);
+    for (function, body) in &mut module.functions {
+        if function.as_inner().as_str() == name {
+            body.value.body = FunctionBody::Move {
+                locals: ssa.locals.clone(),
+                code: ssa.code.clone(),
+            };
+        }
+    }
+    format_module(&module, &FormatOptions::default())
+}
+
+fn phis(ssa: &SsaFunction, join: fn(&Join) -> bool) -> Vec<Phi> {
+    ssa.phis
+        .iter()
+        .filter(|(key, _)| join(key))
+        .flat_map(|(_, phis)| phis.clone())
+        .collect()
+}
+
+#[test]
+fn assignments_define_versions() {
+    let ssa = ssa("choose");
+    assert_contains(
+        &format_ssa("choose"),
+        &[
+            "x_1 = 1;",
+            "x_2 = move(x_1) + 1;",
+            "x_3 = 3;",
+            "return move(x_4);",
+        ],
+    );
+    assert_eq!(
+        phis(&ssa, |join| match join {
+            Join::AfterIf(_) => true,
+            _ => false,
+        }),
+        vec![Phi {
+            dest: var("x_4"),
+            args: vec![(Edge::Then, var("x_2")), (Edge::Else, var("x_3"))],
+        }]
+    );
+    assert_eq!(ssa.versions[&var("x_4")], var("x"));
+    assert_eq!(ssa.versions[&var("b")], var("b"));
+    let locals: Vec<String> = ssa
+        .locals
+        .iter()
+        .map(|(var, _)| var.value.to_string())
+        .collect();
+    assert_eq!(locals, vec!["x_1", "x_2", "x_3", "x_4"]);
+}
+
+#[test]
+fn loop_heads_join_their_latches() {
+    let ssa = ssa("sum");
+    assert_contains(
+        &format_ssa("sum"),
+        &[
+            "while (copy(i_2) < copy(n)) {",
+            "i_3 = move(i_2) + 1;",
+            "s_3 = move(s_2) + copy(i_3);",
+            "return move(s_2);",
+        ],
+    );
+    let heads = phis(&ssa, |join| match join {
+        Join::LoopHead(_) => true,
+        _ => false,
+    });
+    assert_eq!(heads.len(), 2);
+    assert_eq!(heads[0].dest, var("i_2"));
+    let edges: Vec<Edge> = heads[0].args.iter().map(|(edge, _)| *edge).collect();
+    assert_eq!(edges[0], Edge::Entry);
+    assert_eq!(edges[1], Edge::BodyEnd);
+    match edges[2] {
+        Edge::Continue(_) => (),
+        edge => panic!("unexpected edge {:?}", edge),
+    }
+    // `s` is not assigned on the way to the `continue`.
+    assert_eq!(heads[1].args[2].1, var("s_2"));
+}
+
+#[test]
+fn phis_become_assignments() {
+    let mut module = parse_module(MODULE).unwrap();
+    transform_module(&mut module, |_| ());
+    assert_contains(
+        &format_module(&module, &FormatOptions::default()),
+        &[
+            // Before the loops, and at the end of their bodies.
+            "i_2 = move(i_1);",
+            "s_2 = move(s_3);",
+            // At the end of the branches of an `if`.
+            "x_4 = move(x_2);",
+            "x_4 = move(x_3);",
+            // Before the `break`s of a loop: `i_3` is still read by the `break`.
+            "i_4 = move(i_2);",
+            "i_4 = copy(i_3);",
+            "break copy(i_3);",
+            // The version after a `while` is the one at its head.
+            "i_2 = move(i_3);",
+            "return move(i_2);",
+        ],
+    );
+}
+
+#[test]
+fn borrowed_locals_keep_their_name() {
+    assert_contains(
+        &format_ssa("read"),
+        &[
+            "y = move(x);",
+            "r_1 = &y;",
+            "y = *move(r_1) + 1;",
+            "return move(y);",
+        ],
+    );
+}
+
+#[test]
+fn unreachable_code_is_left_out() {
+    let formatted = format_ssa("unwrap");
+    assert_contains(&formatted, &["T { v: v_1 } = move(t);", "abort 1;"]);
+    assert!(!formatted.contains("= 2;"), "{}", formatted);
+}
+
+#[test]
+fn functions_verify_after_a_round_trip() {
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        verify: true,
+        ssa: true,
+        ..Compiler::default()
+    };
+    compiler.into_compiled_module(MODULE).unwrap();
+}
+
+#[test]
+fn stdlib_compiles_through_ssa() {
+    let modules: &[&str] = &[
+        include_str!("../../../stdlib/modules/libra_coin.mvir"),
+        include_str!("../../../stdlib/modules/libra_account.mvir"),
+        include_str!("../../../stdlib/modules/libra_system.mvir"),
+        include_str!("../../../stdlib/modules/validator_config.mvir"),
+    ];
+    for code in modules {
+        let compiler = Compiler {
+            verify: true,
+            ssa: true,
+            ..Compiler::default()
+        };
+        compiler.into_compiled_module(code).unwrap();
+    }
+}
+
+#[test]
+fn native_functions_have_no_ssa_form() {
+    let module: ModuleDefinition =
+        parse_module("module M { native public hash(data: bytearray): bytearray; }").unwrap();
+    assert!(to_ssa(&module.functions[0].1.value).is_none());
+}
@@ -0,0 +1,62 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::bail;
+use ir_to_bytecode::{
+    compiler::{compile_module, compile_program, compile_script},
+    parser::{parse_program, parse_program_streaming},
+};
+use libra_types::account_address::AccountAddress;
+use vm::file_format::CompiledModule;
+
+const PROGRAM: &str = "
+modules:
+module A {
+    public one(): u64 {
+        return 1;
+    }
+}
+module B {
+    import Transaction.A;
+    public two(): u64 {
+        return A.one() + A.one();
+    }
+}
+script:
+import Transaction.B;
+main() {
+    assert(B.two() == 2, 42);
+    return;
+}
+";
+
+#[test]
+fn modules_compile_as_they_are_parsed() {
+    let address = AccountAddress::default();
+    let mut modules = vec![];
+    let script = parse_program_streaming(PROGRAM, |module| {
+        modules.push(compile_module(address, module, &modules)?.0);
+        Ok(())
+    })
+    .unwrap();
+    let script = compile_script(address, script, &modules).unwrap().0;
+
+    let no_deps: &[CompiledModule] = &[];
+    let program = compile_program(address, parse_program(PROGRAM).unwrap(), no_deps)
+        .unwrap()
+        .0;
+    assert_eq!(modules, program.modules);
+    assert_eq!(script, program.script);
+}
+
+#[test]
+fn module_errors_stop_parsing() {
+    let mut names = vec![];
+    let err = parse_program_streaming(PROGRAM, |module| {
+        names.push(module.name.to_string());
+        bail!("cannot compile {}", module.name)
+    })
+    .unwrap_err();
+    assert_eq!(err.to_string(), "cannot compile A");
+    assert_eq!(names, vec!["A"]);
+}
@@ -0,0 +1,62 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use ir_to_bytecode::parser::{parse_program_with_options, ParserOptions, DEFAULT_MAX_EXP_DEPTH};
+
+const STRICT: ParserOptions = ParserOptions {
+    skip_specs: false,
+    reject_specs: false,
+    parallel: false,
+    max_byte_array_len: None,
+    reserved_names: &[],
+    strict: true,
+    max_exp_depth: Some(DEFAULT_MAX_EXP_DEPTH),
+};
+
+// Returns the error of parsing `program` in strict mode, after checking that it parses otherwise.
+fn strict_error(program: &str) -> String {
+    assert!(parse_program_with_options(program, ParserOptions::default()).is_ok());
+    parse_program_with_options(program, STRICT)
+        .unwrap_err()
+        .to_string()
+}
+
+#[test]
+fn transaction_imports_are_rejected() {
+    let program = "
+modules:
+module M {
+    public f() { return; }
+}
+script:
+import Transaction.M;
+main() {
+    M.f();
+    return;
+}";
+    assert_eq!(
+        strict_error(program),
+        "ParserError: Deprecated `Transaction` import at 65: import the module by its address instead"
+    );
+}
+
+#[test]
+fn assert_is_rejected() {
+    assert_eq!(
+        strict_error("main() { assert(true, 42); return; }"),
+        "ParserError: Deprecated `assert` at 9: use `if (!cond) abort code` instead"
+    );
+}
+
+#[test]
+fn current_syntax_is_accepted() {
+    let program = "
+import 0x0.LibraAccount;
+main() {
+    if (!LibraAccount.exists(get_txn_sender())) {
+        abort 42;
+    }
+    return;
+}";
+    assert!(parse_program_with_options(program, STRICT).is_ok());
+}
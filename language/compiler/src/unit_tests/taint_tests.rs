@@ -0,0 +1,101 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::util::taint_report;
+use ir_to_bytecode::{
+    parser::{parse_module, parse_script},
+    taint::{module_taint, script_taint, Sink},
+};
+use move_ir_types::ast::BinOp;
+
+const MODULE: &str = "module Bank {
+    resource Account { balance: u64 }
+    resource Vault { amount: u64 }
+
+    public deposit(amount: u64) acquires Account {
+        let account: &mut Self.Account;
+        let balance: u64;
+        account = borrow_global_mut<Account>(get_txn_sender());
+        balance = *(&copy(account).balance);
+        *(&mut move(account).balance) = move(balance) + move(amount);
+        return;
+    }
+
+    public reset() acquires Account {
+        let account: &mut Self.Account;
+        let fee: u64;
+        fee = 10;
+        account = borrow_global_mut<Account>(get_txn_sender());
+        *(&mut copy(account).balance) = *(&copy(account).balance) - move(fee);
+        return;
+    }
+
+    public store(amount: u64) {
+        let a: u64;
+        let b: u64;
+        a = 0;
+        b, a = (move(amount), move(a));
+        move_to_sender<Vault>(Vault { amount: move(a) });
+        move_to_sender<Vault>(Vault { amount: move(b) });
+        return;
+    }
+}
+";
+
+#[test]
+fn tainted_flows() {
+    let module = parse_module(MODULE).unwrap();
+    let flows = module_taint(&module);
+    let sinks: Vec<&Sink> = flows.iter().map(|flow| &flow.sink).collect();
+    match sinks.as_slice() {
+        [Sink::Arithmetic(BinOp::Add), Sink::MoveToSender(name)] => {
+            assert_eq!(name.to_string(), "Vault")
+        }
+        sinks => panic!("unexpected sinks {:?}", sinks),
+    }
+    assert_eq!(
+        taint_report(MODULE, &flows),
+        "10:41: warning: transaction argument combined with a value from global storage by `+`\n\
+         29:9: warning: transaction argument moved to the sender's storage by `move_to_sender<Vault>`"
+    );
+}
+
+#[test]
+fn taint_flows_around_loops() {
+    let module = parse_module(
+        "module M {
+    resource T { balance: u64 }
+
+    public drain(n: u64): u64 acquires T {
+        let i: u64;
+        let total: u64;
+        i = 0;
+        total = *(&borrow_global<T>(get_txn_sender()).balance);
+        while (copy(i) < 3) {
+            total = move(total) * copy(i);
+            i = copy(n);
+        }
+        return move(total);
+    }
+}
+",
+    )
+    .unwrap();
+    let flows = module_taint(&module);
+    assert_eq!(flows.len(), 1);
+    assert_eq!(flows[0].sink, Sink::Arithmetic(BinOp::Mul));
+}
+
+#[test]
+fn scripts_without_storage_have_no_flows() {
+    let script = parse_script(
+        "import 0x0.LibraAccount;
+main(payee: address, amount: u64) {
+    LibraAccount.pay_from_sender(move(payee), move(amount) * 2);
+    return;
+}
+",
+    )
+    .unwrap();
+    assert!(script_taint(&script).is_empty());
+}
@@ -0,0 +1,190 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{unit_tests::testutils::verifying_compiler, Compiler};
+use ir_to_bytecode::target::{
+    check_module_target, instruction_version, type_version, LATEST_BYTECODE_VERSION,
+};
+use vm::file_format::{Bytecode, SignatureToken, StructHandleIndex};
+
+const MODULE: &str = "module M {
+    struct Counter { value: u8 }
+
+    public shift(x: u64): u64 {
+        return move(x) << 2u8;
+    }
+
+    public plain(x: u64): u64 {
+        return move(x) + 1;
+    }
+}
+";
+
+fn compiler(target_version: Option<u64>) -> Compiler {
+    Compiler {
+        target_version,
+        ..verifying_compiler()
+    }
+}
+
+#[test]
+fn newer_constructs_are_reported_against_the_source() {
+    let err = compiler(Some(1))
+        .into_compiled_module(MODULE)
+        .unwrap_err()
+        .to_string();
+    let lines: Vec<_> = err.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "Code needs a newer bytecode version than the target one:",
+            "2:22: type `u8` of field `value` needs bytecode version 2, but the target version is 1",
+        ]
+    );
+}
+
+#[test]
+fn the_latest_version_is_targeted_by_default() {
+    compiler(None).into_compiled_module(MODULE).unwrap();
+    compiler(Some(LATEST_BYTECODE_VERSION))
+        .into_compiled_module(MODULE)
+        .unwrap();
+}
+
+#[test]
+fn code_of_the_first_version_compiles_for_it() {
+    let source = "module M {
+    struct Counter { value: u64 }
+
+    public plain(x: u64): u64 {
+        return move(x) + 1;
+    }
+}
+";
+    compiler(Some(1)).into_compiled_module(source).unwrap();
+}
+
+#[test]
+fn scripts_are_checked() {
+    let source = "main(n: u8) {
+    let x: u128;
+    x = 1u128;
+    return;
+}
+";
+    let err = compiler(Some(1))
+        .into_script(source)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("type `u8` in the signature of `main` needs bytecode version 2"),
+        "{}",
+        err
+    );
+    assert!(
+        err.contains("type `u128` of local `x` of `main` needs bytecode version 2"),
+        "{}",
+        err
+    );
+    assert!(
+        err.contains("instruction `LdU128` needs bytecode version 2"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn shifts_by_constants_are_lowered_for_older_targets() {
+    let source = "main(x: u64) {
+    let y: u64;
+    y = copy(x) << 3u8;
+    y = move(y) >> 3u8;
+    _ = move(x) << 0u8;
+    return;
+}
+";
+    let script = compiler(Some(1)).into_script(source).unwrap();
+    assert_eq!(instr_count!(script, Bytecode::Shl), 0);
+    assert_eq!(instr_count!(script, Bytecode::Shr), 0);
+    assert_eq!(instr_count!(script, Bytecode::LdU8(_)), 0);
+    // x << 3 is (x % 2^61) * 2^3 and y >> 3 is y / 2^3, while a shift by 0 is no instruction.
+    assert_eq!(instr_count!(script, Bytecode::Mod), 1);
+    assert_eq!(instr_count!(script, Bytecode::Mul), 1);
+    assert_eq!(instr_count!(script, Bytecode::Div), 1);
+
+    let script = compiler(None).into_script(source).unwrap();
+    assert_eq!(instr_count!(script, Bytecode::Shl), 2);
+    assert_eq!(instr_count!(script, Bytecode::Shr), 1);
+}
+
+#[test]
+fn integer_builtins_of_u64_are_lowered_for_older_targets() {
+    let source = "main(x: u64) {
+    let y: u64;
+    let ok: bool;
+    y = count_ones(copy(x)) + to_u64(copy(x));
+    y, ok = checked_add(move(y), copy(x));
+    _ = saturating_sub(move(y), move(x));
+    return;
+}
+";
+    let script = compiler(Some(1)).into_script(source).unwrap();
+    assert_eq!(instr_count!(script, Bytecode::CastU64), 0);
+    assert_eq!(instr_count!(script, Bytecode::LdU8(_)), 0);
+
+    let script = compiler(None).into_script(source).unwrap();
+    assert_eq!(instr_count!(script, Bytecode::CastU64), 2);
+}
+
+#[test]
+fn shifts_by_variables_are_not_lowered() {
+    let source = "main(x: u64, n: u8) {
+    _ = move(x) << move(n);
+    return;
+}
+";
+    let err = compiler(Some(1))
+        .into_script(source)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("2:9: instruction `Shl` needs bytecode version 2"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn unknown_versions_are_rejected() {
+    for version in &[0, LATEST_BYTECODE_VERSION + 1] {
+        let err = compiler(Some(*version))
+            .into_compiled_module(MODULE)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Unsupported bytecode version {}, the supported versions are 1 to 2",
+                version
+            )
+        );
+    }
+}
+
+#[test]
+fn versions_of_instructions_and_types() {
+    assert_eq!(instruction_version(&Bytecode::LdU64(1)), 1);
+    assert_eq!(instruction_version(&Bytecode::CastU64), 2);
+    assert_eq!(type_version(&SignatureToken::U64), 1);
+    let nested = SignatureToken::Reference(Box::new(SignatureToken::Struct(
+        StructHandleIndex(0),
+        vec![SignatureToken::Bool, SignatureToken::U128],
+    )));
+    assert_eq!(type_version(&nested), 2);
+}
+
+#[test]
+fn checks_run_on_compiled_modules() {
+    let (module, source_map, _) = compiler(None).compile_mod(MODULE).unwrap();
+    assert_eq!(check_module_target(&module, &source_map, 1).len(), 3);
+    assert!(check_module_target(&module, &source_map, 2).is_empty());
+}
@@ -1,13 +1,16 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::Compiler;
 use anyhow::Result;
 use bytecode_verifier::{VerifiedModule, VerifiedScript};
 use ir_to_bytecode::{
     compiler::{compile_module, compile_program},
     parser::{parse_module, parse_program},
 };
+use libra_temppath::TempPath;
 use libra_types::{account_address::AccountAddress, vm_error::VMStatus};
+use std::fs;
 use stdlib::stdlib_modules;
 use vm::{
     access::ScriptAccess,
@@ -157,3 +160,58 @@ fn stdlib() -> Vec<CompiledModule> {
         .map(|m| m.clone().into_inner())
         .collect()
 }
+
+/// A compiler for sources that do not import the stdlib modules, which are then not loaded.
+pub fn compiler() -> Compiler {
+    Compiler {
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    }
+}
+
+/// A compiler for sources that do not import the stdlib modules, verifying the code it compiles.
+pub fn verifying_compiler() -> Compiler {
+    Compiler {
+        verify: true,
+        ..compiler()
+    }
+}
+
+/// Asserts that `text` contains each of the `expected` snippets.
+pub fn assert_contains(text: &str, expected: &[&str]) {
+    for line in expected {
+        assert!(text.contains(line), "`{}` not found in\n{}", line, text);
+    }
+}
+
+/// Creates a temporary directory holding `files`, given by their path relative to it and their
+/// content.
+pub fn temp_dir(files: &[(&str, &str)]) -> TempPath {
+    let dir = TempPath::new();
+    dir.create_as_dir().unwrap();
+    for (name, content) in files {
+        let path = dir.path().join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+/// The sources of the stdlib modules, in the order they are compiled in.
+pub const STDLIB_SOURCES: &[&str] = &[
+    include_str!("../../../stdlib/modules/offer.mvir"),
+    include_str!("../../../stdlib/modules/address_util.mvir"),
+    include_str!("../../../stdlib/modules/bytearray_util.mvir"),
+    include_str!("../../../stdlib/modules/libra_coin.mvir"),
+    include_str!("../../../stdlib/modules/hash.mvir"),
+    include_str!("../../../stdlib/modules/signature.mvir"),
+    include_str!("../../../stdlib/modules/u64_util.mvir"),
+    include_str!("../../../stdlib/modules/lcs.mvir"),
+    include_str!("../../../stdlib/modules/vector.mvir"),
+    include_str!("../../../stdlib/modules/validator_config.mvir"),
+    include_str!("../../../stdlib/modules/gas_schedule.mvir"),
+    include_str!("../../../stdlib/modules/libra_time.mvir"),
+    include_str!("../../../stdlib/modules/libra_transaction_timeout.mvir"),
+    include_str!("../../../stdlib/modules/libra_account.mvir"),
+    include_str!("../../../stdlib/modules/libra_system.mvir"),
+];
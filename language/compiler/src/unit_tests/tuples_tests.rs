@@ -0,0 +1,100 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::Compiler;
+use ir_to_bytecode::{
+    formatter::{format_module, FormatOptions},
+    parser::parse_module,
+    tuples::lower_module,
+};
+
+const MODULE: &str = "module M {
+    pair(x: u64): (u64, bool) {
+        return move(x), true;
+    }
+    swap(p: (u64, bool)): (bool, u64) {
+        let a: u64;
+        let b: bool;
+        a, b = move(p);
+        return move(b), move(a);
+    }
+    first(): u64 {
+        let t: (u64, bool);
+        let s: (bool, u64);
+        let t_0: u64;
+        let n: u64;
+        t = Self.pair(3);
+        s = Self.swap(copy(t));
+        _, t_0 = move(s);
+        n, _ = move(t);
+        return move(n) + move(t_0);
+    }
+}
+";
+
+#[test]
+fn tuples_are_lowered_to_their_elements() {
+    let mut module = parse_module(MODULE).unwrap();
+    let formatted = format_module(&module, &FormatOptions::default());
+    assert!(
+        formatted.contains("swap(p: (u64, bool)): (bool, u64) {"),
+        "{}",
+        formatted
+    );
+    assert!(lower_module(&mut module).is_empty());
+    let formatted = format_module(&module, &FormatOptions::default());
+    for lowered in &[
+        "pair(x: u64): u64 * bool {",
+        "swap(p_0: u64, p_1: bool): bool * u64 {",
+        "a, b = (move(p_0), move(p_1));",
+        "let t__0: u64;",
+        "let t_1: bool;",
+        "s_0, s_1 = Self.swap((copy(t__0), copy(t_1)));",
+        "n, _ = (move(t__0), move(t_1));",
+    ] {
+        assert!(formatted.contains(lowered), "{}", formatted);
+    }
+}
+
+#[test]
+fn tuple_modules_verify() {
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        verify: true,
+        check_types: true,
+        ..Compiler::default()
+    };
+    compiler.into_compiled_module(MODULE).unwrap();
+}
+
+#[test]
+fn tuple_misuses_are_located() {
+    let source = "module M {
+    resource T { f: (u64, bool) }
+    f(r: &(u64, bool)): (u64, bool) {
+        let t: (u64, bool);
+        let a: u64;
+        let b: bool;
+        t = (1, true, 2);
+        a, b, _ = move(t);
+        _ = &t;
+        return move(a);
+    }
+}
+";
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    let error = compiler.into_compiled_module(source).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Tuple lowering failed:\n\
+         2:18: field f cannot have the tuple type (u64, bool)\n\
+         3:7: r cannot have the type &(u64, bool), which contains a tuple that is not at its top\n\
+         7:9: cannot assign 3 values to 2 targets\n\
+         8:9: cannot assign 2 values to 3 targets\n\
+         9:13: the tuple t cannot be borrowed\n\
+         10:9: cannot return 1 value from a function returning 2"
+    );
+}
@@ -0,0 +1,398 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::Compiler;
+use ir_to_bytecode::{
+    parser::{parse_module, parse_program, parse_script},
+    type_checker::{check_module, check_program, check_script},
+};
+use stdlib::transaction_scripts;
+
+fn type_check_module(code: &str) -> Result<(), String> {
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        check_types: true,
+        ..Compiler::default()
+    };
+    compiler
+        .into_compiled_module(code)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+fn script_errors(code: &str) -> Vec<String> {
+    let script = parse_script(code).unwrap();
+    check_script(&script)
+        .into_iter()
+        .map(|error| error.message)
+        .collect()
+}
+
+#[test]
+fn stdlib_modules_type_check() {
+    for module in stdlib::stdlib::module_defs() {
+        assert_eq!(check_module(module), vec![], "module {}", module.name);
+    }
+}
+
+#[test]
+fn stdlib_scripts_type_check() {
+    for code in &[
+        transaction_scripts::add_validator(),
+        transaction_scripts::peer_to_peer(),
+        transaction_scripts::peer_to_peer_with_metadata(),
+        transaction_scripts::create_account(),
+        transaction_scripts::register_validator(),
+        transaction_scripts::remove_validator(),
+        transaction_scripts::rotate_consensus_pubkey(),
+        transaction_scripts::rotate_key(),
+        transaction_scripts::mint(),
+        transaction_scripts::block_prologue(),
+    ] {
+        assert_eq!(script_errors(code), Vec::<String>::new(), "{}", code);
+    }
+}
+
+#[test]
+fn unbound_names() {
+    let errors = script_errors(
+        "
+        import 0x0.LibraAccount;
+        main() {
+            let x: u64;
+            x = move(y);
+            Coin.foo();
+            return;
+        }
+        ",
+    );
+    assert_eq!(errors, vec!["unbound local `y`", "unbound module `Coin`"]);
+}
+
+#[test]
+fn operand_types() {
+    let errors = script_errors(
+        "
+        main() {
+            let x: u64;
+            let b: bool;
+            let s: u8;
+            x = 1 + true;
+            b = copy(x) < 2u8;
+            s = 1u8;
+            x = copy(x) << move(s);
+            x = move(x) << 1;
+            if (move(b)) {
+                abort 0;
+            }
+            if (1) {
+                abort true;
+            }
+            return;
+        }
+        ",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "expected an integer, found `bool`",
+            "mismatched operand types `u64` and `u8` for `<`",
+            "expected a value of type `u8`, found `u64`",
+            "expected a value of type `bool`, found `u64`",
+            "expected a value of type `u64`, found `bool`",
+        ]
+    );
+}
+
+#[test]
+fn assignment_and_return_types() {
+    let errors = script_errors(
+        "
+        main() {
+            let x: u64;
+            let y: bool;
+            x, y = (1, 2);
+            x = (1, true);
+            return 1;
+        }
+        ",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "cannot assign a value of type `u64` to local `y` of type `bool`",
+            "expected 1 values to assign, found 2",
+            "expected to return (), found (`u64`)",
+        ]
+    );
+}
+
+#[test]
+fn bit_builtin_types() {
+    let errors = script_errors(
+        "
+        main() {
+            let x: u8;
+            let y: u64;
+            x = rotl(1u8, 2u8);
+            y = rotr(1u64, 2u8) + count_ones(1u128);
+            x = rotl(1u64, 2u8);
+            _ = rotr(true, 2u8);
+            _ = rotl(1u8, 2u64);
+            _ = count_ones(0x1);
+            return;
+        }
+        ",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "cannot assign a value of type `u64` to local `x` of type `u8`",
+            "`rotr` expects an integer, found `bool`",
+            "`rotl` expects a `u8` amount, found `u64`",
+            "`count_ones` expects an integer, found `address`",
+        ]
+    );
+}
+
+#[test]
+fn overflow_builtin_types() {
+    let errors = script_errors(
+        "
+        main() {
+            let x: u8;
+            let ok: bool;
+            x, ok = checked_add(1u8, 2u8);
+            x = saturating_sub(move(x), 1u8);
+            ok, x = checked_sub(1u8, 2u8);
+            _ = saturating_add(1u8, 2u64);
+            _ = checked_add(true, false);
+            return;
+        }
+        ",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "cannot assign a value of type `u8` to local `ok` of type `bool`",
+            "cannot assign a value of type `bool` to local `x` of type `u8`",
+            "`saturating_add` expects two integers of the same type, found `u8` and `u64`",
+            "`checked_add` expects two integers of the same type, found `bool` and `bool`",
+        ]
+    );
+}
+
+#[test]
+fn to_bytes_types() {
+    let errors = script_errors(
+        "
+        main() {
+            let x: u64;
+            let b: bytearray;
+            x = 1;
+            b = to_bytes<u64>(&x);
+            x = to_bytes<u64>(&x);
+            _ = to_bytes<u64>(&mut x);
+            _ = to_bytes<bool>(&x);
+            return;
+        }
+        ",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "cannot assign a value of type `bytearray` to local `x` of type `u64`",
+            "argument 1 of `to_bytes<u64>` expects a value of type `&u64`, found `&mut u64`",
+            "argument 1 of `to_bytes<bool>` expects a value of type `&bool`, found `&u64`",
+        ]
+    );
+}
+
+#[test]
+fn to_bytes_of_structs_and_references() {
+    let module = parse_module(
+        "
+        module M {
+            import 0x0.Vector;
+            struct Pair { a: u8, b: bool }
+            bytes(p: &Self.Pair, v: &Vector.T<Self.Pair>): bytearray * bytearray {
+                return to_bytes<Self.Pair>(move(p)), to_bytes<Vector.T<Self.Pair>>(move(v));
+            }
+            reference_bytes(p: &Self.Pair): bytearray {
+                return to_bytes<&Self.Pair>(&p);
+            }
+        }
+        ",
+    )
+    .unwrap();
+    let errors: Vec<_> = check_module(&module)
+        .into_iter()
+        .map(|error| error.message)
+        .collect();
+    assert_eq!(
+        errors,
+        vec!["`to_bytes<&Self.Pair>` cannot serialize a reference"]
+    );
+}
+
+#[test]
+fn hash_builtin_types() {
+    let errors = script_errors(
+        "
+        main() {
+            let x: u64;
+            x = 1;
+            _ = sha3_256(h\"00\");
+            x = sha2_256(h\"00\");
+            _ = sha3_256(copy(x));
+            return;
+        }
+        ",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "cannot assign a value of type `bytearray` to local `x` of type `u64`",
+            "argument 1 of `sha3_256` expects a value of type `bytearray`, found `u64`",
+        ]
+    );
+}
+
+#[test]
+fn emit_types() {
+    let errors = script_errors(
+        "
+        import 0x0.LibraAccount;
+        main() {
+            let handle: LibraAccount.EventHandle<u64>;
+            handle = LibraAccount.new_event_handle<u64>();
+            emit &mut handle, 1;
+            emit &mut handle, true;
+            emit &handle, 1;
+            LibraAccount.destroy_handle<u64>(move(handle));
+            return;
+        }
+        ",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "`emit` expects an event handle of type `&mut LibraAccount.EventHandle<bool>`, \
+             found `&mut LibraAccount.EventHandle<u64>`",
+            "`emit` expects an event handle of type `&mut LibraAccount.EventHandle<u64>`, \
+             found `&LibraAccount.EventHandle<u64>`",
+        ]
+    );
+}
+
+#[test]
+fn break_value_types() {
+    let errors = script_errors(
+        "
+        main() {
+            let x: u64;
+            x = loop {
+                break true;
+            };
+            x = loop {
+                break;
+            };
+            while (true) {
+                break 1;
+            }
+            return;
+        }
+        ",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "cannot assign a value of type `bool` to local `x` of type `u64`",
+            "expected a value to break out of a loop with a value",
+            "cannot break with a value out of a loop without a value",
+        ]
+    );
+}
+
+#[test]
+fn struct_and_reference_rules() {
+    let code = "module M {
+    resource R { f: u64 }
+    struct S { g: bool }
+
+    new(): Self.R {
+        return R { f: true, h: 0 };
+    }
+
+    write(r: &Self.R) {
+        *(&mut copy(r).f) = 1;
+        *(&copy(r).f) = 2;
+        return;
+    }
+
+    leak(x: u64): &u64 {
+        return &x;
+    }
+
+    publish() {
+        move_to_sender<S>(S { g: false });
+        return;
+    }
+
+    call(): u64 {
+        return Self.new(1);
+    }
+}";
+    let err = type_check_module(code).unwrap_err();
+    let expected = "Type checking failed:
+6:29: struct `R` has no field `h`
+6:23: expected a value of type `u64`, found `bool`
+10:11: cannot mutably borrow field `f` through an immutable reference
+11:9: cannot assign through an immutable reference
+16:16: cannot return a reference to local `x`
+20:9: `S` is not a resource and cannot be held in global storage
+25:16: `Self.new` expects 0 arguments, found 1
+25:9: expected to return (`u64`), found (`Self.R`)";
+    assert_eq!(err, expected);
+}
+
+#[test]
+fn program_checks_modules_and_script() {
+    let code = "
+        module M {
+            f(): u64 {
+                return Self.g();
+            }
+        }
+        main() {
+            let x: u64;
+            x = M.f(true);
+            return;
+        }
+        ";
+    let errors: Vec<String> = check_program(&parse_program(code).unwrap())
+        .into_iter()
+        .map(|error| error.message)
+        .collect();
+    // Calls into other modules are left to the verifier
+    assert_eq!(errors, vec!["unbound function `Self.g`"]);
+}
+
+#[test]
+fn unpack_fields_follow_declaration() {
+    let code = "module M {
+    struct S { a: u64, b: bool }
+
+    f(s: Self.S): u64 {
+        let a: u64;
+        let b: bool;
+        S { b, a } = move(s);
+        return move(a);
+    }
+}";
+    let err = type_check_module(code).unwrap_err();
+    assert_eq!(
+        err,
+        "Type checking failed:\n7:9: the fields of struct `S` must be listed in declaration order"
+    );
+}
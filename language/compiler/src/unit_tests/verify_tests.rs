@@ -0,0 +1,123 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::Compiler;
+use bytecode_source_map::utils::{line_and_column, verification_error_location};
+use ir_to_bytecode::{compiler::compile_script, parser::parse_script};
+use libra_types::{
+    account_address::AccountAddress,
+    vm_error::{StatusCode, VMStatus},
+};
+use vm::{
+    errors::{append_err_info, err_at_offset, verification_error},
+    file_format::CompiledModule,
+    IndexKind,
+};
+
+fn compile_and_verify_script(code: &str) -> anyhow::Result<()> {
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        verify: true,
+        ..Compiler::default()
+    };
+    compiler.into_script(code).map(|_| ())
+}
+
+#[test]
+fn verify_valid_script() {
+    let code = String::from(
+        "
+        main() {
+            let x: u64;
+            x = 1;
+            return;
+        }
+        ",
+    );
+    assert!(compile_and_verify_script(&code).is_ok());
+}
+
+#[test]
+fn verify_error_points_at_ir_line() {
+    let code = String::from(
+        "main() {
+    let x: u64;
+    let y: u64;
+    x = 1;
+    y = move(x) + move(x);
+    return;
+}",
+    );
+    let err = compile_and_verify_script(&code).unwrap_err().to_string();
+    assert!(err.contains("MOVELOC_UNAVAILABLE_ERROR"), "{}", err);
+    // The second `move(x)` is on line 5, column 19
+    assert!(err.contains("\n5:19: "), "{}", err);
+}
+
+#[test]
+fn verify_error_in_module_function() {
+    let code = String::from(
+        "module M {
+    f() {
+        let x: u64;
+        return move(x);
+    }
+}
+main() {
+    return;
+}",
+    );
+    let err = compile_and_verify_script(&code).unwrap_err().to_string();
+    // Returning a value from a function without a return type is a stack usage error
+    // reported on the `return` statement.
+    assert!(err.contains("\n4:"), "{}", err);
+}
+
+#[test]
+fn skip_verification_by_default() {
+    let code = String::from(
+        "
+        main() {
+            let x: u64;
+            return move(x);
+        }
+        ",
+    );
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    assert!(compiler.into_script(&code).is_ok());
+}
+
+#[test]
+fn verify_error_location_from_status() {
+    let code = "main() {\n    let x: u64;\n    x = 1;\n    return;\n}";
+    let script = parse_script(code).unwrap();
+    let (_, source_map) =
+        compile_script(AccountAddress::default(), script, &Vec::<CompiledModule>::new()).unwrap();
+
+    // An error at a code offset of `main` maps to the IR the instruction was compiled from, here
+    // the `x = 1` on line 3.
+    let status = append_err_info(
+        err_at_offset(StatusCode::MOVELOC_UNAVAILABLE_ERROR, 0),
+        IndexKind::FunctionDefinition,
+        0,
+    );
+    let loc = verification_error_location(&source_map, &status).unwrap();
+    assert_eq!(line_and_column(code, loc.start()).0, 3);
+
+    // An error on `main` without an offset maps to its declaration.
+    let status = verification_error(
+        IndexKind::FunctionDefinition,
+        0,
+        StatusCode::INVALID_MAIN_FUNCTION_SIGNATURE,
+    );
+    assert!(verification_error_location(&source_map, &status).is_some());
+
+    // Errors that do not point into the source map are not mapped.
+    let status = VMStatus::new(StatusCode::UNKNOWN_VERIFICATION_ERROR);
+    assert_eq!(verification_error_location(&source_map, &status), None);
+    let status = verification_error(IndexKind::FunctionDefinition, 5, StatusCode::UNUSED_FIELD);
+    assert_eq!(verification_error_location(&source_map, &status), None);
+}
@@ -1,11 +1,35 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::Context;
-use bytecode_source_map::source_map::ModuleSourceMap;
-use ir_to_bytecode::{compiler::compile_module, parser::parse_module};
-use libra_types::account_address::AccountAddress;
-use move_ir_types::ast::Loc;
+use anyhow::{bail, Context, Result};
+use bytecode_source_map::{
+    source_map::ModuleSourceMap,
+    utils::{line_and_column, verification_error_location},
+};
+use ir_to_bytecode::{
+    acquires::{
+        acquires_diff, comma_separated, fill_acquires, resolve_acquires, AcquiresDiff, Acquisition,
+        UnusedAcquires,
+    },
+    borrows::FunctionBorrows,
+    coalesce::CoalescedFunction,
+    compiler::compile_module,
+    constants::FoldedBranch,
+    dead_stores::DeadStore,
+    errors::{
+        AcquiresError, ArityError, CfgError, FunctionError, LimitError, NativeError, TargetError,
+        TupleError, TypeError,
+    },
+    freeze::Freeze,
+    inline::Inlining,
+    lint::Lint,
+    moves::InferredMove,
+    parser::parse_module,
+    relocate::Relocation,
+    taint::TaintedFlow,
+};
+use libra_types::{account_address::AccountAddress, vm_error::VMStatus};
+use move_ir_types::ast::{FunctionName, Loc, ModuleDefinition, StructName};
 use std::{fs, path::Path};
 use vm::{access::ModuleAccess, file_format::CompiledModule};
 
@@ -20,3 +44,409 @@ pub fn do_compile_module<T: ModuleAccess>(
     let parsed_module = parse_module(&source).unwrap();
     compile_module(address, parsed_module, dependencies).unwrap()
 }
+
+/// Formats bytecode verifier errors for a compilation unit, one per line, prefixing each error
+/// with the line and column of the IR source it was mapped back to when possible.
+pub fn verification_error_report(
+    source: &str,
+    source_map: &ModuleSourceMap<Loc>,
+    errors: &[VMStatus],
+) -> String {
+    errors
+        .iter()
+        .map(
+            |status| match verification_error_location(source_map, status) {
+                Some(loc) => {
+                    let (line, column) = line_and_column(source, loc.start());
+                    format!("{}:{}: {}", line, column, status)
+                }
+                None => status.to_string(),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats type checking errors, one per line, prefixed with the line and column of the IR
+/// source they point at.
+pub fn type_error_report(source: &str, errors: &[TypeError]) -> String {
+    errors
+        .iter()
+        .map(|error| {
+            let (line, column) = line_and_column(source, error.loc.start());
+            format!("{}:{}: {}", line, column, error)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the errors of native function declarations, one per line, prefixed with the line and
+/// column of the IR source they point at.
+pub fn native_error_report(source: &str, errors: &[NativeError]) -> String {
+    errors
+        .iter()
+        .map(|error| {
+            let (line, column) = line_and_column(source, error.loc.start());
+            format!("{}:{}: {}", line, column, error)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the misuses of tuples, one per line, prefixed with the line and column of the IR
+/// source they point at.
+pub fn tuple_error_report(source: &str, errors: &[TupleError]) -> String {
+    errors
+        .iter()
+        .map(|error| {
+            let (line, column) = line_and_column(source, error.loc.start());
+            format!("{}:{}: {}", line, column, error)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the declarations kept with the name of another by `cfg` attributes, one per line,
+/// prefixed with the line and column of the IR source they point at when they have one.
+pub fn cfg_error_report(source: &str, errors: &[CfgError]) -> String {
+    errors
+        .iter()
+        .map(|error| match error.loc {
+            Some(loc) => {
+                let (line, column) = line_and_column(source, loc.start());
+                format!("{}:{}: {}", line, column, error)
+            }
+            None => error.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the declarations exceeding a limit of the binary format, one per line, prefixed with
+/// the line and column of the IR source they point at when they have a location.
+pub fn limit_error_report(source: &str, errors: &[LimitError]) -> String {
+    errors
+        .iter()
+        .map(|error| match error.loc {
+            Some(loc) => {
+                let (line, column) = line_and_column(source, loc.start());
+                format!("{}:{}: {}", line, column, error)
+            }
+            None => error.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the errors of the functions left out of a partially compiled module, one per line,
+/// prefixed with the line and column of the IR source they point at.
+pub fn function_error_report(source: &str, errors: &[FunctionError]) -> String {
+    errors
+        .iter()
+        .map(|error| {
+            let (line, column) = line_and_column(source, error.loc.start());
+            format!("{}:{}: {}", line, column, error)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the constructs needing a newer bytecode version than the target one, one per line,
+/// prefixed with the line and column of the IR source they point at.
+pub fn target_error_report(source: &str, errors: &[TargetError]) -> String {
+    errors
+        .iter()
+        .map(|error| {
+            let (line, column) = line_and_column(source, error.loc.start());
+            format!("{}:{}: {}", line, column, error)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats a multi-value assignment of a call returning a different number of values, followed by
+/// notes pointing at the assigned targets and at the call.
+pub fn arity_error_report(source: &str, error: &ArityError) -> String {
+    let (line, column) = line_and_column(source, error.lvalues.start());
+    let (call_line, call_column) = line_and_column(source, error.call.start());
+    format!(
+        "{}:{}: {}\n{}:{}: note: {} targets assigned here\n{}:{}: note: `{}` returns {} values",
+        line,
+        column,
+        error,
+        line,
+        column,
+        error.assigned,
+        call_line,
+        call_column,
+        error.function,
+        error.returned
+    )
+}
+
+/// Formats dead store warnings, each followed by a note pointing at the overwriting store when
+/// there is one.
+pub fn dead_store_report(source: &str, stores: &[DeadStore]) -> String {
+    let mut report = vec![];
+    for store in stores {
+        let (line, column) = line_and_column(source, store.loc.start());
+        report.push(format!("{}:{}: warning: {}", line, column, store));
+        if let Some(loc) = store.overwritten_at {
+            let (line, column) = line_and_column(source, loc.start());
+            report.push(format!("{}:{}: note: overwritten here", line, column));
+        }
+    }
+    report.join("\n")
+}
+
+/// Formats the references of every function as notes, one per line: what the function borrows,
+/// followed by each of its borrows, escapes and freezes.
+pub fn borrow_report(source: &str, functions: &[FunctionBorrows]) -> String {
+    let mut report = vec![];
+    for function in functions {
+        let (line, column) = line_and_column(source, function.loc.start());
+        report.push(format!("{}:{}: note: {}", line, column, function));
+        for reference_use in &function.uses {
+            let (line, column) = line_and_column(source, reference_use.loc.start());
+            report.push(format!("{}:{}: note: {}", line, column, reference_use));
+        }
+    }
+    report.join("\n")
+}
+
+/// Formats the flows of transaction arguments into sensitive operations as warnings, one per line.
+pub fn taint_report(source: &str, flows: &[TaintedFlow]) -> String {
+    flows
+        .iter()
+        .map(|flow| {
+            let (line, column) = line_and_column(source, flow.loc.start());
+            format!("{}:{}: warning: {}", line, column, flow)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the inserted freezes as notes, one per line.
+pub fn freeze_report(source: &str, freezes: &[Freeze]) -> String {
+    freezes
+        .iter()
+        .map(|freeze| {
+            let (line, column) = line_and_column(source, freeze.loc.start());
+            format!("{}:{}: note: {}", line, column, freeze)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the inlined calls as notes and the functions marked `#[inline]` that cannot be inlined
+/// as warnings, one per line.
+pub fn inlining_report(source: &str, inlining: &Inlining) -> String {
+    let mut report = vec![];
+    for call in &inlining.calls {
+        let (line, column) = line_and_column(source, call.loc.start());
+        report.push(format!("{}:{}: note: {}", line, column, call));
+    }
+    for function in &inlining.not_inlined {
+        let (line, column) = line_and_column(source, function.loc.start());
+        report.push(format!("{}:{}: warning: {}", line, column, function));
+    }
+    report.join("\n")
+}
+
+/// Formats the branches whose condition always has the same value as notes, one per line.
+pub fn folding_report(source: &str, branches: &[FoldedBranch]) -> String {
+    branches
+        .iter()
+        .map(|branch| {
+            let (line, column) = line_and_column(source, branch.loc.start());
+            format!("{}:{}: note: {}", line, column, branch)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the functions whose locals share slots as notes, one per line.
+pub fn coalescing_report(source: &str, functions: &[CoalescedFunction]) -> String {
+    functions
+        .iter()
+        .map(|function| {
+            let (line, column) = line_and_column(source, function.loc.start());
+            format!("{}:{}: note: {}", line, column, function)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the moves chosen for bare uses of locals as notes, one per line.
+pub fn moves_report(source: &str, moves: &[InferredMove]) -> String {
+    moves
+        .iter()
+        .map(|inferred| {
+            let (line, column) = line_and_column(source, inferred.loc.start());
+            format!("{}:{}: note: {}", line, column, inferred)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the relocated addresses as notes, one per line, prefixed with the line and column of
+/// the IR source they point at unless they are the address of an import.
+pub fn relocation_report(source: &str, relocations: &[Relocation]) -> String {
+    relocations
+        .iter()
+        .map(|relocation| match relocation.loc() {
+            Some(loc) => {
+                let (line, column) = line_and_column(source, loc.start());
+                format!("{}:{}: note: {}", line, column, relocation)
+            }
+            None => format!("note: {}", relocation),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats lints, one per line, each followed by its notes.
+pub fn lint_report(source: &str, lints: &[Lint]) -> String {
+    let mut report = vec![];
+    for lint in lints {
+        let (line, column) = line_and_column(source, lint.report.loc.start());
+        report.push(format!("{}:{}: {}", line, column, lint));
+        for (loc, note) in &lint.report.notes {
+            let (line, column) = line_and_column(source, loc.start());
+            report.push(format!("{}:{}: note: {}", line, column, note));
+        }
+    }
+    report.join("\n")
+}
+
+/// Formats `acquires` mismatches, one function at a time. Each function is located in the IR
+/// source by its header, followed by the uses justifying every missing entry and the list it
+/// should declare. A use through a call is followed by the uses in the called functions, down to
+/// the access of global storage.
+pub fn acquires_report(source: &str, diffs: &[AcquiresDiff]) -> String {
+    let mut report = vec![];
+    for diff in diffs {
+        let (line, column) = line_and_column(source, diff.loc.start());
+        report.push(format!("{}:{}: {}", line, column, diff));
+        for struct_name in &diff.missing {
+            report.extend(acquisition_lines(
+                source,
+                &diff.function,
+                struct_name,
+                &diff.uses[struct_name],
+            ));
+        }
+        let suggested = diff.suggested();
+        if suggested.is_empty() {
+            report.push("    help: remove the acquires list".to_string());
+        } else {
+            report.push(format!(
+                "    help: declare `acquires {}`",
+                comma_separated(&suggested)
+            ));
+        }
+    }
+    report.join("\n")
+}
+
+/// Formats the `acquires` entries that no path of their function accesses as warnings, one
+/// function at a time. Each function is located in the IR source by its header, followed by the
+/// uses leading to the accesses in code no path reaches and the trimmed list it should declare.
+pub fn unused_acquires_report(source: &str, unused: &[UnusedAcquires]) -> String {
+    let mut report = vec![];
+    for function in unused {
+        let (line, column) = line_and_column(source, function.loc.start());
+        report.push(format!("{}:{}: warning: {}", line, column, function));
+        for (struct_name, chain) in &function.unreachable {
+            report.extend(acquisition_lines(
+                source,
+                &function.function,
+                struct_name,
+                chain,
+            ));
+        }
+        let help = match (function.unreachable.is_empty(), function.trimmed.is_empty()) {
+            (true, true) => "remove the acquires list".to_string(),
+            (false, true) => "remove the code no path reaches and the acquires list".to_string(),
+            (true, false) => format!("declare `acquires {}`", comma_separated(&function.trimmed)),
+            (false, false) => format!(
+                "remove the code no path reaches and declare `acquires {}`",
+                comma_separated(&function.trimmed)
+            ),
+        };
+        report.push(format!("    help: {}", help));
+    }
+    report.join("\n")
+}
+
+// Formats the uses of `function` and of the functions it calls leading to the access of
+// `struct_name`, one per line.
+fn acquisition_lines(
+    source: &str,
+    function: &FunctionName,
+    struct_name: &StructName,
+    chain: &[(FunctionName, Acquisition)],
+) -> Vec<String> {
+    chain
+        .iter()
+        .map(|(caller, acquisition)| {
+            let (line, column) = line_and_column(source, acquisition.loc.start());
+            let description = acquisition.describe(struct_name);
+            if caller == function {
+                format!("    {}:{}: {}", line, column, description)
+            } else {
+                format!("    {}:{}: in `{}`, {}", line, column, caller, description)
+            }
+        })
+        .collect()
+}
+
+/// Formats invalid `acquires` entries, one per line, prefixed with the line and column of the
+/// header of their function and followed by the resource they were probably meant to name.
+pub fn acquires_error_report(source: &str, errors: &[AcquiresError]) -> String {
+    let mut report = vec![];
+    for error in errors {
+        let (line, column) = line_and_column(source, error.loc().start());
+        report.push(format!("{}:{}: {}", line, column, error));
+        if let Some(suggestion) = error.suggestion() {
+            report.push(format!("    help: did you mean `{}`?", suggestion));
+        }
+    }
+    report.join("\n")
+}
+
+/// Checks and/or fills in the `acquires` lists of a parsed module. When `check` is set, any
+/// function whose declared list differs from the inferred one is reported as an error located in
+/// `source`. When `infer` is set, every list is replaced with the inferred one. Otherwise, the
+/// declared lists must only name resources of the module.
+pub fn process_acquires(
+    source: &str,
+    module: &mut ModuleDefinition,
+    infer: bool,
+    check: bool,
+) -> Result<()> {
+    if !infer {
+        let errors = resolve_acquires(module);
+        if !errors.is_empty() {
+            bail!(
+                "Invalid acquires in module {}:\n{}",
+                module.name,
+                acquires_error_report(source, &errors)
+            );
+        }
+    }
+    if check {
+        let diffs = acquires_diff(module);
+        if !diffs.is_empty() {
+            bail!(
+                "Acquires check failed in module {}:\n{}",
+                module.name,
+                acquires_report(source, &diffs)
+            );
+        }
+    }
+    if infer {
+        fill_acquires(module);
+    }
+    Ok(())
+}
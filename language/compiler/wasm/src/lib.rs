@@ -0,0 +1,162 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! The Move IR compiler front-end for WebAssembly, so that a browser can check and compile
+//! sources without a server round trip, as in a playground or a wallet validating scripts.
+//!
+//! The crate is built for `wasm32-unknown-unknown` with `wasm-pack build language/compiler/wasm`,
+//! which also generates the JavaScript bindings of the functions below. A source holds a single
+//! script or module, as a `.mvir` file does. Diagnostics are JSON arrays of the objects described
+//! in `ir_to_bytecode::diagnostics`.
+//!
+//! The parser is built without its `parallel` feature, since WebAssembly has no threads.
+
+pub use ir_to_bytecode::diagnostics::Diagnostic;
+use ir_to_bytecode::{
+    compiler::{compile_module, compile_script},
+    diagnostics::parse_address,
+    parser::try_parse_script_or_module,
+    type_checker::{check_module, check_script},
+};
+use libra_types::account_address::AccountAddress;
+use move_ir_types::ast::ScriptOrModule;
+use serde::Serialize;
+use stdlib::stdlib_modules;
+use wasm_bindgen::prelude::*;
+
+#[derive(Serialize)]
+struct ParseResult {
+    kind: Option<&'static str>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("diagnostics always serialize to JSON")
+}
+
+/// Parses `source`, returning `{"kind": "script" | "module" | null, "diagnostics": [...]}` as
+/// JSON. The parser stops at the first error, so there is at most one diagnostic.
+#[wasm_bindgen]
+pub fn parse(source: &str) -> String {
+    let result = match try_parse_script_or_module(source) {
+        Ok(ScriptOrModule::Script(_)) => ParseResult {
+            kind: Some("script"),
+            diagnostics: vec![],
+        },
+        Ok(ScriptOrModule::Module(_)) => ParseResult {
+            kind: Some("module"),
+            diagnostics: vec![],
+        },
+        Err(err) => ParseResult {
+            kind: None,
+            diagnostics: vec![Diagnostic::from_syntax_error(source, err)],
+        },
+    };
+    to_json(&result)
+}
+
+/// Returns the diagnostics of compiling `source` at `address`, as JSON. The array is empty when
+/// [`compile`](fn.compile.html) would succeed.
+#[wasm_bindgen]
+pub fn diagnostics(source: &str, address: Option<String>) -> String {
+    to_json(
+        &compile_source(source, address.as_deref())
+            .err()
+            .unwrap_or_default(),
+    )
+}
+
+/// Compiles `source` at `address` into serialized bytecode, throwing the JSON diagnostics if it
+/// does not compile.
+#[wasm_bindgen]
+pub fn compile(source: &str, address: Option<String>) -> Result<Vec<u8>, JsValue> {
+    compile_source(source, address.as_deref())
+        .map_err(|diagnostics| JsValue::from_str(&to_json(&diagnostics)))
+}
+
+/// Compiles the script or module of `source` against the standard library, with the sender or
+/// module address `address`, the default address if absent. Sources are parsed and type checked,
+/// but the bytecode is not verified.
+pub fn compile_source(source: &str, address: Option<&str>) -> Result<Vec<u8>, Vec<Diagnostic>> {
+    let address = match address {
+        Some(address) if !address.is_empty() => parse_address(address).map_err(|err| vec![err])?,
+        _ => AccountAddress::default(),
+    };
+    let parsed = try_parse_script_or_module(source)
+        .map_err(|err| vec![Diagnostic::from_syntax_error(source, err)])?;
+    let type_errors = match &parsed {
+        ScriptOrModule::Script(script) => check_script(script),
+        ScriptOrModule::Module(module) => check_module(module),
+    };
+    if !type_errors.is_empty() {
+        return Err(type_errors
+            .into_iter()
+            .map(|err| Diagnostic::located(source, err.loc, err.message))
+            .collect());
+    }
+
+    let mut bytes = vec![];
+    let deps = stdlib_modules();
+    match parsed {
+        ScriptOrModule::Script(script) => compile_script(address, script, deps)
+            .and_then(|(script, _)| script.serialize(&mut bytes)),
+        ScriptOrModule::Module(module) => compile_module(address, module, deps)
+            .and_then(|(module, _)| module.serialize(&mut bytes)),
+    }
+    .map_err(|err| vec![Diagnostic::unlocated(format!("{:#}", err))])?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vm::file_format::{CompiledModule, CompiledScript};
+
+    const SCRIPT: &str = "import 0x0.LibraAccount;
+main() {
+    let balance: u64;
+    balance = LibraAccount.balance(get_txn_sender());
+    return;
+}
+";
+
+    const MODULE: &str = "module M {
+    public f(): u64 {
+        return 42;
+    }
+}
+";
+
+    #[test]
+    fn parse_reports_the_kind_or_the_error() {
+        assert_eq!(parse(SCRIPT), r#"{"kind":"script","diagnostics":[]}"#);
+        assert_eq!(parse(MODULE), r#"{"kind":"module","diagnostics":[]}"#);
+        let json: serde_json::Value = serde_json::from_str(&parse("module M {\n  f(")).unwrap();
+        assert_eq!(json["kind"], serde_json::Value::Null);
+        assert_eq!(json["diagnostics"][0]["line"], 2);
+    }
+
+    #[test]
+    fn scripts_and_modules_compile_to_bytecode() {
+        let bytes = compile_source(SCRIPT, None).unwrap();
+        CompiledScript::deserialize(&bytes).unwrap();
+        let bytes = compile_source(MODULE, Some("0x123")).unwrap();
+        CompiledModule::deserialize(&bytes).unwrap();
+        assert_eq!(diagnostics(MODULE, None), "[]");
+    }
+
+    #[test]
+    fn errors_are_reported_as_diagnostics() {
+        let source = "main() {\n    let x: u64;\n    x = true;\n    return;\n}\n";
+        let errors = compile_source(source, None).unwrap_err();
+        assert_eq!(errors[0].line, Some(3));
+
+        let source =
+            "import 0x0.LibraAccount;\nmain() {\n    LibraAccount.nope();\n    return;\n}\n";
+        let errors = compile_source(source, None).unwrap_err();
+        assert_eq!(errors[0].line, None);
+
+        let errors = compile_source(MODULE, Some("xyz")).unwrap_err();
+        assert!(errors[0].message.starts_with("Invalid address xyz"));
+    }
+}
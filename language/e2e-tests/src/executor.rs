@@ -11,11 +11,13 @@ use libra_config::config::{VMConfig, VMPublishingOption};
 use libra_state_view::StateView;
 use libra_types::{
     access_path::AccessPath,
+    account_address::AccountAddress,
     account_config::AccountResource,
     crypto_proxies::ValidatorSet,
     language_storage::ModuleId,
     transaction::{
-        SignedTransaction, Transaction, TransactionOutput, TransactionPayload, TransactionStatus,
+        SignedTransaction, Transaction, TransactionArgument, TransactionOutput, TransactionPayload,
+        TransactionStatus,
     },
     vm_error::{StatusCode, VMStatus},
     write_set::WriteSet,
@@ -213,6 +215,18 @@ impl FakeExecutor {
             .expect("A block with one transaction should have one output")
     }
 
+    /// Runs `script` as a query from `sender` and returns the values its main function returns.
+    /// The writes of the query are discarded.
+    pub fn execute_query(
+        &self,
+        sender: AccountAddress,
+        script: Vec<u8>,
+        args: Vec<TransactionArgument>,
+    ) -> Result<Vec<TransactionArgument>, VMStatus> {
+        let mut vm = LibraVM::new(&self.config);
+        vm.execute_query(&self.data_store, sender, script, args)
+    }
+
     /// Get the blob for the associated AccessPath
     pub fn read_from_access_path(&self, path: &AccessPath) -> Option<Vec<u8>> {
         StateView::get(&self.data_store, path).unwrap()
@@ -11,10 +11,12 @@
 
 mod account_universe;
 mod create_account;
+mod generic_scripts;
 mod genesis;
 mod mint;
 mod module_publishing;
 mod peer_to_peer;
+mod queries;
 mod rotate_key;
 mod scripts;
 mod validator_set_management;
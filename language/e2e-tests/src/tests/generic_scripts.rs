@@ -0,0 +1,99 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    account::AccountData, compile::compile_script, executor::test_all_genesis,
+    transaction_status_eq,
+};
+use libra_config::config::VMPublishingOption;
+use libra_types::{
+    account_config,
+    language_storage::TypeTag,
+    transaction::{Script, TransactionArgument, TransactionPayload, TransactionStatus},
+    vm_error::{StatusCode, VMStatus},
+};
+
+// Offers an empty vector of `Element`s, which is published under a different type for each
+// `Element`.
+const OFFER_EMPTY_VECTOR: &str = "
+import 0x0.Offer;
+import 0x0.Vector;
+
+main<Element: unrestricted>(for: address) {
+    assert(!Offer.exists_at<Vector.T<Element>>(get_txn_sender()), 77);
+    Offer.create<Vector.T<Element>>(Vector.empty<Element>(), move(for));
+    return;
+}
+";
+
+#[test]
+fn generic_script_instantiated_with_type_arguments() {
+    test_all_genesis(Some(VMPublishingOption::Open), |mut executor| {
+        let sender = AccountData::new(1_000_000, 10);
+        executor.add_account_data(&sender);
+        let program = compile_script(OFFER_EMPTY_VECTOR);
+
+        let offer_txn = |ty_args: Vec<TypeTag>, sequence_number: u64| {
+            sender.account().create_signed_txn_impl(
+                *sender.address(),
+                TransactionPayload::Script(Script::new_generic(
+                    program.clone(),
+                    ty_args,
+                    vec![TransactionArgument::Address(*sender.address())],
+                )),
+                sequence_number,
+                100_000,
+                1,
+            )
+        };
+
+        executor.execute_and_apply(offer_txn(vec![TypeTag::U64], 10));
+
+        // An offer of `Vector.T<u64>` now exists, but none of `Vector.T<bool>`.
+        let output = executor.execute_transaction(offer_txn(vec![TypeTag::U64], 11));
+        assert!(transaction_status_eq(
+            &output.status(),
+            &TransactionStatus::Keep(VMStatus::new(StatusCode::ABORTED).with_sub_status(77))
+        ));
+        executor.apply_write_set(output.write_set());
+        executor.execute_and_apply(offer_txn(vec![TypeTag::Bool], 12));
+    });
+}
+
+#[test]
+fn generic_script_with_wrong_type_arguments() {
+    test_all_genesis(Some(VMPublishingOption::Open), |mut executor| {
+        let sender = AccountData::new(1_000_000, 10);
+        executor.add_account_data(&sender);
+        let program = compile_script(OFFER_EMPTY_VECTOR);
+
+        let cases = vec![
+            (vec![], StatusCode::NUMBER_OF_TYPE_ACTUALS_MISMATCH),
+            (
+                vec![TypeTag::U64, TypeTag::U64],
+                StatusCode::NUMBER_OF_TYPE_ACTUALS_MISMATCH,
+            ),
+            // `Element` must be unrestricted, and an account is a resource.
+            (
+                vec![TypeTag::Struct(account_config::account_struct_tag())],
+                StatusCode::CONTRAINT_KIND_MISMATCH,
+            ),
+        ];
+        for (idx, (ty_args, status)) in cases.into_iter().enumerate() {
+            let txn = sender.account().create_signed_txn_impl(
+                *sender.address(),
+                TransactionPayload::Script(Script::new_generic(
+                    program.clone(),
+                    ty_args,
+                    vec![TransactionArgument::Address(*sender.address())],
+                )),
+                10 + idx as u64,
+                100_000,
+                1,
+            );
+            let output = executor.execute_transaction(txn);
+            assert_eq!(output.status().vm_status().major_status, status);
+            executor.apply_write_set(output.write_set());
+        }
+    });
+}
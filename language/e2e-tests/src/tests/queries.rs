@@ -0,0 +1,83 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{account::AccountData, compile::compile_script, executor::test_all_genesis_default};
+use libra_types::{transaction::TransactionArgument, vm_error::StatusCode};
+
+#[test]
+fn query_returns_values() {
+    test_all_genesis_default(|executor| {
+        let sender = AccountData::new(1_000_000, 10);
+        let script = compile_script(
+            "
+            main(x: u64): u64 * bool * address {
+                return copy(x) + 1, move(x) > 1, get_txn_sender();
+            }
+            ",
+        );
+        let values = executor
+            .execute_query(
+                *sender.address(),
+                script,
+                vec![TransactionArgument::U64(41)],
+            )
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![
+                TransactionArgument::U64(42),
+                TransactionArgument::Bool(true),
+                TransactionArgument::Address(*sender.address()),
+            ]
+        );
+    });
+}
+
+#[test]
+fn query_reads_state_and_discards_writes() {
+    test_all_genesis_default(|mut executor| {
+        let sender = AccountData::new(1_000_000, 10);
+        let receiver = AccountData::new(100_000, 10);
+        executor.add_account_data(&sender);
+        executor.add_account_data(&receiver);
+        let script = compile_script(
+            "
+            import 0x0.LibraAccount;
+            main(payee: address): u64 {
+                LibraAccount.pay_from_sender(move(payee), 1000);
+                return LibraAccount.balance(get_txn_sender());
+            }
+            ",
+        );
+        let args = vec![TransactionArgument::Address(*receiver.address())];
+        let values = executor
+            .execute_query(*sender.address(), script, args)
+            .unwrap();
+        assert_eq!(values, vec![TransactionArgument::U64(999_000)]);
+        let sender_resource = executor
+            .read_account_resource(sender.account())
+            .expect("sender must exist");
+        assert_eq!(sender_resource.balance(), 1_000_000);
+    });
+}
+
+#[test]
+fn query_returning_other_values_is_rejected() {
+    test_all_genesis_default(|executor| {
+        let sender = AccountData::new(1_000_000, 10);
+        let script = compile_script(
+            "
+            main(): u128 {
+                return to_u128(1);
+            }
+            ",
+        );
+        let status = executor
+            .execute_query(*sender.address(), script, vec![])
+            .unwrap_err();
+        assert_eq!(
+            status.major_status,
+            StatusCode::INVALID_MAIN_FUNCTION_SIGNATURE
+        );
+    });
+}
@@ -5,6 +5,7 @@ use crate::{
     common::{strip, Sp},
     errors::*,
 };
+use ir_to_bytecode::annotations::Annotation;
 use std::iter::Peekable;
 
 /// The basic unit of input to the directive parser.
@@ -216,6 +217,26 @@ impl Directive {
     }
 }
 
+impl Directive {
+    /// Returns the directives checking the outcome stated by an annotation on the line `s`. They
+    /// span the whole annotation.
+    pub fn from_annotation(annotation: &Annotation, s: &str) -> Vec<Sp<Directive>> {
+        let start = s.len() - s.trim_start().len();
+        let end = s.trim_end().len();
+        let patterns = match annotation {
+            Annotation::Abort(code) => vec!["ABORTED".to_string(), code.to_string()],
+            Annotation::Compiler(message) => vec![message.clone()],
+            Annotation::Verifier(status) | Annotation::Runtime(status) => {
+                vec![format!("{:?}", status)]
+            }
+        };
+        patterns
+            .into_iter()
+            .map(|pattern| Sp::new(Directive::Check(pattern), start, end))
+            .collect()
+    }
+}
+
 impl AsRef<Directive> for Directive {
     fn as_ref(&self) -> &Directive {
         self
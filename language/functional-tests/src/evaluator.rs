@@ -25,13 +25,14 @@ use libra_types::{
 };
 use std::{
     fmt::{self, Debug},
-    str::FromStr,
     time::Duration,
 };
 use vm::file_format::{CompiledModule, CompiledScript};
 use vm::gas_schedule::{GasAlgebra, MAXIMUM_NUMBER_OF_GAS_UNITS};
 use vm::views::ModuleView;
 
+pub use ir_to_bytecode::directives::Stage;
+
 /// A transaction to be evaluated by the testing infra.
 /// Contains code and a transaction config.
 #[derive(Debug)]
@@ -40,30 +41,6 @@ pub struct Transaction<'a> {
     pub input: String,
 }
 
-/// Indicates one step in the pipeline the given move module/program goes through.
-//  Ord is derived as we need to be able to determine if one stage is before another.
-#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
-pub enum Stage {
-    Compiler,
-    Verifier,
-    Serializer,
-    Runtime,
-}
-
-impl FromStr for Stage {
-    type Err = Error;
-
-    fn from_str(s: &str) -> Result<Self> {
-        match s {
-            "compiler" => Ok(Stage::Compiler),
-            "verifier" => Ok(Stage::Verifier),
-            "serializer" => Ok(Stage::Serializer),
-            "runtime" => Ok(Stage::Runtime),
-            _ => Err(ErrorKind::Other(format!("unrecognized stage '{:?}'", s)).into()),
-        }
-    }
-}
-
 /// Evaluation status: success or failure.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Status {
@@ -13,6 +13,7 @@ use crate::{
     errors::*,
     evaluator::Transaction,
 };
+use ir_to_bytecode::annotations::Annotation;
 use once_cell::sync::Lazy;
 use regex::{Captures, Regex};
 
@@ -81,6 +82,16 @@ pub fn split_input(
             transaction_config.push(entry);
             continue;
         }
+        if let Some(annotation) = Annotation::parse_line(line)
+            .map_err(|msg| ErrorKind::Other(format!("line {}: {}", line_idx + 1, msg)))?
+        {
+            directives.extend(
+                Directive::from_annotation(&annotation, line)
+                    .into_iter()
+                    .map(|sp| sp.into_line_sp(line_idx)),
+            );
+            continue;
+        }
         if let Ok(dirs) = Directive::parse_line(line) {
             directives.extend(dirs.into_iter().map(|sp| sp.into_line_sp(line_idx)));
             continue;
@@ -69,3 +69,26 @@ fn parse_input_empty_transaction_with_config() {
         main() {}
     ").unwrap_err();
 }
+
+#[rustfmt::skip]
+#[test]
+fn parse_input_annotations() {
+    let input = r"
+        //! abort 7
+        //! verifier: COPYLOC_EXISTS_BORROW_ERROR
+        main() { abort 7; }
+    ";
+    let (_, directives, _) = split_input(input.lines()).unwrap();
+    let patterns: Vec<_> = directives.iter().map(|d| d.inner.pattern_str()).collect();
+    assert_eq!(patterns, vec!["ABORTED", "7", "COPYLOC_EXISTS_BORROW_ERROR"]);
+    assert_eq!((directives[0].line, directives[0].start, directives[0].end), (1, 8, 19));
+}
+
+#[rustfmt::skip]
+#[test]
+fn parse_input_malformed_annotation() {
+    parse_input(r"
+        //! verifier: NOT_A_STATUS
+        main() {}
+    ").unwrap_err();
+}
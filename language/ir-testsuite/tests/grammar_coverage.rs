@@ -0,0 +1,54 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks that the test suite exercises every production of the IR grammar, so that each syntax
+//! form has at least one test.
+
+use functional_tests::{
+    config::global::Config as GlobalConfig,
+    preprocessor::{build_transactions, split_input},
+};
+use ir_to_bytecode::parser::parse_script_or_module;
+use ir_to_bytecode_syntax::coverage;
+use std::{
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+
+fn collect_tests(dir: &Path, tests: &mut Vec<PathBuf>) {
+    for entry in read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_tests(&path, tests);
+        } else if path.extension().map_or(false, |ext| ext == "mvir") {
+            tests.push(path);
+        }
+    }
+}
+
+#[test]
+fn test_suite_covers_grammar() {
+    let mut tests = vec![];
+    collect_tests(Path::new("tests"), &mut tests);
+
+    coverage::reset();
+    for path in tests {
+        let input = read_to_string(&path).unwrap();
+        let (config, _, transactions) = split_input(input.lines()).unwrap();
+        let config = GlobalConfig::build(&config).unwrap();
+        for transaction in build_transactions(&config, &transactions).unwrap() {
+            // Tests of parse errors exercise the productions up to the error.
+            let _ = parse_script_or_module(&transaction.input);
+        }
+    }
+    // The test preprocessor substitutes addresses for placeholders, so they never reach the
+    // parser from the test suite.
+    parse_script_or_module("main() { return {{placeholder}}; }").unwrap();
+
+    let report = coverage::report();
+    assert!(
+        report.uncovered().is_empty(),
+        "add tests for the uncovered productions:\n{}",
+        report
+    );
+}
@@ -0,0 +1,84 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks that every script and module of the test suite that parses can be printed and parsed
+//! back into the same AST, both with the source formatter and with the AST printer.
+
+use functional_tests::{
+    config::global::Config as GlobalConfig,
+    preprocessor::{build_transactions, split_input},
+};
+use ir_to_bytecode::{
+    formatter::{format_module, format_script, format_source, FormatOptions},
+    parser::parse_script_or_module,
+};
+use move_ir_types::{ast::ScriptOrModule, location::EraseLocations};
+use std::{fs::read_to_string, path::Path};
+
+fn parse(source: &str) -> anyhow::Result<ScriptOrModule> {
+    let mut parsed = parse_script_or_module(source)?;
+    parsed.erase_locations();
+    Ok(parsed)
+}
+
+fn same_structure(left: &ScriptOrModule, right: &ScriptOrModule) -> bool {
+    match (left, right) {
+        (ScriptOrModule::Module(left), ScriptOrModule::Module(right)) => left == right,
+        (ScriptOrModule::Script(left), ScriptOrModule::Script(right)) => {
+            left.imports == right.imports && left.main == right.main
+        }
+        _ => false,
+    }
+}
+
+fn check_reparse(parsed: &ScriptOrModule, printed: &str, printer: &str) -> anyhow::Result<()> {
+    let reparsed = parse(printed).map_err(|err| {
+        anyhow::format_err!("{} output does not parse: {}\n{}", printer, err, printed)
+    })?;
+    if !same_structure(parsed, &reparsed) {
+        anyhow::bail!("{} output parses to a different AST:\n{}", printer, printed);
+    }
+    Ok(())
+}
+
+/// Drops the specification clauses, invariants and synthetics, which the AST printer leaves out.
+fn without_specifications(script_or_module: &ScriptOrModule) -> ScriptOrModule {
+    let mut result = script_or_module.clone();
+    match &mut result {
+        ScriptOrModule::Script(script) => script.main.value.specifications.clear(),
+        ScriptOrModule::Module(module) => {
+            module.synthetics.clear();
+            for s in &mut module.structs {
+                s.value.invariants.clear();
+            }
+            for (_, f) in &mut module.functions {
+                f.value.specifications.clear();
+            }
+        }
+    }
+    result
+}
+
+fn run_test(path: &Path) -> datatest_stable::Result<()> {
+    let input = read_to_string(path)?;
+    let (config, _, transactions) = split_input(input.lines())?;
+    let config = GlobalConfig::build(&config)?;
+    let options = FormatOptions::default();
+    for transaction in build_transactions(&config, &transactions)? {
+        let source = &transaction.input;
+        // Tests of parse errors have nothing to print.
+        let parsed = match parse(source) {
+            Ok(parsed) => parsed,
+            Err(_) => continue,
+        };
+        check_reparse(&parsed, &format_source(source, &options)?, "formatter")?;
+        let printed = match &parsed {
+            ScriptOrModule::Script(script) => format_script(script, &options),
+            ScriptOrModule::Module(module) => format_module(module, &options),
+        };
+        check_reparse(&without_specifications(&parsed), &printed, "printer")?;
+    }
+    Ok(())
+}
+
+datatest_stable::harness!(run_test, "tests", r".*\.mvir");
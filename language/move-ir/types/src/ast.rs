@@ -13,8 +13,9 @@ use libra_types::{
 use once_cell::sync::Lazy;
 use std::{
     collections::{HashSet, VecDeque},
-    fmt,
+    fmt, mem,
     ops::Deref,
+    sync::Arc,
 };
 
 /// Generic wrapper that keeps file locations for any ast-node
@@ -66,6 +67,8 @@ pub struct Script {
     pub imports: Vec<ImportDefinition>,
     /// The transaction script's `main` procedure
     pub main: Function,
+    /// The version of the language declared by a `pragma language` at the start of the source
+    pub language_version: Option<u64>,
 }
 
 //**************************************************************************************************
@@ -99,6 +102,8 @@ pub struct ModuleDefinition {
     pub functions: Vec<(FunctionName, Function)>,
     /// the synthetic, specification variables the module defines.
     pub synthetics: Vec<SyntheticDefinition>,
+    /// The version of the language declared by a `pragma language` at the start of the source
+    pub language_version: Option<u64>,
 }
 
 /// Either a qualified module name like `addr.m` or `Transaction.m`, which refers to a module in
@@ -109,6 +114,35 @@ pub enum ModuleIdent {
     Qualified(QualifiedModuleIdent),
 }
 
+//**************************************************************************************************
+// Conditional compilation
+//**************************************************************************************************
+
+/// The condition of a `#[cfg(...)]` attribute, under which the declaration it is attached to is
+/// compiled
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CfgPredicate {
+    /// `name`, which holds if the flag is set, e.g. `test`
+    Flag(String),
+    /// `feature = "name"`, which holds if the feature is enabled
+    Feature(String),
+    /// `not(p)`
+    Not(Box<CfgPredicate>),
+    /// `all(p, ...)`, which holds if every predicate holds, including when there are none
+    All(Vec<CfgPredicate>),
+    /// `any(p, ...)`, which holds if some predicate holds, so not when there are none
+    Any(Vec<CfgPredicate>),
+}
+
+/// The attribute of a procedure overriding whether the inlining pass inlines its calls
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InlineHint {
+    /// `#[inline]`, which inlines the calls whatever the size of the procedure
+    Inline,
+    /// `#[never_inline]`, which keeps the calls
+    NeverInline,
+}
+
 //**************************************************************************************************
 // Imports
 //**************************************************************************************************
@@ -122,6 +156,8 @@ pub struct ImportDefinition {
     /// the alias for that dependency
     /// `m`
     pub alias: ModuleName,
+    /// The condition under which the import is compiled, if it has a `cfg` attribute
+    pub cfg: Option<CfgPredicate>,
 }
 
 //**************************************************************************************************
@@ -184,6 +220,8 @@ pub enum Type {
     Reference(bool, Box<Type>),
     /// A type parameter
     TypeParameter(TypeVar_),
+    /// A tuple of at least two types, which only locals, parameters and return values may have
+    Tuple(Vec<Type>),
 }
 
 //**************************************************************************************************
@@ -228,6 +266,8 @@ pub struct StructDefinition_ {
     pub fields: StructDefinitionFields,
     /// the invariants for this struct
     pub invariants: Vec<Invariant>,
+    /// The condition under which the struct is compiled, if it has a `cfg` attribute
+    pub cfg: Option<CfgPredicate>,
 }
 
 /// The type of a StructDefinition along with its source location information
@@ -293,6 +333,9 @@ pub struct Function_ {
     pub visibility: FunctionVisibility,
     /// The type signature
     pub signature: FunctionSignature,
+    /// The location of the header, from the start of the declaration to the end of its acquires
+    /// list. It is empty for functions that were not parsed
+    pub header: Loc,
     /// List of nominal resources (declared in this module) that the procedure might access
     /// Either through: BorrowGlobal, MoveFrom, or transitively through another procedure
     /// This list of acquires grants the borrow checker the ability to statically verify the safety
@@ -302,6 +345,12 @@ pub struct Function_ {
     pub specifications: Vec<Condition>,
     /// The code for the procedure
     pub body: FunctionBody,
+    /// The condition under which the procedure is compiled, if it has a `cfg` attribute
+    pub cfg: Option<CfgPredicate>,
+    /// The inlining attribute of the procedure, if it has one
+    pub inline: Option<InlineHint>,
+    /// The keys and values of the `annotate` attribute of the procedure, in source order
+    pub annotations: Vec<(String, String)>,
 }
 
 /// The type of a Function coupled with its source location information.
@@ -338,6 +387,30 @@ pub enum Builtin {
     ToU64,
     /// Cast an integer into u128.
     ToU128,
+
+    /// Rotate the bits of an integer left by a `u8` amount, modulo its width.
+    Rotl,
+    /// Rotate the bits of an integer right by a `u8` amount, modulo its width.
+    Rotr,
+    /// Count the bits set in an integer, as a u64.
+    CountOnes,
+
+    /// Add two integers, returning the sum and `true`, or 0 and `false` on overflow.
+    CheckedAdd,
+    /// Subtract two integers, returning the difference and `true`, or 0 and `false` on underflow.
+    CheckedSub,
+    /// Add two integers, clamping the sum to the largest value of their type.
+    SaturatingAdd,
+    /// Subtract two integers, clamping the difference to 0.
+    SaturatingSub,
+
+    /// Hash a bytearray with SHA-256.
+    Sha2_256,
+    /// Hash a bytearray with SHA3-256.
+    Sha3_256,
+
+    /// Serialize the value a reference points to with LCS, the canonical serialization.
+    ToBytes(Type),
 }
 
 /// Enum for different function calls
@@ -379,10 +452,13 @@ pub enum Cmd_ {
     Abort(Option<Box<Exp>>),
     /// `return e_1, ... , e_j`
     Return(Box<Exp>),
-    /// `break`
-    Break,
+    /// `break` or `break e`, leaving a loop whose value is assigned with the value of `e`
+    Break(Option<Box<Exp>>),
     /// `continue`
     Continue,
+    /// `emit h, e`, emitting the event `e` to the event handle `h`, a
+    /// `&mut LibraAccount.EventHandle` of the type of `e`
+    Emit(Box<Exp>, Box<Exp>),
     Exp(Box<Exp>),
 }
 /// The type of a command with its location
@@ -413,6 +489,9 @@ pub struct While {
 pub struct Loop {
     /// The body of the loop
     pub block: Block,
+    /// For `l_1, ..., l_j = loop { s };`, the values the `break e` commands of the loop assign,
+    /// empty for a loop without a value
+    pub results: Vec<LValue>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -424,7 +503,7 @@ pub enum Statement {
     IfElseStatement(IfElse),
     /// `while (e) { s }`
     WhileStatement(While),
-    /// `loop { s }`
+    /// `loop { s }` or `l_1, ..., l_j = loop { s };`
     LoopStatement(Loop),
     /// no-op that eases parsing in some places
     EmptyStatement,
@@ -458,8 +537,8 @@ pub enum CopyableVal_ {
     U128(u128),
     /// true or false
     Bool(bool),
-    /// `b"<bytes>"`
-    ByteArray(ByteArray),
+    /// `b"<bytes>"`, shared so that copies of the AST do not copy large literals
+    ByteArray(Arc<ByteArray>),
 }
 
 /// The type of a value and its location
@@ -522,7 +601,7 @@ pub enum BinOp {
 }
 
 /// Enum for all expressions
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub enum Exp_ {
     /// `*e`
     Dereference(Box<Exp>),
@@ -551,12 +630,16 @@ pub enum Exp_ {
     Move(Var),
     /// `copy(x)`
     Copy(Var),
+    /// `x`, moved or copied as decided by the compiler
+    Var(Var),
     /// `&x` or `&mut x`
     BorrowLocal(bool, Var),
     /// `f(e)` or `f(e_1, e_2, ..., e_j)`
     FunctionCall(FunctionCall, Box<Exp>),
     /// (e_1, e_2, e_3, ..., e_j)
     ExprList(Vec<Exp>),
+    /// `{{name}}`, a value supplied when compiling the script
+    Placeholder(Identifier),
 }
 
 /// The type for a `Exp_` and its location
@@ -586,7 +669,11 @@ impl Program {
 impl Script {
     /// Create a new `Script` from the imports and the main function
     pub fn new(imports: Vec<ImportDefinition>, main: Function) -> Self {
-        Script { imports, main }
+        Script {
+            imports,
+            main,
+            language_version: None,
+        }
     }
 
     /// Accessor for the body of the 'main' procedure
@@ -682,6 +769,7 @@ impl ModuleDefinition {
             structs,
             functions,
             synthetics,
+            language_version: None,
         })
     }
 
@@ -748,7 +836,11 @@ impl ImportDefinition {
             Some(alias) => alias,
             None => ident.name().clone(),
         };
-        ImportDefinition { ident, alias }
+        ImportDefinition {
+            ident,
+            alias,
+            cfg: None,
+        }
     }
 }
 
@@ -793,6 +885,7 @@ impl StructDefinition_ {
             type_formals,
             fields: StructDefinitionFields::Move { fields },
             invariants,
+            cfg: None,
         })
     }
 
@@ -810,6 +903,7 @@ impl StructDefinition_ {
             type_formals,
             fields: StructDefinitionFields::Native,
             invariants: vec![],
+            cfg: None,
         })
     }
 }
@@ -867,9 +961,13 @@ impl Function_ {
         Function_ {
             visibility,
             signature,
+            header: Loc::default(),
             acquires,
             specifications,
             body,
+            cfg: None,
+            inline: None,
+            annotations: vec![],
         }
     }
 }
@@ -1012,7 +1110,7 @@ impl Exp_ {
 
     /// Creates a new bytearray `Exp` with no location information
     pub fn byte_array(buf: ByteArray) -> Exp {
-        Exp_::value(CopyableVal_::ByteArray(buf))
+        Exp_::value(CopyableVal_::ByteArray(Arc::new(buf)))
     }
 
     /// Creates a new pack/struct-instantiation `Exp` with no location information
@@ -1067,6 +1165,107 @@ impl Exp_ {
     pub fn expr_list(exps: Vec<Exp>) -> Exp {
         Spanned::no_loc(Exp_::ExprList(exps))
     }
+
+    /// Returns the subexpressions of the expression, in the order they are evaluated.
+    pub fn subexps(&self) -> Vec<&Exp> {
+        match self {
+            Exp_::Dereference(e)
+            | Exp_::UnaryExp(_, e)
+            | Exp_::Borrow { exp: e, .. }
+            | Exp_::FunctionCall(_, e) => vec![e],
+            Exp_::BinopExp(lhs, _, rhs) => vec![lhs, rhs],
+            Exp_::Pack(_, _, fields) => fields.iter().map(|(_, e)| e).collect(),
+            Exp_::ExprList(exps) => exps.iter().collect(),
+            Exp_::Value(_)
+            | Exp_::Move(_)
+            | Exp_::Copy(_)
+            | Exp_::Var(_)
+            | Exp_::BorrowLocal(_, _)
+            | Exp_::Placeholder(_) => vec![],
+        }
+    }
+
+    /// Returns the subexpressions of the expression, in the order they are evaluated.
+    pub fn subexps_mut(&mut self) -> Vec<&mut Exp> {
+        match self {
+            Exp_::Dereference(e)
+            | Exp_::UnaryExp(_, e)
+            | Exp_::Borrow { exp: e, .. }
+            | Exp_::FunctionCall(_, e) => vec![e],
+            Exp_::BinopExp(lhs, _, rhs) => vec![lhs, rhs],
+            Exp_::Pack(_, _, fields) => fields.iter_mut().map(|(_, e)| e).collect(),
+            Exp_::ExprList(exps) => exps.iter_mut().collect(),
+            Exp_::Value(_)
+            | Exp_::Move(_)
+            | Exp_::Copy(_)
+            | Exp_::Var(_)
+            | Exp_::BorrowLocal(_, _)
+            | Exp_::Placeholder(_) => vec![],
+        }
+    }
+
+    // Copies the expression, with empty lists in place of its subexpressions.
+    fn clone_without_subexps(&self) -> Exp_ {
+        let hole = || Spanned::no_loc(Exp_::ExprList(vec![]));
+        match self {
+            Exp_::Dereference(_) => Exp_::Dereference(Box::new(hole())),
+            Exp_::UnaryExp(op, _) => Exp_::UnaryExp(op.clone(), Box::new(hole())),
+            Exp_::BinopExp(_, op, _) => {
+                Exp_::BinopExp(Box::new(hole()), op.clone(), Box::new(hole()))
+            }
+            Exp_::Value(v) => Exp_::Value(v.clone()),
+            Exp_::Pack(name, tys, fields) => Exp_::Pack(
+                name.clone(),
+                tys.clone(),
+                fields.iter().map(|(f, _)| (f.clone(), hole())).collect(),
+            ),
+            Exp_::Borrow {
+                is_mutable, field, ..
+            } => Exp_::Borrow {
+                is_mutable: *is_mutable,
+                exp: Box::new(hole()),
+                field: field.clone(),
+            },
+            Exp_::Move(v) => Exp_::Move(v.clone()),
+            Exp_::Copy(v) => Exp_::Copy(v.clone()),
+            Exp_::Var(v) => Exp_::Var(v.clone()),
+            Exp_::BorrowLocal(is_mutable, v) => Exp_::BorrowLocal(*is_mutable, v.clone()),
+            Exp_::FunctionCall(call, _) => Exp_::FunctionCall(call.clone(), Box::new(hole())),
+            Exp_::ExprList(exps) => Exp_::ExprList(exps.iter().map(|_| hole()).collect()),
+            Exp_::Placeholder(name) => Exp_::Placeholder(name.clone()),
+        }
+    }
+
+    /// Moves the subexpressions out of the expression, in the order they are evaluated, leaving
+    /// empty lists in their place.
+    pub fn take_subexps(&mut self) -> Vec<Exp> {
+        self.subexps_mut()
+            .into_iter()
+            .map(|e| mem::replace(e, Spanned::no_loc(Exp_::ExprList(vec![]))))
+            .collect()
+    }
+}
+
+/// Calls `f` with `exp` and then with each of its subexpressions, in the order they are
+/// evaluated. The expressions left to visit are kept on a stack of their own, so that visiting
+/// deeply nested expressions does not overflow the stack.
+pub fn visit_exp<'a>(exp: &'a Exp, mut f: impl FnMut(&'a Exp)) {
+    let mut exps = vec![exp];
+    while let Some(exp) = exps.pop() {
+        f(exp);
+        exps.extend(exp.value.subexps().into_iter().rev());
+    }
+}
+
+/// Calls `f` with `exp` and then with each of its subexpressions, as
+/// [`visit_exp`](fn.visit_exp.html) does. `f` may replace the expression it is given, whose new
+/// subexpressions are then visited.
+pub fn visit_exp_mut(exp: &mut Exp, mut f: impl FnMut(&mut Exp)) {
+    let mut exps = vec![exp];
+    while let Some(exp) = exps.pop() {
+        f(exp);
+        exps.extend(exp.value.subexps_mut().into_iter().rev());
+    }
 }
 
 /// Parses a field.
@@ -1122,6 +1321,40 @@ impl<T> Spanned<T> {
     }
 }
 
+// The drop glue of an expression would recurse into its subexpressions, which are nested without
+// bound in the source. They are moved out and dropped one at a time instead.
+impl Drop for Exp_ {
+    fn drop(&mut self) {
+        let mut exps = self.take_subexps();
+        while let Some(mut exp) = exps.pop() {
+            exps.append(&mut exp.value.take_subexps());
+        }
+    }
+}
+
+// The derived clone would recurse into the subexpressions as the drop glue does. Each expression
+// is copied without its subexpressions instead, and they are copied into their places one at a
+// time.
+impl Clone for Exp_ {
+    fn clone(&self) -> Self {
+        let mut copy = self.clone_without_subexps();
+        let mut exps: Vec<_> = copy.subexps_mut().into_iter().zip(self.subexps()).collect();
+        while let Some((dest, src)) = exps.pop() {
+            *dest = Spanned {
+                span: src.span,
+                value: src.value.clone_without_subexps(),
+            };
+            exps.extend(
+                dest.value
+                    .subexps_mut()
+                    .into_iter()
+                    .zip(src.value.subexps()),
+            );
+        }
+        copy
+    }
+}
+
 impl Iterator for Block_ {
     type Item = Statement;
 
@@ -1198,6 +1431,27 @@ impl fmt::Display for ImportDefinition {
     }
 }
 
+impl fmt::Display for InlineHint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InlineHint::Inline => write!(f, "inline"),
+            InlineHint::NeverInline => write!(f, "never_inline"),
+        }
+    }
+}
+
+impl fmt::Display for CfgPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CfgPredicate::Flag(name) => write!(f, "{}", name),
+            CfgPredicate::Feature(name) => write!(f, "feature = \"{}\"", name),
+            CfgPredicate::Not(predicate) => write!(f, "not({})", predicate),
+            CfgPredicate::All(predicates) => write!(f, "all({})", intersperse(predicates, ", ")),
+            CfgPredicate::Any(predicates) => write!(f, "any({})", intersperse(predicates, ", ")),
+        }
+    }
+}
+
 impl fmt::Display for ModuleName {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.0)
@@ -1278,10 +1532,11 @@ impl fmt::Display for FunctionBody {
 
 // TODO: This function should take an iterator instead.
 fn intersperse<T: fmt::Display>(items: &[T], join: &str) -> String {
-    // TODO: Any performance issues here? Could be O(n^2) if not optimized.
-    items.iter().fold(String::new(), |acc, v| {
-        format!("{acc}{join}{v}", acc = acc, join = join, v = v)
-    })
+    items
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(join)
 }
 
 fn format_fields<T: fmt::Display>(fields: &[(Field, T)]) -> String {
@@ -1342,6 +1597,7 @@ impl fmt::Display for Type {
                 write!(f, "&{}{}", if *is_mutable { "mut " } else { "" }, t)
             }
             Type::TypeParameter(s) => write!(f, "{}", s),
+            Type::Tuple(tys) => write!(f, "({})", intersperse(tys, ", ")),
         }
     }
 }
@@ -1375,6 +1631,16 @@ impl fmt::Display for Builtin {
             Builtin::ToU8 => write!(f, "to_u8"),
             Builtin::ToU64 => write!(f, "to_u64"),
             Builtin::ToU128 => write!(f, "to_u128"),
+            Builtin::Rotl => write!(f, "rotl"),
+            Builtin::Rotr => write!(f, "rotr"),
+            Builtin::CountOnes => write!(f, "count_ones"),
+            Builtin::CheckedAdd => write!(f, "checked_add"),
+            Builtin::CheckedSub => write!(f, "checked_sub"),
+            Builtin::SaturatingAdd => write!(f, "saturating_add"),
+            Builtin::SaturatingSub => write!(f, "saturating_sub"),
+            Builtin::Sha2_256 => write!(f, "sha2_256"),
+            Builtin::Sha3_256 => write!(f, "sha3_256"),
+            Builtin::ToBytes(t) => write!(f, "to_bytes<{}>", t),
         }
     }
 }
@@ -1434,8 +1700,10 @@ impl fmt::Display for Cmd_ {
             Cmd_::Abort(None) => write!(f, "abort;"),
             Cmd_::Abort(Some(err)) => write!(f, "abort {};", err),
             Cmd_::Return(exps) => write!(f, "return {};", exps),
-            Cmd_::Break => write!(f, "break;"),
+            Cmd_::Break(None) => write!(f, "break;"),
+            Cmd_::Break(Some(e)) => write!(f, "break {};", e),
             Cmd_::Continue => write!(f, "continue;"),
+            Cmd_::Emit(handle, e) => write!(f, "emit {}, {};", handle, e),
             Cmd_::Exp(e) => write!(f, "({});", e),
         }
     }
@@ -1472,7 +1740,17 @@ impl fmt::Display for While {
 
 impl fmt::Display for Loop {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "loop {{\n{:indent$}\n}}", self.block, indent = 4)?;
+        if self.results.is_empty() {
+            write!(f, "loop {{\n{:indent$}\n}}", self.block, indent = 4)?;
+        } else {
+            write!(
+                f,
+                "{} = loop {{\n{:indent$}\n}};",
+                intersperse(&self.results, ", "),
+                self.block,
+                indent = 4
+            )?;
+        }
         Ok(())
     }
 }
@@ -1586,6 +1864,7 @@ impl fmt::Display for Exp_ {
             ),
             Exp_::Move(v) => write!(f, "move({})", v),
             Exp_::Copy(v) => write!(f, "copy({})", v),
+            Exp_::Var(v) => write!(f, "{}", v),
             Exp_::BorrowLocal(is_mutable, v) => {
                 write!(f, "&{}{}", if *is_mutable { "mut " } else { "" }, v)
             }
@@ -1597,6 +1876,7 @@ impl fmt::Display for Exp_ {
                     write!(f, "({})", intersperse(exps, ", "))
                 }
             }
+            Exp_::Placeholder(name) => write!(f, "{{{{{}}}}}", name),
         }
     }
 }
@@ -0,0 +1,582 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Approximate memory footprint of ASTs.
+//!
+//! [`memory_footprint`](fn.memory_footprint.html) adds up the memory an AST uses, including the
+//! unused capacity of its vectors, so that services keeping parsed programs in memory can budget
+//! for them. The memory is split between the nodes of the AST, the strings and byte arrays they
+//! hold, and the spans locating them in the source.
+//!
+//! The sizes do not include the overhead of the allocator. Every field of every node is looked at,
+//! so a field added to the AST does not compile until it is accounted for here.
+
+// The fields without a heap size are matched by name, and not with `..`, for the above.
+#![allow(clippy::unneeded_field_pattern)]
+
+use crate::{ast::*, spec_language_ast::*};
+use libra_types::{
+    byte_array::ByteArray,
+    identifier::{IdentStr, Identifier},
+};
+use std::{collections::VecDeque, mem::size_of};
+
+/// The memory used by an AST, in bytes.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MemoryFootprint {
+    /// The memory of the nodes, spans excluded, starting with the root.
+    pub nodes: usize,
+    /// The memory of identifiers, strings and byte arrays.
+    pub strings: usize,
+    /// The memory of the spans.
+    pub spans: usize,
+}
+
+impl MemoryFootprint {
+    pub fn total(&self) -> usize {
+        self.nodes + self.strings + self.spans
+    }
+}
+
+/// Returns the memory used by `ast`, as if it was itself allocated on the heap.
+pub fn memory_footprint<T: HeapSize>(ast: &T) -> MemoryFootprint {
+    let mut footprint = MemoryFootprint {
+        nodes: size_of::<T>(),
+        ..MemoryFootprint::default()
+    };
+    ast.add_heap_size(&mut footprint);
+    footprint
+}
+
+/// A value that can own memory on the heap.
+pub trait HeapSize {
+    /// Adds the memory the value owns to `footprint`, not counting the value itself.
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint);
+}
+
+impl<T: HeapSize> HeapSize for Box<T> {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        footprint.nodes += size_of::<T>();
+        (**self).add_heap_size(footprint)
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        footprint.nodes += self.capacity() * size_of::<T>();
+        for item in self {
+            item.add_heap_size(footprint)
+        }
+    }
+}
+
+impl<T: HeapSize> HeapSize for VecDeque<T> {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        footprint.nodes += self.capacity() * size_of::<T>();
+        for item in self {
+            item.add_heap_size(footprint)
+        }
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        if let Some(value) = self {
+            value.add_heap_size(footprint)
+        }
+    }
+}
+
+impl<T: HeapSize, U: HeapSize> HeapSize for (T, U) {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        self.0.add_heap_size(footprint);
+        self.1.add_heap_size(footprint)
+    }
+}
+
+impl<T: HeapSize> HeapSize for Spanned<T> {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        // The span is part of the node, which was counted by its owner.
+        footprint.nodes -= size_of::<Loc>();
+        footprint.spans += size_of::<Loc>();
+        self.value.add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for String {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        footprint.strings += self.capacity()
+    }
+}
+
+impl HeapSize for Identifier {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        footprint.strings += self.len()
+    }
+}
+
+impl HeapSize for &IdentStr {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        // The names of the AST are identifiers, which own their string.
+        footprint.strings += self.len()
+    }
+}
+
+/// Values owning no memory.
+macro_rules! no_heap_size {
+    ($($ty:ty),*) => {
+        $(
+            impl HeapSize for $ty {
+                fn add_heap_size(&self, _footprint: &mut MemoryFootprint) {}
+            }
+        )*
+    };
+}
+
+no_heap_size!(bool, Kind, FunctionVisibility, UnaryOp, BinOp);
+
+impl HeapSize for Program {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        let Program { modules, script } = self;
+        modules.add_heap_size(footprint);
+        script.add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for ScriptOrModule {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        match self {
+            ScriptOrModule::Script(script) => script.add_heap_size(footprint),
+            ScriptOrModule::Module(module) => module.add_heap_size(footprint),
+        }
+    }
+}
+
+impl HeapSize for Script {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        let Script {
+            imports,
+            main,
+            language_version: _,
+        } = self;
+        imports.add_heap_size(footprint);
+        main.add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for ModuleDefinition {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        let ModuleDefinition {
+            name,
+            imports,
+            structs,
+            functions,
+            synthetics,
+            language_version: _,
+        } = self;
+        name.add_heap_size(footprint);
+        imports.add_heap_size(footprint);
+        structs.add_heap_size(footprint);
+        functions.add_heap_size(footprint);
+        synthetics.add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for ModuleName {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        self.as_inner().add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for StructName {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        self.as_inner().add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for FunctionName {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        self.as_inner().add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for Var_ {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        self.name().add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for TypeVar_ {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        self.name().add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for Field_ {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        self.name().add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for ModuleIdent {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        match self {
+            ModuleIdent::Transaction(name) => name.add_heap_size(footprint),
+            ModuleIdent::Qualified(QualifiedModuleIdent { name, address: _ }) => {
+                name.add_heap_size(footprint)
+            }
+        }
+    }
+}
+
+impl HeapSize for ImportDefinition {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        let ImportDefinition { ident, alias, cfg } = self;
+        ident.add_heap_size(footprint);
+        alias.add_heap_size(footprint);
+        cfg.add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for CfgPredicate {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        match self {
+            CfgPredicate::Flag(name) | CfgPredicate::Feature(name) => name.add_heap_size(footprint),
+            CfgPredicate::Not(predicate) => predicate.add_heap_size(footprint),
+            CfgPredicate::All(predicates) | CfgPredicate::Any(predicates) => {
+                predicates.add_heap_size(footprint)
+            }
+        }
+    }
+}
+
+impl HeapSize for Type {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        match self {
+            Type::Address | Type::U8 | Type::U64 | Type::U128 | Type::Bool | Type::ByteArray => (),
+            Type::Struct(ident, tys) => {
+                ident.add_heap_size(footprint);
+                tys.add_heap_size(footprint)
+            }
+            Type::Tuple(tys) => tys.add_heap_size(footprint),
+            Type::Reference(_, ty) => ty.add_heap_size(footprint),
+            Type::TypeParameter(var) => var.add_heap_size(footprint),
+        }
+    }
+}
+
+impl HeapSize for QualifiedStructIdent {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        let QualifiedStructIdent { module, name } = self;
+        module.add_heap_size(footprint);
+        name.add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for StructDefinition_ {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        let StructDefinition_ {
+            is_nominal_resource: _,
+            name,
+            type_formals,
+            fields,
+            invariants,
+            cfg,
+        } = self;
+        name.add_heap_size(footprint);
+        type_formals.add_heap_size(footprint);
+        if let StructDefinitionFields::Move { fields } = fields {
+            fields.add_heap_size(footprint)
+        }
+        invariants.add_heap_size(footprint);
+        cfg.add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for Function_ {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        let Function_ {
+            visibility: _,
+            signature,
+            header: _,
+            acquires,
+            specifications,
+            body,
+            cfg,
+            inline: _,
+            annotations,
+        } = self;
+        // The header is a span held in the node.
+        footprint.nodes -= size_of::<Loc>();
+        footprint.spans += size_of::<Loc>();
+        signature.add_heap_size(footprint);
+        acquires.add_heap_size(footprint);
+        specifications.add_heap_size(footprint);
+        cfg.add_heap_size(footprint);
+        annotations.add_heap_size(footprint);
+        if let FunctionBody::Move { locals, code } = body {
+            locals.add_heap_size(footprint);
+            code.add_heap_size(footprint)
+        }
+    }
+}
+
+impl HeapSize for FunctionSignature {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        let FunctionSignature {
+            formals,
+            return_type,
+            type_formals,
+        } = self;
+        formals.add_heap_size(footprint);
+        return_type.add_heap_size(footprint);
+        type_formals.add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for Block_ {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        let Block_ { stmts } = self;
+        stmts.add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for Statement {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        match self {
+            Statement::CommandStatement(cmd) => cmd.add_heap_size(footprint),
+            Statement::IfElseStatement(IfElse {
+                cond,
+                if_block,
+                else_block,
+            }) => {
+                cond.add_heap_size(footprint);
+                if_block.add_heap_size(footprint);
+                else_block.add_heap_size(footprint)
+            }
+            Statement::WhileStatement(While { cond, block }) => {
+                cond.add_heap_size(footprint);
+                block.add_heap_size(footprint)
+            }
+            Statement::LoopStatement(Loop { block, results }) => {
+                block.add_heap_size(footprint);
+                results.add_heap_size(footprint)
+            }
+            Statement::EmptyStatement => (),
+        }
+    }
+}
+
+impl HeapSize for Cmd_ {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        match self {
+            Cmd_::Assign(lvalues, e) => {
+                lvalues.add_heap_size(footprint);
+                e.add_heap_size(footprint)
+            }
+            Cmd_::Unpack(name, tys, bindings, e) => {
+                name.add_heap_size(footprint);
+                tys.add_heap_size(footprint);
+                bindings.add_heap_size(footprint);
+                e.add_heap_size(footprint)
+            }
+            Cmd_::Abort(e) | Cmd_::Break(e) => e.add_heap_size(footprint),
+            Cmd_::Return(e) | Cmd_::Exp(e) => e.add_heap_size(footprint),
+            Cmd_::Emit(handle, e) => {
+                handle.add_heap_size(footprint);
+                e.add_heap_size(footprint)
+            }
+            Cmd_::Continue => (),
+        }
+    }
+}
+
+impl HeapSize for LValue_ {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        match self {
+            LValue_::Var(var) => var.add_heap_size(footprint),
+            LValue_::Mutate(e) => e.add_heap_size(footprint),
+            LValue_::Pop => (),
+        }
+    }
+}
+
+impl HeapSize for Exp_ {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        match self {
+            Exp_::Dereference(e) | Exp_::UnaryExp(_, e) => e.add_heap_size(footprint),
+            Exp_::BinopExp(lhs, _, rhs) => {
+                lhs.add_heap_size(footprint);
+                rhs.add_heap_size(footprint)
+            }
+            Exp_::Value(value) => value.add_heap_size(footprint),
+            Exp_::Pack(name, tys, fields) => {
+                name.add_heap_size(footprint);
+                tys.add_heap_size(footprint);
+                fields.add_heap_size(footprint)
+            }
+            Exp_::Borrow {
+                is_mutable: _,
+                exp,
+                field,
+            } => {
+                exp.add_heap_size(footprint);
+                field.add_heap_size(footprint)
+            }
+            Exp_::Move(var) | Exp_::Copy(var) | Exp_::Var(var) | Exp_::BorrowLocal(_, var) => {
+                var.add_heap_size(footprint)
+            }
+            Exp_::FunctionCall(call, e) => {
+                call.add_heap_size(footprint);
+                e.add_heap_size(footprint)
+            }
+            Exp_::ExprList(exps) => exps.add_heap_size(footprint),
+            Exp_::Placeholder(name) => name.add_heap_size(footprint),
+        }
+    }
+}
+
+impl HeapSize for CopyableVal_ {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        match self {
+            CopyableVal_::ByteArray(bytes) => {
+                // Counted for every node sharing it, with the counts of its `Arc`.
+                footprint.nodes += 2 * size_of::<usize>() + size_of::<ByteArray>();
+                footprint.strings += bytes.len()
+            }
+            CopyableVal_::Address(_)
+            | CopyableVal_::U8(_)
+            | CopyableVal_::U64(_)
+            | CopyableVal_::U128(_)
+            | CopyableVal_::Bool(_) => (),
+        }
+    }
+}
+
+impl HeapSize for FunctionCall_ {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        match self {
+            FunctionCall_::Builtin(builtin) => match builtin {
+                Builtin::Exists(name, tys)
+                | Builtin::BorrowGlobal(_, name, tys)
+                | Builtin::MoveFrom(name, tys)
+                | Builtin::MoveToSender(name, tys) => {
+                    name.add_heap_size(footprint);
+                    tys.add_heap_size(footprint)
+                }
+                Builtin::ToBytes(ty) => ty.add_heap_size(footprint),
+                Builtin::GetTxnSender
+                | Builtin::Freeze
+                | Builtin::ToU8
+                | Builtin::ToU64
+                | Builtin::ToU128
+                | Builtin::Rotl
+                | Builtin::Rotr
+                | Builtin::CountOnes
+                | Builtin::CheckedAdd
+                | Builtin::CheckedSub
+                | Builtin::SaturatingAdd
+                | Builtin::SaturatingSub
+                | Builtin::Sha2_256
+                | Builtin::Sha3_256 => (),
+            },
+            FunctionCall_::ModuleFunctionCall {
+                module,
+                name,
+                type_actuals,
+            } => {
+                module.add_heap_size(footprint);
+                name.add_heap_size(footprint);
+                type_actuals.add_heap_size(footprint)
+            }
+        }
+    }
+}
+
+impl HeapSize for Condition_ {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        match self {
+            Condition_::Ensures(e)
+            | Condition_::Requires(e)
+            | Condition_::AbortsIf(e)
+            | Condition_::SucceedsIf(e) => e.add_heap_size(footprint),
+        }
+    }
+}
+
+impl HeapSize for Invariant_ {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        let Invariant_ {
+            modifier,
+            condition,
+        } = self;
+        modifier.add_heap_size(footprint);
+        condition.add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for SyntheticDefinition_ {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        let SyntheticDefinition_ { name, type_ } = self;
+        name.add_heap_size(footprint);
+        type_.add_heap_size(footprint)
+    }
+}
+
+impl HeapSize for SpecExp {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        match self {
+            SpecExp::Constant(value) => value.add_heap_size(footprint),
+            SpecExp::StorageLocation(location)
+            | SpecExp::Dereference(location)
+            | SpecExp::Reference(location) => location.add_heap_size(footprint),
+            SpecExp::GlobalExists {
+                type_,
+                type_actuals,
+                address,
+            } => {
+                type_.add_heap_size(footprint);
+                type_actuals.add_heap_size(footprint);
+                address.add_heap_size(footprint)
+            }
+            SpecExp::Not(e) | SpecExp::Old(e) => e.add_heap_size(footprint),
+            SpecExp::Binop(lhs, _, rhs) => {
+                lhs.add_heap_size(footprint);
+                rhs.add_heap_size(footprint)
+            }
+            SpecExp::Call(name, args) => {
+                name.add_heap_size(footprint);
+                args.add_heap_size(footprint)
+            }
+            SpecExp::IfElse(cond, if_exp, else_exp) => {
+                cond.add_heap_size(footprint);
+                if_exp.add_heap_size(footprint);
+                else_exp.add_heap_size(footprint)
+            }
+        }
+    }
+}
+
+impl HeapSize for StorageLocation {
+    fn add_heap_size(&self, footprint: &mut MemoryFootprint) {
+        match self {
+            StorageLocation::Formal(name) => name.add_heap_size(footprint),
+            StorageLocation::GlobalResource {
+                type_,
+                type_actuals,
+                address,
+            } => {
+                type_.add_heap_size(footprint);
+                type_actuals.add_heap_size(footprint);
+                address.add_heap_size(footprint)
+            }
+            StorageLocation::AccessPath { base, fields } => {
+                base.add_heap_size(footprint);
+                fields.add_heap_size(footprint)
+            }
+            StorageLocation::TxnSenderAddress
+            | StorageLocation::Address(_)
+            | StorageLocation::Ret(_) => (),
+        }
+    }
+}
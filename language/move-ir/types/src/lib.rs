@@ -4,4 +4,8 @@
 //! Base types for the Move IR.
 
 pub mod ast;
+pub mod footprint;
+pub mod location;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod proptest_types;
 pub mod spec_language_ast;
@@ -0,0 +1,236 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Erasure of source locations.
+//!
+//! The nodes the parser produces carry their location in the source, and comparing two nodes
+//! compares their locations as well. Erasing the locations of two ASTs makes them equal exactly
+//! when they have the same structure, e.g. when checking that printing an AST and parsing it again
+//! gives back the same AST.
+
+use crate::{
+    ast::{
+        Block_, Cmd_, CopyableVal_, Exp_, Field_, FunctionBody, FunctionCall_, FunctionSignature,
+        Function_, IfElse, Kind, LValue_, Loc, Loop, ModuleDefinition, Script, ScriptOrModule,
+        Spanned, Statement, StructDefinitionFields, StructDefinition_, Type, TypeVar_, Var_, While,
+    },
+    spec_language_ast::{Condition_, Invariant_, SyntheticDefinition_},
+};
+use std::collections::VecDeque;
+
+/// A node whose locations, including those of the nodes it contains, can be erased.
+pub trait EraseLocations {
+    /// Replaces every location in the node with the empty location of `Spanned::no_loc`.
+    fn erase_locations(&mut self);
+}
+
+impl<T: EraseLocations> EraseLocations for Spanned<T> {
+    fn erase_locations(&mut self) {
+        self.span = Loc::default();
+        self.value.erase_locations();
+    }
+}
+
+impl<T: EraseLocations> EraseLocations for Box<T> {
+    fn erase_locations(&mut self) {
+        (**self).erase_locations();
+    }
+}
+
+impl<T: EraseLocations> EraseLocations for Option<T> {
+    fn erase_locations(&mut self) {
+        if let Some(node) = self {
+            node.erase_locations();
+        }
+    }
+}
+
+impl<T: EraseLocations> EraseLocations for Vec<T> {
+    fn erase_locations(&mut self) {
+        for node in self {
+            node.erase_locations();
+        }
+    }
+}
+
+impl<T: EraseLocations> EraseLocations for VecDeque<T> {
+    fn erase_locations(&mut self) {
+        for node in self {
+            node.erase_locations();
+        }
+    }
+}
+
+impl<S: EraseLocations, T: EraseLocations> EraseLocations for (S, T) {
+    fn erase_locations(&mut self) {
+        self.0.erase_locations();
+        self.1.erase_locations();
+    }
+}
+
+/// Nodes that do not contain any location.
+macro_rules! no_locations {
+    ($($ty:ty),*) => {
+        $(
+            impl EraseLocations for $ty {
+                fn erase_locations(&mut self) {}
+            }
+        )*
+    };
+}
+
+no_locations!(
+    Condition_,
+    CopyableVal_,
+    Field_,
+    FunctionCall_,
+    Invariant_,
+    Kind,
+    SyntheticDefinition_,
+    Type,
+    TypeVar_,
+    Var_
+);
+
+impl EraseLocations for ScriptOrModule {
+    fn erase_locations(&mut self) {
+        match self {
+            ScriptOrModule::Script(script) => script.erase_locations(),
+            ScriptOrModule::Module(module) => module.erase_locations(),
+        }
+    }
+}
+
+impl EraseLocations for Script {
+    fn erase_locations(&mut self) {
+        self.main.erase_locations();
+    }
+}
+
+impl EraseLocations for ModuleDefinition {
+    fn erase_locations(&mut self) {
+        self.structs.erase_locations();
+        self.functions
+            .iter_mut()
+            .for_each(|(_, f)| f.erase_locations());
+        self.synthetics.erase_locations();
+    }
+}
+
+impl EraseLocations for StructDefinition_ {
+    fn erase_locations(&mut self) {
+        self.type_formals.erase_locations();
+        if let StructDefinitionFields::Move { fields } = &mut self.fields {
+            fields.erase_locations();
+        }
+        self.invariants.erase_locations();
+    }
+}
+
+impl EraseLocations for Function_ {
+    fn erase_locations(&mut self) {
+        self.signature.erase_locations();
+        self.header = Loc::default();
+        self.specifications.erase_locations();
+        if let FunctionBody::Move { locals, code } = &mut self.body {
+            locals.erase_locations();
+            code.erase_locations();
+        }
+    }
+}
+
+impl EraseLocations for FunctionSignature {
+    fn erase_locations(&mut self) {
+        self.formals.erase_locations();
+        self.type_formals.erase_locations();
+    }
+}
+
+impl EraseLocations for Block_ {
+    fn erase_locations(&mut self) {
+        self.stmts.erase_locations();
+    }
+}
+
+impl EraseLocations for Statement {
+    fn erase_locations(&mut self) {
+        match self {
+            Statement::CommandStatement(cmd) => cmd.erase_locations(),
+            Statement::IfElseStatement(IfElse {
+                cond,
+                if_block,
+                else_block,
+            }) => {
+                cond.erase_locations();
+                if_block.erase_locations();
+                else_block.erase_locations();
+            }
+            Statement::WhileStatement(While { cond, block }) => {
+                cond.erase_locations();
+                block.erase_locations();
+            }
+            Statement::LoopStatement(Loop { block, results }) => {
+                block.erase_locations();
+                results.erase_locations();
+            }
+            Statement::EmptyStatement => (),
+        }
+    }
+}
+
+impl EraseLocations for Cmd_ {
+    fn erase_locations(&mut self) {
+        match self {
+            Cmd_::Assign(lvalues, e) => {
+                lvalues.erase_locations();
+                e.erase_locations();
+            }
+            Cmd_::Unpack(_, _, bindings, e) => {
+                bindings.erase_locations();
+                e.erase_locations();
+            }
+            Cmd_::Abort(e) | Cmd_::Break(e) => e.erase_locations(),
+            Cmd_::Return(e) | Cmd_::Exp(e) => e.erase_locations(),
+            Cmd_::Emit(handle, e) => {
+                handle.erase_locations();
+                e.erase_locations();
+            }
+            Cmd_::Continue => (),
+        }
+    }
+}
+
+impl EraseLocations for LValue_ {
+    fn erase_locations(&mut self) {
+        match self {
+            LValue_::Var(var) => var.erase_locations(),
+            LValue_::Mutate(e) => e.erase_locations(),
+            LValue_::Pop => (),
+        }
+    }
+}
+
+impl EraseLocations for Exp_ {
+    fn erase_locations(&mut self) {
+        match self {
+            Exp_::Dereference(e) | Exp_::UnaryExp(_, e) | Exp_::Borrow { exp: e, .. } => {
+                e.erase_locations()
+            }
+            Exp_::BinopExp(lhs, _, rhs) => {
+                lhs.erase_locations();
+                rhs.erase_locations();
+            }
+            Exp_::Value(v) => v.erase_locations(),
+            Exp_::Pack(_, _, fields) => fields.erase_locations(),
+            Exp_::Move(var) | Exp_::Copy(var) | Exp_::Var(var) | Exp_::BorrowLocal(_, var) => {
+                var.erase_locations()
+            }
+            Exp_::FunctionCall(f, args) => {
+                f.erase_locations();
+                args.erase_locations();
+            }
+            Exp_::ExprList(exps) => exps.erase_locations(),
+            Exp_::Placeholder(_) => (),
+        }
+    }
+}
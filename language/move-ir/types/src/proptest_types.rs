@@ -0,0 +1,576 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Strategies for generating ASTs, for property-based testing and fuzzing.
+//!
+//! The generated ASTs are syntactically well formed: every name is a valid identifier that is not a
+//! keyword, the names declared together, such as the fields of a struct or the functions of a
+//! module, are distinct, and every node has a shape the parser can produce, so that printing an AST
+//! gives source that parses. It does not always parse back to the same AST, since the parser keeps
+//! the parentheses that operator precedence requires as expression lists. The ASTs are not meant to
+//! type check. Locations are all empty, and no specifications are generated since those are only
+//! ever printed from their source.
+
+use crate::ast::{
+    BinOp, Block_, Builtin, CfgPredicate, Cmd_, CopyableVal_, Exp, Exp_, Field_, Fields, Function,
+    FunctionBody, FunctionCall_, FunctionName, FunctionVisibility, Function_, IfElse,
+    ImportDefinition, InlineHint, Kind, LValue, LValue_, Loop, ModuleDefinition, ModuleIdent,
+    ModuleName, QualifiedModuleIdent, QualifiedStructIdent, Script, Spanned, Statement,
+    StructDefinition, StructDefinitionFields, StructDefinition_, StructName, Type, TypeVar,
+    TypeVar_, UnaryOp, Var, Var_, While,
+};
+use libra_types::{
+    account_address::{AccountAddress, ADDRESS_LENGTH},
+    byte_array::ByteArray,
+    identifier::Identifier,
+};
+use proptest::{
+    collection::{btree_map, vec},
+    option,
+    prelude::*,
+};
+use std::{collections::HashSet, fmt, hash::Hash, ops::Range, sync::Arc};
+
+/// Names the lexer turns into keywords, or into other tokens when followed by `(` or `<`.
+const RESERVED_NAMES: &[&str] = &[
+    "abort",
+    "aborts_if",
+    "acquires",
+    "address",
+    "as",
+    "assert",
+    "bool",
+    "borrow_global",
+    "borrow_global_mut",
+    "break",
+    "bytearray",
+    "continue",
+    "copy",
+    "else",
+    "ensures",
+    "exists",
+    "false",
+    "freeze",
+    "get_txn_sender",
+    "global",
+    "global_exists",
+    "if",
+    "import",
+    "invariant",
+    "let",
+    "loop",
+    "main",
+    "module",
+    "modules",
+    "move",
+    "move_from",
+    "move_to_sender",
+    "native",
+    "old",
+    "public",
+    "requires",
+    "resource",
+    "return",
+    "script",
+    "struct",
+    "succeeds_if",
+    "synthetic",
+    "to_u128",
+    "to_u64",
+    "to_u8",
+    "true",
+    "txn_sender",
+    "u128",
+    "u64",
+    "u8",
+    "unrestricted",
+    "while",
+    "RET",
+    "Self",
+];
+
+fn name(regex: &'static str) -> impl Strategy<Value = Identifier> {
+    regex
+        .prop_filter("reserved name", |s| !RESERVED_NAMES.contains(&s.as_str()))
+        .prop_map(|s| Identifier::new(s).expect("generated names are valid identifiers"))
+}
+
+/// Names of locals, fields and functions.
+fn lower_name() -> impl Strategy<Value = Identifier> {
+    name("[a-z][a-z0-9_]{0,5}")
+}
+
+/// Names of modules, structs and type parameters.
+fn upper_name() -> impl Strategy<Value = Identifier> {
+    name("[A-Z][A-Za-z0-9]{0,5}")
+}
+
+fn var() -> impl Strategy<Value = Var> {
+    lower_name().prop_map(|n| Spanned::no_loc(Var_::new(n)))
+}
+
+fn field() -> impl Strategy<Value = Field_> {
+    lower_name().prop_map(Field_::new)
+}
+
+/// Keeps the first of the items with the same key, for the names the parser requires to be
+/// distinct.
+fn dedup_by_key<T, K: Eq + Hash>(items: &mut Vec<T>, key: impl Fn(&T) -> K) {
+    let mut keys = HashSet::new();
+    items.retain(|item| keys.insert(key(item)));
+}
+
+fn fields<T: fmt::Debug>(
+    value: impl Strategy<Value = T>,
+    size: Range<usize>,
+) -> impl Strategy<Value = Fields<T>> {
+    vec((field().prop_map(Spanned::no_loc), value), size).prop_map(|mut fields| {
+        dedup_by_key(&mut fields, |(f, _)| f.value.clone());
+        fields
+    })
+}
+
+fn struct_name() -> impl Strategy<Value = StructName> {
+    upper_name().prop_map(StructName::new)
+}
+
+fn module_name() -> impl Strategy<Value = ModuleName> {
+    prop_oneof![
+        1 => Just(ModuleName::module_self()),
+        3 => upper_name().prop_map(ModuleName::new),
+    ]
+}
+
+fn address() -> impl Strategy<Value = AccountAddress> {
+    prop_oneof![
+        Just(AccountAddress::default()),
+        any::<u8>().prop_map(|b| {
+            let mut bytes = [0; ADDRESS_LENGTH];
+            bytes[ADDRESS_LENGTH - 1] = b;
+            AccountAddress::new(bytes)
+        }),
+        vec(any::<u8>(), ADDRESS_LENGTH).prop_map(|bytes| {
+            let mut address = [0; ADDRESS_LENGTH];
+            address.copy_from_slice(&bytes);
+            AccountAddress::new(address)
+        }),
+    ]
+}
+
+fn kind() -> impl Strategy<Value = Kind> {
+    prop_oneof![
+        Just(Kind::All),
+        Just(Kind::Resource),
+        Just(Kind::Unrestricted)
+    ]
+}
+
+fn type_formals() -> impl Strategy<Value = Vec<(TypeVar, Kind)>> {
+    vec(
+        (
+            upper_name().prop_map(|n| Spanned::no_loc(TypeVar_::new(n))),
+            kind(),
+        ),
+        0..3,
+    )
+}
+
+/// A type that is not a reference.
+fn value_type() -> impl Strategy<Value = Type> {
+    let leaf = prop_oneof![
+        Just(Type::Address),
+        Just(Type::U8),
+        Just(Type::U64),
+        Just(Type::U128),
+        Just(Type::Bool),
+        Just(Type::ByteArray),
+        upper_name().prop_map(|n| Type::TypeParameter(TypeVar_::new(n))),
+    ];
+    leaf.prop_recursive(3, 8, 2, |inner| {
+        (module_name(), struct_name(), vec(inner, 0..3))
+            .prop_map(|(m, n, tys)| Type::Struct(QualifiedStructIdent::new(m, n), tys))
+    })
+}
+
+fn type_actuals() -> impl Strategy<Value = Vec<Type>> {
+    vec(value_type(), 0..2)
+}
+
+impl Arbitrary for Type {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// Generates a type, which is only a reference or a tuple at the top level.
+    fn arbitrary_with(_params: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            6 => value_type(),
+            2 => (any::<bool>(), value_type()).prop_map(|(m, ty)| Type::reference(m, ty)),
+            1 => vec(value_type(), 2..4).prop_map(Type::Tuple),
+        ]
+        .boxed()
+    }
+}
+
+fn value() -> impl Strategy<Value = CopyableVal_> {
+    prop_oneof![
+        address().prop_map(CopyableVal_::Address),
+        any::<u8>().prop_map(CopyableVal_::U8),
+        any::<u64>().prop_map(CopyableVal_::U64),
+        any::<u128>().prop_map(CopyableVal_::U128),
+        any::<bool>().prop_map(CopyableVal_::Bool),
+        vec(any::<u8>(), 0..8).prop_map(|b| CopyableVal_::ByteArray(Arc::new(ByteArray::new(b)))),
+    ]
+}
+
+fn bin_op() -> impl Strategy<Value = BinOp> {
+    prop_oneof![
+        Just(BinOp::Add),
+        Just(BinOp::Sub),
+        Just(BinOp::Mul),
+        Just(BinOp::Mod),
+        Just(BinOp::Div),
+        Just(BinOp::BitOr),
+        Just(BinOp::BitAnd),
+        Just(BinOp::Xor),
+        Just(BinOp::Shl),
+        Just(BinOp::Shr),
+        Just(BinOp::And),
+        Just(BinOp::Or),
+        Just(BinOp::Eq),
+        Just(BinOp::Neq),
+        Just(BinOp::Lt),
+        Just(BinOp::Gt),
+        Just(BinOp::Le),
+        Just(BinOp::Ge),
+    ]
+}
+
+fn function_call() -> impl Strategy<Value = FunctionCall_> {
+    let builtin = prop_oneof![
+        (struct_name(), type_actuals()).prop_map(|(n, tys)| Builtin::Exists(n, tys)),
+        (any::<bool>(), struct_name(), type_actuals())
+            .prop_map(|(m, n, tys)| Builtin::BorrowGlobal(m, n, tys)),
+        Just(Builtin::GetTxnSender),
+        (struct_name(), type_actuals()).prop_map(|(n, tys)| Builtin::MoveFrom(n, tys)),
+        (struct_name(), type_actuals()).prop_map(|(n, tys)| Builtin::MoveToSender(n, tys)),
+        Just(Builtin::Freeze),
+        Just(Builtin::ToU8),
+        Just(Builtin::ToU64),
+        Just(Builtin::ToU128),
+        Just(Builtin::Rotl),
+        Just(Builtin::Rotr),
+        Just(Builtin::CountOnes),
+        Just(Builtin::CheckedAdd),
+        Just(Builtin::CheckedSub),
+        Just(Builtin::SaturatingAdd),
+        Just(Builtin::SaturatingSub),
+        Just(Builtin::Sha2_256),
+        Just(Builtin::Sha3_256),
+        value_type().prop_map(Builtin::ToBytes),
+    ];
+    prop_oneof![
+        builtin.prop_map(FunctionCall_::Builtin),
+        (module_name(), lower_name(), type_actuals())
+            .prop_map(|(m, n, tys)| { FunctionCall_::module_call(m, FunctionName::new(n), tys) }),
+    ]
+}
+
+/// A call, with its arguments in parentheses as the parser gives them.
+fn call(call: FunctionCall_, args: Vec<Exp>) -> Exp_ {
+    Exp_::FunctionCall(Spanned::no_loc(call), Box::new(Exp_::expr_list(args)))
+}
+
+impl Arbitrary for Exp_ {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_params: Self::Parameters) -> Self::Strategy {
+        let leaf = prop_oneof![
+            value().prop_map(|v| Exp_::Value(Spanned::no_loc(v))),
+            var().prop_map(Exp_::Move),
+            var().prop_map(Exp_::Copy),
+            var().prop_map(Exp_::Var),
+            (any::<bool>(), var()).prop_map(|(m, v)| Exp_::BorrowLocal(m, v)),
+            lower_name().prop_map(Exp_::Placeholder),
+        ];
+        leaf.prop_recursive(4, 32, 3, |inner| {
+            let inner = inner.prop_map(Spanned::no_loc);
+            prop_oneof![
+                inner
+                    .clone()
+                    .prop_map(|e| Exp_::UnaryExp(UnaryOp::Not, Box::new(e))),
+                inner.clone().prop_map(|e| Exp_::Dereference(Box::new(e))),
+                (inner.clone(), bin_op(), inner.clone()).prop_map(|(lhs, op, rhs)| Exp_::BinopExp(
+                    Box::new(lhs),
+                    op,
+                    Box::new(rhs)
+                )),
+                (
+                    any::<bool>(),
+                    inner
+                        .clone()
+                        .prop_filter("operand ends with a name", |e| !ends_with_name(e)),
+                    field()
+                )
+                    .prop_map(|(m, e, f)| Exp_::Borrow {
+                        is_mutable: m,
+                        exp: Box::new(e),
+                        field: f,
+                    }),
+                (function_call(), vec(inner.clone(), 0..3)).prop_map(|(f, args)| call(f, args)),
+                (struct_name(), type_actuals(), fields(inner.clone(), 0..3))
+                    .prop_map(|(n, tys, fields)| Exp_::Pack(n, tys, fields)),
+                vec(inner, 0..3).prop_map(Exp_::ExprList),
+            ]
+        })
+        .boxed()
+    }
+}
+
+/// Whether the expression ends with a name, such as a borrowed local, a borrowed field or a
+/// boolean, which the lexer would join with the `.f` of a field borrow into a single `x.f` token.
+fn ends_with_name(e: &Exp) -> bool {
+    match &e.value {
+        Exp_::BorrowLocal(..) | Exp_::Borrow { .. } => true,
+        Exp_::Value(v) => match v.value {
+            CopyableVal_::Bool(_) => true,
+            _ => false,
+        },
+        Exp_::Dereference(e) | Exp_::UnaryExp(_, e) => ends_with_name(e),
+        _ => false,
+    }
+}
+
+fn exp() -> impl Strategy<Value = Exp> {
+    any::<Exp_>().prop_map(Spanned::no_loc)
+}
+
+fn lvalue() -> impl Strategy<Value = LValue> {
+    prop_oneof![
+        var().prop_map(LValue_::Var),
+        exp().prop_map(LValue_::Mutate),
+        Just(LValue_::Pop),
+    ]
+    .prop_map(Spanned::no_loc)
+}
+
+fn cmd() -> impl Strategy<Value = Cmd_> {
+    prop_oneof![
+        (vec(lvalue(), 1..3), exp()).prop_map(|(lvalues, e)| Cmd_::Assign(lvalues, e)),
+        (struct_name(), type_actuals(), fields(var(), 0..3), exp())
+            .prop_map(|(n, tys, bindings, e)| Cmd_::Unpack(n, tys, bindings, Box::new(e))),
+        option::of(exp()).prop_map(|e| Cmd_::Abort(e.map(Box::new))),
+        vec(exp(), 0..3).prop_map(|exps| Cmd_::return_(Exp_::expr_list(exps))),
+        option::of(exp()).prop_map(|e| Cmd_::Break(e.map(Box::new))),
+        Just(Cmd_::Continue),
+        (exp(), exp()).prop_map(|(handle, e)| Cmd_::Emit(Box::new(handle), Box::new(e))),
+        (function_call(), vec(exp(), 0..3))
+            .prop_map(|(f, args)| Cmd_::Exp(Box::new(Spanned::no_loc(call(f, args))))),
+        vec(exp(), 0..3).prop_map(|exps| Cmd_::Exp(Box::new(Exp_::expr_list(exps)))),
+    ]
+}
+
+fn block(statements: impl Strategy<Value = Statement>) -> impl Strategy<Value = Block_> {
+    vec(statements, 0..4).prop_map(Block_::new)
+}
+
+impl Arbitrary for Statement {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_params: Self::Parameters) -> Self::Strategy {
+        let leaf = prop_oneof![
+            8 => cmd().prop_map(|c| Statement::CommandStatement(Spanned::no_loc(c))),
+            1 => Just(Statement::EmptyStatement),
+        ];
+        leaf.prop_recursive(3, 16, 4, |inner| {
+            let block = block(inner).prop_map(Spanned::no_loc).boxed();
+            prop_oneof![
+                (exp(), block.clone(), option::of(block.clone())).prop_map(
+                    |(cond, if_block, else_block)| Statement::IfElseStatement(IfElse {
+                        cond,
+                        if_block,
+                        else_block,
+                    })
+                ),
+                (exp(), block.clone())
+                    .prop_map(|(cond, block)| Statement::WhileStatement(While { cond, block })),
+                (block, vec(lvalue(), 0..3))
+                    .prop_map(|(block, results)| Statement::LoopStatement(Loop { block, results })),
+            ]
+        })
+        .boxed()
+    }
+}
+
+fn function_body() -> impl Strategy<Value = FunctionBody> {
+    prop_oneof![
+        1 => Just(FunctionBody::Native),
+        4 => (vec((var(), any::<Type>()), 0..3), block(any::<Statement>()))
+            .prop_map(|(locals, code)| FunctionBody::Move { locals, code }),
+    ]
+}
+
+fn function(body: impl Strategy<Value = FunctionBody>) -> impl Strategy<Value = Function> {
+    (
+        prop_oneof![
+            Just(FunctionVisibility::Public),
+            Just(FunctionVisibility::Internal)
+        ],
+        vec((var(), any::<Type>()), 0..3),
+        vec(any::<Type>(), 0..3),
+        type_formals(),
+        vec(struct_name(), 0..2),
+        body,
+        cfg(),
+        option::weighted(
+            0.2,
+            prop_oneof![Just(InlineHint::Inline), Just(InlineHint::NeverInline)],
+        ),
+        btree_map(lower_name(), "[a-z0-9 .-]{0,8}", 0..2),
+    )
+        .prop_map(
+            |(
+                visibility,
+                formals,
+                return_type,
+                type_formals,
+                acquires,
+                body,
+                cfg,
+                inline,
+                annotations,
+            )| {
+                Spanned::no_loc(Function_ {
+                    cfg,
+                    inline,
+                    annotations: annotations
+                        .into_iter()
+                        .map(|(key, value)| (key.into_string(), value))
+                        .collect(),
+                    ..Function_::new(
+                        visibility,
+                        formals,
+                        return_type,
+                        type_formals,
+                        acquires,
+                        vec![],
+                        body,
+                    )
+                })
+            },
+        )
+}
+
+fn cfg_predicate() -> impl Strategy<Value = CfgPredicate> {
+    let leaf = prop_oneof![
+        lower_name().prop_map(|n| CfgPredicate::Flag(n.into_string())),
+        "[a-z][a-z0-9_-]{0,5}".prop_map(CfgPredicate::Feature),
+    ];
+    leaf.prop_recursive(2, 6, 3, |inner| {
+        prop_oneof![
+            inner.clone().prop_map(|p| CfgPredicate::Not(Box::new(p))),
+            vec(inner.clone(), 0..3).prop_map(CfgPredicate::All),
+            vec(inner, 0..3).prop_map(CfgPredicate::Any),
+        ]
+    })
+}
+
+/// The `cfg` attribute of a declaration, which most do not have.
+fn cfg() -> impl Strategy<Value = Option<CfgPredicate>> {
+    option::weighted(0.2, cfg_predicate())
+}
+
+fn import() -> impl Strategy<Value = ImportDefinition> {
+    let ident = prop_oneof![
+        upper_name().prop_map(|n| ModuleIdent::Transaction(ModuleName::new(n))),
+        (upper_name(), address()).prop_map(|(n, a)| {
+            ModuleIdent::Qualified(QualifiedModuleIdent::new(ModuleName::new(n), a))
+        }),
+    ];
+    (ident, option::of(upper_name()), cfg()).prop_map(|(ident, alias, cfg)| ImportDefinition {
+        cfg,
+        ..ImportDefinition::new(ident, alias.map(ModuleName::new))
+    })
+}
+
+fn struct_definition() -> impl Strategy<Value = StructDefinition> {
+    let fields = prop_oneof![
+        1 => Just(StructDefinitionFields::Native),
+        4 => fields(value_type(), 0..4)
+            .prop_map(|fields| StructDefinitionFields::Move { fields }),
+    ];
+    (any::<bool>(), struct_name(), type_formals(), fields, cfg()).prop_map(
+        |(is_nominal_resource, name, type_formals, fields, cfg)| {
+            Spanned::no_loc(StructDefinition_ {
+                is_nominal_resource,
+                name,
+                type_formals,
+                fields,
+                invariants: vec![],
+                cfg,
+            })
+        },
+    )
+}
+
+impl Arbitrary for ModuleDefinition {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_params: Self::Parameters) -> Self::Strategy {
+        (
+            upper_name(),
+            vec(import(), 0..3),
+            vec(struct_definition(), 0..3),
+            vec(
+                (
+                    lower_name().prop_map(FunctionName::new),
+                    function(function_body()),
+                ),
+                0..3,
+            ),
+        )
+            .prop_map(|(name, mut imports, mut structs, mut functions)| {
+                dedup_by_key(&mut imports, |import| import.alias.clone());
+                dedup_by_key(&mut structs, |s| s.value.name.clone());
+                dedup_by_key(&mut functions, |(name, _)| name.clone());
+                ModuleDefinition {
+                    name: ModuleName::new(name),
+                    imports,
+                    structs,
+                    functions,
+                    synthetics: vec![],
+                    language_version: None,
+                }
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for Script {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_params: Self::Parameters) -> Self::Strategy {
+        let main = (
+            vec((var(), any::<Type>()), 0..3),
+            vec((var(), any::<Type>()), 0..3),
+            block(any::<Statement>()),
+        )
+            .prop_map(|(formals, locals, code)| {
+                Spanned::no_loc(Function_::new(
+                    FunctionVisibility::Public,
+                    formals,
+                    vec![],
+                    vec![],
+                    vec![],
+                    vec![],
+                    FunctionBody::Move { locals, code },
+                ))
+            });
+        (vec(import(), 0..3), main)
+            .prop_map(|(imports, main)| Script::new(imports, main))
+            .boxed()
+    }
+}
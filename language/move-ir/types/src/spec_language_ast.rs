@@ -58,6 +58,9 @@ pub enum SpecExp {
     Old(Box<SpecExp>),
     /// Call to a helper function.
     Call(String, Vec<SpecExp>),
+    /// The value of the second expression if the first one is true, and of the third one
+    /// otherwise (written if (c) e1 else e2)
+    IfElse(Box<SpecExp>, Box<SpecExp>, Box<SpecExp>),
 }
 
 /// A specification directive to be verified
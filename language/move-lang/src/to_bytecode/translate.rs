@@ -279,6 +279,7 @@ fn module(
         struct_defs,
         field_defs,
         function_defs,
+        metadata: vec![],
     };
     let compiled_module = compiled_module_mut
         .freeze()
@@ -215,6 +215,18 @@ impl<'env> SpecTranslator<'env> {
                 let right = self.translate_expr(right);
                 self.translate_binop(op, left, right)
             }
+            SpecExp::IfElse(cond, if_expr, else_expr) => {
+                let BoogieExpr(c, ct) = self.translate_expr(cond);
+                let _ = self.require_type(ct, &GlobalType::Bool);
+                let BoogieExpr(s1, t1) = self.translate_expr(if_expr);
+                let BoogieExpr(s2, t2) = self.translate_expr(else_expr);
+                let t = if t1 == UNKNOWN_TYPE {
+                    t2
+                } else {
+                    self.require_type(t2, &t1)
+                };
+                BoogieExpr(format!("(if b#Boolean({}) then {} else {})", c, s1, s2), t)
+            }
             SpecExp::Old(expr) => {
                 let BoogieExpr(s, t) = self.translate_expr(expr);
                 BoogieExpr(format!("old({})", s), t)
@@ -7,8 +7,8 @@ pub mod stdlib;
 pub mod transaction_scripts;
 
 use bytecode_source_map::source_map::{ModuleSourceMap, SourceMap};
-use bytecode_verifier::{verify_module_dependencies, VerifiedModule};
-use ir_to_bytecode::compiler::compile_module;
+use bytecode_verifier::VerifiedModule;
+use ir_to_bytecode::build::{compile_modules, BuildOptions};
 use libra_types::{account_address::AccountAddress, account_config};
 use move_ir_types::ast::Loc;
 use once_cell::sync::Lazy;
@@ -37,26 +37,19 @@ pub fn stdlib_source_map() -> &'static [ModuleSourceMap<Loc>] {
 /// A copy of the stdlib built with the [default address](account_config::core_code_address) is
 /// available through [`stdlib_modules`].
 pub fn build_stdlib(address: AccountAddress) -> (Vec<VerifiedModule>, SourceMap<Loc>) {
-    let mut stdlib_modules = vec![];
-    let mut stdlib_source_maps = vec![];
-
-    for module_def in stdlib::module_defs() {
-        let (compiled_module, source_map) =
-            compile_module(address, (*module_def).clone(), &stdlib_modules)
-                .expect("stdlib module failed to compile");
-        let verified_module =
-            VerifiedModule::new(compiled_module).expect("stdlib module failed to verify");
-
-        let verification_errors = verify_module_dependencies(&verified_module, &stdlib_modules);
-        // Fail if the module doesn't verify
-        for e in &verification_errors {
-            println!("{:?}", e);
-        }
-        assert!(verification_errors.is_empty());
-
-        stdlib_modules.push(verified_module);
-        stdlib_source_maps.push(source_map)
+    let module_defs = stdlib::module_defs()
+        .iter()
+        .map(|module_def| (*module_def).clone())
+        .collect();
+    let options = BuildOptions {
+        address,
+        ..BuildOptions::default()
+    };
+    let build = compile_modules(module_defs, &options);
+    // Fail if a module doesn't compile or verify
+    for error in &build.errors {
+        println!("{:?}", error);
     }
-
-    (stdlib_modules, stdlib_source_maps)
+    assert!(build.is_success(), "stdlib failed to build");
+    build.into_modules()
 }
@@ -1,14 +1,19 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use ir_to_bytecode::parser::parse_module;
+use ir_to_bytecode::parser::{parse_module_with_options, ParserOptions};
 use move_ir_types::ast::ModuleDefinition;
 use once_cell::sync::Lazy;
 
 macro_rules! make_module_definition {
     ($source_path: literal) => {{
         let struct_body = include_str!($source_path);
-        parse_module(struct_body).unwrap()
+        // Specifications are not compiled, so they are not parsed either.
+        let options = ParserOptions {
+            skip_specs: true,
+            ..ParserOptions::default()
+        };
+        parse_module_with_options(struct_body, options).unwrap()
     }};
 }
 
@@ -34,6 +39,8 @@ static ADDRESS_UTIL_MODULE: Lazy<ModuleDefinition> =
     Lazy::new(|| make_module_definition!("../modules/address_util.mvir"));
 static U64_UTIL_MODULE: Lazy<ModuleDefinition> =
     Lazy::new(|| make_module_definition!("../modules/u64_util.mvir"));
+static LCS_MODULE: Lazy<ModuleDefinition> =
+    Lazy::new(|| make_module_definition!("../modules/lcs.mvir"));
 static VECTOR_MODULE: Lazy<ModuleDefinition> =
     Lazy::new(|| make_module_definition!("../modules/vector.mvir"));
 static BYTEARRAY_UTIL_MODULE: Lazy<ModuleDefinition> =
@@ -41,8 +48,9 @@ static BYTEARRAY_UTIL_MODULE: Lazy<ModuleDefinition> =
 static GAS_SCHEDULE: Lazy<ModuleDefinition> =
     Lazy::new(|| make_module_definition!("../modules/gas_schedule.mvir"));
 static MODULE_DEFS: Lazy<Vec<&'static ModuleDefinition>> = Lazy::new(|| {
-    // Note: a module can depend on earlier modules in the list, but not vice versa. Don't try
-    // to rearrange without considering this!
+    // Note: modules are built in the order of their imports, and otherwise in the order of this
+    // list, which is the order of `stdlib_modules`. Keeping a module after the modules it imports
+    // keeps that order stable.
     vec![
         &*OFFER_MODULE,
         &*ADDRESS_UTIL_MODULE,
@@ -51,6 +59,7 @@ static MODULE_DEFS: Lazy<Vec<&'static ModuleDefinition>> = Lazy::new(|| {
         &*NATIVE_HASH_MODULE,
         &*SIGNATURE_MODULE,
         &*U64_UTIL_MODULE,
+        &*LCS_MODULE,
         &*VECTOR_MODULE,
         &*VALIDATOR_CONFIG_MODULE,
         &*GAS_SCHEDULE, // depends on Vector
@@ -0,0 +1,344 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A canonical text dump of compiled modules and scripts, meant to be compared with `diff`.
+//!
+//! Two compilations of the same source lay out the tables of a module in whatever order the
+//! compiler met the declarations, and every instruction refers to its operands by index, so the
+//! usual disassembly of a module changes all over when a single declaration is added. The
+//! canonical dump instead:
+//!
+//! - sorts the imports by name and address, and the structs and functions by name, keeping the
+//!   fields of a struct in declaration order since it is their layout;
+//! - names every handle after what it refers to, as `LibraAccount.balance` or `R.value`, and
+//!   every type parameter and local by its position, as `T0` or `L1`, as a compiled module does
+//!   not know the names of the source;
+//! - labels the targets of branches `B0`, `B1`, ... in code order instead of printing the offset
+//!   of every instruction, so that inserting an instruction only changes the lines around it;
+//! - indents with spaces and ends every line the same way, whatever the options of the usual
+//!   disassembler.
+//!
+//! It needs no source map, so the dumps of a published module and of its new version can be
+//! compared directly during an upgrade review.
+
+use libra_types::account_address::AccountAddress;
+use std::collections::BTreeMap;
+use vm::{
+    access::ModuleAccess,
+    file_format::{
+        Bytecode, CodeOffset, CompiledModule, CompiledScript, FieldDefinitionIndex,
+        FunctionDefinition, FunctionHandleIndex, Kind, LocalsSignatureIndex, ModuleHandleIndex,
+        SignatureToken, StructDefinition, StructDefinitionIndex, StructFieldInformation,
+        StructHandleIndex,
+    },
+};
+
+/// The canonical dump of a compiled module.
+pub fn canonical_module(module: &CompiledModule) -> String {
+    let printer = Printer { module };
+    let mut lines = vec![format!("module {}.{}", module.address(), module.name())];
+    printer.imports(&mut lines);
+    let mut structs: Vec<_> = module.struct_defs().iter().collect();
+    structs
+        .sort_by_key(|def| module.identifier_at(module.struct_handle_at(def.struct_handle).name));
+    for def in structs {
+        lines.push(String::new());
+        printer.struct_def(def, &mut lines);
+    }
+    let mut functions: Vec<_> = module.function_defs().iter().collect();
+    functions.sort_by_key(|def| module.identifier_at(module.function_handle_at(def.function).name));
+    for def in functions {
+        lines.push(String::new());
+        printer.function_def(def, &mut lines);
+    }
+    finish(lines)
+}
+
+/// The canonical dump of a compiled script.
+pub fn canonical_script(script: &CompiledScript) -> String {
+    let module = script.clone().into_module();
+    let printer = Printer { module: &module };
+    let mut lines = vec!["script".to_string()];
+    printer.imports(&mut lines);
+    lines.push(String::new());
+    printer.function_def(&module.function_defs()[0], &mut lines);
+    finish(lines)
+}
+
+fn finish(lines: Vec<String>) -> String {
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+struct Printer<'a> {
+    module: &'a CompiledModule,
+}
+
+impl<'a> Printer<'a> {
+    fn imports(&self, lines: &mut Vec<String>) {
+        let mut imports: Vec<(String, &AccountAddress)> = self
+            .module
+            .module_handles()
+            .iter()
+            .skip(CompiledModule::IMPLEMENTED_MODULE_INDEX as usize + 1)
+            .map(|handle| {
+                (
+                    self.module.identifier_at(handle.name).to_string(),
+                    self.module.address_at(handle.address),
+                )
+            })
+            .collect();
+        imports.sort();
+        if !imports.is_empty() {
+            lines.push(String::new());
+        }
+        for (name, address) in imports {
+            lines.push(format!("import {}.{}", address, name));
+        }
+    }
+
+    fn struct_def(&self, def: &StructDefinition, lines: &mut Vec<String>) {
+        let handle = self.module.struct_handle_at(def.struct_handle);
+        let kind = if handle.is_nominal_resource {
+            "resource"
+        } else {
+            "struct"
+        };
+        let header = format!(
+            "{} {}{}",
+            kind,
+            self.module.identifier_at(handle.name),
+            type_formals(&handle.type_formals)
+        );
+        match &def.field_information {
+            StructFieldInformation::Native => lines.push(format!("native {}", header)),
+            StructFieldInformation::Declared {
+                field_count,
+                fields,
+            } => {
+                lines.push(format!("{} {{", header));
+                for i in fields.0..fields.0 + field_count {
+                    let field = self.module.field_def_at(FieldDefinitionIndex::new(i));
+                    lines.push(format!(
+                        "    {}: {}",
+                        self.module.identifier_at(field.name),
+                        self.type_(&self.module.type_signature_at(field.signature).0)
+                    ));
+                }
+                lines.push("}".to_string());
+            }
+        }
+    }
+
+    fn function_def(&self, def: &FunctionDefinition, lines: &mut Vec<String>) {
+        let handle = self.module.function_handle_at(def.function);
+        let signature = self.module.function_signature_at(handle.signature);
+        let args: Vec<_> = signature
+            .arg_types
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| format!("L{}: {}", i, self.type_(ty)))
+            .collect();
+        let mut header = String::new();
+        if def.is_native() {
+            header.push_str("native ");
+        }
+        if def.is_public() {
+            header.push_str("public ");
+        }
+        header.push_str(&format!(
+            "{}{}({})",
+            self.module.identifier_at(handle.name),
+            type_formals(&signature.type_formals),
+            args.join(", ")
+        ));
+        if !signature.return_types.is_empty() {
+            header.push_str(&format!(": {}", self.types(&signature.return_types, " * ")));
+        }
+        lines.push(header);
+        let mut acquires: Vec<_> = def
+            .acquires_global_resources
+            .iter()
+            .map(|idx| self.struct_def_name(*idx))
+            .collect();
+        acquires.sort();
+        if !acquires.is_empty() {
+            lines.push(format!("    acquires {}", acquires.join(", ")));
+        }
+        if def.is_native() {
+            return;
+        }
+        let locals = &self.module.locals_signature_at(def.code.locals).0;
+        for (i, ty) in locals.iter().enumerate().skip(signature.arg_types.len()) {
+            lines.push(format!("    let L{}: {}", i, self.type_(ty)));
+        }
+        self.code(&def.code.code, lines);
+    }
+
+    fn code(&self, code: &[Bytecode], lines: &mut Vec<String>) {
+        let mut labels = BTreeMap::new();
+        for instruction in code {
+            match instruction {
+                Bytecode::BrTrue(offset) | Bytecode::BrFalse(offset) | Bytecode::Branch(offset) => {
+                    labels.insert(*offset, 0);
+                }
+                _ => (),
+            }
+        }
+        for (i, label) in labels.values_mut().enumerate() {
+            *label = i;
+        }
+        for (offset, instruction) in code.iter().enumerate() {
+            if let Some(label) = labels.get(&(offset as CodeOffset)) {
+                lines.push(format!("  B{}:", label));
+            }
+            lines.push(format!("    {}", self.instruction(instruction, &labels)));
+        }
+    }
+
+    fn instruction(&self, instruction: &Bytecode, labels: &BTreeMap<CodeOffset, usize>) -> String {
+        let label = |offset: &CodeOffset| format!("B{}", labels[offset]);
+        match instruction {
+            Bytecode::BrTrue(offset) => format!("BrTrue({})", label(offset)),
+            Bytecode::BrFalse(offset) => format!("BrFalse({})", label(offset)),
+            Bytecode::Branch(offset) => format!("Branch({})", label(offset)),
+            Bytecode::LdByteArray(idx) => {
+                let bytes = self.module.byte_array_at(*idx).as_bytes();
+                let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                format!("LdByteArray(h\"{}\")", hex)
+            }
+            Bytecode::LdAddr(idx) => format!("LdAddr({})", self.module.address_at(*idx)),
+            Bytecode::CopyLoc(idx) => format!("CopyLoc(L{})", idx),
+            Bytecode::MoveLoc(idx) => format!("MoveLoc(L{})", idx),
+            Bytecode::StLoc(idx) => format!("StLoc(L{})", idx),
+            Bytecode::MutBorrowLoc(idx) => format!("MutBorrowLoc(L{})", idx),
+            Bytecode::ImmBorrowLoc(idx) => format!("ImmBorrowLoc(L{})", idx),
+            Bytecode::MutBorrowField(idx) => format!("MutBorrowField({})", self.field_name(*idx)),
+            Bytecode::ImmBorrowField(idx) => format!("ImmBorrowField({})", self.field_name(*idx)),
+            Bytecode::Call(idx, types) => format!("Call({})", self.function_name(*idx, *types)),
+            Bytecode::Pack(idx, types) => format!("Pack({})", self.struct_type(*idx, *types)),
+            Bytecode::Unpack(idx, types) => format!("Unpack({})", self.struct_type(*idx, *types)),
+            Bytecode::Exists(idx, types) => format!("Exists({})", self.struct_type(*idx, *types)),
+            Bytecode::MutBorrowGlobal(idx, types) => {
+                format!("MutBorrowGlobal({})", self.struct_type(*idx, *types))
+            }
+            Bytecode::ImmBorrowGlobal(idx, types) => {
+                format!("ImmBorrowGlobal({})", self.struct_type(*idx, *types))
+            }
+            Bytecode::MoveFrom(idx, types) => {
+                format!("MoveFrom({})", self.struct_type(*idx, *types))
+            }
+            Bytecode::MoveToSender(idx, types) => {
+                format!("MoveToSender({})", self.struct_type(*idx, *types))
+            }
+            // The other instructions have no operand, or a literal one.
+            _ => format!("{:?}", instruction),
+        }
+    }
+
+    // The prefix naming an item of the module `idx`, empty for the module itself.
+    fn module_prefix(&self, idx: ModuleHandleIndex) -> String {
+        if idx.0 == CompiledModule::IMPLEMENTED_MODULE_INDEX {
+            String::new()
+        } else {
+            let handle = self.module.module_handle_at(idx);
+            format!("{}.", self.module.identifier_at(handle.name))
+        }
+    }
+
+    fn struct_handle_name(&self, idx: StructHandleIndex) -> String {
+        let handle = self.module.struct_handle_at(idx);
+        format!(
+            "{}{}",
+            self.module_prefix(handle.module),
+            self.module.identifier_at(handle.name)
+        )
+    }
+
+    fn struct_def_name(&self, idx: StructDefinitionIndex) -> String {
+        self.struct_handle_name(self.module.struct_def_at(idx).struct_handle)
+    }
+
+    fn struct_type(&self, idx: StructDefinitionIndex, types: LocalsSignatureIndex) -> String {
+        format!(
+            "{}{}",
+            self.struct_def_name(idx),
+            self.type_arguments(types)
+        )
+    }
+
+    fn field_name(&self, idx: FieldDefinitionIndex) -> String {
+        let field = self.module.field_def_at(idx);
+        format!(
+            "{}.{}",
+            self.struct_handle_name(field.struct_),
+            self.module.identifier_at(field.name)
+        )
+    }
+
+    fn function_name(&self, idx: FunctionHandleIndex, types: LocalsSignatureIndex) -> String {
+        let handle = self.module.function_handle_at(idx);
+        format!(
+            "{}{}{}",
+            self.module_prefix(handle.module),
+            self.module.identifier_at(handle.name),
+            self.type_arguments(types)
+        )
+    }
+
+    fn type_arguments(&self, idx: LocalsSignatureIndex) -> String {
+        let types = &self.module.locals_signature_at(idx).0;
+        if types.is_empty() {
+            String::new()
+        } else {
+            format!("<{}>", self.types(types, ", "))
+        }
+    }
+
+    fn types(&self, types: &[SignatureToken], separator: &str) -> String {
+        types
+            .iter()
+            .map(|ty| self.type_(ty))
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    fn type_(&self, ty: &SignatureToken) -> String {
+        match ty {
+            SignatureToken::Bool => "bool".to_string(),
+            SignatureToken::U8 => "u8".to_string(),
+            SignatureToken::U64 => "u64".to_string(),
+            SignatureToken::U128 => "u128".to_string(),
+            SignatureToken::ByteArray => "bytearray".to_string(),
+            SignatureToken::Address => "address".to_string(),
+            SignatureToken::Struct(idx, types) => {
+                let name = self.struct_handle_name(*idx);
+                if types.is_empty() {
+                    name
+                } else {
+                    format!("{}<{}>", name, self.types(types, ", "))
+                }
+            }
+            SignatureToken::Reference(ty) => format!("&{}", self.type_(ty)),
+            SignatureToken::MutableReference(ty) => format!("&mut {}", self.type_(ty)),
+            SignatureToken::TypeParameter(idx) => format!("T{}", idx),
+        }
+    }
+}
+
+fn type_formals(kinds: &[Kind]) -> String {
+    if kinds.is_empty() {
+        return String::new();
+    }
+    let formals: Vec<_> = kinds
+        .iter()
+        .enumerate()
+        .map(|(i, kind)| match kind {
+            Kind::All => format!("T{}", i),
+            Kind::Resource => format!("T{}: resource", i),
+            Kind::Unrestricted => format!("T{}: unrestricted", i),
+        })
+        .collect();
+    format!("<{}>", formals.join(", "))
+}
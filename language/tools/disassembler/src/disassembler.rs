@@ -5,14 +5,17 @@ use anyhow::{bail, format_err, Result};
 use bytecode_source_map::{
     mapping::SourceMapping,
     source_map::{FunctionSourceMap, SourceName},
+    utils::line_and_column,
 };
 use bytecode_verifier::control_flow_graph::{ControlFlowGraph, VMControlFlowGraph};
 use libra_types::identifier::{IdentStr, Identifier};
+use move_ir_types::ast::Loc;
+use std::collections::BTreeMap;
 use vm::access::ModuleAccess;
 use vm::file_format::{
-    Bytecode, FieldDefinitionIndex, FunctionDefinition, FunctionDefinitionIndex, FunctionSignature,
-    Kind, LocalsSignature, LocalsSignatureIndex, SignatureToken, StructDefinition,
-    StructDefinitionIndex, StructFieldInformation, TableIndex, TypeSignature,
+    Bytecode, CodeOffset, FieldDefinitionIndex, FunctionDefinition, FunctionDefinitionIndex,
+    FunctionSignature, Kind, LocalsSignature, LocalsSignatureIndex, SignatureToken,
+    StructDefinition, StructDefinitionIndex, StructFieldInformation, TableIndex, TypeSignature,
 };
 
 /// Holds the various options that we support while disassembling code.
@@ -46,6 +49,9 @@ pub struct Disassembler<Location: Clone + Eq + Default> {
     source_mapper: SourceMapping<Location>,
     // The various options that we can set for disassembly.
     options: DisassemblerOptions,
+    // Lines of IR source to print ahead of the instructions they were compiled to, keyed by
+    // function definition index and code offset.
+    source_lines: BTreeMap<(TableIndex, CodeOffset), String>,
 }
 
 impl<Location: Clone + Eq + Default> Disassembler<Location> {
@@ -53,6 +59,7 @@ impl<Location: Clone + Eq + Default> Disassembler<Location> {
         Self {
             source_mapper,
             options,
+            source_lines: BTreeMap::new(),
         }
     }
 
@@ -472,23 +479,30 @@ impl<Location: Clone + Eq + Default> Disassembler<Location> {
             })
             .collect::<Result<Vec<String>>>()?;
 
-        let mut instrs: Vec<String> = instrs
-            .into_iter()
-            .enumerate()
-            .map(|(instr_index, dis_instr)| format!("\t{}: {}", instr_index, dis_instr))
-            .collect();
-
+        let mut block_labels = BTreeMap::new();
         if self.options.print_basic_blocks {
             let cfg = VMControlFlowGraph::new(&function_def.code.code);
             for (block_number, block_id) in cfg.blocks().iter().enumerate() {
-                instrs.insert(
-                    *block_id as usize + block_number,
-                    format!("B{}:", block_number),
-                );
+                block_labels.insert(*block_id, format!("B{}:", block_number));
+            }
+        }
+
+        let mut lines = vec![];
+        for (instr_index, dis_instr) in instrs.into_iter().enumerate() {
+            let code_offset = instr_index as CodeOffset;
+            if let Some(label) = block_labels.get(&code_offset) {
+                lines.push(label.clone());
+            }
+            if let Some(source_line) = self
+                .source_lines
+                .get(&(function_definition_index.0, code_offset))
+            {
+                lines.push(format!("\t// {}", source_line));
             }
+            lines.push(format!("\t{}: {}", instr_index, dis_instr));
         }
 
-        Ok(instrs)
+        Ok(lines)
     }
 
     fn disassemble_type_formals(
@@ -706,3 +720,37 @@ impl<Location: Clone + Eq + Default> Disassembler<Location> {
         ))
     }
 }
+
+impl Disassembler<Loc> {
+    /// Interleave the disassembled code with the IR source it was compiled from: every time the
+    /// instructions move on to a new line of source, that line is printed ahead of them. Requires
+    /// the source code to be attached to the source mapping.
+    pub fn interleave_source(&mut self) -> Result<()> {
+        let source = match &self.source_mapper.source_code {
+            Some((_, source)) => source,
+            None => bail!("Unable to interleave source since source code is not available"),
+        };
+        let source_lines: Vec<&str> = source.lines().collect();
+        for function_index in 0..self.source_mapper.bytecode.function_defs().len() {
+            let function_definition_index = FunctionDefinitionIndex(function_index as TableIndex);
+            let function_source_map = self
+                .source_mapper
+                .source_map
+                .get_function_source_map(function_definition_index)?;
+            let mut previous_line = None;
+            for (code_offset, loc) in function_source_map.code_map.iter() {
+                let (line, _) = line_and_column(source, loc.start());
+                if previous_line == Some(line) {
+                    continue;
+                }
+                previous_line = Some(line);
+                let text = source_lines.get(line - 1).map_or("", |text| text.trim());
+                self.source_lines.insert(
+                    (function_definition_index.0, *code_offset),
+                    format!("{}: {}", line, text),
+                );
+            }
+        }
+        Ok(())
+    }
+}
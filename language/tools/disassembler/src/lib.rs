@@ -1,4 +1,8 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod canonical;
 pub mod disassembler;
+
+#[cfg(test)]
+mod unit_tests;
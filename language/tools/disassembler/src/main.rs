@@ -6,7 +6,10 @@
 use bytecode_source_map::{
     mapping::SourceMapping, source_map::ModuleSourceMap, utils::module_source_map_from_file,
 };
-use disassembler::disassembler::{Disassembler, DisassemblerOptions};
+use disassembler::{
+    canonical::{canonical_module, canonical_script},
+    disassembler::{Disassembler, DisassemblerOptions},
+};
 use libra_types::transaction::Module;
 use move_ir_types::ast::Loc;
 use serde_json;
@@ -46,6 +49,17 @@ struct Args {
     /// Print locals within each function.
     #[structopt(long = "locals")]
     pub print_locals: bool,
+
+    /// Print each line of IR source followed by the bytecode it compiled to. Requires the IR
+    /// source and the source map to reside next to the bytecode file.
+    #[structopt(long = "source")]
+    pub print_source: bool,
+
+    /// Print the canonical dump of the bytecode instead, with sorted declarations, named handles
+    /// and labeled branches, to compare two versions of a module with `diff`. Needs no source map
+    /// and ignores the other printing options.
+    #[structopt(long = "canonical")]
+    pub canonical: bool,
 }
 
 fn main() {
@@ -72,6 +86,20 @@ fn main() {
     let module_bytes: Module = serde_json::from_str(bytecode_source.as_str())
         .expect("Unable to deserialize bytecode file");
 
+    if args.canonical {
+        let dump = if args.is_script {
+            let compiled_script = CompiledScript::deserialize(module_bytes.code())
+                .expect("Script blob can't be deserialized");
+            canonical_script(&compiled_script)
+        } else {
+            let compiled_module = CompiledModule::deserialize(module_bytes.code())
+                .expect("Module blob can't be deserialized");
+            canonical_module(&compiled_module)
+        };
+        print!("{}", dump);
+        return;
+    }
+
     let ir_source_path = Path::new(&args.bytecode_file_path).with_extension(mvir_extension);
     let ir_source = fs::read_to_string(&ir_source_path).ok();
     let source_map = module_source_map_from_file::<Loc>(
@@ -79,7 +107,7 @@ fn main() {
     );
 
     let mut disassembler_options = DisassemblerOptions::new();
-    disassembler_options.print_code = args.print_code | args.print_basic_blocks;
+    disassembler_options.print_code = args.print_code | args.print_basic_blocks | args.print_source;
     disassembler_options.only_public = args.only_public;
     disassembler_options.print_basic_blocks = args.print_basic_blocks;
     disassembler_options.print_locals = args.print_locals;
@@ -105,7 +133,12 @@ fn main() {
             .with_source_code((ir_source_path.to_str().unwrap().to_string(), source_code));
     }
 
-    let disassembler = Disassembler::new(source_mapping, disassembler_options);
+    let mut disassembler = Disassembler::new(source_mapping, disassembler_options);
+    if args.print_source {
+        disassembler
+            .interleave_source()
+            .expect("Unable to interleave IR source");
+    }
 
     let dissassemble_string = disassembler.disassemble().expect("Unable to dissassemble");
 
@@ -0,0 +1,139 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::canonical::{canonical_module, canonical_script};
+use ir_to_bytecode::{
+    compiler::{compile_module, compile_script},
+    parser::{parse_module, parse_script},
+};
+use libra_types::account_address::AccountAddress;
+use vm::file_format::CompiledModule;
+
+const MODULE: &str = "module M {
+    resource R { value: u64 }
+    struct S<T: unrestricted> { x: T, y: bool }
+
+    public get(addr: address): u64 acquires R {
+        return *&(borrow_global<R>(move(addr))).value;
+    }
+
+    public make(x: u64): Self.S<u64> {
+        return S<u64> { x: Self.max(copy(x), 1), y: true };
+    }
+
+    max(a: u64, b: u64): u64 {
+        if (copy(a) > copy(b)) {
+            return move(a);
+        }
+        return move(b);
+    }
+}
+";
+
+// The declarations of `MODULE` in another order.
+const REORDERED: &str = "module M {
+    struct S<T: unrestricted> { x: T, y: bool }
+    resource R { value: u64 }
+
+    max(a: u64, b: u64): u64 {
+        if (copy(a) > copy(b)) {
+            return move(a);
+        }
+        return move(b);
+    }
+
+    public make(x: u64): Self.S<u64> {
+        return S<u64> { x: Self.max(copy(x), 1), y: true };
+    }
+
+    public get(addr: address): u64 acquires R {
+        return *&(borrow_global<R>(move(addr))).value;
+    }
+}
+";
+
+fn compile(source: &str) -> CompiledModule {
+    let module = parse_module(source).unwrap();
+    compile_module(AccountAddress::default(), module, &[] as &[CompiledModule])
+        .unwrap()
+        .0
+}
+
+#[test]
+fn declarations_are_sorted_and_handles_named() {
+    let dump = canonical_module(&compile(MODULE));
+    let lines: Vec<_> = dump.lines().collect();
+    assert_eq!(lines[0], format!("module {}.M", AccountAddress::default()));
+    let headers: Vec<_> = lines
+        .iter()
+        .filter(|line| !line.starts_with(' ') && !line.is_empty() && **line != "}")
+        .collect();
+    assert_eq!(
+        headers,
+        vec![
+            &lines[0],
+            &"resource R {",
+            &"struct S<T0: unrestricted> {",
+            &"public get(L0: address): u64",
+            &"public make(L0: u64): S<u64>",
+            &"max(L0: u64, L1: u64): u64",
+        ]
+    );
+    for line in &[
+        "    x: T0",
+        "    acquires R",
+        "    ImmBorrowGlobal(R)",
+        "    ImmBorrowField(R.value)",
+        "    Call(max)",
+        "    Pack(S<u64>)",
+        "    BrFalse(B0)",
+        "  B0:",
+    ] {
+        assert!(lines.contains(line), "missing {:?} in\n{}", line, dump);
+    }
+    assert!(!dump.contains('['), "{}", dump);
+    assert!(dump.ends_with("    Ret\n"), "{}", dump);
+}
+
+#[test]
+fn the_order_of_declarations_does_not_matter() {
+    let module = compile(MODULE);
+    let reordered = compile(REORDERED);
+    assert_ne!(module, reordered);
+    assert_eq!(canonical_module(&module), canonical_module(&reordered));
+}
+
+#[test]
+fn scripts_are_dumped_with_their_imports() {
+    let source = "import 0x0.LibraAccount;
+main(payee: address) {
+    let balance: u64;
+    balance = LibraAccount.balance(move(payee));
+    return;
+}
+";
+    let script = parse_script(source).unwrap();
+    let deps = stdlib_like_deps();
+    let (compiled, _) = compile_script(AccountAddress::default(), script, &deps).unwrap();
+    let dump = canonical_script(&compiled);
+    assert!(dump.starts_with(&format!(
+        "script\n\nimport {}.LibraAccount\n\npublic main(L0: address)\n    let L1: u64\n",
+        AccountAddress::default()
+    )));
+    assert!(
+        dump.contains("    Call(LibraAccount.balance)\n"),
+        "{}",
+        dump
+    );
+}
+
+// A module declaring the function the script calls.
+fn stdlib_like_deps() -> Vec<CompiledModule> {
+    let source = "module LibraAccount {
+    public balance(addr: address): u64 {
+        return 0;
+    }
+}
+";
+    vec![compile(source)]
+}
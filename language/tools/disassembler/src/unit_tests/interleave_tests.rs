@@ -0,0 +1,118 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::disassembler::{Disassembler, DisassemblerOptions};
+use bytecode_source_map::{mapping::SourceMapping, source_map::ModuleSourceMap};
+use ir_to_bytecode::{compiler::compile_script, parser::parse_script};
+use libra_types::account_address::AccountAddress;
+use move_ir_types::ast::Loc;
+use vm::file_format::{CompiledModule, CompiledScript};
+
+const SCRIPT: &str = "main() {
+    let x: u64;
+    let y: u64;
+    x = 1;
+    y = copy(x) +
+        2;
+    return;
+}
+";
+
+fn compile(source: &str) -> (CompiledScript, ModuleSourceMap<Loc>) {
+    let script = parse_script(source).unwrap();
+    compile_script(AccountAddress::default(), script, &[] as &[CompiledModule]).unwrap()
+}
+
+fn new_disassembler(
+    script: CompiledScript,
+    source_map: ModuleSourceMap<Loc>,
+    source: Option<&str>,
+) -> Disassembler<Loc> {
+    let mut source_mapping = SourceMapping::new_from_script(source_map, script);
+    if let Some(source) = source {
+        source_mapping.with_source_code(("script.mvir".to_string(), source.to_string()));
+    }
+    let mut options = DisassemblerOptions::new();
+    options.print_code = true;
+    Disassembler::new(source_mapping, options)
+}
+
+fn source_comments(disassembly: &str) -> Vec<&str> {
+    disassembly
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("// "))
+        .collect()
+}
+
+#[test]
+fn interleave_with_source_map() {
+    let (script, source_map) = compile(SCRIPT);
+    let mut disassembler = new_disassembler(script, source_map, Some(SCRIPT));
+    disassembler.interleave_source().unwrap();
+    let disassembly = disassembler.disassemble().unwrap();
+
+    let comments = source_comments(&disassembly);
+    assert!(comments.contains(&"// 4: x = 1;"), "{}", disassembly);
+    assert!(comments.contains(&"// 7: return;"), "{}", disassembly);
+    // Declarations do not compile to instructions.
+    assert!(!comments.iter().any(|c| c.contains("let x")), "{}", disassembly);
+
+    // Every source line precedes the instructions compiled from it.
+    let lines: Vec<&str> = disassembly.lines().map(str::trim).collect();
+    let first_comment = lines.iter().position(|l| l.starts_with("// ")).unwrap();
+    let first_instr = lines.iter().position(|l| l.starts_with("0: ")).unwrap();
+    assert_eq!(first_comment + 1, first_instr, "{}", disassembly);
+    let return_comment = lines.iter().position(|l| *l == "// 7: return;").unwrap();
+    assert!(lines[return_comment + 1].ends_with(": Ret"), "{}", disassembly);
+}
+
+#[test]
+fn interleave_multi_line_statement() {
+    let (script, source_map) = compile(SCRIPT);
+    let mut disassembler = new_disassembler(script, source_map, Some(SCRIPT));
+    disassembler.interleave_source().unwrap();
+    let disassembly = disassembler.disassemble().unwrap();
+
+    // The statement spans lines 5 and 6: each line is printed, trimmed, ahead of the instructions
+    // compiled from it, and never twice in a row.
+    let comments = source_comments(&disassembly);
+    assert!(comments.contains(&"// 5: y = copy(x) +"), "{}", disassembly);
+    assert!(comments.contains(&"// 6: 2;"), "{}", disassembly);
+    assert!(comments.windows(2).all(|w| w[0] != w[1]), "{}", disassembly);
+}
+
+#[test]
+fn interleave_without_code_locations() {
+    // A dummy source map only knows that the code of `main` starts somewhere on the first line.
+    let (script, _) = compile(SCRIPT);
+    let source_map = ModuleSourceMap::dummy_from_script(&script).unwrap();
+    let mut disassembler = new_disassembler(script, source_map, Some(SCRIPT));
+    disassembler.interleave_source().unwrap();
+    let disassembly = disassembler.disassemble().unwrap();
+
+    assert_eq!(source_comments(&disassembly), vec!["// 1: main() {"]);
+}
+
+#[test]
+fn interleave_with_locations_past_the_source() {
+    // Locations past the end of the attached source fall on the line after its end, which has no
+    // text to show.
+    let (script, source_map) = compile(SCRIPT);
+    let source: String = SCRIPT.lines().take(4).map(|line| format!("{}\n", line)).collect();
+    let mut disassembler = new_disassembler(script, source_map, Some(&source));
+    disassembler.interleave_source().unwrap();
+    let disassembly = disassembler.disassemble().unwrap();
+
+    let comments = source_comments(&disassembly);
+    assert_eq!(comments.first(), Some(&"// 4: x = 1;"), "{}", disassembly);
+    assert!(comments[1..].iter().all(|c| *c == "// 5:"), "{}", disassembly);
+    assert!(comments.len() > 1, "{}", disassembly);
+}
+
+#[test]
+fn interleave_requires_source() {
+    let (script, source_map) = compile(SCRIPT);
+    let mut disassembler = new_disassembler(script, source_map, None);
+    assert!(disassembler.interleave_source().is_err());
+}
@@ -0,0 +1,272 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+//! An interactive session for experimenting with Move IR commands.
+//!
+//! A [`Session`](struct.Session.html) accumulates imports, local declarations and statements as
+//! they are entered. Each new statement is checked with the command parser, and then the whole
+//! session is compiled into the body of a transaction script and executed against an in-memory
+//! genesis state, on behalf of a funded sender account. Locals only live for the duration of a
+//! script, so the statements entered so far are replayed every time: the state the script runs
+//! against is never modified, and a statement is only kept if the script executes successfully.
+//!
+//! Modules can be loaded into the session. They are compiled at the sender's address and
+//! published directly into the state, where later scripts can import them.
+
+use anyhow::{bail, format_err, Result};
+use bytecode_verifier::VerifiedModule;
+use compiler::Compiler;
+use ir_to_bytecode::parser::parse_cmd_;
+use language_e2e_tests::{account::AccountData, executor::FakeExecutor};
+use libra_types::{
+    account_address::AccountAddress,
+    language_storage::ModuleId,
+    transaction::{TransactionOutput, TransactionStatus},
+    vm_error::{StatusCode, VMStatus},
+};
+use std::fmt;
+use vm::access::ModuleAccess;
+
+/// The maximum amount of gas a session script can use.
+const MAX_GAS_AMOUNT: u64 = 1_000_000;
+
+/// The result of executing the session with a new statement.
+#[derive(Debug)]
+pub struct Outcome {
+    /// The status the script finished with.
+    pub status: VMStatus,
+    /// The gas used by the whole session script.
+    pub gas_used: u64,
+    /// The number of events the session script emitted.
+    pub events: usize,
+    /// The number of values the session script wrote to global storage.
+    pub writes: usize,
+}
+
+impl Outcome {
+    fn new(output: &TransactionOutput) -> Self {
+        Outcome {
+            status: output.status().vm_status().clone(),
+            gas_used: output.gas_used(),
+            events: output.events().len(),
+            writes: output.write_set().iter().len(),
+        }
+    }
+
+    /// Whether the script executed successfully.
+    pub fn succeeded(&self) -> bool {
+        self.status.major_status == StatusCode::EXECUTED
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.status.major_status {
+            StatusCode::EXECUTED => write!(f, "executed")?,
+            StatusCode::ABORTED => write!(
+                f,
+                "aborted with code {}",
+                self.status.sub_status.unwrap_or_default()
+            )?,
+            _ => write!(f, "failed with {}", self.status)?,
+        }
+        write!(
+            f,
+            " (gas used: {}, events: {}, writes: {})",
+            self.gas_used, self.events, self.writes
+        )
+    }
+}
+
+/// What an input line was.
+#[derive(Debug)]
+pub enum Evaluated {
+    /// An import, kept in the session.
+    Import,
+    /// A local declaration, kept in the session.
+    Local,
+    /// A statement. It is kept in the session if the outcome is successful.
+    Statement(Outcome),
+}
+
+/// A session of imports, locals and statements, along with the state they are executed against.
+pub struct Session {
+    executor: FakeExecutor,
+    sender: AccountData,
+    modules: Vec<VerifiedModule>,
+    imports: Vec<String>,
+    locals: Vec<String>,
+    statements: Vec<String>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    /// Creates an empty session over the genesis state, with a freshly created sender.
+    pub fn new() -> Self {
+        let mut executor = FakeExecutor::from_genesis_file();
+        let sender = AccountData::new(MAX_GAS_AMOUNT, 0);
+        executor.add_account_data(&sender);
+        Session {
+            executor,
+            sender,
+            modules: vec![],
+            imports: vec![],
+            locals: vec![],
+            statements: vec![],
+        }
+    }
+
+    /// The address the session scripts are sent from, and the modules are published at.
+    pub fn sender(&self) -> AccountAddress {
+        *self.sender.address()
+    }
+
+    /// The imports of the session.
+    pub fn imports(&self) -> &[String] {
+        &self.imports
+    }
+
+    /// The local declarations of the session.
+    pub fn locals(&self) -> &[String] {
+        &self.locals
+    }
+
+    /// Forgets the imports, locals and statements. Loaded modules are kept.
+    pub fn reset(&mut self) {
+        self.imports.clear();
+        self.locals.clear();
+        self.statements.clear();
+    }
+
+    /// Evaluates an input: an `import`, a `let` declaration, or a statement.
+    ///
+    /// Imports and declarations are kept if the session still compiles with them. Statements are
+    /// executed along with the rest of the session.
+    pub fn eval(&mut self, input: &str) -> Result<Evaluated> {
+        let input = input.trim();
+        if input.is_empty() {
+            bail!("empty input");
+        }
+        let input = if input.ends_with(';') || input.ends_with('}') {
+            input.to_string()
+        } else {
+            format!("{};", input)
+        };
+
+        if input.starts_with("import ") {
+            self.imports.push(input);
+            if let Err(err) = self.compile(&self.statements) {
+                self.imports.pop();
+                return Err(err);
+            }
+            return Ok(Evaluated::Import);
+        }
+        if input.starts_with("let ") {
+            self.locals.push(input);
+            if let Err(err) = self.compile(&self.statements) {
+                self.locals.pop();
+                return Err(err);
+            }
+            return Ok(Evaluated::Local);
+        }
+
+        if is_command(&input) {
+            parse_cmd_(input.trim_end_matches(';'), self.sender())?;
+        }
+        let mut statements = self.statements.clone();
+        statements.push(input);
+        let outcome = self.execute(&statements)?;
+        if outcome.succeeded() {
+            self.statements = statements;
+        }
+        Ok(Evaluated::Statement(outcome))
+    }
+
+    /// Compiles a module at the sender's address and publishes it, returning its id.
+    pub fn load_module(&mut self, source: &str) -> Result<ModuleId> {
+        let compiler = Compiler {
+            address: self.sender(),
+            extra_deps: self.modules.clone(),
+            ..Compiler::default()
+        };
+        let module = compiler.into_compiled_module(source)?;
+        let module = VerifiedModule::new(module)
+            .map_err(|(_, errors)| format_err!("Module failed to verify: {:?}", errors))?;
+        let id = module.self_id();
+        self.executor.add_module(&id, module.as_inner());
+        self.modules.push(module);
+        Ok(id)
+    }
+
+    /// Returns the source of the script the session executes.
+    pub fn script_source(&self) -> String {
+        script_source(&self.imports, &self.locals, &self.statements)
+    }
+
+    fn compile(&self, statements: &[String]) -> Result<Vec<u8>> {
+        let compiler = Compiler {
+            address: self.sender(),
+            extra_deps: self.modules.clone(),
+            ..Compiler::default()
+        };
+        compiler.into_script_blob(&script_source(&self.imports, &self.locals, statements))
+    }
+
+    fn execute(&self, statements: &[String]) -> Result<Outcome> {
+        let script = self.compile(statements)?;
+        let txn = self.sender.account().create_signed_txn_with_args(
+            script,
+            vec![],
+            self.sender.sequence_number(),
+            MAX_GAS_AMOUNT,
+            0,
+        );
+        let output = self.executor.execute_transaction(txn);
+        match output.status() {
+            TransactionStatus::Keep(_) => Ok(Outcome::new(&output)),
+            TransactionStatus::Discard(status) => bail!("Transaction discarded with {}", status),
+        }
+    }
+}
+
+/// Whether a statement is a single command, as opposed to a control flow statement or an
+/// `assert`.
+fn is_command(statement: &str) -> bool {
+    let keyword = statement
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or("");
+    match keyword {
+        "if" | "while" | "loop" | "assert" => false,
+        _ => true,
+    }
+}
+
+fn script_source(imports: &[String], locals: &[String], statements: &[String]) -> String {
+    let mut source = String::new();
+    for import in imports {
+        source.push_str(import);
+        source.push('\n');
+    }
+    source.push_str("main() {\n");
+    for line in locals.iter().chain(statements) {
+        source.push_str("    ");
+        source.push_str(line);
+        source.push('\n');
+    }
+    let ends_script = statements
+        .last()
+        .map_or(false, |s| s.starts_with("return") || s.starts_with("abort"));
+    if !ends_script {
+        source.push_str("    return;\n");
+    }
+    source.push_str("}\n");
+    source
+}
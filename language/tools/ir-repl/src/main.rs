@@ -0,0 +1,105 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+use ir_repl::{Evaluated, Session};
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "Move IR REPL",
+    about = "Evaluate Move IR commands interactively against an in-memory genesis state."
+)]
+struct Args {
+    /// Modules to load into the session before starting
+    #[structopt(long = "load", number_of_values = 1, parse(from_os_str))]
+    pub modules: Vec<PathBuf>,
+}
+
+const HELP: &str = "\
+Enter an `import`, a `let` declaration or a statement to execute it with the rest of the session.
+Input spanning several lines is read until its braces are balanced.
+
+    :load <path>    compile a module at the sender's address and publish it
+    :locals         list the local declarations
+    :source         print the script the session executes
+    :reset          forget the imports, locals and statements
+    :help           print this message
+    :quit           exit";
+
+fn load(session: &mut Session, path: &str) {
+    match fs::read_to_string(path) {
+        Ok(source) => match session.load_module(&source) {
+            Ok(id) => println!("loaded {}.{}", id.address(), id.name()),
+            Err(err) => println!("error: {}", err),
+        },
+        Err(err) => println!("error: unable to read {}: {}", path, err),
+    }
+}
+
+fn is_complete(input: &str) -> bool {
+    input.matches('{').count() <= input.matches('}').count()
+}
+
+fn main() {
+    let args = Args::from_args();
+    let mut session = Session::new();
+    for path in &args.modules {
+        load(&mut session, &path.to_string_lossy());
+    }
+    println!("Sender: {}. Type :help for help.", session.sender());
+
+    let stdin = io::stdin();
+    let mut input = String::new();
+    loop {
+        print!("{}", if input.is_empty() { "> " } else { ". " });
+        io::stdout().flush().expect("Unable to write to stdout");
+        let mut line = String::new();
+        if stdin
+            .lock()
+            .read_line(&mut line)
+            .expect("Unable to read stdin")
+            == 0
+        {
+            break;
+        }
+        input.push_str(&line);
+        if !is_complete(&input) {
+            continue;
+        }
+        let entered = input.trim().to_string();
+        input.clear();
+
+        let mut words = entered.split_whitespace();
+        match words.next() {
+            None => continue,
+            Some(":quit") => break,
+            Some(":help") => println!("{}", HELP),
+            Some(":source") => print!("{}", session.script_source()),
+            Some(":locals") => {
+                for local in session.locals() {
+                    println!("{}", local);
+                }
+            }
+            Some(":reset") => session.reset(),
+            Some(":load") => match words.next() {
+                Some(path) => load(&mut session, path),
+                None => println!("error: expected a path"),
+            },
+            Some(command) if command.starts_with(':') => {
+                println!("error: unknown command {}", command)
+            }
+            Some(_) => match session.eval(&entered) {
+                Ok(Evaluated::Statement(outcome)) => println!("{}", outcome),
+                Ok(_) => (),
+                Err(err) => println!("error: {}", err),
+            },
+        }
+    }
+}
@@ -229,6 +229,7 @@ impl<'a> ModuleGenerator<'a> {
             acquires: Vec::new(),
             specifications: Vec::new(),
             signature,
+            header: Loc::default(),
             body: FunctionBody::Move {
                 locals,
                 code: Block_ {
@@ -237,6 +238,9 @@ impl<'a> ModuleGenerator<'a> {
                     ))]),
                 },
             },
+            cfg: None,
+            inline: None,
+            annotations: vec![],
         };
         let fun_name = FunctionName::new(self.identifier());
         self.current_module
@@ -254,6 +258,7 @@ impl<'a> ModuleGenerator<'a> {
             type_formals,
             fields,
             invariants: vec![],
+            cfg: None,
         };
         self.current_module.structs.push(Spanned::no_loc(strct))
     }
@@ -311,6 +316,7 @@ impl<'a> ModuleGenerator<'a> {
             structs: Vec::new(),
             functions: Vec::new(),
             synthetics: Vec::new(),
+            language_version: None,
         };
         Self {
             options,
@@ -8,8 +8,8 @@ use crate::{
         AddressPoolIndex, ByteArrayPoolIndex, CompiledModule, CompiledModuleMut, CompiledScript,
         FieldDefinition, FieldDefinitionIndex, FunctionDefinition, FunctionDefinitionIndex,
         FunctionHandle, FunctionHandleIndex, FunctionSignature, FunctionSignatureIndex,
-        IdentifierIndex, LocalsSignature, LocalsSignatureIndex, MemberCount, ModuleHandle,
-        ModuleHandleIndex, StructDefinition, StructDefinitionIndex, StructHandle,
+        IdentifierIndex, LocalsSignature, LocalsSignatureIndex, MemberCount, MetadataEntry,
+        ModuleHandle, ModuleHandleIndex, StructDefinition, StructDefinitionIndex, StructHandle,
         StructHandleIndex, TypeSignature, TypeSignatureIndex,
     },
     internals::ModuleIndex,
@@ -148,6 +148,18 @@ pub trait ModuleAccess: Sync {
         &self.as_module().as_inner().function_defs
     }
 
+    fn metadata(&self) -> &[MetadataEntry] {
+        &self.as_module().as_inner().metadata
+    }
+
+    /// Returns the value of the metadata entry with key `key`, if the module has one.
+    fn metadata_value(&self, key: &IdentStr) -> Option<&ByteArray> {
+        self.metadata()
+            .iter()
+            .find(|entry| entry.key.as_ident_str() == key)
+            .map(|entry| &entry.value)
+    }
+
     fn module_id_for_handle(&self, module_handle_idx: &ModuleHandle) -> ModuleId {
         self.as_module().module_id_for_handle(module_handle_idx)
     }
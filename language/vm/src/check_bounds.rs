@@ -12,6 +12,7 @@ use crate::{
     IndexKind,
 };
 use libra_types::vm_error::{StatusCode, VMStatus};
+use std::collections::HashSet;
 
 pub struct BoundsChecker<'a> {
     module: &'a CompiledModuleMut,
@@ -100,6 +101,9 @@ impl<'a> BoundsChecker<'a> {
                 .collect(),
         );
 
+        // Check that every metadata key is recorded once, so that readers find its entry.
+        errors.push(self.verify_metadata());
+
         let errors: Vec<_> = errors.into_iter().flatten().collect();
         if !errors.is_empty() {
             return errors;
@@ -126,6 +130,19 @@ impl<'a> BoundsChecker<'a> {
         errors_type_signatures.chain(errors_code_units).collect()
     }
 
+    fn verify_metadata(&self) -> Vec<VMStatus> {
+        let mut keys = HashSet::new();
+        self.module
+            .metadata
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| !keys.insert(&entry.key))
+            .map(|(idx, _)| {
+                verification_error(IndexKind::Metadata, idx, StatusCode::DUPLICATE_ELEMENT)
+            })
+            .collect()
+    }
+
     #[inline]
     fn verify_pool<Context, Item: 'a>(
         kind: IndexKind,
@@ -336,7 +336,8 @@ fn build_common_tables(
             TableType::FUNCTION_DEFS
             | TableType::FIELD_DEFS
             | TableType::STRUCT_DEFS
-            | TableType::MAIN => continue,
+            | TableType::MAIN
+            | TableType::METADATA => continue,
         }
     }
     Ok(())
@@ -359,6 +360,9 @@ fn build_module_tables(
             TableType::FUNCTION_DEFS => {
                 load_function_defs(binary, table, &mut module.function_defs)?;
             }
+            TableType::METADATA => {
+                load_metadata(binary, table, &mut module.metadata)?;
+            }
             TableType::MODULE_HANDLES
             | TableType::STRUCT_HANDLES
             | TableType::FUNCTION_HANDLES
@@ -404,7 +408,10 @@ fn build_script_tables(
             | TableType::LOCALS_SIGNATURES => {
                 continue;
             }
-            TableType::STRUCT_DEFS | TableType::FIELD_DEFS | TableType::FUNCTION_DEFS => {
+            TableType::STRUCT_DEFS
+            | TableType::FIELD_DEFS
+            | TableType::FUNCTION_DEFS
+            | TableType::METADATA => {
                 return Err(VMStatus::new(StatusCode::MALFORMED));
             }
         }
@@ -817,6 +824,44 @@ fn load_function_defs(
     Ok(())
 }
 
+/// Builds the metadata of a module from the `METADATA` table, rejecting the layouts of versions
+/// other than `METADATA_VERSION`.
+fn load_metadata(
+    binary: &[u8],
+    table: &Table,
+    metadata: &mut Vec<MetadataEntry>,
+) -> BinaryLoaderResult<()> {
+    let start = table.offset as usize;
+    let end = start + table.count as usize;
+    let mut cursor = Cursor::new(&binary[start..end]);
+    let version = cursor
+        .read_u8()
+        .map_err(|_| VMStatus::new(StatusCode::MALFORMED))?;
+    if version != BinaryConstants::METADATA_VERSION {
+        return Err(VMStatus::new(StatusCode::UNKNOWN_VERSION));
+    }
+    while cursor.position() < u64::from(table.count) {
+        let key = Identifier::from_utf8(load_metadata_bytes(&mut cursor)?)
+            .map_err(|_| VMStatus::new(StatusCode::MALFORMED))?;
+        let value = ByteArray::new(load_metadata_bytes(&mut cursor)?);
+        metadata.push(MetadataEntry { key, value });
+    }
+    Ok(())
+}
+
+/// Deserializes the key or the value of a `MetadataEntry`: its size as a ULEB128, then its bytes.
+fn load_metadata_bytes(cursor: &mut Cursor<&[u8]>) -> BinaryLoaderResult<Vec<u8>> {
+    let size = read_uleb_u32_internal(cursor)? as usize;
+    if size > std::u16::MAX as usize {
+        return Err(VMStatus::new(StatusCode::MALFORMED));
+    }
+    let mut bytes = vec![0u8; size];
+    cursor
+        .read_exact(&mut bytes)
+        .map_err(|_| VMStatus::new(StatusCode::MALFORMED))?;
+    Ok(bytes)
+}
+
 /// Deserializes a `FunctionDefinition`.
 fn load_function_def(cursor: &mut Cursor<&[u8]>) -> BinaryLoaderResult<FunctionDefinition> {
     let function = read_uleb_u16_internal(cursor)?;
@@ -1083,6 +1128,7 @@ impl TableType {
             0xB => Ok(TableType::TYPE_SIGNATURES),
             0xC => Ok(TableType::FUNCTION_SIGNATURES),
             0xD => Ok(TableType::LOCALS_SIGNATURES),
+            0xE => Ok(TableType::METADATA),
             _ => Err(VMStatus::new(StatusCode::UNKNOWN_TABLE_TYPE)),
         }
     }
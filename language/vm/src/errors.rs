@@ -121,17 +121,47 @@ pub fn bounds_error(kind: IndexKind, idx: usize, len: usize, err: StatusCode) ->
     VMStatus::new(err).with_message(msg)
 }
 
+// The location information of verification errors only lives in their messages. These are the
+// pieces `index_of_error` and `offset_of_error` read it back from, so both sides must use them.
+const INDEX_PREFIX: &str = "at index ";
+const INDEX_KIND_SEPARATOR: &str = " while indexing ";
+const OFFSET_PREFIX: &str = "At offset ";
+
+fn index_message(kind: IndexKind, idx: usize) -> String {
+    format!("{}{}{}{}", INDEX_PREFIX, idx, INDEX_KIND_SEPARATOR, kind)
+}
+
 pub fn verification_error(kind: IndexKind, idx: usize, err: StatusCode) -> VMStatus {
-    let msg = format!("at index {} while indexing {}", idx, kind);
-    VMStatus::new(err).with_message(msg)
+    VMStatus::new(err).with_message(index_message(kind, idx))
 }
 
 pub fn append_err_info(status: VMStatus, kind: IndexKind, idx: usize) -> VMStatus {
-    let msg = format!("at index {} while indexing {}", idx, kind);
-    status.append_message_with_separator(' ', msg)
+    status.append_message_with_separator(' ', index_message(kind, idx))
 }
 
 pub fn err_at_offset(status: StatusCode, offset: usize) -> VMStatus {
-    let msg = format!("At offset {}", offset);
+    let msg = format!("{}{}", OFFSET_PREFIX, offset);
     VMStatus::new(status).with_message(msg)
 }
+
+/// Returns the index of the `kind` entry a status built by `verification_error` or
+/// `append_err_info` points at, if any.
+pub fn index_of_error(status: &VMStatus, kind: IndexKind) -> Option<usize> {
+    let message = status.message.as_ref()?;
+    let kind_suffix = format!("{}{}", INDEX_KIND_SEPARATOR, kind);
+    message.split(INDEX_PREFIX).skip(1).find_map(|rest| {
+        let end = rest.find(INDEX_KIND_SEPARATOR)?;
+        if rest[end..].starts_with(&kind_suffix) {
+            rest[..end].parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns the code offset a status built by `err_at_offset` points at, if any.
+pub fn offset_of_error(status: &VMStatus) -> Option<usize> {
+    let message = status.message.as_ref()?;
+    let rest = message.split(OFFSET_PREFIX).nth(1)?;
+    rest.split_whitespace().next()?.parse().ok()
+}
@@ -1224,7 +1224,7 @@ pub enum Bytecode {
     Shr,
 }
 
-pub const NUMBER_OF_NATIVE_FUNCTIONS: usize = 17;
+pub const NUMBER_OF_NATIVE_FUNCTIONS: usize = 18;
 
 impl ::std::fmt::Debug for Bytecode {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
@@ -1469,10 +1469,27 @@ impl CompiledScriptMut {
             struct_defs: vec![],
             field_defs: vec![],
             function_defs: vec![self.main],
+
+            metadata: vec![],
         }
     }
 }
 
+/// An entry of the metadata of a module: a value recorded under a key, such as how the module was
+/// built.
+///
+/// Metadata describes a module without changing what it does: the VM never reads it, and the
+/// bytecode verifier only checks that its keys are unique.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(any(test, feature = "fuzzing"), derive(Arbitrary))]
+#[cfg_attr(any(test, feature = "fuzzing"), proptest(no_params))]
+pub struct MetadataEntry {
+    /// The key of the entry, unique in the module.
+    pub key: Identifier,
+    /// The value of the entry, in a format defined by the tool recording it.
+    pub value: ByteArray,
+}
+
 /// A `CompiledModule` defines the structure of a module which is the unit of published code.
 ///
 /// A `CompiledModule` contains a definition of types (with their fields) and functions.
@@ -1516,6 +1533,10 @@ pub struct CompiledModuleMut {
     pub field_defs: Vec<FieldDefinition>,
     /// Function defined in this module.
     pub function_defs: Vec<FunctionDefinition>,
+
+    /// Metadata of this module, serialized in the `METADATA` table. Empty unless a tool recorded
+    /// some.
+    pub metadata: Vec<MetadataEntry>,
 }
 
 // Need a custom implementation of Arbitrary because as of proptest-derive 0.1.1, the derivation
@@ -1597,6 +1618,7 @@ impl Arbitrary for CompiledModuleMut {
                 vec(any::<StructDefinition>(), 0..=size),
                 vec(any::<FieldDefinition>(), 0..=size),
                 vec(any_with::<FunctionDefinition>(size), 0..=size),
+                vec(any::<MetadataEntry>(), 0..=size),
             ),
         )
             .prop_map(
@@ -1604,7 +1626,7 @@ impl Arbitrary for CompiledModuleMut {
                     (module_handles, struct_handles, function_handles),
                     (type_signatures, function_signatures, locals_signatures),
                     (identifiers, byte_array_pool, address_pool),
-                    (struct_defs, field_defs, function_defs),
+                    (struct_defs, field_defs, function_defs, metadata),
                 )| {
                     CompiledModuleMut {
                         module_handles,
@@ -1619,6 +1641,7 @@ impl Arbitrary for CompiledModuleMut {
                         struct_defs,
                         field_defs,
                         function_defs,
+                        metadata,
                     }
                 },
             )
@@ -1642,6 +1665,7 @@ impl CompiledModuleMut {
             IndexKind::Identifier => self.identifiers.len(),
             IndexKind::ByteArrayPool => self.byte_array_pool.len(),
             IndexKind::AddressPool => self.address_pool.len(),
+            IndexKind::Metadata => self.metadata.len(),
             // XXX these two don't seem to belong here
             other @ IndexKind::LocalPool
             | other @ IndexKind::CodeDefinition
@@ -1738,6 +1762,7 @@ pub fn empty_module() -> CompiledModuleMut {
         function_signatures: vec![],
         locals_signatures: vec![LocalsSignature(vec![])],
         byte_array_pool: vec![],
+        metadata: vec![],
     }
 }
 
@@ -28,6 +28,8 @@ impl BinaryConstants {
     /// A (Table Type, Start Offset, Byte Count) size, which is 1 byte for the type and
     /// 4 bytes for the offset/count.
     pub const TABLE_HEADER_SIZE: u32 = size_of::<u32>() as u32 * 2 + 1;
+    /// The version of the layout of the `METADATA` table, the first byte of the table.
+    pub const METADATA_VERSION: u8 = 1;
 }
 
 /// Constants for table types in the binary.
@@ -52,6 +54,7 @@ pub enum TableType {
     TYPE_SIGNATURES         = 0xB,
     FUNCTION_SIGNATURES     = 0xC,
     LOCALS_SIGNATURES       = 0xD,
+    METADATA                = 0xE,
 }
 
 /// Constants for signature kinds (type, function, locals). Those values start a signature blob.
@@ -507,4 +507,5 @@ pub enum NativeCostIndex {
     SWAP = 14,
     WRITE_TO_EVENT_STORE = 15,
     SAVE_ACCOUNT = 16,
+    LCS_SERIALIZE = 17,
 }
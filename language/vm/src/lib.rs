@@ -51,6 +51,7 @@ pub enum IndexKind {
     LocalPool,
     CodeDefinition,
     TypeParameter,
+    Metadata,
 }
 
 impl IndexKind {
@@ -74,6 +75,7 @@ impl IndexKind {
             LocalPool,
             CodeDefinition,
             TypeParameter,
+            Metadata,
         ]
     }
 }
@@ -98,6 +100,7 @@ impl fmt::Display for IndexKind {
             LocalPool => "local pool",
             CodeDefinition => "code definition pool",
             TypeParameter => "type parameter",
+            Metadata => "metadata",
         };
 
         f.write_str(desc)
@@ -339,6 +339,8 @@ impl CompiledModuleStrategyGen {
                         identifiers,
                         byte_array_pool,
                         address_pool,
+
+                        metadata: vec![],
                     }
                     .freeze()
                     .expect("valid modules should satisfy the bounds checker")
@@ -92,6 +92,7 @@ struct ModuleSerializer {
     struct_defs: (u32, u32),
     field_defs: (u32, u32),
     function_defs: (u32, u32),
+    metadata: (u32, u32),
 }
 
 /// Holds data to compute the header of a transaction script binary.
@@ -325,6 +326,16 @@ fn serialize_byte_array(binary: &mut BinaryData, byte_array: &ByteArray) -> Resu
     Ok(())
 }
 
+/// Serializes a `MetadataEntry`.
+///
+/// A `MetadataEntry` gets serialized as follows:
+/// - `MetadataEntry.key` as a `String`
+/// - `MetadataEntry.value` as a `ByteArray`
+fn serialize_metadata_entry(binary: &mut BinaryData, entry: &MetadataEntry) -> Result<()> {
+    serialize_string(binary, entry.key.as_str())?;
+    serialize_byte_array(binary, &entry.value)
+}
+
 /// Serializes an `AccountAddress`.
 ///
 /// A `AccountAddress` gets serialized as follows:
@@ -1007,6 +1018,7 @@ impl ModuleSerializer {
             struct_defs: (0, 0),
             field_defs: (0, 0),
             function_defs: (0, 0),
+            metadata: (0, 0),
         }
     }
 
@@ -1014,7 +1026,8 @@ impl ModuleSerializer {
         self.common.serialize_common(binary, module)?;
         self.serialize_struct_definitions(binary, &module.struct_defs)?;
         self.serialize_field_definitions(binary, &module.field_defs)?;
-        self.serialize_function_definitions(binary, &module.function_defs)
+        self.serialize_function_definitions(binary, &module.function_defs)?;
+        self.serialize_metadata(binary, &module.metadata)
     }
 
     fn serialize_header(&mut self, binary: &mut BinaryData) -> Result<()> {
@@ -1040,6 +1053,13 @@ impl ModuleSerializer {
             start_offset,
             self.function_defs.1,
         )?;
+        checked_serialize_table(
+            binary,
+            TableType::METADATA,
+            self.metadata.0,
+            start_offset,
+            self.metadata.1,
+        )?;
         Ok(())
     }
 
@@ -1093,6 +1113,24 @@ impl ModuleSerializer {
         }
         Ok(())
     }
+
+    /// Serializes the `METADATA` table: `METADATA_VERSION`, then the entries.
+    fn serialize_metadata(
+        &mut self,
+        binary: &mut BinaryData,
+        metadata: &[MetadataEntry],
+    ) -> Result<()> {
+        if !metadata.is_empty() {
+            self.common.table_count += 1;
+            self.metadata.0 = check_index_in_binary(binary.len())?;
+            binary.push(BinaryConstants::METADATA_VERSION)?;
+            for entry in metadata {
+                serialize_metadata_entry(binary, entry)?;
+            }
+            self.metadata.1 = checked_calculate_table_size(binary, self.metadata.0)?;
+        }
+        Ok(())
+    }
 }
 
 impl ScriptSerializer {
@@ -2,10 +2,11 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    file_format::{CompiledModule, CompiledScript},
+    access::ModuleAccess,
+    file_format::{basic_test_module, CompiledModule, CompiledScript, MetadataEntry},
     file_format_common::*,
 };
-use libra_types::vm_error::StatusCode;
+use libra_types::{byte_array::ByteArray, identifier::Identifier, vm_error::StatusCode};
 
 #[test]
 fn malformed_simple() {
@@ -65,3 +66,44 @@ fn malformed_simple() {
         StatusCode::UNKNOWN_VERSION
     );
 }
+
+fn module_with_metadata() -> Vec<u8> {
+    let mut module = basic_test_module();
+    module.metadata.push(MetadataEntry {
+        key: Identifier::new("build").unwrap(),
+        value: ByteArray::new(b"v1".to_vec()),
+    });
+    let mut binary = vec![];
+    module.serialize(&mut binary).unwrap();
+    binary
+}
+
+#[test]
+fn metadata_roundtrip() {
+    let module = CompiledModule::deserialize(&module_with_metadata()).unwrap();
+    assert_eq!(module.metadata().len(), 1);
+    assert_eq!(
+        module.metadata_value(&Identifier::new("build").unwrap()),
+        Some(&ByteArray::new(b"v1".to_vec()))
+    );
+    assert_eq!(
+        module.metadata_value(&Identifier::new("source").unwrap()),
+        None
+    );
+}
+
+#[test]
+fn metadata_unknown_version() {
+    let mut binary = module_with_metadata();
+    // The metadata table is the last one: its version, then the key and the value, each after
+    // their one byte size.
+    let version = binary.len() - (1 + 6 + 3);
+    assert_eq!(binary[version], BinaryConstants::METADATA_VERSION);
+    binary[version] += 1;
+    assert_eq!(
+        CompiledModule::deserialize(&binary)
+            .expect_err("Expected unknown metadata version")
+            .major_status,
+        StatusCode::UNKNOWN_VERSION
+    );
+}
@@ -0,0 +1,43 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{errors::*, IndexKind};
+use libra_types::vm_error::{StatusCode, VMStatus};
+
+#[test]
+fn index_of_verification_error() {
+    let status = verification_error(IndexKind::StructDefinition, 3, StatusCode::UNUSED_FIELD);
+    assert_eq!(index_of_error(&status, IndexKind::StructDefinition), Some(3));
+    assert_eq!(index_of_error(&status, IndexKind::FunctionDefinition), None);
+    assert_eq!(offset_of_error(&status), None);
+}
+
+#[test]
+fn offset_and_index_of_located_error() {
+    let status = append_err_info(
+        err_at_offset(StatusCode::MOVELOC_UNAVAILABLE_ERROR, 12),
+        IndexKind::FunctionDefinition,
+        4,
+    );
+    assert_eq!(offset_of_error(&status), Some(12));
+    assert_eq!(index_of_error(&status, IndexKind::FunctionDefinition), Some(4));
+    assert_eq!(index_of_error(&status, IndexKind::FunctionHandle), None);
+}
+
+#[test]
+fn index_of_error_with_several_locations() {
+    let status = append_err_info(
+        verification_error(IndexKind::FieldDefinition, 7, StatusCode::UNUSED_FIELD),
+        IndexKind::StructDefinition,
+        2,
+    );
+    assert_eq!(index_of_error(&status, IndexKind::FieldDefinition), Some(7));
+    assert_eq!(index_of_error(&status, IndexKind::StructDefinition), Some(2));
+}
+
+#[test]
+fn no_location_without_message() {
+    let status = VMStatus::new(StatusCode::UNKNOWN_VERIFICATION_ERROR);
+    assert_eq!(offset_of_error(&status), None);
+    assert_eq!(index_of_error(&status, IndexKind::FunctionDefinition), None);
+}
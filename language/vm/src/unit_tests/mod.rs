@@ -3,5 +3,6 @@
 
 mod binary_tests;
 mod deserializer_tests;
+mod errors_tests;
 mod fixture_tests;
 mod number_tests;
@@ -54,7 +54,7 @@ impl<'alloc> ScriptCache<'alloc> {
             Ok(f)
         } else {
             trace!("[VM] Script cache miss");
-            let script = Self::deserialize_and_verify(raw_bytes, context)?;
+            let script = Self::deserialize_and_verify(raw_bytes, context, VerifiedScript::new)?;
             let fake_module = script.into_module();
             let loaded_module = LoadedModule::new(fake_module);
             Ok(self.map.or_insert_with_transform(
@@ -65,9 +65,21 @@ impl<'alloc> ScriptCache<'alloc> {
         }
     }
 
+    /// Compiles and verifies `raw_bytes` into the module of a script run as a query, whose main
+    /// function may return values. Queries are not cached, so that a transaction never finds in
+    /// the cache a script that was only verified as a query.
+    pub fn load_query_script(
+        raw_bytes: &[u8],
+        context: &mut dyn InterpreterContext,
+    ) -> VMResult<LoadedModule> {
+        let script = Self::deserialize_and_verify(raw_bytes, context, VerifiedScript::new_query)?;
+        Ok(LoadedModule::new(script.into_module()))
+    }
+
     fn deserialize_and_verify(
         raw_bytes: &[u8],
         context: &mut dyn InterpreterContext,
+        verify: fn(CompiledScript) -> Result<VerifiedScript, (CompiledScript, Vec<VMStatus>)>,
     ) -> VMResult<VerifiedScript> {
         let script = match CompiledScript::deserialize(raw_bytes) {
             Ok(script) => script,
@@ -79,7 +91,7 @@ impl<'alloc> ScriptCache<'alloc> {
             }
         };
 
-        let mut errs = match VerifiedScript::new(script) {
+        let mut errs = match verify(script) {
             Ok(script) => {
                 // verify dependencies
                 let script_module = script.self_handle();
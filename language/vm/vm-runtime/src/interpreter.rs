@@ -146,19 +146,69 @@ impl<'txn> Interpreter<'txn> {
             .ok_or_else(|| VMStatus::new(StatusCode::LINKER_ERROR))?;
         let func = FunctionRef::new(loaded_module, *func_idx);
 
-        interp.execute(runtime, context, func, args)
+        interp.execute(runtime, context, func, vec![], vec![], args)
     }
 
     /// Entrypoint into the interpreter. All external calls need to be routed through this
-    /// function.
+    /// function. The type parameters of `func` are instantiated with `type_actuals`, whose tags
+    /// are `type_actual_tags`.
     pub(crate) fn entrypoint(
         context: &mut dyn InterpreterContext,
         runtime: &'txn VMRuntime<'_>,
         txn_data: &'txn TransactionMetadata,
         gas_schedule: &'txn CostTable,
         func: FunctionRef<'txn>,
+        type_actual_tags: Vec<TypeTag>,
+        type_actuals: Vec<Type>,
         args: Vec<Value>,
     ) -> VMResult<()> {
+        Self::entrypoint_impl(
+            context,
+            runtime,
+            txn_data,
+            gas_schedule,
+            func,
+            type_actual_tags,
+            type_actuals,
+            args,
+        )
+        .map(|_| ())
+    }
+
+    /// Entrypoint into the interpreter for the main function of a script run as a query. Returns
+    /// the values returned by the function, which are left on the operand stack.
+    pub(crate) fn query_entrypoint(
+        context: &mut dyn InterpreterContext,
+        runtime: &'txn VMRuntime<'_>,
+        txn_data: &'txn TransactionMetadata,
+        gas_schedule: &'txn CostTable,
+        func: FunctionRef<'txn>,
+        args: Vec<Value>,
+    ) -> VMResult<Vec<Value>> {
+        let return_count = func.return_count();
+        let mut interp = Self::entrypoint_impl(
+            context,
+            runtime,
+            txn_data,
+            gas_schedule,
+            func,
+            vec![],
+            vec![],
+            args,
+        )?;
+        interp.operand_stack.popn(return_count as u16)
+    }
+
+    fn entrypoint_impl(
+        context: &mut dyn InterpreterContext,
+        runtime: &'txn VMRuntime<'_>,
+        txn_data: &'txn TransactionMetadata,
+        gas_schedule: &'txn CostTable,
+        func: FunctionRef<'txn>,
+        type_actual_tags: Vec<TypeTag>,
+        type_actuals: Vec<Type>,
+        args: Vec<Value>,
+    ) -> VMResult<Self> {
         // We charge an intrinsic amount of gas based upon the size of the transaction submitted
         // (in raw bytes).
         let txn_size = txn_data.transaction_size();
@@ -170,11 +220,11 @@ impl<'txn> Interpreter<'txn> {
         let mut interp = Self::new(txn_data, gas_schedule);
         let starting_gas = context.remaining_gas();
         gas!(consume: context, calculate_intrinsic_gas(txn_size))?;
-        let ret = interp.execute(runtime, context, func, args);
+        let ret = interp.execute(runtime, context, func, type_actual_tags, type_actuals, args);
         record_stats!(
             observe | TXN_EXECUTION_GAS_USAGE | starting_gas.sub(context.remaining_gas()).get()
         );
-        ret
+        ret.map(|_| interp)
     }
 
     /// Create a new instance of an `Interpreter` in the context of a transaction with a
@@ -194,11 +244,21 @@ impl<'txn> Interpreter<'txn> {
         runtime: &'txn VMRuntime<'_>,
         context: &mut dyn InterpreterContext,
         function: FunctionRef<'txn>,
+        type_actual_tags: Vec<TypeTag>,
+        type_actuals: Vec<Type>,
         args: Vec<Value>,
     ) -> VMResult<()> {
         // No unwinding of the call stack and value stack need to be done here -- the context will
         // take care of that.
-        self.execute_main(runtime, context, function, args, 0)
+        self.execute_main(
+            runtime,
+            context,
+            function,
+            type_actual_tags,
+            type_actuals,
+            args,
+            0,
+        )
     }
 
     /// Main loop for the execution of a function.
@@ -214,6 +274,8 @@ impl<'txn> Interpreter<'txn> {
         runtime: &'txn VMRuntime<'_>,
         context: &mut dyn InterpreterContext,
         function: FunctionRef<'txn>,
+        type_actual_tags: Vec<TypeTag>,
+        type_actuals: Vec<Type>,
         args: Vec<Value>,
         create_account_marker: usize,
     ) -> VMResult<()> {
@@ -222,7 +284,7 @@ impl<'txn> Interpreter<'txn> {
         for (i, value) in args.into_iter().enumerate() {
             locals.store_loc(i, value)?;
         }
-        let mut current_frame = Frame::new(function, vec![], vec![], locals);
+        let mut current_frame = Frame::new(function, type_actual_tags, type_actuals, locals);
         loop {
             let code = current_frame.code_definition();
             let exit_code = self
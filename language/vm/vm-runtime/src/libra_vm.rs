@@ -15,8 +15,10 @@ use libra_crypto::HashValue;
 use libra_logger::prelude::*;
 use libra_state_view::StateView;
 use libra_types::{
+    account_address::AccountAddress,
     block_metadata::BlockMetadata,
     byte_array::ByteArray,
+    language_storage::TypeTag,
     transaction::{
         ChangeSet, SignatureCheckedTransaction, SignedTransaction, Transaction,
         TransactionArgument, TransactionOutput, TransactionPayload, TransactionStatus,
@@ -53,6 +55,38 @@ impl LibraVM {
         }
     }
 
+    /// Runs `script` as a query from `sender` against `state_view`, and returns the values its main
+    /// function returns. Unlike the script of a transaction, the main function of a query may
+    /// return values, of the types of transaction arguments. Queries are for local execution and
+    /// simulation: they run without the prologue and epilogue of a transaction, and their writes
+    /// and events are discarded.
+    pub fn execute_query(
+        &mut self,
+        state_view: &dyn StateView,
+        sender: AccountAddress,
+        script: Vec<u8>,
+        args: Vec<TransactionArgument>,
+    ) -> VMResult<Vec<TransactionArgument>> {
+        let data_cache = BlockDataCache::new(state_view);
+        self.load_gas_schedule(&data_cache);
+        let txn_data = TransactionMetadata {
+            sender,
+            ..TransactionMetadata::default()
+        };
+        let mut ctx = TransactionExecutionContext::new(txn_data.max_gas_amount(), &data_cache);
+        self.move_vm
+            .execute_query_script(
+                script,
+                self.get_gas_schedule()?,
+                &mut ctx,
+                &txn_data,
+                convert_txn_args(args),
+            )?
+            .into_iter()
+            .map(Value::value_as)
+            .collect()
+    }
+
     fn load_gas_schedule(&mut self, data_cache: &dyn RemoteCache) {
         let mut ctx = SystemExecutionContext::new(data_cache, GasUnits::new(0));
         self.gas_schedule = self.move_vm.load_gas_schedule(&mut ctx, data_cache).ok();
@@ -238,6 +272,7 @@ impl LibraVM {
                 self.run_prologue(gas_schedule, &mut ctx, &txn_data)?;
                 Ok(VerifiedTranscationPayload::Script(
                     script.code().to_vec(),
+                    script.ty_args().to_vec(),
                     script.args().to_vec(),
                 ))
             }
@@ -262,7 +297,7 @@ impl LibraVM {
             VerifiedTranscationPayload::Module(m) => {
                 self.move_vm.publish_module(m, &mut ctx, txn_data)
             }
-            VerifiedTranscationPayload::Script(s, args) => {
+            VerifiedTranscationPayload::Script(s, ty_args, args) => {
                 let gas_schedule = match self.get_gas_schedule() {
                     Ok(s) => s,
                     Err(e) => return discard_error_output(e),
@@ -272,6 +307,7 @@ impl LibraVM {
                     gas_schedule,
                     &mut ctx,
                     txn_data,
+                    ty_args,
                     convert_txn_args(args),
                 )
             }
@@ -584,7 +620,7 @@ pub fn chunk_block_transactions(txns: Vec<Transaction>) -> Vec<TransactionBlock>
 }
 
 enum VerifiedTranscationPayload {
-    Script(Vec<u8>, Vec<TransactionArgument>),
+    Script(Vec<u8>, Vec<TypeTag>, Vec<TransactionArgument>),
     Module(Vec<u8>),
 }
 
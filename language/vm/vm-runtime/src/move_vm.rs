@@ -7,7 +7,10 @@ use crate::{
 };
 use bytecode_verifier::VerifiedModule;
 use libra_types::identifier::Identifier;
-use libra_types::{identifier::IdentStr, language_storage::ModuleId};
+use libra_types::{
+    identifier::IdentStr,
+    language_storage::{ModuleId, TypeTag},
+};
 use move_vm_definition::MoveVMImpl;
 use vm::{errors::VMResult, gas_schedule::CostTable, transaction_metadata::TransactionMetadata};
 use vm_cache_map::Arena;
@@ -71,10 +74,24 @@ impl MoveVM {
         gas_schedule: &CostTable,
         chain_state: &mut S,
         txn_data: &TransactionMetadata,
+        ty_args: Vec<TypeTag>,
         args: Vec<Value>,
     ) -> VMResult<()> {
         self.0.rent(|runtime| {
-            runtime.execute_script(chain_state, txn_data, gas_schedule, script, args)
+            runtime.execute_script(chain_state, txn_data, gas_schedule, script, ty_args, args)
+        })
+    }
+
+    pub fn execute_query_script<S: ChainState>(
+        &self,
+        script: Vec<u8>,
+        gas_schedule: &CostTable,
+        chain_state: &mut S,
+        txn_data: &TransactionMetadata,
+        args: Vec<Value>,
+    ) -> VMResult<Vec<Value>> {
+        self.0.rent(|runtime| {
+            runtime.execute_query_script(chain_state, txn_data, gas_schedule, script, args)
         })
     }
 
@@ -17,13 +17,16 @@ use libra_types::vm_error::sub_status;
 use libra_types::{
     account_config,
     identifier::{IdentStr, Identifier},
-    language_storage::ModuleId,
+    language_storage::{ModuleId, TypeTag},
     vm_error::{StatusCode, VMStatus},
 };
 use vm::{
     access::ModuleAccess,
     errors::{verification_error, vm_error, Location, VMResult},
-    file_format::{FunctionHandleIndex, FunctionSignature, SignatureToken, StructDefinitionIndex},
+    file_format::{
+        CompiledScript, FunctionHandleIndex, FunctionSignature, Kind, SignatureToken,
+        StructDefinitionIndex,
+    },
     gas_schedule::{CostTable, GAS_SCHEDULE_NAME},
     transaction_metadata::TransactionMetadata,
     CompiledModule, IndexKind,
@@ -150,6 +153,7 @@ impl<'alloc> VMRuntime<'alloc> {
         txn_data: &TransactionMetadata,
         gas_schedule: &CostTable,
         script: Vec<u8>,
+        ty_args: Vec<TypeTag>,
         args: Vec<Value>,
     ) -> VMResult<()> {
         let main = self.script_cache.cache_script(&script, context)?;
@@ -158,8 +162,108 @@ impl<'alloc> VMRuntime<'alloc> {
             return Err(VMStatus::new(StatusCode::TYPE_MISMATCH)
                 .with_message("Actual Type Mismatch".to_string()));
         }
+        let type_actuals = self
+            .resolve_type_args(&main.signature().type_formals, &ty_args, context)?
+            .into_iter()
+            .map(|(ty, _)| ty)
+            .collect();
 
-        Interpreter::entrypoint(context, self, txn_data, gas_schedule, main, args)
+        Interpreter::entrypoint(
+            context,
+            self,
+            txn_data,
+            gas_schedule,
+            main,
+            ty_args,
+            type_actuals,
+            args,
+        )
+    }
+
+    /// Resolves the type arguments `ty_args` of a script transaction, along with their kinds,
+    /// checking them against the type parameters `type_formals` they instantiate.
+    fn resolve_type_args(
+        &self,
+        type_formals: &[Kind],
+        ty_args: &[TypeTag],
+        context: &dyn InterpreterContext,
+    ) -> VMResult<Vec<(Type, Kind)>> {
+        if type_formals.len() != ty_args.len() {
+            return Err(
+                VMStatus::new(StatusCode::NUMBER_OF_TYPE_ACTUALS_MISMATCH).with_message(format!(
+                    "expected {} type arguments, got {}",
+                    type_formals.len(),
+                    ty_args.len()
+                )),
+            );
+        }
+        let mut type_actuals = vec![];
+        for (kind, tag) in type_formals.iter().zip(ty_args) {
+            let (ty, actual_kind) = self.resolve_type_tag(tag, context)?;
+            if !actual_kind.is_sub_kind_of(*kind) {
+                return Err(VMStatus::new(StatusCode::CONTRAINT_KIND_MISMATCH)
+                    .with_message(format!("{:?} does not satisfy {:?}", tag, kind)));
+            }
+            type_actuals.push((ty, actual_kind));
+        }
+        Ok(type_actuals)
+    }
+
+    /// Loads the type denoted by `tag` along with its kind.
+    fn resolve_type_tag(
+        &self,
+        tag: &TypeTag,
+        context: &dyn InterpreterContext,
+    ) -> VMResult<(Type, Kind)> {
+        Ok(match tag {
+            TypeTag::Bool => (Type::Bool, Kind::Unrestricted),
+            TypeTag::U8 => (Type::U8, Kind::Unrestricted),
+            TypeTag::U64 => (Type::U64, Kind::Unrestricted),
+            TypeTag::U128 => (Type::U128, Kind::Unrestricted),
+            TypeTag::ByteArray => (Type::ByteArray, Kind::Unrestricted),
+            TypeTag::Address => (Type::Address, Kind::Unrestricted),
+            TypeTag::Struct(struct_tag) => {
+                let module_id = ModuleId::new(struct_tag.address, struct_tag.module.clone());
+                let module = self.code_cache.get_loaded_module(&module_id, context)?;
+                let idx = *module.get_struct_def_index(&struct_tag.name)?;
+                let handle = module.struct_handle_at(module.struct_def_at(idx).struct_handle);
+                let type_actuals =
+                    self.resolve_type_args(&handle.type_formals, &struct_tag.type_params, context)?;
+                // A struct is a resource if it is declared as one or if any of its type actuals is
+                // a resource.
+                let kind = if handle.is_nominal_resource {
+                    Kind::Resource
+                } else {
+                    type_actuals
+                        .iter()
+                        .fold(Kind::Unrestricted, |acc, (_, kind)| acc.join(kind))
+                };
+                let type_actuals = type_actuals.into_iter().map(|(ty, _)| ty).collect();
+                let def = self.resolve_struct_def(module, idx, type_actuals, context)?;
+                (Type::Struct(def), kind)
+            }
+        })
+    }
+
+    /// Runs `script` as a query, whose main function may return values, and returns them. The
+    /// script is verified and loaded for this run only.
+    pub fn execute_query_script(
+        &self,
+        context: &mut dyn InterpreterContext,
+        txn_data: &TransactionMetadata,
+        gas_schedule: &CostTable,
+        script: Vec<u8>,
+        args: Vec<Value>,
+    ) -> VMResult<Vec<Value>> {
+        let loaded_module = ScriptCache::load_query_script(&script, context)?;
+        let main = FunctionRef::new(&loaded_module, CompiledScript::MAIN_INDEX);
+
+        if !verify_actuals(main.signature(), &args) {
+            return Err(VMStatus::new(StatusCode::TYPE_MISMATCH)
+                .with_message("Actual Type Mismatch".to_string()));
+        }
+
+        Interpreter::query_entrypoint(context, self, txn_data, gas_schedule, main, args)
     }
 
     pub fn execute_function(
@@ -136,6 +136,7 @@ fn test_module(name: &'static str) -> VerifiedModule {
         identifiers: idents(vec![name, "func1", "func2"]),
         byte_array_pool: vec![],
         address_pool: vec![AccountAddress::default()],
+        metadata: vec![],
     }
     .freeze()
     .expect("test module should satisfy bounds checker");
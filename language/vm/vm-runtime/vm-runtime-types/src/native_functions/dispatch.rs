@@ -1,7 +1,7 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use super::{hash, primitive_helpers, signature};
+use super::{hash, lcs, primitive_helpers, signature};
 use crate::{
     native_structs::{dispatch::resolve_native_struct, vector::NativeVector},
     value::Value,
@@ -203,6 +203,17 @@ static NATIVE_FUNCTION_MAP: Lazy<NativeFunctionMap> = Lazy::new(|| {
         vec![ByteArray, ByteArray],
         vec![ByteArray]
     );
+    // LCS
+    add!(
+        m,
+        addr,
+        "LCS",
+        "serialize",
+        lcs::native_serialize,
+        vec![Kind::All],
+        vec![Reference(Box::new(TypeParameter(0)))],
+        vec![ByteArray]
+    );
     // Vector
     add!(
         m,
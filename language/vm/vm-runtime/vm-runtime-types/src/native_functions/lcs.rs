@@ -0,0 +1,38 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    native_functions::dispatch::{native_gas, NativeResult},
+    value::{ReferenceValue, Value},
+};
+use libra_types::{
+    byte_array::ByteArray,
+    vm_error::{StatusCode, VMStatus},
+};
+use std::collections::VecDeque;
+use vm::{
+    errors::VMResult,
+    gas_schedule::{CostTable, NativeCostIndex},
+};
+
+/// Serializes the value a reference points to with LCS. The cost is per byte of the result.
+pub fn native_serialize(
+    mut arguments: VecDeque<Value>,
+    cost_table: &CostTable,
+) -> VMResult<NativeResult> {
+    if arguments.len() != 1 {
+        let msg = format!(
+            "wrong number of arguments for serialize expected 1 found {}",
+            arguments.len()
+        );
+        return Err(VMStatus::new(StatusCode::UNREACHABLE).with_message(msg));
+    }
+    let reference = pop_arg!(arguments, ReferenceValue);
+    let serialized = reference
+        .read_ref()?
+        .simple_serialize()
+        .ok_or_else(|| VMStatus::new(StatusCode::DATA_FORMAT_ERROR))?;
+    let cost = native_gas(cost_table, NativeCostIndex::LCS_SERIALIZE, serialized.len());
+    let return_values = vec![Value::byte_array(ByteArray::new(serialized))];
+    Ok(NativeResult::ok(cost, return_values))
+}
@@ -4,5 +4,6 @@
 #[macro_use]
 pub mod dispatch;
 pub mod hash;
+pub mod lcs;
 pub mod primitive_helpers;
 pub mod signature;
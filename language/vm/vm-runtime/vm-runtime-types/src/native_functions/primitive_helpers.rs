@@ -16,6 +16,10 @@ use vm::{
     gas_schedule::{CostTable, NativeCostIndex},
 };
 
+#[cfg(test)]
+#[path = "../unit_tests/primitive_helpers_tests.rs"]
+mod primitive_helpers_tests;
+
 pub fn native_bytearray_concat(
     mut arguments: VecDeque<Value>,
     cost_table: &CostTable,
@@ -0,0 +1,42 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use proptest::prelude::*;
+
+// Returns the bytes a native taking a single argument returns for `value`.
+fn to_bytes(
+    native: fn(VecDeque<Value>, &CostTable) -> VMResult<NativeResult>,
+    value: Value,
+) -> Vec<u8> {
+    let mut arguments = VecDeque::new();
+    arguments.push_back(value);
+    let mut values = native(arguments, &CostTable::zero())
+        .expect("must run")
+        .result
+        .expect("must succeed");
+    assert_eq!(values.len(), 1);
+    values
+        .pop()
+        .unwrap()
+        .value_as::<ByteArray>()
+        .expect("must find ByteArray")
+        .as_bytes()
+        .to_vec()
+}
+
+// The `to_bytes` builtin of the IR compiles to these natives, which must serialize canonically.
+proptest! {
+    #[test]
+    fn u64_to_bytes_is_canonical(x in any::<u64>()) {
+        let bytes = to_bytes(native_u64_to_bytes, Value::u64(x));
+        prop_assert_eq!(bytes, lcs::to_bytes(&x).unwrap());
+    }
+
+    #[test]
+    fn address_to_bytes_is_canonical(bytes in prop::array::uniform32(any::<u8>())) {
+        let address = AccountAddress::new(bytes);
+        let bytes = to_bytes(native_address_to_bytes, Value::address(address));
+        prop_assert_eq!(bytes, lcs::to_bytes(&address).unwrap());
+    }
+}
@@ -11,6 +11,7 @@ use libra_types::{
     access_path::AccessPath,
     account_address::{AccountAddress, ADDRESS_LENGTH},
     byte_array::ByteArray,
+    transaction::TransactionArgument,
     vm_error::{StatusCode, VMStatus},
 };
 use serde::{de, Deserialize, Serialize};
@@ -759,6 +760,21 @@ impl From<Value> for VMResult<ByteArray> {
     }
 }
 
+impl From<Value> for VMResult<TransactionArgument> {
+    fn from(value: Value) -> VMResult<TransactionArgument> {
+        match value.0 {
+            ValueImpl::U64(i) => Ok(TransactionArgument::U64(i)),
+            ValueImpl::Address(address) => Ok(TransactionArgument::Address(address)),
+            ValueImpl::Bool(b) => Ok(TransactionArgument::Bool(b)),
+            ValueImpl::ByteArray(byte_array) => Ok(TransactionArgument::ByteArray(byte_array)),
+            _ => {
+                let msg = format!("Cannot cast {:?} to TransactionArgument", value);
+                Err(VMStatus::new(StatusCode::INTERNAL_TYPE_ERROR).with_message(msg))
+            }
+        }
+    }
+}
+
 impl From<Value> for VMResult<Struct> {
     fn from(value: Value) -> VMResult<Struct> {
         match value.0 {
@@ -61,6 +61,7 @@ mod compiled_module;
 mod consensus_proposal;
 mod inbound_rpc_protocol;
 mod inner_signed_transaction;
+mod move_ir_source;
 mod signed_transaction;
 mod sparse_merkle_proof;
 mod vm_value;
@@ -77,6 +78,7 @@ static ALL_TARGETS: Lazy<BTreeMap<&'static str, Box<dyn FuzzTargetImpl>>> = Lazy
         Box::new(consensus_proposal::ConsensusProposal::default()),
         Box::new(admission_control::AdmissionControlSubmitTransactionRequest::default()),
         Box::new(inbound_rpc_protocol::RpcInboundRequest::default()),
+        Box::new(move_ir_source::MoveIrSourceTarget::default()),
     ];
     targets
         .into_iter()
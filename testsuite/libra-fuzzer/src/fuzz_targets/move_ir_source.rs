@@ -0,0 +1,77 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::FuzzTargetImpl;
+use ir_to_bytecode::{
+    compiler::{compile_module, compile_script},
+    formatter::{format_module, format_script, format_source, FormatOptions},
+    parser::parse_script_or_module,
+};
+use libra_proptest_helpers::ValueGenerator;
+use libra_types::account_address::AccountAddress;
+use move_ir_types::ast::{ModuleDefinition, Script, ScriptOrModule};
+use proptest::prelude::*;
+use std::str;
+use vm::file_format::CompiledModule;
+
+#[derive(Clone, Debug, Default)]
+pub struct MoveIrSourceTarget;
+
+impl FuzzTargetImpl for MoveIrSourceTarget {
+    fn name(&self) -> &'static str {
+        module_name!()
+    }
+
+    fn description(&self) -> &'static str {
+        "Move IR source (parser, printer and compiler)"
+    }
+
+    fn generate(&self, idx: usize, gen: &mut ValueGenerator) -> Option<Vec<u8>> {
+        let options = FormatOptions::default();
+        let source = if idx % 2 == 0 {
+            format_module(&gen.generate(any::<ModuleDefinition>()), &options)
+        } else {
+            format_script(&gen.generate(any::<Script>()), &options)
+        };
+        Some(source.into_bytes())
+    }
+
+    fn fuzz(&self, data: &[u8]) {
+        // Errors are OK -- the fuzzer cares about panics, and about the parser and the printer
+        // disagreeing.
+        let source = match str::from_utf8(data) {
+            Ok(source) => source,
+            Err(_) => return,
+        };
+        let parsed = match parse_script_or_module(source) {
+            Ok(parsed) => parsed,
+            Err(_) => return,
+        };
+        let options = FormatOptions::default();
+        let formatted = format_source(source, &options).expect("source that parses should format");
+        parse_script_or_module(&formatted).expect("formatted source should parse");
+
+        let printed = print(&parsed, &options);
+        let reparsed = parse_script_or_module(&printed).expect("printed AST should parse");
+        assert_eq!(
+            printed,
+            print(&reparsed, &options),
+            "the printed AST should parse back to the same AST"
+        );
+
+        let deps: &[CompiledModule] = &[];
+        let address = AccountAddress::default();
+        let _ = match parsed {
+            ScriptOrModule::Script(script) => compile_script(address, script, deps).map(|_| ()),
+            ScriptOrModule::Module(module) => compile_module(address, module, deps).map(|_| ()),
+        };
+    }
+}
+
+/// Prints an AST without its source, which compares ASTs regardless of their locations.
+fn print(script_or_module: &ScriptOrModule, options: &FormatOptions) -> String {
+    match script_or_module {
+        ScriptOrModule::Script(script) => format_script(script, options),
+        ScriptOrModule::Module(module) => format_module(module, options),
+    }
+}
@@ -1,7 +1,7 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::transaction::transaction_argument::TransactionArgument;
+use crate::{language_storage::TypeTag, transaction::transaction_argument::TransactionArgument};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -11,18 +11,37 @@ pub const SCRIPT_HASH_LENGTH: usize = 32;
 #[derive(Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Script {
     code: Vec<u8>,
+    ty_args: Vec<TypeTag>,
     args: Vec<TransactionArgument>,
 }
 
 impl Script {
     pub fn new(code: Vec<u8>, args: Vec<TransactionArgument>) -> Self {
-        Script { code, args }
+        Self::new_generic(code, vec![], args)
+    }
+
+    /// A script whose main function is instantiated with the types `ty_args`, one per type
+    /// parameter.
+    pub fn new_generic(
+        code: Vec<u8>,
+        ty_args: Vec<TypeTag>,
+        args: Vec<TransactionArgument>,
+    ) -> Self {
+        Script {
+            code,
+            ty_args,
+            args,
+        }
     }
 
     pub fn code(&self) -> &[u8] {
         &self.code
     }
 
+    pub fn ty_args(&self) -> &[TypeTag] {
+        &self.ty_args
+    }
+
     pub fn args(&self) -> &[TransactionArgument] {
         &self.args
     }
@@ -36,6 +55,7 @@ impl fmt::Debug for Script {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Script")
             .field("code", &hex::encode(&self.code))
+            .field("ty_args", &self.ty_args)
             .field("args", &self.args)
             .finish()
     }
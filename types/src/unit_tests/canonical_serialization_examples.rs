@@ -65,8 +65,8 @@ fn test_program_canonical_serialization_example() {
     let input = get_common_program();
 
     let expected_output: Vec<u8> = vec![
-        0x04, 0x00, 0x00, 0x00, 0x6D, 0x6F, 0x76, 0x65, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        0x00, 0xEF, 0xBE, 0xAD, 0xDE, 0x0D, 0xD0, 0xFE, 0xCA,
+        0x04, 0x00, 0x00, 0x00, 0x6D, 0x6F, 0x76, 0x65, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0xEF, 0xBE, 0xAD, 0xDE, 0x0D, 0xD0, 0xFE, 0xCA,
     ];
 
     let actual_output = to_bytes(&input).unwrap();
@@ -92,10 +92,10 @@ fn test_raw_transaction_with_a_program_canonical_serialization_example() {
         0x3A, 0x24, 0xA6, 0x1E, 0x05, 0xD1, 0x29, 0xCA, 0xCE, 0x9E, 0x0E, 0xFC, 0x8B, 0xC9, 0xE3,
         0x38, 0x31, 0xFE, 0xC9, 0xA9, 0xBE, 0x66, 0xF5, 0x0F, 0xD3, 0x52, 0xA2, 0x63, 0x8A, 0x49,
         0xB9, 0xEE, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x04,
-        0x00, 0x00, 0x00, 0x6D, 0x6F, 0x76, 0x65, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        0xEF, 0xBE, 0xAD, 0xDE, 0x0D, 0xD0, 0xFE, 0xCA, 0x10, 0x27, 0x00, 0x00, 0x00, 0x00, 0x00,
-        0x00, 0x20, 0x4E, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x51, 0x01, 0x00, 0x00, 0x00,
-        0x00, 0x00,
+        0x00, 0x00, 0x00, 0x6D, 0x6F, 0x76, 0x65, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0xEF, 0xBE, 0xAD, 0xDE, 0x0D, 0xD0, 0xFE, 0xCA, 0x10, 0x27, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x4E, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x51,
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
     ];
 
     let actual_output = to_bytes(&input).unwrap();
@@ -181,8 +181,9 @@ fn test_transaction_payload_with_a_program_canonical_serialization_example() {
     let input = TransactionPayload::Script(get_common_program());
 
     let expected_output = vec![
-        0x02, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x6D, 0x6F, 0x76, 0x65, 0x01, 0x00, 0x00,
-        0x00, 0x00, 0x00, 0x00, 0x00, 0xEF, 0xBE, 0xAD, 0xDE, 0x0D, 0xD0, 0xFE, 0xCA,
+        0x02, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x6D, 0x6F, 0x76, 0x65, 0x00, 0x00, 0x00,
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xEF, 0xBE, 0xAD, 0xDE, 0x0D, 0xD0,
+        0xFE, 0xCA,
     ];
 
     let actual_output = to_bytes(&input).unwrap();
@@ -8,6 +8,7 @@ use std::fmt;
 fn test_code_fmt() {
     let expect_output = r#"Script {
     code: "6d6f7665",
+    ty_args: [],
     args: [],
 }"#;
     let script = Script::new(b"move".to_vec(), vec![]);